@@ -1,7 +1,23 @@
 use crate::{DeCollectionHandle, DeZSetHandle, SerOutputBatchHandle};
 use dbsp::{algebra::ZRingValue, CollectionHandle, DBData, DBWeight};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use utoipa::{
+    openapi::{RefOr, Schema},
+    ToSchema,
+};
+
+/// Schema of the records in a stream, in OpenAPI format.
+pub type RecordSchema = RefOr<Schema>;
+
+/// Record schemas of all input and output streams in a catalog.
+///
+/// Streams registered without a schema are listed with a `null` schema.
+#[derive(Clone, Serialize)]
+pub struct CatalogSchemas {
+    pub inputs: BTreeMap<String, Option<RecordSchema>>,
+    pub outputs: BTreeMap<String, Option<RecordSchema>>,
+}
 
 /// A catalog of input and output stream handles of a circuit.
 ///
@@ -15,6 +31,8 @@ use std::collections::BTreeMap;
 pub struct Catalog {
     input_collection_handles: BTreeMap<String, Box<dyn DeCollectionHandle>>,
     output_batch_handles: BTreeMap<String, Box<dyn SerOutputBatchHandle>>,
+    input_schemas: BTreeMap<String, RecordSchema>,
+    output_schemas: BTreeMap<String, RecordSchema>,
 }
 
 impl Catalog {
@@ -49,6 +67,43 @@ impl Catalog {
             .insert(name.to_owned(), Box::new(handle));
     }
 
+    /// Associate the schema of record type `T` with a named input stream.
+    ///
+    /// The schema is generated from `T`'s [`ToSchema`] implementation, which
+    /// can be derived with `#[derive(ToSchema)]`.
+    pub fn set_input_schema<T>(&mut self, name: &str)
+    where
+        T: for<'a> ToSchema<'a>,
+    {
+        self.input_schemas.insert(name.to_owned(), T::schema().1);
+    }
+
+    /// Associate the schema of record type `T` with a named output stream.
+    ///
+    /// See [`Self::set_input_schema`].
+    pub fn set_output_schema<T>(&mut self, name: &str)
+    where
+        T: for<'a> ToSchema<'a>,
+    {
+        self.output_schemas.insert(name.to_owned(), T::schema().1);
+    }
+
+    /// Returns record schemas of all input and output streams in the catalog.
+    pub fn schemas(&self) -> CatalogSchemas {
+        CatalogSchemas {
+            inputs: self
+                .input_collection_handles
+                .keys()
+                .map(|name| (name.clone(), self.input_schemas.get(name).cloned()))
+                .collect(),
+            outputs: self
+                .output_batch_handles
+                .keys()
+                .map(|name| (name.clone(), self.output_schemas.get(name).cloned()))
+                .collect(),
+        }
+    }
+
     /// Look up an input stream handle by name.
     pub fn input_collection_handle(&self, name: &str) -> Option<&dyn DeCollectionHandle> {
         self.input_collection_handles.get(name).map(|b| &**b)
@@ -7,13 +7,16 @@
 
 use serde::{Deserialize, Serialize};
 use serde_yaml::Value as YamlValue;
-use std::{borrow::Cow, collections::BTreeMap};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+};
 use utoipa::ToSchema;
 
 /// Default value of `InputEndpointConfig::max_buffered_records`.
 /// It is declared as a function and not as a constant, so it can
 /// be used in `#[serde(default="default_max_buffered_records")]`.
-const fn default_max_buffered_records() -> u64 {
+pub(crate) const fn default_max_buffered_records() -> u64 {
     1_000_000
 }
 
@@ -64,6 +67,61 @@ pub struct GlobalPipelineConfig {
     /// get buffered by the controller, defaults to 0.
     #[serde(default)]
     pub max_buffering_delay_usecs: u64,
+
+    /// Input write-ahead log configuration.
+    #[serde(default)]
+    pub wal: WalConfig,
+
+    /// Maximal amount of memory in bytes that the circuit is allowed to
+    /// allocate.
+    ///
+    /// When set, the controller measures the memory footprint of the circuit
+    /// after each step and aborts the pipeline if it exceeds this limit,
+    /// instead of letting the process run out of memory.  Measuring memory
+    /// usage requires traversing all state maintained by the circuit, which
+    /// adds overhead to each step.  No limit by default.
+    #[serde(default)]
+    pub max_memory_bytes: Option<u64>,
+
+    /// Output streams whose outputs are consolidated before they are sent to
+    /// endpoints.
+    ///
+    /// By default, the outputs computed by different workers during a step
+    /// are sent to the endpoints as separate batches.  For the streams listed
+    /// here, these batches are merged into one, so that a record whose
+    /// insertions and deletions cancel out within the step, e.g., an
+    /// aggregate that was recomputed but didn't change, is not sent at all.
+    /// This option only consolidates the outputs of each step.  It does not
+    /// keep track of outputs sent during previous steps.  It applies to all
+    /// endpoints connected to the stream.
+    #[serde(default)]
+    pub consolidate_outputs: BTreeSet<Cow<'static, str>>,
+}
+
+/// Input write-ahead log configuration.
+///
+/// When enabled, every input buffer received from an input endpoint is
+/// appended to a local log before being pushed to the circuit.  Log entries
+/// are truncated once the circuit has processed them.  On restart, the
+/// controller replays entries that remain in the log, guaranteeing
+/// at-least-once processing of inputs received before a crash.
+#[derive(Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct WalConfig {
+    /// Enable the write-ahead log.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Directory to store the log in.  Defaults to the current working
+    /// directory.
+    #[serde(default)]
+    pub dir: Option<String>,
+
+    /// Call `fsync` after appending each entry to the log.
+    ///
+    /// Without `fsync`, the log survives a crash of the pipeline process, but
+    /// not an OS crash or power failure.
+    #[serde(default)]
+    pub fsync: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize, ToSchema)]
@@ -89,6 +147,40 @@ pub struct InputEndpointConfig {
     /// The default is 1 million.
     #[serde(default = "default_max_buffered_records")]
     pub max_buffered_records: u64,
+
+    /// How to handle records that fail to parse.
+    ///
+    /// The default is `skip`.
+    #[serde(default)]
+    pub on_error: OnErrorPolicy,
+
+    /// Transport endpoint that receives records that fail to parse.
+    ///
+    /// Required when `on_error` is `dead_letter`, ignored otherwise.
+    #[serde(default)]
+    pub dead_letter: Option<TransportConfig>,
+}
+
+/// Policy for handling records that fail to parse at an input endpoint.
+///
+/// Regardless of the policy, parse errors are counted in endpoint stats and
+/// reported to the controller's error callback.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OnErrorPolicy {
+    /// Drop the invalid record and keep ingesting data from the endpoint.
+    #[default]
+    Skip,
+
+    /// Stop the endpoint and mark the pipeline as failed.  Valid records
+    /// received in the same input buffer as the invalid record are
+    /// discarded.
+    Fail,
+
+    /// Forward the raw contents of the invalid record to the transport
+    /// endpoint specified in `InputEndpointConfig::dead_letter` and keep
+    /// ingesting data from the endpoint.
+    DeadLetter,
 }
 
 #[derive(Clone, Serialize, Deserialize, ToSchema)]
@@ -1,3 +1,4 @@
+use crate::ParseError;
 use anyhow::Error as AnyError;
 use dbsp::Error as DBSPError;
 use std::{
@@ -14,6 +15,9 @@ pub enum ConfigError {
     /// Output endpoint with this name already exists.
     DuplicateOutputEndpoint { endpoint_name: String },
 
+    /// Output endpoint with this name does not exist.
+    UnknownOutputEndpoint { endpoint_name: String },
+
     /// Endpoint configuration specifies unknown input format name.
     UnknownInputFormat { format_name: String },
 
@@ -29,6 +33,10 @@ pub enum ConfigError {
     /// Controller configuration specifies output stream name
     /// that is not found in the circuit catalog.
     UnknownOutputStream { stream_name: String },
+
+    /// Input endpoint configuration specifies `on_error: dead_letter`, but
+    /// doesn't specify a dead-letter transport.
+    MissingDeadLetterTransport { endpoint_name: String },
 }
 
 impl Display for ConfigError {
@@ -46,6 +54,9 @@ impl Display for ConfigError {
             Self::DuplicateOutputEndpoint { endpoint_name } => {
                 write!(f, "output endpoint '{endpoint_name}' already exists")
             }
+            Self::UnknownOutputEndpoint { endpoint_name } => {
+                write!(f, "unknown output endpoint '{endpoint_name}'")
+            }
             Self::UnknownOutputFormat { format_name } => {
                 write!(f, "unknown output format '{format_name}'")
             }
@@ -55,6 +66,12 @@ impl Display for ConfigError {
             Self::UnknownOutputStream { stream_name } => {
                 write!(f, "unknown output stream '{stream_name}'")
             }
+            Self::MissingDeadLetterTransport { endpoint_name } => {
+                write!(
+                    f,
+                    "input endpoint '{endpoint_name}' is configured with 'on_error: dead_letter', but doesn't specify a 'dead_letter' transport"
+                )
+            }
         }
     }
 }
@@ -84,6 +101,12 @@ impl ConfigError {
         }
     }
 
+    pub fn unknown_output_endpoint(endpoint_name: &str) -> Self {
+        Self::UnknownOutputEndpoint {
+            endpoint_name: endpoint_name.to_owned(),
+        }
+    }
+
     pub fn unknown_output_format(format_name: &str) -> Self {
         Self::UnknownOutputFormat {
             format_name: format_name.to_owned(),
@@ -101,6 +124,12 @@ impl ConfigError {
             stream_name: stream_name.to_owned(),
         }
     }
+
+    pub fn missing_dead_letter_transport(endpoint_name: &str) -> Self {
+        Self::MissingDeadLetterTransport {
+            endpoint_name: endpoint_name.to_owned(),
+        }
+    }
 }
 
 /// Controller error.
@@ -113,10 +142,11 @@ pub enum ControllerError {
     ///
     /// Error parsing the last input batch.  Parser errors are expected to be
     /// recoverable, i.e., the parser should be able to successfully parse
-    /// new valid inputs after an error.
+    /// new valid inputs after an error.  `error` specifies the location of
+    /// the invalid record and field when known.
     ParseError {
         endpoint_name: String,
-        error: AnyError,
+        error: ParseError,
     },
 
     /// Encode error.
@@ -145,6 +175,9 @@ pub enum ControllerError {
 
     /// Error evaluating the DBSP circuit.
     DbspError { error: DBSPError },
+
+    /// Error writing to or truncating the input write-ahead log.
+    WalError { error: AnyError },
 }
 
 impl StdError for ControllerError {}
@@ -198,6 +231,9 @@ impl Display for ControllerError {
             Self::DbspError { error } => {
                 write!(f, "DBSP error: '{error}'")
             }
+            Self::WalError { error } => {
+                write!(f, "write-ahead log error: '{error}'")
+            }
         }
     }
 }
@@ -227,6 +263,12 @@ impl ControllerError {
         }
     }
 
+    pub fn unknown_output_endpoint(endpoint_name: &str) -> Self {
+        Self::Config {
+            config_error: ConfigError::unknown_output_endpoint(endpoint_name),
+        }
+    }
+
     pub fn unknown_output_format(format_name: &str) -> Self {
         Self::Config {
             config_error: ConfigError::unknown_output_format(format_name),
@@ -245,6 +287,12 @@ impl ControllerError {
         }
     }
 
+    pub fn missing_dead_letter_transport(endpoint_name: &str) -> Self {
+        Self::Config {
+            config_error: ConfigError::missing_dead_letter_transport(endpoint_name),
+        }
+    }
+
     pub fn input_transport_error(endpoint_name: &str, fatal: bool, error: AnyError) -> Self {
         Self::InputTransportError {
             endpoint_name: endpoint_name.to_owned(),
@@ -261,7 +309,7 @@ impl ControllerError {
         }
     }
 
-    pub fn parse_error(endpoint_name: &str, error: AnyError) -> Self {
+    pub fn parse_error(endpoint_name: &str, error: ParseError) -> Self {
         Self::ParseError {
             endpoint_name: endpoint_name.to_owned(),
             error,
@@ -278,4 +326,8 @@ impl ControllerError {
     pub fn dbsp_error(error: DBSPError) -> Self {
         Self::DbspError { error }
     }
+
+    pub fn wal_error(error: AnyError) -> Self {
+        Self::WalError { error }
+    }
 }
@@ -32,23 +32,65 @@
 //! The probe passes the data through to the parser, while counting the number
 //! of transmitted bytes and records and updating respective performance
 //! counters in the controller.
+//!
+//! # Memory limit
+//!
+//! When `max_memory_bytes` is set in the global config, the circuit is killed
+//! as soon as its memory footprint exceeds the limit after a step (see
+//! [`DBSPHandle::set_memory_limit`]).  The circuit thread then reports the
+//! error via the error callback, sets the `memory_limit_exceeded` flag in
+//! controller status, and terminates the pipeline.
+//!
+//! # Input write-ahead log
+//!
+//! When the input WAL is enabled in the global config, the probe appends each
+//! input buffer to the log before passing it to the parser.  The circuit
+//! thread records the size of the log before calling `step()` and truncates
+//! all entries up to this point once the step has completed.  When the
+//! controller is instantiated, it replays entries left in the log by a
+//! previous run through the parsers of the matching input endpoints before
+//! starting the endpoints.
+//!
+//! # Invalid input records
+//!
+//! Records that fail to parse are reported to the error callback and
+//! counted in endpoint stats.  What happens next is determined by the
+//! `on_error` setting of the input endpoint (see [`OnErrorPolicy`]): the
+//! record is dropped (`skip`), forwarded to a dead-letter output transport
+//! endpoint created along with the input endpoint (`dead_letter`), or the
+//! input endpoint is stopped and the pipeline is marked as failed (`fail`).
+//! Parsers always skip invalid records and keep parsing, so `on_error` is
+//! the only setting that controls this behavior.  In the `fail` case, the
+//! probe discards all records in the input buffer that contains the invalid
+//! record, marks the endpoint as failed in controller status and discards
+//! any further input from it, and the backpressure thread disconnects the
+//! endpoint.
+//!
+//! # Quiescence
+//!
+//! After a step that produced output records, the circuit thread runs one
+//! more step even if no new inputs have arrived, and records in controller
+//! status whether that step produced any outputs.  The pipeline is quiescent
+//! (see [`Controller::is_quiescent`]) once the input buffers are empty, the
+//! last step produced no outputs, and all outputs have been flushed to the
+//! output endpoints.
 
 use crate::{
-    Catalog, Encoder, InputConsumer, InputEndpoint, InputFormat, InputTransport, OutputConsumer,
-    OutputEndpoint, OutputFormat, OutputTransport, Parser, PipelineState, SerBatch,
-    SerOutputBatchHandle,
+    Catalog, CatalogSchemas, Encoder, InputConsumer, InputEndpoint, InputFormat, InputTransport,
+    OutputConsumer, OutputEndpoint, OutputFormat, OutputTransport, ParseError, Parser,
+    PipelineState, SerBatch, SerOutputBatchHandle,
 };
 use anyhow::{Error as AnyError, Result as AnyResult};
 use crossbeam::{
     queue::SegQueue,
     sync::{Parker, ShardedLock, Unparker},
 };
-use dbsp::DBSPHandle;
+use dbsp::{DBSPHandle, Error as DBSPError, SchedulerError};
 use log::{debug, error, info};
 use num_traits::FromPrimitive;
 use std::{
     borrow::Cow,
-    collections::{BTreeMap, BTreeSet, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet, VecDeque},
     sync::{
         atomic::{AtomicBool, AtomicU32, Ordering},
         Arc, Mutex,
@@ -60,13 +102,16 @@ use std::{
 mod config;
 mod error;
 mod stats;
+mod wal;
 
+pub(crate) use config::default_max_buffered_records;
 pub use config::{
-    FormatConfig, GlobalPipelineConfig, InputEndpointConfig, OutputEndpointConfig, PipelineConfig,
-    TransportConfig,
+    FormatConfig, GlobalPipelineConfig, InputEndpointConfig, OnErrorPolicy, OutputEndpointConfig,
+    PipelineConfig, TransportConfig, WalConfig,
 };
 pub use error::ControllerError;
 pub use stats::{ControllerStatus, InputEndpointStatus, OutputEndpointStatus};
+use wal::{InputWal, WalReaderId};
 
 pub(crate) type EndpointId = u64;
 
@@ -116,6 +161,8 @@ impl Controller {
     ///   transport or data format.
     ///
     /// * One or more of the endpoints fails to initialize.
+    ///
+    /// * The input write-ahead log is enabled, but cannot be opened.
     pub fn with_config(
         mut circuit: DBSPHandle,
         catalog: Catalog,
@@ -134,7 +181,14 @@ impl Controller {
             circuit_thread_unparker,
             backpressure_thread_unparker,
             error_cb,
-        ));
+        )?);
+
+        circuit.set_memory_limit(
+            config
+                .global
+                .max_memory_bytes
+                .map(|limit| limit.try_into().unwrap_or(usize::MAX)),
+        );
 
         if config.global.cpu_profiler {
             circuit
@@ -147,11 +201,6 @@ impl Controller {
             spawn(move || Self::backpressure_thread(inner, backpressure_thread_parker))
         };
 
-        let circuit_thread_handle = {
-            let inner = inner.clone();
-            spawn(move || Self::circuit_thread(circuit, inner, circuit_thread_parker))
-        };
-
         for (input_name, input_config) in config.inputs.iter() {
             inner.connect_input(input_name, input_config)?;
         }
@@ -160,6 +209,14 @@ impl Controller {
             inner.connect_output(output_name, output_config)?;
         }
 
+        // Start the circuit thread after connecting all endpoints, so that
+        // inputs replayed from the write-ahead log are processed in a single
+        // step and their outputs are sent to all output endpoints.
+        let circuit_thread_handle = {
+            let inner = inner.clone();
+            spawn(move || Self::circuit_thread(circuit, inner, circuit_thread_parker))
+        };
+
         Ok(Self {
             inner,
             circuit_thread_handle,
@@ -190,6 +247,36 @@ impl Controller {
         self.inner.connect_input(endpoint_name, config)
     }
 
+    /// Connect an output endpoint created by the caller.
+    ///
+    /// Unlike endpoints listed in the pipeline configuration, `endpoint` is
+    /// not created by an output transport, so `config.transport` is only
+    /// used for reporting.  The endpoint receives outputs of all circuit
+    /// steps performed after this method returns, encoded in the format
+    /// specified in `config.format`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if an output endpoint named `endpoint_name` already exists or
+    /// `config` specifies an unknown output stream or format.
+    pub fn add_output_endpoint(
+        &self,
+        endpoint_name: &str,
+        config: &OutputEndpointConfig,
+        endpoint: Box<dyn OutputEndpoint>,
+    ) -> AnyResult<()> {
+        self.inner
+            .add_output_endpoint(endpoint_name, config, endpoint)
+    }
+
+    /// Disconnect an output endpoint.
+    ///
+    /// The endpoint stops receiving outputs.  Batches queued for the
+    /// endpoint but not yet encoded are discarded.
+    pub fn disconnect_output(&self, endpoint_name: &str) -> AnyResult<()> {
+        self.inner.disconnect_output(endpoint_name)
+    }
+
     /// Change the state of all input endpoints to running.
     ///
     /// Start streaming data through all connected input endpoints.
@@ -218,6 +305,12 @@ impl Controller {
         self.inner.dump_profile();
     }
 
+    /// Returns record schemas of the input and output streams of the
+    /// circuit (see [`Catalog::schemas`]).
+    pub fn schemas(&self) -> CatalogSchemas {
+        self.inner.catalog.lock().unwrap().schemas()
+    }
+
     /// Terminate the controller, stop all input endpoints and destroy the
     /// circuit.
     pub fn stop(self) -> AnyResult<()> {
@@ -228,6 +321,7 @@ impl Controller {
         self.backpressure_thread_handle
             .join()
             .map_err(|_| AnyError::msg("backpressure thread panicked"))?;
+        self.inner.join_outputs();
         Ok(())
     }
 
@@ -248,6 +342,19 @@ impl Controller {
         self.inner.status.pipeline_complete()
     }
 
+    /// True if the pipeline is quiescent, i.e., all input endpoints have
+    /// drained their backlog, all received records have been processed by the
+    /// circuit, the last `step()` produced no new outputs, and all outputs
+    /// have been pushed to output transport endpoints.
+    ///
+    /// Unlike [`Self::pipeline_complete`], this does not require input
+    /// endpoints to reach end-of-input: a pipeline reading from a stream
+    /// becomes quiescent once it has caught up with its inputs, and leaves
+    /// this state as soon as new inputs arrive.
+    pub fn is_quiescent(&self) -> bool {
+        self.inner.status.is_quiescent()
+    }
+
     /// Circuit thread function: holds the handle to the circuit, calls `step`
     /// on it whenever input data is available, pushes output batches
     /// produced by the circuit to output pipelines.
@@ -262,6 +369,11 @@ impl Controller {
             Duration::from_micros(controller.status.global_config.max_buffering_delay_usecs);
         let min_batch_size_records = controller.status.global_config.min_batch_size_records;
 
+        // Set when a step produced output records, to run one more step even if
+        // no new inputs arrive.  The output of this confirmation step tells us
+        // whether the circuit has settled (see `Controller::is_quiescent`).
+        let mut confirmation_step = false;
+
         loop {
             let dump_profile = controller
                 .dump_profile_request
@@ -298,11 +410,14 @@ impl Controller {
                     // kick the circuit to consume buffered data.  Use strict inequality in case
                     // `min_batch_size_records` is 0.
                     if buffered_records > min_batch_size_records
+                        || confirmation_step
                         || start
                             .map(|start| start.elapsed() >= max_buffering_delay)
                             .unwrap_or(false)
                     {
                         start = None;
+                        let is_confirmation_step = confirmation_step;
+                        confirmation_step = false;
                         // Reset all counters of buffered records and bytes to 0.
                         controller.status.consume_buffered_inputs();
 
@@ -310,25 +425,85 @@ impl Controller {
                         // be fully processed after the `step()` call returns.
                         let processed_records = controller.status.num_total_input_records();
 
+                        // Likewise, all WAL entries logged so far, except those that contain
+                        // incomplete records buffered by parsers, will be consumed by `step()`.
+                        let wal_len = controller
+                            .wal
+                            .as_ref()
+                            .map(|wal| wal.lock().unwrap().consumed());
+
                         // Wake up the backpressure thread to unpause endpoints blocked due to
                         // backpressure.
                         controller.unpark_backpressure();
                         debug!("circuit thread: calling 'circuit.step'");
-                        circuit
-                            .step()
-                            .unwrap_or_else(|e| controller.error(ControllerError::dbsp_error(e)));
+                        let step_start = Instant::now();
+                        let step_succeeded = match circuit.step() {
+                            Ok(()) => true,
+                            Err(e) => {
+                                let memory_limit_exceeded = matches!(
+                                    e,
+                                    DBSPError::Scheduler(
+                                        SchedulerError::MemoryLimitExceeded { .. }
+                                    )
+                                );
+                                controller.error(ControllerError::dbsp_error(e));
+
+                                // The circuit has been killed; shut down the pipeline.
+                                if memory_limit_exceeded {
+                                    controller.status.set_memory_limit_exceeded();
+                                    controller.stop();
+                                    continue;
+                                }
+                                false
+                            }
+                        };
                         debug!("circuit thread: 'circuit.step' returned");
+                        controller.status.step_completed(step_start.elapsed());
+
+                        // Inputs fed to a failed step were not processed: keep them in the
+                        // WAL, so they are replayed after a restart, and don't count them as
+                        // processed.
+                        if step_succeeded {
+                            if let Some(wal_len) = wal_len {
+                                controller
+                                    .wal
+                                    .as_ref()
+                                    .unwrap()
+                                    .lock()
+                                    .unwrap()
+                                    .truncate(wal_len)
+                                    .unwrap_or_else(|e| {
+                                        controller.error(ControllerError::wal_error(e))
+                                    });
+                            }
 
-                        controller
-                            .status
-                            .set_num_total_processed_records(processed_records);
+                            controller
+                                .status
+                                .set_num_total_processed_records(processed_records);
+                        }
 
                         // Push output batches to output pipelines.
+                        let mut step_output_records = 0;
                         let outputs = controller.outputs.read().unwrap();
-                        for (_stream, (output_handle, endpoints)) in outputs.iter_by_stream() {
-                            // TODO: add an endpoint config option to consolidate output batches.
-                            let batch = output_handle.take_from_all();
+                        for (stream, (output_handle, endpoints)) in outputs.iter_by_stream() {
+                            let batch = if controller
+                                .status
+                                .global_config
+                                .consolidate_outputs
+                                .contains(stream)
+                            {
+                                // Drop records whose updates cancel out across workers.
+                                let batch = output_handle.consolidate();
+                                if batch.is_empty() {
+                                    Vec::new()
+                                } else {
+                                    vec![Arc::from(batch)]
+                                }
+                            } else {
+                                output_handle.take_from_all()
+                            };
                             let num_records = batch.iter().map(|b| b.len()).sum();
+                            step_output_records += num_records;
 
                             for endpoint_id in endpoints.iter() {
                                 let endpoint = outputs.lookup_by_id(endpoint_id).unwrap();
@@ -347,6 +522,14 @@ impl Controller {
                                 endpoint.unparker.unpark();
                             }
                         }
+
+                        let produced_output = step_output_records > 0;
+                        controller.status.set_step_produced_output(produced_output);
+
+                        // Run a confirmation step after a step that produced outputs, unless
+                        // this step was itself a confirmation step, so that an idle pipeline
+                        // runs at most one extra step.
+                        confirmation_step = produced_output && !is_confirmation_step;
                     } else if buffered_records > 0 {
                         // We have some buffered data, but less than `min_batch_size_records` --
                         // wait up to `max_buffering_delay` for more data to
@@ -382,14 +565,30 @@ impl Controller {
         // Endpoints paused due to backpressure.
         let mut paused_endpoints = HashSet::new();
 
+        // Endpoints stopped after receiving an invalid record.
+        let mut failed_endpoints = HashSet::new();
+
         loop {
             let inputs = controller.inputs.lock().unwrap();
 
+            // Disconnect newly failed endpoints.  Failed endpoints are never
+            // paused or restarted.
+            for (epid, ep) in inputs.iter() {
+                if !failed_endpoints.contains(epid) && controller.status.is_input_failed(epid) {
+                    ep.endpoint.disconnect();
+                    failed_endpoints.insert(*epid);
+                }
+            }
+
             match controller.state() {
                 PipelineState::Paused => {
                     // Pause circuit if not yet paused.
                     if !global_pause {
                         for (epid, ep) in inputs.iter() {
+                            if failed_endpoints.contains(epid) {
+                                continue;
+                            }
+
                             // Pause the endpoint unless it's already paused due to backpressure.
                             if !paused_endpoints.contains(epid) {
                                 ep.endpoint.pause().unwrap_or_else(|e| {
@@ -408,6 +607,10 @@ impl Controller {
                 PipelineState::Running => {
                     // Resume endpoints that have buffer space, pause endpoints with full buffers.
                     for (epid, ep) in inputs.iter() {
+                        if failed_endpoints.contains(epid) {
+                            continue;
+                        }
+
                         if controller.status.input_endpoint_full(epid) {
                             // The endpoint is full and is not yet in the paused state -- pause it
                             // now.
@@ -481,6 +684,13 @@ struct OutputEndpointDescr {
 
     /// Unparker for the endpoint thread.
     unparker: Unparker,
+
+    /// Handle of the endpoint thread, taken when the controller joins the
+    /// thread during shutdown.
+    thread_handle: Option<JoinHandle<()>>,
+
+    /// Set when the endpoint is disconnected to stop the endpoint thread.
+    disconnected: Arc<AtomicBool>,
 }
 
 impl OutputEndpointDescr {
@@ -489,6 +699,8 @@ impl OutputEndpointDescr {
             endpoint_name: endpoint_name.to_string(),
             queue: Arc::new(SegQueue::new()),
             unparker,
+            thread_handle: None,
+            disconnected: Arc::new(AtomicBool::new(false)),
         }
     }
 }
@@ -499,6 +711,10 @@ type StreamEndpointMap =
 struct OutputEndpoints {
     by_id: BTreeMap<EndpointId, OutputEndpointDescr>,
     by_stream: StreamEndpointMap,
+    /// Id to assign to the next endpoint.  Ids are never reused, so that
+    /// a late message from a removed endpoint (e.g., a transport error
+    /// callback) cannot be attributed to a new endpoint.
+    next_endpoint_id: EndpointId,
 }
 
 impl OutputEndpoints {
@@ -506,6 +722,7 @@ impl OutputEndpoints {
         Self {
             by_id: BTreeMap::new(),
             by_stream: BTreeMap::new(),
+            next_endpoint_id: 0,
         }
     }
 
@@ -530,8 +747,17 @@ impl OutputEndpoints {
             .find(|ep| ep.endpoint_name == endpoint_name)
     }
 
-    fn alloc_endpoint_id(&self) -> EndpointId {
-        self.by_id.keys().rev().next().map(|k| k + 1).unwrap_or(0)
+    fn lookup_id_by_name(&self, endpoint_name: &str) -> Option<EndpointId> {
+        self.by_id
+            .iter()
+            .find(|(_, ep)| ep.endpoint_name == endpoint_name)
+            .map(|(endpoint_id, _)| *endpoint_id)
+    }
+
+    fn alloc_endpoint_id(&mut self) -> EndpointId {
+        let endpoint_id = self.next_endpoint_id;
+        self.next_endpoint_id += 1;
+        endpoint_id
     }
 
     fn insert(
@@ -548,6 +774,17 @@ impl OutputEndpoints {
             .1
             .insert(endpoint_id);
     }
+
+    /// Removes an endpoint.  Stops reading from its stream if this was the
+    /// last endpoint connected to the stream.
+    fn remove(&mut self, endpoint_id: EndpointId) -> Option<OutputEndpointDescr> {
+        let endpoint_descr = self.by_id.remove(&endpoint_id)?;
+        self.by_stream.retain(|_, (_, endpoints)| {
+            endpoints.remove(&endpoint_id);
+            !endpoints.is_empty()
+        });
+        Some(endpoint_descr)
+    }
 }
 
 /// Controller state sharable across threads.
@@ -564,6 +801,15 @@ struct ControllerInner {
     circuit_thread_unparker: Unparker,
     backpressure_thread_unparker: Unparker,
     error_cb: Box<dyn Fn(ControllerError) + Send + Sync>,
+
+    /// Input write-ahead log, if enabled.
+    ///
+    /// Input probes hold the lock while logging and parsing each input
+    /// buffer and retain entries that contain incomplete records buffered by
+    /// their parsers, so that all entries in the log up to
+    /// `InputWal::consumed()` are guaranteed to have been pushed to the
+    /// circuit.
+    wal: Option<Mutex<InputWal>>,
 }
 
 impl ControllerInner {
@@ -573,12 +819,23 @@ impl ControllerInner {
         circuit_thread_unparker: Unparker,
         backpressure_thread_unparker: Unparker,
         error_cb: Box<dyn Fn(ControllerError) + Send + Sync>,
-    ) -> Self {
+    ) -> AnyResult<Self> {
+        for stream in global_config.consolidate_outputs.iter() {
+            if catalog.output_batch_handle(stream).is_none() {
+                Err(ControllerError::unknown_output_stream(stream))?;
+            }
+        }
+
         let status = ControllerStatus::new(global_config);
         let state = AtomicU32::new(PipelineState::Paused as u32);
         let dump_profile_request = AtomicBool::new(false);
+        let wal = if global_config.wal.enabled {
+            Some(Mutex::new(InputWal::open(&global_config.wal)?))
+        } else {
+            None
+        };
 
-        Self {
+        Ok(Self {
             status,
             state,
             dump_profile_request,
@@ -588,7 +845,8 @@ impl ControllerInner {
             circuit_thread_unparker,
             backpressure_thread_unparker,
             error_cb,
-        }
+            wal,
+        })
     }
 
     fn connect_input(
@@ -620,17 +878,63 @@ impl ControllerInner {
 
         let parser = format.new_parser(input_stream, &endpoint_config.format.config)?;
 
-        // Create probe.
         let endpoint_id = inputs.keys().rev().next().map(|k| k + 1).unwrap_or(0);
-        let probe = Box::new(InputProbe::new(
+
+        // Create dead-letter endpoint.
+        let dead_letter = if endpoint_config.on_error == OnErrorPolicy::DeadLetter {
+            let transport_config = endpoint_config
+                .dead_letter
+                .as_ref()
+                .ok_or_else(|| ControllerError::missing_dead_letter_transport(endpoint_name))?;
+            let transport = <dyn OutputTransport>::get_transport(&transport_config.name)
+                .ok_or_else(|| ControllerError::unknown_output_transport(&transport_config.name))?;
+
+            let self_weak = Arc::downgrade(self);
+            let endpoint_name_str = endpoint_name.to_string();
+            let endpoint = transport.new_endpoint(
+                &format!("{endpoint_name}.dead_letter"),
+                &transport_config.config,
+                Box::new(move |fatal: bool, e: AnyError| {
+                    if let Some(controller) = self_weak.upgrade() {
+                        controller.dead_letter_error(endpoint_id, &endpoint_name_str, fatal, e)
+                    }
+                }),
+            )?;
+            Some(Arc::new(Mutex::new(endpoint)))
+        } else {
+            None
+        };
+
+        // Create probe.
+        let mut probe = Box::new(InputProbe::new(
             endpoint_id,
             endpoint_name,
             parser,
+            endpoint_config.on_error,
+            dead_letter,
             self.clone(),
             self.circuit_thread_unparker.clone(),
             self.backpressure_thread_unparker.clone(),
         ));
 
+        // Replay inputs received by the endpoint during a previous run that
+        // were not processed by the circuit.
+        if let Some(wal) = &self.wal {
+            // Hold the lock until replayed records have been pushed to the
+            // circuit (see `ControllerInner::wal`).
+            let mut wal = wal.lock().unwrap();
+            let pending = wal.take_pending(endpoint_name);
+            if !pending.is_empty() {
+                info!(
+                    "input endpoint '{endpoint_name}': replaying {} buffers from the write-ahead log",
+                    pending.len()
+                );
+            }
+            for (offset, data) in pending {
+                probe.parse_logged(&mut wal, offset, &data);
+            }
+        }
+
         // Create transport endpoint.
         let transport = <dyn InputTransport>::get_transport(&endpoint_config.transport.name)
             .ok_or_else(|| {
@@ -676,21 +980,8 @@ impl ControllerInner {
             Err(ControllerError::duplicate_output_endpoint(endpoint_name))?;
         }
 
-        // Create output pipeline, consisting of an encoder, output probe and
-        // transport endpoint; run the pipeline in a separate thread.
-        //
-        // ┌───────┐   ┌───────────┐   ┌────────┐
-        // │encoder├──►│OutputProbe├──►│endpoint├──►
-        // └───────┘   └───────────┘   └────────┘
-
         // Lookup output handle in catalog.
-        let collection_handle = self
-            .catalog
-            .lock()
-            .unwrap()
-            .output_batch_handle(&endpoint_config.stream)
-            .ok_or_else(|| ControllerError::unknown_output_stream(&endpoint_config.stream))?
-            .fork();
+        let collection_handle = self.output_batch_handle(&endpoint_config.stream)?;
 
         // Create transport endpoint.
         let transport = <dyn OutputTransport>::get_transport(&endpoint_config.transport.name)
@@ -712,6 +1003,71 @@ impl ControllerInner {
             }),
         )?;
 
+        self.add_output_pipeline(
+            &mut outputs,
+            endpoint_id,
+            endpoint_name,
+            endpoint_config,
+            collection_handle,
+            endpoint,
+        )
+    }
+
+    fn add_output_endpoint(
+        self: &Arc<Self>,
+        endpoint_name: &str,
+        endpoint_config: &OutputEndpointConfig,
+        endpoint: Box<dyn OutputEndpoint>,
+    ) -> AnyResult<()> {
+        let mut outputs = self.outputs.write().unwrap();
+
+        if outputs.lookup_by_name(endpoint_name).is_some() {
+            Err(ControllerError::duplicate_output_endpoint(endpoint_name))?;
+        }
+
+        let collection_handle = self.output_batch_handle(&endpoint_config.stream)?;
+        let endpoint_id = outputs.alloc_endpoint_id();
+
+        self.add_output_pipeline(
+            &mut outputs,
+            endpoint_id,
+            endpoint_name,
+            endpoint_config,
+            collection_handle,
+            endpoint,
+        )
+    }
+
+    fn output_batch_handle(&self, stream: &str) -> AnyResult<Box<dyn SerOutputBatchHandle>> {
+        Ok(self
+            .catalog
+            .lock()
+            .unwrap()
+            .output_batch_handle(stream)
+            .ok_or_else(|| ControllerError::unknown_output_stream(stream))?
+            .fork())
+    }
+
+    /// Connects `endpoint` to the circuit.
+    ///
+    /// Must be called with the `outputs` lock held, which guarantees that
+    /// the endpoint name and id are still unique.
+    fn add_output_pipeline(
+        self: &Arc<Self>,
+        outputs: &mut OutputEndpoints,
+        endpoint_id: EndpointId,
+        endpoint_name: &str,
+        endpoint_config: &OutputEndpointConfig,
+        collection_handle: Box<dyn SerOutputBatchHandle>,
+        endpoint: Box<dyn OutputEndpoint>,
+    ) -> AnyResult<()> {
+        // Create output pipeline, consisting of an encoder, output probe and
+        // transport endpoint; run the pipeline in a separate thread.
+        //
+        // ┌───────┐   ┌───────────┐   ┌────────┐
+        // │encoder├──►│OutputProbe├──►│endpoint├──►
+        // └───────┘   └───────────┘   └────────┘
+
         // Create probe.
         let probe = Box::new(OutputProbe::new(
             endpoint_id,
@@ -728,8 +1084,14 @@ impl ControllerInner {
         let parker = Parker::new();
         let endpoint_state = OutputEndpointDescr::new(endpoint_name, parker.unparker().clone());
         let queue = endpoint_state.queue.clone();
+        let disconnected = endpoint_state.disconnected.clone();
         let controller = self.clone();
 
+        // Initialize endpoint stats.  The endpoint isn't visible to the
+        // circuit thread until we release the `outputs` lock.
+        self.status
+            .add_output(&endpoint_id, endpoint_name, endpoint_config);
+
         outputs.insert(
             endpoint_id,
             endpoint_config.stream.clone(),
@@ -739,23 +1101,41 @@ impl ControllerInner {
 
         let endpoint_name_string = endpoint_name.to_string();
         // Thread to run the output pipeline.
-        spawn(move || {
+        let thread_handle = spawn(move || {
             Self::output_thread_func(
                 endpoint_id,
                 endpoint_name_string,
                 encoder,
                 parker,
                 queue,
+                disconnected,
                 controller,
             )
         });
+        outputs.by_id.get_mut(&endpoint_id).unwrap().thread_handle = Some(thread_handle);
+
+        Ok(())
+    }
 
+    fn disconnect_output(self: &Arc<Self>, endpoint_name: &str) -> AnyResult<()> {
+        let mut outputs = self.outputs.write().unwrap();
+
+        let endpoint_id = outputs
+            .lookup_id_by_name(endpoint_name)
+            .ok_or_else(|| ControllerError::unknown_output_endpoint(endpoint_name))?;
+        let endpoint_descr = outputs.remove(endpoint_id).unwrap();
         drop(outputs);
 
-        // Initialize endpoint stats.
-        self.status
-            .add_output(&endpoint_id, endpoint_name, endpoint_config);
+        // Stop the endpoint thread.  We don't wait for the thread to exit, as
+        // it may be in the middle of pushing a batch to the transport.
+        endpoint_descr.disconnected.store(true, Ordering::Release);
+        endpoint_descr.unparker.unpark();
+
+        self.status.remove_output(&endpoint_id);
 
+        // The circuit thread may be waiting for this endpoint to drain its
+        // queue.
+        self.unpark_circuit();
         Ok(())
     }
 
@@ -765,10 +1145,13 @@ impl ControllerInner {
         mut encoder: Box<dyn Encoder>,
         parker: Parker,
         queue: Arc<BatchQueue>,
+        disconnected: Arc<AtomicBool>,
         controller: Arc<ControllerInner>,
     ) {
         loop {
-            if controller.state() == PipelineState::Terminated {
+            if controller.state() == PipelineState::Terminated
+                || disconnected.load(Ordering::Acquire)
+            {
                 return;
             }
 
@@ -776,9 +1159,15 @@ impl ControllerInner {
             if let Some((data, processed_records)) = queue.pop() {
                 let num_records = data.iter().map(|b| b.len()).sum();
 
-                encoder
-                    .encode(data.as_slice())
-                    .unwrap_or_else(|e| controller.encode_error(endpoint_id, &endpoint_name, e));
+                // Each queue entry contains the outputs of one circuit step.
+                encoder.consumer().batch_start();
+                match encoder.encode(data.as_slice()) {
+                    Ok(()) => encoder.consumer().batch_end(),
+                    Err(e) => {
+                        encoder.consumer().batch_abort();
+                        controller.encode_error(endpoint_id, &endpoint_name, e);
+                    }
+                }
 
                 // `num_records` output records have been transmitted --
                 // update output stats, wake up the circuit thread if the
@@ -824,10 +1213,36 @@ impl ControllerInner {
         self.state
             .store(PipelineState::Terminated as u32, Ordering::Release);
 
+        // Wake up output threads, so they can exit and drop their endpoints.
+        for ep in self.outputs.read().unwrap().by_id.values() {
+            ep.unparker.unpark();
+        }
+
         self.unpark_circuit();
         self.unpark_backpressure();
     }
 
+    /// Wait for all output threads to exit.
+    ///
+    /// Output endpoints are dropped when their threads exit, which gives
+    /// them a chance to flush buffered data.
+    fn join_outputs(&self) {
+        let thread_handles = self
+            .outputs
+            .write()
+            .unwrap()
+            .by_id
+            .values_mut()
+            .filter_map(|ep| ep.thread_handle.take())
+            .collect::<Vec<_>>();
+
+        for handle in thread_handles {
+            if handle.join().is_err() {
+                error!("output thread panicked");
+            }
+        }
+    }
+
     fn dump_profile(&self) {
         self.dump_profile_request.store(true, Ordering::Release);
         self.unpark_circuit();
@@ -856,11 +1271,43 @@ impl ControllerInner {
         ));
     }
 
-    fn parse_error(&self, endpoint_id: EndpointId, endpoint_name: &str, error: AnyError) {
+    fn parse_error(&self, endpoint_id: EndpointId, endpoint_name: &str, error: ParseError) {
         self.status.parse_error(endpoint_id);
         self.error(ControllerError::parse_error(endpoint_name, error));
     }
 
+    /// Stop an input endpoint configured with `on_error: fail` after an
+    /// invalid record.
+    ///
+    /// Marks the endpoint and the pipeline as failed and wakes up the
+    /// backpressure thread, which disconnects the endpoint.
+    fn fail_input(&self, endpoint_id: EndpointId, error: &ParseError) {
+        self.status.fail_input(endpoint_id, error);
+        self.unpark_backpressure();
+        // The endpoint will not reach end-of-input; let the circuit thread
+        // process records it received before failing.
+        self.unpark_circuit();
+    }
+
+    /// Process an error writing to the dead-letter endpoint of an input
+    /// endpoint.
+    ///
+    /// Errors are accounted to the input endpoint.
+    fn dead_letter_error(
+        &self,
+        endpoint_id: EndpointId,
+        endpoint_name: &str,
+        fatal: bool,
+        error: AnyError,
+    ) {
+        self.input_transport_error(
+            endpoint_id,
+            endpoint_name,
+            fatal,
+            error.context("error writing to dead-letter endpoint"),
+        );
+    }
+
     fn encode_error(&self, endpoint_id: EndpointId, endpoint_name: &str, error: AnyError) {
         self.status.encode_error(endpoint_id);
         self.error(ControllerError::encode_error(endpoint_name, error));
@@ -896,27 +1343,169 @@ struct InputProbe {
     endpoint_id: EndpointId,
     endpoint_name: String,
     parser: Box<dyn Parser>,
+    on_error: OnErrorPolicy,
+
+    /// Endpoint to forward invalid records to, if `on_error` is
+    /// `dead_letter`.  Shared by all forks of the probe.
+    dead_letter: Option<Arc<Mutex<Box<dyn OutputEndpoint>>>>,
     controller: Arc<ControllerInner>,
     circuit_thread_unparker: Unparker,
     backpressure_thread_unparker: Unparker,
+
+    /// Identifies the probe as a reader of the input WAL, if enabled.
+    wal_reader: Option<WalReaderId>,
+
+    /// Offsets and sizes of the most recent WAL entries that contain the
+    /// incomplete record buffered by the parser, if any.
+    wal_entries: VecDeque<(u64, usize)>,
 }
 
 impl InputProbe {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         endpoint_id: EndpointId,
         endpoint_name: &str,
         parser: Box<dyn Parser>,
+        on_error: OnErrorPolicy,
+        dead_letter: Option<Arc<Mutex<Box<dyn OutputEndpoint>>>>,
         controller: Arc<ControllerInner>,
         circuit_thread_unparker: Unparker,
         backpressure_thread_unparker: Unparker,
     ) -> Self {
+        let wal_reader = controller
+            .wal
+            .as_ref()
+            .map(|wal| wal.lock().unwrap().add_reader());
+
         Self {
             endpoint_id,
             endpoint_name: endpoint_name.to_owned(),
             parser,
+            on_error,
+            dead_letter,
             controller,
             circuit_thread_unparker,
             backpressure_thread_unparker,
+            wal_reader,
+            wal_entries: VecDeque::new(),
+        }
+    }
+
+    /// True if the endpoint has been stopped by an invalid record.  Any
+    /// further input received from the endpoint is discarded.
+    fn failed(&self) -> bool {
+        self.controller.status.is_input_failed(&self.endpoint_id)
+    }
+
+    /// Pass input buffer to the parser and push parsed records to the
+    /// circuit.
+    fn parse(&mut self, data: &[u8]) {
+        if self.failed() {
+            return;
+        }
+
+        let (num_records, errors) = self.parser.input(data);
+
+        // Push successfully parsed data to the input handle, update stats.
+        let num_records = self.flush_parser(num_records, &errors);
+        self.controller.status.input_batch(
+            self.endpoint_id,
+            data.len(),
+            num_records,
+            &self.controller.status.global_config,
+            &self.circuit_thread_unparker,
+            &self.backpressure_thread_unparker,
+        );
+
+        self.parse_errors(errors);
+    }
+
+    /// Push `num_records` records parsed from the last input buffer to the
+    /// circuit.
+    ///
+    /// If the buffer contained invalid records and the endpoint fails on
+    /// invalid records, discards all records from the buffer instead, so that
+    /// the endpoint stops at the invalid record.  Returns the number of
+    /// records pushed to the circuit.
+    fn flush_parser(&mut self, num_records: usize, errors: &[ParseError]) -> usize {
+        if !errors.is_empty() && self.on_error == OnErrorPolicy::Fail {
+            self.parser.clear();
+            0
+        } else {
+            self.parser.flush();
+            num_records
+        }
+    }
+
+    /// Like [`Self::parse`], but for an input buffer logged to `wal` at
+    /// `offset`.
+    ///
+    /// Retains WAL entries that contain an incomplete record buffered by the
+    /// parser, so that they are not removed from the log before the record
+    /// has been pushed to the circuit.
+    fn parse_logged(&mut self, wal: &mut InputWal, offset: u64, data: &[u8]) {
+        self.parse(data);
+
+        self.wal_entries.push_back((offset, data.len()));
+        self.retain_wal_entries(wal);
+    }
+
+    /// Retain the WAL entries that the bytes buffered by the parser came from.
+    fn retain_wal_entries(&mut self, wal: &mut InputWal) {
+        // Buffered bytes are always the last bytes received by the parser, so
+        // they come from the smallest suffix of `wal_entries` that is at least
+        // as large.
+        let buffered_len = self.parser.buffered_len();
+        let mut len = 0;
+        let mut retained = 0;
+        for (_offset, size) in self.wal_entries.iter().rev() {
+            if len >= buffered_len {
+                break;
+            }
+            len += size;
+            retained += 1;
+        }
+        self.wal_entries.drain(..self.wal_entries.len() - retained);
+
+        wal.retain(
+            self.wal_reader.unwrap(),
+            self.wal_entries.front().map(|(offset, _size)| *offset),
+        );
+    }
+
+    /// Report parse errors and handle invalid records according to the
+    /// `on_error` policy of the endpoint.
+    fn parse_errors(&mut self, errors: Vec<ParseError>) {
+        for error in errors {
+            match self.on_error {
+                OnErrorPolicy::Skip => {}
+                OnErrorPolicy::Fail => {
+                    if !self.failed() {
+                        self.controller.fail_input(self.endpoint_id, &error);
+                    }
+                }
+                OnErrorPolicy::DeadLetter => self.dead_letter(&error),
+            }
+
+            self.controller
+                .parse_error(self.endpoint_id, &self.endpoint_name, error);
+        }
+    }
+
+    /// Forward the invalid record to the dead-letter endpoint.
+    fn dead_letter(&mut self, error: &ParseError) {
+        // Records that the parser couldn't extract from the input are only
+        // reported to the error callback.
+        if let (Some(dead_letter), Some(record)) = (&self.dead_letter, &error.record) {
+            match dead_letter.lock().unwrap().push_buffer(record) {
+                Ok(()) => self.controller.status.dead_letter_record(self.endpoint_id),
+                Err(e) => self.controller.dead_letter_error(
+                    self.endpoint_id,
+                    &self.endpoint_name,
+                    false,
+                    e,
+                ),
+            }
         }
     }
 }
@@ -925,24 +1514,19 @@ impl InputProbe {
 impl InputConsumer for InputProbe {
     fn input(&mut self, data: &[u8]) {
         // println!("input consumer {} bytes", data.len());
-        // Pass input buffer to the parser.
-        match self.parser.input(data) {
-            Ok(num_records) => {
-                // Success: push data to the input handle, update stats.
-                self.parser.flush();
-                self.controller.status.input_batch(
-                    self.endpoint_id,
-                    data.len(),
-                    num_records,
-                    &self.controller.status.global_config,
-                    &self.circuit_thread_unparker,
-                    &self.backpressure_thread_unparker,
-                );
-            }
-            Err(error) => {
-                self.parser.clear();
-                self.controller
-                    .parse_error(self.endpoint_id, &self.endpoint_name, error);
+        let controller = self.controller.clone();
+
+        match &controller.wal {
+            None => self.parse(data),
+            Some(wal) => {
+                // Log the buffer before parsing it.  Keep holding the lock
+                // until parsed records have been pushed to the circuit (see
+                // `ControllerInner::wal`).
+                let mut wal = wal.lock().unwrap();
+                match wal.append(&self.endpoint_name, data) {
+                    Ok(offset) => self.parse_logged(&mut wal, offset, data),
+                    Err(e) => self.controller.error(ControllerError::wal_error(e)),
+                }
             }
         }
     }
@@ -952,21 +1536,26 @@ impl InputConsumer for InputProbe {
         // no new data has been received, the parser may contain some partially
         // parsed data and may be waiting for, e.g., and end-of-line or
         // end-of-file to finish parsing it).
-        match self.parser.eoi() {
-            Ok(num_records) => {
-                self.parser.flush();
-                self.controller.status.eoi(
-                    self.endpoint_id,
-                    num_records,
-                    &self.circuit_thread_unparker,
-                );
-            }
-            Err(error) => {
-                self.parser.clear();
-                self.controller
-                    .error(ControllerError::parse_error(&self.endpoint_name, error));
-            }
+        if self.failed() {
+            return;
         }
+
+        let controller = self.controller.clone();
+        let mut wal = controller.wal.as_ref().map(|wal| wal.lock().unwrap());
+
+        let (num_records, errors) = self.parser.eoi();
+
+        let num_records = self.flush_parser(num_records, &errors);
+        self.controller
+            .status
+            .eoi(self.endpoint_id, num_records, &self.circuit_thread_unparker);
+
+        // The parser no longer buffers any data.
+        if let Some(wal) = &mut wal {
+            self.retain_wal_entries(wal);
+        }
+
+        self.parse_errors(errors);
     }
 
     fn error(&mut self, fatal: bool, error: AnyError) {
@@ -979,6 +1568,8 @@ impl InputConsumer for InputProbe {
             self.endpoint_id,
             &self.endpoint_name,
             self.parser.fork(),
+            self.on_error,
+            self.dead_letter.clone(),
             self.controller.clone(),
             self.circuit_thread_unparker.clone(),
             self.backpressure_thread_unparker.clone(),
@@ -986,6 +1577,15 @@ impl InputConsumer for InputProbe {
     }
 }
 
+impl Drop for InputProbe {
+    fn drop(&mut self) {
+        // Release WAL entries retained by the probe.
+        if let (Some(wal), Some(reader)) = (&self.controller.wal, self.wal_reader) {
+            wal.lock().unwrap().remove_reader(reader);
+        }
+    }
+}
+
 /// An output probe inserted between the encoder and the output transport
 /// endpoint to track stats.
 struct OutputProbe {
@@ -1009,9 +1609,20 @@ impl OutputProbe {
             controller,
         }
     }
+
+    fn transport_error(&self, error: AnyError) {
+        self.controller
+            .output_transport_error(self.endpoint_id, &self.endpoint_name, false, error);
+    }
 }
 
 impl OutputConsumer for OutputProbe {
+    fn batch_start(&mut self) {
+        if let Err(error) = self.endpoint.batch_start() {
+            self.transport_error(error);
+        }
+    }
+
     fn push_buffer(&mut self, buffer: &[u8]) {
         let num_bytes = buffer.len();
 
@@ -1021,27 +1632,41 @@ impl OutputConsumer for OutputProbe {
                     .status
                     .output_buffer(self.endpoint_id, num_bytes);
             }
-            Err(error) => {
-                self.controller.output_transport_error(
-                    self.endpoint_id,
-                    &self.endpoint_name,
-                    false,
-                    error,
-                );
-            }
+            Err(error) => self.transport_error(error),
+        }
+    }
+
+    fn batch_end(&mut self) {
+        if let Err(error) = self.endpoint.batch_end() {
+            self.transport_error(error);
+        }
+    }
+
+    fn batch_abort(&mut self) {
+        if let Err(error) = self.endpoint.batch_abort() {
+            self.transport_error(error);
         }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use super::wal::InputWal;
     use crate::{
         test::{generate_test_batch, test_circuit, wait, TestStruct},
-        Controller, PipelineConfig,
+        Catalog, Controller, ControllerError, OnErrorPolicy, PipelineConfig, WalConfig,
     };
+    use crossbeam::queue::SegQueue;
     use csv::{ReaderBuilder as CsvReaderBuilder, WriterBuilder as CsvWriterBuilder};
-    use std::fs::remove_file;
-    use tempfile::NamedTempFile;
+    use dbsp::{operator::FilterMap, Error as DBSPError, Runtime, SchedulerError};
+    use std::{
+        fs::{read, remove_file},
+        io::Write,
+        sync::{atomic::Ordering, Arc},
+        thread::sleep,
+        time::Duration,
+    };
+    use tempfile::{NamedTempFile, TempDir};
 
     use proptest::prelude::*;
 
@@ -1076,7 +1701,7 @@ inputs:
             config:
                 path: {:?}
                 buffer_size_bytes: {input_buffer_size_bytes}
-                follow: false
+                mode: once
         format:
             name: csv
 outputs:
@@ -1146,4 +1771,668 @@ outputs:
             assert_eq!(actual, expected);
         }
     }
+
+    fn read_output(output_path: &str) -> Vec<TestStruct> {
+        let mut actual: Vec<_> = CsvReaderBuilder::new()
+            .has_headers(false)
+            .from_path(output_path)
+            .unwrap()
+            .deserialize::<(TestStruct, i32)>()
+            .map(|res| {
+                let (val, weight) = res.unwrap();
+                assert_eq!(weight, 1);
+                val
+            })
+            .collect();
+        actual.sort();
+        actual
+    }
+
+    /// Configuration of a pipeline with WAL enabled that reads its input from
+    /// `input_path` in the given file input `mode`.
+    fn wal_pipeline_config(
+        wal_dir: &TempDir,
+        input_path: &str,
+        mode: &str,
+        output_path: &str,
+    ) -> PipelineConfig {
+        let config_str = format!(
+            r#"
+wal:
+    enabled: true
+    dir: {:?}
+    fsync: true
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {input_path:?}
+                mode: {mode}
+        format:
+            name: csv
+outputs:
+    test_output1:
+        stream: test_output1
+        transport:
+            name: file
+            config:
+                path: {output_path:?}
+        format:
+            name: csv
+        "#,
+            wal_dir.path().to_str().unwrap(),
+        );
+
+        serde_yaml::from_str(&config_str).unwrap()
+    }
+
+    /// Run a pipeline with WAL enabled and the given input file contents to
+    /// completion; return the records it outputs.
+    fn run_with_wal(wal_dir: &TempDir, input: &[u8]) -> Vec<TestStruct> {
+        let (circuit, catalog) = test_circuit(2);
+
+        let mut temp_input_file = NamedTempFile::new().unwrap();
+        temp_input_file.write_all(input).unwrap();
+        let temp_output_path = NamedTempFile::new().unwrap().into_temp_path();
+        let output_path = temp_output_path.to_str().unwrap().to_string();
+        temp_output_path.close().unwrap();
+
+        let config = wal_pipeline_config(
+            wal_dir,
+            temp_input_file.path().to_str().unwrap(),
+            "once",
+            &output_path,
+        );
+        let controller = Controller::with_config(
+            circuit,
+            catalog,
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        controller.start();
+        wait(|| controller.pipeline_complete(), None);
+        controller.stop().unwrap();
+
+        let actual = read_output(&output_path);
+        remove_file(&output_path).unwrap();
+        actual
+    }
+
+    // Records logged to the WAL by a pipeline that crashed before processing
+    // them are processed on restart and are removed from the log afterwards.
+    #[test]
+    fn wal_replay() {
+        let wal_dir = TempDir::new().unwrap();
+        let data = (0..100)
+            .map(|id| TestStruct {
+                id,
+                b: id % 2 == 0,
+                i: Some(id as i64),
+                s: format!("foo{id}"),
+            })
+            .collect::<Vec<_>>();
+
+        // Simulate a pipeline that received inputs and died before calling
+        // `step()`.
+        let mut wal = InputWal::open(&WalConfig {
+            enabled: true,
+            dir: Some(wal_dir.path().to_str().unwrap().to_string()),
+            fsync: false,
+        })
+        .unwrap();
+
+        for chunk in data.chunks(30) {
+            let mut writer = CsvWriterBuilder::new()
+                .has_headers(false)
+                .from_writer(Vec::new());
+            for val in chunk {
+                writer.serialize(val).unwrap();
+            }
+            wal.append("test_input1", &writer.into_inner().unwrap())
+                .unwrap();
+        }
+        drop(wal);
+
+        assert_eq!(run_with_wal(&wal_dir, b""), data);
+
+        // All entries have been consumed by the previous run.
+        assert_eq!(run_with_wal(&wal_dir, b""), Vec::new());
+    }
+
+    // A WAL entry that ends with an incomplete record is not removed from the
+    // log after the records before it have been processed.
+    #[test]
+    fn wal_split_record() {
+        let wal_dir = TempDir::new().unwrap();
+        let wal_config = WalConfig {
+            enabled: true,
+            dir: Some(wal_dir.path().to_str().unwrap().to_string()),
+            fsync: false,
+        };
+
+        // The second record is split across input buffers, only the first of
+        // which was received before the pipeline crashed.
+        let mut wal = InputWal::open(&wal_config).unwrap();
+        wal.append("test_input1", b"0,true,,foo0\n1,fal").unwrap();
+        drop(wal);
+
+        // Restart the pipeline, process the complete record, and crash again
+        // before the rest of the split record arrives.
+        let (circuit, catalog) = test_circuit(2);
+        let temp_input_file = NamedTempFile::new().unwrap();
+        let temp_output_path = NamedTempFile::new().unwrap().into_temp_path();
+        let output_path = temp_output_path.to_str().unwrap().to_string();
+        temp_output_path.close().unwrap();
+
+        let config = wal_pipeline_config(
+            &wal_dir,
+            temp_input_file.path().to_str().unwrap(),
+            "follow",
+            &output_path,
+        );
+        let controller = Controller::with_config(
+            circuit,
+            catalog,
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        controller.start();
+        wait(
+            || controller.status().num_total_processed_records() == 1,
+            None,
+        );
+        controller.stop().unwrap();
+        remove_file(&output_path).unwrap();
+
+        // The entry that contains the incomplete record is still in the log.
+        let mut wal = InputWal::open(&wal_config).unwrap();
+        assert_eq!(
+            wal.take_pending("test_input1"),
+            vec![(0, b"0,true,,foo0\n1,fal".to_vec())]
+        );
+        drop(wal);
+
+        // Once the rest of the record arrives, the complete record is
+        // processed.
+        assert_eq!(
+            run_with_wal(&wal_dir, b"se,,foo1\n"),
+            vec![
+                TestStruct {
+                    id: 0,
+                    b: true,
+                    i: None,
+                    s: "foo0".to_string(),
+                },
+                TestStruct {
+                    id: 1,
+                    b: false,
+                    i: None,
+                    s: "foo1".to_string(),
+                },
+            ]
+        );
+        assert_eq!(run_with_wal(&wal_dir, b""), Vec::new());
+    }
+
+    // A pipeline whose state grows beyond `max_memory_bytes` is aborted
+    // instead of running out of memory.
+    #[test]
+    fn memory_limit() {
+        // Circuit that computes an unbounded cross-product of its inputs.
+        let (circuit, input) = Runtime::init_circuit(2, |circuit| {
+            let (input, hinput) = circuit.add_input_zset::<TestStruct, i32>();
+
+            let indexed = input.index_with(|x| ((), x.clone()));
+            indexed
+                .join(&indexed, |_k, x, y| (x.clone(), y.clone()))
+                .integrate_trace();
+
+            hinput
+        })
+        .unwrap();
+
+        let mut catalog = Catalog::new();
+        catalog.register_input_zset_handle("test_input1", input);
+
+        let temp_input_file = NamedTempFile::new().unwrap();
+        let mut writer = CsvWriterBuilder::new()
+            .has_headers(false)
+            .from_writer(temp_input_file.as_file());
+        for id in 0..500 {
+            writer
+                .serialize(TestStruct {
+                    id,
+                    b: true,
+                    i: None,
+                    s: format!("foo{id}"),
+                })
+                .unwrap();
+        }
+        writer.flush().unwrap();
+
+        let config_str = format!(
+            r#"
+max_memory_bytes: 1000000
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {:?}
+                buffer_size_bytes: 1000
+                mode: once
+        format:
+            name: csv
+        "#,
+            temp_input_file.path().to_str().unwrap(),
+        );
+
+        let config: PipelineConfig = serde_yaml::from_str(&config_str).unwrap();
+
+        let errors = Arc::new(SegQueue::new());
+        let errors_clone = errors.clone();
+
+        let controller = Controller::with_config(
+            circuit,
+            catalog,
+            &config,
+            Box::new(move |e| errors_clone.push(e)) as Box<dyn Fn(ControllerError) + Send + Sync>,
+        )
+        .unwrap();
+
+        controller.start();
+        wait(|| controller.status().memory_limit_exceeded(), None);
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors.pop().unwrap(),
+            ControllerError::DbspError {
+                error: DBSPError::Scheduler(SchedulerError::MemoryLimitExceeded {
+                    limit: 1000000,
+                    ..
+                })
+            }
+        ));
+
+        controller.stop().unwrap();
+    }
+
+    /// Feed a file with one invalid record followed by valid records to an
+    /// input endpoint with the specified `on_error` policy.
+    ///
+    /// Returns the records output by the pipeline, the contents of the
+    /// dead-letter file, and the errors reported to the error callback.
+    fn run_with_bad_record(
+        on_error: OnErrorPolicy,
+    ) -> (Controller, Vec<TestStruct>, Vec<u8>, Vec<ControllerError>) {
+        let (circuit, catalog) = test_circuit(2);
+
+        let mut temp_input_file = NamedTempFile::new().unwrap();
+        writeln!(temp_input_file, "bad,true,0,bad").unwrap();
+        let mut writer = CsvWriterBuilder::new()
+            .has_headers(false)
+            .from_writer(temp_input_file.as_file());
+        for id in 0..100 {
+            writer
+                .serialize(TestStruct {
+                    id,
+                    b: true,
+                    i: None,
+                    s: format!("foo{id}"),
+                })
+                .unwrap();
+        }
+        writer.flush().unwrap();
+        drop(writer);
+
+        let temp_output_path = NamedTempFile::new().unwrap().into_temp_path();
+        let output_path = temp_output_path.to_str().unwrap().to_string();
+        temp_output_path.close().unwrap();
+
+        let dead_letter_file = NamedTempFile::new().unwrap();
+
+        let on_error = match on_error {
+            OnErrorPolicy::Skip => "skip",
+            OnErrorPolicy::Fail => "fail",
+            OnErrorPolicy::DeadLetter => "dead_letter",
+        };
+
+        let config_str = format!(
+            r#"
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {:?}
+                buffer_size_bytes: 100
+                mode: once
+        format:
+            name: csv
+        on_error: {on_error}
+        dead_letter:
+            name: file
+            config:
+                path: {:?}
+outputs:
+    test_output1:
+        stream: test_output1
+        transport:
+            name: file
+            config:
+                path: {:?}
+        format:
+            name: csv
+        "#,
+            temp_input_file.path().to_str().unwrap(),
+            dead_letter_file.path().to_str().unwrap(),
+            output_path,
+        );
+
+        let config: PipelineConfig = serde_yaml::from_str(&config_str).unwrap();
+
+        let errors = Arc::new(SegQueue::new());
+        let errors_clone = errors.clone();
+
+        let controller = Controller::with_config(
+            circuit,
+            catalog,
+            &config,
+            Box::new(move |e| errors_clone.push(e)) as Box<dyn Fn(ControllerError) + Send + Sync>,
+        )
+        .unwrap();
+
+        controller.start();
+        wait(|| controller.pipeline_complete(), None);
+
+        let output = read_output(&output_path);
+        remove_file(&output_path).unwrap();
+
+        let errors = std::iter::from_fn(|| errors.pop()).collect();
+
+        (
+            controller,
+            output,
+            read(dead_letter_file.path()).unwrap(),
+            errors,
+        )
+    }
+
+    fn assert_parse_error(errors: &[ControllerError]) {
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ControllerError::ParseError { endpoint_name, error }
+                if endpoint_name == "test_input1" && error.row == Some(1)
+        ));
+    }
+
+    // The invalid record is dropped; the rest of the input is processed.
+    #[test]
+    fn on_error_skip() {
+        let (controller, output, dead_letters, errors) = run_with_bad_record(OnErrorPolicy::Skip);
+
+        assert_parse_error(&errors);
+        assert_eq!(output.len(), 100);
+        assert!(dead_letters.is_empty());
+
+        let status = controller.status();
+        assert!(!status.input_endpoint_failed());
+        let input_status = status.input_status();
+        let metrics = &input_status.get(&0).unwrap().metrics;
+        assert_eq!(metrics.num_parse_errors.load(Ordering::Acquire), 1);
+        assert!(!metrics.failed.load(Ordering::Acquire));
+        drop(input_status);
+
+        controller.stop().unwrap();
+    }
+
+    // The endpoint is stopped at the first invalid record.
+    #[test]
+    fn on_error_fail() {
+        let (controller, output, dead_letters, errors) = run_with_bad_record(OnErrorPolicy::Fail);
+
+        assert_parse_error(&errors);
+        // The input buffer that contains the invalid record is discarded.
+        assert!(output.is_empty());
+        assert!(dead_letters.is_empty());
+
+        let status = controller.status();
+        assert!(status.input_endpoint_failed());
+        let input_status = status.input_status();
+        let endpoint_status = input_status.get(&0).unwrap();
+        assert_eq!(
+            endpoint_status
+                .metrics
+                .num_parse_errors
+                .load(Ordering::Acquire),
+            1
+        );
+        assert!(endpoint_status.metrics.failed.load(Ordering::Acquire));
+        assert!(endpoint_status.fatal_error.lock().unwrap().is_some());
+        drop(input_status);
+
+        controller.stop().unwrap();
+    }
+
+    // The invalid record is forwarded to the dead-letter endpoint; the rest
+    // of the input is processed.
+    #[test]
+    fn on_error_dead_letter() {
+        let (controller, output, dead_letters, errors) =
+            run_with_bad_record(OnErrorPolicy::DeadLetter);
+
+        assert_parse_error(&errors);
+        assert_eq!(output.len(), 100);
+        assert_eq!(dead_letters, b"bad,true,0,bad\n");
+
+        let status = controller.status();
+        assert!(!status.input_endpoint_failed());
+        let input_status = status.input_status();
+        let metrics = &input_status.get(&0).unwrap().metrics;
+        assert_eq!(metrics.num_parse_errors.load(Ordering::Acquire), 1);
+        assert_eq!(metrics.num_dead_letter_records.load(Ordering::Acquire), 1);
+        drop(input_status);
+
+        controller.stop().unwrap();
+    }
+
+    // After processing a finite input to completion, the pipeline becomes
+    // quiescent and remains quiescent while idle.
+    #[test]
+    fn quiescent() {
+        let (circuit, catalog) = test_circuit(2);
+
+        let temp_input_file = NamedTempFile::new().unwrap();
+        let temp_output_path = NamedTempFile::new().unwrap().into_temp_path();
+        let output_path = temp_output_path.to_str().unwrap().to_string();
+        temp_output_path.close().unwrap();
+
+        let data = (0..100)
+            .map(|id| TestStruct {
+                id,
+                b: id % 2 == 0,
+                i: Some(id as i64),
+                s: format!("foo{id}"),
+            })
+            .collect::<Vec<_>>();
+
+        let mut writer = CsvWriterBuilder::new()
+            .has_headers(false)
+            .from_writer(temp_input_file.as_file());
+        for val in data.iter() {
+            writer.serialize(val).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let config_str = format!(
+            r#"
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {:?}
+                mode: once
+        format:
+            name: csv
+outputs:
+    test_output1:
+        stream: test_output1
+        transport:
+            name: file
+            config:
+                path: {:?}
+        format:
+            name: csv
+        "#,
+            temp_input_file.path().to_str().unwrap(),
+            output_path,
+        );
+
+        let config: PipelineConfig = serde_yaml::from_str(&config_str).unwrap();
+        let controller = Controller::with_config(
+            circuit,
+            catalog,
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        controller.start();
+
+        // The pipeline is trivially quiescent before it has received any
+        // inputs, so also wait for all records to reach the output.
+        wait(
+            || {
+                controller
+                    .status()
+                    .output_status()
+                    .get(&0)
+                    .unwrap()
+                    .transmitted_records()
+                    == data.len() as u64
+                    && controller.is_quiescent()
+            },
+            None,
+        );
+
+        // No new inputs: the pipeline stays quiescent.
+        sleep(Duration::from_millis(100));
+        assert!(controller.is_quiescent());
+        assert!(!controller
+            .status()
+            .global_metrics
+            .step_produced_output
+            .load(Ordering::Acquire));
+
+        controller.stop().unwrap();
+
+        assert_eq!(read_output(&output_path), data);
+        remove_file(&output_path).unwrap();
+    }
+
+    // Updates to a stream listed in `consolidate_outputs` that cancel out
+    // across workers are not sent to the endpoint.
+    #[test]
+    fn consolidate_outputs() {
+        // Each record contributes `+b` and `-!b`, so records with different
+        // values of `b` cancel out, but may be processed by different workers.
+        let (circuit, (input, output)) = Runtime::init_circuit(2, |circuit| {
+            let (input, hinput) = circuit.add_input_zset::<TestStruct, i32>();
+            let flags = input.map(|r: &TestStruct| r.b);
+            let negated_flags = input.map(|r: &TestStruct| !r.b);
+
+            (hinput, flags.minus(&negated_flags).output())
+        })
+        .unwrap();
+
+        let mut catalog = Catalog::new();
+        catalog.register_input_zset_handle("test_input1", input);
+        catalog.register_output_batch_handle("test_output1", output);
+
+        let temp_input_file = NamedTempFile::new().unwrap();
+        let temp_output_path = NamedTempFile::new().unwrap().into_temp_path();
+        let output_path = temp_output_path.to_str().unwrap().to_string();
+        temp_output_path.close().unwrap();
+
+        let mut writer = CsvWriterBuilder::new()
+            .has_headers(false)
+            .from_writer(temp_input_file.as_file());
+        for (id, b) in [(0, true), (1, false), (2, true)] {
+            writer
+                .serialize(TestStruct {
+                    id,
+                    b,
+                    i: None,
+                    s: format!("foo{id}"),
+                })
+                .unwrap();
+        }
+        writer.flush().unwrap();
+
+        // Process all records in one step.
+        let config_str = format!(
+            r#"
+min_batch_size_records: 3
+max_buffering_delay_usecs: 10000000
+consolidate_outputs: [test_output1]
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {:?}
+                mode: once
+        format:
+            name: csv
+outputs:
+    test_output1:
+        stream: test_output1
+        transport:
+            name: file
+            config:
+                path: {:?}
+        format:
+            name: csv
+        "#,
+            temp_input_file.path().to_str().unwrap(),
+            output_path,
+        );
+
+        let config: PipelineConfig = serde_yaml::from_str(&config_str).unwrap();
+        let controller = Controller::with_config(
+            circuit,
+            catalog,
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        controller.start();
+        wait(|| controller.pipeline_complete(), None);
+        controller.stop().unwrap();
+
+        // Only the net change is sent to the endpoint.
+        let actual = CsvReaderBuilder::new()
+            .has_headers(false)
+            .from_path(&output_path)
+            .unwrap()
+            .deserialize::<(bool, i32)>()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![(false, -1), (true, 1)]);
+
+        remove_file(&output_path).unwrap();
+    }
 }
@@ -31,17 +31,22 @@
 //! pending.
 
 use super::{EndpointId, GlobalPipelineConfig, InputEndpointConfig, OutputEndpointConfig};
+use crate::ParseError;
 use anyhow::Error as AnyError;
 use crossbeam::sync::{ShardedLock, ShardedLockReadGuard, Unparker};
 use serde::{Serialize, Serializer};
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, VecDeque},
     sync::{
         atomic::{AtomicBool, AtomicU64, Ordering},
         Mutex,
     },
+    time::{Duration, Instant},
 };
 
+/// Length of the sliding window over which `steps_per_second` is computed.
+const STEP_RATE_WINDOW_SECS: u64 = 10;
+
 #[derive(Default, Serialize)]
 pub struct GlobalControllerMetrics {
     /// Total number of records currently buffered by all endpoints.
@@ -57,6 +62,24 @@ pub struct GlobalControllerMetrics {
     /// for end-to-end progress tracking.
     pub total_processed_records: AtomicU64,
 
+    /// Number of input records received from all endpoints but not yet
+    /// processed by the circuit.
+    // This field is computed on-demand by calling `ControllerStatus::update`.
+    pub input_backlog: AtomicU64,
+
+    /// Total number of steps performed by the circuit.
+    pub total_steps: AtomicU64,
+
+    /// Average number of circuit steps per second over the last
+    /// `STEP_RATE_WINDOW_SECS` seconds.
+    pub steps_per_second: StepRate,
+
+    /// Duration of the last circuit step in microseconds.
+    pub last_step_duration_usecs: AtomicU64,
+
+    /// Total time spent in circuit steps in microseconds.
+    pub total_step_duration_usecs: AtomicU64,
+
     /// True if the pipeline has processed all input data to completion.
     /// This means that the following conditions hold:
     ///
@@ -67,6 +90,19 @@ pub struct GlobalControllerMetrics {
     ///   endponts.
     // This field is computed on-demand by calling `ControllerStatus::update`.
     pub pipeline_complete: AtomicBool,
+
+    /// True if the last `step()` of the circuit produced output records.
+    /// Initially `false`, so a pipeline that hasn't run any steps yet is
+    /// considered quiescent.
+    pub step_produced_output: AtomicBool,
+
+    /// True if the circuit has been aborted after exceeding the memory limit
+    /// configured via `GlobalPipelineConfig::max_memory_bytes`.
+    pub memory_limit_exceeded: AtomicBool,
+
+    /// True if an input endpoint configured with `on_error: fail` has been
+    /// stopped after receiving an invalid record.
+    pub input_endpoint_failed: AtomicBool,
 }
 
 impl GlobalControllerMetrics {
@@ -97,6 +133,100 @@ impl GlobalControllerMetrics {
         self.total_processed_records
             .store(total_processed_records, Ordering::Release);
     }
+
+    fn num_total_steps(&self) -> u64 {
+        self.total_steps.load(Ordering::Acquire)
+    }
+
+    fn step_completed(&self, duration: Duration) {
+        let duration_usecs = duration.as_micros() as u64;
+
+        self.total_steps.fetch_add(1, Ordering::AcqRel);
+        self.steps_per_second.step_completed();
+        self.last_step_duration_usecs
+            .store(duration_usecs, Ordering::Release);
+        self.total_step_duration_usecs
+            .fetch_add(duration_usecs, Ordering::AcqRel);
+    }
+
+    fn step_produced_output(&self) -> bool {
+        self.step_produced_output.load(Ordering::Acquire)
+    }
+
+    fn set_step_produced_output(&self, produced_output: bool) {
+        self.step_produced_output
+            .store(produced_output, Ordering::Release);
+    }
+}
+
+/// Sliding-window estimate of the circuit step rate.
+///
+/// Counts steps in one-second buckets and reports the average rate over the
+/// last `STEP_RATE_WINDOW_SECS` seconds, so the rate drops to zero shortly
+/// after the circuit stops stepping.  The number of buckets is bounded by the
+/// length of the window, regardless of the step rate.
+pub struct StepRate {
+    start: Instant,
+
+    /// `(second, number of steps)` pairs for each second since `start` within
+    /// the window during which the circuit performed at least one step.
+    buckets: Mutex<VecDeque<(u64, u64)>>,
+}
+
+impl Default for StepRate {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            buckets: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+impl StepRate {
+    fn step_completed(&self) {
+        let now = self.start.elapsed().as_secs();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        match buckets.back_mut() {
+            Some((second, steps)) if *second == now => *steps += 1,
+            _ => buckets.push_back((now, 1)),
+        }
+        Self::expire(&mut buckets, now);
+    }
+
+    /// Remove buckets that are outside the window ending at second `now`.
+    fn expire(buckets: &mut VecDeque<(u64, u64)>, now: u64) {
+        while let Some((second, _)) = buckets.front() {
+            if second + STEP_RATE_WINDOW_SECS > now {
+                break;
+            }
+            buckets.pop_front();
+        }
+    }
+
+    /// Average number of steps per second over the sliding window.
+    pub fn steps_per_second(&self) -> f64 {
+        let elapsed = self.start.elapsed();
+        let mut buckets = self.buckets.lock().unwrap();
+        Self::expire(&mut buckets, elapsed.as_secs());
+
+        let steps: u64 = buckets.iter().map(|(_, steps)| steps).sum();
+
+        // Don't overestimate the rate during the first second.
+        let window = elapsed
+            .as_secs_f64()
+            .clamp(1.0, STEP_RATE_WINDOW_SECS as f64);
+        steps as f64 / window
+    }
+}
+
+impl Serialize for StepRate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_f64(self.steps_per_second())
+    }
 }
 
 type InputsStatus = ShardedLock<BTreeMap<EndpointId, InputEndpointStatus>>;
@@ -182,6 +312,11 @@ impl ControllerStatus {
         );
     }
 
+    /// Remove stats of a disconnected output endpoint.
+    pub fn remove_output(&self, endpoint_id: &EndpointId) {
+        self.outputs.write().unwrap().remove(endpoint_id);
+    }
+
     /// Total number of records currently buffered by all input endpoints.
     pub fn num_buffered_input_records(&self) -> u64 {
         self.global_metrics.num_buffered_input_records()
@@ -201,6 +336,67 @@ impl ControllerStatus {
             .set_num_total_processed_records(total_processed_records);
     }
 
+    /// Number of input records received from all input endpoints but not
+    /// yet processed by the circuit.
+    pub fn input_backlog(&self) -> u64 {
+        self.num_total_input_records()
+            .saturating_sub(self.num_total_processed_records())
+    }
+
+    /// Total number of steps performed by the circuit.
+    pub fn num_total_steps(&self) -> u64 {
+        self.global_metrics.num_total_steps()
+    }
+
+    /// Average number of circuit steps per second over a sliding window.
+    pub fn steps_per_second(&self) -> f64 {
+        self.global_metrics.steps_per_second.steps_per_second()
+    }
+
+    /// Duration of the last circuit step.
+    pub fn last_step_duration(&self) -> Duration {
+        Duration::from_micros(
+            self.global_metrics
+                .last_step_duration_usecs
+                .load(Ordering::Acquire),
+        )
+    }
+
+    /// Total time spent in circuit steps.
+    pub fn total_step_duration(&self) -> Duration {
+        Duration::from_micros(
+            self.global_metrics
+                .total_step_duration_usecs
+                .load(Ordering::Acquire),
+        )
+    }
+
+    /// Update step counters after the circuit has completed a step that
+    /// took `duration`.
+    pub fn step_completed(&self, duration: Duration) {
+        self.global_metrics.step_completed(duration);
+    }
+
+    /// True if the circuit has been aborted after exceeding its memory limit.
+    pub fn memory_limit_exceeded(&self) -> bool {
+        self.global_metrics
+            .memory_limit_exceeded
+            .load(Ordering::Acquire)
+    }
+
+    pub fn set_memory_limit_exceeded(&self) {
+        self.global_metrics
+            .memory_limit_exceeded
+            .store(true, Ordering::Release);
+    }
+
+    /// True if an input endpoint has been stopped by an invalid record.
+    pub fn input_endpoint_failed(&self) -> bool {
+        self.global_metrics
+            .input_endpoint_failed
+            .load(Ordering::Acquire)
+    }
+
     /// Input endpoint stats.
     pub fn input_status(&self) -> ShardedLockReadGuard<BTreeMap<EndpointId, InputEndpointStatus>> {
         self.inputs.read().unwrap()
@@ -390,6 +586,31 @@ impl ControllerStatus {
         }
     }
 
+    pub fn dead_letter_record(&self, endpoint_id: EndpointId) {
+        if let Some(endpoint_stats) = self.input_status().get(&endpoint_id) {
+            endpoint_stats.dead_letter_record();
+        }
+    }
+
+    /// Mark the input endpoint as failed after an invalid record and the
+    /// pipeline as failed.
+    pub fn fail_input(&self, endpoint_id: EndpointId, error: &ParseError) {
+        if let Some(endpoint_stats) = self.input_status().get(&endpoint_id) {
+            endpoint_stats.fail(error);
+        }
+        self.global_metrics
+            .input_endpoint_failed
+            .store(true, Ordering::Release);
+    }
+
+    /// True if the input endpoint has been stopped by an invalid record.
+    pub fn is_input_failed(&self, endpoint_id: &EndpointId) -> bool {
+        self.input_status()
+            .get(endpoint_id)
+            .map(|endpoint_stats| endpoint_stats.is_failed())
+            .unwrap_or(false)
+    }
+
     pub fn encode_error(&self, endpoint_id: EndpointId) {
         if let Some(endpoint_stats) = self.output_status().get(&endpoint_id) {
             endpoint_stats.encode_error();
@@ -410,11 +631,12 @@ impl ControllerStatus {
 
     /// True if the pipeline has processed all inputs to completion.
     pub fn pipeline_complete(&self) -> bool {
-        // All input endpoints (if any) are at end of input.
+        // All input endpoints (if any) are at end of input or have been
+        // stopped by an invalid record.
         if !self
             .input_status()
             .values()
-            .all(|endpoint_stats| endpoint_stats.is_eoi())
+            .all(|endpoint_stats| endpoint_stats.is_eoi() || endpoint_stats.is_failed())
         {
             return false;
         }
@@ -436,10 +658,50 @@ impl ControllerStatus {
         true
     }
 
+    /// True if the pipeline is quiescent, i.e., it has no outstanding work:
+    ///
+    /// * No input records are buffered waiting for the circuit.
+    /// * All received input records have been processed by the circuit.
+    /// * The last step of the circuit produced no output records.
+    /// * All outputs have been pushed to their respective transport endpoints.
+    ///
+    /// Unlike [`Self::pipeline_complete`], this does not require input
+    /// endpoints to reach end-of-input, so a streaming pipeline can become
+    /// quiescent after draining its backlog and leave this state again as
+    /// soon as new inputs arrive.
+    pub fn is_quiescent(&self) -> bool {
+        if self.num_buffered_input_records() != 0 {
+            return false;
+        }
+
+        let total_input_records = self.num_total_input_records();
+
+        if self.num_total_processed_records() != total_input_records {
+            return false;
+        }
+
+        if self.global_metrics.step_produced_output() {
+            return false;
+        }
+
+        self.output_status().values().all(|endpoint_stats| {
+            endpoint_stats.num_total_processed_input_records() == total_input_records
+                && endpoint_stats.num_buffered_records() == 0
+        })
+    }
+
+    pub fn set_step_produced_output(&self, produced_output: bool) {
+        self.global_metrics
+            .set_step_produced_output(produced_output);
+    }
+
     pub fn update(&self) {
         self.global_metrics
             .pipeline_complete
             .store(self.pipeline_complete(), Ordering::Release);
+        self.global_metrics
+            .input_backlog
+            .store(self.input_backlog(), Ordering::Release);
     }
 }
 
@@ -463,7 +725,14 @@ pub struct InputEndpointMetrics {
 
     pub num_parse_errors: AtomicU64,
 
+    /// Number of invalid records forwarded to the dead-letter endpoint.
+    pub num_dead_letter_records: AtomicU64,
+
     pub end_of_input: AtomicBool,
+
+    /// True if the endpoint has been stopped after receiving an invalid
+    /// record (see `OnErrorPolicy::Fail`).
+    pub failed: AtomicBool,
 }
 
 /// Input endpoint status information.
@@ -532,6 +801,28 @@ impl InputEndpointStatus {
         self.metrics.num_parse_errors.fetch_add(1, Ordering::AcqRel);
     }
 
+    /// Increment dead-letter record counter.
+    fn dead_letter_record(&self) {
+        self.metrics
+            .num_dead_letter_records
+            .fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Mark the endpoint as failed.  If this is the first fatal error, save
+    /// it in `self.fatal_error`.
+    fn fail(&self, error: &ParseError) {
+        self.metrics.failed.store(true, Ordering::Release);
+
+        let mut fatal_error = self.fatal_error.lock().unwrap();
+        if fatal_error.is_none() {
+            *fatal_error = Some(format!("parse error: {error}"));
+        }
+    }
+
+    fn is_failed(&self) -> bool {
+        self.metrics.failed.load(Ordering::Acquire)
+    }
+
     /// Increment transport error counter.  If this is the first fatal error,
     /// save it in `self.fatal_error`.
     fn transport_error(&self, fatal: bool, error: &AnyError) {
@@ -653,4 +944,8 @@ impl OutputEndpointStatus {
             .total_processed_input_records
             .load(Ordering::Acquire)
     }
+
+    fn num_buffered_records(&self) -> u64 {
+        self.metrics.buffered_records.load(Ordering::Acquire)
+    }
 }
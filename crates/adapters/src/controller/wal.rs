@@ -0,0 +1,358 @@
+//! Input write-ahead log.
+//!
+//! The log is a single append-only file that stores raw input buffers
+//! received by input endpoints, tagged with endpoint names.  Each entry is
+//! encoded as:
+//!
+//! ```text
+//! ┌────────────────┬─────────────┬────────────────┬──────┐
+//! │name length: u32│endpoint name│data length: u64│ data │
+//! └────────────────┴─────────────┴────────────────┴──────┘
+//! ```
+//!
+//! with integers stored in little-endian format.  A partially written entry
+//! at the end of the file (e.g., due to a crash in the middle of a write) is
+//! discarded when the log is opened.
+//!
+//! Entries are identified by their offsets in the log.  Offsets are counted
+//! from the beginning of the log when it was opened and are not affected by
+//! truncation.
+
+use super::WalConfig;
+use anyhow::{Error as AnyError, Result as AnyResult};
+use log::warn;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{create_dir_all, rename, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+/// Name of the log file inside the WAL directory.
+const WAL_FILE_NAME: &str = "input.wal";
+
+/// Identifies a reader of the log (see [`InputWal::add_reader`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct WalReaderId(usize);
+
+pub(crate) struct InputWal {
+    path: PathBuf,
+    file: File,
+    fsync: bool,
+
+    /// Offset of the first entry in the file.
+    start: u64,
+
+    /// Offset of the end of the log.
+    end: u64,
+
+    /// Entries found in the log when it was opened, grouped by endpoint
+    /// name, that haven't been replayed yet.
+    pending: BTreeMap<String, Vec<(u64, Vec<u8>)>>,
+
+    /// For each reader, the offset of the oldest entry that the reader
+    /// hasn't fully pushed to the circuit, if any.
+    retained: HashMap<WalReaderId, Option<u64>>,
+
+    next_reader_id: usize,
+}
+
+impl InputWal {
+    /// Open the log in the directory specified by `config`, creating the
+    /// directory and the log file if they don't exist.
+    pub(crate) fn open(config: &WalConfig) -> AnyResult<Self> {
+        let dir = Path::new(config.dir.as_deref().unwrap_or("."));
+        create_dir_all(dir).map_err(|e| {
+            AnyError::msg(format!(
+                "failed to create WAL directory '{}': {e}",
+                dir.display()
+            ))
+        })?;
+
+        let path = dir.join(WAL_FILE_NAME);
+        let mut file = Self::open_file(&path)?;
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        let mut pending: BTreeMap<String, Vec<(u64, Vec<u8>)>> = BTreeMap::new();
+        let mut len = 0;
+        while let Some((endpoint_name, data, entry_len)) = decode_entry(&contents[len..]) {
+            pending
+                .entry(endpoint_name)
+                .or_default()
+                .push((len as u64, data));
+            len += entry_len;
+        }
+
+        if len < contents.len() {
+            warn!(
+                "discarding {} bytes of incomplete entries at the end of the WAL file '{}'",
+                contents.len() - len,
+                path.display()
+            );
+            file.set_len(len as u64)?;
+        }
+
+        Ok(Self {
+            path,
+            file,
+            fsync: config.fsync,
+            start: 0,
+            end: len as u64,
+            pending,
+            retained: HashMap::new(),
+            next_reader_id: 0,
+        })
+    }
+
+    fn open_file(path: &Path) -> AnyResult<File> {
+        OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .map_err(|e| {
+                AnyError::msg(format!("failed to open WAL file '{}': {e}", path.display()))
+            })
+    }
+
+    /// Remove and return entries for `endpoint_name` found in the log when
+    /// it was opened, along with their offsets.
+    pub(crate) fn take_pending(&mut self, endpoint_name: &str) -> Vec<(u64, Vec<u8>)> {
+        self.pending.remove(endpoint_name).unwrap_or_default()
+    }
+
+    /// Register a new reader of the log.
+    ///
+    /// A reader is a parser that consumes entries from the log and may
+    /// buffer an incomplete record from one entry until it receives the next
+    /// one.  Entries retained by a reader (see [`Self::retain`]) are not
+    /// removed by [`Self::truncate`].
+    pub(crate) fn add_reader(&mut self) -> WalReaderId {
+        let reader = WalReaderId(self.next_reader_id);
+        self.next_reader_id += 1;
+        self.retained.insert(reader, None);
+        reader
+    }
+
+    /// Unregister `reader`, releasing any entries it retains.
+    pub(crate) fn remove_reader(&mut self, reader: WalReaderId) {
+        self.retained.remove(&reader);
+    }
+
+    /// Retain entries starting from `offset` until `reader` has pushed them
+    /// to the circuit; `None` releases all entries retained by `reader`.
+    pub(crate) fn retain(&mut self, reader: WalReaderId, offset: Option<u64>) {
+        debug_assert!(offset.map_or(true, |offset| offset >= self.start));
+        self.retained.insert(reader, offset);
+    }
+
+    /// Offset of the end of the log.
+    pub(crate) fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Offset up to which entries appended to the log so far can be removed
+    /// once the circuit has processed all records pushed to it, i.e., the
+    /// offset of the oldest entry retained by a reader or [`Self::end`].
+    ///
+    /// The value can be passed to [`Self::truncate`].
+    pub(crate) fn consumed(&self) -> u64 {
+        self.retained
+            .values()
+            .flatten()
+            .copied()
+            .fold(self.end, u64::min)
+    }
+
+    /// Append an input buffer received from `endpoint_name` to the log.
+    ///
+    /// Returns the offset of the new entry.
+    pub(crate) fn append(&mut self, endpoint_name: &str, data: &[u8]) -> AnyResult<u64> {
+        let mut entry = Vec::with_capacity(12 + endpoint_name.len() + data.len());
+        entry.extend_from_slice(&(endpoint_name.len() as u32).to_le_bytes());
+        entry.extend_from_slice(endpoint_name.as_bytes());
+        entry.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        entry.extend_from_slice(data);
+
+        self.file.write_all(&entry)?;
+        if self.fsync {
+            self.file.sync_data()?;
+        }
+        let offset = self.end;
+        self.end += entry.len() as u64;
+
+        Ok(offset)
+    }
+
+    /// Remove all entries before offset `consumed` from the log.
+    ///
+    /// `consumed` must be a value previously returned by [`Self::consumed`].
+    pub(crate) fn truncate(&mut self, consumed: u64) -> AnyResult<()> {
+        debug_assert!(consumed <= self.end);
+
+        // Entries for endpoints that weren't connected by now are dropped
+        // from the log.
+        for (endpoint_name, entries) in std::mem::take(&mut self.pending) {
+            warn!(
+                "discarding {} WAL entries for unknown input endpoint '{endpoint_name}'",
+                entries.len()
+            );
+        }
+
+        if consumed <= self.start {
+            return Ok(());
+        }
+
+        if consumed == self.end {
+            self.file.set_len(0)?;
+        } else {
+            // Copy remaining entries to a new file and atomically replace the
+            // log with it.
+            let mut tail = Vec::with_capacity((self.end - consumed) as usize);
+            self.file.seek(SeekFrom::Start(consumed - self.start))?;
+            (&mut self.file)
+                .take(self.end - consumed)
+                .read_to_end(&mut tail)?;
+
+            let tmp_path = self.path.with_extension("wal.tmp");
+            let mut tmp_file = File::create(&tmp_path)?;
+            tmp_file.write_all(&tail)?;
+            if self.fsync {
+                tmp_file.sync_data()?;
+            }
+            rename(&tmp_path, &self.path)?;
+            self.file = Self::open_file(&self.path)?;
+        }
+
+        if self.fsync {
+            self.file.sync_all()?;
+        }
+        self.start = consumed;
+
+        Ok(())
+    }
+}
+
+/// Decode the first complete entry in `bytes`.
+///
+/// Returns endpoint name, data, and the encoded size of the entry, or `None`
+/// if `bytes` don't contain a complete entry.
+fn decode_entry(bytes: &[u8]) -> Option<(String, Vec<u8>, usize)> {
+    let name_len = u32::from_le_bytes(bytes.get(0..4)?.try_into().unwrap()) as usize;
+    let name = bytes.get(4..4 + name_len)?;
+    let data_start = 4 + name_len + 8;
+    let data_len =
+        u64::from_le_bytes(bytes.get(4 + name_len..data_start)?.try_into().unwrap()) as usize;
+    let data = bytes.get(data_start..data_start.checked_add(data_len)?)?;
+
+    Some((
+        String::from_utf8_lossy(name).into_owned(),
+        data.to_vec(),
+        data_start + data_len,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{InputWal, WAL_FILE_NAME};
+    use crate::controller::WalConfig;
+    use std::{fs::OpenOptions, io::Write};
+    use tempfile::TempDir;
+
+    fn config(dir: &TempDir) -> WalConfig {
+        WalConfig {
+            enabled: true,
+            dir: Some(dir.path().to_str().unwrap().to_string()),
+            fsync: true,
+        }
+    }
+
+    fn data(entries: Vec<(u64, Vec<u8>)>) -> Vec<Vec<u8>> {
+        entries.into_iter().map(|(_offset, data)| data).collect()
+    }
+
+    #[test]
+    fn wal_reopen_and_truncate() {
+        let dir = TempDir::new().unwrap();
+
+        let mut wal = InputWal::open(&config(&dir)).unwrap();
+        wal.append("in1", b"1,2\n").unwrap();
+        let checkpoint = wal.end();
+        wal.append("in2", b"3,4\n").unwrap();
+        wal.append("in1", b"5,6\n").unwrap();
+        drop(wal);
+
+        // Simulate a crash in the middle of writing an entry.
+        OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(WAL_FILE_NAME))
+            .unwrap()
+            .write_all(&[3, 0, 0, 0, b'i'])
+            .unwrap();
+
+        let mut wal = InputWal::open(&config(&dir)).unwrap();
+        assert_eq!(
+            data(wal.take_pending("in1")),
+            vec![b"1,2\n".to_vec(), b"5,6\n".to_vec()]
+        );
+        assert_eq!(data(wal.take_pending("in2")), vec![b"3,4\n".to_vec()]);
+        assert!(wal.take_pending("in3").is_empty());
+
+        // Remove the first entry only.
+        wal.truncate(checkpoint).unwrap();
+        drop(wal);
+
+        let mut wal = InputWal::open(&config(&dir)).unwrap();
+        assert_eq!(data(wal.take_pending("in1")), vec![b"5,6\n".to_vec()]);
+        assert_eq!(data(wal.take_pending("in2")), vec![b"3,4\n".to_vec()]);
+
+        wal.append("in1", b"7,8\n").unwrap();
+        wal.truncate(wal.consumed()).unwrap();
+        drop(wal);
+
+        let mut wal = InputWal::open(&config(&dir)).unwrap();
+        assert!(wal.take_pending("in1").is_empty());
+        assert!(wal.take_pending("in2").is_empty());
+    }
+
+    // Entries retained by a reader survive truncation.
+    #[test]
+    fn wal_retain() {
+        let dir = TempDir::new().unwrap();
+
+        let mut wal = InputWal::open(&config(&dir)).unwrap();
+        let reader1 = wal.add_reader();
+        let reader2 = wal.add_reader();
+
+        wal.append("in1", b"1,2\n").unwrap();
+        let offset = wal.append("in1", b"3,").unwrap();
+        wal.retain(reader1, Some(offset));
+        wal.append("in2", b"5,6\n").unwrap();
+        assert_eq!(wal.consumed(), offset);
+
+        wal.truncate(wal.consumed()).unwrap();
+        assert_eq!(wal.consumed(), offset);
+
+        // Truncating again is a no-op.
+        wal.truncate(wal.consumed()).unwrap();
+
+        let offset = wal.append("in1", b"4\n7,").unwrap();
+        wal.retain(reader1, Some(offset));
+        wal.retain(reader2, None);
+        wal.truncate(wal.consumed()).unwrap();
+        drop(wal);
+
+        let mut wal = InputWal::open(&config(&dir)).unwrap();
+        assert_eq!(wal.take_pending("in1"), vec![(0, b"4\n7,".to_vec())]);
+        assert!(wal.take_pending("in2").is_empty());
+
+        // Removing the reader releases its entries.
+        let reader1 = wal.add_reader();
+        wal.retain(reader1, Some(0));
+        assert_eq!(wal.consumed(), 0);
+        wal.remove_reader(reader1);
+        assert_eq!(wal.consumed(), wal.end());
+    }
+}
@@ -0,0 +1,544 @@
+use crate::{
+    format::{Encoder, InputFormat, OutputFormat, ParseError, Parser},
+    DeCollectionHandle, OutputConsumer, SerBatch,
+};
+use anyhow::{anyhow, bail, Result as AnyResult};
+use apache_avro::{from_avro_datum, to_avro_datum, to_value, Schema as AvroSchema};
+use erased_serde::Deserializer as ErasedDeserializer;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use serde_yaml::Value as YamlValue;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use ureq::{Agent, AgentBuilder};
+use utoipa::ToSchema;
+
+/// First byte of a message in the Confluent wire format.
+const MAGIC_BYTE: u8 = 0;
+
+/// Size of the Confluent wire format header: the magic byte followed by a
+/// 4-byte big-endian schema id.
+const HEADER_SIZE: usize = 5;
+
+/// Timeout of each request to the schema registry.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait before asking the registry again for a schema that
+/// could not be retrieved.
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Client for a Confluent schema registry.
+///
+/// Schemas stored in the registry are immutable, so the client caches every
+/// schema it fetches or registers by id and only contacts the registry
+/// the first time it encounters a schema id.  Failed lookups are cached
+/// too, so that a stream of messages with an unknown schema id, or an
+/// unreachable registry, doesn't cost a request per message: until
+/// [`RETRY_INTERVAL`] has passed, such messages fail with the error of the
+/// last lookup.  The caches are shared by all forks of a parser.
+///
+/// Requests are blocking, since parsers and encoders are synchronous, and
+/// are bounded by [`REQUEST_TIMEOUT`].
+struct SchemaRegistry {
+    url: String,
+    agent: Agent,
+    schemas: Mutex<HashMap<u32, Arc<AvroSchema>>>,
+    /// Time and error message of the last failed lookup of each schema id.
+    failures: Mutex<HashMap<u32, (Instant, String)>>,
+}
+
+/// Response to `GET /schemas/ids/{id}`.
+#[derive(Deserialize)]
+struct SchemaResponse {
+    schema: String,
+}
+
+/// Response to `POST /subjects/{subject}/versions`.
+#[derive(Deserialize)]
+struct RegisterResponse {
+    id: u32,
+}
+
+impl SchemaRegistry {
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            agent: AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+            schemas: Mutex::new(HashMap::new()),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Lookup schema by id.
+    fn schema(&self, id: u32) -> AnyResult<Arc<AvroSchema>> {
+        if let Some(schema) = self.schemas.lock().unwrap().get(&id) {
+            return Ok(schema.clone());
+        }
+
+        if let Some((time, error)) = self.failures.lock().unwrap().get(&id) {
+            if time.elapsed() < RETRY_INTERVAL {
+                bail!("{error}");
+            }
+        }
+
+        // Don't hold the locks while waiting for the registry.
+        match self.fetch_schema(id) {
+            Ok(schema) => {
+                self.failures.lock().unwrap().remove(&id);
+                Ok(self
+                    .schemas
+                    .lock()
+                    .unwrap()
+                    .entry(id)
+                    .or_insert_with(|| Arc::new(schema))
+                    .clone())
+            }
+            Err(e) => {
+                let error = format!("error retrieving schema {id} from the schema registry: {e}");
+                self.failures
+                    .lock()
+                    .unwrap()
+                    .insert(id, (Instant::now(), error.clone()));
+                bail!("{error}")
+            }
+        }
+    }
+
+    fn fetch_schema(&self, id: u32) -> AnyResult<AvroSchema> {
+        let response: SchemaResponse = self
+            .agent
+            .get(&format!("{}/schemas/ids/{id}", self.url))
+            .call()?
+            .into_json()?;
+        Ok(AvroSchema::parse_str(&response.schema)?)
+    }
+
+    /// Register `schema` under `subject` and return its id.
+    ///
+    /// If the schema is already registered under `subject`, the registry
+    /// returns the id of the existing schema.
+    fn register(&self, subject: &str, schema: &AvroSchema) -> AnyResult<u32> {
+        let response: RegisterResponse = self
+            .agent
+            .post(&format!("{}/subjects/{subject}/versions", self.url))
+            .set("Content-Type", "application/vnd.schemaregistry.v1+json")
+            .send_json(json!({ "schema": schema.canonical_form() }))
+            .map_err(|e| anyhow!("error registering schema under subject '{subject}': {e}"))?
+            .into_json()?;
+
+        self.schemas
+            .lock()
+            .unwrap()
+            .insert(response.id, Arc::new(schema.clone()));
+
+        Ok(response.id)
+    }
+}
+
+/// Avro format parser.
+///
+/// Decodes messages in the Confluent wire format: a magic byte, followed
+/// by the 4-byte id of the writer schema in the schema registry, followed
+/// by a binary-encoded Avro datum.  Each buffer passed to the parser must
+/// contain exactly one message, so this format only works with
+/// message-oriented transports such as Kafka.
+///
+/// Avro records are mapped to the fields of the input stream's record type
+/// by name.
+pub struct AvroInputFormat;
+
+#[derive(Deserialize, ToSchema)]
+pub struct AvroParserConfig {
+    /// Schema registry URL, e.g., `http://localhost:8081`.
+    registry_url: String,
+}
+
+impl InputFormat for AvroInputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("avro")
+    }
+
+    fn new_parser(
+        &self,
+        input_stream: &dyn DeCollectionHandle,
+        config: &YamlValue,
+    ) -> AnyResult<Box<dyn Parser>> {
+        let config = AvroParserConfig::deserialize(config)?;
+        let registry = Arc::new(SchemaRegistry::new(&config.registry_url));
+
+        Ok(Box::new(AvroParser::new(input_stream, registry)) as Box<dyn Parser>)
+    }
+}
+
+struct AvroParser {
+    /// Input handle to push parsed data to.
+    input_stream: Box<dyn DeCollectionHandle>,
+
+    registry: Arc<SchemaRegistry>,
+
+    /// Number of messages received so far, including invalid ones.  Used to
+    /// report the location of parse errors.
+    num_rows: u64,
+}
+
+impl AvroParser {
+    fn new(input_stream: &dyn DeCollectionHandle, registry: Arc<SchemaRegistry>) -> Self {
+        Self {
+            input_stream: input_stream.fork(),
+            registry,
+            num_rows: 0,
+        }
+    }
+
+    /// Decode a framed Avro message and push it to the input stream.
+    fn parse_message(&mut self, data: &[u8]) -> AnyResult<()> {
+        if data.len() < HEADER_SIZE || data[0] != MAGIC_BYTE {
+            bail!("message does not start with a valid Avro wire format header");
+        }
+
+        let schema_id = u32::from_be_bytes(data[1..HEADER_SIZE].try_into().unwrap());
+        let schema = self.registry.schema(schema_id)?;
+        let value = from_avro_datum(&schema, &mut &data[HEADER_SIZE..], None)?;
+
+        // Go through JSON to deserialize the datum into the record type of
+        // the input stream.
+        let value = JsonValue::try_from(value)?;
+        let mut deserializer = <dyn ErasedDeserializer>::erase(&value);
+        self.input_stream.insert(&mut deserializer)?;
+
+        Ok(())
+    }
+}
+
+impl Parser for AvroParser {
+    fn input(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        if data.is_empty() {
+            return (0, Vec::new());
+        }
+
+        self.num_rows += 1;
+        match self.parse_message(data) {
+            Ok(()) => (1, Vec::new()),
+            Err(e) => {
+                let mut error = ParseError::new(format!("invalid avro message: {e}"));
+                error.row = Some(self.num_rows);
+                error.record = Some(data.to_vec());
+                (0, vec![error])
+            }
+        }
+    }
+
+    fn eoi(&mut self) -> (usize, Vec<ParseError>) {
+        // Messages are parsed as soon as they are received; there is never
+        // any leftover data.
+        (0, Vec::new())
+    }
+
+    fn buffered_len(&self) -> usize {
+        0
+    }
+
+    fn flush(&mut self) {
+        self.input_stream.flush();
+    }
+
+    fn clear(&mut self) {
+        self.input_stream.clear_buffer();
+    }
+
+    fn fork(&self) -> Box<dyn Parser> {
+        Box::new(Self::new(&*self.input_stream, self.registry.clone()))
+    }
+}
+
+/// Avro format encoder.
+///
+/// Encodes each record as a separate message in the Confluent wire format
+/// (see [`AvroInputFormat`]).  The writer schema is registered with the
+/// schema registry when the encoder is created.
+///
+/// Avro messages cannot represent deletions.  A record with weight `w > 0`
+/// is encoded as `w` identical messages.  Records with negative weights are
+/// not encoded and are reported as an error.
+pub struct AvroOutputFormat;
+
+#[derive(Deserialize, ToSchema)]
+pub struct AvroEncoderConfig {
+    /// Schema registry URL, e.g., `http://localhost:8081`.
+    registry_url: String,
+
+    /// Subject to register the writer schema under.  Defaults to the fully
+    /// qualified name of the schema, i.e., the `RecordNameStrategy` of the
+    /// Confluent serializer.  Use `<topic>-value` to follow the default
+    /// `TopicNameStrategy`.
+    #[serde(default)]
+    subject: Option<String>,
+
+    /// Avro schema of the records in the output stream, in JSON.
+    schema: String,
+}
+
+impl OutputFormat for AvroOutputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("avro")
+    }
+
+    fn new_encoder(
+        &self,
+        config: &YamlValue,
+        consumer: Box<dyn OutputConsumer>,
+    ) -> AnyResult<Box<dyn Encoder>> {
+        let config = AvroEncoderConfig::deserialize(config)?;
+        let schema = AvroSchema::parse_str(&config.schema)?;
+
+        let subject = match config.subject {
+            Some(subject) => subject,
+            None => schema
+                .name()
+                .ok_or_else(|| {
+                    anyhow!("'subject' must be specified for Avro schemas that don't have a name")
+                })?
+                .fullname(None),
+        };
+
+        let registry = SchemaRegistry::new(&config.registry_url);
+        let schema_id = registry.register(&subject, &schema)?;
+
+        Ok(Box::new(AvroEncoder::new(consumer, schema, schema_id)))
+    }
+}
+
+struct AvroEncoder {
+    /// Input handle to push serialized data to.
+    output_consumer: Box<dyn OutputConsumer>,
+
+    schema: AvroSchema,
+
+    /// Wire format header prepended to every message.
+    header: [u8; HEADER_SIZE],
+}
+
+impl AvroEncoder {
+    fn new(output_consumer: Box<dyn OutputConsumer>, schema: AvroSchema, schema_id: u32) -> Self {
+        let mut header = [MAGIC_BYTE; HEADER_SIZE];
+        header[1..].copy_from_slice(&schema_id.to_be_bytes());
+
+        Self {
+            output_consumer,
+            schema,
+            header,
+        }
+    }
+}
+
+impl Encoder for AvroEncoder {
+    fn consumer(&mut self) -> &mut dyn OutputConsumer {
+        self.output_consumer.as_mut()
+    }
+
+    fn encode(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<()> {
+        let mut num_deletions = 0;
+
+        for batch in batches.iter() {
+            let mut cursor = batch.cursor();
+
+            while cursor.key_valid() {
+                let w = cursor.weight();
+
+                if w < 0 {
+                    num_deletions += 1;
+                } else if w > 0 {
+                    let value = to_value(cursor.key())?.resolve(&self.schema)?;
+
+                    let mut buffer = self.header.to_vec();
+                    buffer.extend_from_slice(&to_avro_datum(&self.schema, value)?);
+
+                    for _ in 0..w {
+                        self.output_consumer.push_buffer(&buffer);
+                    }
+                }
+
+                cursor.step_key();
+            }
+        }
+
+        if num_deletions > 0 {
+            bail!(
+                "avro format cannot encode deletions; {num_deletions} deleted records were not written"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AvroEncoder, AvroParser, SchemaRegistry};
+    use crate::{
+        seroutput::SerBatchImpl,
+        test::{MockDeZSet, TestStruct},
+        Encoder, OutputConsumer, Parser, SerBatch,
+    };
+    use apache_avro::Schema as AvroSchema;
+    use dbsp::{trace::Batch, OrdZSet};
+    use std::sync::{Arc, Mutex};
+
+    const SCHEMA: &str = r#"{
+    "type": "record",
+    "name": "TestStruct",
+    "fields": [
+        {"name": "id", "type": "long"},
+        {"name": "b", "type": "boolean"},
+        {"name": "i", "type": ["null", "long"]},
+        {"name": "s", "type": "string"}
+    ]
+}"#;
+
+    const SCHEMA_ID: u32 = 42;
+
+    /// Output consumer that stores each buffer as a separate message.
+    #[derive(Clone, Default)]
+    struct MockOutputConsumer(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl OutputConsumer for MockOutputConsumer {
+        fn push_buffer(&mut self, buffer: &[u8]) {
+            self.0.lock().unwrap().push(buffer.to_vec());
+        }
+    }
+
+    /// Registry that already contains `SCHEMA` and cannot be reached over
+    /// the network.
+    fn test_registry() -> Arc<SchemaRegistry> {
+        let registry = SchemaRegistry::new("http://127.0.0.1:1");
+        registry
+            .schemas
+            .lock()
+            .unwrap()
+            .insert(SCHEMA_ID, Arc::new(AvroSchema::parse_str(SCHEMA).unwrap()));
+        Arc::new(registry)
+    }
+
+    fn test_struct(id: u32, b: bool, i: Option<i64>, s: &str) -> TestStruct {
+        TestStruct {
+            id,
+            b,
+            i,
+            s: s.to_string(),
+        }
+    }
+
+    #[test]
+    fn avro_roundtrip() {
+        let consumer = MockOutputConsumer::default();
+        let mut encoder = AvroEncoder::new(
+            Box::new(consumer.clone()),
+            AvroSchema::parse_str(SCHEMA).unwrap(),
+            SCHEMA_ID,
+        );
+
+        let batch = OrdZSet::from_keys(
+            (),
+            vec![
+                (test_struct(1, true, Some(10), "foo"), 1i32),
+                (test_struct(2, false, None, "bar"), 2),
+            ],
+        );
+        encoder
+            .encode(&[Arc::new(SerBatchImpl::new(batch)) as Arc<dyn SerBatch>])
+            .unwrap();
+
+        let messages = consumer.0.lock().unwrap().clone();
+        assert_eq!(messages.len(), 3);
+        for message in messages.iter() {
+            assert_eq!(&message[0..5], &[0, 0, 0, 0, 42]);
+        }
+
+        let zset = MockDeZSet::<TestStruct>::new();
+        let mut parser = AvroParser::new(&zset, test_registry());
+        for message in messages.iter() {
+            assert_eq!(parser.input(message), (1, Vec::new()));
+        }
+        parser.flush();
+
+        assert_eq!(
+            zset.state().flushed,
+            vec![
+                (test_struct(1, true, Some(10), "foo"), true),
+                (test_struct(2, false, None, "bar"), true),
+                (test_struct(2, false, None, "bar"), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn avro_invalid_messages() {
+        let zset = MockDeZSet::<TestStruct>::new();
+        let mut parser = AvroParser::new(&zset, test_registry());
+
+        // Missing magic byte.
+        let (num_records, errors) = parser.input(b"\x01\x00\x00\x00\x2a");
+        assert_eq!(num_records, 0);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row, Some(1));
+
+        // Unknown schema id; the registry is unreachable.
+        let (num_records, errors) = parser.input(b"\x00\x00\x00\x00\x2b\x02");
+        assert_eq!(num_records, 0);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row, Some(2));
+
+        // Truncated datum.
+        let (num_records, errors) = parser.input(b"\x00\x00\x00\x00\x2a\x02");
+        assert_eq!(num_records, 0);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row, Some(3));
+
+        parser.flush();
+        assert!(zset.state().flushed.is_empty());
+    }
+
+    // Failed lookups are not repeated until the retry interval has passed.
+    #[test]
+    fn avro_registry_failures() {
+        let registry = test_registry();
+
+        let error = registry.schema(43).unwrap_err().to_string();
+        let (time, cached_error) = registry.failures.lock().unwrap()[&43].clone();
+        assert_eq!(cached_error, error);
+
+        assert_eq!(registry.schema(43).unwrap_err().to_string(), error);
+        assert_eq!(registry.failures.lock().unwrap()[&43].0, time);
+
+        // Known schemas are still served from the cache.
+        assert!(registry.schema(SCHEMA_ID).is_ok());
+    }
+
+    #[test]
+    fn avro_deletions() {
+        let consumer = MockOutputConsumer::default();
+        let mut encoder = AvroEncoder::new(
+            Box::new(consumer.clone()),
+            AvroSchema::parse_str(SCHEMA).unwrap(),
+            SCHEMA_ID,
+        );
+
+        let batch = OrdZSet::from_keys(
+            (),
+            vec![
+                (test_struct(1, true, Some(10), "foo"), -1i32),
+                (test_struct(2, false, None, "bar"), 1),
+            ],
+        );
+
+        // The insertion is written; the deletion is reported as an error.
+        assert!(encoder
+            .encode(&[Arc::new(SerBatchImpl::new(batch)) as Arc<dyn SerBatch>])
+            .is_err());
+        assert_eq!(consumer.0.lock().unwrap().len(), 1);
+    }
+}
@@ -1,13 +1,13 @@
 use crate::{
-    format::{Encoder, InputFormat, OutputFormat, Parser},
+    format::{split_on_newline, Encoder, InputFormat, OutputFormat, ParseError, Parser},
     DeCollectionHandle, OutputConsumer, SerBatch,
 };
 use anyhow::{Error as AnyError, Result as AnyResult};
 use csv::{
-    byte_record_deserializer, Reader as CsvReader, ReaderBuilder as CsvReaderBuilder,
+    byte_record_deserializer, ByteRecord, Reader as CsvReader, ReaderBuilder as CsvReaderBuilder,
     WriterBuilder as CsvWriterBuilder,
 };
-use erased_serde::Deserializer as ErasedDeserializer;
+use erased_serde::{Deserializer as ErasedDeserializer, Error as EError};
 use serde::Deserialize;
 use serde_yaml::Value as YamlValue;
 use std::{borrow::Cow, io::Read, mem::take, sync::Arc};
@@ -16,8 +16,39 @@ use utoipa::ToSchema;
 /// CSV format parser.
 pub struct CsvInputFormat;
 
-#[derive(Deserialize, ToSchema)]
-pub struct CsvParserConfig;
+const fn default_delimiter() -> char {
+    ','
+}
+
+const fn default_quote() -> char {
+    '"'
+}
+
+#[derive(Clone, Deserialize, ToSchema)]
+pub struct CsvParserConfig {
+    /// Field delimiter.  Must be an ASCII character.  Defaults to `,`.
+    #[serde(default = "default_delimiter")]
+    delimiter: char,
+
+    /// Quote character.  Must be an ASCII character.  Defaults to `"`.
+    #[serde(default = "default_quote")]
+    quote: char,
+
+    /// The first record of the input stream is a header that names the
+    /// columns and is skipped by the parser.  Records are always mapped to
+    /// columns by position.  Defaults to `false`.
+    #[serde(default)]
+    has_headers: bool,
+}
+
+/// Validates that `c` can be used as the `option` of the CSV parser.
+fn ascii_option(option: &str, c: char) -> AnyResult<u8> {
+    u8::try_from(c).ok().filter(u8::is_ascii).ok_or_else(|| {
+        AnyError::msg(format!(
+            "csv {option} must be an ASCII character, found '{c}'"
+        ))
+    })
+}
 
 impl InputFormat for CsvInputFormat {
     fn name(&self) -> Cow<'static, str> {
@@ -27,9 +58,13 @@ impl InputFormat for CsvInputFormat {
     fn new_parser(
         &self,
         input_stream: &dyn DeCollectionHandle,
-        _config: &YamlValue,
+        config: &YamlValue,
     ) -> AnyResult<Box<dyn Parser>> {
-        Ok(Box::new(CsvParser::new(input_stream)) as Box<dyn Parser>)
+        let config = CsvParserConfig::deserialize(config)?;
+        ascii_option("delimiter", config.delimiter)?;
+        ascii_option("quote", config.quote)?;
+
+        Ok(Box::new(CsvParser::new(input_stream, config)) as Box<dyn Parser>)
     }
 }
 
@@ -45,67 +80,139 @@ struct CsvParser {
     /// Builder used to create a new CSV reader for each received data
     /// buffer.
     builder: CsvReaderBuilder,
+
+    config: CsvParserConfig,
+
+    /// The header still needs to be skipped before parsing records.
+    ///
+    /// We create a new CSV reader for each input buffer, so the reader's
+    /// own header handling cannot be used.
+    skip_header: bool,
+
+    /// Number of records received so far, including invalid ones.  Used to
+    /// report the location of parse errors.
+    num_rows: u64,
 }
 
 impl CsvParser {
-    fn new(input_stream: &dyn DeCollectionHandle) -> Self {
+    fn new(input_stream: &dyn DeCollectionHandle, config: CsvParserConfig) -> Self {
         let mut builder = CsvReaderBuilder::new();
-        builder.has_headers(false);
+        builder
+            .has_headers(false)
+            .delimiter(config.delimiter as u8)
+            .quote(config.quote as u8);
 
         Self {
             input_stream: input_stream.fork(),
             leftover: Vec::new(),
             builder,
+            skip_header: config.has_headers,
+            config,
+            num_rows: 0,
         }
     }
 
-    fn parse_from_reader<R>(
-        input_stream: &mut dyn DeCollectionHandle,
-        mut reader: CsvReader<R>,
-    ) -> AnyResult<usize>
+    fn parse_from_reader<R>(&mut self, mut reader: CsvReader<R>) -> (usize, Vec<ParseError>)
     where
         R: Read,
     {
         let mut num_records = 0;
+        let mut errors = Vec::new();
+        let mut records = reader.byte_records();
+
+        if self.skip_header && records.next().is_some() {
+            self.skip_header = false;
+        }
+
+        for record in records {
+            self.num_rows += 1;
+            let row = self.num_rows;
+
+            let result = match record {
+                Ok(record) => {
+                    let mut deserializer = byte_record_deserializer(&record, None);
+                    let mut deserializer = <dyn ErasedDeserializer>::erase(&mut deserializer);
+                    self.input_stream
+                        .insert(&mut deserializer)
+                        .map_err(|e| Self::deserialize_error(row, &record, &e))
+                }
+                Err(e) => {
+                    let mut error = ParseError::new(format!("invalid csv record: {e}"));
+                    error.row = Some(row);
+                    Err(error)
+                }
+            };
 
-        for record in reader.byte_records() {
-            let record = record?;
-
-            let mut deserializer = byte_record_deserializer(&record, None);
-            let mut deserializer = <dyn ErasedDeserializer>::erase(&mut deserializer);
-            input_stream.insert(&mut deserializer).map_err(|e| {
-                AnyError::msg(format!(
-                    "failed to deserialize csv record '{record:?}': {e}"
-                ))
-            })?;
-            num_records += 1;
+            match result {
+                Ok(()) => num_records += 1,
+                Err(error) => errors.push(error),
+            }
         }
 
-        Ok(num_records)
+        (num_records, errors)
     }
 
-    /// Returns the index of the first character following the last newline
-    /// in `data`.
-    fn split_on_newline(data: &[u8]) -> usize {
-        let data_len = data.len();
-        let index = data
-            .iter()
-            .rev()
-            .position(|&x| x == b'\n')
-            .unwrap_or(data_len);
+    /// Convert an error returned by the deserializer into a `ParseError`
+    /// that points to the offending field.
+    fn deserialize_error(row: u64, record: &ByteRecord, error: &EError) -> ParseError {
+        let message = error.to_string();
+
+        // The CSV deserializer prefixes errors with the 0-based index of the
+        // field that failed to parse: "field <N>: <error>".
+        let (field, description) = message
+            .strip_prefix("field ")
+            .and_then(|rest| rest.split_once(": "))
+            .and_then(|(field, description)| Some((field.parse::<u64>().ok()?, description)))
+            .map_or((None, message.as_str()), |(field, description)| {
+                (Some(field), description)
+            });
+
+        ParseError {
+            description: description.to_string(),
+            row: Some(row),
+            column: field.map(|field| field + 1),
+            expected_type: expected_type(description).map(str::to_string),
+            found: field
+                .and_then(|field| record.get(field as usize))
+                .map(|found| String::from_utf8_lossy(found).into_owned()),
+            record: Self::encode_record(record),
+        }
+    }
 
-        data_len - index
+    /// Re-encode a record as a CSV line.
+    fn encode_record(record: &ByteRecord) -> Option<Vec<u8>> {
+        let mut writer = CsvWriterBuilder::new().from_writer(Vec::new());
+        writer.write_byte_record(record).ok()?;
+        writer.into_inner().ok()
+    }
+}
+
+/// Best-effort guess of the type the deserializer expected based on the
+/// error it returned.
+fn expected_type(description: &str) -> Option<&str> {
+    if let Some((_, expected)) = description.split_once(", expected ") {
+        return Some(expected);
+    }
+
+    match description {
+        "invalid digit found in string"
+        | "cannot parse integer from empty string"
+        | "number too large to fit in target type"
+        | "number too small to fit in target type" => Some("integer"),
+        "invalid float literal" | "cannot parse float from empty string" => Some("float"),
+        "provided string was not `true` or `false`" => Some("boolean"),
+        _ => None,
     }
 }
 
 impl Parser for CsvParser {
-    fn input(&mut self, data: &[u8]) -> AnyResult<usize> {
+    fn input(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
         // println!("input {} bytes:\n{}\nself.leftover:\n{}", data.len(),
         //    std::str::from_utf8(data).map(|s| s.to_string()).unwrap_or_else(|e|
         // format!("invalid csv: {e}")),    std::str::from_utf8(&self.leftover).
         // map(|s| s.to_string()).unwrap_or_else(|e| format!("invalid csv: {e}")));
 
-        let leftover = Self::split_on_newline(data);
+        let leftover = split_on_newline(data);
 
         // println!("leftover: {leftover}");
 
@@ -114,31 +221,38 @@ impl Parser for CsvParser {
             // the `leftover` buffer so it gets processed with the next input
             // buffer.
             self.leftover.extend_from_slice(data);
-            Ok(0)
+            (0, Vec::new())
         } else {
+            let mut prefix = take(&mut self.leftover);
             let reader = self
                 .builder
-                .from_reader(Read::chain(&*self.leftover, &data[0..leftover]));
+                .from_reader(Read::chain(&*prefix, &data[0..leftover]));
 
-            let res = Self::parse_from_reader(&mut *self.input_stream, reader);
+            let res = self.parse_from_reader(reader);
             // println!("parse returned: {res:?}");
 
-            self.leftover.clear();
-            self.leftover.extend_from_slice(&data[leftover..]);
+            prefix.clear();
+            prefix.extend_from_slice(&data[leftover..]);
+            self.leftover = prefix;
 
             res
         }
     }
 
-    fn eoi(&mut self) -> AnyResult<usize> {
+    fn eoi(&mut self) -> (usize, Vec<ParseError>) {
         if self.leftover.is_empty() {
-            return Ok(0);
+            return (0, Vec::new());
         }
 
         // Try to interpret the leftover chunk as a complete CSV line.
-        let reader = self.builder.from_reader(&*self.leftover);
+        let leftover = take(&mut self.leftover);
+        let reader = self.builder.from_reader(&*leftover);
+
+        self.parse_from_reader(reader)
+    }
 
-        Self::parse_from_reader(&mut *self.input_stream, reader)
+    fn buffered_len(&self) -> usize {
+        self.leftover.len()
     }
 
     fn flush(&mut self) {
@@ -150,7 +264,7 @@ impl Parser for CsvParser {
     }
 
     fn fork(&self) -> Box<dyn Parser> {
-        Box::new(Self::new(&*self.input_stream))
+        Box::new(Self::new(&*self.input_stream, self.config.clone()))
     }
 }
 
@@ -211,6 +325,10 @@ impl CsvEncoder {
 }
 
 impl Encoder for CsvEncoder {
+    fn consumer(&mut self) -> &mut dyn OutputConsumer {
+        self.output_consumer.as_mut()
+    }
+
     fn encode(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<()> {
         let buffer = take(&mut self.buffer);
         let mut writer = self.builder.from_writer(buffer);
@@ -249,3 +367,163 @@ impl Encoder for CsvEncoder {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{test::MockDeZSet, InputFormat};
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Eq, Deserialize)]
+    struct TestStruct {
+        id: u32,
+        b: bool,
+        i: i64,
+        s: String,
+    }
+
+    impl TestStruct {
+        fn new(id: u32, b: bool, i: i64, s: &str) -> Self {
+            Self {
+                id,
+                b,
+                i,
+                s: s.to_string(),
+            }
+        }
+    }
+
+    // Row 5 contains a type error in the third column.
+    const INPUT: &[u8] = b"1,true,10,foo
+2,false,20,bar
+3,true,30,baz
+4,false,40,qux
+5,true,fifty,quux
+6,false,60,corge
+";
+
+    #[test]
+    fn csv_skip_bad_records() {
+        let zset = MockDeZSet::<TestStruct>::new();
+        let mut parser = <dyn InputFormat>::get_format("csv")
+            .unwrap()
+            .new_parser(&zset, &serde_yaml::Value::Null)
+            .unwrap();
+
+        // Split the input in the middle of the bad record.
+        let (num_records, errors) = parser.input(&INPUT[0..64]);
+        assert_eq!(num_records, 4);
+        assert!(errors.is_empty());
+
+        let (num_records, errors) = parser.input(&INPUT[64..]);
+        assert_eq!(num_records, 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row, Some(5));
+        assert_eq!(errors[0].column, Some(3));
+        assert_eq!(errors[0].expected_type.as_deref(), Some("integer"));
+        assert_eq!(errors[0].found.as_deref(), Some("fifty"));
+        assert_eq!(
+            errors[0].record.as_deref(),
+            Some(&b"5,true,fifty,quux\n"[..])
+        );
+
+        parser.flush();
+        let expected = vec![
+            TestStruct::new(1, true, 10, "foo"),
+            TestStruct::new(2, false, 20, "bar"),
+            TestStruct::new(3, true, 30, "baz"),
+            TestStruct::new(4, false, 40, "qux"),
+            TestStruct::new(6, false, 60, "corge"),
+        ];
+        let flushed = zset
+            .state()
+            .flushed
+            .drain(..)
+            .map(|(val, polarity)| {
+                assert!(polarity);
+                val
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(flushed, expected);
+    }
+
+    #[test]
+    fn csv_error_location() {
+        let zset = MockDeZSet::<TestStruct>::new();
+        let mut parser = <dyn InputFormat>::get_format("csv")
+            .unwrap()
+            .new_parser(&zset, &serde_yaml::Value::Null)
+            .unwrap();
+
+        let (num_records, errors) = parser.input(INPUT);
+        assert_eq!(num_records, 5);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row, Some(5));
+        assert_eq!(errors[0].column, Some(3));
+
+        // Errors in subsequent buffers point to the correct location in the
+        // stream.
+        let (num_records, errors) = parser.input(b"7,true,70,grault\n8,maybe,80,garply\n");
+        assert_eq!(num_records, 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row, Some(8));
+        assert_eq!(errors[0].column, Some(2));
+        assert_eq!(errors[0].expected_type.as_deref(), Some("boolean"));
+
+        parser.flush();
+        assert_eq!(zset.state().flushed.len(), 6);
+        assert_eq!(
+            zset.state().flushed.last(),
+            Some(&(TestStruct::new(7, true, 70, "grault"), true))
+        );
+    }
+
+    #[test]
+    fn csv_custom_dialect() {
+        let zset = MockDeZSet::<TestStruct>::new();
+        let mut parser = <dyn InputFormat>::get_format("csv")
+            .unwrap()
+            .new_parser(
+                &zset,
+                &serde_yaml::from_str(
+                    r#"
+delimiter: ";"
+quote: "'"
+has_headers: true
+"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        // The header is skipped even when it arrives in pieces.
+        let (num_records, errors) = parser.input(b"id;b;");
+        assert_eq!(num_records, 0);
+        assert!(errors.is_empty());
+
+        let (num_records, errors) = parser.input(b"i;s\n1;true;10;'foo;bar'\n2;no;20;baz\n");
+        assert_eq!(num_records, 0);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row, Some(2));
+        assert_eq!(errors[0].column, Some(2));
+        assert_eq!(errors[0].found.as_deref(), Some("no"));
+
+        let (num_records, errors) = parser.input(b"3;false;30;'qux'\n");
+        assert_eq!(num_records, 1);
+        assert!(errors.is_empty());
+
+        parser.flush();
+        assert_eq!(
+            zset.state().flushed,
+            vec![(TestStruct::new(3, false, 30, "qux"), true)]
+        );
+    }
+
+    #[test]
+    fn csv_invalid_delimiter() {
+        let zset = MockDeZSet::<TestStruct>::new();
+        assert!(<dyn InputFormat>::get_format("csv")
+            .unwrap()
+            .new_parser(&zset, &serde_yaml::from_str("delimiter: \"§\"").unwrap())
+            .is_err());
+    }
+}
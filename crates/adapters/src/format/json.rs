@@ -0,0 +1,362 @@
+use crate::{
+    format::{split_on_newline, Encoder, InputFormat, OutputFormat, ParseError, Parser},
+    DeCollectionHandle, OutputConsumer, SerBatch,
+};
+use anyhow::Result as AnyResult;
+use erased_serde::Deserializer as ErasedDeserializer;
+use serde::Deserialize;
+use serde_json::{
+    de::SliceRead, ser::PrettyFormatter, Deserializer as JsonDeserializer,
+    Serializer as JsonSerializer,
+};
+use serde_yaml::Value as YamlValue;
+use std::{borrow::Cow, mem::take, sync::Arc};
+use utoipa::ToSchema;
+
+/// JSON format parser.
+///
+/// Parses newline-delimited JSON: each line of the input contains one or
+/// more whitespace-separated JSON values, each of which is deserialized into
+/// a record of the input stream.
+pub struct JsonInputFormat;
+
+/// JSON parser configuration.
+///
+/// The parser doesn't have any options yet.
+#[derive(Deserialize, ToSchema)]
+pub struct JsonParserConfig {}
+
+impl InputFormat for JsonInputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("json")
+    }
+
+    fn new_parser(
+        &self,
+        input_stream: &dyn DeCollectionHandle,
+        config: &YamlValue,
+    ) -> AnyResult<Box<dyn Parser>> {
+        JsonParserConfig::deserialize(config)?;
+
+        Ok(Box::new(JsonParser::new(input_stream)) as Box<dyn Parser>)
+    }
+}
+
+struct JsonParser {
+    /// Input handle to push parsed data to.
+    input_stream: Box<dyn DeCollectionHandle>,
+
+    /// Since we cannot assume that the input buffer ends on line end,
+    /// we save the "leftover" part of the buffer after the last new-line
+    /// character and prepend it to the next input buffer.
+    leftover: Vec<u8>,
+
+    /// Number of records received so far, including invalid ones.  Used to
+    /// report the location of parse errors.
+    num_rows: u64,
+}
+
+impl JsonParser {
+    fn new(input_stream: &dyn DeCollectionHandle) -> Self {
+        Self {
+            input_stream: input_stream.fork(),
+            leftover: Vec::new(),
+            num_rows: 0,
+        }
+    }
+
+    /// Parse all records in `data`, which must consist of complete lines.
+    fn parse_lines(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        let mut num_records = 0;
+        let mut errors = Vec::new();
+        for line in data.split(|&c| c == b'\n') {
+            let mut deserializer = JsonDeserializer::from_slice(line);
+
+            // `end` succeeds once only whitespace remains in the line.
+            while deserializer.end().is_err() {
+                self.num_rows += 1;
+
+                match self.parse_record(&mut deserializer, line) {
+                    Ok(()) => num_records += 1,
+                    Err(error) => {
+                        errors.push(error);
+                        // We can't tell where the invalid record ends, so
+                        // skip the rest of the line.
+                        break;
+                    }
+                }
+            }
+        }
+
+        (num_records, errors)
+    }
+
+    /// Deserialize the next record from `deserializer` and push it to the
+    /// input stream.
+    fn parse_record(
+        &mut self,
+        deserializer: &mut JsonDeserializer<SliceRead<'_>>,
+        line: &[u8],
+    ) -> Result<(), ParseError> {
+        let mut deserializer = <dyn ErasedDeserializer>::erase(deserializer);
+
+        self.input_stream.insert(&mut deserializer).map_err(|e| {
+            let mut error = ParseError::new(format!("invalid json record: {e}"));
+            error.row = Some(self.num_rows);
+            error.record = Some(line.to_vec());
+            error
+        })
+    }
+}
+
+impl Parser for JsonParser {
+    fn input(&mut self, data: &[u8]) -> (usize, Vec<ParseError>) {
+        let leftover = split_on_newline(data);
+
+        if leftover == 0 {
+            // `data` doesn't contain a new-line character; append it to
+            // the `leftover` buffer so it gets processed with the next input
+            // buffer.
+            self.leftover.extend_from_slice(data);
+            (0, Vec::new())
+        } else {
+            let mut lines = take(&mut self.leftover);
+            lines.extend_from_slice(&data[0..leftover]);
+
+            let res = self.parse_lines(&lines);
+
+            lines.clear();
+            lines.extend_from_slice(&data[leftover..]);
+            self.leftover = lines;
+
+            res
+        }
+    }
+
+    fn eoi(&mut self) -> (usize, Vec<ParseError>) {
+        // Try to interpret the leftover chunk as a complete line.
+        let leftover = take(&mut self.leftover);
+        self.parse_lines(&leftover)
+    }
+
+    fn buffered_len(&self) -> usize {
+        self.leftover.len()
+    }
+
+    fn flush(&mut self) {
+        self.input_stream.flush();
+    }
+
+    fn clear(&mut self) {
+        self.input_stream.clear_buffer();
+    }
+
+    fn fork(&self) -> Box<dyn Parser> {
+        Box::new(Self::new(&*self.input_stream))
+    }
+}
+
+/// JSON format encoder.
+///
+/// Encodes each update as a `[record, weight]` JSON array.  Updates are
+/// separated by newlines.
+pub struct JsonOutputFormat;
+
+const fn default_buffer_size_records() -> usize {
+    10_000
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct JsonEncoderConfig {
+    #[serde(default = "default_buffer_size_records")]
+    buffer_size_records: usize,
+
+    /// Pretty-print each update over multiple lines instead of encoding it
+    /// as a single line.  Pretty-printed output cannot be parsed back by the
+    /// `json` input format, which expects one or more complete JSON values
+    /// per line.
+    #[serde(default)]
+    pretty: bool,
+}
+
+impl OutputFormat for JsonOutputFormat {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("json")
+    }
+
+    fn new_encoder(
+        &self,
+        config: &YamlValue,
+        consumer: Box<dyn OutputConsumer>,
+    ) -> AnyResult<Box<dyn Encoder>> {
+        let config = JsonEncoderConfig::deserialize(config)?;
+
+        Ok(Box::new(JsonEncoder::new(consumer, config)))
+    }
+}
+
+struct JsonEncoder {
+    /// Input handle to push serialized data to.
+    output_consumer: Box<dyn OutputConsumer>,
+
+    config: JsonEncoderConfig,
+
+    buffer: Vec<u8>,
+}
+
+impl JsonEncoder {
+    fn new(output_consumer: Box<dyn OutputConsumer>, config: JsonEncoderConfig) -> Self {
+        Self {
+            output_consumer,
+            config,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl Encoder for JsonEncoder {
+    fn consumer(&mut self) -> &mut dyn OutputConsumer {
+        self.output_consumer.as_mut()
+    }
+
+    fn encode(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<()> {
+        let mut buffer = take(&mut self.buffer);
+        let mut num_records = 0;
+
+        for batch in batches.iter() {
+            let mut cursor = batch.cursor();
+
+            while cursor.key_valid() {
+                let w = cursor.weight();
+                let update = (cursor.key(), w);
+
+                if self.config.pretty {
+                    let mut serializer =
+                        JsonSerializer::with_formatter(&mut buffer, PrettyFormatter::new());
+                    serde::Serialize::serialize(&update, &mut serializer)?;
+                } else {
+                    serde_json::to_writer(&mut buffer, &update)?;
+                }
+                buffer.push(b'\n');
+                num_records += 1;
+
+                if num_records >= self.config.buffer_size_records {
+                    self.output_consumer.push_buffer(&buffer);
+                    buffer.clear();
+                    num_records = 0;
+                }
+
+                cursor.step_key();
+            }
+        }
+
+        if num_records > 0 {
+            self.output_consumer.push_buffer(&buffer);
+            buffer.clear();
+        }
+
+        self.buffer = buffer;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test::MockDeZSet, InputFormat};
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Eq, Deserialize)]
+    struct TestStruct {
+        id: u32,
+        b: bool,
+        i: i64,
+        s: String,
+    }
+
+    impl TestStruct {
+        fn new(id: u32, b: bool, i: i64, s: &str) -> Self {
+            Self {
+                id,
+                b,
+                i,
+                s: s.to_string(),
+            }
+        }
+    }
+
+    // Record 5 contains a type error in field `i`.
+    const INPUT: &[u8] = br#"{"id": 1, "b": true, "i": 10, "s": "foo"}
+{"id": 2, "b": false, "i": 20, "s": "bar"} {"id": 3, "b": true, "i": 30, "s": "baz"}
+
+{"id": 4, "b": false, "i": 40, "s": "qux"}
+{"id": 5, "b": true, "i": "fifty", "s": "quux"}
+{"id": 6, "b": false, "i": 60, "s": "corge"}
+"#;
+
+    #[test]
+    fn json_skip_bad_records() {
+        let zset = MockDeZSet::<TestStruct>::new();
+        let mut parser = <dyn InputFormat>::get_format("json")
+            .unwrap()
+            .new_parser(&zset, &serde_yaml::Value::Null)
+            .unwrap();
+
+        // Split the input in the middle of the bad record.
+        let (num_records, errors) = parser.input(&INPUT[0..190]);
+        assert_eq!(num_records, 4);
+        assert!(errors.is_empty());
+
+        let (num_records, errors) = parser.input(&INPUT[190..]);
+        assert_eq!(num_records, 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row, Some(5));
+        assert_eq!(
+            errors[0].record.as_deref(),
+            Some(&br#"{"id": 5, "b": true, "i": "fifty", "s": "quux"}"#[..])
+        );
+
+        parser.flush();
+        let expected = vec![
+            TestStruct::new(1, true, 10, "foo"),
+            TestStruct::new(2, false, 20, "bar"),
+            TestStruct::new(3, true, 30, "baz"),
+            TestStruct::new(4, false, 40, "qux"),
+            TestStruct::new(6, false, 60, "corge"),
+        ];
+        let flushed = zset
+            .state()
+            .flushed
+            .drain(..)
+            .map(|(val, polarity)| {
+                assert!(polarity);
+                val
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(flushed, expected);
+    }
+
+    #[test]
+    fn json_eoi() {
+        let zset = MockDeZSet::<TestStruct>::new();
+        let mut parser = <dyn InputFormat>::get_format("json")
+            .unwrap()
+            .new_parser(&zset, &serde_yaml::Value::Null)
+            .unwrap();
+
+        // A record without a trailing newline is parsed at the end of input.
+        let (num_records, errors) = parser.input(br#"{"id": 7, "b": true, "i": 70, "s": "x"}"#);
+        assert_eq!(num_records, 0);
+        assert!(errors.is_empty());
+
+        let (num_records, errors) = parser.eoi();
+        assert_eq!(num_records, 1);
+        assert!(errors.is_empty());
+
+        parser.flush();
+        assert_eq!(
+            zset.state().flushed,
+            vec![(TestStruct::new(7, true, 70, "x"), true)]
+        );
+    }
+}
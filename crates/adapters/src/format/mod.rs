@@ -2,22 +2,49 @@ use crate::{DeCollectionHandle, SerBatch};
 use anyhow::Result as AnyResult;
 use once_cell::sync::Lazy;
 use serde_yaml::Value as YamlValue;
-use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
-
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    error::Error as StdError,
+    fmt::{Display, Error as FmtError, Formatter},
+    sync::Arc,
+};
+
+#[cfg(feature = "with-avro")]
+mod avro;
 mod csv;
+mod json;
 
+#[cfg(feature = "with-avro")]
+pub use self::avro::{AvroEncoderConfig, AvroParserConfig};
+#[cfg(feature = "with-avro")]
+use self::avro::{AvroInputFormat, AvroOutputFormat};
 pub use self::csv::{CsvEncoderConfig, CsvParserConfig};
 use self::csv::{CsvInputFormat, CsvOutputFormat};
+pub use self::json::{JsonEncoderConfig, JsonParserConfig};
+use self::json::{JsonInputFormat, JsonOutputFormat};
 
 /// Static map of supported input formats.
 // TODO: support for registering new formats at runtime in order to allow
 // external crates to implement new formats.
-static INPUT_FORMATS: Lazy<BTreeMap<&'static str, Box<dyn InputFormat>>> =
-    Lazy::new(|| BTreeMap::from([("csv", Box::new(CsvInputFormat) as Box<dyn InputFormat>)]));
+static INPUT_FORMATS: Lazy<BTreeMap<&'static str, Box<dyn InputFormat>>> = Lazy::new(|| {
+    BTreeMap::from([
+        #[cfg(feature = "with-avro")]
+        ("avro", Box::new(AvroInputFormat) as Box<dyn InputFormat>),
+        ("csv", Box::new(CsvInputFormat) as Box<dyn InputFormat>),
+        ("json", Box::new(JsonInputFormat) as Box<dyn InputFormat>),
+    ])
+});
 
 /// Static map of supported output formats.
-static OUTPUT_FORMATS: Lazy<BTreeMap<&'static str, Box<dyn OutputFormat>>> =
-    Lazy::new(|| BTreeMap::from([("csv", Box::new(CsvOutputFormat) as Box<dyn OutputFormat>)]));
+static OUTPUT_FORMATS: Lazy<BTreeMap<&'static str, Box<dyn OutputFormat>>> = Lazy::new(|| {
+    BTreeMap::from([
+        #[cfg(feature = "with-avro")]
+        ("avro", Box::new(AvroOutputFormat) as Box<dyn OutputFormat>),
+        ("csv", Box::new(CsvOutputFormat) as Box<dyn OutputFormat>),
+        ("json", Box::new(JsonOutputFormat) as Box<dyn OutputFormat>),
+    ])
+});
 
 /// Trait that represents a specific data format.
 ///
@@ -47,6 +74,62 @@ impl dyn InputFormat {
     }
 }
 
+/// Error parsing an input record.
+///
+/// Location fields are 1-based and are set when the parser is able to
+/// pinpoint the offending part of the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Error description.
+    pub description: String,
+
+    /// Index of the invalid record in the input stream.
+    pub row: Option<u64>,
+
+    /// Index of the invalid field within the record.
+    pub column: Option<u64>,
+
+    /// Type expected in the invalid field, e.g., "integer".
+    pub expected_type: Option<String>,
+
+    /// Contents of the invalid field.
+    pub found: Option<String>,
+
+    /// Raw contents of the invalid record in the input format, used to
+    /// forward the record to a dead-letter endpoint.
+    pub record: Option<Vec<u8>>,
+}
+
+impl ParseError {
+    pub fn new(description: String) -> Self {
+        Self {
+            description,
+            row: None,
+            column: None,
+            expected_type: None,
+            found: None,
+            record: None,
+        }
+    }
+}
+
+impl StdError for ParseError {}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        if let Some(row) = self.row {
+            write!(f, "row {row}: ")?;
+        }
+        if let Some(column) = self.column {
+            write!(f, "column {column}: ")?;
+        }
+        if let (Some(expected_type), Some(found)) = (&self.expected_type, &self.found) {
+            write!(f, "expected {expected_type}, found '{found}': ")?;
+        }
+        write!(f, "{}", self.description)
+    }
+}
+
 /// Parser that converts a raw byte stream into a stream of database records.
 pub trait Parser: Send {
     /// Push a chunk of data to the parser.
@@ -59,18 +142,28 @@ pub trait Parser: Send {
     /// that cannot be fully parsed until more data or an end-of-file
     /// notification is received.
     ///
-    /// Returns the number of records in the parsed representation or an error
-    /// if parsing fails.
-    fn input(&mut self, data: &[u8]) -> AnyResult<usize>;
+    /// Returns the number of records pushed to the circuit along with errors
+    /// encountered while parsing `data`.  Records that fail to parse are
+    /// skipped and the parser keeps parsing the rest of the input.  What
+    /// happens to the invalid records is decided by the caller, e.g., by the
+    /// `on_error` policy of the controller's input endpoint.
+    fn input(&mut self, data: &[u8]) -> (usize, Vec<ParseError>);
 
     /// End-of-input-stream notification.
     ///
     /// No more data will be received from the stream.  The parser uses this
     /// notification to complete or discard any incompletely parsed records.
     ///
-    /// Returns the number of additional records pushed to the circuit or an
-    /// error if parsing fails.
-    fn eoi(&mut self) -> AnyResult<usize>;
+    /// Returns the number of additional records pushed to the circuit along
+    /// with any parse errors.
+    fn eoi(&mut self) -> (usize, Vec<ParseError>);
+
+    /// The number of bytes of incomplete records buffered by the parser.
+    ///
+    /// These are always the last bytes received via [`Self::input`].  They
+    /// haven't been pushed to the circuit yet and will be parsed once more
+    /// data or an end-of-file notification is received.
+    fn buffered_len(&self) -> usize;
 
     /// Flush input handles.
     ///
@@ -119,9 +212,37 @@ impl dyn OutputFormat {
 }
 
 pub trait Encoder: Send {
+    /// Returns a reference to the consumer that the encoder is connected to.
+    fn consumer(&mut self) -> &mut dyn OutputConsumer;
+
     fn encode(&mut self, batches: &[Arc<dyn SerBatch>]) -> AnyResult<()>;
 }
 
 pub trait OutputConsumer: Send {
+    /// Called before pushing the outputs of a circuit step to the consumer
+    /// (see [`OutputEndpoint::batch_start`](`crate::OutputEndpoint::batch_start`)).
+    fn batch_start(&mut self) {}
+
     fn push_buffer(&mut self, buffer: &[u8]);
+
+    /// Called after pushing all outputs of a circuit step to the consumer.
+    fn batch_end(&mut self) {}
+
+    /// Called instead of [`Self::batch_end`] when encoding the outputs of a
+    /// circuit step failed
+    /// (see [`OutputEndpoint::batch_abort`](`crate::OutputEndpoint::batch_abort`)).
+    fn batch_abort(&mut self) {}
+}
+
+/// Returns the index of the first character following the last newline
+/// in `data`.
+pub(crate) fn split_on_newline(data: &[u8]) -> usize {
+    let data_len = data.len();
+    let index = data
+        .iter()
+        .rev()
+        .position(|&x| x == b'\n')
+        .unwrap_or(data_len);
+
+    data_len - index
 }
@@ -132,16 +132,17 @@ pub enum PipelineState {
     Terminated = 2,
 }
 
-pub use catalog::Catalog;
+pub use catalog::{Catalog, CatalogSchemas, RecordSchema};
 pub use deinput::{
     DeCollectionHandle, DeMapHandle, DeScalarHandle, DeScalarHandleImpl, DeSetHandle, DeZSetHandle,
 };
-pub use format::{Encoder, InputFormat, OutputConsumer, OutputFormat, Parser};
+pub use format::{Encoder, InputFormat, OutputConsumer, OutputFormat, ParseError, Parser};
 pub use seroutput::{SerBatch, SerCursor, SerOutputBatchHandle};
 
 pub use controller::{
     Controller, ControllerError, ControllerStatus, FormatConfig, GlobalPipelineConfig,
-    InputEndpointConfig, OutputEndpointConfig, PipelineConfig, TransportConfig,
+    InputEndpointConfig, OnErrorPolicy, OutputEndpointConfig, PipelineConfig, TransportConfig,
+    WalConfig,
 };
 pub use transport::{
     FileInputTransport, InputConsumer, InputEndpoint, InputTransport, OutputEndpoint,
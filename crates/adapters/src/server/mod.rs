@@ -1,5 +1,6 @@
 use crate::{
-    Catalog, Controller, ControllerError, HttpInputTransport, HttpOutputTransport, PipelineConfig,
+    controller::default_max_buffered_records, Catalog, Controller, ControllerError, FormatConfig,
+    HttpInputTransport, HttpOutputTransport, OutputEndpointConfig, PipelineConfig, TransportConfig,
 };
 use actix_web::{
     dev::{Server, ServiceFactory, ServiceRequest},
@@ -16,14 +17,26 @@ use dbsp::DBSPHandle;
 use env_logger::Env;
 use log::{error, info};
 use serde::Serialize;
-use std::{net::TcpListener, sync::Mutex};
+use serde_yaml::Value as YamlValue;
+use std::{
+    borrow::Cow,
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
 use tokio::{
     spawn,
     sync::mpsc::{channel, Receiver, Sender},
 };
 mod prometheus;
+mod subscription;
 
-use self::prometheus::PrometheusMetrics;
+use self::{
+    prometheus::PrometheusMetrics,
+    subscription::{Subscription, SubscriptionArgs},
+};
 
 struct ServerState {
     metadata: String,
@@ -33,6 +46,8 @@ struct ServerState {
     /// the self-destruct task when shutting down
     /// the server.
     terminate_sender: Option<Sender<()>>,
+    /// Used to generate unique names of subscription endpoints.
+    subscription_counter: AtomicU64,
 }
 
 impl ServerState {
@@ -47,6 +62,7 @@ impl ServerState {
             controller: Mutex::new(Some(controller)),
             prometheus,
             terminate_sender,
+            subscription_counter: AtomicU64::new(0),
         }
     }
 }
@@ -240,11 +256,14 @@ where
         .service(pause)
         .service(shutdown)
         .service(status)
+        .service(quiescent)
         .service(metrics)
         .service(metadata)
+        .service(schema)
         .service(dump_profile)
         .service(input_endpoint)
         .service(output_endpoint)
+        .service(output_stream)
 }
 
 #[get("/start")]
@@ -288,6 +307,18 @@ async fn status(state: WebData<ServerState>) -> impl Responder {
     }
 }
 
+/// Returns `true` if the pipeline has drained all its inputs and the last
+/// step of the circuit produced no new outputs.
+#[get("/quiescent")]
+async fn quiescent(state: WebData<ServerState>) -> impl Responder {
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => HttpResponse::Ok().json(controller.is_quiescent()),
+        None => {
+            HttpResponse::Conflict().json(&ErrorResponse::new("The pipeline has been terminated"))
+        }
+    }
+}
+
 /// This endpoint is invoked by the Prometheus server.
 #[get("/metrics")]
 async fn metrics(state: WebData<ServerState>) -> impl Responder {
@@ -311,6 +342,17 @@ async fn metadata(state: WebData<ServerState>) -> impl Responder {
         .body(state.metadata.clone())
 }
 
+/// Returns record schemas of all input and output streams of the pipeline.
+#[get("/schema")]
+async fn schema(state: WebData<ServerState>) -> impl Responder {
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => HttpResponse::Ok().json(controller.schemas()),
+        None => {
+            HttpResponse::Conflict().json(&ErrorResponse::new("The pipeline has been terminated"))
+        }
+    }
+}
+
 #[get("/dump_profile")]
 async fn dump_profile(state: WebData<ServerState>) -> impl Responder {
     match &*state.controller.lock().unwrap() {
@@ -376,6 +418,282 @@ async fn output_endpoint(req: HttpRequest, stream: web::Payload) -> impl Respond
     }
 }
 
+/// Subscribe to an output stream of the pipeline.
+///
+/// Streams changes to `stream_name` to the client as server-sent events,
+/// one event per buffer produced by the encoder.  Query parameters:
+///
+/// * `format` - output format (`json` by default).
+/// * `backpressure` - what to do when the client falls behind the pipeline:
+///   `drop` (default) discards outputs, `buffer` queues them in memory.
+///
+/// The subscription ends when the client disconnects or the pipeline
+/// terminates.
+#[get("/output_stream/{stream_name}")]
+async fn output_stream(
+    state: WebData<ServerState>,
+    stream_name: web::Path<String>,
+    args: web::Query<SubscriptionArgs>,
+) -> impl Responder {
+    let endpoint_name = format!(
+        "subscription-{}",
+        state.subscription_counter.fetch_add(1, Ordering::Relaxed)
+    );
+    let config = OutputEndpointConfig {
+        stream: Cow::Owned(stream_name.into_inner()),
+        transport: TransportConfig {
+            name: Cow::Borrowed("sse"),
+            config: YamlValue::Null,
+        },
+        format: FormatConfig {
+            name: Cow::Owned(args.format.clone()),
+            config: YamlValue::Null,
+        },
+        max_buffered_records: default_max_buffered_records(),
+    };
+
+    let (subscription, endpoint) = Subscription::new(args.backpressure);
+
+    match &*state.controller.lock().unwrap() {
+        Some(controller) => {
+            match controller.add_output_endpoint(&endpoint_name, &config, Box::new(endpoint)) {
+                Ok(()) => HttpResponse::Ok()
+                    .content_type("text/event-stream")
+                    .insert_header(("Cache-Control", "no-cache"))
+                    .streaming(subscription.into_body(state.clone(), &endpoint_name)),
+                Err(e) => HttpResponse::BadRequest().json(&ErrorResponse::new(&format!(
+                    "Failed to subscribe to output stream: {e}"
+                ))),
+            }
+        }
+        None => {
+            HttpResponse::Conflict().json(&ErrorResponse::new("The pipeline has been terminated"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{build_app, PrometheusMetrics, ServerState};
+    use crate::{test::test_circuit, Controller, PipelineConfig};
+    use actix_web::{middleware::Logger, web::Data as WebData, App};
+    use futures::StreamExt;
+    use serde_json::Value as JsonValue;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[actix_web::test]
+    async fn test_schema() {
+        let (circuit, catalog) = test_circuit(1);
+
+        let config: PipelineConfig = serde_yaml::from_str("inputs: {}").unwrap();
+        let controller = Controller::with_config(
+            circuit,
+            catalog,
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        let prometheus = PrometheusMetrics::new(&controller).unwrap();
+        let state = WebData::new(ServerState::new(
+            controller,
+            prometheus,
+            "metadata".to_string(),
+            None,
+        ));
+        let server =
+            actix_test::start(move || build_app(App::new().wrap(Logger::default()), state.clone()));
+
+        let mut resp = server.get("/schema").send().await.unwrap();
+        assert!(resp.status().is_success());
+
+        // Both streams carry `TestStruct` records.
+        let schemas: JsonValue = resp.json().await.unwrap();
+        for schema in [
+            &schemas["inputs"]["test_input1"],
+            &schemas["outputs"]["test_output1"],
+        ] {
+            let properties = &schema["properties"];
+            assert_eq!(properties.as_object().unwrap().len(), 4);
+            assert_eq!(properties["id"]["type"], "integer");
+            assert_eq!(properties["b"]["type"], "boolean");
+            assert_eq!(properties["i"]["type"], "integer");
+            assert_eq!(properties["i"]["nullable"], true);
+            assert_eq!(properties["s"]["type"], "string");
+        }
+
+        let resp = server.get("/shutdown").send().await.unwrap();
+        assert!(resp.status().is_success());
+    }
+
+    #[actix_web::test]
+    async fn test_metrics() {
+        let (circuit, catalog) = test_circuit(1);
+
+        let config_str = r#"
+inputs:
+    test_input_http:
+        stream: test_input1
+        transport:
+            name: http
+        format:
+            name: csv
+outputs:
+    test_output_http:
+        stream: test_output1
+        transport:
+            name: http
+        format:
+            name: csv
+"#;
+        let config: PipelineConfig = serde_yaml::from_str(config_str).unwrap();
+        let controller = Controller::with_config(
+            circuit,
+            catalog,
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        let prometheus = PrometheusMetrics::new(&controller).unwrap();
+        let state = WebData::new(ServerState::new(
+            controller,
+            prometheus,
+            "metadata".to_string(),
+            None,
+        ));
+        let server =
+            actix_test::start(move || build_app(App::new().wrap(Logger::default()), state.clone()));
+
+        let mut resp = server.get("/metrics").send().await.unwrap();
+        assert!(resp.status().is_success());
+
+        let body = resp.body().await.unwrap();
+        let metrics = std::str::from_utf8(&body).unwrap();
+        for metric in [
+            "total_steps 0",
+            "total_input_records 0",
+            "last_step_duration_seconds 0",
+            "total_step_duration_seconds 0",
+            "input_total_records{endpoint=\"test_input_http\",stream=\"test_input1\"} 0",
+            "output_buffered_batches{endpoint=\"test_output_http\",stream=\"test_output1\"} 0",
+        ] {
+            assert!(
+                metrics.lines().any(|line| line == metric),
+                "missing '{metric}' in:\n{metrics}"
+            );
+        }
+
+        let resp = server.get("/shutdown").send().await.unwrap();
+        assert!(resp.status().is_success());
+    }
+
+    #[actix_web::test]
+    async fn test_output_stream() {
+        let (circuit, catalog) = test_circuit(1);
+
+        let mut input_file = NamedTempFile::new().unwrap();
+        input_file
+            .write_all(b"1,true,10,foo\n2,false,20,bar\n")
+            .unwrap();
+
+        let config_str = format!(
+            r#"
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: file
+            config:
+                path: {:?}
+                mode: once
+        format:
+            name: csv
+"#,
+            input_file.path().to_str().unwrap()
+        );
+        let config: PipelineConfig = serde_yaml::from_str(&config_str).unwrap();
+        let controller = Controller::with_config(
+            circuit,
+            catalog,
+            &config,
+            Box::new(|e| panic!("error: {e}")),
+        )
+        .unwrap();
+
+        let prometheus = PrometheusMetrics::new(&controller).unwrap();
+        let state = WebData::new(ServerState::new(
+            controller,
+            prometheus,
+            "metadata".to_string(),
+            None,
+        ));
+        let server =
+            actix_test::start(move || build_app(App::new().wrap(Logger::default()), state.clone()));
+
+        let resp = server
+            .get("/output_stream/no_such_stream")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 400);
+
+        let resp = server
+            .get("/output_stream/test_output1?format=xml")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 400);
+
+        // Subscribe before starting the pipeline, so we don't miss any outputs.
+        let mut resp = server
+            .get("/output_stream/test_output1?backpressure=buffer")
+            .send()
+            .await
+            .unwrap();
+        assert!(resp.status().is_success());
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "text/event-stream"
+        );
+
+        let resp_start = server.get("/start").send().await.unwrap();
+        assert!(resp_start.status().is_success());
+
+        let mut events = String::new();
+        while events.matches("data: ").count() < 2 {
+            let chunk = resp.next().await.unwrap().unwrap();
+            events.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        assert!(events.ends_with("\n\n"));
+
+        let mut updates = events
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str::<JsonValue>(data).unwrap())
+            .collect::<Vec<_>>();
+        updates.sort_by_key(|update| update[0]["id"].as_u64());
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0][0]["s"], "foo");
+        assert_eq!(updates[0][1], 1);
+        assert_eq!(updates[1][0]["s"], "bar");
+
+        // Metrics of the subscription endpoint are exported while it's live.
+        let mut metrics = server.get("/metrics").send().await.unwrap();
+        assert!(metrics.status().is_success());
+        let metrics = metrics.body().await.unwrap();
+        assert!(std::str::from_utf8(&metrics)
+            .unwrap()
+            .contains("endpoint=\"subscription-0\""));
+
+        drop(resp);
+
+        let resp = server.get("/shutdown").send().await.unwrap();
+        assert!(resp.status().is_success());
+    }
+}
+
 #[cfg(test)]
 #[cfg(feature = "with-kafka")]
 #[cfg(feature = "server")]
@@ -517,6 +835,10 @@ outputs:
         let resp = server.get("/status").send().await.unwrap();
         assert!(resp.status().is_success());
 
+        println!("/quiescent");
+        let resp = server.get("/quiescent").send().await.unwrap();
+        assert!(resp.status().is_success());
+
         println!("/metadata");
         let resp = server.get("/metadata").send().await.unwrap();
         assert!(resp.status().is_success());
@@ -648,4 +970,87 @@ outputs:
         drop(buffer_consumer);
         drop(kafka_resources);
     }
+
+    #[actix_web::test]
+    async fn test_server_json() {
+        let mut runner = TestRunner::default();
+        let data = generate_test_batches(100, 1000)
+            .new_tree(&mut runner)
+            .unwrap()
+            .current();
+
+        let _ = log::set_logger(&TEST_LOGGER);
+        log::set_max_level(LevelFilter::Debug);
+
+        let kafka_resources = KafkaResources::create_topics(&[
+            ("test_server_json_input_topic", 1),
+            ("test_server_json_output_topic", 1),
+        ]);
+
+        let buffer_consumer = BufferConsumer::new_json("test_server_json_output_topic");
+
+        let config_str = r#"
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: kafka
+            config:
+                bootstrap.servers: "localhost"
+                auto.offset.reset: "earliest"
+                topics: [test_server_json_input_topic]
+                log_level: debug
+        format:
+            name: json
+outputs:
+    test_output1:
+        stream: test_output1
+        transport:
+            name: kafka
+            config:
+                bootstrap.servers: "localhost"
+                topic: test_server_json_output_topic
+                max_inflight_messages: 0
+        format:
+            name: json
+"#;
+
+        let (circuit, catalog) = test_circuit(4);
+
+        let config: PipelineConfig = serde_yaml::from_str(config_str).unwrap();
+        let controller = Controller::with_config(
+            circuit,
+            catalog,
+            &config,
+            Box::new(|e| panic!("error: {e}")) as Box<dyn Fn(ControllerError) + Send + Sync>,
+        )
+        .unwrap();
+
+        let prometheus = PrometheusMetrics::new(&controller).unwrap();
+        let state = WebData::new(ServerState::new(
+            controller,
+            prometheus,
+            "metadata".to_string(),
+            None,
+        ));
+        let server =
+            actix_test::start(move || build_app(App::new().wrap(Logger::default()), state.clone()));
+
+        let producer = TestProducer::new();
+        producer.send_to_topic_json(&data, "test_server_json_input_topic");
+
+        println!("/start");
+        let resp = server.get("/start").send().await.unwrap();
+        assert!(resp.status().is_success());
+
+        buffer_consumer.wait_for_output_unordered(&data);
+        buffer_consumer.clear();
+
+        println!("/shutdown");
+        let resp = server.get("/shutdown").send().await.unwrap();
+        assert!(resp.status().is_success());
+
+        drop(buffer_consumer);
+        drop(kafka_resources);
+    }
 }
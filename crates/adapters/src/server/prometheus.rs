@@ -3,25 +3,36 @@ use crate::{
     Controller,
 };
 use anyhow::{Error as AnyError, Result as AnyResult};
-use prometheus::{Encoder, IntGauge, Opts, Registry, TextEncoder};
-use std::{collections::BTreeMap, sync::atomic::Ordering};
+use prometheus::{Encoder, Gauge, IntCounter, IntGauge, Opts, Registry, TextEncoder};
+use std::{
+    collections::BTreeMap,
+    sync::{atomic::Ordering, Mutex},
+};
 
 /// Prometheus metrics of the controller.
 ///
 /// The primary metrics are stored in `controller.status` and are mirrored
-/// to Prometheus metrics on demand.
+/// to Prometheus metrics on demand.  Endpoints can be added and removed
+/// while the pipeline is running (e.g., by `/output_stream` subscriptions),
+/// so per-endpoint metrics are registered and unregistered lazily, to match
+/// the set of endpoints in `controller.status` at the time of the scrape.
 pub(crate) struct PrometheusMetrics {
     registry: Registry,
-    input_metrics: BTreeMap<EndpointId, InputMetrics>,
-    output_metrics: BTreeMap<EndpointId, OutputMetrics>,
+    global_metrics: GlobalMetrics,
+    input_metrics: Mutex<BTreeMap<EndpointId, InputMetrics>>,
+    output_metrics: Mutex<BTreeMap<EndpointId, OutputMetrics>>,
 }
 
 impl PrometheusMetrics {
     pub(crate) fn new(controller: &Controller) -> AnyResult<Self> {
-        let mut result = Self {
-            registry: Registry::new(),
-            input_metrics: BTreeMap::new(),
-            output_metrics: BTreeMap::new(),
+        let registry = Registry::new();
+        let global_metrics = GlobalMetrics::new(&registry)?;
+
+        let result = Self {
+            registry,
+            global_metrics,
+            input_metrics: Mutex::new(BTreeMap::new()),
+            output_metrics: Mutex::new(BTreeMap::new()),
         };
 
         let status = controller.status();
@@ -38,19 +49,20 @@ impl PrometheusMetrics {
     }
 
     pub(crate) fn add_input_endpoint(
-        &mut self,
+        &self,
         endpoint_id: EndpointId,
         status: &InputEndpointStatus,
     ) -> AnyResult<()> {
-        let total_bytes = self.create_gauge("input_total_bytes", &status.endpoint_name)?;
-        let total_records = self.create_gauge("input_total_records", &status.endpoint_name)?;
-        let buffered_bytes = self.create_gauge("input_buffered_bytes", &status.endpoint_name)?;
-        let buffered_records =
-            self.create_gauge("input_buffered_records", &status.endpoint_name)?;
+        let endpoint = &status.endpoint_name;
+        let stream = &status.config.stream;
+
+        let total_bytes = self.create_gauge("input_total_bytes", endpoint, stream)?;
+        let total_records = self.create_gauge("input_total_records", endpoint, stream)?;
+        let buffered_bytes = self.create_gauge("input_buffered_bytes", endpoint, stream)?;
+        let buffered_records = self.create_gauge("input_buffered_records", endpoint, stream)?;
         let num_transport_errors =
-            self.create_gauge("input_num_transport_errors", &status.endpoint_name)?;
-        let num_parse_errors =
-            self.create_gauge("input_num_parse_errors", &status.endpoint_name)?;
+            self.create_gauge("input_num_transport_errors", endpoint, stream)?;
+        let num_parse_errors = self.create_gauge("input_num_parse_errors", endpoint, stream)?;
 
         let input_metrics = InputMetrics {
             total_bytes,
@@ -61,7 +73,32 @@ impl PrometheusMetrics {
             num_parse_errors,
         };
 
-        self.input_metrics.insert(endpoint_id, input_metrics);
+        self.input_metrics
+            .lock()
+            .unwrap()
+            .insert(endpoint_id, input_metrics);
+        Ok(())
+    }
+
+    /// Unregister metrics of input endpoints that are no longer in `status`.
+    fn remove_stale_input_endpoints(
+        &self,
+        status: &BTreeMap<EndpointId, InputEndpointStatus>,
+    ) -> AnyResult<()> {
+        let mut input_metrics = self.input_metrics.lock().unwrap();
+        let stale = input_metrics
+            .keys()
+            .filter(|endpoint_id| !status.contains_key(endpoint_id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for endpoint_id in stale {
+            let metrics = input_metrics.remove(&endpoint_id).unwrap();
+            for gauge in metrics.gauges() {
+                self.registry.unregister(Box::new(gauge.clone()))?;
+            }
+        }
+
         Ok(())
     }
 
@@ -70,7 +107,17 @@ impl PrometheusMetrics {
         endpoint_id: EndpointId,
         status: &InputEndpointStatus,
     ) -> AnyResult<()> {
-        let metrics = self.input_metrics.get(&endpoint_id).ok_or_else(|| {
+        if !self
+            .input_metrics
+            .lock()
+            .unwrap()
+            .contains_key(&endpoint_id)
+        {
+            self.add_input_endpoint(endpoint_id, status)?;
+        }
+
+        let input_metrics = self.input_metrics.lock().unwrap();
+        let metrics = input_metrics.get(&endpoint_id).ok_or_else(|| {
             AnyError::msg(format!("Missing metrics for input endpoint {endpoint_id}"))
         })?;
 
@@ -97,22 +144,21 @@ impl PrometheusMetrics {
     }
 
     pub(crate) fn add_output_endpoint(
-        &mut self,
+        &self,
         endpoint_id: EndpointId,
         status: &OutputEndpointStatus,
     ) -> AnyResult<()> {
-        let transmitted_bytes =
-            self.create_gauge("output_transmitted_bytes", &status.endpoint_name)?;
+        let endpoint = &status.endpoint_name;
+        let stream = &status.config.stream;
+
+        let transmitted_bytes = self.create_gauge("output_transmitted_bytes", endpoint, stream)?;
         let transmitted_records =
-            self.create_gauge("output_transmitted_records", &status.endpoint_name)?;
-        let buffered_records =
-            self.create_gauge("output_buffered_records", &status.endpoint_name)?;
-        let buffered_batches =
-            self.create_gauge("output_buffered_batches", &status.endpoint_name)?;
+            self.create_gauge("output_transmitted_records", endpoint, stream)?;
+        let buffered_records = self.create_gauge("output_buffered_records", endpoint, stream)?;
+        let buffered_batches = self.create_gauge("output_buffered_batches", endpoint, stream)?;
         let num_transport_errors =
-            self.create_gauge("output_num_transport_errors", &status.endpoint_name)?;
-        let num_encode_errors =
-            self.create_gauge("output_num_encode_errors", &status.endpoint_name)?;
+            self.create_gauge("output_num_transport_errors", endpoint, stream)?;
+        let num_encode_errors = self.create_gauge("output_num_encode_errors", endpoint, stream)?;
 
         let output_metrics = OutputMetrics {
             transmitted_bytes,
@@ -123,7 +169,32 @@ impl PrometheusMetrics {
             num_encode_errors,
         };
 
-        self.output_metrics.insert(endpoint_id, output_metrics);
+        self.output_metrics
+            .lock()
+            .unwrap()
+            .insert(endpoint_id, output_metrics);
+        Ok(())
+    }
+
+    /// Unregister metrics of output endpoints that are no longer in `status`.
+    fn remove_stale_output_endpoints(
+        &self,
+        status: &BTreeMap<EndpointId, OutputEndpointStatus>,
+    ) -> AnyResult<()> {
+        let mut output_metrics = self.output_metrics.lock().unwrap();
+        let stale = output_metrics
+            .keys()
+            .filter(|endpoint_id| !status.contains_key(endpoint_id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for endpoint_id in stale {
+            let metrics = output_metrics.remove(&endpoint_id).unwrap();
+            for gauge in metrics.gauges() {
+                self.registry.unregister(Box::new(gauge.clone()))?;
+            }
+        }
+
         Ok(())
     }
 
@@ -132,7 +203,17 @@ impl PrometheusMetrics {
         endpoint_id: EndpointId,
         status: &OutputEndpointStatus,
     ) -> AnyResult<()> {
-        let metrics = self.output_metrics.get(&endpoint_id).ok_or_else(|| {
+        if !self
+            .output_metrics
+            .lock()
+            .unwrap()
+            .contains_key(&endpoint_id)
+        {
+            self.add_output_endpoint(endpoint_id, status)?;
+        }
+
+        let output_metrics = self.output_metrics.lock().unwrap();
+        let metrics = output_metrics.get(&endpoint_id).ok_or_else(|| {
             AnyError::msg(format!("Missing metrics for output endpoint {endpoint_id}"))
         })?;
 
@@ -162,13 +243,45 @@ impl PrometheusMetrics {
     pub(crate) fn metrics(&self, controller: &Controller) -> AnyResult<Vec<u8>> {
         let status = controller.status();
 
-        for (endpoint_id, endpoint_status) in status.input_status().iter() {
+        // The step count only grows, so catch the counter up with it.
+        let total_steps = self.global_metrics.total_steps.lock().unwrap();
+        total_steps.inc_by(status.num_total_steps().saturating_sub(total_steps.get()));
+        drop(total_steps);
+        self.global_metrics
+            .steps_per_second
+            .set(status.steps_per_second());
+        self.global_metrics
+            .input_backlog_records
+            .set(status.input_backlog() as i64);
+        self.global_metrics
+            .total_input_records
+            .set(status.num_total_input_records() as i64);
+        self.global_metrics
+            .total_processed_records
+            .set(status.num_total_processed_records() as i64);
+        self.global_metrics
+            .buffered_input_records
+            .set(status.num_buffered_input_records() as i64);
+        self.global_metrics
+            .last_step_duration_seconds
+            .set(status.last_step_duration().as_secs_f64());
+        self.global_metrics
+            .total_step_duration_seconds
+            .set(status.total_step_duration().as_secs_f64());
+
+        let input_status = status.input_status();
+        self.remove_stale_input_endpoints(&input_status)?;
+        for (endpoint_id, endpoint_status) in input_status.iter() {
             self.update_input_metrics(*endpoint_id, endpoint_status)?;
         }
+        drop(input_status);
 
-        for (endpoint_id, endpoint_status) in status.output_status().iter() {
+        let output_status = status.output_status();
+        self.remove_stale_output_endpoints(&output_status)?;
+        for (endpoint_id, endpoint_status) in output_status.iter() {
             self.update_output_metrics(*endpoint_id, endpoint_status)?;
         }
+        drop(output_status);
 
         let mut buffer = vec![];
         let encoder = TextEncoder::new();
@@ -178,8 +291,10 @@ impl PrometheusMetrics {
         Ok(buffer)
     }
 
-    fn create_gauge(&self, name: &str, endpoint: &str) -> AnyResult<IntGauge> {
-        let opts = Opts::new(name, name).const_label("endpoint", endpoint);
+    fn create_gauge(&self, name: &str, endpoint: &str, stream: &str) -> AnyResult<IntGauge> {
+        let opts = Opts::new(name, name)
+            .const_label("endpoint", endpoint)
+            .const_label("stream", stream);
         let gauge = IntGauge::with_opts(opts)?;
         self.registry.register(Box::new(gauge.clone()))?;
 
@@ -187,6 +302,80 @@ impl PrometheusMetrics {
     }
 }
 
+/// Pipeline-wide metrics.
+struct GlobalMetrics {
+    // Locked while catching up with the controller's step count, so that
+    // concurrent scrapes don't both add the same steps.
+    total_steps: Mutex<IntCounter>,
+    steps_per_second: Gauge,
+    input_backlog_records: IntGauge,
+    total_input_records: IntGauge,
+    total_processed_records: IntGauge,
+    buffered_input_records: IntGauge,
+    last_step_duration_seconds: Gauge,
+    total_step_duration_seconds: Gauge,
+}
+
+impl GlobalMetrics {
+    fn new(registry: &Registry) -> AnyResult<Self> {
+        let total_steps = IntCounter::new("total_steps", "Total number of circuit steps")?;
+        registry.register(Box::new(total_steps.clone()))?;
+
+        let steps_per_second = Gauge::new(
+            "steps_per_second",
+            "Circuit steps per second over a sliding window",
+        )?;
+        registry.register(Box::new(steps_per_second.clone()))?;
+
+        let input_backlog_records = IntGauge::new(
+            "input_backlog_records",
+            "Input records received but not yet processed by the circuit",
+        )?;
+        registry.register(Box::new(input_backlog_records.clone()))?;
+
+        let total_input_records = IntGauge::new(
+            "total_input_records",
+            "Total number of records received from all input endpoints",
+        )?;
+        registry.register(Box::new(total_input_records.clone()))?;
+
+        let total_processed_records = IntGauge::new(
+            "total_processed_records",
+            "Total number of input records processed by the circuit",
+        )?;
+        registry.register(Box::new(total_processed_records.clone()))?;
+
+        let buffered_input_records = IntGauge::new(
+            "buffered_input_records",
+            "Input records buffered by all input endpoints",
+        )?;
+        registry.register(Box::new(buffered_input_records.clone()))?;
+
+        let last_step_duration_seconds = Gauge::new(
+            "last_step_duration_seconds",
+            "Duration of the last circuit step",
+        )?;
+        registry.register(Box::new(last_step_duration_seconds.clone()))?;
+
+        let total_step_duration_seconds = Gauge::new(
+            "total_step_duration_seconds",
+            "Total time spent in circuit steps",
+        )?;
+        registry.register(Box::new(total_step_duration_seconds.clone()))?;
+
+        Ok(Self {
+            total_steps: Mutex::new(total_steps),
+            steps_per_second,
+            input_backlog_records,
+            total_input_records,
+            total_processed_records,
+            buffered_input_records,
+            last_step_duration_seconds,
+            total_step_duration_seconds,
+        })
+    }
+}
+
 struct InputMetrics {
     total_bytes: IntGauge,
     total_records: IntGauge,
@@ -196,6 +385,19 @@ struct InputMetrics {
     num_parse_errors: IntGauge,
 }
 
+impl InputMetrics {
+    fn gauges(&self) -> [&IntGauge; 6] {
+        [
+            &self.total_bytes,
+            &self.total_records,
+            &self.buffered_bytes,
+            &self.buffered_records,
+            &self.num_transport_errors,
+            &self.num_parse_errors,
+        ]
+    }
+}
+
 struct OutputMetrics {
     transmitted_bytes: IntGauge,
     transmitted_records: IntGauge,
@@ -204,3 +406,16 @@ struct OutputMetrics {
     num_transport_errors: IntGauge,
     num_encode_errors: IntGauge,
 }
+
+impl OutputMetrics {
+    fn gauges(&self) -> [&IntGauge; 6] {
+        [
+            &self.transmitted_bytes,
+            &self.transmitted_records,
+            &self.buffered_records,
+            &self.buffered_batches,
+            &self.num_transport_errors,
+            &self.num_encode_errors,
+        ]
+    }
+}
@@ -0,0 +1,180 @@
+//! Output stream subscriptions: stream outputs of the pipeline to HTTP
+//! clients as server-sent events.
+
+use super::ServerState;
+use crate::OutputEndpoint;
+use actix_web::{web::Data as WebData, Error as ActixError};
+use anyhow::Result as AnyResult;
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::{stream, Stream};
+use log::{debug, error};
+use serde::Deserialize;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Maximal number of events queued for a subscription with the
+/// [`Backpressure::Drop`] policy.
+const MAX_QUEUED_EVENTS: usize = 100;
+
+/// What to do with outputs when the client reads them slower than the
+/// pipeline produces them.
+///
+/// Subscriptions never stall the circuit.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum Backpressure {
+    /// Discard output buffers while the client is more than
+    /// `MAX_QUEUED_EVENTS` buffers behind.
+    #[default]
+    Drop,
+
+    /// Queue all outputs in memory until the client reads them.
+    Buffer,
+}
+
+/// Query parameters of the `/output_stream/{stream_name}` endpoint.
+#[derive(Deserialize)]
+pub(super) struct SubscriptionArgs {
+    /// Output format.  Each output buffer produced by the encoder is sent as
+    /// a single event, so the format must produce text.
+    #[serde(default = "default_format")]
+    pub(super) format: String,
+
+    #[serde(default)]
+    pub(super) backpressure: Backpressure,
+}
+
+fn default_format() -> String {
+    "json".to_string()
+}
+
+/// Output endpoint that forwards output buffers to a subscription.
+pub(super) struct SubscriptionEndpoint {
+    sender: UnboundedSender<Bytes>,
+
+    /// Number of events sent but not yet received by the client.
+    queued: Arc<AtomicUsize>,
+
+    backpressure: Backpressure,
+}
+
+impl OutputEndpoint for SubscriptionEndpoint {
+    fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
+        if self.backpressure == Backpressure::Drop
+            && self.queued.load(Ordering::Acquire) >= MAX_QUEUED_EVENTS
+        {
+            debug!(
+                "subscription: client is too slow; dropping {} bytes",
+                buffer.len()
+            );
+            return Ok(());
+        }
+
+        self.queued.fetch_add(1, Ordering::AcqRel);
+
+        // Sending fails if the client has disconnected, in which case the
+        // endpoint is about to be disconnected from the pipeline.
+        let _ = self.sender.send(event(buffer));
+        Ok(())
+    }
+}
+
+/// Encodes `buffer` as a server-sent event, with one `data` field per line.
+fn event(buffer: &[u8]) -> Bytes {
+    let mut event = BytesMut::with_capacity(buffer.len() + 16);
+
+    for line in buffer
+        .split(|&c| c == b'\n')
+        .filter(|line| !line.is_empty())
+    {
+        event.put_slice(b"data: ");
+        event.put_slice(line);
+        event.put_u8(b'\n');
+    }
+    event.put_u8(b'\n');
+
+    event.freeze()
+}
+
+/// Disconnects the endpoint of a subscription from the pipeline when the
+/// client goes away and the response body is dropped.
+struct SubscriptionGuard {
+    state: WebData<ServerState>,
+    endpoint_name: String,
+}
+
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        if let Some(controller) = &*self.state.controller.lock().unwrap() {
+            debug!("subscription '{}': client disconnected", self.endpoint_name);
+            if let Err(e) = controller.disconnect_output(&self.endpoint_name) {
+                error!(
+                    "failed to disconnect output endpoint '{}': {e}",
+                    self.endpoint_name
+                );
+            }
+        }
+    }
+}
+
+/// Receiving side of a subscription.
+pub(super) struct Subscription {
+    receiver: UnboundedReceiver<Bytes>,
+    queued: Arc<AtomicUsize>,
+}
+
+impl Subscription {
+    /// Creates a subscription and the output endpoint that feeds it.
+    pub(super) fn new(backpressure: Backpressure) -> (Self, SubscriptionEndpoint) {
+        let (sender, receiver) = unbounded_channel();
+        let queued = Arc::new(AtomicUsize::new(0));
+
+        let endpoint = SubscriptionEndpoint {
+            sender,
+            queued: queued.clone(),
+            backpressure,
+        };
+
+        (Self { receiver, queued }, endpoint)
+    }
+
+    /// Converts the subscription into a response body that streams events to
+    /// the client.
+    ///
+    /// `endpoint_name` is the name under which the endpoint is connected to
+    /// the controller.  The endpoint is disconnected when the body is dropped.
+    /// The body ends when the endpoint is destroyed, e.g., because the
+    /// pipeline terminates.
+    pub(super) fn into_body(
+        self,
+        state: WebData<ServerState>,
+        endpoint_name: &str,
+    ) -> impl Stream<Item = Result<Bytes, ActixError>> {
+        let guard = SubscriptionGuard {
+            state,
+            endpoint_name: endpoint_name.to_string(),
+        };
+
+        stream::unfold((self, guard), |(mut subscription, guard)| async move {
+            let event = subscription.receiver.recv().await?;
+            subscription.queued.fetch_sub(1, Ordering::AcqRel);
+            Some((Ok(event), (subscription, guard)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::event;
+
+    #[test]
+    fn test_event() {
+        assert_eq!(
+            &event(b"[{\"id\": 1}, 1]\n[{\"id\": 2}, -1]\n")[..],
+            b"data: [{\"id\": 1}, 1]\ndata: [{\"id\": 2}, -1]\n\n"
+        );
+    }
+}
@@ -3,6 +3,7 @@ use proptest::{collection, prelude::*};
 use proptest_derive::Arbitrary;
 use serde::{Deserialize, Serialize};
 use size_of::SizeOf;
+use utoipa::ToSchema;
 
 #[derive(
     Debug,
@@ -18,6 +19,7 @@ use size_of::SizeOf;
     Encode,
     Decode,
     Arbitrary,
+    ToSchema,
 )]
 pub struct TestStruct {
     pub id: u32,
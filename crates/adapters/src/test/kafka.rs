@@ -127,6 +127,22 @@ impl TestProducer {
         // println!("Data written to '{topic}'");
     }
 
+    /// Like [`Self::send_to_topic`], but encodes each record as a line of
+    /// JSON.
+    pub fn send_to_topic_json(&self, data: &[Vec<TestStruct>], topic: &str) {
+        for batch in data {
+            let mut bytes = Vec::with_capacity(batch.len() * 64);
+
+            for val in batch.iter() {
+                serde_json::to_writer(&mut bytes, val).unwrap();
+                bytes.push(b'\n');
+            }
+
+            let record = <BaseRecord<(), [u8], ()>>::to(topic).payload(&bytes);
+            self.producer.send(record).unwrap();
+        }
+    }
+
     pub fn send_string(&self, string: &str, topic: &str) {
         let record = <BaseRecord<(), str, ()>>::to(topic).payload(string);
         self.producer.send(record).unwrap();
@@ -148,8 +164,38 @@ impl Drop for BufferConsumer {
     }
 }
 
+/// Parse a CSV-encoded buffer of `(record, weight)` pairs.
+fn parse_csv(payload: &[u8]) -> Vec<(TestStruct, i32)> {
+    let mut builder = CsvReaderBuilder::new();
+    builder.has_headers(false);
+    let mut reader = builder.from_reader(payload);
+    reader
+        .deserialize::<(TestStruct, i32)>()
+        .map(Result::unwrap)
+        .collect()
+}
+
+/// Parse a buffer of `(record, weight)` pairs encoded as JSON arrays, one
+/// per line.
+fn parse_json(payload: &[u8]) -> Vec<(TestStruct, i32)> {
+    serde_json::Deserializer::from_slice(payload)
+        .into_iter::<(TestStruct, i32)>()
+        .map(Result::unwrap)
+        .collect()
+}
+
 impl BufferConsumer {
+    /// Create a consumer for a topic that contains CSV-encoded data.
     pub fn new(topic: &str) -> Self {
+        Self::with_parser(topic, parse_csv)
+    }
+
+    /// Create a consumer for a topic that contains JSON-encoded data.
+    pub fn new_json(topic: &str) -> Self {
+        Self::with_parser(topic, parse_json)
+    }
+
+    fn with_parser(topic: &str, parse: fn(&[u8]) -> Vec<(TestStruct, i32)>) -> Self {
         let buffer: Arc<Mutex<Vec<TestStruct>>> = Arc::new(Mutex::new(Vec::new()));
         let buffer_clone = buffer.clone();
 
@@ -198,15 +244,9 @@ impl BufferConsumer {
                             // message.payload().map(|payload| consumer.input(payload));
 
                             if let Some(payload) = message.payload() {
-                                let mut builder = CsvReaderBuilder::new();
-                                builder.has_headers(false);
-                                let mut reader = builder.from_reader(payload);
                                 let mut buffer = buffer_clone.lock().unwrap();
                                 // let mut num_received = 0;
-                                for (record, w) in reader
-                                    .deserialize::<(TestStruct, i32)>()
-                                    .map(Result::unwrap)
-                                {
+                                for (record, w) in parse(payload) {
                                     // num_received += 1;
                                     assert_eq!(w, 1);
                                     // println!("received record: {:?}", record);
@@ -1,5 +1,7 @@
-use crate::{controller::FormatConfig, DeCollectionHandle, InputConsumer, InputFormat, Parser};
-use anyhow::{Error as AnyError, Result as AnyResult};
+use crate::{
+    controller::FormatConfig, DeCollectionHandle, InputConsumer, InputFormat, ParseError, Parser,
+};
+use anyhow::Error as AnyError;
 use std::sync::{Arc, Mutex, MutexGuard};
 
 pub type ErrorCallback = Box<dyn FnMut(&AnyError) + Send>;
@@ -15,8 +17,9 @@ pub struct MockInputConsumerState {
     /// The last error received from the endpoint since the last `reset`.
     pub endpoint_error: Option<AnyError>,
 
-    /// The last result returned by the parser.
-    pub parser_result: Option<AnyResult<usize>>,
+    /// The last result returned by the parser: the number of parsed records
+    /// and parse errors.
+    pub parser_result: Option<(usize, Vec<ParseError>)>,
 
     /// Parser to push data to.
     parser: Box<dyn Parser>,
@@ -95,9 +98,9 @@ impl InputConsumer for MockInputConsumer {
         state.data.extend_from_slice(data);
         let parser_result = state.parser.input(data);
         // println!("parser returned '{:?}'", state.parser_result);
-        if let Err(e) = &parser_result {
+        for e in parser_result.1.iter() {
             if let Some(error_cb) = &mut state.error_cb {
-                error_cb(e);
+                error_cb(&AnyError::new(e.clone()));
             } else {
                 panic!("mock_input_consumer: parse error '{e}'");
             }
@@ -108,7 +108,9 @@ pub fn test_circuit(workers: usize) -> (DBSPHandle, Catalog) {
 
     let mut catalog = Catalog::new();
     catalog.register_input_zset_handle("test_input1", input);
+    catalog.set_input_schema::<TestStruct>("test_input1");
     catalog.register_output_batch_handle("test_output1", output);
+    catalog.set_output_schema::<TestStruct>("test_output1");
 
     (circuit, catalog)
 }
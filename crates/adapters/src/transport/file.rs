@@ -1,14 +1,17 @@
 use super::{InputConsumer, InputEndpoint, InputTransport, OutputEndpoint, OutputTransport};
-use crate::PipelineState;
+use crate::{format::split_on_newline, PipelineState};
 use anyhow::{Error as AnyError, Result as AnyResult};
 use crossbeam::sync::{Parker, Unparker};
+use glob::glob;
 use num_traits::FromPrimitive;
 use serde::Deserialize;
 use serde_yaml::Value as YamlValue;
 use std::{
     borrow::Cow,
+    collections::HashSet,
     fs::File,
     io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicU32, Ordering},
         Arc,
@@ -21,6 +24,9 @@ use utoipa::ToSchema;
 const SLEEP_MS: u64 = 200;
 
 /// `InputTransport` implementation that reads data from file.
+///
+/// The endpoint reads one or more files that match a glob pattern and
+/// forwards their contents to the parser in whole lines.
 pub struct FileInputTransport;
 
 impl InputTransport for FileInputTransport {
@@ -43,7 +49,11 @@ impl InputTransport for FileInputTransport {
 
 #[derive(Deserialize, ToSchema)]
 pub struct FileInputConfig {
-    /// File path.
+    /// File path or glob pattern, e.g., `data/*.csv`.
+    ///
+    /// Files that match the pattern are read one after another in
+    /// lexicographic order of their paths, so replaying the same set of
+    /// files always produces the same input.
     path: String,
 
     /// Read buffer size.
@@ -52,14 +62,192 @@ pub struct FileInputConfig {
     /// default is used.
     buffer_size_bytes: Option<usize>,
 
-    /// Enable file following.
+    /// What to do upon reaching the end of the input files.
+    ///
+    /// Default: `once`.
+    mode: Option<FileInputMode>,
+
+    /// Deprecated: use `mode` instead.
+    ///
+    /// `follow: true` is equivalent to `mode: follow`, `follow: false` is
+    /// equivalent to `mode: once`.  Specifying both `follow` and `mode` is
+    /// an error.
+    follow: Option<bool>,
+}
+
+impl FileInputConfig {
+    /// Input mode, taking the deprecated `follow` flag into account.
+    fn mode(&self) -> AnyResult<FileInputMode> {
+        match (self.mode, self.follow) {
+            (Some(_), Some(_)) => Err(AnyError::msg(
+                "'follow' is deprecated and cannot be combined with 'mode'",
+            )),
+            (Some(mode), None) => Ok(mode),
+            (None, Some(true)) => Ok(FileInputMode::Follow),
+            (None, Some(false)) | (None, None) => Ok(FileInputMode::Once),
+        }
+    }
+}
+
+/// File input mode.
+#[derive(Deserialize, ToSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileInputMode {
+    /// Read all files that match the pattern once, output an
+    /// [`eoi`](`InputConsumer::eoi`) message and stop.
+    #[default]
+    Once,
+
+    /// Keep watching the files for new content appended to them, similar to
+    /// `tail -f`.  New files that match the pattern are picked up as they
+    /// are created.
+    Follow,
+}
+
+/// A file that matches the input pattern.
+struct InputFile {
+    reader: BufReader<File>,
+
+    /// Trailing incomplete line read from the file.
+    ///
+    /// Data is forwarded to the consumer in whole lines, so that partial
+    /// lines of different files don't get glued together by the parser.
+    partial: Vec<u8>,
+}
+
+impl InputFile {
+    fn open(path: &Path, buffer_size: Option<usize>) -> AnyResult<Self> {
+        let file = File::open(path).map_err(|e| {
+            AnyError::msg(format!(
+                "Failed to open input file '{}': {e}",
+                path.display()
+            ))
+        })?;
+        let reader = match buffer_size {
+            Some(buffer_size) if buffer_size > 0 => BufReader::with_capacity(buffer_size, file),
+            _ => BufReader::new(file),
+        };
+
+        Ok(Self {
+            reader,
+            partial: Vec::new(),
+        })
+    }
+
+    /// Read the next chunk of the file and forward all complete lines in it
+    /// to `consumer`.
+    ///
+    /// Returns `false` on end of file.
+    fn read(&mut self, consumer: &mut dyn InputConsumer) -> AnyResult<bool> {
+        let data = self.reader.fill_buf()?;
+        if data.is_empty() {
+            return Ok(false);
+        }
+
+        let len = data.len();
+        let split = split_on_newline(data);
+
+        if split > 0 {
+            if self.partial.is_empty() {
+                consumer.input(&data[0..split]);
+            } else {
+                self.partial.extend_from_slice(&data[0..split]);
+                consumer.input(&self.partial);
+                self.partial.clear();
+            }
+        }
+        self.partial.extend_from_slice(&data[split..]);
+        self.reader.consume(len);
+
+        Ok(true)
+    }
+
+    /// Forward the last line of the file if it isn't terminated by a
+    /// newline.
+    fn finish(&mut self, consumer: &mut dyn InputConsumer) {
+        if !self.partial.is_empty() {
+            self.partial.push(b'\n');
+            consumer.input(&self.partial);
+            self.partial.clear();
+        }
+    }
+}
+
+/// All files that match the input pattern.
+struct InputFiles {
+    pattern: String,
+    buffer_size: Option<usize>,
+    mode: FileInputMode,
+
+    /// Paths of all files in `files`.
+    paths: HashSet<PathBuf>,
+
+    files: Vec<InputFile>,
+
+    /// Index of the file currently being read.
+    current: usize,
+}
+
+impl InputFiles {
+    fn new(config: &FileInputConfig) -> AnyResult<Self> {
+        let mut files = Self {
+            pattern: config.path.clone(),
+            buffer_size: config.buffer_size_bytes,
+            mode: config.mode()?,
+            paths: HashSet::new(),
+            files: Vec::new(),
+            current: 0,
+        };
+        files.scan()?;
+
+        if files.files.is_empty() && files.mode == FileInputMode::Once {
+            return Err(AnyError::msg(format!(
+                "No input files match '{}'",
+                config.path
+            )));
+        }
+
+        Ok(files)
+    }
+
+    /// Open files that match the pattern and haven't been opened yet.
+    fn scan(&mut self) -> AnyResult<()> {
+        let mut new_paths = glob(&self.pattern)
+            .map_err(|e| AnyError::msg(format!("Invalid file pattern '{}': {e}", self.pattern)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        new_paths.retain(|path| path.is_file() && !self.paths.contains(path));
+        new_paths.sort();
+
+        for path in new_paths {
+            self.files.push(InputFile::open(&path, self.buffer_size)?);
+            self.paths.insert(path);
+        }
+
+        Ok(())
+    }
+
+    /// Forward the next chunk of input to `consumer`.
     ///
-    /// When `false`, the endpoint outputs an [`eoi`](`InputConsumer::eoi`)
-    /// message and stops upon reaching the end of file.  When `true`, the
-    /// endpoint will keep watching the file and outputting any new content
-    /// appended to it.
-    #[serde(default)]
-    follow: bool,
+    /// Returns `false` when all files have been read to the end.
+    fn read(&mut self, consumer: &mut dyn InputConsumer) -> AnyResult<bool> {
+        while self.current < self.files.len() {
+            if self.files[self.current].read(consumer)? {
+                return Ok(true);
+            }
+            if self.mode == FileInputMode::Once {
+                self.files[self.current].finish(consumer);
+            }
+            self.current += 1;
+        }
+
+        if self.mode == FileInputMode::Follow {
+            // Start over, checking all files for new data.
+            self.current = 0;
+            self.scan()?;
+        }
+
+        Ok(false)
+    }
 }
 
 struct FileInputEndpoint {
@@ -78,22 +266,12 @@ impl FileInputEndpoint {
     }
 
     fn connect(&mut self, consumer: Box<dyn InputConsumer>) -> AnyResult<()> {
-        let file = File::open(&self.config.path).map_err(|e| {
-            AnyError::msg(format!(
-                "Failed to open input file '{}': {e}",
-                self.config.path
-            ))
-        })?;
-        let reader = match self.config.buffer_size_bytes {
-            Some(buffer_size) if buffer_size > 0 => BufReader::with_capacity(buffer_size, file),
-            _ => BufReader::new(file),
-        };
+        let files = InputFiles::new(&self.config)?;
 
         let parker = Parker::new();
         self.unparker = Some(parker.unparker().clone());
         let status = self.status.clone();
-        let follow = self.config.follow;
-        let _worker = spawn(move || Self::worker_thread(reader, consumer, parker, status, follow));
+        let _worker = spawn(move || Self::worker_thread(files, consumer, parker, status));
         Ok(())
     }
 
@@ -104,38 +282,29 @@ impl FileInputEndpoint {
     }
 
     fn worker_thread(
-        mut reader: BufReader<File>,
+        mut files: InputFiles,
         mut consumer: Box<dyn InputConsumer>,
         parker: Parker,
         status: Arc<AtomicU32>,
-        follow: bool,
     ) {
         loop {
             match PipelineState::from_u32(status.load(Ordering::Acquire)) {
                 Some(PipelineState::Paused) => parker.park(),
-                Some(PipelineState::Running) => {
-                    let data = reader.fill_buf();
-                    match data {
-                        Err(e) => {
-                            consumer.error(true, AnyError::from(e));
+                Some(PipelineState::Running) => match files.read(consumer.as_mut()) {
+                    Err(e) => {
+                        consumer.error(true, e);
+                        return;
+                    }
+                    Ok(true) => {}
+                    Ok(false) => {
+                        if files.mode == FileInputMode::Once {
+                            consumer.eoi();
                             return;
-                        }
-                        Ok(data) if data.is_empty() => {
-                            if !follow {
-                                consumer.eoi();
-                                return;
-                            } else {
-                                sleep(Duration::from_millis(SLEEP_MS));
-                            }
-                        }
-                        Ok(data) => {
-                            // println!("read {} bytes from file", data.len());
-                            consumer.input(data);
-                            let len = data.len();
-                            reader.consume(len);
+                        } else {
+                            sleep(Duration::from_millis(SLEEP_MS));
                         }
                     }
-                }
+                },
                 Some(PipelineState::Terminated) => return,
                 _ => unreachable!(),
             }
@@ -228,11 +397,12 @@ impl OutputEndpoint for FileOutputEndpoint {
 
 #[cfg(test)]
 mod test {
+    use super::{FileInputConfig, FileInputMode};
     use crate::test::{mock_input_pipeline, wait};
     use csv::WriterBuilder as CsvWriterBuilder;
     use serde::{Deserialize, Serialize};
-    use std::{io::Write, thread::sleep, time::Duration};
-    use tempfile::NamedTempFile;
+    use std::{fs, io::Write, thread::sleep, time::Duration};
+    use tempfile::{tempdir, NamedTempFile};
 
     #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
     struct TestStruct {
@@ -299,6 +469,75 @@ format:
         }
     }
 
+    #[test]
+    fn test_csv_file_glob() {
+        let temp_dir = tempdir().unwrap();
+
+        // Files are read in lexicographic order.  The last line of `a.csv`
+        // is not terminated by a newline.
+        fs::write(temp_dir.path().join("b.csv"), "baz,true,30\n").unwrap();
+        fs::write(temp_dir.path().join("a.csv"), "foo,true,10\nbar,false,-10").unwrap();
+        fs::write(temp_dir.path().join("c.txt"), "not csv\n").unwrap();
+
+        let config_str = format!(
+            r#"
+stream: test_input
+transport:
+    name: file
+    config:
+        path: {:?}
+        buffer_size_bytes: 5
+format:
+    name: csv
+"#,
+            temp_dir.path().join("*.csv").to_str().unwrap()
+        );
+
+        let (endpoint, consumer, zset) =
+            mock_input_pipeline::<TestStruct>(serde_yaml::from_str(&config_str).unwrap());
+
+        endpoint.start().unwrap();
+        wait(|| consumer.state().eoi, None);
+
+        let expected = vec![
+            TestStruct::new("foo".to_string(), true, 10),
+            TestStruct::new("bar".to_string(), false, -10),
+            TestStruct::new("baz".to_string(), true, 30),
+        ];
+        let flushed = zset
+            .state()
+            .flushed
+            .iter()
+            .map(|(val, polarity)| {
+                assert!(polarity);
+                val.clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(flushed, expected);
+    }
+
+    #[test]
+    fn test_deprecated_follow() {
+        let config = |yaml: &str| serde_yaml::from_str::<FileInputConfig>(yaml).unwrap();
+
+        assert_eq!(config("path: foo").mode().unwrap(), FileInputMode::Once);
+        assert_eq!(
+            config("path: foo\nmode: follow").mode().unwrap(),
+            FileInputMode::Follow
+        );
+        assert_eq!(
+            config("path: foo\nfollow: true").mode().unwrap(),
+            FileInputMode::Follow
+        );
+        assert_eq!(
+            config("path: foo\nfollow: false").mode().unwrap(),
+            FileInputMode::Once
+        );
+        assert!(config("path: foo\nmode: once\nfollow: true")
+            .mode()
+            .is_err());
+    }
+
     #[test]
     fn test_csv_file_follow() {
         let test_data = vec![
@@ -317,7 +556,7 @@ transport:
     config:
         path: {:?}
         buffer_size_bytes: 5
-        follow: true
+        mode: follow
 format:
     name: csv
 "#,
@@ -369,7 +608,7 @@ format:
             || {
                 let state = consumer.state();
                 // println!("result: {:?}", state.parser_result);
-                state.parser_result.is_some() && state.parser_result.as_ref().unwrap().is_err()
+                state.parser_result.is_some() && !state.parser_result.as_ref().unwrap().1.is_empty()
             },
             None,
         );
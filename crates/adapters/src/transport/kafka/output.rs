@@ -5,6 +5,7 @@ use crossbeam::sync::{Parker, Unparker};
 use log::debug;
 use rdkafka::{
     config::{FromClientConfigAndContext, RDKafkaLogLevel},
+    error::KafkaError,
     producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer},
     ClientConfig, ClientContext,
 };
@@ -21,6 +22,9 @@ use utoipa::{
 
 const OUTPUT_POLLING_INTERVAL: Duration = Duration::from_millis(100);
 
+/// Timeout for initializing, committing, and aborting transactions.
+const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// `OutputTransport` implementation that writes to a Kafka topic.
 pub struct KafkaOutputTransport;
 
@@ -77,6 +81,40 @@ pub struct KafkaOutputConfig {
     /// Defaults to 1000.
     #[serde(default = "default_max_inflight_messages")]
     max_inflight_messages: u32,
+
+    /// Write the outputs of each circuit step to the topic in a Kafka
+    /// transaction.
+    ///
+    /// Consumers that read the topic with `isolation.level=read_committed`
+    /// (the `librdkafka` default) observe the outputs of a step all at once
+    /// or not at all.  In particular, they never observe the partial output of
+    /// a step that was interrupted by a crash: when the pipeline restarts with
+    /// the same `transactional_id`, Kafka aborts the incomplete transaction.
+    /// The transaction is also aborted if sending or encoding any part of the
+    /// step's output fails.
+    ///
+    /// This does **not** provide exactly-once delivery.  Only the output of
+    /// the endpoint is transactional: offsets of input topics are committed
+    /// by input endpoints independently of output transactions.  A pipeline
+    /// that restarts after a crash may process some inputs again and write
+    /// their outputs in a new transaction, so delivery is at-least-once.
+    ///
+    /// Committing a transaction waits for the broker to acknowledge all
+    /// messages in the transaction and adds a round trip to the broker, during
+    /// which the endpoint doesn't send any data.  This lowers throughput when
+    /// steps are small; use the `min_batch_size_records` and
+    /// `max_buffering_delay_usecs` pipeline settings to make steps larger.
+    ///
+    /// Requires `transactional_id`.  Defaults to `false`.
+    #[serde(default)]
+    transactional: bool,
+
+    /// Transactional id of the producer, which must be unique among
+    /// producers writing to the Kafka cluster and stable across pipeline
+    /// restarts.
+    ///
+    /// Only used when `transactional` is `true`.
+    transactional_id: Option<String>,
 }
 
 impl KafkaOutputConfig {
@@ -94,6 +132,19 @@ impl KafkaOutputConfig {
             "bootstrap.servers",
             &env::var("REDPANDA_BROKERS").unwrap_or_else(|_| "localhost".to_string()),
         );
+
+        if self.transactional {
+            let transactional_id = self.transactional_id.clone().ok_or_else(|| {
+                AnyError::msg("'transactional' requires 'transactional_id' to be set")
+            })?;
+            self.kafka_options
+                .insert("transactional.id".to_string(), transactional_id);
+        } else if self.transactional_id.is_some() {
+            return Err(AnyError::msg(
+                "'transactional_id' is only valid with 'transactional: true'",
+            ));
+        }
+
         Ok(())
     }
 }
@@ -129,6 +180,33 @@ unacknowledged messages reaches this limit, sending of a new message
 blocks until additional acknowledgements arrive from the broker.
 
 Defaults to 1000."#)),
+                )
+                .property(
+                    "transactional",
+                    ObjectBuilder::new()
+                        .schema_type(SchemaType::Boolean)
+                        .description(Some(r#"Write the outputs of each circuit step to the topic in a Kafka transaction.
+
+Consumers that read the topic with `isolation.level=read_committed`
+observe the outputs of a step all at once or not at all.  Committing a
+transaction adds a round trip to the broker per step, which lowers
+throughput when steps are small.
+
+Input offsets are not committed as part of the transaction, so after a
+restart the pipeline may output some records again: delivery is
+at-least-once, not exactly-once.
+
+Requires `transactional_id`.  Defaults to `false`."#)),
+                )
+                .property(
+                    "transactional_id",
+                    ObjectBuilder::new()
+                        .schema_type(SchemaType::String)
+                        .description(Some(r#"Transactional id of the producer, which must be unique among
+producers writing to the Kafka cluster and stable across pipeline
+restarts.
+
+Only used when `transactional` is `true`."#)),
                 )
                 .additional_properties(Some(
                         ObjectBuilder::new()
@@ -189,6 +267,12 @@ struct KafkaOutputEndpoint {
     topic: String,
     max_inflight_messages: u32,
     parker: Parker,
+
+    /// Output each step in a transaction.
+    transactional: bool,
+
+    /// Sending part of the current step's output failed.
+    batch_failed: bool,
 }
 
 impl KafkaOutputEndpoint {
@@ -218,16 +302,59 @@ impl KafkaOutputEndpoint {
         // Create Kafka producer.
         let kafka_producer = ThreadedProducer::from_config_and_context(&client_config, context)?;
 
+        if config.transactional {
+            // Fences off earlier instances of the producer with the same
+            // transactional id and aborts any transaction they left
+            // incomplete, e.g., because the pipeline crashed mid-step.
+            kafka_producer.init_transactions(TRANSACTION_TIMEOUT)?;
+        }
+
         Ok(Self {
             kafka_producer,
             topic: config.topic,
             max_inflight_messages: config.max_inflight_messages,
             parker,
+            transactional: config.transactional,
+            batch_failed: false,
         })
     }
+
+    fn commit_transaction(&self) -> AnyResult<()> {
+        loop {
+            match self.kafka_producer.commit_transaction(TRANSACTION_TIMEOUT) {
+                Ok(()) => return Ok(()),
+                Err(KafkaError::Transaction(e)) if e.is_retriable() => {
+                    debug!("Retrying Kafka transaction commit: {e}");
+                }
+                Err(KafkaError::Transaction(e)) if e.txn_requires_abort() => {
+                    // The outputs of the step are lost, but aborting the
+                    // transaction allows the endpoint to output subsequent
+                    // steps.
+                    self.abort_transaction()?;
+                    return Err(AnyError::msg(format!(
+                        "Kafka transaction aborted; outputs of the step were discarded: {e}"
+                    )));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn abort_transaction(&self) -> AnyResult<()> {
+        self.kafka_producer.abort_transaction(TRANSACTION_TIMEOUT)?;
+        Ok(())
+    }
 }
 
 impl OutputEndpoint for KafkaOutputEndpoint {
+    fn batch_start(&mut self) -> AnyResult<()> {
+        self.batch_failed = false;
+        if self.transactional {
+            self.kafka_producer.begin_transaction()?;
+        }
+        Ok(())
+    }
+
     fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
         // Wait for the number of unacknowledged messages to drop
         // below `max_inflight_messages`.
@@ -242,9 +369,31 @@ impl OutputEndpoint for KafkaOutputEndpoint {
         }
 
         let record = <BaseRecord<(), [u8], ()>>::to(&self.topic).payload(buffer);
-        self.kafka_producer
-            .send(record)
-            .map_err(|(err, _record)| err)?;
+        if let Err((err, _record)) = self.kafka_producer.send(record) {
+            self.batch_failed = true;
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    fn batch_end(&mut self) -> AnyResult<()> {
+        if self.transactional {
+            if self.batch_failed {
+                // Don't commit a step with missing outputs.
+                self.abort_transaction()?;
+                return Err(AnyError::msg(
+                    "Kafka transaction aborted because sending part of the step's output failed",
+                ));
+            }
+            self.commit_transaction()?;
+        }
+        Ok(())
+    }
+
+    fn batch_abort(&mut self) -> AnyResult<()> {
+        if self.transactional {
+            self.abort_transaction()?;
+        }
         Ok(())
     }
 }
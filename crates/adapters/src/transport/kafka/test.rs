@@ -4,7 +4,7 @@ use crate::{
         kafka::{BufferConsumer, KafkaResources, TestProducer},
         mock_input_pipeline, test_circuit, wait, MockDeZSet, TestStruct, TEST_LOGGER,
     },
-    Controller, PipelineConfig,
+    Controller, OutputTransport, PipelineConfig,
 };
 use log::LevelFilter;
 use proptest::prelude::*;
@@ -212,3 +212,193 @@ outputs:
         drop(kafka_resources);
     }
 }
+
+/// The input backlog grows after a burst of input and drops back to zero
+/// once the circuit catches up; the step counter increases monotonically.
+#[test]
+fn kafka_step_rate_and_backlog() {
+    let _ = log::set_logger(&TEST_LOGGER);
+    log::set_max_level(LevelFilter::Debug);
+
+    let kafka_resources = KafkaResources::create_topics(&[
+        ("step_rate_test_input_topic", 1),
+        ("step_rate_test_output_topic", 1),
+    ]);
+
+    // Large `min_batch_size_records` and `max_buffering_delay_usecs` make the
+    // circuit hold on to the burst for two seconds before processing it, so
+    // the backlog is observable.
+    let config_str = r#"
+min_batch_size_records: 1000000
+max_buffering_delay_usecs: 2000000
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: kafka
+            config:
+                bootstrap.servers: "localhost"
+                auto.offset.reset: "earliest"
+                topics: [step_rate_test_input_topic]
+                log_level: debug
+        format:
+            name: csv
+outputs:
+    test_output2:
+        stream: test_output1
+        transport:
+            name: kafka
+            config:
+                bootstrap.servers: "localhost"
+                topic: step_rate_test_output_topic
+                max_inflight_messages: 0
+        format:
+            name: csv
+"#;
+
+    let (circuit, catalog) = test_circuit(4);
+    let config: PipelineConfig = serde_yaml::from_str(config_str).unwrap();
+    let controller = Controller::with_config(
+        circuit,
+        catalog,
+        &config,
+        Box::new(|e| panic!("error: {e}")),
+    )
+    .unwrap();
+
+    let buffer_consumer = BufferConsumer::new("step_rate_test_output_topic");
+    controller.start();
+
+    let data = vec![(0..1000)
+        .map(|id| TestStruct {
+            id,
+            b: id % 2 == 0,
+            i: Some(id as i64),
+            s: format!("record {id}"),
+        })
+        .collect::<Vec<_>>()];
+    TestProducer::new().send_to_topic(&data, "step_rate_test_input_topic");
+
+    // Track the backlog and the step counter until the circuit catches up.
+    let mut max_backlog = 0;
+    let mut steps = controller.status().num_total_steps();
+    wait(
+        || {
+            let status = controller.status();
+            let new_steps = status.num_total_steps();
+            assert!(new_steps >= steps);
+            steps = new_steps;
+
+            max_backlog = max_backlog.max(status.input_backlog());
+            status.num_total_processed_records() == 1000 && buffer_consumer.len() == 1000
+        },
+        None,
+    );
+
+    assert!(max_backlog > 0);
+    assert_eq!(controller.status().input_backlog(), 0);
+    assert!(controller.status().num_total_steps() > 0);
+    assert!(controller.status().steps_per_second() > 0.0);
+
+    buffer_consumer.wait_for_output_unordered(&data);
+    drop(buffer_consumer);
+
+    controller.stop().unwrap();
+    println!("Delete Kafka resources");
+    drop(kafka_resources);
+}
+
+#[test]
+fn kafka_transactional_config() {
+    let transport = <dyn OutputTransport>::get_transport("kafka").unwrap();
+
+    for config_str in [
+        // `transactional` without `transactional_id`.
+        r#"
+topic: transactional_config_test_topic
+transactional: true
+"#,
+        // `transactional_id` without `transactional`.
+        r#"
+topic: transactional_config_test_topic
+transactional_id: transactional_config_test
+"#,
+    ] {
+        let config = serde_yaml::from_str(config_str).unwrap();
+        assert!(transport
+            .new_endpoint("test", &config, Box::new(|_, e| panic!("error: {e}")))
+            .is_err());
+    }
+}
+
+/// Outputs of a pipeline with a transactional Kafka output endpoint are
+/// delivered to a `read_committed` consumer.
+#[test]
+fn kafka_transactional_end_to_end() {
+    let _ = log::set_logger(&TEST_LOGGER);
+    log::set_max_level(LevelFilter::Debug);
+
+    let kafka_resources = KafkaResources::create_topics(&[
+        ("transactional_test_input_topic", 1),
+        ("transactional_test_output_topic", 1),
+    ]);
+
+    let config_str = r#"
+inputs:
+    test_input1:
+        stream: test_input1
+        transport:
+            name: kafka
+            config:
+                bootstrap.servers: "localhost"
+                auto.offset.reset: "earliest"
+                topics: [transactional_test_input_topic]
+                log_level: debug
+        format:
+            name: csv
+outputs:
+    test_output2:
+        stream: test_output1
+        transport:
+            name: kafka
+            config:
+                bootstrap.servers: "localhost"
+                topic: transactional_test_output_topic
+                transactional: true
+                transactional_id: transactional_test
+        format:
+            name: csv
+"#;
+
+    let (circuit, catalog) = test_circuit(4);
+    let config: PipelineConfig = serde_yaml::from_str(config_str).unwrap();
+    let controller = Controller::with_config(
+        circuit,
+        catalog,
+        &config,
+        Box::new(|e| panic!("error: {e}")),
+    )
+    .unwrap();
+
+    // `BufferConsumer` uses the default `isolation.level=read_committed`, so
+    // it only observes committed transactions.
+    let buffer_consumer = BufferConsumer::new("transactional_test_output_topic");
+    controller.start();
+
+    let data = vec![(0..1000)
+        .map(|id| TestStruct {
+            id,
+            b: id % 2 == 0,
+            i: Some(id as i64),
+            s: format!("record {id}"),
+        })
+        .collect::<Vec<_>>()];
+    TestProducer::new().send_to_topic(&data, "transactional_test_input_topic");
+
+    buffer_consumer.wait_for_output_unordered(&data);
+    drop(buffer_consumer);
+
+    controller.stop().unwrap();
+    println!("Delete Kafka resources");
+    drop(kafka_resources);
+}
@@ -12,7 +12,12 @@ mod http;
 #[cfg(feature = "with-kafka")]
 mod kafka;
 
-pub use file::{FileInputConfig, FileInputTransport, FileOutputConfig, FileOutputTransport};
+#[cfg(feature = "with-parquet")]
+mod parquet;
+
+pub use file::{
+    FileInputConfig, FileInputMode, FileInputTransport, FileOutputConfig, FileOutputTransport,
+};
 
 #[cfg(feature = "server")]
 pub use http::{HttpInputTransport, HttpOutputTransport};
@@ -22,6 +27,9 @@ pub use kafka::{
     KafkaInputConfig, KafkaInputTransport, KafkaLogLevel, KafkaOutputConfig, KafkaOutputTransport,
 };
 
+#[cfg(feature = "with-parquet")]
+pub use parquet::{ParquetOutputConfig, ParquetOutputTransport};
+
 /// Static map of supported input transports.
 // TODO: support for registering new transports at runtime in order to allow
 // external crates to implement new transports.
@@ -61,6 +69,11 @@ static OUTPUT_TRANSPORT: Lazy<BTreeMap<&'static str, Box<dyn OutputTransport>>>
             "kafka",
             Box::new(KafkaOutputTransport) as Box<dyn OutputTransport>,
         ),
+        #[cfg(feature = "with-parquet")]
+        (
+            "parquet",
+            Box::new(ParquetOutputTransport) as Box<dyn OutputTransport>,
+        ),
     ])
 });
 
@@ -207,5 +220,31 @@ impl dyn OutputTransport {
 }
 
 pub trait OutputEndpoint: Send {
+    /// Notifies the endpoint that the buffers that follow, up to the next
+    /// call to [`batch_end`](`Self::batch_end`), contain the outputs of a
+    /// single circuit step.
+    ///
+    /// Transactional endpoints use step boundaries to output each step
+    /// atomically.  The default implementation does nothing.
+    fn batch_start(&mut self) -> AnyResult<()> {
+        Ok(())
+    }
+
     fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()>;
+
+    /// Notifies the endpoint that all outputs of the current step have been
+    /// pushed.  The default implementation does nothing.
+    fn batch_end(&mut self) -> AnyResult<()> {
+        Ok(())
+    }
+
+    /// Called instead of [`batch_end`](`Self::batch_end`) when the outputs of
+    /// the current step could not be encoded, so the buffers pushed since
+    /// [`batch_start`](`Self::batch_start`) are incomplete.
+    ///
+    /// Transactional endpoints discard the step's outputs.  The default
+    /// implementation does nothing.
+    fn batch_abort(&mut self) -> AnyResult<()> {
+        Ok(())
+    }
 }
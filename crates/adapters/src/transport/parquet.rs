@@ -0,0 +1,435 @@
+use super::{OutputEndpoint, OutputTransport};
+use anyhow::{Error as AnyError, Result as AnyResult};
+use arrow::{
+    datatypes::{Schema, SchemaRef},
+    json::{reader::infer_json_schema_from_iterator, ReaderBuilder},
+};
+use log::error;
+use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+use std::{
+    borrow::Cow,
+    fs::{create_dir_all, File},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use utoipa::ToSchema;
+
+/// `OutputTransport` implementation that writes data to Parquet files.
+///
+/// The endpoint must be used with the `json` output format (without
+/// pretty-printing), which encodes each update as a `[record, weight]` array
+/// on a separate line.  Records must serialize as JSON objects.  Each update
+/// is written to the Parquet file as a row that contains the fields of the
+/// record plus the weight of the update, so that deletions are representable.
+///
+/// Column types are inferred from the records.  When a buffer contains
+/// fields or types that are not in the schema of the current file, e.g., a
+/// column that only contained nulls so far, the endpoint closes the current
+/// file and writes subsequent data to a new file with the extended schema.
+/// When the type of a column changes instead, e.g., from integer to string,
+/// the schemas cannot be merged, and the new file gets the schema inferred
+/// from the new buffer alone.
+///
+/// # Backpressure
+///
+/// Data is written synchronously in [`OutputEndpoint::push_buffer`].  When
+/// writing falls behind, the output queue of the endpoint fills up and the
+/// controller stops the circuit once the queue reaches its high water mark.
+pub struct ParquetOutputTransport;
+
+impl OutputTransport for ParquetOutputTransport {
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("parquet")
+    }
+
+    fn new_endpoint(
+        &self,
+        _name: &str,
+        config: &YamlValue,
+        _async_error_callback: Box<dyn Fn(bool, AnyError) + Send + Sync>,
+    ) -> AnyResult<Box<dyn OutputEndpoint>> {
+        let config = ParquetOutputConfig::deserialize(config)?;
+        let ep = ParquetOutputEndpoint::new(config)?;
+
+        Ok(Box::new(ep))
+    }
+}
+
+const fn default_row_group_size_records() -> usize {
+    10_000
+}
+
+fn default_file_prefix() -> String {
+    "output".to_string()
+}
+
+fn default_weight_column() -> String {
+    "weight".to_string()
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ParquetOutputConfig {
+    /// Directory to write Parquet files to.  The directory is created if it
+    /// doesn't exist.
+    path: String,
+
+    /// Output files are named `<file_prefix>-<n>.parquet`, where `n` is
+    /// the sequence number of the file.  Numbering starts after the last
+    /// existing file in the directory.
+    #[serde(default = "default_file_prefix")]
+    file_prefix: String,
+
+    /// Maximal number of rows in a row group.
+    #[serde(default = "default_row_group_size_records")]
+    row_group_size_records: usize,
+
+    /// Start a new file once the current file reaches this size.
+    ///
+    /// Data is written to disk one row group at a time, so files can exceed
+    /// this size by up to a row group.
+    max_file_size_bytes: Option<u64>,
+
+    /// Start a new file once the current file has been open for this many
+    /// seconds.  Checked whenever data is written to the file.
+    max_file_duration_secs: Option<u64>,
+
+    /// Name of the column that stores the weight of each update.
+    #[serde(default = "default_weight_column")]
+    weight_column: String,
+}
+
+/// Parquet file currently being written.
+struct ParquetFile {
+    writer: ArrowWriter<File>,
+
+    /// Handle to the same file used to query its size.
+    file: File,
+
+    created: Instant,
+}
+
+struct ParquetOutputEndpoint {
+    config: ParquetOutputConfig,
+
+    /// Schema of the current file.
+    schema: Option<SchemaRef>,
+
+    current_file: Option<ParquetFile>,
+
+    /// Sequence number of the next file.
+    next_file: usize,
+}
+
+impl ParquetOutputEndpoint {
+    fn new(config: ParquetOutputConfig) -> AnyResult<Self> {
+        create_dir_all(&config.path).map_err(|e| {
+            AnyError::msg(format!(
+                "Failed to create output directory '{}': {e}",
+                config.path
+            ))
+        })?;
+
+        let mut ep = Self {
+            config,
+            schema: None,
+            current_file: None,
+            next_file: 0,
+        };
+
+        // Don't overwrite files written by earlier runs.
+        while ep.file_path(ep.next_file).exists() {
+            ep.next_file += 1;
+        }
+
+        Ok(ep)
+    }
+
+    fn file_path(&self, n: usize) -> PathBuf {
+        PathBuf::from(&self.config.path).join(format!("{}-{n}.parquet", self.config.file_prefix))
+    }
+
+    /// Split `buffer` into updates and convert each update into a JSON
+    /// object that contains the fields of the record and the weight.
+    fn parse_rows(&self, buffer: &[u8]) -> AnyResult<Vec<JsonValue>> {
+        let mut rows = Vec::new();
+
+        for update in serde_json::Deserializer::from_slice(buffer).into_iter::<(JsonValue, i64)>() {
+            let (record, weight) = update.map_err(|e| {
+                AnyError::msg(format!(
+                    "expected an update encoded as a '[record, weight]' JSON array: {e}"
+                ))
+            })?;
+
+            let mut fields = match record {
+                JsonValue::Object(fields) => fields,
+                _ => {
+                    return Err(AnyError::msg(format!(
+                        "expected a record encoded as a JSON object, found '{record}'"
+                    )))
+                }
+            };
+
+            if fields
+                .insert(self.config.weight_column.clone(), weight.into())
+                .is_some()
+            {
+                return Err(AnyError::msg(format!(
+                    "record contains a field named '{}', which clashes with the weight column",
+                    self.config.weight_column
+                )));
+            }
+
+            rows.push(JsonValue::Object(fields));
+        }
+
+        Ok(rows)
+    }
+
+    /// Returns the file to write data with `schema` to, opening a new file
+    /// if necessary.
+    fn file(&mut self, schema: &SchemaRef) -> AnyResult<&mut ParquetFile> {
+        if self.current_file.is_none() {
+            let path = self.file_path(self.next_file);
+            let file = File::create(&path).map_err(|e| {
+                AnyError::msg(format!(
+                    "Failed to create output file '{}': {e}",
+                    path.display()
+                ))
+            })?;
+            let props = WriterProperties::builder()
+                .set_max_row_group_size(self.config.row_group_size_records)
+                .build();
+
+            self.current_file = Some(ParquetFile {
+                writer: ArrowWriter::try_new(file.try_clone()?, schema.clone(), Some(props))?,
+                file,
+                created: Instant::now(),
+            });
+            self.next_file += 1;
+        }
+
+        Ok(self.current_file.as_mut().unwrap())
+    }
+
+    /// Flush buffered rows and close the current file.
+    fn close_file(&mut self) -> AnyResult<()> {
+        if let Some(file) = self.current_file.take() {
+            file.writer.close()?;
+        }
+        Ok(())
+    }
+
+    /// Checks whether the current file has reached its maximal size or age.
+    fn should_rotate(&self) -> AnyResult<bool> {
+        let file = match &self.current_file {
+            Some(file) => file,
+            None => return Ok(false),
+        };
+
+        if let Some(max_size) = self.config.max_file_size_bytes {
+            if file.file.metadata()?.len() >= max_size {
+                return Ok(true);
+            }
+        }
+
+        if let Some(max_duration) = self.config.max_file_duration_secs {
+            if file.created.elapsed() >= Duration::from_secs(max_duration) {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+impl OutputEndpoint for ParquetOutputEndpoint {
+    fn push_buffer(&mut self, buffer: &[u8]) -> AnyResult<()> {
+        let rows = self.parse_rows(buffer)?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let schema = infer_json_schema_from_iterator(rows.iter().map(Ok))?;
+        let schema = match &self.schema {
+            None => Arc::new(schema),
+            Some(current) => match Schema::try_merge([current.as_ref().clone(), schema.clone()]) {
+                Ok(merged) if &merged == current.as_ref() => current.clone(),
+                Ok(merged) => {
+                    // The current file cannot store the new columns.
+                    self.close_file()?;
+                    Arc::new(merged)
+                }
+                Err(_) => {
+                    // A column changed its type.  Start over with the schema
+                    // of the new data; merging with it would fail for all
+                    // subsequent buffers.
+                    self.close_file()?;
+                    Arc::new(schema)
+                }
+            },
+        };
+        self.schema = Some(schema.clone());
+
+        let mut decoder = ReaderBuilder::new(schema.clone())
+            .with_batch_size(rows.len())
+            .build_decoder()?;
+        decoder.serialize(&rows)?;
+        let batch = decoder.flush()?.unwrap();
+
+        self.file(&schema)?.writer.write(&batch)?;
+
+        if self.should_rotate()? {
+            self.close_file()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ParquetOutputEndpoint {
+    fn drop(&mut self) {
+        // Write the footer of the current file; otherwise the file is not
+        // readable.
+        if let Err(e) = self.close_file() {
+            error!("Failed to close Parquet file: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::OutputTransport;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::{fs::File, path::Path};
+    use tempfile::tempdir;
+
+    fn read_rows(path: &Path) -> usize {
+        ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum()
+    }
+
+    #[test]
+    fn test_parquet_output() {
+        let temp_dir = tempdir().unwrap();
+        let config =
+            serde_yaml::from_str(&format!("path: {:?}", temp_dir.path().to_str().unwrap()))
+                .unwrap();
+
+        let mut endpoint = <dyn OutputTransport>::get_transport("parquet")
+            .unwrap()
+            .new_endpoint("test", &config, Box::new(|_, _| {}))
+            .unwrap();
+
+        endpoint
+            .push_buffer(b"[{\"id\": 1, \"s\": \"foo\"}, 1]\n[{\"id\": 2, \"s\": \"bar\"}, -1]\n")
+            .unwrap();
+        endpoint
+            .push_buffer(b"[{\"id\": 3, \"s\": \"baz\"}, 2]\n")
+            .unwrap();
+
+        // Records that aren't objects are rejected.
+        assert!(endpoint.push_buffer(b"[[1, 2], 1]\n").is_err());
+
+        // A new column doesn't fit the schema of the current file and goes
+        // to a new file.
+        endpoint
+            .push_buffer(b"[{\"id\": 4, \"s\": \"qux\", \"b\": true}, 1]\n")
+            .unwrap();
+
+        // Dropping the endpoint closes the file.
+        drop(endpoint);
+
+        let path = temp_dir.path().join("output-0.parquet");
+        let batches = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let schema = batches[0].schema();
+        let fields = schema
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, vec!["id", "s", "weight"]);
+        assert_eq!(read_rows(&path), 3);
+        assert_eq!(read_rows(&temp_dir.path().join("output-1.parquet")), 1);
+        assert!(!temp_dir.path().join("output-2.parquet").exists());
+    }
+
+    #[test]
+    fn test_parquet_type_change() {
+        let temp_dir = tempdir().unwrap();
+        let config =
+            serde_yaml::from_str(&format!("path: {:?}", temp_dir.path().to_str().unwrap()))
+                .unwrap();
+
+        let mut endpoint = <dyn OutputTransport>::get_transport("parquet")
+            .unwrap()
+            .new_endpoint("test", &config, Box::new(|_, _| {}))
+            .unwrap();
+
+        endpoint
+            .push_buffer(b"[{\"id\": 1, \"x\": 1}, 1]\n")
+            .unwrap();
+
+        // `x` changes from integer to string: the data goes to a new file,
+        // which also receives subsequent data of the same type.
+        endpoint
+            .push_buffer(b"[{\"id\": 2, \"x\": \"foo\"}, 1]\n")
+            .unwrap();
+        endpoint
+            .push_buffer(b"[{\"id\": 3, \"x\": \"bar\"}, 1]\n")
+            .unwrap();
+        drop(endpoint);
+
+        assert_eq!(read_rows(&temp_dir.path().join("output-0.parquet")), 1);
+        assert_eq!(read_rows(&temp_dir.path().join("output-1.parquet")), 2);
+        assert!(!temp_dir.path().join("output-2.parquet").exists());
+    }
+
+    #[test]
+    fn test_parquet_rotation() {
+        let temp_dir = tempdir().unwrap();
+        let config = serde_yaml::from_str(&format!(
+            r#"
+path: {:?}
+file_prefix: test
+row_group_size_records: 1
+max_file_size_bytes: 1
+"#,
+            temp_dir.path().to_str().unwrap()
+        ))
+        .unwrap();
+
+        let mut endpoint = <dyn OutputTransport>::get_transport("parquet")
+            .unwrap()
+            .new_endpoint("test", &config, Box::new(|_, _| {}))
+            .unwrap();
+
+        for i in 0..3 {
+            endpoint
+                .push_buffer(format!("[{{\"id\": {i}}}, 1]\n").as_bytes())
+                .unwrap();
+        }
+        drop(endpoint);
+
+        // Each row group exceeds the maximal file size.
+        for n in 0..3 {
+            assert_eq!(
+                read_rows(&temp_dir.path().join(format!("test-{n}.parquet"))),
+                1
+            );
+        }
+        assert!(!temp_dir.path().join("test-3.parquet").exists());
+    }
+}
@@ -7,7 +7,7 @@ use crate::{
 use dbsp::{
     circuit::{
         operator_traits::{Operator, UnaryOperator},
-        Scope,
+        CheckpointError, Scope,
     },
     trace::{Batch, BatchReader, Batcher, Cursor},
 };
@@ -37,6 +37,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 // Set -> Set
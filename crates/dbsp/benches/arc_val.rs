@@ -0,0 +1,80 @@
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, Criterion,
+};
+use dbsp::{
+    operator::{FilterMap, Generator},
+    trace::Batch,
+    utils::ArcVal,
+    DBData, OrdZSet, RootCircuit, Stream,
+};
+
+/// Number of records in each input batch.
+const BATCH_SIZE: usize = 10_000;
+
+/// Number of people mentioned in each record.
+const PEOPLE_PER_RECORD: usize = 50;
+
+/// Number of filters in the chain.
+const CHAIN_LENGTH: usize = 5;
+
+type PeopleBatch<V> = OrdZSet<(u64, V), isize>;
+
+/// Builds a batch of `(id, people)` records shaped like the GDELT personal
+/// network entries, wrapping each list of people with `wrap`.
+fn batch<V>(wrap: fn(Vec<String>) -> V) -> PeopleBatch<V>
+where
+    V: DBData,
+{
+    let tuples = (0..BATCH_SIZE)
+        .map(|i| {
+            let people = (0..PEOPLE_PER_RECORD)
+                .map(|j| format!("person {:016}", i * PEOPLE_PER_RECORD + j))
+                .collect();
+            ((i as u64, wrap(people)), 1)
+        })
+        .collect();
+
+    PeopleBatch::from_keys((), tuples)
+}
+
+/// Runs a chain of filters that keep every record, so each filter clones
+/// every record of its input.  The source clones its batch on every step,
+/// like any other operator that copies records.
+fn bench_filter_chain<V>(
+    group: &mut BenchmarkGroup<'_, WallTime>,
+    name: &str,
+    wrap: fn(Vec<String>) -> V,
+) where
+    V: DBData + AsRef<Vec<String>>,
+{
+    let input = batch(wrap);
+
+    let (circuit, ()) = RootCircuit::build(move |circuit| {
+        let mut stream: Stream<_, PeopleBatch<V>> =
+            circuit.add_source(Generator::new(move || input.clone()));
+
+        for _ in 0..CHAIN_LENGTH {
+            stream = stream.filter(|(_, people): &(u64, V)| !people.as_ref().is_empty());
+        }
+    })
+    .unwrap();
+
+    group.bench_function(name, |b| {
+        b.iter(|| circuit.step().unwrap());
+    });
+}
+
+/// Compare copying records whose large values are stored inline with copying
+/// records that store them as `ArcVal`s.
+fn arc_val_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("arc-val");
+    group.sample_size(10);
+
+    bench_filter_chain(&mut group, "vec", |people| people);
+    bench_filter_chain(&mut group, "arc-val", ArcVal::new);
+
+    group.finish();
+}
+
+criterion_group!(benches, arc_val_benches);
+criterion_main!(benches);
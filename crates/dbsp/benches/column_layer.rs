@@ -5,12 +5,14 @@ use dbsp::{
         consolidation::consolidate,
         layers::{
             column_layer::{ColumnLayer, ColumnLayerBuilder},
-            Builder, MergeBuilder, Trie, TupleBuilder,
+            ordered_leaf::{OrderedLeaf, OrderedLeafBuilder},
+            Builder, Cursor, MergeBuilder, Trie, TupleBuilder,
         },
     },
 };
 use rand::{distributions::Standard, prelude::Distribution, Rng, SeedableRng};
 use rand_xoshiro::Xoshiro256StarStar;
+use std::hint::black_box;
 
 const SEED: [u8; 32] = [
     0x7f, 0xc3, 0x59, 0x18, 0x45, 0x19, 0xc0, 0xaa, 0xd2, 0xec, 0x31, 0x26, 0xbb, 0x74, 0x2f, 0x8b,
@@ -65,6 +67,65 @@ where
     (left_builder.done(), right_builder.done())
 }
 
+/// A wide key type, so that aggregating weights over an array-of-structs
+/// layout has to pull mostly keys through the cache.
+type WideKey = [u64; 16];
+
+/// Sums the weights of a batch of 1M rows with a wide key type, comparing the
+/// struct-of-arrays [`ColumnLayer`] with the array-of-structs [`OrderedLeaf`].
+fn aggregate(c: &mut Criterion) {
+    const LENGTH: usize = 1_000_000;
+
+    let mut rng = Xoshiro256StarStar::from_seed(SEED);
+    let mut data: Vec<(WideKey, isize)> = (0..LENGTH).map(|_| rng.gen()).collect();
+    consolidate(&mut data);
+
+    let mut column_builder =
+        <ColumnLayerBuilder<WideKey, isize> as TupleBuilder>::with_capacity(LENGTH);
+    column_builder.extend_tuples(data.iter().cloned());
+    let column = column_builder.done();
+
+    let mut leaf_builder =
+        <OrderedLeafBuilder<WideKey, isize> as TupleBuilder>::with_capacity(LENGTH);
+    leaf_builder.extend_tuples(data.into_iter());
+    let leaf: OrderedLeaf<WideKey, isize> = leaf_builder.done();
+
+    let mut group = c.benchmark_group("aggregate");
+    group.bench_function("column-layer-cursor", |b| {
+        b.iter(|| {
+            let mut cursor = column.cursor();
+            let mut sum = 0isize;
+            while cursor.valid() {
+                sum = sum.wrapping_add(*cursor.current_diff());
+                cursor.step();
+            }
+            black_box(sum)
+        });
+    });
+    group.bench_function("column-layer-slice", |b| {
+        b.iter(|| {
+            let sum = column
+                .cursor()
+                .remaining_diffs()
+                .iter()
+                .fold(0isize, |sum, diff| sum.wrapping_add(*diff));
+            black_box(sum)
+        });
+    });
+    group.bench_function("ordered-leaf-cursor", |b| {
+        b.iter(|| {
+            let mut cursor = leaf.cursor();
+            let mut sum = 0isize;
+            while cursor.valid() {
+                sum = sum.wrapping_add(cursor.item().1);
+                cursor.step();
+            }
+            black_box(sum)
+        });
+    });
+    group.finish();
+}
+
 macro_rules! leaf_benches {
     ($($name:literal = $size:literal),* $(,)?) => {
         fn merge_ordered_column_leaf_builder(c: &mut Criterion) {
@@ -192,5 +253,10 @@ leaf_benches! {
     "100,000,000" = 100_000_000,
 }
 
-criterion_group!(benches, column_leaf, merge_ordered_column_leaf_builder,);
+criterion_group!(
+    benches,
+    column_leaf,
+    merge_ordered_column_leaf_builder,
+    aggregate,
+);
 criterion_main!(benches);
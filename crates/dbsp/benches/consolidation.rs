@@ -191,6 +191,66 @@ macro_rules! consolidation_benches {
     };
 }
 
+/// Compare radix sort-based consolidation of primitive keys with the generic
+/// comparison sort-based implementation.
+fn radix_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("consolidate-primitive");
+    group.sample_size(10);
+
+    let mut rng = Xoshiro256StarStar::from_seed(SEED);
+    // Draw keys from a smaller domain than the number of tuples, so that
+    // some of them get consolidated.
+    let unsorted: Vec<(u64, isize)> = (0..1_000_000)
+        .map(|_| (rng.gen_range(0..500_000), rng.gen_range(-1..=1)))
+        .collect();
+
+    group.bench_function("radix", |b| {
+        b.iter_batched(
+            || unsorted.clone(),
+            |mut unsorted| consolidation::consolidate_primitive(black_box(&mut unsorted)),
+            BatchSize::PerIteration,
+        );
+    });
+
+    group.bench_function("comparison", |b| {
+        b.iter_batched(
+            || unsorted.clone(),
+            |mut unsorted| consolidation::consolidate(black_box(&mut unsorted)),
+            BatchSize::PerIteration,
+        );
+    });
+
+    group.finish();
+}
+
+/// Compare single-threaded consolidation of a large batch with
+/// multi-threaded consolidation.
+#[cfg(feature = "parallel-consolidation")]
+fn parallel_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("consolidate-parallel");
+    group.sample_size(10);
+
+    let unsorted = data::<((usize, usize), isize)>(10_000_000);
+
+    group.bench_function("sequential", |b| {
+        b.iter_batched(
+            || unsorted.clone(),
+            |mut unsorted| consolidation::consolidate(black_box(&mut unsorted)),
+            BatchSize::PerIteration,
+        );
+    });
+
+    group.bench_function("parallel", |b| {
+        b.iter_batched(
+            || unsorted.clone(),
+            |mut unsorted| consolidation::consolidate_parallel(black_box(&mut unsorted)),
+            BatchSize::PerIteration,
+        );
+    });
+
+    group.finish();
+}
+
 consolidation_benches! {
     "0" = 0,
     "10" = 10,
@@ -203,5 +263,13 @@ consolidation_benches! {
     "100,000,000" = 100_000_000,
 }
 
-criterion_group!(benches, consolidation_benches);
+#[cfg(not(feature = "parallel-consolidation"))]
+criterion_group!(benches, consolidation_benches, radix_benches);
+#[cfg(feature = "parallel-consolidation")]
+criterion_group!(
+    benches,
+    consolidation_benches,
+    radix_benches,
+    parallel_benches
+);
 criterion_main!(benches);
@@ -59,7 +59,7 @@
 use arcstr::{literal, ArcStr};
 use bincode::{Decode, Encode};
 use csv::{ReaderBuilder, Trim};
-use dbsp::CollectionHandle;
+use dbsp::{utils::ArcVal, CollectionHandle};
 use hashbrown::{HashMap, HashSet};
 use size_of::SizeOf;
 use std::{
@@ -88,7 +88,9 @@ type Normalizations = HashMap<&'static str, &'static [ArcStr], Xxh3Builder>;
 pub struct PersonalNetworkGkgEntry {
     pub id: ArcStr,
     pub date: u64,
-    pub people: Vec<ArcStr>,
+    // `people` vectors are large and get cloned by every operator that
+    // copies an entry; `ArcVal` makes these clones cheap.
+    pub people: ArcVal<Vec<ArcStr>>,
 }
 
 impl PartialEq for PersonalNetworkGkgEntry {
@@ -247,7 +249,11 @@ pub fn parse_personal_network_gkg(
                     people.sort();
                     people.dedup();
 
-                    let entry = PersonalNetworkGkgEntry { id, date, people };
+                    let entry = PersonalNetworkGkgEntry {
+                        id,
+                        date,
+                        people: ArcVal::new(people),
+                    };
                     handle.push(entry, 1);
                     records += 1;
                 }
@@ -8,15 +8,15 @@ use crate::data::{
 use arcstr::ArcStr;
 use clap::Parser;
 use dbsp::{
-    trace::{BatchReader, Cursor},
-    Runtime,
+    trace::{BatchReader, CompactionPolicy, Cursor},
+    Runtime, RuntimeConfig,
 };
 use std::{
     cmp::Reverse,
     io::{BufRead, BufReader, Write},
     num::NonZeroUsize,
     thread,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 #[derive(Debug, Clone, Parser)]
@@ -54,6 +54,16 @@ struct Args {
     #[clap(long)]
     update_master_list: bool,
 
+    /// The merge effort factor used by traces; higher values compact traces
+    /// more eagerly, reducing memory usage at the cost of CPU time
+    #[clap(long, default_value = "1")]
+    compaction_effort: NonZeroUsize,
+
+    /// Pin workers to cores and allocate their traces on the local NUMA node
+    /// (requires the `numa` feature, Linux only)
+    #[clap(long)]
+    numa_local: bool,
+
     // When running with `cargo bench` the binary gets the `--bench` flag, so we
     // have to parse and ignore it so clap doesn't get angry
     #[doc(hidden)]
@@ -78,56 +88,61 @@ fn main() {
         }
     }
 
-    let (mut handle, mut entries) = Runtime::init_circuit(threads, move |circuit| {
-        let (events, handle) = circuit.add_input_zset();
-
-        let mut network_buf = Vec::with_capacity(4096);
-        personal_network::personal_network(person, args.date_start, args.date_end, &events)
-            .gather(0)
-            .inspect(move |network| {
-                if !network.is_empty() {
-                    let mut cursor = network.cursor();
-                    while cursor.key_valid() {
-                        if cursor.val_valid() {
-                            let count = cursor.weight();
-                            let (source, target) = cursor.key().clone();
-                            network_buf.push((source, target, count));
+    let config = RuntimeConfig::default()
+        .with_compaction_policy(CompactionPolicy::with_effort(args.compaction_effort.get()))
+        .with_numa_local(args.numa_local);
+
+    let (mut handle, mut entries) =
+        Runtime::init_circuit_with_config(threads, config, move |circuit| {
+            let (events, handle) = circuit.add_input_zset();
+
+            let mut network_buf = Vec::with_capacity(4096);
+            personal_network::personal_network(person, args.date_start, args.date_end, &events)
+                .gather(0)
+                .inspect(move |network| {
+                    if !network.is_empty() {
+                        let mut cursor = network.cursor();
+                        while cursor.key_valid() {
+                            if cursor.val_valid() {
+                                let count = cursor.weight();
+                                let (source, target) = cursor.key().clone();
+                                network_buf.push((source, target, count));
+                            }
+                            cursor.step_key();
                         }
-                        cursor.step_key();
-                    }
 
-                    if !network_buf.is_empty() {
-                        let total_connections = network_buf.len();
-                        network_buf.sort_unstable_by(
-                            |(source1, target1, mentions1), (source2, target2, mentions2)| {
-                                Reverse(mentions1)
-                                    .cmp(&Reverse(mentions2))
-                                    .then_with(|| source1.cmp(source2))
-                                    .then_with(|| target1.cmp(target2))
-                            },
-                        );
-
-                        if let Some(topk) = args.topk.map(NonZeroUsize::get) {
-                            network_buf.truncate(topk);
+                        if !network_buf.is_empty() {
+                            let total_connections = network_buf.len();
+                            network_buf.sort_unstable_by(
+                                |(source1, target1, mentions1), (source2, target2, mentions2)| {
+                                    Reverse(mentions1)
+                                        .cmp(&Reverse(mentions2))
+                                        .then_with(|| source1.cmp(source2))
+                                        .then_with(|| target1.cmp(target2))
+                                },
+                            );
+
+                            if let Some(topk) = args.topk.map(NonZeroUsize::get) {
+                                network_buf.truncate(topk);
+                            }
+
+                            let mut stdout = std::io::stdout().lock();
+
+                            writeln!(stdout, "Network ({total_connections} total connections):")
+                                .unwrap();
+                            for (source, target, count) in network_buf.drain(..) {
+                                writeln!(stdout, "- {source}, {target}, {count}").unwrap();
+                            }
+                            writeln!(stdout).unwrap();
+
+                            stdout.flush().unwrap();
                         }
-
-                        let mut stdout = std::io::stdout().lock();
-
-                        writeln!(stdout, "Network ({total_connections} total connections):")
-                            .unwrap();
-                        for (source, target, count) in network_buf.drain(..) {
-                            writeln!(stdout, "- {source}, {target}, {count}").unwrap();
-                        }
-                        writeln!(stdout).unwrap();
-
-                        stdout.flush().unwrap();
                     }
-                }
-            });
+                });
 
-        handle
-    })
-    .unwrap();
+            handle
+        })
+        .unwrap();
 
     let mut file_urls = BufReader::new(get_master_file(args.update_master_list))
         .lines()
@@ -160,6 +175,7 @@ fn main() {
     let (mut interner, normalizations, invalid) = build_gdelt_normalizations();
 
     let (mut are_remaining_urls, mut current_batch) = (true, 0);
+    let mut total_elapsed = Duration::ZERO;
     while current_batch < args.batches.get() && are_remaining_urls {
         let (mut aggregate, mut records) = (0, 0);
         loop {
@@ -190,6 +206,7 @@ fn main() {
         handle.step().unwrap();
 
         let elapsed = start.elapsed();
+        total_elapsed += elapsed;
         if args.aggregate_batches.get() == 1 {
             println!(
                 "ingested batch {current_batch}/{batches} ({records} record{}) in {elapsed:#?}",
@@ -204,4 +221,10 @@ fn main() {
             );
         }
     }
+
+    println!(
+        "ingested {current_batch} batch{} in {total_elapsed:#?} (compaction effort {})",
+        if current_batch == 1 { "" } else { "es" },
+        args.compaction_effort,
+    );
 }
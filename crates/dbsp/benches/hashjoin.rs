@@ -0,0 +1,69 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use dbsp::{operator::Generator, trace::Batch, Circuit, OrdIndexedZSet, RootCircuit, Stream};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256StarStar;
+
+/// The seed for our prng-generated benchmarks
+const SEED: [u8; 32] = [
+    0x7f, 0xc3, 0x59, 0x18, 0x45, 0x19, 0xc0, 0xaa, 0xd2, 0xec, 0x31, 0x26, 0xbb, 0x74, 0x2f, 0x8b,
+    0x11, 0x7d, 0xc, 0xe4, 0x64, 0xbf, 0x72, 0x17, 0x46, 0x28, 0x46, 0x42, 0xb2, 0x4b, 0x72, 0x18,
+];
+
+/// Number of tuples added to each input at every step.
+const BATCH_SIZE: usize = 10_000;
+
+type StringBatch = OrdIndexedZSet<String, u64, isize>;
+
+/// Generates a batch of `BATCH_SIZE` tuples whose keys are long strings drawn
+/// from a set of `keys` distinct keys.  Keys share a common prefix, which
+/// makes comparing them relatively expensive.
+fn batch(rng: &mut Xoshiro256StarStar, keys: u64) -> StringBatch {
+    let tuples = (0..BATCH_SIZE)
+        .map(|_| {
+            let key = format!("https://example.com/users/{:016}", rng.gen_range(0..keys));
+            ((key, rng.gen()), 1)
+        })
+        .collect();
+
+    StringBatch::from_tuples((), tuples)
+}
+
+/// Compare hash join with the default sort-merge-based incremental join on
+/// high-cardinality string keys.  Each step adds a new batch to both inputs,
+/// so the traces of both inputs grow over the course of the benchmark.
+fn hashjoin_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("incremental-join");
+    group.sample_size(10);
+
+    for keys in [10_000, 100_000, 1_000_000] {
+        for hash in [false, true] {
+            let (circuit, ()) = RootCircuit::build(move |circuit| {
+                let mut left_rng = Xoshiro256StarStar::from_seed(SEED);
+                let mut right_rng = left_rng.clone();
+                right_rng.jump();
+
+                let left: Stream<_, StringBatch> =
+                    circuit.add_source(Generator::new(move || batch(&mut left_rng, keys)));
+                let right: Stream<_, StringBatch> =
+                    circuit.add_source(Generator::new(move || batch(&mut right_rng, keys)));
+
+                if hash {
+                    left.hashjoin(&right, |_k, v1, v2| (*v1, *v2));
+                } else {
+                    left.join(&right, |_k, v1, v2| (*v1, *v2));
+                }
+            })
+            .unwrap();
+
+            let name = if hash { "hash" } else { "default" };
+            group.bench_function(format!("{name}-{keys}"), |b| {
+                b.iter(|| circuit.step().unwrap());
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, hashjoin_benches);
+criterion_main!(benches);
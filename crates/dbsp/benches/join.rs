@@ -0,0 +1,64 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use dbsp::{operator::Generator, trace::Batch, Circuit, OrdIndexedZSet, RootCircuit, Stream};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256StarStar;
+
+/// The seed for our prng-generated benchmarks
+const SEED: [u8; 32] = [
+    0x7f, 0xc3, 0x59, 0x18, 0x45, 0x19, 0xc0, 0xaa, 0xd2, 0xec, 0x31, 0x26, 0xbb, 0x74, 0x2f, 0x8b,
+    0x11, 0x7d, 0xc, 0xe4, 0x64, 0xbf, 0x72, 0x17, 0x46, 0x28, 0x46, 0x42, 0xb2, 0x4b, 0x72, 0x18,
+];
+
+type Batch64 = OrdIndexedZSet<u64, u64, isize>;
+
+/// Generates a batch with `keys` keys drawn from `0..2 * keys`, so that two
+/// batches generated this way have roughly half of their keys in common.
+fn batch(rng: &mut Xoshiro256StarStar, keys: usize) -> Batch64 {
+    let tuples = (0..keys)
+        .map(|_| ((rng.gen_range(0..2 * keys as u64), rng.gen()), 1))
+        .collect();
+
+    Batch64::from_tuples((), tuples)
+}
+
+/// Compare sort-merge join with the default join of two batches with similar
+/// sets of keys.
+fn join_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stream-join");
+    group.sample_size(10);
+
+    let mut rng = Xoshiro256StarStar::from_seed(SEED);
+
+    for keys in [1_000, 100_000, 1_000_000] {
+        let left = batch(&mut rng, keys);
+        let right = batch(&mut rng, keys);
+
+        for merge in [false, true] {
+            let (left, right) = (left.clone(), right.clone());
+
+            let (circuit, ()) = RootCircuit::build(move |circuit| {
+                let left: Stream<_, Batch64> =
+                    circuit.add_source(Generator::new(move || left.clone()));
+                let right: Stream<_, Batch64> =
+                    circuit.add_source(Generator::new(move || right.clone()));
+
+                if merge {
+                    left.merge_join(&right, |_k, v1, v2| (*v1, *v2));
+                } else {
+                    left.stream_join(&right, |_k, v1, v2| (*v1, *v2));
+                }
+            })
+            .unwrap();
+
+            let name = if merge { "merge" } else { "default" };
+            group.bench_function(format!("{name}-{keys}"), |b| {
+                b.iter(|| circuit.step().unwrap());
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, join_benches);
+criterion_main!(benches);
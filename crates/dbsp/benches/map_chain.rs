@@ -0,0 +1,59 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use dbsp::{
+    operator::{FilterMap, Generator},
+    trace::Batch,
+    OrdZSet, RootCircuit, Stream,
+};
+
+/// Number of records in each input batch.
+const BATCH_SIZE: usize = 100_000;
+
+/// Number of maps in the chain.
+const CHAIN_LENGTH: usize = 5;
+
+type StringBatch = OrdZSet<String, isize>;
+
+fn batch() -> StringBatch {
+    let tuples = (0..BATCH_SIZE)
+        .map(|i| (format!("https://example.com/users/{i:016}"), 1))
+        .collect();
+
+    StringBatch::from_keys((), tuples)
+}
+
+/// Compare a chain of `map`s with a chain of `map_owned`s that rewrite long
+/// string records.  Each operator is the only consumer of its input, so
+/// `map_owned` receives batches by value and reuses their strings, while
+/// `map` clones every record.
+fn map_chain_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("map-chain");
+    group.sample_size(10);
+
+    for owned in [false, true] {
+        let (circuit, ()) = RootCircuit::build(move |circuit| {
+            let mut stream: Stream<_, StringBatch> = circuit.add_source(Generator::new(batch));
+
+            for _ in 0..CHAIN_LENGTH {
+                stream = if owned {
+                    stream.map_owned(|mut s: String| {
+                        s.make_ascii_uppercase();
+                        s
+                    })
+                } else {
+                    stream.map(|s: &String| s.to_ascii_uppercase())
+                };
+            }
+        })
+        .unwrap();
+
+        let name = if owned { "map-owned" } else { "map" };
+        group.bench_function(name, |b| {
+            b.iter(|| circuit.step().unwrap());
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, map_chain_benches);
+criterion_main!(benches);
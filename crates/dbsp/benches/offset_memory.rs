@@ -0,0 +1,70 @@
+//! Compares the memory footprint of large `OrdIndexedZSet`s with `usize` and
+//! `u32` offsets.
+//!
+//! Offsets are the only part of a batch that depends on the offset type, so
+//! the difference between the two columns is the memory saved by 32-bit
+//! offsets.  The saving is largest for batches with few values per key,
+//! where the offset array is about as long as the value array.
+
+use dbsp::{
+    trace::{layers::OrdOffset, Batch, BatchReader, Cursor},
+    OrdIndexedZSet,
+};
+use indicatif::HumanBytes;
+use size_of::SizeOf;
+use std::time::Instant;
+
+/// Number of tuples in each batch.
+const TUPLES: u64 = 10_000_000;
+
+fn build<O>(values_per_key: u64) -> OrdIndexedZSet<u64, u64, isize, O>
+where
+    O: OrdOffset,
+{
+    let tuples = (0..TUPLES)
+        .map(|i| ((i / values_per_key, i % values_per_key), 1))
+        .collect();
+
+    OrdIndexedZSet::from_tuples((), tuples)
+}
+
+/// Returns the number of bytes allocated by the batch and the time it takes
+/// to scan it with a cursor.
+fn measure<O>(values_per_key: u64) -> (usize, f64)
+where
+    O: OrdOffset,
+{
+    let batch = build::<O>(values_per_key);
+    let bytes = batch.size_of().total_bytes();
+
+    let start = Instant::now();
+    let mut cursor = batch.cursor();
+    let mut values = 0;
+    while cursor.key_valid() {
+        while cursor.val_valid() {
+            values += 1;
+            cursor.step_val();
+        }
+        cursor.step_key();
+    }
+    assert_eq!(values, TUPLES);
+
+    (bytes, start.elapsed().as_secs_f64())
+}
+
+fn main() {
+    println!("{TUPLES} tuples of (u64, u64) with isize weights");
+    println!("values/key  usize offsets  u32 offsets  saved   scan (usize/u32)");
+
+    for values_per_key in [1, 4, 16] {
+        let (wide_bytes, wide_scan) = measure::<usize>(values_per_key);
+        let (narrow_bytes, narrow_scan) = measure::<u32>(values_per_key);
+
+        println!(
+            "{values_per_key:>10}  {:>13}  {:>11}  {:>5.1}%  {wide_scan:.3}s/{narrow_scan:.3}s",
+            HumanBytes(wide_bytes as u64).to_string(),
+            HumanBytes(narrow_bytes as u64).to_string(),
+            (wide_bytes - narrow_bytes) as f64 * 100.0 / wide_bytes as f64,
+        );
+    }
+}
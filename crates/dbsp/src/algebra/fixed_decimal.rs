@@ -0,0 +1,536 @@
+use crate::algebra::{CheckedInt, HasOne, HasZero, MulByRef};
+use num::{BigInt, Signed, ToPrimitive};
+use size_of::{Context, SizeOf};
+use std::{
+    fmt::{self, Debug, Display},
+    hash::Hash,
+    marker::PhantomData,
+    ops::{Add, AddAssign, Mul, Neg},
+};
+
+#[cfg(feature = "with-serde")]
+use serde::{Deserialize, Serialize};
+
+/// Determines what [`FixedDecimal`] arithmetic does on overflow.
+pub trait OverflowPolicy: Copy + Default + Eq + Ord + Hash + Debug + Send + Sync + 'static {
+    /// Called with the saturated result of an operation that overflowed.
+    /// Returns the result of the operation or panics.
+    fn overflow(saturated: i128) -> i128;
+}
+
+/// Panic when an operation overflows, like [`CheckedInt`].
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct PanicOnOverflow;
+
+impl OverflowPolicy for PanicOnOverflow {
+    #[cold]
+    #[inline(never)]
+    fn overflow(_saturated: i128) -> i128 {
+        panic!("an operation on a FixedDecimal overflowed or underflowed")
+    }
+}
+
+/// Clamp the result of an operation that overflows to the largest or
+/// smallest representable value.
+///
+/// Saturating addition is not associative once a sum saturates, e.g.,
+/// `(MAX + 1) + (-1) != MAX + (1 + (-1))`, so the weights of a collection may
+/// depend on the order in which they were added up.  Only use this policy
+/// when overflows are not expected in practice.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SaturateOnOverflow;
+
+impl OverflowPolicy for SaturateOnOverflow {
+    #[inline]
+    fn overflow(saturated: i128) -> i128 {
+        saturated
+    }
+}
+
+/// A signed decimal number with `SCALE` digits after the decimal point.
+///
+/// The number is stored as an `i128` count of `10^-SCALE` units, e.g.,
+/// `FixedDecimal<2>` represents `12.34` as `1234`.  Addition and negation are
+/// exact, which makes `FixedDecimal` suitable for summing money amounts, both
+/// as the weight type of a collection and as the value computed by
+/// [`aggregate_linear_incremental`](`crate::Stream::aggregate_linear_incremental`).
+/// Multiplication of two decimals rounds the product toward zero to `SCALE`
+/// digits; multiplying by an integer weight is exact.
+///
+/// The `O` type argument selects what happens on overflow:
+/// [`PanicOnOverflow`] (the default) panics, just like [`CheckedInt`], while
+/// [`SaturateOnOverflow`] clamps the result to the representable range.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+pub struct FixedDecimal<const SCALE: u32, O = PanicOnOverflow> {
+    units: i128,
+    #[cfg_attr(feature = "with-serde", serde(skip))]
+    __type: PhantomData<O>,
+}
+
+impl<const SCALE: u32, O> FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    /// The number of units in `1`, i.e., `10^SCALE`.
+    pub const ONE: i128 = 10i128.pow(SCALE);
+
+    /// Creates a decimal from a count of `10^-SCALE` units, e.g.,
+    /// `FixedDecimal::<2>::from_units(1234)` is `12.34`.
+    #[inline]
+    pub const fn from_units(units: i128) -> Self {
+        Self {
+            units,
+            __type: PhantomData,
+        }
+    }
+
+    /// Returns the number of `10^-SCALE` units in `self`.
+    #[inline]
+    pub const fn into_units(self) -> i128 {
+        self.units
+    }
+
+    /// Creates a decimal with the value of an integer.
+    #[inline]
+    pub fn from_integer(value: i128) -> Self {
+        Self::from_units(
+            value
+                .checked_mul(Self::ONE)
+                .unwrap_or_else(|| O::overflow(value.saturating_mul(Self::ONE))),
+        )
+    }
+
+    /// Returns the integer part of `self`, rounded toward zero.
+    #[inline]
+    pub const fn trunc(self) -> i128 {
+        self.units / Self::ONE
+    }
+
+    #[inline]
+    fn checked_add(self, other: Self) -> Self {
+        Self::from_units(
+            self.units
+                .checked_add(other.units)
+                .unwrap_or_else(|| O::overflow(self.units.saturating_add(other.units))),
+        )
+    }
+
+    #[inline]
+    fn checked_mul_int(self, factor: i128) -> Self {
+        Self::from_units(
+            self.units
+                .checked_mul(factor)
+                .unwrap_or_else(|| O::overflow(self.units.saturating_mul(factor))),
+        )
+    }
+}
+
+impl<const SCALE: u32, O> Add for FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    type Output = Self;
+
+    #[inline]
+    fn add(self, other: Self) -> Self {
+        self.checked_add(other)
+    }
+}
+
+impl<'a, const SCALE: u32, O> Add<&'a FixedDecimal<SCALE, O>> for &'a FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    type Output = FixedDecimal<SCALE, O>;
+
+    #[inline]
+    fn add(self, other: Self) -> Self::Output {
+        self.checked_add(*other)
+    }
+}
+
+impl<const SCALE: u32, O> AddAssign for FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    #[inline]
+    fn add_assign(&mut self, other: Self) {
+        *self = self.checked_add(other);
+    }
+}
+
+impl<const SCALE: u32, O> AddAssign<&'_ FixedDecimal<SCALE, O>> for FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    #[inline]
+    fn add_assign(&mut self, other: &Self) {
+        *self = self.checked_add(*other);
+    }
+}
+
+impl<const SCALE: u32, O> Neg for FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        self.checked_mul_int(-1)
+    }
+}
+
+impl<const SCALE: u32, O> Neg for &FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    type Output = FixedDecimal<SCALE, O>;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        -*self
+    }
+}
+
+impl<const SCALE: u32, O> Mul for FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, other: Self) -> Self {
+        let units = match self.units.checked_mul(other.units) {
+            Some(product) => product / Self::ONE,
+            // The product of the units overflows, but the rescaled product may
+            // still fit, so redo the multiplication in a wider integer.
+            None => {
+                let product = BigInt::from(self.units) * other.units / Self::ONE;
+                product.to_i128().unwrap_or_else(|| {
+                    O::overflow(if product.is_negative() {
+                        i128::MIN
+                    } else {
+                        i128::MAX
+                    })
+                })
+            }
+        };
+        Self::from_units(units)
+    }
+}
+
+impl<'a, const SCALE: u32, O> Mul<&'a FixedDecimal<SCALE, O>> for &'a FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    type Output = FixedDecimal<SCALE, O>;
+
+    #[inline]
+    fn mul(self, other: Self) -> Self::Output {
+        *self * *other
+    }
+}
+
+/// Multiplication by integer weights, used to weigh values summed by linear
+/// aggregates.
+macro_rules! impl_mul_by_int {
+    ($($int:ty),* $(,)?) => {
+        $(
+            impl<const SCALE: u32, O> MulByRef<$int> for FixedDecimal<SCALE, O>
+            where
+                O: OverflowPolicy,
+            {
+                type Output = Self;
+
+                #[inline]
+                fn mul_by_ref(&self, weight: &$int) -> Self::Output {
+                    self.checked_mul_int(*weight as i128)
+                }
+            }
+
+            impl<const SCALE: u32, O> MulByRef<CheckedInt<$int>> for FixedDecimal<SCALE, O>
+            where
+                O: OverflowPolicy,
+            {
+                type Output = Self;
+
+                #[inline]
+                fn mul_by_ref(&self, weight: &CheckedInt<$int>) -> Self::Output {
+                    self.checked_mul_int(weight.into_inner() as i128)
+                }
+            }
+
+            impl<const SCALE: u32, O> From<CheckedInt<$int>> for FixedDecimal<SCALE, O>
+            where
+                O: OverflowPolicy,
+            {
+                #[inline]
+                fn from(value: CheckedInt<$int>) -> Self {
+                    Self::from_integer(value.into_inner() as i128)
+                }
+            }
+        )*
+    };
+}
+
+impl_mul_by_int! {
+    i32,
+    i64,
+    isize,
+}
+
+impl<const SCALE: u32, O> HasZero for FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.units == 0
+    }
+
+    #[inline]
+    fn zero() -> Self {
+        Self::from_units(0)
+    }
+}
+
+impl<const SCALE: u32, O> HasOne for FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    #[inline]
+    fn one() -> Self {
+        Self::from_units(Self::ONE)
+    }
+}
+
+impl<const SCALE: u32, O> SizeOf for FixedDecimal<SCALE, O> {
+    #[inline]
+    fn size_of_children(&self, _context: &mut Context) {}
+}
+
+impl<const SCALE: u32, O> Debug for FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl<const SCALE: u32, O> Display for FixedDecimal<SCALE, O>
+where
+    O: OverflowPolicy,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.units < 0 { "-" } else { "" };
+        let units = self.units.unsigned_abs();
+        let one = Self::ONE as u128;
+
+        if SCALE == 0 {
+            write!(f, "{sign}{units}")
+        } else {
+            write!(
+                f,
+                "{sign}{}.{:0width$}",
+                units / one,
+                units % one,
+                width = SCALE as usize
+            )
+        }
+    }
+}
+
+impl<const SCALE: u32, O> bincode::Encode for FixedDecimal<SCALE, O> {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        encoder: &mut E,
+    ) -> core::result::Result<(), bincode::error::EncodeError> {
+        bincode::Encode::encode(&self.units, encoder)
+    }
+}
+
+impl<const SCALE: u32, O> bincode::Decode for FixedDecimal<SCALE, O> {
+    fn decode<D: bincode::de::Decoder>(
+        decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        let units: i128 = bincode::Decode::decode(decoder)?;
+        Ok(Self {
+            units,
+            __type: PhantomData,
+        })
+    }
+}
+
+impl<'de, const SCALE: u32, O> bincode::BorrowDecode<'de> for FixedDecimal<SCALE, O> {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        let units: i128 = bincode::BorrowDecode::borrow_decode(decoder)?;
+        Ok(Self {
+            units,
+            __type: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FixedDecimal, SaturateOnOverflow};
+    use crate::{
+        algebra::{CheckedInt, HasOne, HasZero, MulByRef, ZRingValue},
+        indexed_zset, Runtime,
+    };
+    use proptest::prelude::*;
+
+    type Money = FixedDecimal<2>;
+    type SaturatingMoney = FixedDecimal<2, SaturateOnOverflow>;
+
+    fn assert_zring_value<R: ZRingValue>() {}
+
+    #[test]
+    fn fixed_decimal_arithmetic() {
+        assert_zring_value::<Money>();
+        assert_zring_value::<SaturatingMoney>();
+
+        let price = Money::from_units(1234);
+        assert_eq!(price.to_string(), "12.34");
+        assert_eq!((-price).to_string(), "-12.34");
+        assert_eq!(Money::from_units(5).to_string(), "0.05");
+        assert_eq!(price.trunc(), 12);
+
+        assert_eq!(price + Money::from_units(66), Money::from_integer(13));
+        assert_eq!(price * Money::from_integer(2), Money::from_units(2468));
+        // 12.34 * 0.5 = 6.17, 6.17 * 0.5 = 3.085, rounded toward zero.
+        assert_eq!(
+            price * Money::from_units(50) * Money::from_units(50),
+            Money::from_units(308)
+        );
+        assert_eq!(price.mul_by_ref(&-3isize), Money::from_units(-3702));
+        assert_eq!(
+            price.mul_by_ref(&CheckedInt::new(2i64)),
+            Money::from_units(2468)
+        );
+        assert_eq!(Money::from(CheckedInt::new(7i32)), Money::from_integer(7));
+        assert_eq!(Money::one(), Money::from_integer(1));
+        assert!((price + -price).is_zero());
+    }
+
+    #[test]
+    fn fixed_decimal_wide_mul() {
+        // The product of the units overflows an `i128`, the rescaled product
+        // doesn't.
+        let large = Money::from_units(i128::MAX / 10);
+        let half = Money::from_units(50);
+        assert_eq!(large * half, Money::from_units(i128::MAX / 10 / 2));
+        assert_eq!(-large * half, Money::from_units(-(i128::MAX / 10 / 2)));
+        assert_eq!(&half * &large, large * half);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fixed_decimal_overflow_panics() {
+        let _ = Money::from_units(i128::MAX) + Money::from_units(1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fixed_decimal_mul_overflow_panics() {
+        let _ = Money::from_units(i128::MAX) * Money::from_integer(2);
+    }
+
+    #[test]
+    fn fixed_decimal_overflow_saturates() {
+        let max = SaturatingMoney::from_units(i128::MAX);
+        let min = SaturatingMoney::from_units(i128::MIN);
+
+        assert_eq!(max + SaturatingMoney::from_units(1), max);
+        assert_eq!(min + SaturatingMoney::from_units(-1), min);
+        assert_eq!(max.mul_by_ref(&-2isize), min);
+        assert_eq!(-min, max);
+        assert_eq!(max * SaturatingMoney::from_integer(2), max);
+        assert_eq!(max * SaturatingMoney::from_integer(-2), min);
+    }
+
+    // Sums of money amounts computed by a linear aggregate.
+    fn fixed_decimal_aggregate_test(workers: usize) {
+        let (mut dbsp, (mut input, output)) = Runtime::init_circuit(workers, |circuit| {
+            let (stream, handle) = circuit.add_input_indexed_zset::<u64, Money, isize>();
+
+            let totals = stream.aggregate_linear_incremental(|_account, amount: &Money| *amount);
+
+            (handle, totals.integrate().output())
+        })
+        .unwrap();
+
+        input.append(&mut vec![
+            (1, (Money::from_units(1050), 2)),
+            (1, (Money::from_units(-25), 1)),
+            (2, (Money::from_units(1), 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { Money::from_units(2075) => 1 },
+                2 => { Money::from_units(1) => 1 },
+            }
+        );
+
+        input.append(&mut vec![(2, (Money::from_units(1), -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { Money::from_units(2075) => 1 } }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn fixed_decimal_aggregate_test1() {
+        fixed_decimal_aggregate_test(1);
+    }
+
+    #[test]
+    fn fixed_decimal_aggregate_test4() {
+        fixed_decimal_aggregate_test(4);
+    }
+
+    // Bounds on generated values, so that sums of three of them can't
+    // overflow.
+    const BOUND: i128 = i128::MAX / 4;
+
+    proptest! {
+        #[test]
+        fn fixed_decimal_add_associative(
+            a in -BOUND..BOUND,
+            b in -BOUND..BOUND,
+            c in -BOUND..BOUND,
+        ) {
+            let (a, b, c) = (Money::from_units(a), Money::from_units(b), Money::from_units(c));
+
+            prop_assert_eq!((a + b) + c, a + (b + c));
+            prop_assert_eq!(a + b, b + a);
+            prop_assert_eq!(a + Money::zero(), a);
+            prop_assert!((a + -a).is_zero());
+        }
+
+        #[test]
+        fn fixed_decimal_saturating_add_associative(
+            a in -BOUND..BOUND,
+            b in -BOUND..BOUND,
+            c in -BOUND..BOUND,
+        ) {
+            let (a, b, c) = (
+                SaturatingMoney::from_units(a),
+                SaturatingMoney::from_units(b),
+                SaturatingMoney::from_units(c),
+            );
+
+            prop_assert_eq!((a + b) + c, a + (b + c));
+        }
+    }
+}
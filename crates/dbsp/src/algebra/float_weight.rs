@@ -0,0 +1,267 @@
+use crate::algebra::{HasOne, HasZero, F64};
+use size_of::{Context, SizeOf};
+use std::{
+    fmt::{self, Debug, Display},
+    ops::{Add, AddAssign, Mul, Neg},
+};
+
+#[cfg(feature = "with-serde")]
+use serde::{Deserialize, Serialize};
+
+/// A floating point weight that treats values within epsilon of zero as zero.
+///
+/// Integer weights cancel out exactly, but sums of floating point weights
+/// accumulate rounding errors: inserting a record with weight `0.1` and `0.2`
+/// and then deleting it with weight `-0.3` leaves a residual weight of
+/// `5.5e-17` instead of zero.  [`F64`] treats such residuals as regular
+/// weights, so the record never disappears from the collection.
+/// `F64Weight` instead considers any weight whose absolute value is at most
+/// `10^EPSILON_EXP` to be zero.  The default epsilon is `1e-9`; use, e.g.,
+/// `F64Weight<-6>` for a larger one.
+///
+/// # Interaction with consolidation and traces
+///
+/// Batches drop tuples whose weight [`is_zero`](`HasZero::is_zero`), both
+/// when they are built from unconsolidated tuples and when two batches are
+/// merged, so near-cancellations collapse in the same places exact
+/// cancellations do:
+///
+/// * Updates to the same record within a step are summed when the batch is
+///   built, and the record is dropped from the batch if the sum is within
+///   epsilon of zero.
+/// * Updates to a record in different steps end up in different batches of
+///   a trace.  Until the trace merges these batches, cursors over the trace
+///   return each of the weights separately, and operators that sum them,
+///   e.g., via `map_times`, see the residual weight.  Once the batches are
+///   merged, the residual is dropped, so residual weights don't accumulate
+///   in traces across steps.
+///
+/// Dropping residuals is lossy: a weight smaller than epsilon is dropped
+/// even if it is a legitimate update, and weights of the same record in
+/// different batches are only summed when the batches are merged.  Choose
+/// an epsilon several orders of magnitude smaller than the smallest weight
+/// the application cares about.
+///
+/// Equality and ordering are exact, i.e., two weights that differ by less
+/// than epsilon are still considered different.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "with-serde", serde(transparent))]
+pub struct F64Weight<const EPSILON_EXP: i32 = -9>(F64);
+
+impl<const EPSILON_EXP: i32> F64Weight<EPSILON_EXP> {
+    #[inline]
+    pub const fn new(weight: f64) -> Self {
+        Self(F64::new(weight))
+    }
+
+    #[inline]
+    pub const fn into_inner(self) -> f64 {
+        self.0.into_inner()
+    }
+
+    /// Weights whose absolute value is at most `epsilon()` are considered
+    /// zero.
+    #[inline]
+    pub fn epsilon() -> f64 {
+        10f64.powi(EPSILON_EXP)
+    }
+}
+
+impl<const EPSILON_EXP: i32> From<f64> for F64Weight<EPSILON_EXP> {
+    #[inline]
+    fn from(weight: f64) -> Self {
+        Self::new(weight)
+    }
+}
+
+impl<const EPSILON_EXP: i32> Add for F64Weight<EPSILON_EXP> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl<'a, const EPSILON_EXP: i32> Add<&'a F64Weight<EPSILON_EXP>> for &'a F64Weight<EPSILON_EXP> {
+    type Output = F64Weight<EPSILON_EXP>;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        *self + *rhs
+    }
+}
+
+impl<const EPSILON_EXP: i32> AddAssign for F64Weight<EPSILON_EXP> {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const EPSILON_EXP: i32> AddAssign<&'_ F64Weight<EPSILON_EXP>> for F64Weight<EPSILON_EXP> {
+    #[inline]
+    fn add_assign(&mut self, rhs: &Self) {
+        *self = *self + *rhs;
+    }
+}
+
+impl<const EPSILON_EXP: i32> Mul for F64Weight<EPSILON_EXP> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self(self.0 * rhs.0)
+    }
+}
+
+impl<'a, const EPSILON_EXP: i32> Mul<&'a F64Weight<EPSILON_EXP>> for &'a F64Weight<EPSILON_EXP> {
+    type Output = F64Weight<EPSILON_EXP>;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        *self * *rhs
+    }
+}
+
+impl<const EPSILON_EXP: i32> Neg for F64Weight<EPSILON_EXP> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+impl<const EPSILON_EXP: i32> Neg for &F64Weight<EPSILON_EXP> {
+    type Output = F64Weight<EPSILON_EXP>;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        -*self
+    }
+}
+
+impl<const EPSILON_EXP: i32> HasZero for F64Weight<EPSILON_EXP> {
+    #[inline]
+    fn zero() -> Self {
+        Self::new(0.0)
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.into_inner().abs() <= Self::epsilon()
+    }
+}
+
+impl<const EPSILON_EXP: i32> HasOne for F64Weight<EPSILON_EXP> {
+    #[inline]
+    fn one() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl<const EPSILON_EXP: i32> SizeOf for F64Weight<EPSILON_EXP> {
+    #[inline]
+    fn size_of_children(&self, _context: &mut Context) {}
+}
+
+impl<const EPSILON_EXP: i32> Debug for F64Weight<EPSILON_EXP> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl<const EPSILON_EXP: i32> Display for F64Weight<EPSILON_EXP> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<const EPSILON_EXP: i32> bincode::Encode for F64Weight<EPSILON_EXP> {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        encoder: &mut E,
+    ) -> core::result::Result<(), bincode::error::EncodeError> {
+        bincode::Encode::encode(&self.0, encoder)
+    }
+}
+
+impl<const EPSILON_EXP: i32> bincode::Decode for F64Weight<EPSILON_EXP> {
+    fn decode<D: bincode::de::Decoder>(
+        decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        let weight: F64 = bincode::Decode::decode(decoder)?;
+        Ok(Self(weight))
+    }
+}
+
+impl<'de, const EPSILON_EXP: i32> bincode::BorrowDecode<'de> for F64Weight<EPSILON_EXP> {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        let weight: F64 = bincode::BorrowDecode::borrow_decode(decoder)?;
+        Ok(Self(weight))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::F64Weight;
+    use crate::{
+        algebra::{HasZero, ZRingValue},
+        trace::{consolidation::consolidate, Batch, BatchReader},
+        OrdZSet,
+    };
+
+    fn assert_zring_value<R: ZRingValue>() {}
+
+    #[test]
+    fn epsilon() {
+        assert_zring_value::<F64Weight>();
+
+        let residual = F64Weight::<-9>::new(0.1) + F64Weight::new(0.2) + F64Weight::new(-0.3);
+        assert_ne!(residual, F64Weight::new(0.0));
+        assert!(residual.is_zero());
+        assert!(F64Weight::<-9>::new(-1e-10).is_zero());
+        assert!(!F64Weight::<-9>::new(1e-8).is_zero());
+        assert!(!F64Weight::<-12>::new(1e-10).is_zero());
+    }
+
+    #[test]
+    fn near_cancellations_consolidate() {
+        let mut tuples = vec![
+            ("a", F64Weight::<-9>::new(0.1)),
+            ("b", F64Weight::new(0.5)),
+            ("a", F64Weight::new(0.2)),
+            ("a", F64Weight::new(-0.3)),
+        ];
+        consolidate(&mut tuples);
+        assert_eq!(tuples, vec![("b", F64Weight::new(0.5))]);
+
+        // Residuals split across batches are dropped when the batches are
+        // merged.
+        let batch1 = OrdZSet::from_keys(
+            (),
+            vec![
+                ("a".to_string(), F64Weight::<-9>::new(0.1)),
+                ("b".to_string(), F64Weight::new(0.5)),
+            ],
+        );
+        let batch2 = OrdZSet::from_keys(
+            (),
+            vec![
+                ("a".to_string(), F64Weight::new(0.2)),
+                ("a".to_string(), F64Weight::new(-0.3)),
+            ],
+        );
+        let merged = batch1.merge(&batch2);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(
+            merged,
+            OrdZSet::from_keys((), vec![("b".to_string(), F64Weight::new(0.5))])
+        );
+    }
+}
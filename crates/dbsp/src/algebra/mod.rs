@@ -3,6 +3,8 @@
 
 #[macro_use]
 mod checked_int;
+mod fixed_decimal;
+mod float_weight;
 mod floats;
 mod lattice;
 mod order;
@@ -11,6 +13,8 @@ mod present;
 pub mod zset;
 
 pub use checked_int::CheckedInt;
+pub use fixed_decimal::{FixedDecimal, OverflowPolicy, PanicOnOverflow, SaturateOnOverflow};
+pub use float_weight::F64Weight;
 pub use floats::{F32, F64};
 pub use lattice::Lattice;
 pub use order::{PartialOrder, TotalOrder};
@@ -254,6 +258,9 @@ pub trait ZRingValue: RingValue {
 
     /// True if value is less than or equal to zero.
     fn le0(&self) -> bool;
+
+    /// True if value is greater than zero.
+    fn gt0(&self) -> bool;
 }
 
 /// Default implementation of `ZRingValue` for all types that have the required
@@ -271,6 +278,11 @@ where
     fn le0(&self) -> bool {
         *self <= Self::zero()
     }
+
+    #[inline]
+    fn gt0(&self) -> bool {
+        *self > Self::zero()
+    }
 }
 
 impl MulByRef<isize> for i32 {
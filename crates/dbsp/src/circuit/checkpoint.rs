@@ -0,0 +1,429 @@
+//! Checkpointing the state of a circuit.
+//!
+//! A checkpoint captures the state of all operators of a circuit between
+//! clock cycles, so that a circuit with the same structure can later resume
+//! computation from that state, e.g., after a restart (see
+//! [`DBSPHandle::checkpoint`](`crate::DBSPHandle::checkpoint`) and
+//! [`Runtime::restore_circuit`](`crate::Runtime::restore_circuit`)).
+//!
+//! ## On-disk format
+//!
+//! A checkpoint is a directory with one file per worker, named
+//! `worker-<index>.checkpoint`, and a `workers` file that stores the number
+//! of workers as text.  A worker file contains a `bincode`-encoded vector of
+//! `(node path, node name, state)` entries, one for each operator that
+//! returned state from
+//! [`Operator::checkpoint`](`crate::circuit::operator_traits::Operator::checkpoint`).
+//! The node name is used to detect attempts to restore a checkpoint into a
+//! circuit with a different structure.
+//!
+//! ## Operator state
+//!
+//! Operators that carry state across clock cycles save it in
+//! [`Operator::checkpoint`](`crate::circuit::operator_traits::Operator::checkpoint`).
+//! Operators that store values of arbitrary types, e.g.,
+//! [`Z1`](`crate::operator::Z1`), rely on the [`Checkpoint`] trait to
+//! serialize them.
+
+#[cfg(feature = "persistence")]
+use crate::trace::{BatchReader, Cursor};
+use crate::{
+    circuit::{circuit_builder::Node, GlobalNodeId, NodeId, RootCircuit},
+    trace::Batch,
+    Error as DBSPError,
+};
+use bincode::{config::standard, decode_from_slice, encode_to_vec, Decode, Encode};
+#[cfg(feature = "persistence")]
+use std::collections::BTreeMap;
+use std::{
+    collections::HashMap,
+    fmt::{Display, Error as FmtError, Formatter},
+    fs::{self, create_dir_all, remove_dir_all},
+    path::{Path, PathBuf},
+};
+
+/// Name of the file that stores the number of workers in a checkpoint.
+const WORKERS_FILE: &str = "workers";
+
+/// Error saving or restoring the state of an operator.
+#[derive(Debug)]
+pub enum CheckpointError {
+    /// The operator has state that it cannot serialize.
+    Unsupported,
+    /// Failed to encode or decode operator state.
+    Encoding(String),
+}
+
+impl Display for CheckpointError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::Unsupported => f.write_str("operator state cannot be checkpointed"),
+            Self::Encoding(error) => write!(f, "error encoding operator state: {error}"),
+        }
+    }
+}
+
+/// Encodes operator state for a checkpoint.
+pub(crate) fn encode_state<T>(state: &T) -> Result<Vec<u8>, CheckpointError>
+where
+    T: Encode,
+{
+    encode_to_vec(state, standard()).map_err(|e| CheckpointError::Encoding(e.to_string()))
+}
+
+/// Decodes operator state encoded with [`encode_state`].
+pub(crate) fn decode_state<T>(state: &[u8]) -> Result<T, CheckpointError>
+where
+    T: Decode,
+{
+    let (state, _) = decode_from_slice(state, standard())
+        .map_err(|e| CheckpointError::Encoding(e.to_string()))?;
+    Ok(state)
+}
+
+/// A value that can be saved in a checkpoint.
+///
+/// Operators that store values of type `T` across clock cycles, e.g.,
+/// [`Z1`](`crate::operator::Z1`), require `T: Checkpoint`.  The trait is
+/// implemented for scalar types, strings, and tuples, vectors, and options
+/// of checkpointable types.  Batches implement it when the `persistence`
+/// feature, which makes all data types serializable, is enabled.
+///
+/// Both methods return [`CheckpointError::Unsupported`] by default, so
+/// types that cannot be serialized can implement the trait with an empty
+/// `impl` block.  Operators only serialize values that differ from their
+/// initial state, so such types only fail a checkpoint when they hold data.
+pub trait Checkpoint {
+    /// Serializes `self`.
+    fn checkpoint(&self) -> Result<Vec<u8>, CheckpointError> {
+        Err(CheckpointError::Unsupported)
+    }
+
+    /// Replaces the contents of `self` with the value serialized by
+    /// [`Self::checkpoint`].
+    fn restore(&mut self, _state: &[u8]) -> Result<(), CheckpointError> {
+        Err(CheckpointError::Unsupported)
+    }
+}
+
+/// Implements [`Checkpoint`] for types that implement `bincode`'s `Encode`
+/// and `Decode`.
+macro_rules! checkpoint_bincode {
+    ($($type:ty),+ $(,)?) => {
+        $(
+            impl Checkpoint for $type {
+                fn checkpoint(&self) -> Result<Vec<u8>, CheckpointError> {
+                    encode_state(self)
+                }
+
+                fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+                    *self = decode_state(state)?;
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
+checkpoint_bincode! {
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    String,
+}
+
+/// Implements [`Checkpoint`] for tuples of checkpointable types by
+/// serializing each element separately.
+macro_rules! checkpoint_tuple {
+    ($($name:ident),+) => {
+        impl<$($name),+> Checkpoint for ($($name,)+)
+        where
+            $($name: Checkpoint,)+
+        {
+            #[allow(non_snake_case)]
+            fn checkpoint(&self) -> Result<Vec<u8>, CheckpointError> {
+                let ($($name,)+) = self;
+                encode_state(&vec![$($name.checkpoint()?),+])
+            }
+
+            #[allow(non_snake_case)]
+            fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+                let elements: Vec<Vec<u8>> = decode_state(state)?;
+                let mut elements = elements.iter();
+                let ($($name,)+) = self;
+                $(
+                    $name.restore(elements.next().ok_or_else(|| {
+                        CheckpointError::Encoding("tuple has too few elements".to_string())
+                    })?)?;
+                )+
+                Ok(())
+            }
+        }
+    };
+}
+
+checkpoint_tuple!(A);
+checkpoint_tuple!(A, B);
+checkpoint_tuple!(A, B, C);
+checkpoint_tuple!(A, B, C, D);
+checkpoint_tuple!(A, B, C, D, E);
+checkpoint_tuple!(A, B, C, D, E, F);
+
+impl<T> Checkpoint for Option<T>
+where
+    T: Checkpoint + Default,
+{
+    fn checkpoint(&self) -> Result<Vec<u8>, CheckpointError> {
+        let state = self.as_ref().map(T::checkpoint).transpose()?;
+        encode_state(&state)
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        let state: Option<Vec<u8>> = decode_state(state)?;
+        *self = match state {
+            Some(state) => {
+                let mut value = T::default();
+                value.restore(&state)?;
+                Some(value)
+            }
+            None => None,
+        };
+        Ok(())
+    }
+}
+
+impl<T> Checkpoint for Vec<T>
+where
+    T: Checkpoint + Default,
+{
+    fn checkpoint(&self) -> Result<Vec<u8>, CheckpointError> {
+        let state = self
+            .iter()
+            .map(T::checkpoint)
+            .collect::<Result<Vec<_>, _>>()?;
+        encode_state(&state)
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        let state: Vec<Vec<u8>> = decode_state(state)?;
+        *self = state
+            .iter()
+            .map(|state| {
+                let mut value = T::default();
+                value.restore(state)?;
+                Ok(value)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(())
+    }
+}
+
+/// Saves the contents of a batch as a list of `(key, val, time, diff)`
+/// updates, which `restore` groups into one batch per timestamp.
+#[cfg(feature = "persistence")]
+impl<B> Checkpoint for B
+where
+    B: Batch,
+{
+    fn checkpoint(&self) -> Result<Vec<u8>, CheckpointError> {
+        let mut updates = Vec::with_capacity(self.len());
+        let mut cursor = self.cursor();
+        while cursor.key_valid() {
+            while cursor.val_valid() {
+                let key = cursor.key().clone();
+                let val = cursor.val().clone();
+                cursor.map_times(|time, diff| {
+                    updates.push((key.clone(), val.clone(), time.clone(), diff.clone()))
+                });
+                cursor.step_val();
+            }
+            cursor.step_key();
+        }
+
+        encode_state(&updates)
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        let updates: Vec<(B::Key, B::Val, B::Time, B::R)> = decode_state(state)?;
+
+        let mut batches = BTreeMap::new();
+        for (key, val, time, diff) in updates {
+            batches
+                .entry(time)
+                .or_insert_with(Vec::new)
+                .push((B::item_from(key, val), diff));
+        }
+
+        let mut result: Option<B> = None;
+        for (time, tuples) in batches {
+            let batch = B::from_tuples(time, tuples);
+            result = Some(match result {
+                Some(result) => result.merge(&batch),
+                None => batch,
+            });
+        }
+
+        if let Some(result) = result {
+            *self = result;
+        }
+        Ok(())
+    }
+}
+
+/// Batch contents can only be serialized when the `persistence` feature
+/// makes all data types serializable.
+#[cfg(not(feature = "persistence"))]
+impl<B> Checkpoint for B where B: Batch {}
+
+/// State of an operator: node path, node name, and the state returned by
+/// the operator.
+type NodeState = (Vec<usize>, String, Vec<u8>);
+
+fn node_path(node_id: &GlobalNodeId) -> Vec<usize> {
+    node_id.path().iter().map(NodeId::id).collect()
+}
+
+/// Collects the state of all operators in `circuit`.
+pub(crate) fn checkpoint_circuit(circuit: &RootCircuit) -> Result<Vec<u8>, DBSPError> {
+    let mut states: Vec<NodeState> = Vec::new();
+    let mut result = Ok(());
+
+    circuit.map_nodes_recursive(&mut |node: &dyn Node| {
+        if result.is_err() {
+            return;
+        }
+
+        match node.checkpoint() {
+            Ok(Some(state)) => {
+                states.push((node_path(node.global_id()), node.name().into_owned(), state))
+            }
+            Ok(None) => {}
+            Err(error) => {
+                result = Err(DBSPError::Checkpoint {
+                    node_id: node.global_id().clone(),
+                    name: node.name(),
+                    error,
+                })
+            }
+        }
+    });
+    result?;
+
+    encode_state(&states)
+        .map_err(|e| DBSPError::Custom(format!("failed to encode checkpoint: {e}")))
+}
+
+/// Restores the state of all operators in `circuit` from a checkpoint
+/// produced by [`checkpoint_circuit`].
+pub(crate) fn restore_circuit(circuit: &RootCircuit, checkpoint: &[u8]) -> Result<(), DBSPError> {
+    let states: Vec<NodeState> = decode_state(checkpoint)
+        .map_err(|e| DBSPError::Custom(format!("malformed checkpoint: {e}")))?;
+    let mut states: HashMap<Vec<usize>, (String, Vec<u8>)> = states
+        .into_iter()
+        .map(|(path, name, state)| (path, (name, state)))
+        .collect();
+    let mut result = Ok(());
+
+    circuit.map_nodes_recursive_mut(&mut |node: &mut dyn Node| {
+        if result.is_err() {
+            return;
+        }
+
+        if let Some((name, state)) = states.remove(&node_path(node.global_id())) {
+            result = if node.name() != name.as_str() {
+                Err(DBSPError::Custom(format!(
+                    "checkpoint does not match the circuit: node {} is '{}', but the checkpoint contains state of '{name}'",
+                    node.global_id(),
+                    node.name()
+                )))
+            } else {
+                node.restore(&state)
+                    .map_err(|error| DBSPError::Checkpoint {
+                        node_id: node.global_id().clone(),
+                        name: node.name(),
+                        error,
+                    })
+            };
+        }
+    });
+    result?;
+
+    if let Some((path, (name, _))) = states.iter().next() {
+        let node_id = GlobalNodeId::from_path_vec(path.iter().copied().map(NodeId::new).collect());
+        return Err(DBSPError::Custom(format!(
+            "checkpoint does not match the circuit: the checkpoint contains state of '{name}' ({node_id}), which does not exist in the circuit"
+        )));
+    }
+
+    Ok(())
+}
+
+fn worker_file(path: &Path, worker: usize) -> PathBuf {
+    path.join(format!("worker-{worker}.checkpoint"))
+}
+
+/// Writes the checkpoints of all workers to a new directory `path`.
+///
+/// The checkpoint is first written to a temporary directory next to `path`,
+/// which is then renamed to `path`, so that a crash while writing the
+/// checkpoint does not leave an incomplete checkpoint behind.
+pub(crate) fn write_checkpoint(path: &Path, checkpoints: &[Vec<u8>]) -> Result<(), DBSPError> {
+    if path.exists() {
+        return Err(DBSPError::Custom(format!(
+            "checkpoint directory '{}' already exists",
+            path.display()
+        )));
+    }
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    if tmp_path.exists() {
+        // Left behind by an interrupted checkpoint.
+        remove_dir_all(&tmp_path)?;
+    }
+    create_dir_all(&tmp_path)?;
+
+    for (worker, checkpoint) in checkpoints.iter().enumerate() {
+        fs::write(worker_file(&tmp_path, worker), checkpoint)?;
+    }
+    fs::write(tmp_path.join(WORKERS_FILE), checkpoints.len().to_string())?;
+
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Reads the checkpoints of `nworkers` workers from directory `path`.
+pub(crate) fn read_checkpoint(path: &Path, nworkers: usize) -> Result<Vec<Vec<u8>>, DBSPError> {
+    let workers = fs::read_to_string(path.join(WORKERS_FILE))?;
+    let workers = workers.trim().parse::<usize>().map_err(|e| {
+        DBSPError::Custom(format!(
+            "malformed checkpoint '{}': invalid number of workers '{workers}': {e}",
+            path.display()
+        ))
+    })?;
+
+    if workers != nworkers {
+        return Err(DBSPError::Custom(format!(
+            "checkpoint '{}' was taken with {workers} workers and cannot be restored into a runtime with {nworkers} workers",
+            path.display()
+        )));
+    }
+
+    (0..nworkers)
+        .map(|worker| fs::read(worker_file(path, worker)).map_err(DBSPError::from))
+        .collect()
+}
@@ -29,10 +29,11 @@
 use crate::{
     circuit::{
         cache::{CircuitCache, CircuitStoreMarker},
-        metadata::OperatorMeta,
+        checkpoint::{checkpoint_circuit, restore_circuit, CheckpointError},
+        metadata::{OperatorLocation, OperatorMeta},
         operator_traits::{
             BinaryOperator, Data, ImportOperator, NaryOperator, QuaternaryOperator, SinkOperator,
-            SourceOperator, StrictUnaryOperator, TernaryOperator, UnaryOperator,
+            SourceOperator, SplitOperator, StrictUnaryOperator, TernaryOperator, UnaryOperator,
         },
         schedule::{
             DynamicScheduler, Error as SchedulerError, Executor, IterativeExecutor, OnceExecutor,
@@ -41,19 +42,23 @@ use crate::{
         trace::{CircuitEvent, SchedulerEvent},
     },
     circuit_cache_key,
-    operator::communication::Exchange,
+    operator::communication::{
+        new_broadcast_operators, new_gather_operators, BroadcastReceiver, BroadcastSender,
+        Exchange, ExchangeReceiver, GatherSender,
+    },
     time::{Timestamp, UnitTimestamp},
-    Runtime,
+    Error as DBSPError, Runtime,
 };
 use std::{
+    any::Any,
     borrow::Cow,
-    cell::{Ref, RefCell, RefMut, UnsafeCell},
+    cell::{Cell, Ref, RefCell, RefMut, UnsafeCell},
     collections::HashMap,
     fmt,
     fmt::{Debug, Display, Write},
     iter::repeat,
     marker::PhantomData,
-    panic::Location,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe, Location},
     rc::Rc,
     thread::panicking,
 };
@@ -132,7 +137,9 @@ impl<C, D> Stream<C, D> {
     ///
     /// If the stream originates in a subcircuit, returns id of the operator
     /// inside the subcircuit (or one of its subcircuits) that produces the
-    /// contents of the stream.
+    /// contents of the stream.  If the stream is the second output of a
+    /// [`SplitOperator`], returns the id of the operator extended with
+    /// output index `1`.
     pub fn origin_node_id(&self) -> &GlobalNodeId {
         &self.origin_node_id
     }
@@ -145,6 +152,28 @@ impl<C, D> Stream<C, D> {
     pub fn ptr_eq<D2>(&self, other: &Stream<C, D2>) -> bool {
         self.origin_node_id() == other.origin_node_id()
     }
+
+    /// Identity transformation: returns a stream with the same contents as
+    /// `self`.
+    ///
+    /// Unlike an identity [`map`](`crate::operator::FilterMap::map`), e.g.,
+    /// `map(|x| x.clone())`, this method doesn't add an operator to the
+    /// circuit: the returned stream is a new handle to `self`, so its
+    /// contents are never copied.  Use it wherever a circuit needs a stream
+    /// of the same type as `self`, e.g., in generated code that emits an
+    /// identity projection.
+    ///
+    /// An identity is only a true no-op if the input and output types are
+    /// the same.  Converting a stream to a different type, e.g., re-indexing
+    /// a Z-set with `map_index(|(k, v)| (k.clone(), v.clone()))` or changing
+    /// the batch type that stores the collection, requires a real operator
+    /// that copies each record into a new batch.
+    pub fn passthrough(&self) -> Self
+    where
+        C: Clone,
+    {
+        self.clone()
+    }
 }
 
 // Internal streams API only used inside this module.
@@ -268,6 +297,59 @@ where
 /// parent, etc.
 pub type Scope = u16;
 
+thread_local! {
+    // Nesting depth of `eval_node` calls in the current thread.
+    static EVAL_DEPTH: Cell<usize> = Cell::new(0);
+
+    // `true` while a panic whose operator context has already been recorded
+    // unwinds through nested `eval_node` calls.
+    static PANIC_ANNOTATED: Cell<bool> = Cell::new(false);
+
+    // Message of the last panic raised by an operator in the current thread,
+    // annotated with the context of the operator.
+    static OPERATOR_PANIC_MESSAGE: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Extracts the message from a panic payload, if the payload is a string.
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> Option<&str> {
+    if let Some(message) = panic.downcast_ref::<&'static str>() {
+        Some(*message)
+    } else {
+        panic.downcast_ref::<String>().map(String::as_str)
+    }
+}
+
+/// Returns the message of the last panic raised by an operator in the current
+/// thread, annotated with the name, global id, and location of the operator.
+pub(crate) fn take_operator_panic_message() -> Option<String> {
+    OPERATOR_PANIC_MESSAGE.with(|message| message.borrow_mut().take())
+}
+
+/// Forget the context of any operator panic recorded in the current thread.
+fn clear_operator_panic_context() {
+    PANIC_ANNOTATED.with(|annotated| annotated.set(false));
+    OPERATOR_PANIC_MESSAGE.with(|message| *message.borrow_mut() = None);
+}
+
+/// Annotate the message of a panic raised by `node` with the name, global id,
+/// and location of the operator.
+fn operator_panic_message(node: &dyn Node, panic: &(dyn Any + Send)) -> String {
+    let message = panic_message(panic).unwrap_or("Box<dyn Any>");
+
+    match node.location() {
+        Some(location) => format!(
+            "panic in operator '{}' ({}) at {location}: {message}",
+            node.name(),
+            node.global_id(),
+        ),
+        None => format!(
+            "panic in operator '{}' ({}): {message}",
+            node.name(),
+            node.global_id(),
+        ),
+    }
+}
+
 /// Node in a circuit.  A node wraps an operator with strongly typed
 /// input and output streams.
 pub trait Node {
@@ -279,6 +361,12 @@ pub trait Node {
 
     fn name(&self) -> Cow<'static, str>;
 
+    /// The location where the operator wrapped by this node was created,
+    /// if known.
+    fn location(&self) -> OperatorLocation {
+        None
+    }
+
     /// `true` if the node encapsulates an asynchronous operator (see
     /// [`Operator::is_async()`](super::operator_traits::Operator::is_async)).
     /// `false` for synchronous operators and subcircuits.
@@ -333,7 +421,19 @@ pub trait Node {
 
     fn fixedpoint(&self, scope: Scope) -> bool;
 
+    /// Serializes the state of the operator (see
+    /// [`Operator::checkpoint`](super::operator_traits::Operator::checkpoint)).
+    /// Returns `Ok(None)` for subcircuits, whose operators are checkpointed
+    /// individually.
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError>;
+
+    /// Restores the state of the operator (see
+    /// [`Operator::restore`](super::operator_traits::Operator::restore)).
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError>;
+
     fn map_nodes_recursive(&self, _f: &mut dyn FnMut(&dyn Node)) {}
+
+    fn map_nodes_recursive_mut(&mut self, _f: &mut dyn FnMut(&mut dyn Node)) {}
 }
 
 /// Id of an operator, guaranteed to be unique within a circuit.
@@ -643,6 +743,9 @@ pub trait Circuit: WithClock + Clone + 'static {
     /// Returns vector of local node ids in the circuit.
     fn node_ids(&self) -> Vec<NodeId>;
 
+    /// Returns the name of the node with the specified local id.
+    fn node_name(&self, id: NodeId) -> Cow<'static, str>;
+
     /// Relative depth of `self` from the root circuit.
     ///
     /// Returns 0 if `self` is the root circuit, 1 if `self` is an immediate
@@ -799,6 +902,63 @@ pub trait Circuit: WithClock + Clone + 'static {
         SndOp: SinkOperator<I>,
         RcvOp: SourceOperator<O>;
 
+    /// Create a pair of exchange operators ([`BroadcastSender`],
+    /// [`BroadcastReceiver`]) that implements 1-to-N communication: at each
+    /// clock cycle, worker `sender_worker` sends a copy of its input value to
+    /// all workers.
+    ///
+    /// The output stream in each worker contains the value sent by
+    /// `sender_worker` at the current clock cycle.  Inputs in all other
+    /// workers are discarded.  This is useful, e.g., for replicating a small
+    /// dimension table computed by one worker to all workers.
+    ///
+    /// Use [`Self::add_exchange`] to add the operators to the circuit.
+    ///
+    /// # Arguments
+    ///
+    /// * `runtime` - [`Runtime`] within which operators are created.
+    /// * `location` - source location reported by the operators.
+    /// * `sender_worker` - index of the worker that sends data.
+    fn new_broadcast_operators<T>(
+        &self,
+        runtime: &Runtime,
+        location: OperatorLocation,
+        sender_worker: usize,
+    ) -> (BroadcastSender<T>, BroadcastReceiver<T>)
+    where
+        T: Default + Clone + Send + 'static;
+
+    /// Create a pair of exchange operators ([`GatherSender`],
+    /// [`ExchangeReceiver`]) that implements N-to-1 communication: at each
+    /// clock cycle, all workers send their input value to worker
+    /// `receiver_worker`.
+    ///
+    /// The output stream in `receiver_worker` contains values received from
+    /// all workers, folded using `combine`.  The output streams in all other
+    /// workers contain `TO::default()`.  This is useful, e.g., for funneling
+    /// results to a single writer.
+    ///
+    /// Use [`Self::add_exchange`] to add the operators to the circuit.
+    ///
+    /// # Arguments
+    ///
+    /// * `runtime` - [`Runtime`] within which operators are created.
+    /// * `location` - source location reported by the operators.
+    /// * `receiver_worker` - index of the worker that receives data.
+    /// * `combine` - re-assemble logic that combines values received from all
+    ///   workers into a single output value.
+    fn new_gather_operators<TI, TO, CL>(
+        &self,
+        runtime: &Runtime,
+        location: OperatorLocation,
+        receiver_worker: usize,
+        combine: CL,
+    ) -> (GatherSender<TI>, ExchangeReceiver<TI, CL>)
+    where
+        TO: Default + Clone,
+        TI: Send + 'static,
+        CL: Fn(&mut TO, TI) + 'static;
+
     /// Add a sink operator (see [`SinkOperator`]).
     fn add_sink<I, Op>(&self, operator: Op, input_stream: &Stream<Self, I>)
     where
@@ -840,6 +1000,23 @@ pub trait Circuit: WithClock + Clone + 'static {
         O: Data,
         Op: UnaryOperator<I, O>;
 
+    /// Add a unary operator with two output streams (see [`SplitOperator`]).
+    ///
+    /// The operator is a single node in the circuit.  The origin of its
+    /// second output stream (see [`Stream::origin_node_id`]) is the global id
+    /// of the node extended with output index `1`, which distinguishes the
+    /// two streams, e.g., in circuit caches.
+    fn add_split_operator<I, O1, O2, Op>(
+        &self,
+        operator: Op,
+        input_stream: &Stream<Self, I>,
+    ) -> (Stream<Self, O1>, Stream<Self, O2>)
+    where
+        I: Data,
+        O1: Data,
+        O2: Data,
+        Op: SplitOperator<I, O1, O2>;
+
     /// Add a binary operator (see [`BinaryOperator`]).
     fn add_binary_operator<I1, I2, O, Op>(
         &self,
@@ -1616,6 +1793,15 @@ where
         }
     }
 
+    /// Like [`Self::map_nodes_recursive`], but gives `f` mutable access to
+    /// nodes.
+    pub(crate) fn map_nodes_recursive_mut(&self, f: &mut dyn FnMut(&mut dyn Node)) {
+        for node in self.inner_mut().nodes.iter_mut() {
+            f(node.as_mut());
+            node.map_nodes_recursive_mut(f);
+        }
+    }
+
     fn clear(&mut self) {
         self.inner_mut().clear();
     }
@@ -1665,6 +1851,10 @@ where
             .collect()
     }
 
+    fn node_name(&self, id: NodeId) -> Cow<'static, str> {
+        self.inner().nodes[id.0].name()
+    }
+
     fn root_scope(&self) -> Scope {
         self.inner().root_scope
     }
@@ -1811,7 +2001,42 @@ where
         // reference to a node and pass it to an operator,
         // but this module doesn't expose nodes, only
         // streams.
-        unsafe { circuit.nodes[id.0].eval()? };
+        //
+        // The outermost `eval_node` call resets the panic context of the
+        // current thread, so that it never reports a panic raised by an
+        // earlier evaluation that was caught and discarded by the caller.
+        let depth = EVAL_DEPTH.with(|d| d.replace(d.get() + 1));
+        if depth == 0 {
+            clear_operator_panic_context();
+        }
+        let result = catch_unwind(AssertUnwindSafe(|| unsafe { circuit.nodes[id.0].eval() }));
+        EVAL_DEPTH.with(|d| d.set(depth));
+
+        match result {
+            Ok(result) => {
+                // A panic raised and caught inside the operator must not be
+                // attributed to it.
+                if depth == 0 {
+                    clear_operator_panic_context();
+                }
+                result?
+            }
+            Err(panic) => {
+                // Only the innermost `eval_node` call records the context of the
+                // operator that raised the panic; outer calls (i.e., evaluating the
+                // subcircuits that contain the operator) leave it unchanged.  The
+                // original payload is re-raised in either case.
+                let annotated = PANIC_ANNOTATED.with(|annotated| annotated.replace(depth != 0));
+                if !annotated {
+                    let message =
+                        operator_panic_message(circuit.nodes[id.0].as_ref(), panic.as_ref());
+                    OPERATOR_PANIC_MESSAGE.with(|m| *m.borrow_mut() = Some(message));
+                }
+
+                drop(circuit);
+                resume_unwind(panic)
+            }
+        }
 
         circuit.log_scheduler_event(&SchedulerEvent::eval_end(circuit.nodes[id.0].as_ref()));
 
@@ -1905,6 +2130,39 @@ where
         output_stream
     }
 
+    fn new_broadcast_operators<T>(
+        &self,
+        runtime: &Runtime,
+        location: OperatorLocation,
+        sender_worker: usize,
+    ) -> (BroadcastSender<T>, BroadcastReceiver<T>)
+    where
+        T: Default + Clone + Send + 'static,
+    {
+        new_broadcast_operators(runtime, Runtime::worker_index(), location, sender_worker)
+    }
+
+    fn new_gather_operators<TI, TO, CL>(
+        &self,
+        runtime: &Runtime,
+        location: OperatorLocation,
+        receiver_worker: usize,
+        combine: CL,
+    ) -> (GatherSender<TI>, ExchangeReceiver<TI, CL>)
+    where
+        TO: Default + Clone,
+        TI: Send + 'static,
+        CL: Fn(&mut TO, TI) + 'static,
+    {
+        new_gather_operators(
+            runtime,
+            Runtime::worker_index(),
+            location,
+            receiver_worker,
+            combine,
+        )
+    }
+
     fn add_sink<I, Op>(&self, operator: Op, input_stream: &Stream<Self, I>)
     where
         I: Data,
@@ -1979,6 +2237,32 @@ where
         })
     }
 
+    fn add_split_operator<I, O1, O2, Op>(
+        &self,
+        operator: Op,
+        input_stream: &Stream<Self, I>,
+    ) -> (Stream<Self, O1>, Stream<Self, O2>)
+    where
+        I: Data,
+        O1: Data,
+        O2: Data,
+        Op: SplitOperator<I, O1, O2>,
+    {
+        let input_preference = operator.input_preference();
+        self.add_node(|id| {
+            self.log_circuit_event(&CircuitEvent::operator(
+                GlobalNodeId::child_of(self, id),
+                operator.name(),
+                operator.location(),
+            ));
+
+            let node = SplitNode::new(operator, input_stream.clone(), self.clone(), id);
+            let output_streams = node.output_streams();
+            self.connect_stream(input_stream, id, input_preference);
+            (node, output_streams)
+        })
+    }
+
     fn add_binary_operator<I1, I2, O, Op>(
         &self,
         operator: Op,
@@ -2353,7 +2637,7 @@ where
                     // status with peers.
                     let worker_index = Runtime::worker_index();
                     let exchange_id = runtime.sequence_next(worker_index);
-                    let exchange = Exchange::with_runtime(&runtime, exchange_id);
+                    let exchange = Exchange::with_runtime(&runtime, exchange_id, 1);
 
                     let unparker = Runtime::parker().with(|parker| parker.unparker().clone());
                     exchange.register_sender_callback(worker_index, move || unparker.unpark());
@@ -2533,6 +2817,10 @@ where
         self.operator.name()
     }
 
+    fn location(&self) -> OperatorLocation {
+        self.operator.location()
+    }
+
     fn local_id(&self) -> NodeId {
         self.id.local_node_id().unwrap()
     }
@@ -2579,6 +2867,14 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        self.operator.restore(state)
+    }
 }
 
 struct SourceNode<C, O, Op> {
@@ -2615,6 +2911,10 @@ where
         self.operator.name()
     }
 
+    fn location(&self) -> OperatorLocation {
+        self.operator.location()
+    }
+
     fn local_id(&self) -> NodeId {
         self.id.local_node_id().unwrap()
     }
@@ -2655,6 +2955,14 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        self.operator.restore(state)
+    }
 }
 
 struct UnaryNode<C, I, O, Op> {
@@ -2694,6 +3002,10 @@ where
         self.operator.name()
     }
 
+    fn location(&self) -> OperatorLocation {
+        self.operator.location()
+    }
+
     fn local_id(&self) -> NodeId {
         self.id.local_node_id().unwrap()
     }
@@ -2737,6 +3049,116 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        self.operator.restore(state)
+    }
+}
+
+struct SplitNode<C, I, O1, O2, Op> {
+    id: GlobalNodeId,
+    operator: Op,
+    input_stream: Stream<C, I>,
+    output_stream1: Stream<C, O1>,
+    output_stream2: Stream<C, O2>,
+}
+
+impl<C, I, O1, O2, Op> SplitNode<C, I, O1, O2, Op>
+where
+    Op: SplitOperator<I, O1, O2>,
+    C: Circuit,
+{
+    fn new(operator: Op, input_stream: Stream<C, I>, circuit: C, id: NodeId) -> Self {
+        let global_id = circuit.global_node_id().child(id);
+        let origin2 = global_id.child(NodeId(1));
+
+        Self {
+            id: global_id,
+            operator,
+            input_stream,
+            output_stream1: Stream::new(circuit.clone(), id),
+            output_stream2: Stream::with_origin(circuit, id, origin2),
+        }
+    }
+
+    fn output_streams(&self) -> (Stream<C, O1>, Stream<C, O2>) {
+        (self.output_stream1.clone(), self.output_stream2.clone())
+    }
+}
+
+impl<C, I, O1, O2, Op> Node for SplitNode<C, I, O1, O2, Op>
+where
+    C: Circuit,
+    I: Clone,
+    O1: Clone,
+    O2: Clone,
+    Op: SplitOperator<I, O1, O2>,
+{
+    fn name(&self) -> Cow<'static, str> {
+        self.operator.name()
+    }
+
+    fn location(&self) -> OperatorLocation {
+        self.operator.location()
+    }
+
+    fn local_id(&self) -> NodeId {
+        self.id.local_node_id().unwrap()
+    }
+
+    fn global_id(&self) -> &GlobalNodeId {
+        &self.id
+    }
+
+    fn is_async(&self) -> bool {
+        self.operator.is_async()
+    }
+
+    fn ready(&self) -> bool {
+        self.operator.ready()
+    }
+
+    fn register_ready_callback(&mut self, cb: Box<dyn Fn() + Send + Sync>) {
+        self.operator.register_ready_callback(cb);
+    }
+
+    unsafe fn eval(&mut self) -> Result<(), SchedulerError> {
+        let (output1, output2) = match self.input_stream.take() {
+            Cow::Owned(v) => self.operator.eval_owned(v),
+            Cow::Borrowed(v) => self.operator.eval(v),
+        };
+        self.output_stream1.put(output1);
+        self.output_stream2.put(output2);
+        Ok(())
+    }
+
+    fn clock_start(&mut self, scope: Scope) {
+        self.operator.clock_start(scope);
+    }
+
+    unsafe fn clock_end(&mut self, scope: Scope) {
+        self.operator.clock_end(scope);
+    }
+
+    fn metadata(&self, output: &mut OperatorMeta) {
+        self.operator.metadata(output);
+    }
+
+    fn fixedpoint(&self, scope: Scope) -> bool {
+        self.operator.fixedpoint(scope)
+    }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        self.operator.restore(state)
+    }
 }
 
 struct SinkNode<C, I, Op> {
@@ -2769,6 +3191,10 @@ where
         self.operator.name()
     }
 
+    fn location(&self) -> OperatorLocation {
+        self.operator.location()
+    }
+
     fn local_id(&self) -> NodeId {
         self.id.local_node_id().unwrap()
     }
@@ -2812,6 +3238,14 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        self.operator.restore(state)
+    }
 }
 
 struct BinaryNode<C, I1, I2, O, Op> {
@@ -2864,6 +3298,10 @@ where
         self.operator.name()
     }
 
+    fn location(&self) -> OperatorLocation {
+        self.operator.location()
+    }
+
     fn local_id(&self) -> NodeId {
         self.id.local_node_id().unwrap()
     }
@@ -2927,6 +3365,14 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        self.operator.restore(state)
+    }
 }
 
 struct TernaryNode<C, I1, I2, I3, O, Op> {
@@ -2991,6 +3437,10 @@ where
         self.operator.name()
     }
 
+    fn location(&self) -> OperatorLocation {
+        self.operator.location()
+    }
+
     fn local_id(&self) -> NodeId {
         self.id.local_node_id().unwrap()
     }
@@ -3046,6 +3496,14 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        self.operator.restore(state)
+    }
 }
 
 struct QuaternaryNode<C, I1, I2, I3, I4, O, Op> {
@@ -3122,6 +3580,10 @@ where
         self.operator.name()
     }
 
+    fn location(&self) -> OperatorLocation {
+        self.operator.location()
+    }
+
     fn local_id(&self) -> NodeId {
         self.id.local_node_id().unwrap()
     }
@@ -3183,6 +3645,14 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        self.operator.restore(state)
+    }
 }
 
 struct NaryNode<C, I, O, Op>
@@ -3250,6 +3720,10 @@ where
         self.operator.name()
     }
 
+    fn location(&self) -> OperatorLocation {
+        self.operator.location()
+    }
+
     fn local_id(&self) -> NodeId {
         self.id.local_node_id().unwrap()
     }
@@ -3305,6 +3779,14 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         self.operator.fixedpoint(scope)
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        self.operator.checkpoint()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        self.operator.restore(state)
+    }
 }
 
 // The output half of a feedback node.  We implement a feedback node using a
@@ -3358,6 +3840,10 @@ where
         unsafe { &*self.operator.get() }.name()
     }
 
+    fn location(&self) -> OperatorLocation {
+        unsafe { &*self.operator.get() }.location()
+    }
+
     fn local_id(&self) -> NodeId {
         self.id.local_node_id().unwrap()
     }
@@ -3402,6 +3888,14 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         unsafe { (*self.operator.get()).fixedpoint(scope) }
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        unsafe { (*self.operator.get()).checkpoint() }
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        unsafe { (*self.operator.get()).restore(state) }
+    }
 }
 
 /// The input half of a feedback node
@@ -3437,6 +3931,10 @@ where
         unsafe { &*self.operator.get() }.name()
     }
 
+    fn location(&self) -> OperatorLocation {
+        unsafe { &*self.operator.get() }.location()
+    }
+
     fn local_id(&self) -> NodeId {
         self.id.local_node_id().unwrap()
     }
@@ -3478,6 +3976,15 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         unsafe { (*self.operator.get()).fixedpoint(scope) }
     }
+
+    // The operator is shared with `FeedbackOutputNode`, which checkpoints it.
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
+
+    fn restore(&mut self, _state: &[u8]) -> Result<(), CheckpointError> {
+        Err(CheckpointError::Unsupported)
+    }
 }
 
 /// Input connector of a feedback operator.
@@ -3618,9 +4125,21 @@ where
         self.circuit.inner().fixedpoint(scope + 1)
     }
 
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
+
+    fn restore(&mut self, _state: &[u8]) -> Result<(), CheckpointError> {
+        Err(CheckpointError::Unsupported)
+    }
+
     fn map_nodes_recursive(&self, f: &mut dyn FnMut(&dyn Node)) {
         self.circuit.map_nodes_recursive(f);
     }
+
+    fn map_nodes_recursive_mut(&mut self, f: &mut dyn FnMut(&mut dyn Node)) {
+        self.circuit.map_nodes_recursive_mut(f);
+    }
 }
 
 /// Top-level circuit with executor.
@@ -3662,6 +4181,21 @@ impl CircuitHandle {
         self.executor.run(&self.circuit)
     }
 
+    /// Serializes the state of all operators in the circuit.
+    ///
+    /// Must be invoked between steps.
+    pub(crate) fn checkpoint(&self) -> Result<Vec<u8>, DBSPError> {
+        checkpoint_circuit(&self.circuit)
+    }
+
+    /// Restores the state of all operators in the circuit from a checkpoint
+    /// produced by [`Self::checkpoint`].
+    ///
+    /// Must be invoked before the first step.
+    pub(crate) fn restore(&self, checkpoint: &[u8]) -> Result<(), DBSPError> {
+        restore_circuit(&self.circuit, checkpoint)
+    }
+
     /// Attach a scheduler event handler to the circuit.
     ///
     /// This method is identical to
@@ -3690,13 +4224,26 @@ impl CircuitHandle {
 
 #[cfg(test)]
 mod tests {
+    use super::take_operator_panic_message;
     use crate::{
-        circuit::schedule::{DynamicScheduler, Scheduler, StaticScheduler},
+        circuit::{
+            operator_traits::{Operator, SourceOperator},
+            schedule::{DynamicScheduler, Error as SchedulerError, Scheduler, StaticScheduler},
+            Scope,
+        },
         monitor::TraceMonitor,
-        operator::{Generator, Z1},
-        Circuit, RootCircuit,
+        operator::{FilterMap, Generator, Z1},
+        zset, Circuit, OrdZSet, RootCircuit, Runtime, RuntimeConfig,
+    };
+    use std::{
+        borrow::Cow,
+        cell::{Cell, RefCell},
+        ops::Deref,
+        panic::{catch_unwind, AssertUnwindSafe},
+        rc::Rc,
+        time::Duration,
+        vec::Vec,
     };
-    use std::{cell::RefCell, ops::Deref, rc::Rc, vec::Vec};
 
     // Compute the sum of numbers from 0 to 99.
     #[test]
@@ -3744,6 +4291,150 @@ mod tests {
         assert_eq!(&expected_output, actual_output.borrow().deref());
     }
 
+    // `passthrough` doesn't add nodes to the circuit.
+    #[test]
+    fn passthrough() {
+        let circuit = RootCircuit::build(|circuit| {
+            let mut n = 0;
+            let source = circuit.add_source(Generator::new(move || {
+                n += 1;
+                zset! { n => 1 }
+            }));
+
+            let num_nodes = circuit.num_nodes();
+            let passthrough = source.passthrough();
+            assert_eq!(circuit.num_nodes(), num_nodes);
+            assert!(passthrough.ptr_eq(&source));
+
+            let mut expected = 0;
+            passthrough.inspect(move |zset: &OrdZSet<usize, isize>| {
+                expected += 1;
+                assert_eq!(zset, &zset! { expected => 1 });
+            });
+        })
+        .unwrap()
+        .0;
+
+        for _ in 0..10 {
+            circuit.step().unwrap();
+        }
+    }
+
+    // A cycle without a strict operator is rejected at construction time.
+    #[test]
+    fn cycle_without_strict_operator_static() {
+        cycle_without_strict_operator::<StaticScheduler>();
+    }
+
+    #[test]
+    fn cycle_without_strict_operator_dynamic() {
+        cycle_without_strict_operator::<DynamicScheduler>();
+    }
+
+    fn cycle_without_strict_operator<S>()
+    where
+        S: Scheduler + 'static,
+    {
+        let result = RootCircuit::build_with_scheduler::<_, _, S>(|circuit| {
+            let source = circuit.add_source(Generator::new(|| 1usize));
+            let plus_one = source.apply_named("PlusOne", |x| x + 1);
+            let plus_two = plus_one.apply_named("PlusTwo", |x| x + 2);
+            plus_two.inspect(|_| {});
+
+            // Close the loop `PlusOne -> PlusTwo -> PlusOne`.
+            circuit.add_dependency(plus_two.local_node_id(), plus_one.local_node_id());
+        });
+
+        let err = match result {
+            Err(err) => err,
+            Ok(_) => panic!("circuit with a cycle without a strict operator was accepted"),
+        };
+
+        if let SchedulerError::CycleWithoutStrictOperator { cycle } = &err {
+            let mut names: Vec<_> = cycle.iter().map(|(_, name)| name.to_string()).collect();
+            names.sort();
+            assert_eq!(names, vec!["PlusOne".to_string(), "PlusTwo".to_string()]);
+        } else {
+            panic!("unexpected error: {err}");
+        }
+
+        let message = err.to_string();
+        assert!(message.contains("cycle without a strict operator"));
+        assert!(message.contains("'PlusOne'"));
+        assert!(message.contains("'PlusTwo'"));
+    }
+
+    /// Async source operator that never becomes ready.
+    struct NeverReady;
+
+    impl Operator for NeverReady {
+        fn name(&self) -> Cow<'static, str> {
+            Cow::from("NeverReady")
+        }
+
+        fn is_async(&self) -> bool {
+            true
+        }
+
+        fn ready(&self) -> bool {
+            false
+        }
+
+        fn fixedpoint(&self, _scope: Scope) -> bool {
+            true
+        }
+    }
+
+    impl SourceOperator<usize> for NeverReady {
+        fn eval(&mut self) -> usize {
+            unreachable!()
+        }
+    }
+
+    // A circuit waiting for an async operator that never becomes ready
+    // fails with `Stalled` when stall detection is enabled.
+    #[test]
+    fn stalled_circuit_static() {
+        stalled_circuit::<StaticScheduler>();
+    }
+
+    #[test]
+    fn stalled_circuit_dynamic() {
+        stalled_circuit::<DynamicScheduler>();
+    }
+
+    fn stalled_circuit<S>()
+    where
+        S: Scheduler + 'static,
+    {
+        let config = RuntimeConfig::default().with_stall_timeout(Some(Duration::from_millis(100)));
+
+        Runtime::run_with_config(1, config, || {
+            let circuit = RootCircuit::build_with_scheduler::<_, _, S>(|circuit| {
+                circuit.add_source(NeverReady).inspect(|_| {});
+                circuit
+                    .add_source(Generator::new(|| 1usize))
+                    .inspect(|_| {});
+            })
+            .unwrap()
+            .0;
+
+            let err = circuit.step().unwrap_err();
+            if let SchedulerError::Stalled { pending_nodes } = &err {
+                let names: Vec<_> = pending_nodes
+                    .iter()
+                    .map(|(_, name)| name.as_ref())
+                    .collect();
+                assert_eq!(names, vec!["NeverReady"]);
+            } else {
+                panic!("unexpected error: {err}");
+            }
+            assert!(err.to_string().contains("'NeverReady'"));
+        })
+        .join()
+        .unwrap();
+    }
+
     // Recursive circuit
     #[test]
     fn recursive_sum_circuit_static() {
@@ -3863,4 +4554,65 @@ mod tests {
             n * my_factorial(n - 1)
         }
     }
+
+    #[test]
+    fn operator_panic_context() {
+        let circuit = RootCircuit::build(|circuit| {
+            circuit
+                .add_source(Generator::new(|| zset! { 1usize => 1isize }))
+                .map(|_x| -> usize { panic!("malformed record") });
+        })
+        .unwrap()
+        .0;
+
+        let panic = catch_unwind(AssertUnwindSafe(|| circuit.step())).unwrap_err();
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"malformed record"));
+        assert_eq!(
+            take_operator_panic_message().unwrap(),
+            "panic in operator 'Map' ([1]): malformed record"
+        );
+    }
+
+    #[test]
+    fn operator_panic_location() {
+        let circuit = RootCircuit::build(|circuit| {
+            circuit
+                .add_source(Generator::new(|| 1usize))
+                .apply(|_x| -> usize { panic!("malformed record") });
+        })
+        .unwrap()
+        .0;
+
+        let panic = catch_unwind(AssertUnwindSafe(|| circuit.step())).unwrap_err();
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"malformed record"));
+        let message = take_operator_panic_message().unwrap();
+        assert!(message.starts_with(&format!("panic in operator 'Apply' ([1]) at {}:", file!())));
+        assert!(message.ends_with(": malformed record"));
+    }
+
+    // A panic caught and discarded by the caller is not reported by later
+    // evaluations of the circuit.
+    #[test]
+    fn operator_panic_context_reset() {
+        let fail = Rc::new(Cell::new(true));
+        let fail_clone = fail.clone();
+        let circuit = RootCircuit::build(move |circuit| {
+            circuit
+                .add_source(Generator::new(|| 1usize))
+                .apply(move |x| -> usize {
+                    if fail_clone.get() {
+                        panic!("malformed record")
+                    }
+                    *x
+                });
+        })
+        .unwrap()
+        .0;
+
+        assert!(catch_unwind(AssertUnwindSafe(|| circuit.step())).is_err());
+
+        fail.set(false);
+        circuit.step().unwrap();
+        assert_eq!(take_operator_panic_message(), None);
+    }
 }
@@ -1,12 +1,27 @@
 use crate::{
-    circuit::runtime::RuntimeHandle, profile::Profiler, Error as DBSPError, RootCircuit, Runtime,
-    RuntimeError, SchedulerError,
+    circuit::{
+        checkpoint::{read_checkpoint, write_checkpoint},
+        runtime::RuntimeHandle,
+        GlobalNodeId,
+    },
+    operator::{NamedInputsId, NamedOutputsId},
+    profile::{Profile, Profiler},
+    trace::Batch,
+    Error as DBSPError, NamedInputHandle, NamedOutputHandle, RootCircuit, Runtime, RuntimeConfig,
+    RuntimeError, SchedulerError, Transaction,
 };
-use crossbeam::channel::{bounded, Receiver, Sender, TryRecvError};
+use crossbeam::channel::{bounded, Receiver, RecvError, SendError, Sender, TryRecvError};
 use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
     fs,
-    fs::create_dir_all,
+    fs::{create_dir_all, File},
+    future::Future,
+    io::{BufWriter, Write},
     path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
     thread::Result as ThreadResult,
     time::Instant,
 };
@@ -34,6 +49,55 @@ impl Runtime {
     /// TODO: Document other requirements.  Not all operators are currently
     /// thread-safe.
     pub fn init_circuit<F, T>(nworkers: usize, constructor: F) -> Result<(DBSPHandle, T), DBSPError>
+    where
+        F: FnOnce(&mut RootCircuit) -> T + Clone + Send + 'static,
+        T: Clone + Send + 'static,
+    {
+        Self::init_circuit_with_config(nworkers, RuntimeConfig::default(), constructor)
+    }
+
+    /// Instantiate a circuit in a multithreaded runtime and restore its state
+    /// from a checkpoint.
+    ///
+    /// Builds the circuit exactly like [`Runtime::init_circuit`] and then
+    /// restores the state of its operators from the checkpoint stored in
+    /// directory `path` by [`DBSPHandle::checkpoint`].  The circuit must have
+    /// the same structure and the runtime the same number of workers as the
+    /// circuit that the checkpoint was taken from; otherwise the checkpoint
+    /// is rejected.
+    ///
+    /// Inputs fed to the circuit after the checkpoint was taken are not part
+    /// of the checkpoint.  The application must resume feeding inputs from
+    /// the position it was at when the checkpoint was taken.  To help with
+    /// that, the checkpoint includes the watermarks of all input handles
+    /// (see [`InputHandle::set_watermark`](`crate::InputHandle::set_watermark`)),
+    /// which the application can read back from the handles returned by
+    /// `constructor`.
+    pub fn restore_circuit<P, F, T>(
+        nworkers: usize,
+        path: P,
+        constructor: F,
+    ) -> Result<(DBSPHandle, T), DBSPError>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&mut RootCircuit) -> T + Clone + Send + 'static,
+        T: Clone + Send + 'static,
+    {
+        let checkpoints = read_checkpoint(path.as_ref(), nworkers)?;
+        let (mut dbsp, res) = Self::init_circuit(nworkers, constructor)?;
+
+        // On error, `dbsp` is dropped, which kills the runtime.
+        dbsp.restore(checkpoints)?;
+        Ok((dbsp, res))
+    }
+
+    /// Like [`Runtime::init_circuit`], but instantiates the runtime with
+    /// the specified configuration (see [`Runtime::run_with_config`]).
+    pub fn init_circuit_with_config<F, T>(
+        nworkers: usize,
+        config: RuntimeConfig,
+        constructor: F,
+    ) -> Result<(DBSPHandle, T), DBSPError>
     where
         F: FnOnce(&mut RootCircuit) -> T + Clone + Send + 'static,
         T: Clone + Send + 'static,
@@ -52,13 +116,19 @@ impl Runtime {
         let (status_senders, status_receivers): (Vec<_>, Vec<_>) =
             (0..nworkers).map(|_| bounded(1)).unzip();
 
-        let runtime = Self::run(nworkers, move || {
+        // Waker of the task awaiting command completion in `DBSPHandle::step_async`.
+        let status_waker = StatusWaker::default();
+
+        let runtime = Self::run_with_config(nworkers, config, move || {
             let worker_index = Runtime::worker_index();
 
             // Drop all but one channels.  This makes sure that if one of the worker panics
             // or exits, its channel will become disconnected.
             let init_sender = init_senders.into_iter().nth(worker_index).unwrap();
-            let status_sender = status_senders.into_iter().nth(worker_index).unwrap();
+            let status_sender = StatusSender::new(
+                status_senders.into_iter().nth(worker_index).unwrap(),
+                status_waker.clone(),
+            );
             let command_receiver = command_receivers.into_iter().nth(worker_index).unwrap();
 
             let (circuit, profiler) = match RootCircuit::build(|circuit| {
@@ -99,6 +169,60 @@ impl Runtime {
                             return;
                         }
                     }
+                    Ok(Command::EnableScheduleTrace) => {
+                        profiler.enable_schedule_trace();
+                        if status_sender.send(Ok(Response::Unit)).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(Command::TakeScheduleTrace) => {
+                        if status_sender
+                            .send(Ok(Response::ScheduleTrace(profiler.take_schedule_trace())))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                    Ok(Command::MemoryUsage) => {
+                        if status_sender
+                            .send(Ok(Response::MemoryUsage(profiler.allocated_bytes())))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                    Ok(Command::OperatorMemoryUsage) => {
+                        if status_sender
+                            .send(Ok(Response::OperatorMemoryUsage(
+                                profiler.operator_allocated_bytes(),
+                            )))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                    Ok(Command::Checkpoint) => {
+                        if status_sender
+                            .send(Ok(Response::Checkpoint(circuit.checkpoint())))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                    Ok(Command::Restore(checkpoints)) => {
+                        let status = circuit.restore(&checkpoints[worker_index]);
+                        if status_sender.send(Ok(Response::Restore(status))).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(Command::RetrieveProfile) => {
+                        if status_sender
+                            .send(Ok(Response::CpuProfile(profiler.profile())))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
                     Ok(Command::DumpProfile) => {
                         if status_sender
                             .send(Ok(Response::Profile(profiler.dump_profile())))
@@ -131,26 +255,55 @@ impl Runtime {
         for (worker, receiver) in init_receivers.iter().enumerate() {
             match receiver.recv() {
                 Ok(Err(scheduler_error)) => {
-                    init_status.push(Err(DBSPError::Scheduler(scheduler_error)))
+                    init_status.push(Err(Some(DBSPError::Scheduler(scheduler_error))))
                 }
                 Ok(Ok(ret)) => init_status.push(Ok(ret)),
-                Err(_) => {
-                    init_status.push(Err(DBSPError::Runtime(RuntimeError::WorkerPanic(worker))))
-                }
+                Err(_) => init_status.push(Err(None)),
             }
         }
 
         // On error, kill the runtime.
-        if init_status.iter().any(Result::is_err) {
-            let error = init_status
-                .into_iter()
-                .find_map(|status| status.err())
-                .unwrap();
+        if let Some(worker) = init_status.iter().position(Result::is_err) {
+            let error = init_status.swap_remove(worker).err().unwrap();
+            let rt = runtime.runtime().clone();
             let _ = runtime.kill();
-            return Err(error);
+
+            // The worker's panic info is available once its thread has been
+            // joined by `kill`.
+            return Err(error.unwrap_or_else(|| {
+                DBSPError::Runtime(RuntimeError::WorkerPanic {
+                    worker,
+                    panic_info: rt.panic_info(worker),
+                })
+            }));
         }
 
-        let dbsp = DBSPHandle::new(runtime, command_senders, status_receivers);
+        // Input handles registered via `RootCircuit::add_input_*_named`.
+        let inputs = runtime
+            .runtime()
+            .local_store()
+            .entry(NamedInputsId)
+            .or_insert_with(HashMap::new)
+            .value()
+            .clone();
+
+        // Output handles registered via `Stream::output_named`.
+        let outputs = runtime
+            .runtime()
+            .local_store()
+            .entry(NamedOutputsId)
+            .or_insert_with(HashMap::new)
+            .value()
+            .clone();
+
+        let dbsp = DBSPHandle::new(
+            runtime,
+            command_senders,
+            status_receivers,
+            status_waker,
+            inputs,
+            outputs,
+        );
 
         // `constructor` should return identical results in all workers.  Use
         // worker 0 output.
@@ -163,11 +316,99 @@ enum Command {
     Step,
     EnableProfiler,
     DumpProfile,
+    RetrieveProfile,
+    MemoryUsage,
+    OperatorMemoryUsage,
+    EnableScheduleTrace,
+    TakeScheduleTrace,
+    Checkpoint,
+    // Checkpoints of all workers, indexed by worker.
+    Restore(Arc<Vec<Vec<u8>>>),
 }
 
 enum Response {
     Unit,
     Profile(String),
+    CpuProfile(Profile),
+    MemoryUsage(usize),
+    OperatorMemoryUsage(Vec<(GlobalNodeId, Cow<'static, str>, usize)>),
+    ScheduleTrace(Vec<ScheduleEvent>),
+    // Checkpointing and restoring failures are reported as responses rather
+    // than errors, which kill the runtime.
+    Checkpoint(Result<Vec<u8>, DBSPError>),
+    Restore(Result<(), DBSPError>),
+}
+
+type Status = Result<Response, SchedulerError>;
+
+/// Waker shared by all workers of a runtime, used to notify an async task
+/// awaiting worker responses.
+type StatusWaker = Arc<Mutex<Option<Waker>>>;
+
+/// Worker end of a status channel.
+///
+/// Wakes up the async task waiting for worker responses, if any, after
+/// sending each response and when the worker exits (including by panicking),
+/// so that the task can observe the disconnected channel.
+struct StatusSender {
+    sender: Option<Sender<Status>>,
+    waker: StatusWaker,
+}
+
+impl StatusSender {
+    fn new(sender: Sender<Status>, waker: StatusWaker) -> Self {
+        Self {
+            sender: Some(sender),
+            waker,
+        }
+    }
+
+    fn send(&self, status: Status) -> Result<(), SendError<Status>> {
+        let result = self.sender.as_ref().unwrap().send(status);
+        self.wake();
+        result
+    }
+
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().unwrap().as_ref() {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+impl Drop for StatusSender {
+    fn drop(&mut self) {
+        // Disconnect the channel before waking up the receiver.
+        self.sender.take();
+        self.wake();
+    }
+}
+
+/// Future that resolves to the next response received from a worker.
+struct StatusFuture<'a> {
+    receiver: &'a Receiver<Status>,
+    waker: &'a StatusWaker,
+}
+
+impl<'a> Future for StatusFuture<'a> {
+    type Output = Result<Status, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let try_recv = || match self.receiver.try_recv() {
+            Ok(status) => Poll::Ready(Ok(status)),
+            Err(TryRecvError::Disconnected) => Poll::Ready(Err(RecvError)),
+            Err(TryRecvError::Empty) => Poll::Pending,
+        };
+
+        if let Poll::Ready(result) = try_recv() {
+            return Poll::Ready(result);
+        }
+
+        // Register the waker and check the channel again, in case the
+        // worker responded before the waker was registered.
+        *self.waker.lock().unwrap() = Some(cx.waker().clone());
+        try_recv()
+    }
 }
 
 /// A handle to control the execution of a circuit in a multithreaded runtime.
@@ -180,20 +421,38 @@ pub struct DBSPHandle {
     command_senders: Vec<Sender<Command>>,
     // Channels used to receive command completion status from
     // workers.
-    status_receivers: Vec<Receiver<Result<Response, SchedulerError>>>,
+    status_receivers: Vec<Receiver<Status>>,
+    // Waker notified by workers after sending a response.
+    status_waker: StatusWaker,
+    // Memory limit configured via `set_memory_limit`.
+    memory_limit: Option<usize>,
+    // Input handles created via `RootCircuit::add_input_*_named`.
+    inputs: HashMap<String, NamedInputHandle>,
+    // Output handles created via `Stream::output_named`.
+    outputs: HashMap<String, NamedOutputHandle>,
+    // Output recorder configured via `record_outputs` or `verify_outputs`.
+    output_recorder: Option<OutputRecorder>,
 }
 
 impl DBSPHandle {
     fn new(
         runtime: RuntimeHandle,
         command_senders: Vec<Sender<Command>>,
-        status_receivers: Vec<Receiver<Result<Response, SchedulerError>>>,
+        status_receivers: Vec<Receiver<Status>>,
+        status_waker: StatusWaker,
+        inputs: HashMap<String, NamedInputHandle>,
+        outputs: HashMap<String, NamedOutputHandle>,
     ) -> Self {
         Self {
             start_time: Instant::now(),
             runtime: Some(runtime),
             command_senders,
             status_receivers,
+            status_waker,
+            memory_limit: None,
+            inputs,
+            outputs,
+            output_recorder: None,
         }
     }
 
@@ -203,38 +462,87 @@ impl DBSPHandle {
         self.runtime.take().unwrap().kill()
     }
 
-    fn broadcast_command<F>(&mut self, command: Command, mut handler: F) -> Result<(), DBSPError>
-    where
-        F: FnMut(Response),
-    {
+    /// Kill the runtime after `worker` has panicked and return the
+    /// corresponding error.
+    fn worker_panic(&mut self, worker: usize) -> DBSPError {
+        let runtime = self.runtime.as_ref().unwrap().runtime().clone();
+        let _ = self.kill_inner();
+
+        // The worker's panic info is available once its thread has been
+        // joined by `kill_inner`.
+        DBSPError::Runtime(RuntimeError::WorkerPanic {
+            worker,
+            panic_info: runtime.panic_info(worker),
+        })
+    }
+
+    fn send_command(&mut self, command: Command) -> Result<(), DBSPError> {
         if self.runtime.is_none() {
             return Err(DBSPError::Runtime(RuntimeError::Killed));
         }
 
-        // Send command.
         for (worker, sender) in self.command_senders.iter().enumerate() {
             if matches!(sender.send(command.clone()), Err(_)) {
-                let _ = self.kill_inner();
-                return Err(DBSPError::Runtime(RuntimeError::WorkerPanic(worker)));
+                return Err(self.worker_panic(worker));
             }
             self.runtime.as_ref().unwrap().unpark_worker(worker);
         }
 
+        Ok(())
+    }
+
+    /// Process a response received from `worker`; kill the runtime on error.
+    fn handle_response(
+        &mut self,
+        worker: usize,
+        response: Result<Status, RecvError>,
+    ) -> Result<Response, DBSPError> {
+        match response {
+            Err(_) => Err(self.worker_panic(worker)),
+            Ok(Err(e)) => {
+                let _ = self.kill_inner();
+                Err(DBSPError::Scheduler(e))
+            }
+            Ok(Ok(resp)) => Ok(resp),
+        }
+    }
+
+    fn broadcast_command<F>(&mut self, command: Command, mut handler: F) -> Result<(), DBSPError>
+    where
+        F: FnMut(Response),
+    {
+        self.send_command(command)?;
+
         // Receive responses.
-        for (worker, receiver) in self.status_receivers.iter().enumerate() {
-            match receiver.recv() {
-                Err(_) => {
-                    let _ = self.kill_inner();
-                    return Err(DBSPError::Runtime(RuntimeError::WorkerPanic(worker)));
-                }
-                Ok(Err(e)) => {
-                    let _ = self.kill_inner();
-                    return Err(DBSPError::Scheduler(e));
-                }
-                Ok(Ok(resp)) => handler(resp),
+        for worker in 0..self.status_receivers.len() {
+            let response = self.status_receivers[worker].recv();
+            handler(self.handle_response(worker, response)?);
+        }
+
+        Ok(())
+    }
+
+    async fn broadcast_command_async<F>(
+        &mut self,
+        command: Command,
+        mut handler: F,
+    ) -> Result<(), DBSPError>
+    where
+        F: FnMut(Response),
+    {
+        self.send_command(command)?;
+
+        for worker in 0..self.status_receivers.len() {
+            let response = StatusFuture {
+                receiver: &self.status_receivers[worker],
+                waker: &self.status_waker,
             }
+            .await;
+            handler(self.handle_response(worker, response)?);
         }
 
+        *self.status_waker.lock().unwrap() = None;
+
         Ok(())
     }
 
@@ -243,8 +551,331 @@ impl DBSPHandle {
     }
 
     /// Evaluate the circuit for one clock cycle.
+    ///
+    /// If a memory limit is set (see [`Self::set_memory_limit`]) and the
+    /// circuit exceeds it after the step, the circuit is killed and the
+    /// method returns
+    /// [`SchedulerError::MemoryLimitExceeded`](`crate::SchedulerError::MemoryLimitExceeded`).
     pub fn step(&mut self) -> Result<(), DBSPError> {
-        self.broadcast_command(Command::Step, |_| {})
+        self.broadcast_command(Command::Step, |_| {})?;
+
+        if self.memory_limit.is_some() {
+            let allocated = self.allocated_bytes()?;
+            self.check_memory_limit(allocated)?;
+        }
+
+        self.record_or_verify_outputs()
+    }
+
+    /// Evaluate the circuit for one clock cycle without blocking the calling
+    /// thread.
+    ///
+    /// Returns a future that completes when all workers have finished
+    /// evaluating the circuit.  The future can be awaited from an async
+    /// executor, e.g., a tokio runtime, without resorting to
+    /// `spawn_blocking`: while the step is in progress, the task yields to
+    /// the executor and gets woken up as workers report completion.
+    ///
+    /// Note that this only affects the calling thread.  The circuit is still
+    /// evaluated by the worker threads of the runtime, with each worker
+    /// evaluating its operators sequentially, exactly as in [`Self::step`].
+    /// Dropping the future before it completes leaves the runtime in an
+    /// undefined state; the handle should be killed in this case.
+    pub async fn step_async(&mut self) -> Result<(), DBSPError> {
+        self.broadcast_command_async(Command::Step, |_| {}).await?;
+
+        if self.memory_limit.is_some() {
+            let mut allocated = 0;
+            self.broadcast_command_async(Command::MemoryUsage, |resp| {
+                if let Response::MemoryUsage(bytes) = resp {
+                    allocated += bytes;
+                }
+            })
+            .await?;
+            self.check_memory_limit(allocated)?;
+        }
+
+        self.record_or_verify_outputs()
+    }
+
+    /// Limit the amount of memory the circuit can use.
+    ///
+    /// After each step, the handle computes the total amount of memory
+    /// allocated by the operators of the circuit across all workers (see
+    /// [`Self::allocated_bytes`]).  If this amount exceeds `limit` bytes,
+    /// the circuit is killed and the step returns
+    /// [`SchedulerError::MemoryLimitExceeded`](`crate::SchedulerError::MemoryLimitExceeded`),
+    /// giving the application a chance to fail gracefully instead of
+    /// running out of memory.  `None` removes the limit.
+    ///
+    /// Note that the limit is only checked between steps, so the circuit can
+    /// temporarily exceed it while evaluating a step.  Computing the memory
+    /// footprint of the circuit requires traversing all of its state, which
+    /// adds overhead to each step.
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory_limit = limit;
+    }
+
+    /// Returns the total number of bytes allocated by the operators of the
+    /// circuit in all workers.
+    ///
+    /// This is the sum of `allocated bytes` values reported in the circuit
+    /// profile (see [`Self::dump_profile`]) by all operators that maintain
+    /// state, e.g., traces.
+    pub fn allocated_bytes(&mut self) -> Result<usize, DBSPError> {
+        let mut allocated = 0;
+
+        self.broadcast_command(Command::MemoryUsage, |resp| {
+            if let Response::MemoryUsage(bytes) = resp {
+                allocated += bytes;
+            }
+        })?;
+
+        Ok(allocated)
+    }
+
+    /// Returns the number of bytes allocated by each operator of the circuit
+    /// that maintains state, e.g., traces, summed over all workers.
+    ///
+    /// Returns a `(node id, operator name, bytes)` triple per operator.  This
+    /// is a per-operator breakdown of the total returned by
+    /// [`Self::allocated_bytes`], useful for finding the operators that
+    /// consume most of the memory, and is just as expensive to compute.
+    #[allow(clippy::type_complexity)]
+    pub fn memory_usage(
+        &mut self,
+    ) -> Result<Vec<(GlobalNodeId, Cow<'static, str>, usize)>, DBSPError> {
+        let mut usage: Vec<(GlobalNodeId, Cow<'static, str>, usize)> = Vec::new();
+        // Index of each node in `usage`.
+        let mut nodes = HashMap::new();
+
+        self.broadcast_command(Command::OperatorMemoryUsage, |resp| {
+            if let Response::OperatorMemoryUsage(worker_usage) = resp {
+                for (node_id, name, bytes) in worker_usage {
+                    match nodes.get(&node_id) {
+                        Some(&index) => usage[index].2 += bytes,
+                        None => {
+                            nodes.insert(node_id.clone(), usage.len());
+                            usage.push((node_id, name, bytes));
+                        }
+                    }
+                }
+            }
+        })?;
+
+        Ok(usage)
+    }
+
+    /// Kill the circuit if `allocated` exceeds the memory limit.
+    fn check_memory_limit(&mut self, allocated: usize) -> Result<(), DBSPError> {
+        match self.memory_limit {
+            Some(limit) if allocated > limit => {
+                let _ = self.kill_inner();
+                Err(DBSPError::Scheduler(SchedulerError::MemoryLimitExceeded {
+                    limit,
+                    allocated,
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Save the state of the circuit to directory `path`.
+    ///
+    /// Must be invoked between steps.  Writes the state of all operators in
+    /// all workers to a new directory `path`, which must not exist.  The
+    /// circuit can later be reconstructed from the checkpoint, e.g., after a
+    /// restart, using [`Runtime::restore_circuit`].  The checkpoint is
+    /// stop-the-world: all workers are blocked while it is taken.
+    ///
+    /// Traces and other batches, which hold the bulk of the state of most
+    /// circuits, can only be checkpointed when the crate is built with the
+    /// `persistence` feature, which makes all data types serializable.
+    /// Operators whose state cannot be serialized fail the checkpoint with
+    /// [`Error::Checkpoint`](`crate::Error::Checkpoint`), unless their state
+    /// is empty.  This includes operators that keep state in closures, such
+    /// as [`Stream::apply_stateful`](`crate::Stream::apply_stateful`) and
+    /// [`Generator`](`crate::operator::Generator`).  The checkpoint also
+    /// includes the watermarks of all input handles (see
+    /// [`InputHandle::set_watermark`](`crate::InputHandle::set_watermark`)).
+    ///
+    /// A failed checkpoint does not affect the circuit, which can continue
+    /// running.
+    pub fn checkpoint<P: AsRef<Path>>(&mut self, path: P) -> Result<(), DBSPError> {
+        let mut checkpoints = Vec::with_capacity(self.num_workers());
+        let mut error = None;
+
+        self.broadcast_command(Command::Checkpoint, |resp| {
+            if let Response::Checkpoint(checkpoint) = resp {
+                match checkpoint {
+                    Ok(checkpoint) => checkpoints.push(checkpoint),
+                    Err(e) => {
+                        error.get_or_insert(e);
+                    }
+                }
+            }
+        })?;
+
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        write_checkpoint(path.as_ref(), &checkpoints)
+    }
+
+    /// Restore the state of the circuit from the checkpoints of all workers.
+    fn restore(&mut self, checkpoints: Vec<Vec<u8>>) -> Result<(), DBSPError> {
+        let mut error = None;
+
+        self.broadcast_command(Command::Restore(Arc::new(checkpoints)), |resp| {
+            if let Response::Restore(Err(e)) = resp {
+                error.get_or_insert(e);
+            }
+        })?;
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns all input handles created using
+    /// [`RootCircuit::add_input_zset_named`] and
+    /// [`RootCircuit::add_input_indexed_zset_named`], indexed by name.
+    pub fn inputs(&self) -> &HashMap<String, NamedInputHandle> {
+        &self.inputs
+    }
+
+    /// Apply updates to multiple named inputs atomically.
+    ///
+    /// Calls `f` with a [`Transaction`] object, which buffers updates to
+    /// named inputs added via [`Transaction::input`].  Once `f` returns, all
+    /// buffered updates are pushed to their respective inputs.  Since this
+    /// method and [`Self::step`] both require exclusive access to the
+    /// handle, the commit cannot interleave with a step: the next clock
+    /// cycle observes all updates in the transaction, and a step never
+    /// observes a part of a transaction.  If `f` panics, none of the
+    /// updates are applied.
+    ///
+    /// This only applies to updates pushed via the transaction.  Updates
+    /// pushed directly through (clones of) the input handles from other
+    /// threads are not synchronized with the transaction.
+    ///
+    /// ```text
+    /// dbsp.transaction(|txn| {
+    ///     txn.input("a").append(&mut a_updates);
+    ///     txn.input("b").append(&mut b_updates);
+    /// });
+    /// dbsp.step()?;
+    /// ```
+    pub fn transaction<F, T>(&mut self, f: F) -> T
+    where
+        F: FnOnce(&mut Transaction<'_>) -> T,
+    {
+        let mut txn = Transaction::new(&self.inputs);
+        let result = f(&mut txn);
+        txn.commit();
+        result
+    }
+
+    /// Returns all output handles created using
+    /// [`Stream::output_named`](`crate::Stream::output_named`), indexed by
+    /// name.
+    pub fn outputs(&self) -> &HashMap<String, NamedOutputHandle> {
+        &self.outputs
+    }
+
+    /// Read batches produced by all workers during the last clock cycle
+    /// from the output named `name` and consolidate them into a single batch.
+    ///
+    /// Returns `None` if there is no output with this name or if the output
+    /// stream doesn't carry batches of type `B`.  See
+    /// [`OutputHandle::consolidate`](`crate::OutputHandle::consolidate`).
+    pub fn take_output<B>(&self, name: &str) -> Option<B>
+    where
+        B: Batch<Time = ()> + Send,
+    {
+        Some(self.outputs.get(name)?.downcast::<B>()?.consolidate())
+    }
+
+    /// Record the outputs of the circuit to a file for regression testing.
+    ///
+    /// After each subsequent step, the handle writes the contents of all
+    /// named outputs (see [`Stream::output_named`](`crate::Stream::output_named`))
+    /// produced by each worker during the step to `path`, using their
+    /// `Debug` representation.  A later run of the same circuit with the
+    /// same inputs can be checked against the recording using
+    /// [`Self::verify_outputs`].  Steps are numbered starting from 0 at
+    /// the first step after this call.
+    ///
+    /// Recording does not consume the outputs, so they can still be read
+    /// via output handles after each step.
+    pub fn record_outputs<P: AsRef<Path>>(&mut self, path: P) -> Result<(), DBSPError> {
+        let file = BufWriter::new(File::create(path)?);
+
+        self.output_recorder = Some(OutputRecorder {
+            step: 0,
+            mode: RecorderMode::Record(file),
+        });
+        Ok(())
+    }
+
+    /// Compare the outputs of the circuit against a recording made with
+    /// [`Self::record_outputs`].
+    ///
+    /// After each subsequent step, the contents of all named outputs are
+    /// compared with the corresponding step of the recording.  If they
+    /// differ, [`Self::step`] returns
+    /// [`Error::OutputMismatch`](`crate::Error::OutputMismatch`), which
+    /// reports the step number and the recorded lines missing from the
+    /// actual outputs and vice versa.  The step itself has completed by
+    /// then: its outputs can still be read, and later steps are verified as
+    /// usual.  Every step produces a line per named output per worker, so
+    /// running more steps than were recorded fails verification.
+    ///
+    /// Since outputs are recorded per worker, the recording can only be
+    /// verified using a runtime with the same number of workers.
+    pub fn verify_outputs<P: AsRef<Path>>(&mut self, path: P) -> Result<(), DBSPError> {
+        let mut steps: Vec<Vec<String>> = Vec::new();
+
+        for line in fs::read_to_string(path)?.lines() {
+            if is_step_header(line) {
+                steps.push(Vec::new());
+            } else if let Some(step) = steps.last_mut() {
+                step.push(line.to_string());
+            } else {
+                return Err(DBSPError::Custom(format!(
+                    "malformed output recording: unexpected line '{line}'"
+                )));
+            }
+        }
+
+        self.output_recorder = Some(OutputRecorder {
+            step: 0,
+            mode: RecorderMode::Verify(steps),
+        });
+        Ok(())
+    }
+
+    /// Record or verify outputs produced by the last step, if enabled.
+    fn record_or_verify_outputs(&mut self) -> Result<(), DBSPError> {
+        if self.output_recorder.is_none() {
+            return Ok(());
+        }
+
+        // One line per output per worker, sorted by output name.
+        let mut names: Vec<&String> = self.outputs.keys().collect();
+        names.sort();
+
+        let mut snapshot = Vec::new();
+        for name in names {
+            for (worker, val) in self.outputs[name].snapshot().into_iter().enumerate() {
+                let val = val.replace('\\', "\\\\").replace('\n', "\\n");
+                snapshot.push(format!("{name}[{worker}]: {val}"));
+            }
+        }
+
+        self.output_recorder.as_mut().unwrap().step(snapshot)
     }
 
     /// Enable CPU profiler.
@@ -285,6 +916,51 @@ impl DBSPHandle {
         Ok(dir_path)
     }
 
+    /// Returns the number of invocations and the total evaluation time of
+    /// each operator, summed over all workers.
+    ///
+    /// Operators are only profiled while the CPU profiler is enabled (see
+    /// [`Self::enable_cpu_profiler`]); otherwise the returned profile is
+    /// empty.  Profiling has no overhead until it is enabled.
+    pub fn profile(&mut self) -> Result<Profile, DBSPError> {
+        let mut profile = Profile::default();
+
+        self.broadcast_command(Command::RetrieveProfile, |resp| {
+            if let Response::CpuProfile(worker_profile) = resp {
+                profile.merge(worker_profile);
+            }
+        })?;
+
+        Ok(profile)
+    }
+
+    /// Start recording scheduler events in all workers.
+    ///
+    /// Records clock, step, wait, and operator evaluation events, along with
+    /// their timestamps, until the trace is retrieved with
+    /// [`Self::take_schedule_trace`].  This is useful for debugging
+    /// scheduling stalls, e.g., with async operators.  Recording has no
+    /// overhead until this method is invoked.
+    pub fn capture_schedule_trace(&mut self) -> Result<(), DBSPError> {
+        self.broadcast_command(Command::EnableScheduleTrace, |_| {})
+    }
+
+    /// Stop recording scheduler events and return events recorded by all
+    /// workers since the last call to [`Self::capture_schedule_trace`].
+    ///
+    /// Use [`ScheduleTrace::to_json`] to export the trace for visualization.
+    pub fn take_schedule_trace(&mut self) -> Result<ScheduleTrace, DBSPError> {
+        let mut events = Vec::new();
+
+        self.broadcast_command(Command::TakeScheduleTrace, |resp| {
+            if let Response::ScheduleTrace(worker_events) = resp {
+                events.extend(worker_events);
+            }
+        })?;
+
+        Ok(ScheduleTrace::new(events))
+    }
+
     /// Terminate the execution of the circuit, exiting all worker threads.
     ///
     /// If one or more of the worker threads panics, returns the argument the
@@ -310,9 +986,92 @@ impl Drop for DBSPHandle {
     }
 }
 
+/// Output recorder configured by [`DBSPHandle::record_outputs`] or
+/// [`DBSPHandle::verify_outputs`].
+#[derive(Debug)]
+struct OutputRecorder {
+    // Number of steps recorded or verified so far.
+    step: u64,
+    mode: RecorderMode,
+}
+
+#[derive(Debug)]
+enum RecorderMode {
+    Record(BufWriter<File>),
+    // Expected outputs of each step.
+    Verify(Vec<Vec<String>>),
+}
+
+impl OutputRecorder {
+    /// Record or verify the outputs of the next step.
+    fn step(&mut self, snapshot: Vec<String>) -> Result<(), DBSPError> {
+        let step = self.step;
+        self.step += 1;
+
+        match &mut self.mode {
+            RecorderMode::Record(file) => {
+                writeln!(file, "step {step}")?;
+                for line in snapshot {
+                    writeln!(file, "{line}")?;
+                }
+                file.flush()?;
+            }
+            RecorderMode::Verify(steps) => {
+                let expected = steps.get(step as usize).map(Vec::as_slice).unwrap_or(&[]);
+                if expected != snapshot.as_slice() {
+                    return Err(DBSPError::OutputMismatch {
+                        step,
+                        diff: diff_lines(expected, &snapshot),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns `true` if `line` starts a new step in an output recording.
+fn is_step_header(line: &str) -> bool {
+    line.strip_prefix("step ")
+        .map_or(false, |step| step.parse::<u64>().is_ok())
+}
+
+/// Lists lines of `expected` missing from `actual`, prefixed with `-`,
+/// followed by lines of `actual` missing from `expected`, prefixed with `+`.
+fn diff_lines(expected: &[String], actual: &[String]) -> String {
+    let expected_set: HashSet<&String> = expected.iter().collect();
+    let actual_set: HashSet<&String> = actual.iter().collect();
+
+    let removed = expected
+        .iter()
+        .filter(|line| !actual_set.contains(line))
+        .map(|line| format!("- {line}\n"));
+    let added = actual
+        .iter()
+        .filter(|line| !expected_set.contains(line))
+        .map(|line| format!("+ {line}\n"));
+
+    removed.chain(added).collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{operator::Generator, Circuit, Error as DBSPError, Runtime, RuntimeError};
+    #[cfg(feature = "persistence")]
+    use crate::CollectionHandle;
+    use crate::{
+        circuit::{CheckpointError, GlobalNodeId},
+        operator::{FilterMap, Generator},
+        profile::ScheduleEventKind,
+        zset, Circuit, Error as DBSPError, InputHandle, OrdZSet, OutputHandle, RootCircuit,
+        Runtime, RuntimeError, SchedulerError,
+    };
+    use std::{
+        collections::HashSet,
+        panic::{catch_unwind, AssertUnwindSafe},
+        path::Path,
+    };
+    use tempfile::TempDir;
 
     // Panic during initialization in worker thread.
     #[test]
@@ -335,7 +1094,13 @@ mod tests {
         });
 
         if let DBSPError::Runtime(err) = res.unwrap_err() {
-            assert_eq!(err, RuntimeError::WorkerPanic(0));
+            assert_eq!(
+                err,
+                RuntimeError::WorkerPanic {
+                    worker: 0,
+                    panic_info: Some("explicit panic".to_string())
+                }
+            );
         } else {
             panic!();
         }
@@ -369,7 +1134,15 @@ mod tests {
         .unwrap();
 
         if let DBSPError::Runtime(err) = handle.step().unwrap_err() {
-            assert_eq!(err, RuntimeError::WorkerPanic(0));
+            assert_eq!(
+                err,
+                RuntimeError::WorkerPanic {
+                    worker: 0,
+                    panic_info: Some(
+                        "panic in operator 'Generator' ([0]): explicit panic".to_string()
+                    )
+                }
+            );
         } else {
             panic!();
         }
@@ -419,4 +1192,640 @@ mod tests {
 
         handle.step().unwrap();
     }
+
+    // A circuit that exceeds its memory limit is killed instead of running
+    // out of memory.
+    #[test]
+    fn test_memory_limit1() {
+        test_memory_limit(1);
+    }
+
+    #[test]
+    fn test_memory_limit4() {
+        test_memory_limit(4);
+    }
+
+    fn test_memory_limit(nworkers: usize) {
+        const LIMIT: usize = 10_000_000;
+
+        let (mut handle, mut input_handle) = Runtime::init_circuit(nworkers, |circuit| {
+            let (input, input_handle) = circuit.add_input_zset::<usize, isize>();
+
+            // Unbounded cross-product of all inputs.
+            let indexed = input.index_with(|x| (0usize, *x));
+            indexed
+                .join(&indexed, |_k, x, y| (*x, *y))
+                .integrate_trace();
+
+            input_handle
+        })
+        .unwrap();
+
+        handle.set_memory_limit(Some(LIMIT));
+
+        let mut allocated = 0;
+        let mut error = None;
+
+        for step in 0..100 {
+            let mut batch: Vec<_> = (step * 100..step * 100 + 100).map(|x| (x, 1)).collect();
+            input_handle.append(&mut batch);
+
+            if let Err(e) = handle.step() {
+                error = Some(e);
+                break;
+            }
+
+            let new_allocated = handle.allocated_bytes().unwrap();
+            assert!(new_allocated > allocated);
+            assert!(new_allocated <= LIMIT);
+            allocated = new_allocated;
+        }
+
+        match error {
+            Some(DBSPError::Scheduler(SchedulerError::MemoryLimitExceeded {
+                limit,
+                allocated,
+            })) => {
+                assert_eq!(limit, LIMIT);
+                assert!(allocated > LIMIT);
+            }
+            e => panic!("expected memory limit error, found {e:?}"),
+        }
+
+        // The circuit has been killed.
+        assert!(matches!(
+            handle.step(),
+            Err(DBSPError::Runtime(RuntimeError::Killed))
+        ));
+    }
+
+    // Per-operator memory usage adds up to the total memory usage.
+    #[test]
+    fn test_memory_usage1() {
+        test_memory_usage(1);
+    }
+
+    #[test]
+    fn test_memory_usage4() {
+        test_memory_usage(4);
+    }
+
+    fn test_memory_usage(nworkers: usize) {
+        let (mut handle, mut input_handle) = Runtime::init_circuit(nworkers, |circuit| {
+            let (input, input_handle) = circuit.add_input_zset::<usize, isize>();
+            input.integrate_trace();
+            input_handle
+        })
+        .unwrap();
+
+        let mut batch: Vec<_> = (0..1000).map(|x| (x, 1)).collect();
+        input_handle.append(&mut batch);
+        handle.step().unwrap();
+
+        let usage = handle.memory_usage().unwrap();
+        assert!(usage.iter().any(|(_, _, bytes)| *bytes > 0));
+        assert_eq!(
+            usage.iter().map(|(_, _, bytes)| bytes).sum::<usize>(),
+            handle.allocated_bytes().unwrap()
+        );
+
+        // Each operator is reported once.
+        let nodes: HashSet<&GlobalNodeId> = usage.iter().map(|(node_id, _, _)| node_id).collect();
+        assert_eq!(nodes.len(), usage.len());
+
+        handle.kill().unwrap();
+    }
+
+    // Operators are profiled after the CPU profiler is enabled.
+    #[test]
+    fn test_profile1() {
+        test_profile(1);
+    }
+
+    #[test]
+    fn test_profile4() {
+        test_profile(4);
+    }
+
+    fn test_profile(nworkers: usize) {
+        let (mut handle, (mut input_handle, node_id)) =
+            Runtime::init_circuit(nworkers, |circuit| {
+                let (input, input_handle) = circuit.add_input_zset::<usize, isize>();
+                let distinct = input.distinct();
+                (input_handle, distinct.origin_node_id().clone())
+            })
+            .unwrap();
+
+        handle.step().unwrap();
+        assert!(handle.profile().unwrap().operators().is_empty());
+
+        handle.enable_cpu_profiler().unwrap();
+
+        for step in 0..3 {
+            input_handle.append(&mut vec![(step, 1)]);
+            handle.step().unwrap();
+        }
+
+        let profile = handle.profile().unwrap();
+        let distinct = profile.operator(&node_id).unwrap();
+        assert_eq!(distinct.invocations, 3 * nworkers);
+        assert!(distinct.name.contains("Distinct"));
+
+        handle.kill().unwrap();
+    }
+
+    // `step_async` computes the same results as `step` and can be awaited
+    // from a tokio runtime.
+    #[test]
+    fn test_step_async1() {
+        test_step_async(1);
+    }
+
+    #[test]
+    fn test_step_async4() {
+        test_step_async(4);
+    }
+
+    fn test_step_async(nworkers: usize) {
+        let circuit = move || {
+            Runtime::init_circuit(nworkers, |circuit| {
+                let (input, input_handle) = circuit.add_input_zset::<usize, isize>();
+                let output_handle = input.map(|x| x % 5).integrate().output();
+                (input_handle, output_handle)
+            })
+            .unwrap()
+        };
+
+        let (mut sync_handle, (mut sync_input, sync_output)) = circuit();
+        let (mut async_handle, (mut async_input, async_output)) = circuit();
+
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        for step in 0..10 {
+            let mut batch: Vec<_> = (step * 10..step * 10 + 10).map(|x| (x, 1)).collect();
+            sync_input.append(&mut batch.clone());
+            async_input.append(&mut batch);
+
+            sync_handle.step().unwrap();
+            tokio.block_on(async_handle.step_async()).unwrap();
+
+            let expected: OrdZSet<usize, isize> = sync_output.consolidate();
+            assert_eq!(async_output.consolidate(), expected);
+        }
+
+        assert_eq!(
+            sync_output.consolidate(),
+            zset! { 0 => 20, 1 => 20, 2 => 20, 3 => 20, 4 => 20 }
+        );
+
+        sync_handle.kill().unwrap();
+        async_handle.kill().unwrap();
+    }
+
+    // Panic in `Circuit::step` is reported by `step_async`.
+    #[test]
+    fn test_step_async_panic() {
+        let (mut handle, _) = Runtime::init_circuit(4, |circuit| {
+            circuit.add_source(Generator::new(|| {
+                if Runtime::worker_index() == 2 {
+                    panic!()
+                } else {
+                    5usize
+                }
+            }));
+        })
+        .unwrap();
+
+        let tokio = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        if let DBSPError::Runtime(err) = tokio.block_on(handle.step_async()).unwrap_err() {
+            assert!(matches!(err, RuntimeError::WorkerPanic { worker: 2, .. }));
+        } else {
+            panic!();
+        }
+    }
+
+    // Outputs created with `output_named` can be enumerated and read by name.
+    #[test]
+    fn test_named_outputs1() {
+        test_named_outputs(1);
+    }
+
+    #[test]
+    fn test_named_outputs4() {
+        test_named_outputs(4);
+    }
+
+    fn test_named_outputs(nworkers: usize) {
+        let (mut handle, mut input_handle) = Runtime::init_circuit(nworkers, |circuit| {
+            let (input, input_handle) = circuit.add_input_zset::<usize, isize>();
+
+            input.map(|x| x * 2).output_named("doubled");
+            input
+                .map(|x| (*x as i64, x.to_string()))
+                .output_named("strings");
+
+            input_handle
+        })
+        .unwrap();
+
+        let mut names: Vec<_> = handle.outputs().keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, vec!["doubled".to_string(), "strings".to_string()]);
+
+        input_handle.append(&mut vec![(1, 1), (2, 1)]);
+        handle.step().unwrap();
+
+        assert_eq!(
+            handle.take_output::<OrdZSet<usize, isize>>("doubled"),
+            Some(zset! { 2 => 1, 4 => 1 })
+        );
+        // Draining one output doesn't affect the other.
+        assert_eq!(
+            handle.take_output::<OrdZSet<usize, isize>>("doubled"),
+            Some(zset! {})
+        );
+        assert_eq!(
+            handle.take_output::<OrdZSet<(i64, String), isize>>("strings"),
+            Some(zset! { (1, "1".to_string()) => 1, (2, "2".to_string()) => 1 })
+        );
+
+        // Unknown names and mismatched types.
+        assert_eq!(handle.take_output::<OrdZSet<usize, isize>>("foo"), None);
+        assert_eq!(handle.take_output::<OrdZSet<usize, isize>>("strings"), None);
+
+        handle.kill().unwrap();
+    }
+
+    // Outputs recorded by one run can be verified against another run.
+    #[test]
+    fn test_record_outputs1() {
+        test_record_outputs(1);
+    }
+
+    #[test]
+    fn test_record_outputs4() {
+        test_record_outputs(4);
+    }
+
+    // Runs a circuit that multiplies inputs below 10 by 2 and other inputs by
+    // `factor`, recording or verifying its outputs in `path`.  Returns the
+    // result of the first failed step, if any.
+    fn run_recorded(
+        path: &Path,
+        nworkers: usize,
+        factor: usize,
+        record: bool,
+    ) -> Result<(), DBSPError> {
+        let (mut handle, mut input_handle) = Runtime::init_circuit(nworkers, move |circuit| {
+            let (input, input_handle) = circuit.add_input_zset::<usize, isize>();
+
+            input
+                .map(move |x| if *x < 10 { x * 2 } else { x * factor })
+                .output_named("doubled");
+
+            input_handle
+        })
+        .unwrap();
+
+        if record {
+            handle.record_outputs(path).unwrap();
+        } else {
+            handle.verify_outputs(path).unwrap();
+        }
+
+        let mut result = Ok(());
+        for mut inputs in [vec![(1, 1), (2, 1)], vec![(10, 1)], vec![(1, -1)]] {
+            input_handle.append(&mut inputs);
+            result = handle.step();
+            if result.is_err() {
+                break;
+            }
+
+            // Recording doesn't consume outputs.
+            assert!(handle
+                .take_output::<OrdZSet<usize, isize>>("doubled")
+                .is_some());
+        }
+
+        handle.kill().unwrap();
+        result
+    }
+
+    fn test_record_outputs(nworkers: usize) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("outputs");
+
+        run_recorded(&path, nworkers, 2, true).unwrap();
+
+        // Same computation: verification succeeds.
+        run_recorded(&path, nworkers, 2, false).unwrap();
+
+        // Changed computation: verification fails at the first step whose
+        // outputs differ.
+        match run_recorded(&path, nworkers, 3, false) {
+            Err(DBSPError::OutputMismatch { step, diff }) => {
+                assert_eq!(step, 1);
+
+                let removed: Vec<_> = diff.lines().filter(|l| l.starts_with("- ")).collect();
+                let added: Vec<_> = diff.lines().filter(|l| l.starts_with("+ ")).collect();
+                assert_eq!(removed.len(), 1);
+                assert_eq!(added.len(), 1);
+                assert!(removed[0].starts_with("- doubled[") && removed[0].contains("20"));
+                assert!(added[0].starts_with("+ doubled[") && added[0].contains("30"));
+            }
+            result => panic!("expected output mismatch, found {result:?}"),
+        }
+    }
+
+    // Updates in a transaction are observed by the same step.
+    #[test]
+    fn test_transaction1() {
+        test_transaction(1);
+    }
+
+    #[test]
+    fn test_transaction4() {
+        test_transaction(4);
+    }
+
+    fn test_transaction(nworkers: usize) {
+        let (mut handle, output) = Runtime::init_circuit(nworkers, |circuit| {
+            let (a, _) = circuit.add_input_indexed_zset_named::<usize, String, isize>("a");
+            let (b, _) = circuit.add_input_indexed_zset_named::<usize, String, isize>("b");
+
+            // A non-incremental join only produces outputs for updates to both
+            // inputs that arrive in the same step.
+            a.stream_join(&b, |k, va, vb| (*k, va.clone(), vb.clone()))
+                .output()
+        })
+        .unwrap();
+
+        let mut names: Vec<_> = handle.inputs().keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, vec!["a".to_string(), "b".to_string()]);
+
+        handle.transaction(|txn| {
+            txn.input("a").append(&mut vec![
+                (1usize, ("a1".to_string(), 1isize)),
+                (2usize, ("a2".to_string(), 1isize)),
+            ]);
+            txn.input("b").push(1usize, ("b1".to_string(), 1isize));
+            txn.input("b").push(2usize, ("b2".to_string(), 1isize));
+        });
+        handle.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! {
+                (1, "a1".to_string(), "b1".to_string()) => 1,
+                (2, "a2".to_string(), "b2".to_string()) => 1,
+            }
+        );
+
+        // A transaction that panics doesn't apply any updates.
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            handle.transaction(|txn| {
+                txn.input("a").push(3usize, ("a3".to_string(), 1isize));
+                txn.input("b").push(3usize, ("b3".to_string(), 1isize));
+                panic!("abort transaction");
+            })
+        }));
+        assert!(result.is_err());
+        handle.step().unwrap();
+        assert_eq!(output.consolidate(), zset! {});
+
+        handle.kill().unwrap();
+    }
+
+    // Scheduler event traces capture the structure of each step.
+    #[test]
+    fn test_schedule_trace() {
+        let (mut handle, mut input_handle) = Runtime::init_circuit(2, |circuit| {
+            let (input, input_handle) = circuit.add_input_zset::<(usize, usize), isize>();
+            input.transitive_closure().output();
+            input_handle
+        })
+        .unwrap();
+
+        // Events are only recorded after capture starts.
+        handle.step().unwrap();
+        handle.capture_schedule_trace().unwrap();
+
+        for i in 0..3 {
+            input_handle.append(&mut vec![((i, i + 1), 1)]);
+            handle.step().unwrap();
+        }
+
+        let trace = handle.take_schedule_trace().unwrap();
+
+        for worker in 0..2 {
+            let events: Vec<_> = trace.worker_events(worker).collect();
+
+            // Three root steps.
+            let root_steps: Vec<_> = events
+                .iter()
+                .filter(|event| {
+                    matches!(
+                        event.kind,
+                        ScheduleEventKind::StepStart | ScheduleEventKind::StepEnd
+                    ) && event.node_id.as_ref() == Some(&GlobalNodeId::root())
+                })
+                .map(|event| event.kind)
+                .collect();
+            assert_eq!(
+                root_steps,
+                [ScheduleEventKind::StepStart, ScheduleEventKind::StepEnd].repeat(3)
+            );
+            assert_eq!(events.first().unwrap().kind, ScheduleEventKind::StepStart);
+            assert_eq!(events.last().unwrap().kind, ScheduleEventKind::StepEnd);
+
+            // Start and end events are properly nested; the nested circuit
+            // starts and ends a clock epoch within each root step.
+            let mut stack = Vec::new();
+            let mut clock_starts = 0;
+            let mut evals = 0;
+            for event in events.iter() {
+                match event.kind {
+                    ScheduleEventKind::ClockStart => {
+                        assert_eq!(stack.len(), 2);
+                        clock_starts += 1;
+                    }
+                    ScheduleEventKind::EvalStart => evals += 1,
+                    _ => {}
+                }
+
+                if event.kind.is_start() {
+                    stack.push(event);
+                } else {
+                    let start = stack.pop().unwrap();
+                    assert_eq!(start.node_id, event.node_id);
+                    assert!(start.time <= event.time);
+                }
+            }
+            assert!(stack.is_empty());
+            assert_eq!(clock_starts, 3);
+            assert!(evals > 0);
+        }
+
+        let json = trace.to_json();
+        assert!(json.starts_with("{\"traceEvents\":[{\"name\":\"step []\""));
+        assert!(json.contains("\"ph\":\"E\""));
+
+        // Capture has stopped.
+        handle.step().unwrap();
+        assert!(handle.take_schedule_trace().unwrap().events().is_empty());
+
+        handle.kill().unwrap();
+    }
+
+    // Operators whose state cannot be serialized fail the checkpoint without
+    // affecting the circuit.
+    #[test]
+    fn test_checkpoint_unsupported() {
+        let path = std::env::temp_dir().join("test_checkpoint_unsupported");
+        let _ = std::fs::remove_dir_all(&path);
+
+        let (mut handle, (input_handle, output)) = Runtime::init_circuit(2, |circuit| {
+            let (input, input_handle) = circuit.add_input_stream::<usize>();
+            let output = input
+                .apply_stateful(0, |sum: &mut usize, x: &usize| {
+                    *sum += *x;
+                    *sum
+                })
+                .output();
+            (input_handle, output)
+        })
+        .unwrap();
+
+        input_handle.set_for_all(1);
+        handle.step().unwrap();
+        assert_eq!(output.take_from_all(), vec![1, 1]);
+
+        match handle.checkpoint(&path) {
+            Err(DBSPError::Checkpoint {
+                name,
+                error: CheckpointError::Unsupported,
+                ..
+            }) => assert_eq!(name, "ApplyStateful"),
+            result => panic!("expected checkpoint error, found {result:?}"),
+        }
+        assert!(!path.exists());
+
+        input_handle.set_for_all(2);
+        handle.step().unwrap();
+        assert_eq!(output.take_from_all(), vec![3, 3]);
+
+        handle.kill().unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_integral1() {
+        test_checkpoint_integral(1);
+    }
+
+    #[test]
+    fn test_checkpoint_integral4() {
+        test_checkpoint_integral(4);
+    }
+
+    fn integral_circuit(circuit: &mut RootCircuit) -> (InputHandle<isize>, OutputHandle<isize>) {
+        let (input, input_handle) = circuit.add_input_stream::<isize>();
+        (input_handle, input.integrate().output())
+    }
+
+    // Integrals of scalars and input watermarks survive a checkpoint without
+    // the `persistence` feature.
+    fn test_checkpoint_integral(nworkers: usize) {
+        let path = std::env::temp_dir().join(format!("test_checkpoint_integral{nworkers}"));
+        let _ = std::fs::remove_dir_all(&path);
+
+        let (mut handle, (input_handle, output)) =
+            Runtime::init_circuit(nworkers, integral_circuit).unwrap();
+
+        input_handle.set_for_all(5);
+        input_handle.set_watermark(100);
+        handle.step().unwrap();
+        assert_eq!(output.take_from_all(), vec![5; nworkers]);
+
+        // Watermarks that the circuit has not seen yet are not checkpointed.
+        input_handle.set_watermark(200);
+        handle.checkpoint(&path).unwrap();
+        handle.kill().unwrap();
+
+        let (mut handle, (input_handle, output)) =
+            Runtime::restore_circuit(nworkers, &path, integral_circuit).unwrap();
+        assert_eq!(input_handle.watermark(), 100);
+
+        input_handle.set_for_all(-2);
+        handle.step().unwrap();
+        assert_eq!(output.take_from_all(), vec![3; nworkers]);
+
+        handle.kill().unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn test_checkpoint_restore1() {
+        test_checkpoint_restore(1);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn test_checkpoint_restore4() {
+        test_checkpoint_restore(4);
+    }
+
+    #[cfg(feature = "persistence")]
+    fn distinct_circuit(
+        circuit: &mut RootCircuit,
+    ) -> (
+        CollectionHandle<usize, isize>,
+        OutputHandle<OrdZSet<usize, isize>>,
+    ) {
+        let (input, input_handle) = circuit.add_input_zset::<usize, isize>();
+        (input_handle, input.distinct().output())
+    }
+
+    #[cfg(feature = "persistence")]
+    fn test_checkpoint_restore(nworkers: usize) {
+        let path = std::env::temp_dir().join(format!("test_checkpoint_restore{nworkers}"));
+        let _ = std::fs::remove_dir_all(&path);
+
+        let (mut handle, (mut input_handle, output)) =
+            Runtime::init_circuit(nworkers, distinct_circuit).unwrap();
+
+        input_handle.append(&mut vec![(1, 1), (2, 1), (2, 1)]);
+        handle.step().unwrap();
+        assert_eq!(output.consolidate(), zset! {1 => 1, 2 => 1});
+
+        handle.checkpoint(&path).unwrap();
+        handle.kill().unwrap();
+
+        let (mut handle, (mut input_handle, output)) =
+            Runtime::restore_circuit(nworkers, &path, distinct_circuit).unwrap();
+
+        // The restored circuit remembers that it has seen `2`.
+        input_handle.append(&mut vec![(2, 1), (3, 1)]);
+        handle.step().unwrap();
+        assert_eq!(output.consolidate(), zset! {3 => 1});
+
+        input_handle.append(&mut vec![(1, -1)]);
+        handle.step().unwrap();
+        assert_eq!(output.consolidate(), zset! {1 => -1});
+
+        handle.kill().unwrap();
+
+        // The checkpoint can only be restored with the same number of workers.
+        assert!(matches!(
+            Runtime::restore_circuit(nworkers + 1, &path, distinct_circuit),
+            Err(DBSPError::Custom(_))
+        ));
+
+        std::fs::remove_dir_all(&path).unwrap();
+    }
 }
@@ -4,6 +4,7 @@
 //! consumes one or more input streams and produces an output stream.
 
 use crate::circuit::{
+    checkpoint::CheckpointError,
     metadata::{OperatorLocation, OperatorMeta},
     OwnershipPreference, Scope,
 };
@@ -194,6 +195,30 @@ pub trait Operator: 'static {
     /// of the fixed point computation, but not as part of an integrator circuit
     /// ([`Stream::integrate`](`crate::circuit::Stream::integrate`)).
     fn fixedpoint(&self, scope: Scope) -> bool;
+
+    /// Serializes the state of the operator for a checkpoint of the circuit
+    /// (see [`DBSPHandle::checkpoint`](`crate::DBSPHandle::checkpoint`)).
+    ///
+    /// Invoked between clock cycles of the root circuit.  Returns `Ok(None)`
+    /// if the operator currently has no state to save, and
+    /// [`CheckpointError::Unsupported`] if it has state that it cannot
+    /// serialize.
+    ///
+    /// The default implementation returns [`CheckpointError::Unsupported`],
+    /// so that an operator that doesn't know how to save its state cannot
+    /// silently lose it.  Stateless operators opt in by returning `Ok(None)`;
+    /// operators that carry state across clock cycles must override this
+    /// method and [`Self::restore`].
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Err(CheckpointError::Unsupported)
+    }
+
+    /// Restores state saved by [`Self::checkpoint`].
+    ///
+    /// Invoked on a newly constructed operator, before the first clock cycle.
+    fn restore(&mut self, _state: &[u8]) -> Result<(), CheckpointError> {
+        Err(CheckpointError::Unsupported)
+    }
 }
 
 /// A source operator that injects data from the outside world or from the
@@ -241,6 +266,28 @@ pub trait UnaryOperator<I, O>: Operator {
     }
 }
 
+/// A unary operator that consumes a stream of inputs of type `I` and
+/// produces two output streams of types `O1` and `O2`.
+///
+/// The operator is evaluated once per clock cycle and computes both outputs
+/// in a single pass over its input, e.g., to partition the input into
+/// records that satisfy a predicate and records that don't.
+pub trait SplitOperator<I, O1, O2>: Operator {
+    /// Consume input by reference.
+    fn eval(&mut self, input: &I) -> (O1, O2);
+
+    /// Consume input by value.
+    fn eval_owned(&mut self, input: I) -> (O1, O2) {
+        self.eval(&input)
+    }
+
+    /// Ownership preference on the operator's input stream
+    /// (see [`OwnershipPreference`]).
+    fn input_preference(&self) -> OwnershipPreference {
+        OwnershipPreference::INDIFFERENT
+    }
+}
+
 /// A binary operator consumes two input streams carrying values
 /// of types `I1` and `I2` and produces a stream of outputs of type `O`.
 pub trait BinaryOperator<I1, I2, O>: Operator {
@@ -1,30 +1,110 @@
 //! A multithreaded runtime for evaluating DBSP circuits in a data-parallel
 //! fashion.
 
+use crate::{
+    circuit::circuit_builder::{panic_message, take_operator_panic_message},
+    trace::{spine_fueled::CompactionPolicy, spine_persistent::SpillPolicy},
+};
 use crossbeam::channel::bounded;
 use crossbeam_utils::sync::{Parker, Unparker};
 use std::{
     cell::{Cell, RefCell},
     fmt,
     fmt::{Debug, Display, Error as FmtError, Formatter},
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     thread::{Builder, JoinHandle, LocalKey, Result as ThreadResult},
+    time::Duration,
 };
 use typedmap::{TypedDashMap, TypedMapKey};
 
+#[cfg(all(target_os = "linux", feature = "numa"))]
+mod numa {
+    use std::{
+        mem::{size_of, zeroed},
+        ptr::null,
+    };
+
+    // Allocate memory on the node of the CPU that touches it first (see
+    // `set_mempolicy(2)`).
+    const MPOL_LOCAL: libc::c_int = 4;
+
+    /// Pin the current thread to the `worker_index`-th core of its affinity
+    /// mask and restrict its allocations to the NUMA node of that core.
+    ///
+    /// Best-effort: on failure the thread keeps its current affinity and
+    /// memory policy.
+    pub(super) fn bind_worker(worker_index: usize) {
+        unsafe {
+            let mut available: libc::cpu_set_t = zeroed();
+            if libc::sched_getaffinity(0, size_of::<libc::cpu_set_t>(), &mut available) != 0 {
+                return;
+            }
+
+            let cpus: Vec<usize> = (0..libc::CPU_SETSIZE as usize)
+                .filter(|&cpu| libc::CPU_ISSET(cpu, &available))
+                .collect();
+            if cpus.is_empty() {
+                return;
+            }
+
+            let mut pinned: libc::cpu_set_t = zeroed();
+            libc::CPU_SET(cpus[worker_index % cpus.len()], &mut pinned);
+            if libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &pinned) != 0 {
+                return;
+            }
+
+            // Once the thread is pinned, the local node is the node of its
+            // core.
+            libc::syscall(
+                libc::SYS_set_mempolicy,
+                MPOL_LOCAL,
+                null::<libc::c_ulong>(),
+                0 as libc::c_ulong,
+            );
+        }
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "numa")))]
+mod numa {
+    /// NUMA-local allocation is not supported on this platform or without
+    /// the `numa` feature: workers use the default scheduling and allocation
+    /// policies.
+    pub(super) fn bind_worker(_worker_index: usize) {}
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Error {
-    WorkerPanic(usize),
+    /// A worker thread panicked.
+    WorkerPanic {
+        /// Index of the worker thread.
+        worker: usize,
+        /// Panic message, annotated with the name, global id, and location
+        /// of the operator that raised the panic, when the panic occurred
+        /// while evaluating an operator.  `None` if the panic payload is not
+        /// a string.
+        panic_info: Option<String>,
+    },
     Killed,
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
         match self {
-            Self::WorkerPanic(worker) => {
+            Self::WorkerPanic {
+                worker,
+                panic_info: Some(panic_info),
+            } => {
+                write!(f, "worker thread '{worker}' panicked: {panic_info}")
+            }
+            Self::WorkerPanic {
+                worker,
+                panic_info: None,
+            } => {
                 write!(f, "worker thread '{worker}' panicked")
             }
             Self::Killed => f.write_str("circuit killed by the user"),
@@ -61,24 +141,106 @@ pub struct LocalStoreMarker;
 /// Local data store shared by all workers in a runtime.
 pub type LocalStore = TypedDashMap<LocalStoreMarker>;
 
+/// Runtime configuration.
+///
+/// Settings that apply to all circuits instantiated by a runtime (see
+/// [`Runtime::run_with_config`]).  Start from the default configuration and
+/// change individual settings with the `with_` methods, e.g.,
+/// `RuntimeConfig::default().with_numa_local(true)`, so that adding new
+/// settings doesn't break existing code.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RuntimeConfig {
+    /// Compaction policy of traces created by worker threads.
+    pub compaction_policy: CompactionPolicy,
+
+    /// Spill policy of
+    /// [`PersistentSpine`](`crate::trace::PersistentSpine`) traces created by
+    /// worker threads.
+    pub spill_policy: SpillPolicy,
+
+    /// Enables stall detection in circuit schedulers.
+    ///
+    /// When set, a scheduler that spends this long without evaluating any
+    /// operator, because all remaining operators are waiting for async
+    /// operators that don't become ready, fails the current step with
+    /// [`SchedulerError::Stalled`](`crate::SchedulerError::Stalled`) instead
+    /// of waiting forever.  This is a debugging aid for, e.g., incorrectly
+    /// wired exchange operators.  The timeout should be well above the time
+    /// it takes for other workers to complete their part of a step, or slow
+    /// steps are reported as stalls.  A stalled circuit is left in the
+    /// middle of a step and must not be stepped again (see
+    /// [`SchedulerError::Stalled`](`crate::SchedulerError::Stalled`)).
+    ///
+    /// `None` (the default) disables stall detection.
+    pub stall_timeout: Option<Duration>,
+
+    /// Pin each worker thread to a CPU core and allocate its memory,
+    /// including the storage of the traces it creates, on the NUMA node of
+    /// that core.
+    ///
+    /// Worker `i` is pinned to the `i`-th core (modulo the number of cores)
+    /// in the CPU affinity mask of the thread that starts the runtime.  On
+    /// multi-socket machines this avoids cross-socket memory accesses on
+    /// every trace lookup.
+    ///
+    /// Requires the `numa` feature and Linux; this setting is ignored
+    /// otherwise, as well as when the operating system refuses to pin the
+    /// thread or change its memory policy, in which case workers fall back
+    /// to the default scheduling and allocation policies.
+    pub numa_local: bool,
+}
+
+impl RuntimeConfig {
+    /// Sets [`Self::compaction_policy`].
+    pub fn with_compaction_policy(mut self, compaction_policy: CompactionPolicy) -> Self {
+        self.compaction_policy = compaction_policy;
+        self
+    }
+
+    /// Sets [`Self::spill_policy`].
+    pub fn with_spill_policy(mut self, spill_policy: SpillPolicy) -> Self {
+        self.spill_policy = spill_policy;
+        self
+    }
+
+    /// Sets [`Self::stall_timeout`].
+    pub fn with_stall_timeout(mut self, stall_timeout: Option<Duration>) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    /// Sets [`Self::numa_local`].
+    pub fn with_numa_local(mut self, numa_local: bool) -> Self {
+        self.numa_local = numa_local;
+        self
+    }
+}
+
 struct RuntimeInner {
     nworkers: usize,
+    config: RuntimeConfig,
     store: LocalStore,
+    // Panic message of each worker thread that has panicked.
+    panic_info: Vec<Mutex<Option<String>>>,
 }
 
 impl Debug for RuntimeInner {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("RuntimeInner")
             .field("nworkers", &self.nworkers)
+            .field("config", &self.config)
             .finish()
     }
 }
 
 impl RuntimeInner {
-    fn new(nworkers: usize) -> Self {
+    fn new(nworkers: usize, config: RuntimeConfig) -> Self {
         Self {
             nworkers,
+            config,
             store: TypedDashMap::new(),
+            panic_info: (0..nworkers).map(|_| Mutex::new(None)).collect(),
         }
     }
 }
@@ -138,7 +300,16 @@ impl Runtime {
     where
         F: FnOnce() + Clone + Send + 'static,
     {
-        let runtime = Self(Arc::new(RuntimeInner::new(workers)));
+        Self::run_with_config(workers, RuntimeConfig::default(), circuit)
+    }
+
+    /// Like [`Self::run`], but uses the specified runtime configuration
+    /// instead of the default one.
+    pub fn run_with_config<F>(workers: usize, config: RuntimeConfig, circuit: F) -> RuntimeHandle
+    where
+        F: FnOnce() + Clone + Send + 'static,
+    {
+        let runtime = Self(Arc::new(RuntimeInner::new(workers, config)));
 
         let mut handles = Vec::with_capacity(workers);
         handles.extend((0..workers).map(|worker_index| {
@@ -150,9 +321,14 @@ impl Runtime {
                 .name(format!("dbsp-worker-{worker_index}"))
                 .spawn(move || {
                     // Set the worker's runtime handle and index
-                    RUNTIME.with(|rt| *rt.borrow_mut() = Some(runtime));
+                    RUNTIME.with(|rt| *rt.borrow_mut() = Some(runtime.clone()));
                     WORKER_INDEX.with(|idx| idx.set(worker_index));
 
+                    // Bind the worker to its core before it allocates any state.
+                    if runtime.inner().config.numa_local {
+                        numa::bind_worker(worker_index);
+                    }
+
                     // Send the main thread our parker and kill signal
                     // TODO: Share a single kill signal across all workers
                     init_sender
@@ -162,8 +338,14 @@ impl Runtime {
                         ))
                         .unwrap();
 
-                    // Build the worker's circuit
-                    build_circuit();
+                    // Build the worker's circuit.  If it panics, record the panic
+                    // message before re-raising the original payload.
+                    if let Err(panic) = catch_unwind(AssertUnwindSafe(build_circuit)) {
+                        let panic_info = take_operator_panic_message()
+                            .or_else(|| panic_message(panic.as_ref()).map(str::to_string));
+                        *runtime.inner().panic_info[worker_index].lock().unwrap() = panic_info;
+                        resume_unwind(panic);
+                    }
                 })
                 .unwrap_or_else(|error| {
                     panic!("failed to spawn worker thread {worker_index}: {error}");
@@ -211,6 +393,11 @@ impl Runtime {
         self.inner().nworkers
     }
 
+    /// Returns the configuration of this runtime.
+    pub fn config(&self) -> &RuntimeConfig {
+        &self.inner().config
+    }
+
     /// Returns reference to the data store shared by all workers within the
     /// runtime.
     ///
@@ -224,6 +411,20 @@ impl Runtime {
         &self.inner().store
     }
 
+    /// Returns the panic message of worker thread `worker_index`, or `None`
+    /// if the worker has not panicked or its panic payload is not a string.
+    ///
+    /// The message is annotated with the context of the operator that raised
+    /// the panic, if any.  It is recorded before the worker thread
+    /// terminates, so it is guaranteed to be available after the thread has
+    /// been joined.
+    pub fn panic_info(&self, worker_index: usize) -> Option<String> {
+        self.inner().panic_info[worker_index]
+            .lock()
+            .unwrap()
+            .clone()
+    }
+
     /// A per-worker sequential counter.
     ///
     /// This method can be used to generate unique identifiers that will be the
@@ -347,7 +548,7 @@ impl TypedMapKey<LocalStoreMarker> for WorkerId {
 
 #[cfg(test)]
 mod tests {
-    use super::Runtime;
+    use super::{Runtime, RuntimeConfig};
     use crate::{
         circuit::schedule::{DynamicScheduler, Scheduler, StaticScheduler},
         operator::Generator,
@@ -442,4 +643,74 @@ mod tests {
         sleep(Duration::from_millis(100));
         hruntime.kill().unwrap();
     }
+
+    // A worker panic preserves the original payload and records the context of
+    // the operator that raised it.
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_panic_info() {
+        let hruntime = Runtime::run(2, || {
+            let root = RootCircuit::build(|circuit| {
+                circuit
+                    .add_source(Generator::new(|| 1usize))
+                    .apply(|x| -> usize {
+                        if Runtime::worker_index() == 1 {
+                            panic!("malformed record")
+                        }
+                        *x
+                    });
+            })
+            .unwrap()
+            .0;
+
+            root.step().unwrap();
+        });
+
+        let runtime = hruntime.runtime().clone();
+        let panic = hruntime.join().unwrap_err();
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"malformed record"));
+
+        assert_eq!(runtime.panic_info(0), None);
+        let panic_info = runtime.panic_info(1).unwrap();
+        assert!(panic_info.starts_with("panic in operator 'Apply' ([1]) at "));
+        assert!(panic_info.ends_with(": malformed record"));
+    }
+
+    // Workers bound to their NUMA node evaluate circuits as usual.
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_numa_local() {
+        let config = RuntimeConfig::default().with_numa_local(true);
+
+        let hruntime = Runtime::run_with_config(2, config, || {
+            // With NUMA support, each worker is pinned to a single core.
+            #[cfg(all(target_os = "linux", feature = "numa"))]
+            unsafe {
+                let mut cpus: libc::cpu_set_t = std::mem::zeroed();
+                assert_eq!(
+                    libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut cpus),
+                    0
+                );
+                assert_eq!(libc::CPU_COUNT(&cpus), 1);
+            }
+
+            let data = Rc::new(RefCell::new(vec![]));
+            let data_clone = data.clone();
+            let root = RootCircuit::build(move |circuit| {
+                circuit
+                    .add_source(Generator::new(|| 1usize))
+                    .inspect(move |n: &usize| data_clone.borrow_mut().push(*n));
+            })
+            .unwrap()
+            .0;
+
+            for _ in 0..10 {
+                root.step().unwrap();
+            }
+
+            assert_eq!(&*data.borrow(), &vec![1; 10]);
+        });
+
+        hruntime.join().unwrap();
+    }
 }
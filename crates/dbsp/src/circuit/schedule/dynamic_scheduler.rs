@@ -38,18 +38,41 @@
 //! scans the ready set marking operators ready and moving them to the runnable
 //! queue when necessary. If the runnable queue is still empty, the scheduler
 //! thread parks itself waiting for the next ready notification.
+//!
+//! ## Scheduling across workers
+//!
+//! Each worker thread runs its own replica of the circuit, with its own
+//! scheduler instance.  The scheduler only evaluates nodes of its own replica
+//! and never hands them to other workers: circuit nodes and streams are not
+//! `Send`, and stateful operators (e.g., traces maintained by joins and
+//! aggregates) hold the state of the worker's shard of the data, so
+//! evaluating a node on a different thread would require migrating its
+//! state.  As a result, an idle worker cannot take over work from a busy
+//! one, and the scheduler deliberately does not implement work stealing;
+//! the only point where workers wait for each other are exchange
+//! operators, where the fastest worker blocks until its peers catch up.
+//! Skew must therefore be addressed at the level of data partitioning, e.g.,
+//! by making sure that keys are evenly distributed across shards, or
+//! mitigated by buffered exchange operators
+//! ([`new_buffered_exchange_operators`](`crate::operator::communication::new_buffered_exchange_operators`)),
+//! which allow workers to run ahead of slow peers.  The Nexmark benchmark
+//! can generate skewed inputs to measure the impact of such changes (see
+//! the `--hot-auction-ratio`, `--hot-bidders-ratio`, and
+//! `--hot-sellers-ratio` options).
 
 use std::{
     cell::{RefCell, RefMut},
     collections::{HashMap, HashSet},
     ops::Deref,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use crate::circuit::{
     runtime::Runtime,
     schedule::{
-        util::{circuit_graph, ownership_constraints},
+        stall_timeout,
+        util::{check_cycles, circuit_graph, ownership_constraints},
         Error, Scheduler,
     },
     trace::SchedulerEvent,
@@ -162,6 +185,9 @@ struct Inner {
 
     /// Tasks that are ready to be executed.
     runnable: RunQueue,
+
+    /// See [`RuntimeConfig::stall_timeout`](`crate::RuntimeConfig::stall_timeout`).
+    stall_timeout: Option<Duration>,
 }
 
 impl Inner {
@@ -227,10 +253,33 @@ impl Inner {
         }
     }
 
+    /// Returns a [`Error::Stalled`] error listing async nodes that the
+    /// scheduler is waiting for.
+    fn stalled_error<C>(&self, circuit: &C) -> Error
+    where
+        C: Circuit,
+    {
+        let pending_nodes = self
+            .tasks
+            .iter()
+            .filter(|task| task.is_async && !task.is_ready && !task.scheduled)
+            .map(|task| {
+                (
+                    GlobalNodeId::child_of(circuit, task.node_id),
+                    circuit.node_name(task.node_id),
+                )
+            })
+            .collect();
+
+        Error::Stalled { pending_nodes }
+    }
+
     fn prepare<C>(circuit: &C) -> Result<Self, Error>
     where
         C: Circuit,
     {
+        check_cycles(circuit)?;
+
         // Check that ownership constraints don't introduce cycles.
         let mut g = circuit_graph(circuit);
 
@@ -304,6 +353,7 @@ impl Inner {
             tasks,
             notifications: Notifications::new(num_async_nodes, unparker),
             runnable: RunQueue::with_capacity(num_nodes),
+            stall_timeout: stall_timeout(),
         };
 
         // Setup scheduler callbacks.
@@ -333,6 +383,9 @@ impl Inner {
         circuit.log_scheduler_event(&SchedulerEvent::step_start(circuit.global_id().deref()));
 
         let mut completed_tasks = 0;
+        // Deadline for making progress when stall detection is enabled and
+        // the scheduler is waiting for async operators.
+        let mut stall_deadline: Option<Instant> = None;
 
         // Reset unsatisfied dependencies, initialize runnable queue.
         for task in self.tasks.iter_mut() {
@@ -361,10 +414,24 @@ impl Inner {
                         circuit.log_scheduler_event(&SchedulerEvent::wait_start(
                             circuit.global_id().deref(),
                         ));
-                        Runtime::parker().with(|parker| parker.park());
+                        match self.stall_timeout {
+                            None => Runtime::parker().with(|parker| parker.park()),
+                            Some(timeout) => {
+                                let deadline =
+                                    *stall_deadline.get_or_insert_with(|| Instant::now() + timeout);
+                                let now = Instant::now();
+                                if now >= deadline {
+                                    return Err(self.stalled_error(circuit));
+                                }
+                                Runtime::parker()
+                                    .with(|parker| parker.park_timeout(deadline - now));
+                            }
+                        }
                         circuit.log_scheduler_event(&SchedulerEvent::wait_end(
                             circuit.global_id().deref(),
                         ));
+                    } else {
+                        stall_deadline = None;
                     }
                 }
 
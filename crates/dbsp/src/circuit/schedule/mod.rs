@@ -1,10 +1,12 @@
 //! The scheduling framework controls the execution of a circuit at runtime.
 
-use super::{trace::SchedulerEvent, Circuit, GlobalNodeId};
+use super::{trace::SchedulerEvent, Circuit, GlobalNodeId, Runtime};
 use itertools::Itertools;
 use std::{
+    borrow::Cow,
     fmt::{Display, Error as FmtError, Formatter},
     string::ToString,
+    time::Duration,
 };
 
 mod static_scheduler;
@@ -25,9 +27,30 @@ pub enum Error {
     },
     /// Ownership constraints introduce a cycle in the circuit graph.
     CyclicCircuit { node_id: GlobalNodeId },
+    /// The circuit contains a cycle that doesn't go through a strict
+    /// operator.  `cycle` lists the ids and names of operators in the cycle,
+    /// in the order of the edges connecting them.
+    CycleWithoutStrictOperator {
+        cycle: Vec<(GlobalNodeId, Cow<'static, str>)>,
+    },
     /// Execution of the circuit interrupted by the user (via
     /// [`RuntimeHandle::kill`](`crate::circuit::RuntimeHandle::kill`)).
     Killed,
+    /// Memory allocated by the circuit exceeds the limit configured via
+    /// [`DBSPHandle::set_memory_limit`](`crate::DBSPHandle::set_memory_limit`).
+    MemoryLimitExceeded { limit: usize, allocated: usize },
+    /// The scheduler made no progress for
+    /// [`RuntimeConfig::stall_timeout`](`crate::RuntimeConfig::stall_timeout`).
+    /// `pending_nodes` lists the ids and names of async operators the
+    /// scheduler was waiting for.
+    ///
+    /// The scheduler gives up in the middle of a step: some operators have
+    /// been evaluated during the step and others haven't, and the clock
+    /// hasn't advanced.  The circuit is in an inconsistent state and must
+    /// not be stepped again; drop or kill it instead.
+    Stalled {
+        pending_nodes: Vec<(GlobalNodeId, Cow<'static, str>)>,
+    },
 }
 
 impl Display for Error {
@@ -40,11 +63,33 @@ impl Display for Error {
             Self::CyclicCircuit { node_id } => {
                 write!(f, "unschedulable circuit due to a cyclic topology: cycle through node '{node_id}'")
             }
+            Self::CycleWithoutStrictOperator { cycle } => {
+                write!(f, "circuit contains a cycle without a strict operator (every cycle must contain a strict operator, e.g., 'Z1'): {}",
+                       cycle.iter().map(|(node_id, name)| format!("'{name}' ({node_id})")).format(" -> "))
+            }
             Self::Killed => f.write_str("circuit has been killed by the user"),
+            Self::MemoryLimitExceeded { limit, allocated } => {
+                write!(f, "circuit has been aborted after exceeding its memory limit: {allocated} bytes allocated, the limit is {limit} bytes")
+            }
+            Self::Stalled { pending_nodes } => {
+                write!(
+                    f,
+                    "circuit stalled waiting for async operators that never became ready: {}",
+                    pending_nodes
+                        .iter()
+                        .map(|(node_id, name)| format!("'{name}' ({node_id})"))
+                        .format(", ")
+                )
+            }
         }
     }
 }
 
+/// Returns the stall timeout configured for the current runtime, if any.
+fn stall_timeout() -> Option<Duration> {
+    Runtime::runtime().and_then(|runtime| runtime.config().stall_timeout)
+}
+
 /// A scheduler defines the order in which nodes in a circuit are evaluated at
 /// runtime.
 ///
@@ -173,8 +218,11 @@ where
 mod util {
 
     use crate::circuit::{schedule::Error, Circuit, GlobalNodeId, NodeId, OwnershipPreference};
-    use petgraph::graphmap::DiGraphMap;
-    use std::{collections::HashMap, ops::Deref};
+    use petgraph::{algo::tarjan_scc, graphmap::DiGraphMap};
+    use std::{
+        collections::{HashMap, HashSet, VecDeque},
+        ops::Deref,
+    };
 
     /// Dump circuit topology as a graph.
     pub(crate) fn circuit_graph<C>(circuit: &C) -> DiGraphMap<NodeId, ()>
@@ -194,6 +242,71 @@ mod util {
         g
     }
 
+    /// Check that every cycle in the circuit graph contains a strict operator.
+    ///
+    /// A strict operator is represented in the circuit graph by a pair of
+    /// disconnected source and sink nodes, so any cycle in the graph is a
+    /// cycle without a strict operator, which cannot be scheduled.  Catching
+    /// such cycles at construction time produces a more useful error than
+    /// the topological sort performed by the scheduler.  Returns
+    /// [`Error::CycleWithoutStrictOperator`] listing the operators in one
+    /// such cycle.
+    pub(crate) fn check_cycles<C>(circuit: &C) -> Result<(), Error>
+    where
+        C: Circuit,
+    {
+        let g = circuit_graph(circuit);
+
+        for component in tarjan_scc(&g) {
+            let start = component[0];
+            if component.len() == 1 && !g.contains_edge(start, start) {
+                continue;
+            }
+
+            // Find the shortest path from `start` back to itself within the
+            // strongly connected component.
+            let component: HashSet<NodeId> = component.into_iter().collect();
+            let mut predecessors = HashMap::new();
+            let mut queue = VecDeque::from([start]);
+
+            'bfs: while let Some(node) = queue.pop_front() {
+                for next in g.neighbors(node) {
+                    if !component.contains(&next) || predecessors.contains_key(&next) {
+                        continue;
+                    }
+                    predecessors.insert(next, node);
+                    if next == start {
+                        break 'bfs;
+                    }
+                    queue.push_back(next);
+                }
+            }
+
+            let mut cycle = vec![start];
+            let mut node = predecessors[&start];
+            while node != start {
+                cycle.push(node);
+                node = predecessors[&node];
+            }
+            cycle.reverse();
+            cycle.rotate_right(1);
+
+            return Err(Error::CycleWithoutStrictOperator {
+                cycle: cycle
+                    .into_iter()
+                    .map(|node_id| {
+                        (
+                            GlobalNodeId::child_of(circuit, node_id),
+                            circuit.node_name(node_id),
+                        )
+                    })
+                    .collect(),
+            });
+        }
+
+        Ok(())
+    }
+
     /// Helper function used by schedulers to enforce ownership preferences.
     ///
     /// Individual schedulers can implement their own algorithms to enforce (or
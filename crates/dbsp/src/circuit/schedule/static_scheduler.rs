@@ -3,19 +3,27 @@
 use crate::circuit::{
     runtime::Runtime,
     schedule::{
-        util::{circuit_graph, ownership_constraints},
+        stall_timeout,
+        util::{check_cycles, circuit_graph, ownership_constraints},
         Error, Scheduler,
     },
     trace::SchedulerEvent,
     Circuit, GlobalNodeId, NodeId,
 };
 use petgraph::algo::toposort;
-use std::{ops::Deref, thread::yield_now};
+use std::{
+    ops::Deref,
+    thread::yield_now,
+    time::{Duration, Instant},
+};
 
 /// Static scheduler evaluates nodes in the circuit in a fixed order computed
 /// based on its dependency graph.
 pub struct StaticScheduler {
     schedule: Vec<(NodeId, bool)>,
+
+    /// See [`RuntimeConfig::stall_timeout`](`crate::RuntimeConfig::stall_timeout`).
+    stall_timeout: Option<Duration>,
 }
 
 impl Scheduler for StaticScheduler {
@@ -27,6 +35,8 @@ impl Scheduler for StaticScheduler {
     where
         C: Circuit,
     {
+        check_cycles(circuit)?;
+
         let mut g = circuit_graph(circuit);
 
         // Add ownership constraints to the graph.
@@ -46,7 +56,10 @@ impl Scheduler for StaticScheduler {
             .map(|node_id| (node_id, circuit.is_async_node(node_id)))
             .collect();
 
-        Ok(Self { schedule })
+        Ok(Self {
+            schedule,
+            stall_timeout: stall_timeout(),
+        })
     }
 
     fn step<C>(&self, circuit: &C) -> Result<(), Error>
@@ -62,6 +75,7 @@ impl Scheduler for StaticScheduler {
                 }
                 circuit.eval_node(*node_id)?;
             } else {
+                let stall_deadline = self.stall_timeout.map(|timeout| Instant::now() + timeout);
                 loop {
                     if Runtime::kill_in_progress() {
                         return Err(Error::Killed);
@@ -70,6 +84,14 @@ impl Scheduler for StaticScheduler {
                         circuit.eval_node(*node_id)?;
                         break;
                     }
+                    if stall_deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                        return Err(Error::Stalled {
+                            pending_nodes: vec![(
+                                GlobalNodeId::child_of(circuit, *node_id),
+                                circuit.node_name(*node_id),
+                            )],
+                        });
+                    }
                     circuit.log_scheduler_event(&SchedulerEvent::wait_start(
                         circuit.global_id().deref(),
                     ));
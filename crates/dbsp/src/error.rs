@@ -1,5 +1,9 @@
-use crate::{RuntimeError, SchedulerError};
+use crate::{
+    circuit::{CheckpointError, GlobalNodeId},
+    RuntimeError, SchedulerError,
+};
 use std::{
+    borrow::Cow,
     fmt::{Display, Error as FmtError, Formatter},
     io::Error as IOError,
 };
@@ -9,6 +13,21 @@ pub enum Error {
     Scheduler(SchedulerError),
     Runtime(RuntimeError),
     IO(IOError),
+    /// Outputs of the circuit diverged from a recording made with
+    /// [`DBSPHandle::record_outputs`](`crate::DBSPHandle::record_outputs`).
+    /// `diff` lists expected lines missing from the output, prefixed with
+    /// `-`, and unexpected output lines, prefixed with `+`.
+    OutputMismatch {
+        step: u64,
+        diff: String,
+    },
+    /// Failed to save or restore the state of operator `name` with id
+    /// `node_id` (see [`DBSPHandle::checkpoint`](`crate::DBSPHandle::checkpoint`)).
+    Checkpoint {
+        node_id: GlobalNodeId,
+        name: Cow<'static, str>,
+        error: CheckpointError,
+    },
     Custom(String),
 }
 
@@ -24,6 +43,19 @@ impl Display for Error {
             Self::IO(error) => {
                 write!(f, "IO error: '{error}'")
             }
+            Self::OutputMismatch { step, diff } => {
+                write!(f, "output mismatch at step {step}:\n{diff}")
+            }
+            Self::Checkpoint {
+                node_id,
+                name,
+                error,
+            } => {
+                write!(
+                    f,
+                    "checkpoint error in operator '{name}' ({node_id}): {error}"
+                )
+            }
             Self::Custom(error) => f.write_str(error),
         }
     }
@@ -12,6 +12,7 @@ pub mod algebra;
 pub mod mimalloc;
 pub mod monitor;
 pub mod operator;
+pub mod plan;
 pub mod profile;
 pub mod time;
 pub mod trace;
@@ -25,9 +26,12 @@ pub use crate::time::Timestamp;
 
 pub use algebra::{IndexedZSet, ZSet};
 pub use circuit::{
-    ChildCircuit, Circuit, CircuitHandle, DBSPHandle, RootCircuit, Runtime, RuntimeError,
-    SchedulerError, Stream,
+    Checkpoint, ChildCircuit, Circuit, CircuitHandle, DBSPHandle, RootCircuit, Runtime,
+    RuntimeConfig, RuntimeError, SchedulerError, Stream,
+};
+pub use operator::{
+    CollectionHandle, InputHandle, NamedInputHandle, NamedOutputHandle, OutputHandle, Transaction,
+    UpsertHandle,
 };
-pub use operator::{CollectionHandle, InputHandle, OutputHandle, UpsertHandle};
 pub use trace::ord::{OrdIndexedZSet, OrdZSet};
 pub use trace::{DBData, DBTimestamp, DBWeight};
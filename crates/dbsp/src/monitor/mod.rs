@@ -277,6 +277,24 @@ impl TraceMonitorInternal {
         *self.region_stack.last_mut().unwrap() = region;
     }
 
+    /// Returns the node that writes to a stream with the given origin.
+    ///
+    /// The second output stream of a split operator (see
+    /// [`Circuit::add_split_operator`](`crate::circuit::Circuit::add_split_operator`))
+    /// originates from output index `1` of the operator node; all other
+    /// origins identify the node itself.
+    fn stream_origin(&self, origin: &GlobalNodeId) -> GlobalNodeId {
+        if self.circuit.node_ref(origin).is_none() {
+            if let Some(parent) = origin.parent_id() {
+                if matches!(self.circuit.node_ref(&parent), Some(node) if !node.is_circuit()) {
+                    return parent;
+                }
+            }
+        }
+
+        origin.clone()
+    }
+
     fn circuit_event(&mut self, event: &CircuitEvent) -> Result<(), TraceError> {
         //println!("event: {}", event);
         if event.is_node_event() {
@@ -370,7 +388,7 @@ impl TraceMonitorInternal {
                 Ok(())
             }
         } else if event.is_edge_event() {
-            let from = event.from().unwrap();
+            let from = &self.stream_origin(event.from().unwrap());
             let to = event.to().unwrap();
             let kind = event.edge_kind().unwrap();
             self.circuit
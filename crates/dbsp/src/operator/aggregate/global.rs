@@ -0,0 +1,414 @@
+//! Aggregation of an entire relation to a single value.
+
+#[cfg(feature = "persistence")]
+use crate::circuit::checkpoint::{decode_state, encode_state};
+use crate::{
+    algebra::{HasOne, HasZero, Semigroup, ZRingValue, ZSet},
+    circuit::{
+        operator_traits::{BinaryOperator, Operator},
+        CheckpointError, Scope,
+    },
+    operator::{aggregate::Aggregator, communication::new_exchange_operators},
+    trace::{Batch, BatchReader, Builder, Cursor, Spine},
+    Circuit, OrdZSet, RootCircuit, Runtime, Stream,
+};
+use std::{borrow::Cow, collections::HashMap, hash::Hash, marker::PhantomData, panic::Location};
+
+impl<Z> Stream<RootCircuit, Z>
+where
+    Z: ZSet + Send,
+    Z::R: ZRingValue,
+{
+    /// Incrementally aggregate the entire contents of a Z-set to a single
+    /// value.
+    ///
+    /// Transforms a stream of changes to a Z-set to a stream of changes to
+    /// a singleton Z-set that contains the result of applying `aggregator`
+    /// to all keys in the input with weight `+1`, e.g., the total number of
+    /// rows or the global maximum.  When the input relation is empty, the
+    /// output relation is empty too.
+    ///
+    /// Each worker aggregates its own shard of the input.  Partial aggregates
+    /// are sent to worker 0, which combines them using the
+    /// [`Semigroup`](`Aggregator::Semigroup`) of the aggregator and produces
+    /// all outputs of the operator.  Partial aggregates must therefore be
+    /// combinable in any order.
+    ///
+    /// # Performance
+    ///
+    /// Each worker maintains the aggregate of every key in its shard and
+    /// combines them in a balanced tree.  A change to the input only
+    /// re-aggregates the keys it touches, looking up their current weights
+    /// in the integral of the input, and recombines the partial aggregate in
+    /// `O(log n)` semigroup operations per key, where `n` is the number of
+    /// distinct keys in the shard.
+    #[track_caller]
+    pub fn global_aggregate<A>(
+        &self,
+        aggregator: A,
+    ) -> Stream<RootCircuit, OrdZSet<A::Output, Z::R>>
+    where
+        A: Aggregator<Z::Key, (), Z::R>,
+    {
+        let circuit = self.circuit();
+        let stream = self.shard();
+
+        let partial = circuit.add_binary_operator(
+            PartialAggregate::new(aggregator.clone()),
+            &stream,
+            &stream.integrate_trace(),
+        );
+
+        let combined = match Runtime::runtime() {
+            Some(runtime) if runtime.num_workers() > 1 => {
+                let num_workers = runtime.num_workers();
+
+                let (sender, receiver) = new_exchange_operators(
+                    &runtime,
+                    Runtime::worker_index(),
+                    Some(Location::caller()),
+                    move |partial: Option<A::Accumulator>,
+                          partials: &mut Vec<Option<A::Accumulator>>| {
+                        partials.push(partial);
+                        for _ in 1..num_workers {
+                            partials.push(None);
+                        }
+                    },
+                    |result: &mut Option<A::Accumulator>, partial| {
+                        *result = A::Semigroup::combine_opt(result, &partial);
+                    },
+                );
+
+                circuit.add_exchange(sender, receiver, &partial)
+            }
+            _ => partial,
+        };
+
+        combined
+            .apply(move |accumulator: &Option<A::Accumulator>| {
+                let output = accumulator
+                    .iter()
+                    .map(|accumulator| (aggregator.finalize(accumulator.clone()), Z::R::one()))
+                    .collect();
+                OrdZSet::from_keys((), output)
+            })
+            .differentiate()
+    }
+}
+
+/// Aggregates the local shard of the input relation.
+///
+/// Keeps the aggregate of each key in an [`AccumulatorTree`] and only
+/// recomputes the aggregates of keys that occur in the input delta.
+struct PartialAggregate<Z, A>
+where
+    Z: ZSet,
+    A: Aggregator<Z::Key, (), Z::R>,
+{
+    aggregator: A,
+    accumulators: AccumulatorTree<Z::Key, A::Accumulator>,
+    _type: PhantomData<Z>,
+}
+
+impl<Z, A> PartialAggregate<Z, A>
+where
+    Z: ZSet,
+    A: Aggregator<Z::Key, (), Z::R>,
+{
+    fn new(aggregator: A) -> Self {
+        Self {
+            aggregator,
+            accumulators: AccumulatorTree::new(),
+            _type: PhantomData,
+        }
+    }
+
+    /// Aggregates the key under `cursor`, whose weight is the total weight
+    /// of the key in the input relation.
+    fn aggregate_key<C>(&self, cursor: &mut C) -> Option<A::Accumulator>
+    where
+        C: Cursor<Z::Key, (), (), Z::R>,
+    {
+        if !cursor.val_valid() {
+            return None;
+        }
+
+        let weight = cursor.weight();
+        if weight.is_zero() {
+            return None;
+        }
+
+        let mut builder = Z::Builder::with_capacity((), 1);
+        builder.push((Z::item_from(cursor.key().clone(), ()), weight));
+        self.aggregator.aggregate(&mut builder.done().cursor())
+    }
+}
+
+impl<Z, A> Operator for PartialAggregate<Z, A>
+where
+    Z: ZSet,
+    A: Aggregator<Z::Key, (), Z::R>,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("PartialAggregate")
+    }
+
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        panic!("'PartialAggregate' operator used in fixedpoint iteration")
+    }
+
+    #[cfg(feature = "persistence")]
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        if self.accumulators.is_empty() {
+            Ok(None)
+        } else {
+            encode_state(&self.accumulators.entries()).map(Some)
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        let entries: Vec<(Z::Key, A::Accumulator)> = decode_state(state)?;
+
+        self.accumulators = AccumulatorTree::new();
+        for (key, accumulator) in entries {
+            self.accumulators
+                .update::<A::Semigroup>(&key, Some(accumulator));
+        }
+        Ok(())
+    }
+
+    // Keys and accumulators can only be serialized when the `persistence`
+    // feature makes all data types serializable.
+    #[cfg(not(feature = "persistence"))]
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        if self.accumulators.is_empty() {
+            Ok(None)
+        } else {
+            Err(CheckpointError::Unsupported)
+        }
+    }
+}
+
+impl<Z, A> BinaryOperator<Z, Spine<Z>, Option<A::Accumulator>> for PartialAggregate<Z, A>
+where
+    Z: ZSet,
+    A: Aggregator<Z::Key, (), Z::R>,
+{
+    fn eval(&mut self, delta: &Z, trace: &Spine<Z>) -> Option<A::Accumulator> {
+        let mut delta_cursor = delta.cursor();
+        let mut trace_cursor = trace.cursor();
+
+        while delta_cursor.key_valid() {
+            let key = delta_cursor.key();
+
+            // `trace` already includes `delta`.
+            trace_cursor.seek_key(key);
+            let accumulator = if trace_cursor.key_valid() && trace_cursor.key() == key {
+                self.aggregate_key(&mut trace_cursor)
+            } else {
+                None
+            };
+            self.accumulators.update::<A::Semigroup>(key, accumulator);
+
+            delta_cursor.step_key();
+        }
+
+        self.accumulators.aggregate()
+    }
+}
+
+/// Accumulators of individual keys combined in a segment tree.
+///
+/// Each key with an accumulator occupies a leaf of a complete binary tree
+/// stored in an array; every inner node caches the combined accumulator of
+/// its subtree.  Updating the accumulator of a key recombines the `O(log n)`
+/// nodes on the path from its leaf to the root, which holds the aggregate
+/// of all keys.  Leaves are assigned to keys in no particular order, so the
+/// semigroup must be commutative.
+struct AccumulatorTree<K, A> {
+    // Leaf index of each key.
+    leaves: HashMap<K, usize>,
+    // Leaves released by removed keys.
+    free: Vec<usize>,
+    // Number of leaves, a power of two.
+    capacity: usize,
+    // Node `i` has children `2 * i` and `2 * i + 1`; the root is node `1` and
+    // leaf `j` is node `capacity + j`.
+    nodes: Vec<Option<A>>,
+}
+
+impl<K, A> AccumulatorTree<K, A>
+where
+    K: Clone + Eq + Hash,
+    A: Clone,
+{
+    fn new() -> Self {
+        Self {
+            leaves: HashMap::new(),
+            free: Vec::new(),
+            capacity: 1,
+            nodes: vec![None, None],
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Aggregate of all keys.
+    fn aggregate(&self) -> Option<A> {
+        self.nodes[1].clone()
+    }
+
+    /// Sets the accumulator of `key`, removing the key if `accumulator` is
+    /// `None`.
+    fn update<S>(&mut self, key: &K, accumulator: Option<A>)
+    where
+        S: Semigroup<A>,
+    {
+        let leaf = match (self.leaves.get(key), accumulator.is_some()) {
+            (Some(&leaf), true) => leaf,
+            (Some(&leaf), false) => {
+                self.leaves.remove(key);
+                self.free.push(leaf);
+                leaf
+            }
+            (None, true) => {
+                let leaf = self.free.pop().unwrap_or(self.leaves.len());
+                if leaf == self.capacity {
+                    self.grow::<S>();
+                }
+                self.leaves.insert(key.clone(), leaf);
+                leaf
+            }
+            (None, false) => return,
+        };
+
+        let mut node = self.capacity + leaf;
+        self.nodes[node] = accumulator;
+        while node > 1 {
+            node /= 2;
+            self.nodes[node] = S::combine_opt(&self.nodes[2 * node], &self.nodes[2 * node + 1]);
+        }
+    }
+
+    /// Doubles the number of leaves.
+    fn grow<S>(&mut self)
+    where
+        S: Semigroup<A>,
+    {
+        let capacity = self.capacity * 2;
+        let mut nodes = vec![None; 2 * capacity];
+        for leaf in 0..self.capacity {
+            nodes[capacity + leaf] = self.nodes[self.capacity + leaf].take();
+        }
+        for node in (1..capacity).rev() {
+            nodes[node] = S::combine_opt(&nodes[2 * node], &nodes[2 * node + 1]);
+        }
+
+        self.capacity = capacity;
+        self.nodes = nodes;
+    }
+
+    /// Keys and their accumulators.
+    #[cfg(feature = "persistence")]
+    fn entries(&self) -> Vec<(K, A)> {
+        self.leaves
+            .iter()
+            .map(|(key, &leaf)| {
+                (
+                    key.clone(),
+                    self.nodes[self.capacity + leaf].clone().unwrap(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algebra::DefaultSemigroup,
+        operator::{Fold, Max},
+        zset, OrdZSet, Runtime,
+    };
+    use proptest::{collection, prelude::*};
+    use std::collections::BTreeMap;
+
+    type Input = Vec<Vec<(i64, isize)>>;
+
+    fn global_aggregate_test(workers: usize, inputs: Input) {
+        let (mut dbsp, (mut input_handle, count_handle, max_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (input, input_handle) = circuit.add_input_zset::<i64, isize>();
+
+                let count_handle = input
+                    .global_aggregate(<Fold<_, DefaultSemigroup<_>, _, _>>::new(
+                        0isize,
+                        |count: &mut isize, _key: &i64, weight: isize| *count += weight,
+                    ))
+                    .integrate()
+                    .output();
+                let max_handle = input.global_aggregate(Max).integrate().output();
+
+                (input_handle, count_handle, max_handle)
+            })
+            .unwrap();
+
+        let mut contents = BTreeMap::new();
+
+        for mut step in inputs {
+            for (key, weight) in step.iter() {
+                *contents.entry(*key).or_insert(0) += weight;
+            }
+            contents.retain(|_, weight| *weight != 0);
+
+            input_handle.append(&mut step);
+            dbsp.step().unwrap();
+
+            let (expected_count, expected_max): (OrdZSet<isize, isize>, OrdZSet<i64, isize>) =
+                match contents.keys().next_back() {
+                    Some(max) => (zset! { contents.values().sum() => 1 }, zset! { *max => 1 }),
+                    None => (zset! {}, zset! {}),
+                };
+
+            assert_eq!(count_handle.consolidate(), expected_count);
+            assert_eq!(max_handle.consolidate(), expected_max);
+        }
+
+        dbsp.kill().unwrap();
+    }
+
+    fn global_aggregate_steps() -> Input {
+        vec![
+            vec![(1, 1), (5, 2), (3, 1)],
+            vec![(5, -1)],
+            vec![(5, -1)],
+            vec![],
+            vec![(1, -1), (3, -1)],
+            vec![(-2, 1)],
+        ]
+    }
+
+    #[test]
+    fn global_aggregate_test1() {
+        global_aggregate_test(1, global_aggregate_steps());
+    }
+
+    #[test]
+    fn global_aggregate_test4() {
+        global_aggregate_test(4, global_aggregate_steps());
+    }
+
+    fn input() -> impl Strategy<Value = Input> {
+        collection::vec(collection::vec((-10..10i64, -2..=2isize), 0..10), 0..20)
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_global_aggregate(inputs in input(), workers in 1..=4usize) {
+            global_aggregate_test(workers, inputs);
+        }
+    }
+}
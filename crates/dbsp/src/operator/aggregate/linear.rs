@@ -0,0 +1,225 @@
+#[cfg(feature = "check-linear-aggregates")]
+use crate::{algebra::DefaultSemigroup, operator::Fold, trace::Builder, DBWeight};
+use crate::{
+    algebra::{
+        AddAssignByRef, AddByRef, GroupValue, HasOne, HasZero, IndexedZSet, MulByRef, ZRingValue,
+    },
+    trace::{Batch, BatchReader, Cursor, Spine},
+    DBData, OrdIndexedZSet, OrdZSet, RootCircuit, Stream,
+};
+use std::ops::Neg;
+
+impl<Z> Stream<RootCircuit, Z>
+where
+    Z: IndexedZSet + Send,
+    Z::R: ZRingValue,
+{
+    /// Incremental aggregation operator for linear aggregation functions.
+    ///
+    /// Computes the same result as [`Self::aggregate_linear`]: for each key
+    /// `k` in the input collection, outputs `(k, a)`, where `a` is the sum
+    /// of `f(k, v) * w` over all `(k, v, w)` tuples in the collection.  As
+    /// with `aggregate_linear`, `f` must be linear, i.e., `f(a+b) = f(a) +
+    /// f(b)`, and keys whose aggregate is zero are not in the output.
+    ///
+    /// Unlike `aggregate_linear`, this operator never reads the contents of
+    /// a group.  It maintains a trace of running per-key sums and, on each
+    /// step, adds the contribution of the input delta to the sums of the
+    /// keys it modifies, so the cost of each step is proportional to the
+    /// size of the delta rather than the size of the modified groups.
+    ///
+    /// The operator trusts `f` to be linear.  Enabling the
+    /// `check-linear-aggregates` feature cross-checks each output delta
+    /// against the output of the non-linear [`aggregate`](`Self::aggregate`)
+    /// operator, which re-evaluates `f` over every modified group, and panics
+    /// on mismatch.  This requires storing the entire input collection and
+    /// should only be used for testing.
+    pub fn aggregate_linear_incremental<F, A>(
+        &self,
+        f: F,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<Z::Key, A, Z::R>>
+    where
+        A: DBData + MulByRef<Z::R, Output = A> + GroupValue,
+        F: Fn(&Z::Key, &Z::Val) -> A + Clone + 'static,
+    {
+        let delta = self.weigh(f.clone()).shard();
+
+        let output = delta
+            .apply2(
+                &delta.integrate_trace().delay_trace(),
+                |delta: &OrdZSet<Z::Key, A>, sums: &Spine<OrdZSet<Z::Key, A>>| {
+                    let mut output = Vec::with_capacity(2 * delta.key_count());
+                    let mut delta_cursor = delta.cursor();
+                    let mut sums_cursor = sums.cursor();
+
+                    while delta_cursor.key_valid() {
+                        let key = delta_cursor.key();
+
+                        let mut old = A::zero();
+                        sums_cursor.seek_key(key);
+                        if sums_cursor.key_valid() && sums_cursor.key() == key {
+                            sums_cursor.map_times(|_, sum| old.add_assign_by_ref(sum));
+                        }
+                        let new = old.add_by_ref(&delta_cursor.weight());
+
+                        if new != old {
+                            if !old.is_zero() {
+                                output.push(((key.clone(), old), Z::R::one().neg()));
+                            }
+                            if !new.is_zero() {
+                                output.push(((key.clone(), new), Z::R::one()));
+                            }
+                        }
+                        delta_cursor.step_key();
+                    }
+
+                    OrdIndexedZSet::from_tuples((), output)
+                },
+            )
+            .mark_sharded();
+
+        #[cfg(feature = "check-linear-aggregates")]
+        self.check_linear_aggregate(&output, f);
+
+        output
+    }
+
+    /// Checks that each output delta of
+    /// [`Self::aggregate_linear_incremental`] matches the output of the
+    /// non-linear [`aggregate`](`Self::aggregate`) operator computing the same
+    /// sums.
+    #[cfg(feature = "check-linear-aggregates")]
+    fn check_linear_aggregate<F, A>(
+        &self,
+        output: &Stream<RootCircuit, OrdIndexedZSet<Z::Key, A, Z::R>>,
+        f: F,
+    ) where
+        A: DBData + MulByRef<Z::R, Output = A> + GroupValue,
+        F: Fn(&Z::Key, &Z::Val) -> A + Clone + 'static,
+    {
+        // Pair each value with its key, so the aggregator can apply `f`.
+        let expected = self
+            .apply(|batch: &Z| {
+                let mut builder =
+                    <OrdIndexedZSet<Z::Key, (Z::Key, Z::Val), Z::R> as Batch>::Builder::with_capacity(
+                        (),
+                        batch.len(),
+                    );
+                let mut cursor = batch.cursor();
+                while cursor.key_valid() {
+                    while cursor.val_valid() {
+                        let key = cursor.key().clone();
+                        let val = (key.clone(), cursor.val().clone());
+                        builder.push(((key, val), cursor.weight()));
+                        cursor.step_val();
+                    }
+                    cursor.step_key();
+                }
+                builder.done()
+            })
+            .aggregate(<Fold<_, DefaultSemigroup<_>, _, _>>::new(
+                A::zero(),
+                move |sum: &mut A, (key, val): &(Z::Key, Z::Val), weight: Z::R| {
+                    sum.add_assign_by_ref(&f(key, val).mul_by_ref(&weight))
+                },
+            ));
+
+        output.apply2(
+            &expected,
+            |actual: &OrdIndexedZSet<Z::Key, A, Z::R>,
+             expected: &OrdIndexedZSet<Z::Key, A, Z::R>| {
+                // `aggregate` outputs zero sums, which
+                // `aggregate_linear_incremental` omits.
+                let actual = non_zero_sums(actual);
+                let expected = non_zero_sums(expected);
+                if actual != expected {
+                    panic!(
+                        "aggregate_linear_incremental: non-linear aggregation function \
+                         (incremental output: {actual:?}, expected: {expected:?})"
+                    );
+                }
+            },
+        );
+    }
+}
+
+/// Returns the `(key, sum, weight)` tuples in `batch` whose sum is not zero.
+#[cfg(feature = "check-linear-aggregates")]
+fn non_zero_sums<K, A, R>(batch: &OrdIndexedZSet<K, A, R>) -> Vec<(K, A, R)>
+where
+    K: DBData,
+    A: DBData + HasZero,
+    R: DBWeight,
+{
+    let mut sums = Vec::with_capacity(batch.len());
+    let mut cursor = batch.cursor();
+    while cursor.key_valid() {
+        while cursor.val_valid() {
+            if !cursor.val().is_zero() {
+                sums.push((cursor.key().clone(), cursor.val().clone(), cursor.weight()));
+            }
+            cursor.step_val();
+        }
+        cursor.step_key();
+    }
+    sums
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, Runtime};
+
+    fn aggregate_linear_incremental_test(workers: usize) {
+        let (mut dbsp, (mut input, output)) = Runtime::init_circuit(workers, |circuit| {
+            let (stream, handle) = circuit.add_input_indexed_zset::<u64, i64, isize>();
+
+            let sum = stream.aggregate_linear_incremental(|_key, val: &i64| *val);
+
+            (handle, sum.integrate().output())
+        })
+        .unwrap();
+
+        input.append(&mut vec![(1, (10, 1)), (1, (20, 1)), (2, (5, 2))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { 30 => 1 }, 2 => { 10 => 1 } }
+        );
+
+        // Only the delta contributes to the running sum of key 1.
+        input.append(&mut vec![(1, (5, 1)), (3, (7, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { 35 => 1 }, 2 => { 10 => 1 }, 3 => { 7 => 1 } }
+        );
+
+        // Keys whose sum drops to zero disappear from the output.
+        input.append(&mut vec![(2, (5, -2)), (1, (20, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { 15 => 1 }, 3 => { 7 => 1 } }
+        );
+
+        // Changes that cancel out don't modify the output.
+        input.append(&mut vec![(3, (1, 1)), (3, (-1, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { 15 => 1 }, 3 => { 7 => 1 } }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn aggregate_linear_incremental_test1() {
+        aggregate_linear_incremental_test(1);
+    }
+
+    #[test]
+    fn aggregate_linear_incremental_test4() {
+        aggregate_linear_incremental_test(4);
+    }
+}
@@ -15,7 +15,7 @@ use crate::{
     },
     circuit::{
         operator_traits::{BinaryOperator, Operator, UnaryOperator},
-        Circuit, Scope, Stream, WithClock,
+        CheckpointError, Circuit, Scope, Stream, WithClock,
     },
     time::Timestamp,
     trace::{
@@ -25,16 +25,26 @@ use crate::{
     DBData, DBTimestamp, DBWeight, OrdIndexedZSet, OrdZSet,
 };
 
+mod global;
+mod linear;
+mod stateful;
+
 // Some standard aggregators.
 mod average;
 mod fold;
 mod max;
 mod min;
+mod percentile;
+mod string_agg;
+mod topk;
+mod updates;
 
 pub use average::Avg;
 pub use fold::Fold;
 pub use max::{Max, MaxSemigroup};
 pub use min::{Min, MinSemigroup};
+pub use percentile::Percentile;
+pub use updates::Update;
 
 /// A trait for aggregator objects.  An aggregator summarizes the contents
 /// of a Z-set into a single value.
@@ -243,6 +253,31 @@ where
             .mark_sharded()
     }
 
+    /// Re-index the input stream using `map_func` and aggregate the result.
+    ///
+    /// This is equivalent to `map_index(map_func).aggregate(aggregator)`,
+    /// but fuses re-indexing with the sharding step performed by `aggregate`
+    /// (see [`Self::map_index_shard`]).  In a multi-worker runtime, this saves
+    /// building one batch per step: mapped records are sorted directly into
+    /// per-worker batches instead of being assembled into a batch that is
+    /// then split by `shard`.  With a single worker, the two are equivalent.
+    #[allow(clippy::type_complexity)]
+    pub fn map_aggregate<F, K, V, A>(
+        &self,
+        map_func: F,
+        aggregator: A,
+    ) -> Stream<C, OrdIndexedZSet<K, A::Output, Z::R>>
+    where
+        Z: BatchReader<Time = ()> + Send,
+        Z::R: ZRingValue,
+        F: Fn((&Z::Key, &Z::Val)) -> (K, V) + 'static,
+        K: DBData,
+        V: DBData,
+        A: Aggregator<V, <C as WithClock>::Time, Z::R>,
+    {
+        self.map_index_shard(map_func).aggregate(aggregator)
+    }
+
     /// A version of [`Self::aggregate`] optimized for linear
     /// aggregation functions.
     ///
@@ -350,6 +385,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<Z, A, O> UnaryOperator<Z, O> for Aggregate<Z, A, O>
@@ -587,6 +626,16 @@ where
                 .keys()
                 .all(|ts| !ts.less_equal(&epoch_end))
     }
+
+    // Updates scheduled for future timestamps only exist in nested circuits,
+    // where they span parent clock cycles, and are not serializable.
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        if self.keys_of_interest.is_empty() {
+            Ok(None)
+        } else {
+            Err(CheckpointError::Unsupported)
+        }
+    }
 }
 
 impl<Z, IT, A, Clk> BinaryOperator<Z, IT, Vec<(Z::Key, Option<A::Output>)>>
@@ -696,7 +745,7 @@ mod test {
         algebra::DefaultSemigroup,
         indexed_zset,
         operator::GeneratorNested,
-        operator::{Fold, Min},
+        operator::{FilterMap, Fold, Min},
         trace::{cursor::Cursor, Batch, BatchReader},
         zset, Circuit, OrdIndexedZSet, OrdZSet, RootCircuit, Runtime, Stream,
     };
@@ -733,6 +782,23 @@ mod test {
                 // value and is therefore linear.
                 let sum_linear = |_key: &usize, val: &isize| -> isize { *val };
 
+                // Re-index by value before aggregating to test `map_aggregate`.
+                let by_val = |(k, v): (&usize, &isize)| (*v, *k as isize);
+                let sum_map = input.map_aggregate(by_val, sum.clone()).gather(0);
+                let sum_map_unfused = input.map_index(by_val).aggregate(sum.clone()).gather(0);
+
+                sum_map
+                    .apply2(
+                        &sum_map_unfused,
+                        |d1: &OrdIndexedZSet<isize, isize, isize>,
+                         d2: &OrdIndexedZSet<isize, isize, isize>| {
+                            (d1.clone(), d2.clone())
+                        },
+                    )
+                    .inspect(|(d1, d2)| {
+                        assert_eq!(d1, d2);
+                    });
+
                 let sum_inc = input.aggregate(sum.clone()).gather(0);
                 let sum_inc_linear: Stream<_, OrdIndexedZSet<usize, isize, isize>> =
                     input.aggregate_linear(sum_linear).gather(0);
@@ -0,0 +1,209 @@
+use crate::{
+    algebra::{AddAssignByRef, HasZero, UnimplementedSemigroup, ZRingValue},
+    operator::aggregate::Aggregator,
+    trace::Cursor,
+    DBData, Timestamp,
+};
+use num::ToPrimitive;
+
+/// An [aggregator](`crate::operator::Aggregator`) that returns the value at
+/// quantile `q` of a group.
+///
+/// Weights are interpreted as multiplicities: a value with weight `w > 0`
+/// counts as `w` copies of the value, and values with non-positive weights
+/// are ignored.  Given `n` copies of values in the group, the aggregator
+/// uses the nearest-rank method: it returns the value at rank
+/// `ceil(q * n)` (counting from 1) in sorted order, or the smallest value
+/// if `q` is 0.  In particular, the median of a group with an even number
+/// of values is the lower of the two middle values.
+///
+/// The aggregator scans the sorted values of the group in place without
+/// copying them.  It makes one pass to count values and a second pass from
+/// the nearest end of the group to locate the rank, so quantiles close to
+/// 0 or 1 only touch a few values in the second pass.
+#[derive(Clone, Debug)]
+pub struct Percentile {
+    q: f64,
+}
+
+impl Percentile {
+    /// Create an aggregator that computes quantile `q`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `q` is not in the range `[0, 1]`.
+    pub fn new(q: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&q),
+            "quantile must be in the range [0, 1], got {q}"
+        );
+        Self { q }
+    }
+
+    /// Create an aggregator that computes the median, i.e., quantile 0.5.
+    pub fn median() -> Self {
+        Self::new(0.5)
+    }
+
+    /// Rank (counting from 1) of the value at quantile `self.q` in a group
+    /// of `count > 0` values.
+    fn rank(&self, count: u64) -> u64 {
+        ((self.q * count as f64).ceil() as u64).clamp(1, count)
+    }
+}
+
+/// Number of copies of the value under `cursor`.
+fn multiplicity<C, V, T, R>(cursor: &mut C) -> u64
+where
+    C: Cursor<V, (), T, R>,
+    R: ZRingValue + ToPrimitive,
+{
+    let mut weight = R::zero();
+    cursor.map_times(|_t, w| weight.add_assign_by_ref(w));
+
+    if weight.ge0() {
+        weight.to_u64().unwrap_or_default()
+    } else {
+        0
+    }
+}
+
+impl<V, T, R> Aggregator<V, T, R> for Percentile
+where
+    V: DBData,
+    T: Timestamp,
+    R: ZRingValue + ToPrimitive,
+{
+    type Accumulator = V;
+    type Output = V;
+    type Semigroup = UnimplementedSemigroup<V>;
+
+    fn aggregate<C>(&self, cursor: &mut C) -> Option<Self::Accumulator>
+    where
+        C: Cursor<V, (), T, R>,
+    {
+        let mut count = 0;
+        while cursor.key_valid() {
+            count += multiplicity(cursor);
+            cursor.step_key();
+        }
+
+        if count == 0 {
+            return None;
+        }
+
+        let rank = self.rank(count);
+
+        // Scan from whichever end of the group is closer to `rank`.
+        let mut seen = 0;
+        if rank <= count / 2 {
+            cursor.rewind_keys();
+            while cursor.key_valid() {
+                seen += multiplicity(cursor);
+                if seen >= rank {
+                    return Some(cursor.key().clone());
+                }
+                cursor.step_key();
+            }
+        } else {
+            let reverse_rank = count - rank + 1;
+            cursor.fast_forward_keys();
+            while cursor.key_valid() {
+                seen += multiplicity(cursor);
+                if seen >= reverse_rank {
+                    return Some(cursor.key().clone());
+                }
+                cursor.step_key_reverse();
+            }
+        }
+
+        unreachable!()
+    }
+
+    fn finalize(&self, accumulator: Self::Accumulator) -> Self::Output {
+        accumulator
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Percentile;
+    use crate::{indexed_zset, Runtime};
+
+    fn percentile_test(workers: usize) {
+        let (mut dbsp, (mut input, median, p90, min)) = Runtime::init_circuit(workers, |circuit| {
+            let (stream, handle) = circuit.add_input_indexed_zset::<u64, i64, isize>();
+
+            (
+                handle,
+                stream.aggregate(Percentile::median()).integrate().output(),
+                stream.aggregate(Percentile::new(0.9)).integrate().output(),
+                stream.aggregate(Percentile::new(0.0)).integrate().output(),
+            )
+        })
+        .unwrap();
+
+        // Values of key 1 with multiplicities: 1, 2, 2, 3, 10 (5 values).
+        input.append(&mut vec![
+            (1, (3, 1)),
+            (1, (1, 1)),
+            (1, (2, 2)),
+            (1, (10, 1)),
+            (2, (7, 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            median.consolidate(),
+            indexed_zset! { 1 => { 2 => 1 }, 2 => { 7 => 1 } }
+        );
+        assert_eq!(
+            p90.consolidate(),
+            indexed_zset! { 1 => { 10 => 1 }, 2 => { 7 => 1 } }
+        );
+        assert_eq!(
+            min.consolidate(),
+            indexed_zset! { 1 => { 1 => 1 }, 2 => { 7 => 1 } }
+        );
+
+        // Deleting one copy of 2 and the value 1: 2, 3, 10.  Adding three
+        // copies of 4 to key 2: 4, 4, 4, 7, so the median is the lower middle
+        // value.
+        input.append(&mut vec![(1, (2, -1)), (1, (1, -1)), (2, (4, 3))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            median.consolidate(),
+            indexed_zset! { 1 => { 3 => 1 }, 2 => { 4 => 1 } }
+        );
+        assert_eq!(
+            p90.consolidate(),
+            indexed_zset! { 1 => { 10 => 1 }, 2 => { 7 => 1 } }
+        );
+        assert_eq!(
+            min.consolidate(),
+            indexed_zset! { 1 => { 2 => 1 }, 2 => { 4 => 1 } }
+        );
+
+        // Deleting all values of a key removes it from the output.
+        input.append(&mut vec![(1, (2, -1)), (1, (3, -1)), (1, (10, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(median.consolidate(), indexed_zset! { 2 => { 4 => 1 } });
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn percentile_test1() {
+        percentile_test(1);
+    }
+
+    #[test]
+    fn percentile_test4() {
+        percentile_test(4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn percentile_out_of_range() {
+        Percentile::new(1.5);
+    }
+}
@@ -0,0 +1,283 @@
+//! Aggregation with incrementally maintained per-key state.
+
+use crate::{
+    algebra::{HasOne, HasZero, IndexedZSet, ZRingValue},
+    circuit::{ExportId, ExportStream, OwnershipPreference},
+    operator::{
+        trace::{DelayedTraceId, IntegrateTraceId, TraceBounds, UntimedTraceAppend, Z1Trace},
+        FilterMap,
+    },
+    trace::{Batch, BatchReader, Cursor, Spine},
+    Circuit, DBData, OrdIndexedZSet, RootCircuit, Stream,
+};
+use std::ops::Neg;
+
+impl<Z> Stream<RootCircuit, Z>
+where
+    Z: IndexedZSet + Send,
+    Z::R: ZRingValue,
+{
+    /// Incremental aggregation operator that maintains per-key aggregation
+    /// state of a custom type.
+    ///
+    /// Unlike [`Self::aggregate`], which recomputes the aggregate of a key
+    /// from all of its values whenever the key changes, this operator keeps
+    /// state `S` for each key and updates it with the changes to the key:
+    /// `add(state, value, weight)` is invoked for each value inserted with
+    /// positive `weight`, and `remove(state, value, weight)` is invoked with
+    /// the absolute value of `weight` for each value deleted.  The output
+    /// contains `out(state)` for each key.  This is useful for aggregates
+    /// whose state is expensive to recompute but cheap to update, e.g., a
+    /// running variance.
+    ///
+    /// The state of a key is initialized with `S::default()` when the key
+    /// first appears in the input, and discarded when the total weight of
+    /// the key drops to zero.  `remove` must undo the effect of `add`, so
+    /// that the state only depends on the current contents of the key.
+    ///
+    /// This is a stateful operator that internally maintains the trace of
+    /// per-key states.
+    #[allow(clippy::type_complexity)]
+    pub fn aggregate_stateful<S, AddF, RemoveF, OutF, O>(
+        &self,
+        add: AddF,
+        remove: RemoveF,
+        out: OutF,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<Z::Key, O, Z::R>>
+    where
+        S: DBData + Default,
+        O: DBData,
+        AddF: Fn(&mut S, &Z::Val, Z::R) + 'static,
+        RemoveF: Fn(&mut S, &Z::Val, Z::R) + 'static,
+        OutF: Fn(&S) -> O + 'static,
+    {
+        let circuit = self.circuit();
+        let sharded = self.shard();
+
+        // Per-key states are accumulated into a trace using an integrator
+        // (UntimedTraceAppend + Z1Trace).  Each state is stored along with the
+        // total weight of the key.  Changes are evaluated against the delayed
+        // trace, which contains states as of the previous clock cycle.
+        circuit.region("aggregate_stateful", || {
+            let bounds = <TraceBounds<Z::Key, (S, Z::R)>>::unbounded();
+
+            let (ExportStream { local, export }, z1feedback) = circuit
+                .add_feedback_with_export(Z1Trace::new(true, circuit.root_scope(), bounds.clone()));
+            local.mark_sharded();
+
+            let states = local.apply2(
+                &sharded,
+                move |states: &Spine<OrdIndexedZSet<Z::Key, (S, Z::R), Z::R>>, delta: &Z| {
+                    let mut output = Vec::new();
+                    let mut delta_cursor = delta.cursor();
+                    let mut states_cursor = states.cursor();
+
+                    while delta_cursor.key_valid() {
+                        let key = delta_cursor.key();
+                        let mut old_state = None;
+
+                        states_cursor.seek_key(key);
+                        if states_cursor.key_valid() && states_cursor.key() == key {
+                            while states_cursor.val_valid() {
+                                let weight = states_cursor.weight();
+                                if weight.gt0() {
+                                    old_state = Some(states_cursor.val().clone());
+                                    break;
+                                }
+                                states_cursor.step_val();
+                            }
+                        }
+
+                        let (mut state, mut count) = old_state
+                            .clone()
+                            .unwrap_or_else(|| (S::default(), Z::R::zero()));
+
+                        while delta_cursor.val_valid() {
+                            let weight = delta_cursor.weight();
+                            if weight.ge0() {
+                                add(&mut state, delta_cursor.val(), weight.clone());
+                            } else {
+                                remove(&mut state, delta_cursor.val(), weight.clone().neg());
+                            }
+                            count += weight;
+                            delta_cursor.step_val();
+                        }
+
+                        if let Some(old_state) = old_state {
+                            output.push(((key.clone(), old_state), Z::R::one().neg()));
+                        }
+                        if !count.is_zero() {
+                            output.push(((key.clone(), (state, count)), Z::R::one()));
+                        }
+
+                        delta_cursor.step_key();
+                    }
+
+                    OrdIndexedZSet::from_tuples((), output)
+                },
+            );
+            states.mark_sharded();
+
+            let trace = circuit.add_binary_operator_with_preference(
+                UntimedTraceAppend::<Spine<OrdIndexedZSet<Z::Key, (S, Z::R), Z::R>>>::new(),
+                (&local, OwnershipPreference::STRONGLY_PREFER_OWNED),
+                (&states, OwnershipPreference::PREFER_OWNED),
+            );
+            trace.mark_sharded();
+
+            z1feedback.connect_with_preference(&trace, OwnershipPreference::STRONGLY_PREFER_OWNED);
+
+            circuit.cache_insert(DelayedTraceId::new(trace.origin_node_id().clone()), local);
+            circuit.cache_insert(ExportId::new(trace.origin_node_id().clone()), export);
+            circuit.cache_insert(
+                IntegrateTraceId::new(states.origin_node_id().clone()),
+                (trace, bounds),
+            );
+
+            // Replacing the state of a key retracts the output computed from
+            // the old state.  Outputs that don't change cancel out.
+            let output = states.map_index(move |(key, (state, _count))| (key.clone(), out(state)));
+            output.mark_sharded();
+            output
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algebra::F64,
+        trace::{cursor::Cursor, BatchReader},
+        OrdIndexedZSet, Runtime,
+    };
+    use std::collections::BTreeMap;
+
+    /// Welford's online variance: `(count, mean, m2)`.
+    type Welford = (i64, F64, F64);
+
+    fn welford_add(state: &mut Welford, value: &i64, weight: isize) {
+        let (count, mean, m2) = state;
+        let (value, weight) = (*value as f64, weight as f64);
+
+        let new_count = *count as f64 + weight;
+        let delta = value - mean.into_inner();
+        let new_mean = mean.into_inner() + delta * weight / new_count;
+
+        *m2 = F64::new(m2.into_inner() + weight * delta * (value - new_mean));
+        *mean = F64::new(new_mean);
+        *count += weight as i64;
+    }
+
+    fn welford_remove(state: &mut Welford, value: &i64, weight: isize) {
+        let (count, mean, m2) = state;
+        let (value, weight) = (*value as f64, weight as f64);
+
+        let new_count = *count as f64 - weight;
+        if new_count == 0.0 {
+            (*count, *mean, *m2) = Welford::default();
+            return;
+        }
+
+        let new_mean = (*count as f64 * mean.into_inner() - weight * value) / new_count;
+
+        *m2 = F64::new(m2.into_inner() - weight * (value - new_mean) * (value - mean.into_inner()));
+        *mean = F64::new(new_mean);
+        *count -= weight as i64;
+    }
+
+    fn welford_variance(state: &Welford) -> F64 {
+        F64::new(state.2.into_inner() / state.0 as f64)
+    }
+
+    fn batch_variance(values: &BTreeMap<i64, isize>) -> f64 {
+        let count: isize = values.values().sum();
+        let mean = values
+            .iter()
+            .map(|(value, weight)| *value as f64 * *weight as f64)
+            .sum::<f64>()
+            / count as f64;
+
+        values
+            .iter()
+            .map(|(value, weight)| (*value as f64 - mean).powi(2) * *weight as f64)
+            .sum::<f64>()
+            / count as f64
+    }
+
+    fn aggregate_stateful_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, output_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (input, input_handle) = circuit.add_input_indexed_zset::<u64, i64, isize>();
+                let output_handle = input
+                    .aggregate_stateful(welford_add, welford_remove, welford_variance)
+                    .integrate()
+                    .output();
+
+                (input_handle, output_handle)
+            })
+            .unwrap();
+
+        let steps: Vec<Vec<(u64, (i64, isize))>> = vec![
+            vec![(1, (2, 1)), (1, (4, 1)), (1, (9, 2)), (2, (5, 1))],
+            vec![(1, (4, -1)), (2, (7, 1)), (2, (-3, 3))],
+            vec![(1, (10, 1)), (1, (9, -1)), (2, (5, -1))],
+            // Delete key 2.
+            vec![(2, (7, -1)), (2, (-3, -3))],
+            vec![(1, (2, -1)), (2, (1, 1)), (2, (1, 1)), (2, (100, 1))],
+            vec![(1, (9, -1)), (1, (10, -1))],
+        ];
+
+        let mut contents: BTreeMap<u64, BTreeMap<i64, isize>> = BTreeMap::new();
+
+        for mut step in steps {
+            for (key, (value, weight)) in step.iter() {
+                let values = contents.entry(*key).or_default();
+                *values.entry(*value).or_default() += weight;
+                values.retain(|_, weight| *weight != 0);
+            }
+            contents.retain(|_, values| !values.is_empty());
+
+            input_handle.append(&mut step);
+            dbsp.step().unwrap();
+
+            let output: OrdIndexedZSet<u64, F64, isize> = output_handle.consolidate();
+            let mut actual = BTreeMap::new();
+            let mut cursor = output.cursor();
+            while cursor.key_valid() {
+                while cursor.val_valid() {
+                    assert_eq!(cursor.weight(), 1);
+                    assert!(actual
+                        .insert(*cursor.key(), cursor.val().into_inner())
+                        .is_none());
+                    cursor.step_val();
+                }
+                cursor.step_key();
+            }
+
+            assert_eq!(
+                actual.keys().collect::<Vec<_>>(),
+                contents.keys().collect::<Vec<_>>()
+            );
+            for (key, values) in contents.iter() {
+                let expected = batch_variance(values);
+                assert!(
+                    (actual[key] - expected).abs() < 1e-9,
+                    "key {key}: expected variance {expected}, found {}",
+                    actual[key]
+                );
+            }
+        }
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn aggregate_stateful_test1() {
+        aggregate_stateful_test(1);
+    }
+
+    #[test]
+    fn aggregate_stateful_test4() {
+        aggregate_stateful_test(4);
+    }
+}
@@ -0,0 +1,165 @@
+//! Incremental string aggregation, similar to SQL `STRING_AGG`.
+
+use crate::{
+    algebra::{AddAssignByRef, HasOne, HasZero, Semigroup, ZRingValue, ZSet},
+    circuit::WithClock,
+    operator::aggregate::Fold,
+    trace::BatchReader,
+    Circuit, DBData, DBTimestamp, OrdIndexedZSet, Stream,
+};
+use std::marker::PhantomData;
+
+/// Semigroup over sorted lists of values.
+///
+/// `combine` merges two sorted lists into a sorted list, so partial
+/// aggregates can be combined in any order.
+#[derive(Clone)]
+struct ConcatSemigroup<V>(PhantomData<V>);
+
+impl<V> Semigroup<Vec<V>> for ConcatSemigroup<V>
+where
+    V: Ord + Clone,
+{
+    fn combine(left: &Vec<V>, right: &Vec<V>) -> Vec<V> {
+        let mut result = Vec::with_capacity(left.len() + right.len());
+        let (mut left, mut right) = (left.iter().peekable(), right.iter().peekable());
+
+        while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+            if l <= r {
+                result.push(left.next().unwrap().clone());
+            } else {
+                result.push(right.next().unwrap().clone());
+            }
+        }
+        result.extend(left.cloned());
+        result.extend(right.cloned());
+        result
+    }
+}
+
+impl<C, Z> Stream<C, Z>
+where
+    C: Circuit,
+    <C as WithClock>::Time: DBTimestamp,
+    Z: Clone + 'static,
+{
+    /// Incremental string aggregation, similar to SQL `STRING_AGG` or
+    /// `GROUP_CONCAT`.
+    ///
+    /// Groups records in the input Z-set by `key_func` and, for each group,
+    /// concatenates the strings returned by `value_func` using `separator`.
+    /// Strings are concatenated in the order defined by `order_func`, with
+    /// ties broken by the string value, so the output is deterministic.  A
+    /// record with weight `w > 1` contributes `w` copies of its string.
+    ///
+    /// The output indexed Z-set maps each key to the concatenated string.
+    /// When a group changes, its concatenation is recomputed from the
+    /// integrated input, and the output stream contains a retraction of the
+    /// old string along with the new string.
+    #[allow(clippy::type_complexity)]
+    pub fn string_agg<K, O, KF, VF, OF>(
+        &self,
+        key_func: KF,
+        value_func: VF,
+        separator: &str,
+        order_func: OF,
+    ) -> Stream<C, OrdIndexedZSet<K, String, Z::R>>
+    where
+        Z: ZSet + BatchReader<Time = ()> + Send,
+        Z::R: ZRingValue,
+        K: DBData,
+        O: DBData,
+        KF: Fn(&Z::Key) -> K + 'static,
+        VF: Fn(&Z::Key) -> String + 'static,
+        OF: Fn(&Z::Key) -> O + 'static,
+    {
+        let separator = separator.to_string();
+
+        self.map_aggregate(
+            move |(record, _)| (key_func(record), (order_func(record), value_func(record))),
+            // Values are folded in `(order, string)` order, so the accumulator
+            // is always sorted.
+            Fold::<_, ConcatSemigroup<(O, String)>, _, _>::with_output(
+                Vec::new(),
+                |acc: &mut Vec<(O, String)>, value: &(O, String), weight: Z::R| {
+                    let one = Z::R::one();
+                    let mut count = Z::R::zero();
+                    while count < weight {
+                        acc.push(value.clone());
+                        count.add_assign_by_ref(&one);
+                    }
+                },
+                move |acc: Vec<(O, String)>| {
+                    let values: Vec<String> =
+                        acc.into_iter().map(|(_order, value)| value).collect();
+                    values.join(&separator)
+                },
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ConcatSemigroup;
+    use crate::{
+        algebra::Semigroup, indexed_zset, operator::Generator, zset, Circuit, RootCircuit,
+    };
+
+    #[test]
+    fn concat_semigroup_test() {
+        let left = vec![(1, "a"), (3, "c"), (5, "e")];
+        let right = vec![(2, "b"), (3, "c"), (6, "f")];
+        let expected = vec![(1, "a"), (2, "b"), (3, "c"), (3, "c"), (5, "e"), (6, "f")];
+
+        assert_eq!(ConcatSemigroup::combine(&left, &right), expected);
+        assert_eq!(ConcatSemigroup::combine(&right, &left), expected);
+        assert_eq!(ConcatSemigroup::combine(&left, &Vec::new()), left);
+    }
+
+    #[test]
+    fn string_agg_test() {
+        let (circuit, ()) = RootCircuit::build(move |circuit| {
+            let mut inputs = vec![
+                // Values inserted out of order.
+                zset! {
+                    (1, 30, "c".to_string()) => 1,
+                    (1, 10, "a".to_string()) => 1,
+                    (2, 5, "x".to_string()) => 1,
+                },
+                zset! { (1, 20, "b".to_string()) => 1 },
+                // Deleting a value removes its segment.
+                zset! { (1, 10, "a".to_string()) => -1 },
+                // Deleting the last value removes the key.
+                zset! { (2, 5, "x".to_string()) => -1 },
+                // A record with weight 2 contributes two copies.
+                zset! { (1, 25, "d".to_string()) => 2 },
+            ]
+            .into_iter();
+
+            let mut outputs = vec![
+                indexed_zset! { 1 => { "a,c".to_string() => 1 }, 2 => { "x".to_string() => 1 } },
+                indexed_zset! { 1 => { "a,c".to_string() => -1, "a,b,c".to_string() => 1 } },
+                indexed_zset! { 1 => { "a,b,c".to_string() => -1, "b,c".to_string() => 1 } },
+                indexed_zset! { 2 => { "x".to_string() => -1 } },
+                indexed_zset! { 1 => { "b,c".to_string() => -1, "b,d,d,c".to_string() => 1 } },
+            ]
+            .into_iter();
+
+            circuit
+                .add_source(Generator::new(move || inputs.next().unwrap()))
+                .string_agg(
+                    |(key, _order, _value): &(i32, i32, String)| *key,
+                    |(_key, _order, value)| value.clone(),
+                    ",",
+                    |(_key, order, _value)| *order,
+                )
+                .inspect(move |output| assert_eq!(output, &outputs.next().unwrap()));
+        })
+        .unwrap();
+
+        for _ in 0..5 {
+            circuit.step().unwrap();
+        }
+    }
+}
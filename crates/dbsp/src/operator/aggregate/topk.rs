@@ -0,0 +1,260 @@
+use crate::{
+    algebra::{IndexedZSet, UnimplementedSemigroup, ZRingValue},
+    circuit::WithClock,
+    operator::aggregate::Aggregator,
+    trace::{Batch, BatchReader, Cursor},
+    Circuit, DBData, DBTimestamp, OrdIndexedZSet, Stream, Timestamp,
+};
+use std::cmp::Ordering;
+
+/// An [aggregator](`crate::operator::Aggregator`) that returns the `k`
+/// greatest values of a group according to `cmp`, along with their weights.
+///
+/// Only values with positive weights are considered.  Values that compare
+/// equal according to `cmp` are ordered by their natural order, greater
+/// values first.  The accumulator is sorted by value, so that it only
+/// depends on the contents of the group.
+#[derive(Clone)]
+struct TopK<F> {
+    k: usize,
+    cmp: F,
+}
+
+impl<V, T, R, F> Aggregator<V, T, R> for TopK<F>
+where
+    V: DBData,
+    T: Timestamp,
+    R: DBData + ZRingValue,
+    F: Fn(&V, &V) -> Ordering + Clone + 'static,
+{
+    type Accumulator = Vec<(V, R)>;
+    type Output = Vec<(V, R)>;
+    type Semigroup = UnimplementedSemigroup<Vec<(V, R)>>;
+
+    fn aggregate<C>(&self, cursor: &mut C) -> Option<Self::Accumulator>
+    where
+        C: Cursor<V, (), T, R>,
+    {
+        let mut values = Vec::new();
+        while cursor.key_valid() {
+            let mut weight = R::zero();
+            cursor.map_times(|_t, w| weight.add_assign_by_ref(w));
+
+            if weight.gt0() {
+                values.push((cursor.key().clone(), weight));
+            }
+            cursor.step_key();
+        }
+
+        // Greatest values first; ties are broken by value order.
+        let order =
+            |(v1, _): &(V, R), (v2, _): &(V, R)| (self.cmp)(v2, v1).then_with(|| v2.cmp(v1));
+        if values.len() > self.k {
+            if self.k > 0 {
+                values.select_nth_unstable_by(self.k - 1, order);
+            }
+            values.truncate(self.k);
+        }
+
+        if values.is_empty() {
+            None
+        } else {
+            values.sort_unstable_by(|(v1, _), (v2, _)| v1.cmp(v2));
+            Some(values)
+        }
+    }
+
+    fn finalize(&self, accumulator: Self::Accumulator) -> Self::Output {
+        accumulator
+    }
+}
+
+impl<C, Z> Stream<C, Z>
+where
+    C: Circuit,
+    <C as WithClock>::Time: DBTimestamp,
+    Z: IndexedZSet + Send,
+    Z::R: ZRingValue,
+{
+    /// Incrementally retain the `k` greatest values of each key.
+    ///
+    /// Outputs the subset of the input indexed Z-set that contains, for each
+    /// key, the `k` values with positive weights that are greatest according
+    /// to `cmp`, with their weights in the input.  Values that compare equal
+    /// according to `cmp` are ranked by their natural order, greater values
+    /// first, so the result is deterministic.  Use a reversed comparison,
+    /// e.g., `|a, b| b.cmp(a)`, to retain the `k` smallest values instead.
+    ///
+    /// This operator is built on [`Self::aggregate`]: whenever a key
+    /// changes, its top `k` values are recomputed from the integral of the
+    /// input, the previous top `k` values are retracted and the new ones are
+    /// inserted.  In particular, when retractions shrink the top `k` of a
+    /// key, values that were previously evicted are output again.
+    #[allow(clippy::type_complexity)]
+    pub fn topk<F>(&self, k: usize, cmp: F) -> Stream<C, OrdIndexedZSet<Z::Key, Z::Val, Z::R>>
+    where
+        F: Fn(&Z::Val, &Z::Val) -> Ordering + Clone + 'static,
+    {
+        self.aggregate(TopK { k, cmp })
+            .apply_named(
+                "TopK",
+                |batch: &OrdIndexedZSet<Z::Key, Vec<(Z::Val, Z::R)>, Z::R>| {
+                    let mut tuples = Vec::with_capacity(batch.len());
+                    let mut cursor = batch.cursor();
+                    while cursor.key_valid() {
+                        while cursor.val_valid() {
+                            let weight = cursor.weight();
+                            for (val, val_weight) in cursor.val().iter() {
+                                tuples.push((
+                                    (cursor.key().clone(), val.clone()),
+                                    val_weight.mul_by_ref(&weight),
+                                ));
+                            }
+                            cursor.step_val();
+                        }
+                        cursor.step_key();
+                    }
+
+                    OrdIndexedZSet::from_tuples((), tuples)
+                },
+            )
+            .mark_sharded()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, Runtime};
+
+    fn topk_test(workers: usize) {
+        let (mut dbsp, (mut input, top2, bottom2, top2_integral)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (stream, handle) = circuit.add_input_indexed_zset::<u64, i64, isize>();
+
+                (
+                    handle,
+                    stream.topk(2, |a, b| a.cmp(b)).output(),
+                    stream.topk(2, |a, b| b.cmp(a)).integrate().output(),
+                    stream.topk(2, |a, b| a.cmp(b)).integrate().output(),
+                )
+            })
+            .unwrap();
+
+        // Insertions.
+        input.append(&mut vec![
+            (1, (5, 1)),
+            (1, (3, 2)),
+            (1, (7, 1)),
+            (1, (1, 1)),
+            (2, (4, 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            top2.consolidate(),
+            indexed_zset! { 1 => { 5 => 1, 7 => 1 }, 2 => { 4 => 1 } }
+        );
+        assert_eq!(
+            bottom2.consolidate(),
+            indexed_zset! { 1 => { 1 => 1, 3 => 2 }, 2 => { 4 => 1 } }
+        );
+
+        // Inserting a smaller value doesn't change the top 2.
+        input.append(&mut vec![(1, (2, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(top2.consolidate(), indexed_zset! {});
+
+        // Retracting a value re-emits the largest evicted value.
+        input.append(&mut vec![(1, (7, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            top2.consolidate(),
+            indexed_zset! { 1 => { 7 => -1, 3 => 2 } }
+        );
+        assert_eq!(
+            top2_integral.consolidate(),
+            indexed_zset! { 1 => { 3 => 2, 5 => 1 }, 2 => { 4 => 1 } }
+        );
+
+        // Values with non-positive weights don't count.
+        input.append(&mut vec![(2, (9, -1)), (1, (5, -1)), (1, (3, -2))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            top2_integral.consolidate(),
+            indexed_zset! { 1 => { 1 => 1, 2 => 1 }, 2 => { 4 => 1 } }
+        );
+        assert_eq!(
+            bottom2.consolidate(),
+            indexed_zset! { 1 => { 1 => 1, 2 => 1 }, 2 => { 4 => 1 } }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn topk_test1() {
+        topk_test(1);
+    }
+
+    #[test]
+    fn topk_test4() {
+        topk_test(4);
+    }
+
+    fn topk_ties_test(workers: usize) {
+        let (mut dbsp, (mut input, output)) = Runtime::init_circuit(workers, |circuit| {
+            let (stream, handle) = circuit.add_input_indexed_zset::<u64, (u64, i64), isize>();
+
+            // Rank by the first field only.
+            (
+                handle,
+                stream
+                    .topk(2, |(a, _), (b, _)| a.cmp(b))
+                    .integrate()
+                    .output(),
+            )
+        })
+        .unwrap();
+
+        // Three values tie at rank 5; the greatest two win.
+        input.append(&mut vec![
+            (1, ((5, 10), 1)),
+            (1, ((5, 30), 1)),
+            (1, ((5, 20), 1)),
+            (1, ((1, 40), 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { (5, 20) => 1, (5, 30) => 1 } }
+        );
+
+        // Removing a winner promotes the remaining tied value, not the value
+        // with a lower rank.
+        input.append(&mut vec![(1, ((5, 30), -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { (5, 10) => 1, (5, 20) => 1 } }
+        );
+
+        // Once fewer than `k` values remain, all of them are output.
+        input.append(&mut vec![(1, ((5, 10), -1)), (1, ((5, 20), -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { (1, 40) => 1 } }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn topk_ties_test1() {
+        topk_ties_test(1);
+    }
+
+    #[test]
+    fn topk_ties_test4() {
+        topk_ties_test(4);
+    }
+}
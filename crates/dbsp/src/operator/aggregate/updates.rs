@@ -0,0 +1,153 @@
+//! Aggregation that reports each modified aggregate as a single update
+//! record instead of a retraction and an insertion.
+
+use crate::{
+    algebra::{HasZero, IndexedZSet, ZRingValue},
+    circuit::WithClock,
+    operator::Aggregator,
+    trace::{BatchReader, Cursor},
+    Circuit, DBTimestamp, OrdIndexedZSet, Stream,
+};
+
+/// A change to the value associated with a key.
+///
+/// `old` is `None` when the key is inserted and `new` is `None` when the key
+/// is deleted.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Update<K, V> {
+    pub key: K,
+    pub old: Option<V>,
+    pub new: Option<V>,
+}
+
+impl<K, V> Update<K, V> {
+    pub fn new(key: K, old: Option<V>, new: Option<V>) -> Self {
+        Self { key, old, new }
+    }
+}
+
+impl<C, Z> Stream<C, Z>
+where
+    C: Circuit,
+    <C as WithClock>::Time: DBTimestamp,
+    Z: Clone + 'static,
+{
+    /// Incremental aggregation operator that reports changes to the
+    /// aggregate of each key as [`Update`] records.
+    ///
+    /// Computes the same aggregate as [`Self::aggregate`], but instead of
+    /// encoding a modified aggregate as a retraction of the old value and an
+    /// insertion of the new value, outputs a single `Update { key, old, new }`
+    /// record per modified key.  This is more efficient for consumers that
+    /// apply changes in place, e.g., by updating a row in an external table.
+    ///
+    /// The output is a stream of vectors of updates rather than Z-sets.
+    /// Converting it back to a stream of Z-sets requires knowledge of update
+    /// semantics, so this representation is lossy for consumers that treat
+    /// the output as a multiset of records.  In a multi-worker runtime, each
+    /// worker outputs updates for the keys in its shard of the input.
+    pub fn aggregate_as_updates<A>(
+        &self,
+        aggregator: A,
+    ) -> Stream<C, Vec<Update<Z::Key, A::Output>>>
+    where
+        Z: IndexedZSet + Send,
+        A: Aggregator<Z::Val, <C as WithClock>::Time, Z::R>,
+        Z::R: ZRingValue,
+    {
+        self.aggregate(aggregator)
+            .apply(|delta: &OrdIndexedZSet<Z::Key, A::Output, Z::R>| {
+                let mut updates = Vec::with_capacity(delta.key_count());
+                let mut cursor = delta.cursor();
+
+                // The aggregate contains at most one value per key, so the
+                // delta of each key consists of at most one retraction of the
+                // old value and at most one insertion of the new value.
+                while cursor.key_valid() {
+                    let mut old = None;
+                    let mut new = None;
+
+                    while cursor.val_valid() {
+                        let weight = cursor.weight();
+                        if !weight.is_zero() {
+                            if weight.ge0() {
+                                new = Some(cursor.val().clone());
+                            } else {
+                                old = Some(cursor.val().clone());
+                            }
+                        }
+                        cursor.step_val();
+                    }
+
+                    updates.push(Update::new(cursor.key().clone(), old, new));
+                    cursor.step_key();
+                }
+
+                updates
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Update;
+    use crate::{algebra::DefaultSemigroup, operator::Fold, Runtime};
+
+    fn aggregate_as_updates_test(workers: usize) {
+        let (mut dbsp, (mut input, output)) = Runtime::init_circuit(workers, |circuit| {
+            let (stream, handle) = circuit.add_input_indexed_zset::<u64, i64, isize>();
+
+            let sum =
+                <Fold<_, DefaultSemigroup<_>, _, _>>::new(0, |acc: &mut i64, v: &i64, w: isize| {
+                    *acc += *v * w as i64
+                });
+
+            (handle, stream.aggregate_as_updates(sum).output())
+        })
+        .unwrap();
+
+        let updates = || {
+            let mut updates: Vec<_> = output.take_from_all().into_iter().flatten().collect();
+            updates.sort();
+            updates
+        };
+
+        input.append(&mut vec![(1, (100, 1)), (2, (10, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            updates(),
+            vec![
+                Update::new(1, None, Some(100)),
+                Update::new(2, None, Some(10))
+            ]
+        );
+
+        // The aggregate of key 1 changes from 100 to 150: one update record
+        // instead of a retraction and an insertion.
+        input.append(&mut vec![(1, (50, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(updates(), vec![Update::new(1, Some(100), Some(150))]);
+
+        // Deleting all values of key 2 deletes its aggregate.
+        input.append(&mut vec![(2, (10, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(updates(), vec![Update::new(2, Some(10), None)]);
+
+        // Changes that don't modify the aggregate don't produce updates.
+        input.append(&mut vec![(1, (20, 1)), (1, (-20, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(updates(), vec![]);
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn aggregate_as_updates_test1() {
+        aggregate_as_updates_test(1);
+    }
+
+    #[test]
+    fn aggregate_as_updates_test4() {
+        aggregate_as_updates_test(4);
+    }
+}
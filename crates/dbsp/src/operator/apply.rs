@@ -3,7 +3,7 @@
 use crate::circuit::{
     metadata::OperatorLocation,
     operator_traits::{Data, Operator, UnaryOperator},
-    Circuit, OwnershipPreference, Scope, Stream,
+    CheckpointError, Circuit, OwnershipPreference, Scope, Stream,
 };
 use std::{borrow::Cow, panic::Location};
 
@@ -13,6 +13,11 @@ where
     T1: Clone + 'static,
 {
     /// Apply  the `Apply` operator to `self`.
+    ///
+    /// `func` must not carry state across clock cycles: the operator is
+    /// treated as stateless when the circuit is
+    /// [checkpointed](`crate::DBSPHandle::checkpoint`).  Use
+    /// [`stream_fold`](`Stream::stream_fold`) for stateful computations.
     #[track_caller]
     pub fn apply<F, T2>(&self, func: F) -> Stream<C, T2>
     where
@@ -62,6 +67,39 @@ where
             .add_unary_operator(ApplyOwned::new(func, name.into(), Location::caller()), self)
     }
 
+    /// Apply a function that updates internal state to `self`.
+    ///
+    /// At every clock cycle, invokes `func` with a mutable reference to the
+    /// state, initialized to `init` and preserved across clock cycles, and
+    /// the current input, and outputs the result of `func`.  This is useful
+    /// for simple cross-step computations, such as counting clock cycles or
+    /// accumulating statistics about the input, that don't justify building
+    /// a feedback loop with [`stream_fold`](`Stream::stream_fold`).
+    ///
+    /// # Determinism
+    ///
+    /// The state is private to the operator and is not visible to the rest
+    /// of the circuit.  It cannot be serialized, so a circuit that contains
+    /// this operator cannot be [checkpointed](`crate::DBSPHandle::checkpoint`).
+    /// `func` must compute its output and the new state only from the old
+    /// state and its input, so that the operator produces the same outputs
+    /// when the circuit is rebuilt and fed the same inputs.  In a
+    /// multithreaded runtime, each worker maintains its own copy of the
+    /// state, updated with the worker's local inputs only.
+    ///
+    /// Like [`apply`](`Self::apply`), this operator cannot be used in a
+    /// nested circuit that iterates to a fixed point.
+    #[track_caller]
+    pub fn apply_stateful<S, F, T2>(&self, init: S, func: F) -> Stream<C, T2>
+    where
+        S: 'static,
+        F: Fn(&mut S, &T1) -> T2 + 'static,
+        T2: Clone + 'static,
+    {
+        self.circuit()
+            .add_unary_operator(ApplyStateful::new(init, func, Location::caller()), self)
+    }
+
     /// Apply the `ApplyOwned` operator to `self` with a custom name
     #[track_caller]
     pub fn apply_core<N, T2, O, B, F>(
@@ -124,6 +162,10 @@ where
         // parameterize the operator with custom fixed point check.
         unimplemented!();
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T1, T2, F> UnaryOperator<T1, T2> for Apply<F>
@@ -135,6 +177,52 @@ where
     }
 }
 
+/// Operator that applies a user provided function to its input and internal
+/// state at each timestamp, see [`Stream::apply_stateful`].
+pub struct ApplyStateful<S, F> {
+    state: S,
+    func: F,
+    location: &'static Location<'static>,
+}
+
+impl<S, F> ApplyStateful<S, F> {
+    pub const fn new(state: S, func: F, location: &'static Location<'static>) -> Self {
+        Self {
+            state,
+            func,
+            location,
+        }
+    }
+}
+
+impl<S, F> Operator for ApplyStateful<S, F>
+where
+    S: 'static,
+    F: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("ApplyStateful")
+    }
+
+    fn location(&self) -> OperatorLocation {
+        Some(self.location)
+    }
+
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        panic!("'ApplyStateful' operator used in fixedpoint iteration")
+    }
+}
+
+impl<S, T1, T2, F> UnaryOperator<T1, T2> for ApplyStateful<S, F>
+where
+    S: 'static,
+    F: Fn(&mut S, &T1) -> T2 + 'static,
+{
+    fn eval(&mut self, i1: &T1) -> T2 {
+        (self.func)(&mut self.state, i1)
+    }
+}
+
 pub struct ApplyOwned<F> {
     apply: F,
     name: Cow<'static, str>,
@@ -172,6 +260,10 @@ where
         // parameterize the operator with custom fixed point check.
         unimplemented!();
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T1, T2, F> UnaryOperator<T1, T2> for ApplyOwned<F>
@@ -236,6 +328,10 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         (self.fixpoint)(scope)
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<O, B, F, T1, T2> UnaryOperator<T1, T2> for ApplyCore<O, B, F>
@@ -258,3 +354,39 @@ where
         OwnershipPreference::STRONGLY_PREFER_OWNED
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{trace::BatchReader, OrdZSet, RootCircuit};
+
+    #[test]
+    fn apply_stateful_test() {
+        let (circuit, mut input_handle) = RootCircuit::build(|circuit| {
+            let (input, input_handle) = circuit.add_input_zset::<u64, isize>();
+
+            // Running count of rows seen across all steps.
+            let mut expected = vec![3, 3, 4, 6].into_iter();
+            input
+                .apply_stateful(0, |count: &mut usize, batch: &OrdZSet<u64, isize>| {
+                    *count += batch.len();
+                    *count
+                })
+                .inspect(move |count| assert_eq!(*count, expected.next().unwrap()));
+
+            input_handle
+        })
+        .unwrap();
+
+        let steps: Vec<Vec<(u64, isize)>> = vec![
+            vec![(1, 1), (2, 1), (3, 1)],
+            vec![],
+            vec![(1, -1)],
+            vec![(4, 1), (5, 2)],
+        ];
+
+        for mut step in steps {
+            input_handle.append(&mut step);
+            circuit.step().unwrap();
+        }
+    }
+}
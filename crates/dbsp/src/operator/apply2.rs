@@ -3,7 +3,7 @@
 use crate::circuit::{
     metadata::OperatorLocation,
     operator_traits::{BinaryOperator, Operator},
-    Circuit, OwnershipPreference, Scope, Stream,
+    CheckpointError, Circuit, OwnershipPreference, Scope, Stream,
 };
 use std::{borrow::Cow, panic::Location};
 
@@ -73,6 +73,10 @@ where
         // parameterize the operator with custom fixed point check.
         unimplemented!();
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T1, T2, T3, F> BinaryOperator<T1, T2, T3> for Apply2<F>
@@ -117,6 +121,10 @@ where
         // parameterize the operator with custom fixed point check.
         unimplemented!();
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T1, T2, T3, F> BinaryOperator<T1, T2, T3> for Apply2Owned<F>
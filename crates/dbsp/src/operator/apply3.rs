@@ -0,0 +1,153 @@
+//! Ternary operator that applies an arbitrary ternary function to its inputs.
+
+use crate::circuit::{
+    metadata::OperatorLocation,
+    operator_traits::{Operator, TernaryOperator},
+    CheckpointError, Circuit, Scope, Stream,
+};
+use std::{borrow::Cow, panic::Location};
+
+impl<C, T1> Stream<C, T1>
+where
+    C: Circuit,
+    T1: Clone + 'static,
+{
+    /// Apply a user-provided ternary function to its inputs at each timestamp.
+    ///
+    /// The function is evaluated once all three inputs have been computed for
+    /// the current timestamp and receives them by reference.
+    #[track_caller]
+    pub fn apply3<F, T2, T3, T4>(
+        &self,
+        other1: &Stream<C, T2>,
+        other2: &Stream<C, T3>,
+        func: F,
+    ) -> Stream<C, T4>
+    where
+        T2: Clone + 'static,
+        T3: Clone + 'static,
+        T4: Clone + 'static,
+        F: Fn(&T1, &T2, &T3) -> T4 + 'static,
+    {
+        self.circuit().add_ternary_operator(
+            Apply3::new(func, Location::caller()),
+            self,
+            other1,
+            other2,
+        )
+    }
+}
+
+/// Applies a user-provided ternary function to its inputs at each timestamp.
+pub struct Apply3<F> {
+    func: F,
+    location: &'static Location<'static>,
+}
+
+impl<F> Apply3<F> {
+    pub const fn new(func: F, location: &'static Location<'static>) -> Self
+    where
+        F: 'static,
+    {
+        Self { func, location }
+    }
+}
+
+impl<F> Operator for Apply3<F>
+where
+    F: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("Apply3")
+    }
+
+    fn location(&self) -> OperatorLocation {
+        Some(self.location)
+    }
+
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        // `F` is a stateless `Fn`, so constant inputs produce constant outputs.
+        true
+    }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
+}
+
+impl<T1, T2, T3, T4, F> TernaryOperator<T1, T2, T3, T4> for Apply3<F>
+where
+    T1: Clone + 'static,
+    T2: Clone + 'static,
+    T3: Clone + 'static,
+    F: Fn(&T1, &T2, &T3) -> T4 + 'static,
+{
+    fn eval<'a>(&mut self, i1: Cow<'a, T1>, i2: Cow<'a, T2>, i3: Cow<'a, T3>) -> T4 {
+        (self.func)(i1.as_ref(), i2.as_ref(), i3.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algebra::AddByRef, operator::Generator, zset, Circuit, OrdZSet, RootCircuit, Stream,
+    };
+    use std::vec;
+
+    #[test]
+    fn apply3_test() {
+        let circuit = RootCircuit::build(move |circuit| {
+            let mut inputs1 = vec![1, 2, 3].into_iter();
+            let mut inputs2 = vec![10, 20, 30].into_iter();
+            let mut inputs3 = vec![-11, -22, -33].into_iter();
+
+            let source1 = circuit.add_source(Generator::new(move || inputs1.next().unwrap()));
+            let source2 = circuit.add_source(Generator::new(move || inputs2.next().unwrap()));
+            let source3 = circuit.add_source(Generator::new(move || inputs3.next().unwrap()));
+
+            source1
+                .apply3(&source2, &source3, |x, y, z| *x + *y + *z)
+                .inspect(|sum| assert_eq!(*sum, 0));
+        })
+        .unwrap()
+        .0;
+
+        for _ in 0..3 {
+            circuit.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn apply3_fixedpoint_test() {
+        let circuit = RootCircuit::build(move |circuit| {
+            let source1: Stream<_, OrdZSet<usize, isize>> =
+                circuit.add_source(Generator::new(|| zset! { 1 => 1 }));
+            let source2: Stream<_, OrdZSet<usize, isize>> =
+                circuit.add_source(Generator::new(|| zset! { 2 => 1 }));
+            let source3: Stream<_, OrdZSet<usize, isize>> =
+                circuit.add_source(Generator::new(|| zset! { 1 => 1, 3 => -1 }));
+
+            let sum = circuit
+                .fixedpoint(|child| {
+                    let source1 = source1.delta0(child);
+                    let source2 = source2.delta0(child);
+                    let source3 = source3.delta0(child);
+
+                    Ok(source1
+                        .apply3(&source2, &source3, |x, y, z| x.add_by_ref(y).add_by_ref(z))
+                        .integrate_trace()
+                        .export())
+                })
+                .unwrap()
+                .consolidate();
+
+            sum.inspect(|sum| assert_eq!(sum, &zset! { 1 => 2, 2 => 1, 3 => -1 }));
+        })
+        .unwrap()
+        .0;
+
+        for _ in 0..3 {
+            circuit.step().unwrap();
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! As-of join of a stream of probes with a time-varying reference relation.
+
+use crate::{
+    algebra::{HasZero, ZRingValue},
+    circuit::{
+        operator_traits::{Operator, QuaternaryOperator},
+        CheckpointError, Circuit, Scope, Stream,
+    },
+    operator::FilterMap,
+    trace::{consolidation::consolidate, cursor::Cursor, Batch, BatchReader, Spine},
+    DBData, OrdIndexedZSet, OrdZSet, RootCircuit,
+};
+use std::{borrow::Cow, marker::PhantomData, ops::Neg};
+
+impl<K, V1, R> Stream<RootCircuit, OrdIndexedZSet<K, V1, R>>
+where
+    K: DBData,
+    V1: DBData,
+    R: ZRingValue,
+{
+    /// As-of join of `self` with a time-varying `reference` relation.
+    ///
+    /// Matches each probe row `(k, v1)` in `self` with the latest reference
+    /// row `(k, v2)` with the same key that was valid at the probe's
+    /// timestamp, i.e., the row with the largest `ref_ts(v2)` such that
+    /// `ref_ts(v2) <= probe_ts(v1)`.  Ties between reference rows with the
+    /// same timestamp are broken by picking the largest value.  Outputs
+    /// `join_func(k, v1, v2)` for each matched probe row with the weight of
+    /// the probe.  Probe rows without a match don't produce any outputs.
+    /// Reference rows with non-positive weights are ignored.
+    ///
+    /// # Performance
+    ///
+    /// The reference relation is indexed by key and timestamp, so that new
+    /// probe rows are matched using a backward seek to the probe timestamp.
+    /// A change to the reference relation, however, can change the match
+    /// for any probe with the same key, e.g., a reference row inserted
+    /// before the current match of a probe becomes the new match.  When the
+    /// reference relation changes for a key, the operator re-matches all
+    /// probe rows for the key against the old and new contents of the
+    /// reference relation for the key, which is `O(m + n)`, where `m` and
+    /// `n` are the number of probe and reference rows with the key.  The
+    /// output only contains changes to the matches that are affected.
+    #[allow(clippy::type_complexity)]
+    pub fn asof_join<V2, TS, O, PF, RF, F>(
+        &self,
+        reference: &Stream<RootCircuit, OrdIndexedZSet<K, V2, R>>,
+        probe_ts: PF,
+        ref_ts: RF,
+        join_func: F,
+    ) -> Stream<RootCircuit, OrdZSet<O, R>>
+    where
+        V2: DBData,
+        TS: DBData,
+        O: DBData,
+        PF: Fn(&V1) -> TS + 'static,
+        RF: Fn(&V2) -> TS + 'static,
+        F: Fn(&K, &V1, &V2) -> O + 'static,
+    {
+        let probes = self.shard();
+        let references = reference
+            .map_index(move |(k, v)| (k.clone(), (ref_ts(v), v.clone())))
+            .shard();
+
+        self.circuit().add_quaternary_operator(
+            AsofJoin::new(probe_ts, join_func),
+            &probes,
+            &probes.integrate_trace().delay_trace(),
+            &references,
+            &references.integrate_trace(),
+        )
+    }
+}
+
+struct AsofJoin<TS, PF, F> {
+    probe_ts: PF,
+    join_func: F,
+    _phantom: PhantomData<TS>,
+}
+
+impl<TS, PF, F> AsofJoin<TS, PF, F> {
+    fn new(probe_ts: PF, join_func: F) -> Self {
+        Self {
+            probe_ts,
+            join_func,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<TS, PF, F> Operator for AsofJoin<TS, PF, F>
+where
+    TS: 'static,
+    PF: 'static,
+    F: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("AsofJoin")
+    }
+
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        panic!("'AsofJoin' operator used in fixedpoint iteration")
+    }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
+}
+
+/// Returns the latest row in consolidated `rows` with timestamp `<= ts` and
+/// a positive weight.
+fn find_match<'a, TS, V, R>(rows: &'a [((TS, V), R)], ts: &TS) -> Option<&'a V>
+where
+    TS: Ord,
+    R: ZRingValue,
+{
+    let end = rows.partition_point(|((row_ts, _), _)| row_ts <= ts);
+
+    rows[..end]
+        .iter()
+        .rev()
+        .find(|(_, weight)| weight.gt0())
+        .map(|((_, val), _)| val)
+}
+
+impl<K, V1, V2, TS, R, O, PF, F>
+    QuaternaryOperator<
+        OrdIndexedZSet<K, V1, R>,
+        Spine<OrdIndexedZSet<K, V1, R>>,
+        OrdIndexedZSet<K, (TS, V2), R>,
+        Spine<OrdIndexedZSet<K, (TS, V2), R>>,
+        OrdZSet<O, R>,
+    > for AsofJoin<TS, PF, F>
+where
+    K: DBData,
+    V1: DBData,
+    V2: DBData,
+    TS: DBData,
+    R: ZRingValue,
+    O: DBData,
+    PF: Fn(&V1) -> TS + 'static,
+    F: Fn(&K, &V1, &V2) -> O + 'static,
+{
+    /// * `probe_delta` - changes to the probe relation.
+    /// * `probe_trace` - probe relation up to, but not including the current
+    ///   clock cycle.
+    /// * `ref_delta` - changes to the reference relation indexed by key and
+    ///   timestamp.
+    /// * `ref_trace` - reference relation, including changes in `ref_delta`.
+    fn eval<'a>(
+        &mut self,
+        probe_delta: Cow<'a, OrdIndexedZSet<K, V1, R>>,
+        probe_trace: Cow<'a, Spine<OrdIndexedZSet<K, V1, R>>>,
+        ref_delta: Cow<'a, OrdIndexedZSet<K, (TS, V2), R>>,
+        ref_trace: Cow<'a, Spine<OrdIndexedZSet<K, (TS, V2), R>>>,
+    ) -> OrdZSet<O, R> {
+        let mut output = Vec::new();
+
+        // Match new probes against the current reference relation.
+        let mut probe_cursor = probe_delta.cursor();
+        let mut ref_cursor = ref_trace.cursor();
+
+        while probe_cursor.key_valid() {
+            let key = probe_cursor.key().clone();
+
+            ref_cursor.seek_key(&key);
+            if ref_cursor.key_valid() && ref_cursor.key() == &key {
+                while probe_cursor.val_valid() {
+                    let weight = probe_cursor.weight();
+                    let probe = probe_cursor.val();
+                    let ts = (self.probe_ts)(probe);
+
+                    // Seek backward to the latest reference row that's valid
+                    // at the probe's timestamp.
+                    ref_cursor.fast_forward_vals();
+                    ref_cursor.seek_val_with_reverse(|(ref_ts, _)| ref_ts <= &ts);
+                    while ref_cursor.val_valid() {
+                        if ref_cursor.weight().gt0() {
+                            output
+                                .push(((self.join_func)(&key, probe, &ref_cursor.val().1), weight));
+                            break;
+                        }
+                        ref_cursor.step_val_reverse();
+                    }
+
+                    probe_cursor.step_val();
+                }
+            }
+
+            probe_cursor.step_key();
+        }
+
+        // Re-match existing probes with keys whose reference rows have
+        // changed.
+        let mut delta_cursor = ref_delta.cursor();
+        let mut probe_cursor = probe_trace.cursor();
+        let mut ref_cursor = ref_trace.cursor();
+
+        while delta_cursor.key_valid() {
+            let key = delta_cursor.key().clone();
+
+            probe_cursor.seek_key(&key);
+            if probe_cursor.key_valid() && probe_cursor.key() == &key {
+                let mut new_refs = Vec::new();
+                ref_cursor.seek_key(&key);
+                if ref_cursor.key_valid() && ref_cursor.key() == &key {
+                    ref_cursor
+                        .map_values(|val, weight| new_refs.push((val.clone(), weight.clone())));
+                }
+                consolidate(&mut new_refs);
+
+                let mut old_refs = new_refs.clone();
+                delta_cursor
+                    .map_values(|val, weight| old_refs.push((val.clone(), weight.clone().neg())));
+                consolidate(&mut old_refs);
+
+                while probe_cursor.val_valid() {
+                    let weight = probe_cursor.weight();
+                    if !weight.is_zero() {
+                        let probe = probe_cursor.val();
+                        let ts = (self.probe_ts)(probe);
+                        let old_match = find_match(&old_refs, &ts);
+                        let new_match = find_match(&new_refs, &ts);
+
+                        if old_match != new_match {
+                            if let Some(old_match) = old_match {
+                                output.push((
+                                    (self.join_func)(&key, probe, old_match),
+                                    weight.clone().neg(),
+                                ));
+                            }
+                            if let Some(new_match) = new_match {
+                                output.push(((self.join_func)(&key, probe, new_match), weight));
+                            }
+                        }
+                    }
+
+                    probe_cursor.step_val();
+                }
+            }
+
+            delta_cursor.step_key();
+        }
+
+        OrdZSet::from_keys((), output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{zset, Runtime};
+
+    fn asof_join_test(workers: usize) {
+        let (mut dbsp, (mut trades, mut quotes, output_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                // Trades: symbol -> (time, quantity).
+                let (trades, trades_handle) =
+                    circuit.add_input_indexed_zset::<String, (u64, u64), isize>();
+                // Quotes: symbol -> (time, price).
+                let (quotes, quotes_handle) =
+                    circuit.add_input_indexed_zset::<String, (u64, u64), isize>();
+
+                let output_handle = trades
+                    .asof_join(
+                        &quotes,
+                        |(time, _)| *time,
+                        |(time, _)| *time,
+                        |symbol, (time, quantity), (_, price)| {
+                            (symbol.clone(), *time, *quantity, *price)
+                        },
+                    )
+                    .integrate()
+                    .output();
+
+                (trades_handle, quotes_handle, output_handle)
+            })
+            .unwrap();
+
+        let ibm = || "IBM".to_string();
+        let msft = || "MSFT".to_string();
+
+        quotes.append(&mut vec![
+            (ibm(), ((10, 100), 1)),
+            (ibm(), ((20, 110), 1)),
+            (msft(), ((15, 300), 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! {});
+
+        // Probes match the quote that was valid at the time of the trade.
+        // A trade before the first quote doesn't match.
+        trades.append(&mut vec![
+            (ibm(), ((5, 1), 1)),
+            (ibm(), ((15, 2), 1)),
+            (ibm(), ((25, 3), 1)),
+            (msft(), ((20, 4), 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! {
+                (ibm(), 15, 2, 100) => 1,
+                (ibm(), 25, 3, 110) => 1,
+                (msft(), 20, 4, 300) => 1,
+            }
+        );
+
+        // A quote inserted before existing matches updates them.
+        quotes.append(&mut vec![(ibm(), ((12, 105), 1)), (ibm(), ((3, 90), 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! {
+                (ibm(), 5, 1, 90) => 1,
+                (ibm(), 15, 2, 105) => 1,
+                (ibm(), 25, 3, 110) => 1,
+                (msft(), 20, 4, 300) => 1,
+            }
+        );
+
+        // Deleting a quote falls back to the previous one.
+        quotes.append(&mut vec![(ibm(), ((20, 110), -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! {
+                (ibm(), 5, 1, 90) => 1,
+                (ibm(), 15, 2, 105) => 1,
+                (ibm(), 25, 3, 105) => 1,
+                (msft(), 20, 4, 300) => 1,
+            }
+        );
+
+        // Changes to probes and quotes in the same step.
+        trades.append(&mut vec![(ibm(), ((15, 2), -1)), (msft(), ((14, 5), 1))]);
+        quotes.append(&mut vec![(msft(), ((14, 290), 1)), (ibm(), ((25, 120), 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! {
+                (ibm(), 5, 1, 90) => 1,
+                (ibm(), 25, 3, 120) => 1,
+                (msft(), 14, 5, 290) => 1,
+                (msft(), 20, 4, 300) => 1,
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn asof_join_test1() {
+        asof_join_test(1);
+    }
+
+    #[test]
+    fn asof_join_test4() {
+        asof_join_test(4);
+    }
+}
@@ -0,0 +1,182 @@
+//! Combine the current state of several relations into one record per key.
+
+use crate::{
+    algebra::{IndexedZSet, ZRingValue},
+    circuit::WithClock,
+    Circuit, DBData, DBTimestamp, OrdIndexedZSet, Stream,
+};
+
+impl<C, I1> Stream<C, I1>
+where
+    C: Circuit,
+    <C as WithClock>::Time: DBTimestamp,
+    I1: IndexedZSet + Send,
+    I1::R: ZRingValue,
+{
+    /// Combine the latest values of two relations indexed by the same key.
+    ///
+    /// Treats `self` and `other` as streams of changes to relations that
+    /// map each key to its current value, e.g., the outputs of
+    /// [`Stream::aggregate`], and outputs a stream of changes to a relation
+    /// that maps each key to a tuple containing the current values of both
+    /// inputs.  Whenever any of the inputs changes, the output retracts the
+    /// old tuple for the affected key and inserts a new one, carrying the
+    /// latest value of the other input.
+    ///
+    /// The operator is computed per key: use the unit key `()` to combine
+    /// global aggregates into a single record.  A key that is missing from
+    /// one of the inputs doesn't appear in the output.  A key associated with
+    /// multiple values in an input produces one output tuple for each
+    /// combination of values, i.e., this operator is the same as an inner
+    /// join on the key.
+    #[track_caller]
+    pub fn combine_latest<I2>(
+        &self,
+        other: &Stream<C, I2>,
+    ) -> Stream<C, OrdIndexedZSet<I1::Key, (I1::Val, I2::Val), I1::R>>
+    where
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+        (I1::Val, I2::Val): DBData,
+    {
+        self.join_keyed(other, |_k, v1, v2| (v1.clone(), v2.clone()))
+    }
+
+    /// Combine the latest values of three relations indexed by the same key.
+    ///
+    /// See [`Self::combine_latest`].
+    #[allow(clippy::type_complexity)]
+    #[track_caller]
+    pub fn combine_latest3<I2, I3>(
+        &self,
+        other1: &Stream<C, I2>,
+        other2: &Stream<C, I3>,
+    ) -> Stream<C, OrdIndexedZSet<I1::Key, (I1::Val, I2::Val, I3::Val), I1::R>>
+    where
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+        I3: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+        (I1::Val, I2::Val): DBData,
+        (I1::Val, I2::Val, I3::Val): DBData,
+    {
+        self.combine_latest(other1)
+            .join_keyed(other2, |_k, (v1, v2), v3| {
+                (v1.clone(), v2.clone(), v3.clone())
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, Runtime};
+
+    fn combine_latest_test(workers: usize) {
+        let (mut dbsp, (mut count, mut sum, mut max, mut keyed1, mut keyed2, output, output2)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (count, count_handle) = circuit.add_input_indexed_zset::<(), u64, isize>();
+                let (sum, sum_handle) = circuit.add_input_indexed_zset::<(), i64, isize>();
+                let (max, max_handle) = circuit.add_input_indexed_zset::<(), String, isize>();
+                let (keyed1, keyed1_handle) =
+                    circuit.add_input_indexed_zset::<usize, usize, isize>();
+                let (keyed2, keyed2_handle) =
+                    circuit.add_input_indexed_zset::<usize, String, isize>();
+
+                let output = count.combine_latest3(&sum, &max).integrate().output();
+                let output2 = keyed1.combine_latest(&keyed2).integrate().output();
+
+                (
+                    count_handle,
+                    sum_handle,
+                    max_handle,
+                    keyed1_handle,
+                    keyed2_handle,
+                    output,
+                    output2,
+                )
+            })
+            .unwrap();
+
+        // No output until all inputs have a value.
+        count.append(&mut vec![((), (1, 1))]);
+        sum.append(&mut vec![((), (10, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(output.consolidate(), indexed_zset! {});
+
+        max.append(&mut vec![((), ("foo".to_string(), 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { () => { (1, 10, "foo".to_string()) => 1 } }
+        );
+
+        // Update one input at a time; the output carries the latest values of
+        // the other inputs.
+        count.append(&mut vec![((), (1, -1)), ((), (2, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { () => { (2, 10, "foo".to_string()) => 1 } }
+        );
+
+        sum.append(&mut vec![((), (10, -1)), ((), (-5, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { () => { (2, -5, "foo".to_string()) => 1 } }
+        );
+
+        max.append(&mut vec![
+            ((), ("foo".to_string(), -1)),
+            ((), ("zoo".to_string(), 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { () => { (2, -5, "zoo".to_string()) => 1 } }
+        );
+
+        // Unrelated step: output remains unchanged.
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { () => { (2, -5, "zoo".to_string()) => 1 } }
+        );
+
+        // Per-key combine.
+        keyed1.append(&mut vec![(1, (10, 1)), (2, (20, 1)), (3, (30, 1))]);
+        keyed2.append(&mut vec![
+            (1, ("a".to_string(), 1)),
+            (2, ("b".to_string(), 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output2.consolidate(),
+            indexed_zset! {
+                1 => { (10, "a".to_string()) => 1 },
+                2 => { (20, "b".to_string()) => 1 }
+            }
+        );
+
+        keyed1.append(&mut vec![(1, (10, -1)), (1, (11, 1))]);
+        keyed2.append(&mut vec![(3, ("c".to_string(), 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output2.consolidate(),
+            indexed_zset! {
+                1 => { (11, "a".to_string()) => 1 },
+                2 => { (20, "b".to_string()) => 1 },
+                3 => { (30, "c".to_string()) => 1 }
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn combine_latest_test1() {
+        combine_latest_test(1);
+    }
+
+    #[test]
+    fn combine_latest_test4() {
+        combine_latest_test(4);
+    }
+}
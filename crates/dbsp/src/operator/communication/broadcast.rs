@@ -0,0 +1,98 @@
+//! Operator to replicate a control value across all worker threads.
+
+use crate::{circuit::GlobalNodeId, circuit_cache_key, Circuit, Runtime, Stream};
+use std::panic::Location;
+
+circuit_cache_key!(BroadcastId<C, D>(GlobalNodeId => Stream<C, D>));
+
+impl<C, T> Stream<C, T>
+where
+    C: Circuit,
+    T: Clone + Default + Send + 'static,
+{
+    /// Replicate the value computed by worker 0 to all workers.
+    ///
+    /// At every clock cycle, the output stream in each worker contains the
+    /// value of the input stream in worker 0.  Values produced by other
+    /// workers are discarded.  This is the control-plane counterpart of
+    /// [`shard`](`Self::shard`): use it to make a decision made by one
+    /// worker, e.g., that a threshold has been crossed, visible to all
+    /// workers in the same clock cycle.
+    ///
+    /// The input stream must carry a single control value per worker at each
+    /// clock cycle, not a batch of data: the value is cloned once for every
+    /// worker.  Workers other than 0 would typically produce
+    /// `T::default()`.
+    ///
+    /// Like `shard`, this operator introduces a synchronization barrier
+    /// across all workers.
+    #[track_caller]
+    pub fn broadcast_control(&self) -> Stream<C, T> {
+        let location = Location::caller();
+
+        match Runtime::runtime() {
+            Some(runtime) if runtime.num_workers() > 1 => self
+                .circuit()
+                .cache_get_or_insert_with(BroadcastId::new(self.origin_node_id().clone()), || {
+                    let (sender, receiver) =
+                        self.circuit()
+                            .new_broadcast_operators(&runtime, Some(location), 0);
+
+                    self.circuit().add_exchange(sender, receiver, self)
+                })
+                .clone(),
+            _ => self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{operator::Generator, Circuit, RootCircuit, Runtime};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn broadcast_control_test() {
+        const WORKERS: usize = 4;
+        const STEPS: usize = 10;
+
+        let checked = Arc::new(AtomicUsize::new(0));
+        let checked_clone = checked.clone();
+
+        let hruntime = Runtime::run(WORKERS, move || {
+            let checked = checked_clone.clone();
+
+            let circuit = RootCircuit::build(move |circuit| {
+                // Only worker 0 produces a non-default value.
+                let mut step = 0;
+                let control = circuit.add_source(Generator::new(move || {
+                    step += 1;
+                    if Runtime::worker_index() == 0 {
+                        step
+                    } else {
+                        0
+                    }
+                }));
+
+                let mut expected = 0;
+                control.broadcast_control().inspect(move |value: &usize| {
+                    expected += 1;
+                    assert_eq!(*value, expected);
+                    checked.fetch_add(1, Ordering::Relaxed);
+                });
+            })
+            .unwrap()
+            .0;
+
+            for _ in 0..STEPS {
+                circuit.step().unwrap();
+            }
+        });
+
+        hruntime.join().unwrap();
+        assert_eq!(checked.load(Ordering::Relaxed), WORKERS * STEPS);
+    }
+}
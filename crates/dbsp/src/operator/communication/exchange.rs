@@ -1,15 +1,22 @@
-//! Exchange operators implement a N-to-N communication pattern where
-//! each participant sends exactly one value to and receives exactly one
-//! value from each peer at every clock cycle.
-
-// TODO: We may want to generalize these operators to implement N-to-M
-// communication, including 1-to-N and N-to-1.
+//! Exchange operators implement an N-to-M communication pattern where
+//! each of `N` senders sends exactly one value to each of `M` receivers and
+//! each receiver receives exactly one value from each sender at every clock
+//! cycle.
+//!
+//! The most common case is N-to-N communication, where every worker is both
+//! a sender and a receiver (see [`new_exchange_operators`]).  1-to-N
+//! ([`Circuit::new_broadcast_operators`]) and N-to-1
+//! ([`Circuit::new_gather_operators`]) communication are implemented on top
+//! of the same primitive.
+//!
+//! [`Circuit::new_broadcast_operators`]: crate::circuit::Circuit::new_broadcast_operators
+//! [`Circuit::new_gather_operators`]: crate::circuit::Circuit::new_gather_operators
 
 use crate::{
     circuit::{
         metadata::OperatorLocation,
         operator_traits::{Operator, SinkOperator, SourceOperator},
-        OwnershipPreference, Runtime, Scope,
+        CheckpointError, OwnershipPreference, Runtime, Scope,
     },
     circuit_cache_key,
 };
@@ -17,6 +24,7 @@ use crossbeam_utils::CachePadded;
 use once_cell::sync::OnceCell;
 use std::{
     borrow::Cow,
+    collections::VecDeque,
     marker::PhantomData,
     sync::{
         atomic::{AtomicUsize, Ordering},
@@ -33,33 +41,47 @@ use std::{
 // be used instead.
 circuit_cache_key!(local ExchangeId<T>(usize => Arc<Exchange<T>>));
 
-/// `Exchange` is an N-to-N communication primitive that partitions data across
-/// multiple concurrent threads.
+/// `Exchange` is an N-to-M communication primitive that partitions data
+/// produced by `nsenders` concurrent threads across `nreceivers` threads.
 ///
 /// An instance of `Exchange` can be shared by multiple threads that communicate
-/// in rounds.  In each round each peer _first_ sends exactly one data value to
-/// every other peer (and itself) and then receives one value from each peer.
-/// The send operation can only proceed when all peers have retrieved data
-/// produced at the previous round.  Likewise, the receive operation can proceed
-/// once all incoming values are ready for the current round.
+/// in rounds.  In each round each sender sends exactly one data value to
+/// every receiver and each receiver receives one value from each sender.
+/// The receive operation can proceed once all incoming values are ready for
+/// the current round.  Senders and receivers are identified by their indexes
+/// in `0..nsenders` and `0..nreceivers` respectively.  In the N-to-N case, the
+/// same thread typically acts as both sender and receiver with the same index.
+///
+/// Each sender/receiver pair communicates via a mailbox that buffers up to
+/// `depth` values.  The send operation can only proceed when none of the
+/// sender's mailboxes is full, i.e., a sender can run up to `depth` rounds
+/// ahead of the slowest receiver.  With `depth = 1`, peers proceed in
+/// lockstep: a sender must wait for all receivers to retrieve data produced
+/// at the previous round.  The exchange buffers at most
+/// `depth * nsenders * nreceivers` values at any time, `depth * nreceivers`
+/// per sender.
 pub(crate) struct Exchange<T> {
-    /// The number of communicating peers.
-    npeers: usize,
-    /// `npeers^2` mailboxes, one for each sender/receiver pair.  Note that each
-    /// mailbox is accessed by exactly two threads, so contention is low.
-    mailboxes: Vec<Mutex<Option<T>>>,
-    /// Counts the number of messages received in the current round of
-    /// communication per receiver.  The receiver must wait until it has all
-    /// `npeers` messages before reading all of them from mailboxes in one
-    /// pass.
+    /// The number of senders.
+    nsenders: usize,
+    /// The number of receivers.
+    nreceivers: usize,
+    /// The maximal number of values buffered in each mailbox.
+    depth: usize,
+    /// `nsenders * nreceivers` mailboxes, one for each sender/receiver pair.
+    /// Note that each mailbox is accessed by exactly two threads, so
+    /// contention is low.
+    mailboxes: Vec<Mutex<VecDeque<T>>>,
+    /// Counts the number of non-empty incoming mailboxes per receiver.  The
+    /// receiver must wait until it has messages from all `nsenders` senders
+    /// before reading one message from each mailbox in one pass.
     receiver_counters: Vec<CachePadded<AtomicUsize>>,
-    /// Callback invoked when all `npeers` messages are ready for a receiver.
+    /// Callback invoked when all `nsenders` messages are ready for a receiver.
     receiver_callbacks: Vec<OnceCell<Box<dyn Fn() + Send + Sync>>>,
-    /// Counts the number of empty mailboxes ready to accept new data per
-    /// sender. The sender waits until it has `npeers` available mailboxes
+    /// Counts the number of non-full mailboxes ready to accept new data per
+    /// sender. The sender waits until it has `nreceivers` available mailboxes
     /// before writing all of them in one pass.
     sender_counters: Vec<CachePadded<AtomicUsize>>,
-    /// Callback invoked when all `npeers` mailboxes are available.
+    /// Callback invoked when all `nreceivers` mailboxes are available.
     sender_callbacks: Vec<OnceCell<Box<dyn Fn() + Send + Sync>>>,
 }
 
@@ -67,49 +89,81 @@ impl<T> Exchange<T>
 where
     T: Send + 'static,
 {
-    /// Create a new exchange operator for `npeers` communicating threads.
-    fn new(npeers: usize) -> Self {
+    /// Create a new exchange operator for `nsenders` threads sending data to
+    /// `nreceivers` threads with mailboxes of size `depth`.
+    fn new(nsenders: usize, nreceivers: usize, depth: usize) -> Self {
+        assert!(depth > 0, "exchange depth must be positive");
+
         Self {
-            npeers,
-            mailboxes: (0..npeers * npeers).map(|_| Mutex::new(None)).collect(),
-            receiver_counters: (0..npeers)
+            nsenders,
+            nreceivers,
+            depth,
+            mailboxes: (0..nsenders * nreceivers)
+                .map(|_| Mutex::new(VecDeque::with_capacity(depth)))
+                .collect(),
+            receiver_counters: (0..nreceivers)
                 .map(|_| CachePadded::new(AtomicUsize::new(0)))
                 .collect(),
-            receiver_callbacks: (0..npeers).map(|_| OnceCell::new()).collect(),
-            sender_counters: (0..npeers)
-                .map(|_| CachePadded::new(AtomicUsize::new(npeers)))
+            receiver_callbacks: (0..nreceivers).map(|_| OnceCell::new()).collect(),
+            sender_counters: (0..nsenders)
+                .map(|_| CachePadded::new(AtomicUsize::new(nreceivers)))
                 .collect(),
-            sender_callbacks: (0..npeers).map(|_| OnceCell::new()).collect(),
+            sender_callbacks: (0..nsenders).map(|_| OnceCell::new()).collect(),
         }
     }
 
-    /// Create a new `Exchange` instance if an instance with the same id
+    /// Create a new N-to-N `Exchange` instance if an instance with the same id
     /// (created by another thread) does not yet exist within `runtime`.
-    /// The number of peers will be set to `runtime.num_workers()`.
-    pub(crate) fn with_runtime(runtime: &Runtime, exchange_id: usize) -> Arc<Self> {
+    /// The number of senders and receivers will be set to
+    /// `runtime.num_workers()`.
+    ///
+    /// All threads must specify the same `depth` for the same `exchange_id`.
+    pub(crate) fn with_runtime(runtime: &Runtime, exchange_id: usize, depth: usize) -> Arc<Self> {
+        let npeers = runtime.num_workers();
+        Self::with_runtime_n_to_m(runtime, exchange_id, npeers, npeers, depth)
+    }
+
+    /// Create a new N-to-M `Exchange` instance with `nsenders` senders and
+    /// `nreceivers` receivers if an instance with the same id (created by
+    /// another thread) does not yet exist within `runtime`.
+    ///
+    /// All threads must specify the same `nsenders`, `nreceivers`, and `depth`
+    /// for the same `exchange_id`.
+    pub(crate) fn with_runtime_n_to_m(
+        runtime: &Runtime,
+        exchange_id: usize,
+        nsenders: usize,
+        nreceivers: usize,
+        depth: usize,
+    ) -> Arc<Self> {
         runtime
             .local_store()
             .entry(ExchangeId::new(exchange_id))
-            .or_insert_with(|| Arc::new(Exchange::new(runtime.num_workers())))
+            .or_insert_with(|| Arc::new(Exchange::new(nsenders, nreceivers, depth)))
             .value()
             .clone()
     }
 
+    /// Returns the index of the mailbox for the sender/receiver pair.
+    fn mailbox_index(&self, sender: usize, receiver: usize) -> usize {
+        debug_assert!(sender < self.nsenders);
+        debug_assert!(receiver < self.nreceivers);
+        sender * self.nreceivers + receiver
+    }
+
     /// Returns a reference to a mailbox for the sender/receiver pair.
-    fn mailbox(&self, sender: usize, receiver: usize) -> &Mutex<Option<T>> {
-        debug_assert!(sender < self.npeers);
-        debug_assert!(receiver < self.npeers);
-        &self.mailboxes[sender * self.npeers + receiver]
+    fn mailbox(&self, sender: usize, receiver: usize) -> &Mutex<VecDeque<T>> {
+        &self.mailboxes[self.mailbox_index(sender, receiver)]
     }
 
-    /// True if all `sender`'s outgoing mailboxes are free and ready to accept
-    /// data.
+    /// True if none of `sender`'s outgoing mailboxes is full, i.e., all of
+    /// them are ready to accept data.
     ///
     /// Once this function returns true, a subsequent `try_send_all` operation
     /// is guaranteed to succeed for `sender`.
     fn ready_to_send(&self, sender: usize) -> bool {
-        debug_assert!(sender < self.npeers);
-        self.sender_counters[sender].load(Ordering::Acquire) == self.npeers
+        debug_assert!(sender < self.nsenders);
+        self.sender_counters[sender].load(Ordering::Acquire) == self.nreceivers
     }
 
     /// Write all outgoing messages for `sender` to mailboxes.
@@ -120,11 +174,11 @@ where
     ///
     /// # Errors
     ///
-    /// Fails if at least one of the sender's outgoing mailboxes is not empty.
+    /// Fails if at least one of the sender's outgoing mailboxes is full.
     ///
     /// # Panics
     ///
-    /// Panics if `data` yields fewer than `self.npeers` items.
+    /// Panics if `data` yields fewer than `self.nreceivers` items.
     pub(crate) fn try_send_all<I>(&self, sender: usize, data: &mut I) -> bool
     where
         I: Iterator<Item = T>,
@@ -133,11 +187,25 @@ where
             return false;
         }
 
-        for receiver in 0..self.npeers {
-            *self.mailbox(sender, receiver).lock().unwrap() = data.next();
-            self.sender_counters[sender].fetch_sub(1, Ordering::AcqRel);
-            let old_counter = self.receiver_counters[receiver].fetch_add(1, Ordering::AcqRel);
-            if old_counter >= self.npeers - 1 {
+        for receiver in 0..self.nreceivers {
+            let mut mailbox = self.mailbox(sender, receiver).lock().unwrap();
+            mailbox.push_back(data.next().unwrap());
+
+            // Counters are updated while holding the lock, so that they
+            // reflect transitions of the mailbox between empty, non-empty,
+            // and full states in the order in which they happen.
+            if mailbox.len() == self.depth {
+                self.sender_counters[sender].fetch_sub(1, Ordering::AcqRel);
+            }
+            let notify = if mailbox.len() == 1 {
+                let old_counter = self.receiver_counters[receiver].fetch_add(1, Ordering::AcqRel);
+                old_counter >= self.nsenders - 1
+            } else {
+                false
+            };
+            drop(mailbox);
+
+            if notify {
                 // This can be a spurious callback (see detailed comment in `try_receive_all`)
                 // below.
                 if let Some(cb) = self.receiver_callbacks[receiver].get() {
@@ -153,8 +221,8 @@ where
     /// Once this function returns true, a subsequent `try_receive_all`
     /// operation is guaranteed for `receiver`.
     pub(crate) fn ready_to_receive(&self, receiver: usize) -> bool {
-        debug_assert!(receiver < self.npeers);
-        self.receiver_counters[receiver].load(Ordering::Acquire) == self.npeers
+        debug_assert!(receiver < self.nreceivers);
+        self.receiver_counters[receiver].load(Ordering::Acquire) == self.nsenders
     }
 
     /// Read all incoming messages for `receiver`.
@@ -172,23 +240,29 @@ where
             return false;
         }
 
-        for sender in 0..self.npeers {
-            let data = self
-                .mailbox(sender, receiver)
-                .lock()
-                .unwrap()
-                .take()
-                .unwrap();
+        for sender in 0..self.nsenders {
+            let mut mailbox = self.mailbox(sender, receiver).lock().unwrap();
+            let data = mailbox.pop_front().unwrap();
+
+            if mailbox.is_empty() {
+                self.receiver_counters[receiver].fetch_sub(1, Ordering::Release);
+            }
+            let notify = if mailbox.len() == self.depth - 1 {
+                let old_counter = self.sender_counters[sender].fetch_add(1, Ordering::AcqRel);
+                old_counter >= self.nreceivers - 1
+            } else {
+                false
+            };
+            drop(mailbox);
+
             cb(data);
-            self.receiver_counters[receiver].fetch_sub(1, Ordering::Release);
-            let old_counter = self.sender_counters[sender].fetch_add(1, Ordering::AcqRel);
-            if old_counter >= self.npeers - 1 {
+            if notify {
                 // This can be a spurious callback if the following thread interleaving occurs:
-                // 1. Another receiver increments the sender's counter to `npeers`.
+                // 1. Another receiver increments the sender's counter to `nreceivers`.
                 // 2. The sender starts transmitting messages, writing `receiver`'s mailbox
-                // first    (counter drops to `npeers-1`)
+                // first    (counter drops to `nreceivers-1`)
                 // 3. `receiver` is unblocked and retrieves its message, bumping the counter
-                //    back to `npeers` and generating a spurious sender callback in the
+                //    back to `nreceivers` and generating a spurious sender callback in the
                 // following    line.
                 if let Some(cb) = self.sender_callbacks[sender].get() {
                     cb()
@@ -217,7 +291,7 @@ where
     where
         F: Fn() + Send + Sync + 'static,
     {
-        debug_assert!(sender < self.npeers);
+        debug_assert!(sender < self.nsenders);
         debug_assert!(self.sender_callbacks[sender].get().is_none());
         let res = self.sender_callbacks[sender].set(Box::new(cb) as Box<dyn Fn() + Send + Sync>);
         debug_assert!(res.is_ok());
@@ -242,7 +316,7 @@ where
     where
         F: Fn() + Send + Sync + 'static,
     {
-        debug_assert!(receiver < self.npeers);
+        debug_assert!(receiver < self.nreceivers);
         debug_assert!(self.receiver_callbacks[receiver].get().is_none());
         let res =
             self.receiver_callbacks[receiver].set(Box::new(cb) as Box<dyn Fn() + Send + Sync>);
@@ -300,6 +374,18 @@ where
 /// [`ExchangeSender`]/[`ExchangeReceiver`] pair of operators and connects them
 /// to their counterparts in other workers as in the diagram above.
 ///
+/// Operators created with [`new_buffered_exchange_operators`] instead buffer
+/// up to `depth` values for each peer, so that the sender only blocks once
+/// it gets `depth` clock cycles ahead of the slowest receiver.
+///
+/// [`Circuit::new_broadcast_operators`](`crate::circuit::Circuit::new_broadcast_operators`)
+/// and
+/// [`Circuit::new_gather_operators`](`crate::circuit::Circuit::new_gather_operators`)
+/// create operators where only one worker sends or receives data
+/// respectively.  In workers
+/// that don't participate in sending, `ExchangeSender` discards its inputs
+/// and is a regular synchronous operator.
+///
 /// An [`ExchangeSender`]/[`ExchangeReceiver`] pair is added to a circuit using
 /// the [`Circuit::add_exchange`](`crate::circuit::Circuit::add_exchange`)
 /// method, which registers a dependency between them, making sure that
@@ -374,7 +460,9 @@ where
 /// # }
 /// ```
 pub struct ExchangeSender<D, T, L> {
-    worker_index: usize,
+    /// Index of this worker among the senders of the exchange or `None` if
+    /// this worker doesn't send data.
+    sender_index: Option<usize>,
     location: OperatorLocation,
     partition: L,
     outputs: Vec<T>,
@@ -387,19 +475,18 @@ where
     T: Send + 'static,
 {
     fn new(
-        runtime: &Runtime,
-        worker_index: usize,
+        sender_index: Option<usize>,
         location: OperatorLocation,
-        exchange_id: usize,
+        exchange: Arc<Exchange<T>>,
         partition: L,
     ) -> Self {
-        debug_assert!(worker_index < runtime.num_workers());
+        debug_assert!(sender_index.map_or(true, |index| index < exchange.nsenders));
         Self {
-            worker_index,
+            sender_index,
             location,
             partition,
-            outputs: Vec::with_capacity(runtime.num_workers()),
-            exchange: Exchange::with_runtime(runtime, exchange_id),
+            outputs: Vec::with_capacity(exchange.nreceivers),
+            exchange,
             phantom: PhantomData,
         }
     }
@@ -423,24 +510,31 @@ where
     fn clock_end(&mut self, _scope: Scope) {}
 
     fn is_async(&self) -> bool {
-        true
+        self.sender_index.is_some()
     }
 
     fn register_ready_callback<F>(&mut self, cb: F)
     where
         F: Fn() + Send + Sync + 'static,
     {
-        self.exchange
-            .register_sender_callback(self.worker_index, cb)
+        if let Some(sender_index) = self.sender_index {
+            self.exchange.register_sender_callback(sender_index, cb)
+        }
     }
 
     fn ready(&self) -> bool {
-        self.exchange.ready_to_send(self.worker_index)
+        self.sender_index.map_or(true, |sender_index| {
+            self.exchange.ready_to_send(sender_index)
+        })
     }
 
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<D, T, L> SinkOperator<D> for ExchangeSender<D, T, L>
@@ -455,12 +549,14 @@ where
 
     fn eval_owned(&mut self, input: D) {
         debug_assert!(self.ready());
-        self.outputs.clear();
-        (self.partition)(input, &mut self.outputs);
-        let res = self
-            .exchange
-            .try_send_all(self.worker_index, &mut self.outputs.drain(..));
-        debug_assert!(res);
+        if let Some(sender_index) = self.sender_index {
+            self.outputs.clear();
+            (self.partition)(input, &mut self.outputs);
+            let res = self
+                .exchange
+                .try_send_all(sender_index, &mut self.outputs.drain(..));
+            debug_assert!(res);
+        }
     }
 
     fn input_preference(&self) -> OwnershipPreference {
@@ -479,8 +575,14 @@ where
 /// for this worker in the current clock cycle.  The scheduler should use
 /// [`ExchangeReceiver::register_ready_callback`] to get notified when the
 /// operator becomes schedulable.
+///
+/// In workers that don't receive data from the exchange (see
+/// [`Circuit::new_gather_operators`](`crate::circuit::Circuit::new_gather_operators`)),
+/// `ExchangeReceiver` is a synchronous operator that outputs the default value at each clock cycle.
 pub struct ExchangeReceiver<T, L> {
-    worker_index: usize,
+    /// Index of this worker among the receivers of the exchange or `None` if
+    /// this worker doesn't receive data.
+    receiver_index: Option<usize>,
     location: OperatorLocation,
     combine: L,
     exchange: Arc<Exchange<T>>,
@@ -491,19 +593,18 @@ where
     T: Send + 'static,
 {
     fn new(
-        runtime: &Runtime,
-        worker_index: usize,
+        receiver_index: Option<usize>,
         location: OperatorLocation,
-        exchange_id: usize,
+        exchange: Arc<Exchange<T>>,
         combine: L,
     ) -> Self {
-        debug_assert!(worker_index < runtime.num_workers());
+        debug_assert!(receiver_index.map_or(true, |index| index < exchange.nreceivers));
 
         Self {
-            worker_index,
+            receiver_index,
             location,
             combine,
-            exchange: Exchange::with_runtime(runtime, exchange_id),
+            exchange,
         }
     }
 }
@@ -522,24 +623,31 @@ where
     }
 
     fn is_async(&self) -> bool {
-        true
+        self.receiver_index.is_some()
     }
 
     fn register_ready_callback<F>(&mut self, cb: F)
     where
         F: Fn() + Send + Sync + 'static,
     {
-        self.exchange
-            .register_receiver_callback(self.worker_index, cb)
+        if let Some(receiver_index) = self.receiver_index {
+            self.exchange.register_receiver_callback(receiver_index, cb)
+        }
     }
 
     fn ready(&self) -> bool {
-        self.exchange.ready_to_receive(self.worker_index)
+        self.receiver_index.map_or(true, |receiver_index| {
+            self.exchange.ready_to_receive(receiver_index)
+        })
     }
 
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<D, T, L> SourceOperator<D> for ExchangeReceiver<T, L>
@@ -551,11 +659,13 @@ where
     fn eval(&mut self) -> D {
         debug_assert!(self.ready());
         let mut combined = Default::default();
-        let res = self
-            .exchange
-            .try_receive_all(self.worker_index, |x| (self.combine)(&mut combined, x));
+        if let Some(receiver_index) = self.receiver_index {
+            let res = self
+                .exchange
+                .try_receive_all(receiver_index, |x| (self.combine)(&mut combined, x));
+            debug_assert!(res);
+        }
 
-        debug_assert!(res);
         combined
     }
 }
@@ -596,9 +706,137 @@ where
     PL: FnMut(TI, &mut Vec<TE>) + 'static,
     CL: Fn(&mut TO, TE) + 'static,
 {
+    new_buffered_exchange_operators(runtime, worker_index, location, 1, partition, combine)
+}
+
+/// Create an [`ExchangeSender`]/[`ExchangeReceiver`] operator pair that
+/// buffers up to `depth` values per pair of workers.
+///
+/// Operators created by [`new_exchange_operators`] proceed in lockstep: a
+/// worker cannot send data for the next clock cycle until all peers have
+/// received data from the current cycle, so a single slow worker stalls
+/// all senders.  Operators created by this function allow a sender to run
+/// up to `depth` clock cycles ahead of the slowest receiver.  Values are
+/// still delivered to each receiver in the order they were sent.
+///
+/// # Memory bound
+///
+/// Each worker buffers at most `depth * runtime.num_workers()` outgoing
+/// values, i.e., the exchange holds at most `depth * runtime.num_workers()^2`
+/// values at any time.  `depth = 1` is equivalent to
+/// [`new_exchange_operators`].
+///
+/// All workers must use the same `depth` for the same exchange.
+///
+/// # Panics
+///
+/// Panics if `depth` is 0.
+///
+/// # Arguments
+///
+/// See [`new_exchange_operators`].
+pub fn new_buffered_exchange_operators<TI, TO, TE, PL, CL>(
+    runtime: &Runtime,
+    worker_index: usize,
+    location: OperatorLocation,
+    depth: usize,
+    partition: PL,
+    combine: CL,
+) -> (ExchangeSender<TI, TE, PL>, ExchangeReceiver<TE, CL>)
+where
+    TO: Default + Clone,
+    TE: Send + 'static,
+    PL: FnMut(TI, &mut Vec<TE>) + 'static,
+    CL: Fn(&mut TO, TE) + 'static,
+{
+    debug_assert!(worker_index < runtime.num_workers());
+
+    let exchange_id = runtime.sequence_next(worker_index);
+    let exchange = Exchange::with_runtime(runtime, exchange_id, depth);
+    let sender = ExchangeSender::new(Some(worker_index), location, exchange.clone(), partition);
+    let receiver = ExchangeReceiver::new(Some(worker_index), location, exchange, combine);
+    (sender, receiver)
+}
+
+/// Sender half of the operator pair created by
+/// [`Circuit::new_broadcast_operators`](`crate::circuit::Circuit::new_broadcast_operators`).
+pub type BroadcastSender<T> = ExchangeSender<T, T, Box<dyn FnMut(T, &mut Vec<T>)>>;
+
+/// Receiver half of the operator pair created by
+/// [`Circuit::new_broadcast_operators`](`crate::circuit::Circuit::new_broadcast_operators`).
+pub type BroadcastReceiver<T> = ExchangeReceiver<T, fn(&mut T, T)>;
+
+/// Sender half of the operator pair created by
+/// [`Circuit::new_gather_operators`](`crate::circuit::Circuit::new_gather_operators`).
+pub type GatherSender<T> = ExchangeSender<T, T, fn(T, &mut Vec<T>)>;
+
+/// Implementation of
+/// [`Circuit::new_broadcast_operators`](`crate::circuit::Circuit::new_broadcast_operators`).
+pub(crate) fn new_broadcast_operators<T>(
+    runtime: &Runtime,
+    worker_index: usize,
+    location: OperatorLocation,
+    sender_worker: usize,
+) -> (BroadcastSender<T>, BroadcastReceiver<T>)
+where
+    T: Default + Clone + Send + 'static,
+{
+    let num_workers = runtime.num_workers();
+    debug_assert!(worker_index < num_workers);
+    assert!(sender_worker < num_workers);
+
+    let exchange_id = runtime.sequence_next(worker_index);
+    let exchange = Exchange::with_runtime_n_to_m(runtime, exchange_id, 1, num_workers, 1);
+    let sender = ExchangeSender::new(
+        (worker_index == sender_worker).then_some(0),
+        location,
+        exchange.clone(),
+        Box::new(move |value: T, values: &mut Vec<T>| {
+            values.extend((1..num_workers).map(|_| value.clone()));
+            values.push(value);
+        }) as Box<dyn FnMut(T, &mut Vec<T>)>,
+    );
+    let receiver = ExchangeReceiver::new(
+        Some(worker_index),
+        location,
+        exchange,
+        (|result: &mut T, value: T| *result = value) as fn(&mut T, T),
+    );
+    (sender, receiver)
+}
+
+/// Implementation of
+/// [`Circuit::new_gather_operators`](`crate::circuit::Circuit::new_gather_operators`).
+pub(crate) fn new_gather_operators<TI, TO, CL>(
+    runtime: &Runtime,
+    worker_index: usize,
+    location: OperatorLocation,
+    receiver_worker: usize,
+    combine: CL,
+) -> (GatherSender<TI>, ExchangeReceiver<TI, CL>)
+where
+    TO: Default + Clone,
+    TI: Send + 'static,
+    CL: Fn(&mut TO, TI) + 'static,
+{
+    let num_workers = runtime.num_workers();
+    debug_assert!(worker_index < num_workers);
+    assert!(receiver_worker < num_workers);
+
     let exchange_id = runtime.sequence_next(worker_index);
-    let sender = ExchangeSender::new(runtime, worker_index, location, exchange_id, partition);
-    let receiver = ExchangeReceiver::new(runtime, worker_index, location, exchange_id, combine);
+    let exchange = Exchange::with_runtime_n_to_m(runtime, exchange_id, num_workers, 1, 1);
+    let sender = ExchangeSender::new(
+        Some(worker_index),
+        location,
+        exchange.clone(),
+        (|value: TI, values: &mut Vec<TI>| values.push(value)) as fn(TI, &mut Vec<TI>),
+    );
+    let receiver = ExchangeReceiver::new(
+        (worker_index == receiver_worker).then_some(0),
+        location,
+        exchange,
+        combine,
+    );
     (sender, receiver)
 }
 
@@ -610,10 +848,16 @@ mod tests {
             schedule::{DynamicScheduler, Scheduler, StaticScheduler},
             Runtime,
         },
-        operator::{communication::new_exchange_operators, Generator},
+        operator::{
+            communication::{new_buffered_exchange_operators, new_exchange_operators},
+            Generator,
+        },
         Circuit, RootCircuit,
     };
-    use std::thread::yield_now;
+    use std::{
+        thread::{sleep, yield_now},
+        time::Duration,
+    };
 
     // We decrease the number of rounds we do when we're running under miri,
     // otherwise it'll run forever
@@ -627,10 +871,20 @@ mod tests {
     #[test]
     #[cfg_attr(miri, ignore)]
     fn test_exchange() {
+        do_test_exchange(1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_buffered_exchange() {
+        do_test_exchange(4);
+    }
+
+    fn do_test_exchange(depth: usize) {
         const WORKERS: usize = 16;
 
-        let hruntime = Runtime::run(WORKERS, || {
-            let exchange = Exchange::with_runtime(&Runtime::runtime().unwrap(), 0);
+        let hruntime = Runtime::run(WORKERS, move || {
+            let exchange = Exchange::with_runtime(&Runtime::runtime().unwrap(), 0, depth);
 
             for round in 0..ROUNDS {
                 let output_data = vec![round; WORKERS];
@@ -659,6 +913,84 @@ mod tests {
         hruntime.join().unwrap();
     }
 
+    // A sender can send `depth` rounds without waiting for receivers, but
+    // not more.
+    #[test]
+    fn test_exchange_depth() {
+        const DEPTH: usize = 3;
+
+        let exchange = Exchange::new(2, 2, DEPTH);
+
+        for round in 0..DEPTH {
+            assert!(exchange.ready_to_send(0));
+            assert!(exchange.try_send_all(0, &mut vec![round; 2].into_iter()));
+        }
+        assert!(!exchange.ready_to_send(0));
+        assert!(!exchange.try_send_all(0, &mut vec![DEPTH; 2].into_iter()));
+
+        // Receivers are waiting for data from sender 1.
+        assert!(!exchange.ready_to_receive(0));
+        assert!(!exchange.ready_to_receive(1));
+        assert!(exchange.try_send_all(1, &mut vec![0; 2].into_iter()));
+
+        // Receiving round 0 on one receiver is not enough to unblock the
+        // sender.
+        let mut received = Vec::new();
+        assert!(exchange.try_receive_all(0, |x| received.push(x)));
+        assert_eq!(received, vec![0, 0]);
+        assert!(!exchange.ready_to_send(0));
+        assert!(!exchange.ready_to_receive(0));
+
+        received.clear();
+        assert!(exchange.try_receive_all(1, |x| received.push(x)));
+        assert_eq!(received, vec![0, 0]);
+        assert!(exchange.ready_to_send(0));
+        assert!(exchange.try_send_all(0, &mut vec![DEPTH; 2].into_iter()));
+        assert!(!exchange.ready_to_send(0));
+
+        // Sender 1 is not blocked by sender 0 running ahead.
+        for round in 1..=DEPTH {
+            assert!(exchange.try_send_all(1, &mut vec![round; 2].into_iter()));
+        }
+
+        // Values are received in the order they were sent.
+        for receiver in 0..2 {
+            for round in 1..=DEPTH {
+                received.clear();
+                assert!(exchange.try_receive_all(receiver, |x| received.push(x)));
+                assert_eq!(received, vec![round, round]);
+            }
+            assert!(!exchange.ready_to_receive(receiver));
+        }
+        assert!(exchange.ready_to_send(0));
+        assert!(exchange.ready_to_send(1));
+    }
+
+    // Exchange with 3 senders and 2 receivers.
+    #[test]
+    fn test_exchange_n_to_m() {
+        let exchange = Exchange::new(3, 2, 1);
+
+        for sender in 0..3 {
+            assert!(!exchange.ready_to_receive(0));
+            assert!(exchange.try_send_all(sender, &mut vec![sender, sender + 10].into_iter()));
+            assert!(!exchange.ready_to_send(sender));
+        }
+
+        let mut received = Vec::new();
+        assert!(exchange.try_receive_all(1, |x| received.push(x)));
+        assert_eq!(received, vec![10, 11, 12]);
+        assert!(!exchange.ready_to_send(0));
+
+        received.clear();
+        assert!(exchange.try_receive_all(0, |x| received.push(x)));
+        assert_eq!(received, vec![0, 1, 2]);
+
+        for sender in 0..3 {
+            assert!(exchange.ready_to_send(sender));
+        }
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)]
     fn test_exchange_operators_static() {
@@ -729,4 +1061,196 @@ mod tests {
         do_test::<S>(16);
         do_test::<S>(32);
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_buffered_exchange_operators_static() {
+        test_buffered_exchange_operators::<StaticScheduler>();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_buffered_exchange_operators_dynamic() {
+        test_buffered_exchange_operators::<DynamicScheduler>();
+    }
+
+    // Stress test for buffered exchange: same as `test_exchange_operators`,
+    // but workers are artificially slowed down by different amounts at
+    // different points of the circuit, so that some senders run ahead of
+    // slow receivers.
+    fn test_buffered_exchange_operators<S>()
+    where
+        S: Scheduler + 'static,
+    {
+        const ROUNDS: usize = if cfg!(miri) { 32 } else { 512 };
+
+        fn do_test<S>(workers: usize, depth: usize)
+        where
+            S: Scheduler + 'static,
+        {
+            let hruntime = Runtime::run(workers, move || {
+                let worker_index = Runtime::worker_index();
+
+                let circuit = RootCircuit::build_with_scheduler::<_, _, S>(move |circuit| {
+                    let mut n: usize = 0;
+                    let source = circuit.add_source(Generator::new(move || {
+                        // Odd workers are slow to produce data.
+                        if worker_index % 2 == 1 && n % 16 == 0 {
+                            sleep(Duration::from_micros(100 * worker_index as u64));
+                        }
+                        let result = n;
+                        n += 1;
+                        result
+                    }));
+
+                    let (sender, receiver) = new_buffered_exchange_operators(
+                        &Runtime::runtime().unwrap(),
+                        worker_index,
+                        None,
+                        depth,
+                        move |n, vals| {
+                            for _ in 0..workers {
+                                vals.push(n)
+                            }
+                        },
+                        |v: &mut Vec<usize>, n| v.push(n),
+                    );
+
+                    let mut round = 0;
+                    circuit
+                        .add_exchange(sender, receiver, &source)
+                        .inspect(move |v| {
+                            assert_eq!(&vec![round; workers], v);
+
+                            // Even workers are slow to consume data.
+                            if worker_index % 2 == 0 && round % 8 == 0 {
+                                sleep(Duration::from_micros(50 * (worker_index as u64 + 1)));
+                            }
+                            round += 1;
+                        });
+                })
+                .unwrap()
+                .0;
+
+                for _ in 1..ROUNDS {
+                    circuit.step().unwrap();
+                }
+            });
+
+            hruntime.join().unwrap();
+        }
+
+        for depth in [2, 8] {
+            do_test::<S>(1, depth);
+            do_test::<S>(4, depth);
+            do_test::<S>(16, depth);
+        }
+    }
+
+    // Worker 1 broadcasts sequential numbers 0, 1, 2, ... to all workers;
+    // other workers' inputs are discarded.
+    fn test_broadcast_operators<S>()
+    where
+        S: Scheduler + 'static,
+    {
+        const WORKERS: usize = 4;
+
+        let hruntime = Runtime::run(WORKERS, || {
+            let worker_index = Runtime::worker_index();
+
+            let circuit = RootCircuit::build_with_scheduler::<_, _, S>(move |circuit| {
+                let mut n: usize = 0;
+                let source = circuit.add_source(Generator::new(move || {
+                    let result = if worker_index == 1 { n } else { usize::MAX };
+                    n += 1;
+                    result
+                }));
+
+                let (sender, receiver) =
+                    circuit.new_broadcast_operators(&Runtime::runtime().unwrap(), None, 1);
+
+                let mut round = 0;
+                circuit
+                    .add_exchange(sender, receiver, &source)
+                    .inspect(move |n: &usize| {
+                        assert_eq!(*n, round);
+                        round += 1;
+                    });
+            })
+            .unwrap()
+            .0;
+
+            for _ in 0..ROUNDS {
+                circuit.step().unwrap();
+            }
+        });
+
+        hruntime.join().unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_broadcast_operators_static() {
+        test_broadcast_operators::<StaticScheduler>();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_broadcast_operators_dynamic() {
+        test_broadcast_operators::<DynamicScheduler>();
+    }
+
+    // All workers send their index to worker 2, which collects them in a
+    // vector.
+    fn test_gather_operators<S>()
+    where
+        S: Scheduler + 'static,
+    {
+        const WORKERS: usize = 4;
+
+        let hruntime = Runtime::run(WORKERS, || {
+            let worker_index = Runtime::worker_index();
+
+            let circuit = RootCircuit::build_with_scheduler::<_, _, S>(move |circuit| {
+                let source = circuit.add_source(Generator::new(move || worker_index));
+
+                let (sender, receiver) = circuit.new_gather_operators(
+                    &Runtime::runtime().unwrap(),
+                    None,
+                    2,
+                    |v: &mut Vec<usize>, n| v.push(n),
+                );
+
+                circuit
+                    .add_exchange(sender, receiver, &source)
+                    .inspect(move |v: &Vec<usize>| {
+                        if worker_index == 2 {
+                            assert_eq!(v, &(0..WORKERS).collect::<Vec<_>>());
+                        } else {
+                            assert!(v.is_empty());
+                        }
+                    });
+            })
+            .unwrap()
+            .0;
+
+            for _ in 0..ROUNDS {
+                circuit.step().unwrap();
+            }
+        });
+
+        hruntime.join().unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_gather_operators_static() {
+        test_gather_operators::<StaticScheduler>();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_gather_operators_dynamic() {
+        test_gather_operators::<DynamicScheduler>();
+    }
 }
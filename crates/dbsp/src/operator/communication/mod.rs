@@ -1,6 +1,10 @@
+mod broadcast;
 mod exchange;
 mod gather;
 mod shard;
 
-pub(crate) use exchange::Exchange;
-pub use exchange::{new_exchange_operators, ExchangeReceiver, ExchangeSender};
+pub(crate) use exchange::{new_broadcast_operators, new_gather_operators, Exchange};
+pub use exchange::{
+    new_buffered_exchange_operators, new_exchange_operators, BroadcastReceiver, BroadcastSender,
+    ExchangeReceiver, ExchangeSender, GatherSender,
+};
@@ -9,7 +9,7 @@ use crate::{
     circuit_cache_key, default_hash,
     operator::communication::exchange::new_exchange_operators,
     trace::{cursor::Cursor, Batch, BatchReader, Builder, Spine, Trace},
-    Circuit, Runtime, Stream,
+    Circuit, DBData, DBWeight, OrdIndexedZSet, Runtime, Stream,
 };
 use std::{hash::Hash, panic::Location};
 
@@ -199,6 +199,89 @@ where
     }
 }
 
+impl<C, IB> Stream<C, IB>
+where
+    C: Circuit,
+    IB: BatchReader<Time = ()> + Clone + Send + 'static,
+{
+    /// Apply `map_func` to each record in the input stream and shard the
+    /// resulting indexed Z-set across worker threads based on its keys.
+    ///
+    /// This is equivalent to `map_index(map_func).shard()`, but sorts mapped
+    /// records directly into one batch per destination worker, instead of
+    /// first assembling them into a single batch and then splitting it.
+    #[track_caller]
+    pub fn map_index_shard<F, K, V>(&self, map_func: F) -> Stream<C, OrdIndexedZSet<K, V, IB::R>>
+    where
+        F: Fn((&IB::Key, &IB::Val)) -> (K, V) + 'static,
+        K: DBData,
+        V: DBData,
+        IB::R: DBWeight,
+    {
+        let location = Location::caller();
+
+        let output = match Runtime::runtime() {
+            Some(runtime) if runtime.num_workers() > 1 => {
+                let num_workers = runtime.num_workers();
+
+                let (sender, receiver) = new_exchange_operators(
+                    &runtime,
+                    Runtime::worker_index(),
+                    Some(location),
+                    move |batch: IB, batches: &mut Vec<OrdIndexedZSet<K, V, IB::R>>| {
+                        Self::map_shard_batch(&batch, num_workers, &map_func, batches);
+                    },
+                    |trace: &mut Spine<OrdIndexedZSet<K, V, IB::R>>, batch| trace.insert(batch),
+                );
+
+                self.circuit()
+                    .add_exchange(sender, receiver, self)
+                    .consolidate()
+            }
+            _ => self.apply(move |batch: &IB| {
+                let mut batches = Vec::with_capacity(1);
+                Self::map_shard_batch(batch, 1, &map_func, &mut batches);
+                batches.pop().unwrap()
+            }),
+        };
+
+        output.mark_sharded()
+    }
+
+    // Maps tuples in the batch with `map_func` and partitions the results into
+    // `shards` partitions based on the hash of the new key.
+    fn map_shard_batch<F, K, V>(
+        batch: &IB,
+        shards: usize,
+        map_func: &F,
+        outputs: &mut Vec<OrdIndexedZSet<K, V, IB::R>>,
+    ) where
+        F: Fn((&IB::Key, &IB::Val)) -> (K, V),
+        K: DBData,
+        V: DBData,
+        IB::R: DBWeight,
+    {
+        // Mapped tuples are no longer ordered, so we collect them and let
+        // `from_tuples` sort them.
+        let mut tuples = vec![Vec::new(); shards];
+        let mut cursor = batch.cursor();
+
+        while cursor.key_valid() {
+            while cursor.val_valid() {
+                let (key, val) = map_func((cursor.key(), cursor.val()));
+                let batch_index = default_hash(&key) as usize % shards;
+                tuples[batch_index].push(((key, val), cursor.weight()));
+                cursor.step_val();
+            }
+            cursor.step_key();
+        }
+
+        for tuples in tuples {
+            outputs.push(OrdIndexedZSet::from_tuples((), tuples));
+        }
+    }
+}
+
 impl<C, T> Stream<C, T>
 where
     C: Circuit,
@@ -0,0 +1,156 @@
+//! Connected components of an undirected graph.
+
+use crate::{
+    algebra::ZRingValue,
+    circuit::Stream,
+    operator::{FilterMap, Min},
+    DBData, OrdZSet, RootCircuit,
+};
+
+impl<N, R> Stream<RootCircuit, OrdZSet<(N, N), R>>
+where
+    N: DBData,
+    R: ZRingValue,
+{
+    /// Incrementally compute connected components of an undirected graph.
+    ///
+    /// Treats `self` as a stream of changes to a relation that contains
+    /// `(from, to)` edges of an undirected graph, i.e., edge `(a, b)` connects
+    /// `a` to `b` and `b` to `a`, and outputs a stream of changes to the
+    /// relation that maps each node in the graph to its component id,
+    /// defined as the smallest node in its connected component.  Nodes are
+    /// only known through the edges that contain them.  Each `(node,
+    /// component)` pair has weight `1`.
+    ///
+    /// This operator uses [`recursive`](`crate::ChildCircuit::recursive`) to
+    /// build a nested circuit that propagates labels along edges until it
+    /// reaches a fixed point:
+    ///
+    /// ```text
+    /// labels = min_by_node((node, node) + labels ⋈ edges)
+    /// ```
+    ///
+    /// where each node starts with its own id as its label.  Computing the
+    /// fixed point takes as many iterations as the diameter of the largest
+    /// component.
+    ///
+    /// # Cost of updates
+    ///
+    /// Inserting an edge that merges two components relabels the nodes of
+    /// the component with the larger id, and the work is proportional to the
+    /// size of that component.  Deleting an edge is more expensive: the
+    /// component may be split in two, so labels of all nodes in the affected
+    /// component that were derived via the deleted edge are retracted and
+    /// recomputed from scratch, which takes work proportional to the size of
+    /// the component times its diameter in the worst case.
+    pub fn connected_components(&self) -> Stream<RootCircuit, OrdZSet<(N, N), R>> {
+        self.circuit()
+            .recursive(|child, labels: Stream<_, OrdZSet<(N, N), R>>| {
+                let edges = self.delta0(child);
+
+                // Each node is initially labeled with its own id.
+                let nodes = edges.flat_map(|(from, to)| {
+                    [(from.clone(), from.clone()), (to.clone(), to.clone())]
+                });
+
+                // Edges in both directions, indexed by source node.
+                let undirected = edges.flat_map_index(|(from, to)| {
+                    [(from.clone(), to.clone()), (to.clone(), from.clone())]
+                });
+
+                // Propagate the label of each node to its neighbors.
+                let propagated = labels.index().join(&undirected, |_node, label, neighbor| {
+                    (neighbor.clone(), label.clone())
+                });
+
+                // Each node takes the smallest label among its own id and
+                // labels propagated from its neighbors.
+                Ok(nodes
+                    .plus(&propagated)
+                    .index()
+                    .aggregate(Min)
+                    .map(|(node, label)| (node.clone(), label.clone())))
+            })
+            // The nested circuit is fixed and never violates scheduler constraints.
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{zset, Runtime};
+
+    fn connected_components_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, output_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (edges, input_handle) = circuit.add_input_zset::<(usize, usize), isize>();
+                let output_handle = edges.connected_components().integrate().output();
+
+                (input_handle, output_handle)
+            })
+            .unwrap();
+
+        // Two components: 1 - 2 - 3 and 4 - 5.
+        input_handle.append(&mut vec![((2, 1), 1), ((2, 3), 1), ((5, 4), 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! { (1, 1) => 1, (2, 1) => 1, (3, 1) => 1, (4, 4) => 1, (5, 4) => 1 }
+        );
+
+        // Inserting edge 3 - 5 merges the two components; nodes of the
+        // component with the larger id get relabeled.
+        input_handle.append(&mut vec![((3, 5), 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! { (1, 1) => 1, (2, 1) => 1, (3, 1) => 1, (4, 1) => 1, (5, 1) => 1 }
+        );
+
+        // A redundant edge doesn't change the labeling.
+        input_handle.append(&mut vec![((1, 3), 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! { (1, 1) => 1, (2, 1) => 1, (3, 1) => 1, (4, 1) => 1, (5, 1) => 1 }
+        );
+
+        // Deleting edge 2 - 3 doesn't split the component, since 1 - 3
+        // connects the two halves.
+        input_handle.append(&mut vec![((2, 3), -1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! { (1, 1) => 1, (2, 1) => 1, (3, 1) => 1, (4, 1) => 1, (5, 1) => 1 }
+        );
+
+        // Deleting edge 3 - 5 splits the component into 1 - 2, 1 - 3 and
+        // 4 - 5.
+        input_handle.append(&mut vec![((3, 5), -1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! { (1, 1) => 1, (2, 1) => 1, (3, 1) => 1, (4, 4) => 1, (5, 4) => 1 }
+        );
+
+        // Deleting the last edge of a component removes its nodes.
+        input_handle.append(&mut vec![((5, 4), -1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! { (1, 1) => 1, (2, 1) => 1, (3, 1) => 1 }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn connected_components_test1() {
+        connected_components_test(1);
+    }
+
+    #[test]
+    fn connected_components_test4() {
+        connected_components_test(4);
+    }
+}
@@ -5,7 +5,7 @@ use std::{borrow::Cow, marker::PhantomData};
 use crate::{
     circuit::{
         operator_traits::{Operator, UnaryOperator},
-        Circuit, GlobalNodeId, OwnershipPreference, Scope, Stream,
+        CheckpointError, Circuit, GlobalNodeId, OwnershipPreference, Scope, Stream,
     },
     circuit_cache_key,
     trace::{Batch, Trace},
@@ -73,6 +73,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T> UnaryOperator<T, T::Batch> for Consolidate<T>
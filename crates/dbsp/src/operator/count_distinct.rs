@@ -0,0 +1,191 @@
+//! Threshold notifications over per-key distinct counts.
+
+use crate::{
+    algebra::{DefaultSemigroup, HasOne, IndexedZSet, ZRingValue},
+    operator::Fold,
+    trace::{Batch, BatchReader, Builder, Cursor},
+    OrdIndexedZSet, OrdZSet, RootCircuit, Stream,
+};
+use bincode::{Decode, Encode};
+use size_of::SizeOf;
+
+/// Direction in which the distinct count of a group crossed a threshold.
+///
+/// See [`Stream::count_distinct_threshold`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, SizeOf, Encode, Decode)]
+pub enum ThresholdDirection {
+    /// The count grew from below the threshold to at or above it.
+    Up,
+    /// The count dropped from at or above the threshold to below it.
+    Down,
+}
+
+impl<Z> Stream<RootCircuit, Z>
+where
+    Z: IndexedZSet + Send,
+    Z::R: ZRingValue,
+{
+    /// Emit an event whenever the number of distinct values associated with
+    /// a key crosses one of `thresholds`.
+    ///
+    /// The operator maintains the number of distinct values with positive
+    /// weight for each key in the input indexed Z-set.  At each clock cycle
+    /// it compares the old and the new count of every updated key and
+    /// outputs a `(key, threshold, direction)` tuple with weight `+1` for
+    /// each threshold `t` such that `old < t <= new` (direction
+    /// [`Up`](`ThresholdDirection::Up`)) or `new < t <= old` (direction
+    /// [`Down`](`ThresholdDirection::Down`)).  Changes that do not cross
+    /// any threshold produce no output.
+    ///
+    /// Unlike most operators, the output of this operator is a stream of
+    /// events and not a stream of changes to a relation, i.e., integrating
+    /// it does not produce a meaningful collection.
+    #[allow(clippy::type_complexity)]
+    pub fn count_distinct_threshold(
+        &self,
+        mut thresholds: Vec<u64>,
+    ) -> Stream<RootCircuit, OrdZSet<(Z::Key, u64, ThresholdDirection), Z::R>> {
+        thresholds.sort_unstable();
+        thresholds.dedup();
+
+        let counts = self.aggregate(<Fold<_, DefaultSemigroup<_>, _, _>>::new(
+            0u64,
+            |count: &mut u64, _val: &Z::Val, weight: Z::R| {
+                if weight.ge0() {
+                    *count += 1;
+                }
+            },
+        ));
+
+        let output = counts.apply_named(
+            "CountDistinctThreshold",
+            move |delta: &OrdIndexedZSet<Z::Key, u64, Z::R>| {
+                let mut builder =
+                    <<OrdZSet<_, _> as Batch>::Builder>::with_capacity((), delta.key_count());
+                let mut cursor = delta.cursor();
+
+                while cursor.key_valid() {
+                    // The output of `aggregate` contains at most one retraction of
+                    // the old count and one insertion of the new count per key.
+                    let mut old = 0;
+                    let mut new = 0;
+
+                    while cursor.val_valid() {
+                        if cursor.weight().ge0() {
+                            new = *cursor.val();
+                        } else {
+                            old = *cursor.val();
+                        }
+                        cursor.step_val();
+                    }
+
+                    let from = thresholds.partition_point(|t| *t <= old);
+                    let to = thresholds.partition_point(|t| *t <= new);
+
+                    let (crossed, direction) = if from < to {
+                        (&thresholds[from..to], ThresholdDirection::Up)
+                    } else {
+                        (&thresholds[to..from], ThresholdDirection::Down)
+                    };
+
+                    for threshold in crossed {
+                        builder
+                            .push(((cursor.key().clone(), *threshold, direction), HasOne::one()));
+                    }
+
+                    cursor.step_key();
+                }
+
+                builder.done()
+            },
+        );
+
+        output.mark_sharded_if(&counts);
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ThresholdDirection::{Down, Up};
+    use crate::{zset, Runtime};
+
+    fn count_distinct_threshold_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, output_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (input, input_handle) = circuit.add_input_indexed_zset::<usize, usize, isize>();
+                let output_handle = input.count_distinct_threshold(vec![5, 3]).output();
+
+                (input_handle, output_handle)
+            })
+            .unwrap();
+
+        // Rise from 0 to 2: no threshold crossed.
+        input_handle.append(&mut vec![(1, (1, 1)), (1, (2, 1)), (2, (1, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! {});
+
+        // Rise from 2 to 3: cross threshold 3 upwards.
+        input_handle.append(&mut vec![(1, (3, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! { (1, 3, Up) => 1 });
+
+        // Stay above the threshold: duplicates and churn between 3 and 4
+        // values don't produce events.
+        input_handle.append(&mut vec![(1, (3, 1)), (1, (4, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! {});
+
+        input_handle.append(&mut vec![(1, (4, -1)), (1, (5, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! {});
+
+        // Removing one of two copies of a value doesn't change the count.
+        input_handle.append(&mut vec![(1, (3, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! {});
+
+        // Drop from 3 to 2: cross threshold 3 downwards.
+        input_handle.append(&mut vec![(1, (3, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! { (1, 3, Down) => 1 });
+
+        // A single update can cross multiple thresholds.
+        input_handle.append(&mut vec![
+            (2, (2, 1)),
+            (2, (3, 1)),
+            (2, (4, 1)),
+            (2, (5, 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! { (2, 3, Up) => 1, (2, 5, Up) => 1 }
+        );
+
+        input_handle.append(&mut vec![
+            (2, (1, -1)),
+            (2, (2, -1)),
+            (2, (3, -1)),
+            (2, (4, -1)),
+            (2, (5, -1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! { (2, 3, Down) => 1, (2, 5, Down) => 1 }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn count_distinct_threshold_test1() {
+        count_distinct_threshold_test(1);
+    }
+
+    #[test]
+    fn count_distinct_threshold_test4() {
+        count_distinct_threshold_test(4);
+    }
+}
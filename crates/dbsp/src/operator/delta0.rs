@@ -4,7 +4,7 @@ use crate::{
     algebra::HasZero,
     circuit::{
         operator_traits::{Data, ImportOperator, Operator},
-        Circuit, OwnershipPreference, Scope, Stream,
+        CheckpointError, Circuit, OwnershipPreference, Scope, Stream,
     },
 };
 use std::borrow::Cow;
@@ -114,6 +114,10 @@ where
             true
         }
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<D> ImportOperator<D, D> for Delta0<D>
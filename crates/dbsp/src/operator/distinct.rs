@@ -5,7 +5,7 @@ use crate::{
     circuit::{
         metadata::{MetaItem, OperatorMeta},
         operator_traits::{BinaryOperator, Operator, UnaryOperator},
-        Circuit, GlobalNodeId, Scope, Stream, WithClock,
+        CheckpointError, Circuit, GlobalNodeId, Scope, Stream, WithClock,
     },
     circuit_cache_key,
     trace::{ord::OrdValSpine, Batch, BatchReader, Builder, Cursor as TraceCursor, Trace},
@@ -61,6 +61,12 @@ where
     ///
     /// Intuitively, the operator converts the input multiset into a set
     /// by eliminating duplicates.
+    ///
+    /// The operator maintains a trace of the input collection and only
+    /// outputs changes for keys whose weight crosses the boundary between
+    /// zero (or negative) and positive: a weight going from `0` to `2`
+    /// outputs `+1`, from `1` to `0` outputs `-1`, and from `2` to `1`
+    /// outputs nothing.
     pub fn distinct(&self) -> Stream<C, Z>
     where
         Z: IndexedZSet + Send,
@@ -134,6 +140,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<Z> UnaryOperator<Z, Z> for Distinct<Z>
@@ -185,6 +195,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<Z, I> BinaryOperator<Z, I, Z> for DistinctIncrementalTotal<Z, I>
@@ -547,6 +561,16 @@ where
                 .keys()
                 .all(|ts| !ts.less_equal(&epoch_end))
     }
+
+    // Updates scheduled for future timestamps only exist in nested circuits,
+    // where they span parent clock cycles, and are not serializable.
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        if self.keys_of_interest.is_empty() {
+            Ok(None)
+        } else {
+            Err(CheckpointError::Unsupported)
+        }
+    }
 }
 
 impl<Z, T, Clk> BinaryOperator<Z, T, Z> for DistinctIncremental<Z, T, Clk>
@@ -889,6 +913,45 @@ mod test {
         circuit.kill().unwrap();
     }
 
+    // The output of `distinct` only changes when the weight of a key crosses
+    // the boundary between zero and positive.
+    #[test]
+    fn distinct_boundary_test() {
+        let (mut circuit, (mut input, output)) = Runtime::init_circuit(4, |circuit| {
+            let (input, input_handle) = circuit.add_input_zset::<usize, isize>();
+
+            (input_handle, input.distinct().output())
+        })
+        .unwrap();
+
+        // 0 -> 2: the key appears.
+        input.append(&mut vec![(1, 2)]);
+        circuit.step().unwrap();
+        assert_eq!(output.consolidate(), zset! { 1 => 1 });
+
+        // 2 -> 1: no change.
+        input.append(&mut vec![(1, -1)]);
+        circuit.step().unwrap();
+        assert_eq!(output.consolidate(), zset! {});
+
+        // 1 -> 0: the key disappears.
+        input.append(&mut vec![(1, -1)]);
+        circuit.step().unwrap();
+        assert_eq!(output.consolidate(), zset! { 1 => -1 });
+
+        // 0 -> -1: negative weights are not in the support of `distinct`.
+        input.append(&mut vec![(1, -1)]);
+        circuit.step().unwrap();
+        assert_eq!(output.consolidate(), zset! {});
+
+        // -1 -> 1: the key reappears.
+        input.append(&mut vec![(1, 2)]);
+        circuit.step().unwrap();
+        assert_eq!(output.consolidate(), zset! { 1 => 1 });
+
+        circuit.kill().unwrap();
+    }
+
     use proptest::{collection, prelude::*};
 
     type TestZSet = OrdZSet<usize, isize>;
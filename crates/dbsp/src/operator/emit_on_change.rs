@@ -0,0 +1,237 @@
+//! Suppress small changes to per-key values.
+
+use crate::{
+    algebra::{HasOne, IndexedZSet, ZRingValue},
+    circuit::{ExportId, ExportStream, OwnershipPreference},
+    operator::trace::{DelayedTraceId, IntegrateTraceId, TraceBounds, UntimedTraceAppend, Z1Trace},
+    trace::{Batch, BatchReader, Cursor, Spine},
+    Circuit, DBData, OrdIndexedZSet, RootCircuit, Stream,
+};
+use std::ops::{Neg, Sub};
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Emit a new value for a key only when it differs from the last emitted
+    /// value by at least `threshold`.
+    ///
+    /// Treats `self` as a stream of changes to a relation that maps each key
+    /// to its current value, e.g., the output of [`Stream::aggregate`], and
+    /// outputs a stream of changes to a relation that maps each key to its
+    /// _baseline_, computed by `value_func` from the value of the key at the
+    /// time of the last emit.  When the value of a key changes, the operator
+    /// compares it to the baseline and, if `|new - baseline| >= threshold`,
+    /// retracts the old baseline and inserts the new value, which becomes the
+    /// new baseline.  Smaller changes are suppressed, so a value that drifts
+    /// in small steps is only emitted once its cumulative change reaches the
+    /// threshold.
+    ///
+    /// A key that first appears in the input is emitted immediately.  A key
+    /// deleted from the input has its baseline retracted.  If the input
+    /// contains multiple values for a key, only one of them is considered.
+    ///
+    /// This is a stateful operator that internally maintains the trace of
+    /// the input collection and the current baseline of each key.
+    pub fn emit_on_change<F, T>(
+        &self,
+        value_func: F,
+        threshold: T,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, T, B::R>>
+    where
+        F: Fn(&B::Val) -> T + 'static,
+        T: DBData + Sub<Output = T>,
+    {
+        let circuit = self.circuit();
+        let sharded = self.shard();
+
+        // Current value of each key modified by the last input delta, or
+        // `None` if the key has been deleted.
+        let changes = sharded.apply2(
+            &sharded.integrate_trace(),
+            move |delta: &B, trace: &Spine<B>| {
+                let mut changes = Vec::with_capacity(delta.key_count());
+                let mut delta_cursor = delta.cursor();
+                let mut trace_cursor = trace.cursor();
+
+                while delta_cursor.key_valid() {
+                    let key = delta_cursor.key();
+                    let mut value = None;
+
+                    trace_cursor.seek_key(key);
+                    if trace_cursor.key_valid() && trace_cursor.key() == key {
+                        while trace_cursor.val_valid() {
+                            if trace_cursor.weight().gt0() {
+                                value = Some(value_func(trace_cursor.val()));
+                                break;
+                            }
+                            trace_cursor.step_val();
+                        }
+                    }
+
+                    changes.push((key.clone(), value));
+                    delta_cursor.step_key();
+                }
+
+                changes
+            },
+        );
+
+        // The current baselines are accumulated into a trace using an
+        // integrator (UntimedTraceAppend + Z1Trace).  Changes are evaluated
+        // against the delayed trace, which contains baselines as of the
+        // previous clock cycle.
+        circuit.region("emit_on_change", || {
+            let bounds = <TraceBounds<B::Key, T>>::unbounded();
+
+            let (ExportStream { local, export }, z1feedback) = circuit
+                .add_feedback_with_export(Z1Trace::new(true, circuit.root_scope(), bounds.clone()));
+            local.mark_sharded();
+
+            let output = local.apply2(
+                &changes,
+                move |baselines: &Spine<OrdIndexedZSet<B::Key, T, B::R>>,
+                      changes: &Vec<(B::Key, Option<T>)>| {
+                    let mut output = Vec::new();
+                    let mut cursor = baselines.cursor();
+
+                    for (key, value) in changes.iter() {
+                        let mut baseline = None;
+
+                        cursor.seek_key(key);
+                        if cursor.key_valid() && cursor.key() == key {
+                            while cursor.val_valid() {
+                                if cursor.weight().gt0() {
+                                    baseline = Some(cursor.val().clone());
+                                    break;
+                                }
+                                cursor.step_val();
+                            }
+                        }
+
+                        let emit = match (&baseline, value) {
+                            (None, None) => false,
+                            (Some(baseline), Some(value)) => {
+                                let diff = if value >= baseline {
+                                    value.clone() - baseline.clone()
+                                } else {
+                                    baseline.clone() - value.clone()
+                                };
+                                diff >= threshold
+                            }
+                            _ => true,
+                        };
+
+                        if emit {
+                            if let Some(baseline) = baseline {
+                                output.push(((key.clone(), baseline), B::R::one().neg()));
+                            }
+                            if let Some(value) = value {
+                                output.push(((key.clone(), value.clone()), B::R::one()));
+                            }
+                        }
+                    }
+
+                    OrdIndexedZSet::from_tuples((), output)
+                },
+            );
+            output.mark_sharded();
+
+            let trace = circuit.add_binary_operator_with_preference(
+                UntimedTraceAppend::<Spine<OrdIndexedZSet<B::Key, T, B::R>>>::new(),
+                (&local, OwnershipPreference::STRONGLY_PREFER_OWNED),
+                (&output, OwnershipPreference::PREFER_OWNED),
+            );
+            trace.mark_sharded();
+
+            z1feedback.connect_with_preference(&trace, OwnershipPreference::STRONGLY_PREFER_OWNED);
+
+            circuit.cache_insert(DelayedTraceId::new(trace.origin_node_id().clone()), local);
+            circuit.cache_insert(ExportId::new(trace.origin_node_id().clone()), export);
+            circuit.cache_insert(
+                IntegrateTraceId::new(output.origin_node_id().clone()),
+                (trace, bounds),
+            );
+
+            output
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, Runtime};
+
+    fn emit_on_change_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, output_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (input, input_handle) = circuit.add_input_indexed_zset::<u64, i64, isize>();
+                let output_handle = input.emit_on_change(|v| *v, 10).integrate().output();
+
+                (input_handle, output_handle)
+            })
+            .unwrap();
+
+        // New keys are emitted immediately.
+        input_handle.append(&mut vec![(1, (100, 1)), (2, (0, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            indexed_zset! { 1 => { 100 => 1 }, 2 => { 0 => 1 } }
+        );
+
+        // Key 1 drifts in small steps that don't add up to the threshold.
+        for (old, new) in [(100, 103), (103, 106), (106, 109), (109, 104)] {
+            input_handle.append(&mut vec![(1, (old, -1)), (1, (new, 1))]);
+            dbsp.step().unwrap();
+            assert_eq!(
+                output_handle.consolidate(),
+                indexed_zset! { 1 => { 100 => 1 }, 2 => { 0 => 1 } }
+            );
+        }
+
+        // Cumulative change reaches the threshold: one emit.
+        input_handle.append(&mut vec![(1, (104, -1)), (1, (90, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            indexed_zset! { 1 => { 90 => 1 }, 2 => { 0 => 1 } }
+        );
+
+        // The baseline is reset to the emitted value.
+        input_handle.append(&mut vec![(1, (90, -1)), (1, (95, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            indexed_zset! { 1 => { 90 => 1 }, 2 => { 0 => 1 } }
+        );
+
+        input_handle.append(&mut vec![(1, (95, -1)), (1, (80, 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            indexed_zset! { 1 => { 80 => 1 }, 2 => { 0 => 1 } }
+        );
+
+        // Deleted keys are retracted.
+        input_handle.append(&mut vec![(2, (0, -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            indexed_zset! { 1 => { 80 => 1 } }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn emit_on_change_test1() {
+        emit_on_change_test(1);
+    }
+
+    #[test]
+    fn emit_on_change_test4() {
+        emit_on_change_test(4);
+    }
+}
@@ -0,0 +1,164 @@
+//! Regular expression-based extraction of substrings from records.
+
+use crate::{
+    circuit::{Circuit, Stream},
+    operator::FilterMap,
+    DBData, DBWeight, OrdZSet,
+};
+use regex::{Error as RegexError, Regex};
+
+impl<C, K, R> Stream<C, OrdZSet<K, R>>
+where
+    C: Circuit,
+    K: DBData,
+    R: DBWeight,
+{
+    /// Incrementally extract substrings matching a regular expression from a
+    /// string field of each record.
+    ///
+    /// Applies `regex` to the string returned by `field` for each record in
+    /// the input stream and outputs a `(record, substring)` pair:
+    ///
+    /// * for each capture group of each match, if `regex` contains capture
+    ///   groups; groups that don't participate in a match are skipped.
+    /// * for each match, if `regex` doesn't contain capture groups.
+    ///
+    /// Each output pair inherits the weight of its source record, so
+    /// inserting or deleting a record inserts or deletes all substrings
+    /// extracted from it.  A substring that occurs multiple times in the
+    /// same record is output with a proportionally larger weight.
+    ///
+    /// The regular expression is compiled once when the operator is
+    /// constructed.  Returns an error if `regex` is not a valid regular
+    /// expression.
+    ///
+    /// # Example
+    ///
+    /// Extract all `@mentions` from messages:
+    ///
+    /// ```
+    /// # use dbsp::{OrdZSet, RootCircuit, Stream};
+    /// # fn test(messages: Stream<RootCircuit, OrdZSet<(u64, String), isize>>) {
+    /// let mentions: Stream<_, OrdZSet<((u64, String), String), _>> = messages
+    ///     .extract(r"@(\w+)", |(_id, text)| text.as_str())
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn extract<F>(
+        &self,
+        regex: &str,
+        field: F,
+    ) -> Result<Stream<C, OrdZSet<(K, String), R>>, RegexError>
+    where
+        F: Fn(&K) -> &str + 'static,
+    {
+        let regex = Regex::new(regex)?;
+        let has_groups = regex.captures_len() > 1;
+
+        Ok(self.flat_map(move |record: &K| {
+            let mut output = Vec::new();
+
+            for captures in regex.captures_iter(field(record)) {
+                if has_groups {
+                    // Group 0 is the entire match.
+                    for group in captures.iter().skip(1).flatten() {
+                        output.push((record.clone(), group.as_str().to_string()));
+                    }
+                } else {
+                    output.push((record.clone(), captures[0].to_string()));
+                }
+            }
+
+            output
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{zset, RootCircuit, Runtime};
+
+    fn extract_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, mentions, hashtags)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (messages, input_handle) = circuit.add_input_zset::<(u64, String), isize>();
+
+                let mentions = messages
+                    .extract(r"@(\w+)", |(_id, text)| text.as_str())
+                    .unwrap()
+                    .map(|((id, _text), mention)| (*id, mention.clone()))
+                    .integrate()
+                    .output();
+                let hashtags = messages
+                    .extract(r"#\w+", |(_id, text)| text.as_str())
+                    .unwrap()
+                    .map(|((id, _text), hashtag)| (*id, hashtag.clone()))
+                    .integrate()
+                    .output();
+
+                (input_handle, mentions, hashtags)
+            })
+            .unwrap();
+
+        input_handle.append(&mut vec![
+            ((1, "hi @alice and @bob #greeting".to_string()), 1),
+            ((2, "no mentions here".to_string()), 1),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            mentions.consolidate(),
+            zset! { (1, "alice".to_string()) => 1, (1, "bob".to_string()) => 1 }
+        );
+        assert_eq!(
+            hashtags.consolidate(),
+            zset! { (1, "#greeting".to_string()) => 1 }
+        );
+
+        // Adding a record adds its mentions; repeated mentions accumulate.
+        input_handle.append(&mut vec![((3, "@carol @carol @alice".to_string()), 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            mentions.consolidate(),
+            zset! {
+                (1, "alice".to_string()) => 1,
+                (1, "bob".to_string()) => 1,
+                (3, "alice".to_string()) => 1,
+                (3, "carol".to_string()) => 2,
+            }
+        );
+
+        // Removing a record removes its mentions.
+        input_handle.append(&mut vec![(
+            (1, "hi @alice and @bob #greeting".to_string()),
+            -1,
+        )]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            mentions.consolidate(),
+            zset! { (3, "alice".to_string()) => 1, (3, "carol".to_string()) => 2 }
+        );
+        assert_eq!(hashtags.consolidate(), zset! {});
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn extract_test1() {
+        extract_test(1);
+    }
+
+    #[test]
+    fn extract_test4() {
+        extract_test(4);
+    }
+
+    #[test]
+    fn extract_invalid_regex() {
+        RootCircuit::build(|circuit| {
+            let (messages, _input_handle) = circuit.add_input_zset::<String, isize>();
+            assert!(messages.extract(r"(", |text| text.as_str()).is_err());
+        })
+        .unwrap();
+    }
+}
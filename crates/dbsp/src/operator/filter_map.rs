@@ -2,8 +2,8 @@
 
 use crate::{
     circuit::{
-        operator_traits::{Operator, UnaryOperator},
-        Circuit, OwnershipPreference, Scope, Stream,
+        operator_traits::{Operator, SplitOperator, UnaryOperator},
+        CheckpointError, Circuit, OwnershipPreference, Scope, Stream,
     },
     trace::{Batch, BatchReader, Builder, Consumer, Cursor, ValueConsumer},
     DBData, DBWeight, OrdIndexedZSet, OrdZSet,
@@ -82,6 +82,20 @@ pub trait FilterMap<C> {
     where
         F: Fn(Self::ItemRef<'_>) -> bool + 'static;
 
+    /// Split input stream into records that satisfy the `filter_func`
+    /// predicate and records that don't.
+    ///
+    /// Returns a `(kept, rejected)` pair of streams, where `kept` is
+    /// equivalent to `self.filter(filter_func)` and `rejected` contains the
+    /// remaining records, e.g., to feed an audit log or a dead-letter queue.
+    /// This is more efficient than filtering the input twice with
+    /// complementary predicates, as the predicate is evaluated once per
+    /// record in a single pass over each input batch.
+    fn filter_split<F>(&self, filter_func: F) -> (Self, Self)
+    where
+        Self: Sized,
+        F: Fn(Self::ItemRef<'_>) -> bool + 'static;
+
     /// Applies `map_func` to each record in the input stream.  Assembles output
     /// record into `OrdZSet` batches.
     fn map<F, V>(&self, map_func: F) -> Stream<C, OrdZSet<V, Self::R>>
@@ -110,6 +124,42 @@ pub trait FilterMap<C> {
         self.map_index_generic(map_func)
     }
 
+    /// Like [`Self::map_index`], but lets the caller control the order of
+    /// values within each key.
+    ///
+    /// Values associated with a key are stored in the order defined by the
+    /// `Ord` implementation of the value type, which does not necessarily
+    /// match the order the application cares about, e.g., a timestamp
+    /// order.  This method pairs each value `v` produced by `map_func` with
+    /// the secondary ordering key `order_func(&v)`, so that values within
+    /// each key are sorted by `(order_func(&v), v)`.  This is a total order
+    /// that only depends on the contents of the collection and not on the
+    /// order in which records were inserted, with ties between equal
+    /// ordering keys broken by comparing values.
+    ///
+    /// Aggregates that depend on the order of values, e.g., a
+    /// [`Fold`](`crate::operator::Fold`) that keeps the first or last `N`
+    /// values of each group, therefore produce reproducible results ordered
+    /// by `order_func`.
+    #[allow(clippy::type_complexity)]
+    fn map_index_stable<F, G, K, V, O>(
+        &self,
+        map_func: F,
+        order_func: G,
+    ) -> Stream<C, OrdIndexedZSet<K, (O, V), Self::R>>
+    where
+        K: DBData,
+        V: DBData,
+        O: DBData,
+        F: Fn(Self::ItemRef<'_>) -> (K, V) + 'static,
+        G: Fn(&V) -> O + 'static,
+    {
+        self.map_index(move |item| {
+            let (key, val) = map_func(item);
+            (key, (order_func(&val), val))
+        })
+    }
+
     /// Like [`Self::map_index`], but can return any batch type.
     fn map_index_generic<F, K, V, O>(&self, map_func: F) -> Stream<C, O>
     where
@@ -179,6 +229,13 @@ where
         filtered
     }
 
+    fn filter_split<F>(&self, filter_func: F) -> (Self, Self)
+    where
+        F: Fn(Self::ItemRef<'_>) -> bool + 'static,
+    {
+        filter_split_inner(self, move |key, _| filter_func(key))
+    }
+
     fn map_generic<F, T, O>(&self, map_func: F) -> Stream<C, O>
     where
         F: Fn(Self::ItemRef<'_>) -> T + Clone + 'static,
@@ -250,6 +307,13 @@ where
         filtered
     }
 
+    fn filter_split<F>(&self, filter_func: F) -> (Self, Self)
+    where
+        F: Fn(Self::ItemRef<'_>) -> bool + 'static,
+    {
+        filter_split_inner(self, move |key, val| filter_func((key, val)))
+    }
+
     fn map_generic<F, T, O>(&self, map_func: F) -> Stream<C, O>
     where
         F: Fn(Self::ItemRef<'_>) -> T + Clone + 'static,
@@ -291,6 +355,171 @@ where
     }
 }
 
+impl<C, K, R> Stream<C, OrdZSet<K, R>>
+where
+    C: Circuit,
+    K: DBData,
+    R: DBWeight,
+{
+    /// Like [`FilterMap::map`], but lets `map_func` return the input record
+    /// unchanged without cloning it.
+    ///
+    /// `map_func` returns `Cow::Borrowed` for records it passes through
+    /// unmodified and `Cow::Owned` for modified ones.  When the operator
+    /// receives its input batch by value (i.e., it is the only consumer of
+    /// the input stream), unmodified records are moved to the output.
+    /// Otherwise they are cloned, as in [`FilterMap::map`].  This avoids
+    /// cloning records with large fields that most transforms leave intact.
+    pub fn map_cow<F>(&self, map_func: F) -> Self
+    where
+        F: for<'a> Fn(&'a K) -> Cow<'a, K> + 'static,
+    {
+        self.circuit()
+            .add_unary_operator(MapCow::new(map_func), self)
+    }
+
+    /// Like [`FilterMap::map`], but `map_func` takes records by value.
+    ///
+    /// The operator asks for ownership of its input batch.  When it gets it,
+    /// i.e., when it is the only consumer of the input stream, records are
+    /// moved into `map_func`.  Otherwise they are cloned first.  This avoids
+    /// cloning records when `map_func` reuses parts of them, e.g., large
+    /// strings, in its output.
+    pub fn map_owned<F, V>(&self, map_func: F) -> Stream<C, OrdZSet<V, R>>
+    where
+        V: DBData,
+        F: Fn(K) -> V + Clone + 'static,
+    {
+        let map_owned = map_func.clone();
+        self.circuit().add_unary_operator(
+            MapKeys::new(move |x: &K| map_func(x.clone()), map_owned),
+            self,
+        )
+    }
+
+    /// Like [`FilterMap::flat_map`], but `func` takes records by value.
+    ///
+    /// See [`Self::map_owned`] for when records are moved rather than cloned.
+    pub fn flat_map_owned<F, I>(&self, func: F) -> Stream<C, OrdZSet<I::Item, R>>
+    where
+        F: FnMut(K) -> I + 'static,
+        I: IntoIterator + 'static,
+        I::Item: DBData,
+    {
+        self.circuit()
+            .add_unary_operator(FlatMapKeys::new(func), self)
+    }
+}
+
+/// Shared implementation of `filter_split` for indexed and non-indexed
+/// batches.
+fn filter_split_inner<C, B, F>(
+    stream: &Stream<C, B>,
+    filter_func: F,
+) -> (Stream<C, B>, Stream<C, B>)
+where
+    C: Circuit,
+    B: Batch<Time = ()>,
+    F: Fn(&B::Key, &B::Val) -> bool + 'static,
+{
+    let (kept, rejected) = stream
+        .circuit()
+        .add_split_operator(FilterSplit::new(filter_func), &stream.try_sharded_version());
+    kept.mark_sharded_if(stream);
+    rejected.mark_sharded_if(stream);
+
+    (kept, rejected)
+}
+
+/// Internal implementation of `filter_split`.
+///
+/// Partitions each input batch into records that satisfy the predicate,
+/// emitted to the first output, and the remaining records, emitted to the
+/// second output.
+struct FilterSplit<F> {
+    filter: F,
+}
+
+impl<F> FilterSplit<F> {
+    fn new(filter: F) -> Self {
+        Self { filter }
+    }
+}
+
+impl<F> Operator for FilterSplit<F>
+where
+    F: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("FilterSplit")
+    }
+
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        true
+    }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
+}
+
+impl<B, F> SplitOperator<B, B, B> for FilterSplit<F>
+where
+    B: Batch<Time = ()>,
+    F: Fn(&B::Key, &B::Val) -> bool + 'static,
+{
+    fn eval(&mut self, input: &B) -> (B, B) {
+        let mut kept = B::Builder::with_capacity((), input.len());
+        let mut rejected = B::Builder::with_capacity((), input.len());
+
+        let mut cursor = input.cursor();
+        while cursor.key_valid() {
+            while cursor.val_valid() {
+                let builder = if (self.filter)(cursor.key(), cursor.val()) {
+                    &mut kept
+                } else {
+                    &mut rejected
+                };
+                builder.push((
+                    B::item_from(cursor.key().clone(), cursor.val().clone()),
+                    cursor.weight(),
+                ));
+                cursor.step_val();
+            }
+            cursor.step_key();
+        }
+
+        (kept.done(), rejected.done())
+    }
+
+    fn eval_owned(&mut self, input: B) -> (B, B) {
+        let mut kept = B::Builder::with_capacity((), input.len());
+        let mut rejected = B::Builder::with_capacity((), input.len());
+
+        let mut consumer = input.consumer();
+        while consumer.key_valid() {
+            let (key, mut values) = consumer.next_key();
+
+            while values.value_valid() {
+                let (value, weight, ()) = values.next_value();
+
+                let builder = if (self.filter)(&key, &value) {
+                    &mut kept
+                } else {
+                    &mut rejected
+                };
+                builder.push((B::item_from(key.clone(), value), weight));
+            }
+        }
+
+        (kept.done(), rejected.done())
+    }
+
+    fn input_preference(&self) -> OwnershipPreference {
+        OwnershipPreference::WEAKLY_PREFER_OWNED
+    }
+}
+
 /// Internal implementation for filtering [`BatchReader`]s
 pub struct FilterKeys<CI, CO, F> {
     filter: F,
@@ -351,6 +580,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<CI, CO, F> UnaryOperator<CI, CO> for FilterKeys<CI, CO, F>
@@ -461,6 +694,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<CI, CO, F> UnaryOperator<CI, CO> for FilterVals<CI, CO, F>
@@ -552,6 +789,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<CI, CO, F> UnaryOperator<CI, CO> for Map<CI, CO, F>
@@ -575,6 +816,30 @@ where
 
         CO::from_tuples((), batch)
     }
+
+    fn eval_owned(&mut self, i: CI) -> CO {
+        let mut batch = Vec::with_capacity(i.len());
+
+        // `map` only borrows its arguments, but consuming the input moves
+        // weights instead of cloning them and releases input records as soon
+        // as they've been mapped.
+        let mut consumer = i.consumer();
+        while consumer.key_valid() {
+            let (key, mut values) = consumer.next_key();
+
+            while values.value_valid() {
+                let (value, weight, ()) = values.next_value();
+                let (k, v) = (self.map)((&key, &value));
+                batch.push((CO::item_from(k, v), weight));
+            }
+        }
+
+        CO::from_tuples((), batch)
+    }
+
+    fn input_preference(&self) -> OwnershipPreference {
+        OwnershipPreference::WEAKLY_PREFER_OWNED
+    }
 }
 
 /// Internal implementation of `OrdZSet::map`.
@@ -608,6 +873,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<CI, CO, FB, FO> UnaryOperator<CI, CO> for MapKeys<CI, CO, FB, FO>
@@ -641,13 +910,20 @@ where
         let mut consumer = input.consumer();
         while consumer.key_valid() {
             let (key, mut values) = consumer.next_key();
+            let mut key = Some((self.map_owned)(key));
 
-            // FIXME: We assume that this operator will only be used with `OrdZSet` and that
-            // it has either zero or one values, meaning that it'll skip any additional
-            // values
-            if values.value_valid() {
+            while values.value_valid() {
                 let (value, weight, ()) = values.next_value();
-                batch.push((CO::item_from((self.map_owned)(key), value), weight));
+
+                // Move the mapped key into the last tuple and clone it for the
+                // others.  Z-sets have exactly one value per key, so their keys
+                // are never cloned.
+                let key = if values.value_valid() {
+                    key.clone()
+                } else {
+                    key.take()
+                };
+                batch.push((CO::item_from(key.unwrap(), value), weight));
             }
         }
 
@@ -659,6 +935,85 @@ where
     }
 }
 
+/// Internal implementation of `OrdZSet::map_cow`.
+pub struct MapCow<B, F> {
+    map_func: F,
+    _type: PhantomData<B>,
+}
+
+impl<B, F> MapCow<B, F> {
+    pub fn new(map_func: F) -> Self {
+        Self {
+            map_func,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<B, F> Operator for MapCow<B, F>
+where
+    B: 'static,
+    F: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("MapCow")
+    }
+
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        true
+    }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
+}
+
+impl<B, F> UnaryOperator<B, B> for MapCow<B, F>
+where
+    B: Batch<Val = (), Time = ()>,
+    F: for<'a> Fn(&'a B::Key) -> Cow<'a, B::Key> + 'static,
+{
+    fn eval(&mut self, input: &B) -> B {
+        let mut batch = Vec::with_capacity(input.len());
+
+        let mut cursor = input.cursor();
+        while cursor.key_valid() {
+            let w = cursor.weight();
+            let k = (self.map_func)(cursor.key()).into_owned();
+            batch.push((B::item_from(k, ()), w));
+            cursor.step_key();
+        }
+
+        B::from_tuples((), batch)
+    }
+
+    fn eval_owned(&mut self, input: B) -> B {
+        let mut batch = Vec::with_capacity(input.len());
+
+        let mut consumer = input.consumer();
+        while consumer.key_valid() {
+            let (key, mut values) = consumer.next_key();
+
+            if values.value_valid() {
+                let ((), weight, ()) = values.next_value();
+
+                // Reuse the input record unless `map_func` modified it.
+                let mapped = match (self.map_func)(&key) {
+                    Cow::Borrowed(_) => None,
+                    Cow::Owned(mapped) => Some(mapped),
+                };
+                batch.push((B::item_from(mapped.unwrap_or(key), ()), weight));
+            }
+        }
+
+        B::from_tuples((), batch)
+    }
+
+    fn input_preference(&self) -> OwnershipPreference {
+        OwnershipPreference::PREFER_OWNED
+    }
+}
+
 /// Internal implementation of `flat_map` methods.
 pub struct FlatMap<CI, CO, F, I> {
     map_func: F,
@@ -688,6 +1043,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<CI, CO, F, I> UnaryOperator<CI, CO> for FlatMap<CI, CO, F, I>
@@ -724,15 +1083,107 @@ where
     }
 }
 
+/// Internal implementation of `OrdZSet::flat_map_owned`.
+pub struct FlatMapKeys<CI, CO, F, I> {
+    map_func: F,
+    _type: PhantomData<(CI, CO, I)>,
+}
+
+impl<CI, CO, F, I> FlatMapKeys<CI, CO, F, I> {
+    pub fn new(map_func: F) -> Self {
+        Self {
+            map_func,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<CI, CO, F, I> Operator for FlatMapKeys<CI, CO, F, I>
+where
+    CI: 'static,
+    CO: 'static,
+    F: 'static,
+    I: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("FlatMapKeys")
+    }
+
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        true
+    }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
+}
+
+impl<CI, CO, F, I> UnaryOperator<CI, CO> for FlatMapKeys<CI, CO, F, I>
+where
+    CI: BatchReader<Val = (), Time = ()>,
+    CO: Batch<Val = (), Time = (), R = CI::R>,
+    F: FnMut(CI::Key) -> I + 'static,
+    I: IntoIterator<Item = CO::Key> + 'static,
+{
+    fn eval(&mut self, input: &CI) -> CO {
+        let mut batch = Vec::with_capacity(input.len());
+
+        let mut cursor = input.cursor();
+        while cursor.key_valid() {
+            let weight = cursor.weight();
+            for x in (self.map_func)(cursor.key().clone()) {
+                batch.push((CO::item_from(x, ()), weight.clone()));
+            }
+            cursor.step_key();
+        }
+
+        CO::from_tuples((), batch)
+    }
+
+    fn eval_owned(&mut self, input: CI) -> CO {
+        let mut batch = Vec::with_capacity(input.len());
+
+        let mut consumer = input.consumer();
+        while consumer.key_valid() {
+            let (key, mut values) = consumer.next_key();
+
+            if values.value_valid() {
+                let ((), weight, ()) = values.next_value();
+                for x in (self.map_func)(key) {
+                    batch.push((CO::item_from(x, ()), weight.clone()));
+                }
+            }
+        }
+
+        CO::from_tuples((), batch)
+    }
+
+    fn input_preference(&self) -> OwnershipPreference {
+        OwnershipPreference::PREFER_OWNED
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use super::{Map, MapKeys};
     use crate::{
+        algebra::UnimplementedSemigroup,
+        circuit::operator_traits::UnaryOperator,
         indexed_zset,
-        operator::{FilterMap, Generator},
-        trace::ord::OrdZSet,
-        zset, Circuit, RootCircuit,
+        operator::{FilterMap, Fold, Generator},
+        trace::{ord::OrdZSet, BatchReader},
+        zset, Circuit, OrdIndexedZSet, RootCircuit, Runtime, Stream,
+    };
+    use bincode::{Decode, Encode};
+    use size_of::SizeOf;
+    use std::{
+        borrow::Cow,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        vec,
     };
-    use std::vec;
 
     #[test]
     fn filter_map_test() {
@@ -887,4 +1338,343 @@ mod test {
             circuit.step().unwrap();
         }
     }
+
+    fn filter_split_test(workers: usize) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+
+        let (mut dbsp, (mut input, mut i_input, outputs, i_outputs)) =
+            Runtime::init_circuit(workers, move |circuit| {
+                let (input, input_handle) = circuit.add_input_zset::<isize, isize>();
+                let (i_input, i_input_handle) =
+                    circuit.add_input_indexed_zset::<isize, String, isize>();
+
+                // `filter_split` adds a single operator with two distinct
+                // output streams.
+                let num_nodes = circuit.num_nodes();
+                let (kept, rejected) = input.filter_split(move |n| {
+                    calls_clone.fetch_add(1, Ordering::Relaxed);
+                    *n > 0
+                });
+                assert_eq!(circuit.num_nodes(), num_nodes + 1);
+                assert!(!kept.ptr_eq(&rejected));
+                assert!(!kept.integrate().ptr_eq(&rejected.integrate()));
+                let outputs = (
+                    kept.output(),
+                    rejected.output(),
+                    input.filter(|n| *n > 0).output(),
+                    input.filter(|n| *n <= 0).output(),
+                );
+
+                let pred = |(n, s): (&isize, &String)| *n > 0 && s.contains("foo");
+                let (i_kept, i_rejected) = i_input.filter_split(pred);
+                let i_outputs = (
+                    i_kept.output(),
+                    i_rejected.output(),
+                    i_input.filter(pred).output(),
+                    i_input.filter(move |kv| !pred(kv)).output(),
+                );
+
+                (input_handle, i_input_handle, outputs, i_outputs)
+            })
+            .unwrap();
+
+        let mut num_records = 0;
+
+        for step in 0..5isize {
+            let mut batch: Vec<_> = (-10..10).map(|n| (n * (step + 1), 1)).collect();
+            num_records += batch.len();
+            input.append(&mut batch);
+
+            let mut i_batch: Vec<_> = (-10..10)
+                .map(|n| {
+                    let s = if n % 3 == 0 { "foo" } else { "bar" };
+                    (n, (format!("{s}{step}"), 1))
+                })
+                .collect();
+            i_input.append(&mut i_batch);
+
+            dbsp.step().unwrap();
+
+            let (kept, rejected, filtered, filtered_neg) = &outputs;
+            let kept = kept.consolidate();
+            let rejected = rejected.consolidate();
+            assert_eq!(kept, filtered.consolidate());
+            assert_eq!(rejected, filtered_neg.consolidate());
+            assert_eq!(kept.len() + rejected.len(), 20);
+
+            let (i_kept, i_rejected, i_filtered, i_filtered_neg) = &i_outputs;
+            let i_kept = i_kept.consolidate();
+            let i_rejected = i_rejected.consolidate();
+            assert_eq!(i_kept, i_filtered.consolidate());
+            assert_eq!(i_rejected, i_filtered_neg.consolidate());
+            assert_eq!(i_kept.len() + i_rejected.len(), 20);
+        }
+
+        // The predicate is evaluated exactly once per input record.
+        assert_eq!(calls.load(Ordering::Relaxed), num_records);
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn filter_split_test1() {
+        filter_split_test(1);
+    }
+
+    #[test]
+    fn filter_split_test4() {
+        filter_split_test(4);
+    }
+
+    fn map_index_stable_test(workers: usize) {
+        type Bid = (u64, u64, u64); // (auction, date_time, price)
+
+        let (mut dbsp, (mut input1, mut input2, output1, output2)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (bids1, input1) = circuit.add_input_zset::<Bid, isize>();
+                let (bids2, input2) = circuit.add_input_zset::<Bid, isize>();
+
+                // Collect prices of each auction in the order of bid timestamps.
+                let prices = |bids: Stream<RootCircuit, OrdZSet<Bid, isize>>| {
+                    bids.map_index_stable(
+                        |(auction, date_time, price)| (*auction, (*price, *date_time)),
+                        |(_price, date_time)| *date_time,
+                    )
+                    .aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::new(
+                        Vec::new(),
+                        |prices: &mut Vec<u64>, (_, (price, _)): &(u64, (u64, u64)), _| {
+                            prices.push(*price)
+                        },
+                    ))
+                    .integrate()
+                    .output()
+                };
+
+                (input1, input2, prices(bids1), prices(bids2))
+            })
+            .unwrap();
+
+        // The same bids, including bids with identical timestamps, inserted in
+        // different orders and split across steps differently.
+        let bids: Vec<(Bid, isize)> = vec![
+            ((1, 30, 5), 1),
+            ((1, 10, 20), 1),
+            ((1, 20, 7), 1),
+            ((1, 20, 3), 1),
+            ((2, 5, 1), 1),
+            ((2, 5, 100), 1),
+            ((2, 1, 50), 1),
+        ];
+
+        input1.append(&mut bids[..3].to_vec());
+        input2.append(&mut bids.iter().rev().cloned().collect());
+        dbsp.step().unwrap();
+        output1.consolidate();
+        output2.consolidate();
+
+        input1.append(&mut bids[3..].iter().rev().cloned().collect());
+        dbsp.step().unwrap();
+
+        let expected = indexed_zset! {
+            1 => { vec![20, 3, 7, 5] => 1 },
+            2 => { vec![50, 1, 100] => 1 }
+        };
+        assert_eq!(output1.consolidate(), expected);
+        assert_eq!(output2.consolidate(), expected);
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn map_index_stable_test1() {
+        map_index_stable_test(1);
+    }
+
+    #[test]
+    fn map_index_stable_test4() {
+        map_index_stable_test(4);
+    }
+
+    static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts its clones in `CLONES`.
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, SizeOf, Encode, Decode)]
+    struct Counted(usize);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            CLONES.fetch_add(1, Ordering::Relaxed);
+            Self(self.0)
+        }
+    }
+
+    /// Runs `map` over a batch of 100 records and returns its output along
+    /// with the number of records cloned during the step.
+    fn count_clones<F>(map: F) -> (OrdZSet<Counted, isize>, usize)
+    where
+        F: FnOnce(
+            &Stream<RootCircuit, OrdZSet<Counted, isize>>,
+        ) -> Stream<RootCircuit, OrdZSet<Counted, isize>>,
+    {
+        let (circuit, output) = RootCircuit::build(move |circuit| {
+            let input = circuit.add_source(Generator::new(|| {
+                OrdZSet::from_keys((), (0..100).map(|i| (Counted(i), 1)).collect())
+            }));
+            map(&input).output()
+        })
+        .unwrap();
+
+        let before = CLONES.load(Ordering::Relaxed);
+        circuit.step().unwrap();
+        let clones = CLONES.load(Ordering::Relaxed) - before;
+
+        (output.consolidate(), clones)
+    }
+
+    #[test]
+    fn map_cow_test() {
+        let expected: OrdZSet<Counted, isize> = OrdZSet::from_keys(
+            (),
+            (0..100)
+                .map(|i| (Counted(if i % 2 == 0 { i } else { i + 1000 }), 1))
+                .collect(),
+        );
+
+        let (map_output, map_clones) = count_clones(|input| {
+            input.map(|x: &Counted| {
+                if x.0 % 2 == 0 {
+                    x.clone()
+                } else {
+                    Counted(x.0 + 1000)
+                }
+            })
+        });
+
+        let (cow_output, cow_clones) = count_clones(|input| {
+            input.map_cow(|x| {
+                if x.0 % 2 == 0 {
+                    Cow::Borrowed(x)
+                } else {
+                    Cow::Owned(Counted(x.0 + 1000))
+                }
+            })
+        });
+
+        assert_eq!(map_output, expected);
+        assert_eq!(cow_output, expected);
+
+        // `map` clones every unmodified record, while `map_cow` moves them
+        // out of its input batch.
+        assert!(cow_clones + 50 <= map_clones);
+    }
+
+    // `map_cow` produces the same result when it has to borrow its input.
+    #[test]
+    fn map_cow_shared_input_test() {
+        let (circuit, output) = RootCircuit::build(move |circuit| {
+            let input = circuit.add_source(Generator::new(|| zset! { 1 => 1, 2 => 1, 3 => -1 }));
+            let output = input
+                .map_cow(|x: &i64| {
+                    if x % 2 == 0 {
+                        Cow::Borrowed(x)
+                    } else {
+                        Cow::Owned(-x)
+                    }
+                })
+                .output();
+            // A second consumer of `input` forces `map_cow` to borrow it.
+            let _ = input.output();
+            output
+        })
+        .unwrap();
+
+        circuit.step().unwrap();
+        assert_eq!(output.consolidate(), zset! { -1 => 1, 2 => 1, -3 => -1 });
+    }
+
+    // The owned and borrowed paths of `Map` and `MapKeys` produce identical
+    // batches, including for inputs with multiple values per key.
+    #[test]
+    fn map_owned_matches_borrowed() {
+        let input: OrdIndexedZSet<String, String, isize> = indexed_zset! {
+            "alice".to_string() => {"a".to_string() => 1, "b".to_string() => -1},
+            "bob".to_string() => {"c".to_string() => 2},
+            "carol".to_string() => {"a".to_string() => 1, "d".to_string() => 1, "e".to_string() => 3},
+        };
+
+        let mut map = Map::new(|(k, v): (&String, &String)| (v.clone(), k.len()));
+        let borrowed: OrdIndexedZSet<String, usize, isize> = map.eval(&input);
+        let owned: OrdIndexedZSet<String, usize, isize> = map.eval_owned(input.clone());
+        assert_eq!(borrowed, owned);
+
+        let mut map_keys =
+            MapKeys::new(|k: &String| k.to_uppercase(), |k: String| k.to_uppercase());
+        let borrowed: OrdIndexedZSet<String, String, isize> = map_keys.eval(&input);
+        let owned: OrdIndexedZSet<String, String, isize> = map_keys.eval_owned(input);
+        assert_eq!(borrowed, owned);
+        assert_eq!(borrowed.len(), 6);
+
+        let input: OrdZSet<String, isize> = zset! { "x".to_string() => 1, "yy".to_string() => -2 };
+        let mut map_keys = MapKeys::new(|k: &String| k.len(), |k: String| k.len());
+        let borrowed: OrdZSet<usize, isize> = map_keys.eval(&input);
+        let owned: OrdZSet<usize, isize> = map_keys.eval_owned(input);
+        assert_eq!(borrowed, owned);
+        assert_eq!(owned, zset! { 1 => 1, 2 => -2 });
+    }
+
+    #[test]
+    fn map_owned_test() {
+        let expected: OrdZSet<Counted, isize> =
+            OrdZSet::from_keys((), (0..100).map(|i| (Counted(i + 1), 1)).collect());
+
+        let (map_output, map_clones) = count_clones(|input| {
+            input.map(|x: &Counted| {
+                let mut x = x.clone();
+                x.0 += 1;
+                x
+            })
+        });
+        let (owned_output, owned_clones) = count_clones(|input| {
+            input.map_owned(|mut x: Counted| {
+                x.0 += 1;
+                x
+            })
+        });
+        let (flat_output, flat_clones) = count_clones(|input| {
+            input.flat_map_owned(|mut x: Counted| {
+                x.0 += 1;
+                Some(x)
+            })
+        });
+
+        assert_eq!(map_output, expected);
+        assert_eq!(owned_output, expected);
+        assert_eq!(flat_output, expected);
+
+        // `map` has to clone every record it modifies, while the owned
+        // variants receive their input batch by value and move records out
+        // of it.
+        assert!(owned_clones + 100 <= map_clones);
+        assert!(flat_clones + 100 <= map_clones);
+    }
+
+    // The owned variants clone records when they have to borrow their input.
+    #[test]
+    fn map_owned_shared_input_test() {
+        let (circuit, (mapped, flat_mapped)) = RootCircuit::build(move |circuit| {
+            let input = circuit.add_source(Generator::new(|| zset! { 1 => 1, 2 => 1, 3 => -1 }));
+            let mapped = input.map_owned(|x: i64| -x).output();
+            let flat_mapped = input.flat_map_owned(|x: i64| [x, x * 10]).output();
+            (mapped, flat_mapped)
+        })
+        .unwrap();
+
+        circuit.step().unwrap();
+        assert_eq!(mapped.consolidate(), zset! { -1 => 1, -2 => 1, -3 => -1 });
+        assert_eq!(
+            flat_mapped.consolidate(),
+            zset! { 1 => 1, 10 => 1, 2 => 1, 20 => 1, 3 => -1, 30 => -1 }
+        );
+    }
 }
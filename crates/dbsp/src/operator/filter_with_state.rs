@@ -0,0 +1,197 @@
+//! Filter operator with access to the integrated contents of its input.
+
+use crate::{
+    algebra::{HasZero, IndexedZSet},
+    trace::{Batch, BatchReader, Cursor, Spine},
+    RootCircuit, Stream,
+};
+
+impl<Z> Stream<RootCircuit, Z>
+where
+    Z: IndexedZSet + Send,
+{
+    /// Stateful filter: retains records in the input stream that satisfy
+    /// `filter_func`, which can inspect the contents of the input collection
+    /// accumulated so far.
+    ///
+    /// For each `(key, val)` record in the current delta, `filter_func` is
+    /// called with the record and the state of `key` in the integral of all
+    /// previous deltas, excluding the current one.  The state is `None` if
+    /// the key is not present in the integral, including when all of its
+    /// values have zero net weight, e.g., because the key was inserted and
+    /// later deleted.  Otherwise, it is a cursor positioned at the first
+    /// value of `key`.  Values with zero net weight are not filtered out, so
+    /// the predicate should skip values whose
+    /// [`weight`](`crate::trace::Cursor::weight`) is zero.  The predicate can
+    /// move the cursor between the values of `key`, but must not move it to
+    /// a different key.
+    ///
+    /// This enables filters that a stateless predicate cannot express, e.g.,
+    /// emitting a record only the first time its key appears:
+    ///
+    /// ```text
+    /// stream.filter_with_state(|_key, _val, state| state.is_none())
+    /// ```
+    ///
+    /// Unlike [`FilterMap::filter`](`crate::operator::FilterMap::filter`),
+    /// the output of this operator is generally not a linear function of the
+    /// input, so it must be used with care when the input contains
+    /// retractions.
+    pub fn filter_with_state<F>(&self, filter_func: F) -> Self
+    where
+        F: for<'a> Fn(&Z::Key, &Z::Val, Option<&mut <Spine<Z> as BatchReader>::Cursor<'a>>) -> bool
+            + 'static,
+    {
+        let delta = self.shard();
+
+        delta
+            .apply2(
+                &delta.integrate_trace().delay_trace(),
+                move |delta: &Z, delayed_trace: &Spine<Z>| {
+                    let mut output = Vec::new();
+                    let mut delta_cursor = delta.cursor();
+                    let mut trace_cursor = delayed_trace.cursor();
+
+                    while delta_cursor.key_valid() {
+                        // Keys are visited in order, so the trace cursor only
+                        // needs to move forward.
+                        trace_cursor.seek_key(delta_cursor.key());
+                        let present = trace_cursor.key_valid()
+                            && trace_cursor.key() == delta_cursor.key()
+                            && has_nonzero_weight(&mut trace_cursor);
+
+                        while delta_cursor.val_valid() {
+                            let state = if present {
+                                trace_cursor.rewind_vals();
+                                Some(&mut trace_cursor)
+                            } else {
+                                None
+                            };
+
+                            if filter_func(delta_cursor.key(), delta_cursor.val(), state) {
+                                output.push((
+                                    Z::item_from(
+                                        delta_cursor.key().clone(),
+                                        delta_cursor.val().clone(),
+                                    ),
+                                    delta_cursor.weight(),
+                                ));
+                            }
+                            delta_cursor.step_val();
+                        }
+                        delta_cursor.step_key();
+                    }
+
+                    Z::from_tuples((), output)
+                },
+            )
+            .mark_sharded()
+    }
+}
+
+/// Returns `true` if the current key of `cursor` has at least one value
+/// with non-zero net weight.
+fn has_nonzero_weight<K, V, R, C>(cursor: &mut C) -> bool
+where
+    R: HasZero,
+    C: Cursor<K, V, (), R>,
+{
+    while cursor.val_valid() {
+        if !cursor.weight().is_zero() {
+            return true;
+        }
+        cursor.step_val();
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{indexed_zset, Runtime};
+
+    fn filter_with_state_test(workers: usize) {
+        let (mut dbsp, (mut input, output)) = Runtime::init_circuit(workers, |circuit| {
+            let (stream, handle) = circuit.add_input_indexed_zset::<u64, String, isize>();
+
+            // Emit only the first occurrence of each key.
+            let first = stream.filter_with_state(|_key, _val, state| state.is_none());
+
+            (handle, first.integrate().output())
+        })
+        .unwrap();
+
+        input.append(&mut vec![
+            (1, ("a".to_string(), 1)),
+            (2, ("b".to_string(), 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { "a".to_string() => 1 }, 2 => { "b".to_string() => 1 } }
+        );
+
+        // Key 1 has been seen before and is filtered out.
+        input.append(&mut vec![
+            (1, ("c".to_string(), 1)),
+            (3, ("d".to_string(), 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { "a".to_string() => 1 },
+                2 => { "b".to_string() => 1 },
+                3 => { "d".to_string() => 1 }
+            }
+        );
+
+        // Inserting the same record again is also filtered out.
+        input.append(&mut vec![(2, ("b".to_string(), 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { "a".to_string() => 1 },
+                2 => { "b".to_string() => 1 },
+                3 => { "d".to_string() => 1 }
+            }
+        );
+
+        // Deleting a key that has been seen before is filtered out.
+        input.append(&mut vec![(3, ("d".to_string(), -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { "a".to_string() => 1 },
+                2 => { "b".to_string() => 1 },
+                3 => { "d".to_string() => 1 }
+            }
+        );
+
+        // Key 3 now has zero net weight in the integral, so it is treated as
+        // a new key.
+        input.append(&mut vec![(3, ("e".to_string(), 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { "a".to_string() => 1 },
+                2 => { "b".to_string() => 1 },
+                3 => { "d".to_string() => 1, "e".to_string() => 1 }
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn filter_with_state_test1() {
+        filter_with_state_test(1);
+    }
+
+    #[test]
+    fn filter_with_state_test4() {
+        filter_with_state_test(4);
+    }
+}
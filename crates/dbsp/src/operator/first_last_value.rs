@@ -0,0 +1,343 @@
+//! Incremental `FIRST_VALUE` and `LAST_VALUE` window functions.
+
+use crate::{
+    algebra::{HasZero, ZRingValue, ZSet},
+    trace::{consolidation::consolidate, Batch, BatchReader, Cursor, Spine},
+    DBData, OrdIndexedZSet, OrdZSet, RootCircuit, Stream,
+};
+use std::{cmp::Ordering, ops::Neg};
+
+impl<Z> Stream<RootCircuit, Z>
+where
+    Z: ZSet + Send,
+    Z::R: ZRingValue,
+{
+    /// Incrementally compute the `FIRST_VALUE` window function.
+    ///
+    /// Splits the input collection into partitions using `key_func` and, for
+    /// each row in each partition, outputs the value computed by
+    /// `value_func` for the first row in the partition, i.e., the row with
+    /// the smallest timestamp computed by `ts_func`.  Ties between rows with
+    /// the same timestamp are broken by comparing the rows themselves.  Rows
+    /// with non-positive weights are ignored.
+    ///
+    /// Outputs a collection of `(row, first_value)` pairs.
+    ///
+    /// # Performance
+    ///
+    /// When the first row of a partition changes, e.g., a row with a new
+    /// smallest timestamp is inserted, the operator retracts and re-inserts
+    /// output records for every row in the partition.  Such changes are
+    /// `O(n)`, where `n` is the size of the partition, both in terms of work
+    /// and the size of the output.  Other changes only affect the output
+    /// records for the modified rows, but still require scanning the
+    /// partition.
+    #[allow(clippy::type_complexity)]
+    pub fn first_value<PK, TS, V, KF, TF, VF>(
+        &self,
+        key_func: KF,
+        ts_func: TF,
+        value_func: VF,
+    ) -> Stream<RootCircuit, OrdZSet<(Z::Key, V), Z::R>>
+    where
+        PK: DBData,
+        TS: Ord,
+        V: DBData,
+        KF: Fn(&Z::Key) -> PK + Clone + 'static,
+        TF: Fn(&Z::Key) -> TS + 'static,
+        VF: Fn(&Z::Key) -> V + 'static,
+    {
+        self.window_value(key_func, ts_func, value_func, Ordering::Less)
+    }
+
+    /// Incrementally compute the `LAST_VALUE` window function.
+    ///
+    /// Like [`first_value`](`Self::first_value`), but outputs the value of
+    /// the row with the largest timestamp in the partition.
+    #[allow(clippy::type_complexity)]
+    pub fn last_value<PK, TS, V, KF, TF, VF>(
+        &self,
+        key_func: KF,
+        ts_func: TF,
+        value_func: VF,
+    ) -> Stream<RootCircuit, OrdZSet<(Z::Key, V), Z::R>>
+    where
+        PK: DBData,
+        TS: Ord,
+        V: DBData,
+        KF: Fn(&Z::Key) -> PK + Clone + 'static,
+        TF: Fn(&Z::Key) -> TS + 'static,
+        VF: Fn(&Z::Key) -> V + 'static,
+    {
+        self.window_value(key_func, ts_func, value_func, Ordering::Greater)
+    }
+
+    /// Shared implementation of `first_value` and `last_value`.
+    ///
+    /// Selects the row that compares as `preferred` to all other rows in the
+    /// partition.
+    #[allow(clippy::type_complexity)]
+    fn window_value<PK, TS, V, KF, TF, VF>(
+        &self,
+        key_func: KF,
+        ts_func: TF,
+        value_func: VF,
+        preferred: Ordering,
+    ) -> Stream<RootCircuit, OrdZSet<(Z::Key, V), Z::R>>
+    where
+        PK: DBData,
+        TS: Ord,
+        V: DBData,
+        KF: Fn(&Z::Key) -> PK + Clone + 'static,
+        TF: Fn(&Z::Key) -> TS + 'static,
+        VF: Fn(&Z::Key) -> V + 'static,
+    {
+        let partitioned = self
+            .index_with(move |row| (key_func(row), row.clone()))
+            .shard();
+
+        // Value of the selected row in a partition.
+        let select = move |rows: &[(Z::Key, Z::R)]| {
+            rows.iter()
+                .filter(|(_, weight)| weight.gt0())
+                .map(|(row, _)| row)
+                .reduce(|selected, row| {
+                    if (ts_func(row), row).cmp(&(ts_func(selected), selected)) == preferred {
+                        row
+                    } else {
+                        selected
+                    }
+                })
+                .map(&value_func)
+        };
+
+        partitioned.apply2(
+            &partitioned.integrate_trace().delay_trace(),
+            move |delta: &OrdIndexedZSet<PK, Z::Key, Z::R>,
+                  delayed_trace: &Spine<OrdIndexedZSet<PK, Z::Key, Z::R>>| {
+                let mut output = Vec::new();
+                let mut delta_cursor = delta.cursor();
+                let mut trace_cursor = delayed_trace.cursor();
+
+                while delta_cursor.key_valid() {
+                    let mut old_rows = Vec::new();
+
+                    trace_cursor.seek_key(delta_cursor.key());
+                    if trace_cursor.key_valid() && trace_cursor.key() == delta_cursor.key() {
+                        while trace_cursor.val_valid() {
+                            old_rows.push((trace_cursor.val().clone(), trace_cursor.weight()));
+                            trace_cursor.step_val();
+                        }
+                    }
+                    consolidate(&mut old_rows);
+
+                    let mut delta_rows = Vec::new();
+                    while delta_cursor.val_valid() {
+                        delta_rows.push((delta_cursor.val().clone(), delta_cursor.weight()));
+                        delta_cursor.step_val();
+                    }
+
+                    let mut new_rows = old_rows.clone();
+                    new_rows.extend(delta_rows.iter().cloned());
+                    consolidate(&mut new_rows);
+
+                    let old_value = select(&old_rows);
+                    let new_value = select(&new_rows);
+
+                    if old_value == new_value {
+                        // The selected value is unchanged: only update outputs for
+                        // modified rows.
+                        if let Some(value) = new_value {
+                            for (row, _) in delta_rows {
+                                let old_weight = positive_weight(&old_rows, &row);
+                                let new_weight = positive_weight(&new_rows, &row);
+                                if old_weight != new_weight {
+                                    output.push(((row.clone(), value.clone()), old_weight.neg()));
+                                    output.push(((row, value.clone()), new_weight));
+                                }
+                            }
+                        }
+                    } else {
+                        // The selected value has changed: retract the old value and
+                        // insert the new one for all rows in the partition.
+                        if let Some(value) = old_value {
+                            for (row, weight) in old_rows {
+                                if weight.gt0() {
+                                    output.push(((row, value.clone()), weight.neg()));
+                                }
+                            }
+                        }
+                        if let Some(value) = new_value {
+                            for (row, weight) in new_rows {
+                                if weight.gt0() {
+                                    output.push(((row, value.clone()), weight));
+                                }
+                            }
+                        }
+                    }
+
+                    delta_cursor.step_key();
+                }
+
+                OrdZSet::from_keys((), output)
+            },
+        )
+    }
+}
+
+/// Weight of `row` in consolidated `rows`, or zero if the row is missing or
+/// has a non-positive weight.
+fn positive_weight<K, R>(rows: &[(K, R)], row: &K) -> R
+where
+    K: Ord,
+    R: ZRingValue,
+{
+    match rows.binary_search_by(|(k, _)| k.cmp(row)) {
+        Ok(index) if rows[index].1.gt0() => rows[index].1.clone(),
+        _ => R::zero(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        trace::{consolidation::consolidate, Batch},
+        zset, OrdZSet, Runtime,
+    };
+
+    /// `(partition, timestamp, value)`
+    type Row = (usize, usize, String);
+
+    // Brute-force reference implementation.
+    fn brute_force(rows: &[(Row, isize)], last: bool) -> OrdZSet<(Row, String), isize> {
+        let mut output = Vec::new();
+
+        for (row, weight) in rows.iter().filter(|(_, w)| *w > 0) {
+            let partition = rows
+                .iter()
+                .filter(|((p, _, _), w)| *p == row.0 && *w > 0)
+                .map(|((_, ts, v), _)| (ts, v));
+            let selected = if last {
+                partition.max()
+            } else {
+                partition.min()
+            };
+
+            output.push(((row.clone(), selected.unwrap().1.clone()), *weight));
+        }
+
+        OrdZSet::from_keys((), output)
+    }
+
+    fn first_last_value_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, first_delta, first, last)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (input, input_handle) = circuit.add_input_zset::<Row, isize>();
+
+                let first_value =
+                    input.first_value(|(p, _, _)| *p, |(_, ts, _)| *ts, |(_, _, v)| v.clone());
+                let first_delta = first_value.output();
+                let first = first_value.integrate().output();
+                let last = input
+                    .last_value(|(p, _, _)| *p, |(_, ts, _)| *ts, |(_, _, v)| v.clone())
+                    .integrate()
+                    .output();
+
+                (input_handle, first_delta, first, last)
+            })
+            .unwrap();
+
+        let row = |p: usize, ts: usize, v: &str| (p, ts, v.to_string());
+
+        input_handle.append(&mut vec![
+            (row(0, 10, "b"), 1),
+            (row(0, 20, "c"), 1),
+            (row(1, 5, "x"), 1),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            first_delta.consolidate(),
+            zset! {
+                (row(0, 10, "b"), "b".to_string()) => 1,
+                (row(0, 20, "c"), "b".to_string()) => 1,
+                (row(1, 5, "x"), "x".to_string()) => 1,
+            }
+        );
+        first.consolidate();
+        last.consolidate();
+
+        // Inserting a new earliest row updates the first value of all rows in
+        // its partition, but doesn't affect other partitions.
+        input_handle.append(&mut vec![(row(0, 1, "a"), 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            first_delta.consolidate(),
+            zset! {
+                (row(0, 10, "b"), "b".to_string()) => -1,
+                (row(0, 20, "c"), "b".to_string()) => -1,
+                (row(0, 1, "a"), "a".to_string()) => 1,
+                (row(0, 10, "b"), "a".to_string()) => 1,
+                (row(0, 20, "c"), "a".to_string()) => 1,
+            }
+        );
+        first.consolidate();
+        last.consolidate();
+
+        // A row that doesn't change the first value only affects its own
+        // output record.
+        input_handle.append(&mut vec![(row(0, 15, "d"), 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            first_delta.consolidate(),
+            zset! { (row(0, 15, "d"), "a".to_string()) => 1 }
+        );
+
+        let mut contents: Vec<(Row, isize)> = vec![
+            (row(0, 1, "a"), 1),
+            (row(0, 10, "b"), 1),
+            (row(0, 15, "d"), 1),
+            (row(0, 20, "c"), 1),
+            (row(1, 5, "x"), 1),
+        ];
+        assert_eq!(first.consolidate(), brute_force(&contents, false));
+        assert_eq!(last.consolidate(), brute_force(&contents, true));
+
+        let updates: Vec<Vec<(Row, isize)>> = vec![
+            // Ties are broken by comparing rows.
+            vec![(row(1, 5, "w"), 1), (row(1, 7, "y"), 2)],
+            // Delete the first and last rows of partition 0.
+            vec![(row(0, 1, "a"), -1), (row(0, 20, "c"), -1)],
+            vec![(row(0, 30, "e"), 1), (row(2, 0, "z"), 1)],
+            // Empty a partition.
+            vec![
+                (row(1, 5, "w"), -1),
+                (row(1, 5, "x"), -1),
+                (row(1, 7, "y"), -2),
+            ],
+        ];
+
+        for mut update in updates {
+            contents.extend(update.iter().cloned());
+            consolidate(&mut contents);
+
+            input_handle.append(&mut update);
+            dbsp.step().unwrap();
+
+            first_delta.consolidate();
+            assert_eq!(first.consolidate(), brute_force(&contents, false));
+            assert_eq!(last.consolidate(), brute_force(&contents, true));
+        }
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn first_last_value_test1() {
+        first_last_value_test(1);
+    }
+
+    #[test]
+    fn first_last_value_test4() {
+        first_last_value_test(4);
+    }
+}
@@ -3,7 +3,7 @@
 
 use crate::circuit::{
     operator_traits::{Data, Operator, SourceOperator},
-    Scope,
+    CheckpointError, Scope,
 };
 use std::{borrow::Cow, marker::PhantomData};
 
@@ -90,6 +90,10 @@ where
         // can inform the circuit that it's reached a fixedpoint?
         false
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T> SourceOperator<T> for GeneratorNested<T>
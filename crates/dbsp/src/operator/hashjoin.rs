@@ -0,0 +1,1350 @@
+//! Hash join operator.
+
+use crate::{
+    algebra::{AddAssignByRef, HasZero, IndexedZSet, MulByRef, ZRingValue},
+    circuit::{
+        metadata::{OperatorLocation, OperatorMeta},
+        operator_traits::{BinaryOperator, Operator},
+        CheckpointError, Circuit, RootCircuit, Scope, Stream,
+    },
+    time::AntichainRef,
+    trace::{
+        bloom_filter::{BloomFilter, KeyHash},
+        consolidation,
+        layers::{
+            advance,
+            column_layer::{ColumnLayer, ColumnLayerBuilder, ColumnLayerCursor},
+            ordered::OrderedBuilder,
+            retreat, Builder as LayerBuilder, Cursor as TrieCursor, MergeBuilder, OrdOffset,
+            TupleBuilder,
+        },
+        spine_fueled::{MergeState, MergeVariant, Spine},
+        Batch, BatchReader, Batcher, Builder, Consumer, Cursor, Merger, ValueConsumer,
+    },
+    DBData, DBWeight, NumEntries, OrdZSet,
+};
+use bitvec::vec::BitVec;
+use hashbrown::HashMap;
+use size_of::SizeOf;
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    fmt::{self, Debug},
+    iter::once,
+    marker::PhantomData,
+    ops::Range,
+    panic::Location,
+    vec,
+};
+use xxhash_rust::xxh3::Xxh3Builder;
+
+impl<I1> Stream<RootCircuit, I1>
+where
+    I1: IndexedZSet + Send,
+{
+    /// Incrementally join two streams of batches using hash tables.
+    ///
+    /// Computes the same result as [`join`](`Self::join`), but stores the
+    /// integrals of both inputs in traces of hashed batches instead of
+    /// sorted ones.  Each key of the input delta is looked up in every
+    /// batch of the other trace with a single hash probe instead of a
+    /// binary search, which is cheaper for large traces with expensive
+    /// comparisons, e.g., long string keys.  The hashed traces are kept
+    /// separate from the traces used by `join` and other operators, so
+    /// each input is stored twice if it is also consumed by such operators.
+    ///
+    /// This operator only works in the root scope.  Hashed batches don't
+    /// store timestamps, so their traces cannot hold the updates of nested
+    /// clock cycles that a join inside a nested circuit, e.g., a fixedpoint
+    /// computation, needs.  Use [`join`](`Self::join`) in nested circuits.
+    #[track_caller]
+    pub fn hashjoin<I2, F, V>(
+        &self,
+        other: &Stream<RootCircuit, I2>,
+        join_func: F,
+    ) -> Stream<RootCircuit, OrdZSet<V, I1::R>>
+    where
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+        I1::R: ZRingValue,
+        F: Fn(&I1::Key, &I1::Val, &I2::Val) -> V + Clone + 'static,
+        V: DBData,
+    {
+        self.hashjoin_generic(other, move |k, v1, v2| once((join_func(k, v1, v2), ())))
+    }
+
+    /// Like [`Self::hashjoin`], but can return any indexed Z-set type.
+    ///
+    /// The join function returns an iterable collection of `(key, value)`
+    /// pairs, as in [`join_generic`](`Self::join_generic`).
+    #[track_caller]
+    pub fn hashjoin_generic<I2, F, Z, It>(
+        &self,
+        other: &Stream<RootCircuit, I2>,
+        join_func: F,
+    ) -> Stream<RootCircuit, Z>
+    where
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+        I1::R: ZRingValue,
+        Z: IndexedZSet<R = I1::R>,
+        F: Fn(&I1::Key, &I1::Val, &I2::Val) -> It + Clone + 'static,
+        It: IntoIterator<Item = (Z::Key, Z::Val)> + 'static,
+    {
+        let left = self.shard();
+        let right = other.shard();
+
+        let left_trace = left.trace::<Spine<HashedKVBatch<I1::Key, I1::Val, I1::R>>>();
+        let right_trace = right.trace::<Spine<HashedKVBatch<I2::Key, I2::Val, I2::R>>>();
+
+        // The change to the output is `Δleft ⋈ right + left[-1] ⋈ Δright`, where
+        // `right` already includes the current delta and `left[-1]` doesn't.
+        let left_delta = self.circuit().add_binary_operator(
+            HashJoin::new(join_func.clone(), Location::caller()),
+            &left,
+            &right_trace,
+        );
+
+        let right_delta = self.circuit().add_binary_operator(
+            HashJoin::new(
+                move |k: &I1::Key, v2: &I2::Val, v1: &I1::Val| join_func(k, v1, v2),
+                Location::caller(),
+            ),
+            &right,
+            &left_trace.delay_trace(),
+        );
+
+        left_delta.plus(&right_delta)
+    }
+}
+
+struct HashJoin<F, I, V, Z, Iter> {
+    join_func: F,
+    location: &'static Location<'static>,
+    // True if empty input batch was received at the current clock cycle.
+    empty_input: bool,
+    // True if empty output was produced at the current clock cycle.
+    empty_output: bool,
+    __type: PhantomData<*const (I, V, Z, Iter)>,
+}
+
+impl<F, I, V, Z, Iter> HashJoin<F, I, V, Z, Iter> {
+    fn new(join_func: F, location: &'static Location<'static>) -> Self {
+        Self {
+            join_func,
+            location,
+            empty_input: false,
+            empty_output: false,
+            __type: PhantomData,
+        }
+    }
+}
+
+impl<F, I, V, Z, Iter> Operator for HashJoin<F, I, V, Z, Iter>
+where
+    F: 'static,
+    I: 'static,
+    V: 'static,
+    Z: 'static,
+    Iter: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("HashJoin")
+    }
+
+    fn location(&self) -> OperatorLocation {
+        Some(self.location)
+    }
+
+    fn clock_start(&mut self, scope: Scope) {
+        if scope == 0 {
+            self.empty_input = false;
+            self.empty_output = false;
+        }
+    }
+
+    fn clock_end(&mut self, _scope: Scope) {}
+
+    fn metadata(&self, _meta: &mut OperatorMeta) {}
+
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        // Unlike `JoinTrace`, this operator never precomputes outputs for future
+        // timestamps: it is only instantiated in the root scope, where the trace
+        // holds all updates up to the current clock cycle.  Hence we're in a stable
+        // state if input and output at the current clock cycle are both empty.
+        self.empty_input && self.empty_output
+    }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
+}
+
+impl<F, I, V, Z, Iter> BinaryOperator<I, Spine<HashedKVBatch<I::Key, V, I::R>>, Z>
+    for HashJoin<F, I, V, Z, Iter>
+where
+    I: IndexedZSet,
+    V: DBData,
+    F: Fn(&I::Key, &I::Val, &V) -> Iter + Clone + 'static,
+    Z: IndexedZSet<R = I::R>,
+    Z::R: ZRingValue,
+    Iter: IntoIterator<Item = (Z::Key, Z::Val)> + 'static,
+{
+    fn eval(&mut self, index: &I, trace: &Spine<HashedKVBatch<I::Key, V, I::R>>) -> Z {
+        self.empty_input = index.is_empty();
+
+        let mut index_cursor = index.cursor();
+        let mut trace_probe = SpineProbes::new(trace);
+
+        let mut batch = Vec::with_capacity(index.len());
+        while index_cursor.key_valid() {
+            if trace_probe.probe_key(index_cursor.key()) {
+                while index_cursor.val_valid() {
+                    let index_weight = index_cursor.weight();
+                    let v1 = index_cursor.val();
+
+                    while trace_probe.val_valid() {
+                        let output = (self.join_func)(index_cursor.key(), v1, trace_probe.val());
+                        let weight = index_weight.mul_by_ref(trace_probe.weight());
+
+                        for (key, value) in output {
+                            batch.push((Z::item_from(key, value), weight.clone()));
+                        }
+
+                        trace_probe.step_val();
+                    }
+
+                    trace_probe.rewind_vals();
+                    index_cursor.step_val();
+                }
+            }
+
+            index_cursor.step_key();
+        }
+
+        let mut batcher = Z::Batcher::new_batcher(());
+        batcher.push_batch(&mut batch);
+
+        let result = batcher.seal();
+        self.empty_output = result.is_empty();
+
+        result
+    }
+}
+
+struct SpineProbes<'a, K, V, R, O = usize> {
+    probes: Vec<HashedKVBatchProbe<'a, K, V, R, O>>,
+    contains_key: BitVec,
+    current: usize,
+}
+
+impl<'a, K, V, R> SpineProbes<'a, K, V, R>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight,
+{
+    fn new(spine: &'a Spine<HashedKVBatch<K, V, R>>) -> Self {
+        let mut probes = Vec::with_capacity(spine.merging.len());
+        for merge_state in spine.merging.iter().rev() {
+            match merge_state {
+                MergeState::Double(MergeVariant::InProgress(batch1, batch2, _)) => {
+                    if !batch1.is_empty() {
+                        probes.push(batch1.probe());
+                    }
+
+                    if !batch2.is_empty() {
+                        probes.push(batch2.probe());
+                    }
+                }
+
+                MergeState::Double(MergeVariant::Complete(Some(batch)))
+                | MergeState::Single(Some(batch)) => {
+                    if !batch.is_empty() {
+                        probes.push(batch.probe());
+                    }
+                }
+
+                MergeState::Double(MergeVariant::Complete(None))
+                | MergeState::Single(None)
+                | MergeState::Vacant => {}
+            }
+        }
+
+        let contains_key = BitVec::repeat(false, probes.len());
+
+        Self {
+            probes,
+            contains_key,
+            current: 0,
+        }
+    }
+
+    fn probe_key(&mut self, key: &K) -> bool {
+        // Hash the key once for the filters of all batches.
+        let hash = cfg!(feature = "bloom-filter").then(|| KeyHash::new(key));
+        for (idx, probe) in self.probes.iter_mut().enumerate() {
+            self.contains_key.set(idx, probe.probe_key(key, hash));
+        }
+        self.current = self.contains_key.first_one().unwrap_or(self.probes.len());
+        self.contains_key.any()
+    }
+
+    fn val_valid(&self) -> bool {
+        self.current < self.probes.len() && self.probes[self.current].val_valid()
+    }
+
+    fn val(&self) -> &V {
+        self.probes[self.current].val()
+    }
+
+    fn weight(&self) -> &R {
+        self.probes[self.current].weight()
+    }
+
+    fn step_val(&mut self) {
+        if self.current < self.probes.len() {
+            self.probes[self.current].step_val();
+
+            if !self.probes[self.current].val_valid() {
+                self.current = self
+                    .contains_key
+                    .iter()
+                    .enumerate()
+                    .skip(self.current)
+                    .filter_map(|(idx, contains_key)| contains_key.then_some(idx))
+                    .next()
+                    .unwrap_or(self.probes.len());
+            }
+        }
+    }
+
+    fn rewind_vals(&mut self) {
+        self.current = self.contains_key.first_one().unwrap_or(self.probes.len());
+
+        for probe in &mut self.probes {
+            probe.rewind_vals();
+        }
+    }
+}
+
+struct HashedKVBatchProbe<'a, K, V, R, O> {
+    batch: &'a HashedKVBatch<K, V, R, O>,
+    current: usize,
+    start: usize,
+    end: usize,
+}
+
+impl<'a, K, V, R, O> HashedKVBatchProbe<'a, K, V, R, O> {
+    const fn new(batch: &'a HashedKVBatch<K, V, R, O>) -> Self {
+        Self {
+            batch,
+            current: 0,
+            start: 0,
+            end: 0,
+        }
+    }
+
+    fn val_valid(&self) -> bool {
+        self.current < self.end
+    }
+
+    fn val(&self) -> &V {
+        &self.batch.values.keys()[self.current]
+    }
+
+    fn weight(&self) -> &R {
+        &self.batch.values.diffs()[self.current]
+    }
+
+    fn step_val(&mut self) {
+        self.current += 1;
+    }
+
+    fn rewind_vals(&mut self) {
+        self.current = self.start;
+    }
+
+    /// `hash` is the hash of `key`, or `None` when key filters are disabled.
+    fn probe_key(&mut self, key: &K, hash: Option<KeyHash>) -> bool
+    where
+        K: DBData,
+        V: DBData,
+        R: DBWeight,
+        O: OrdOffset,
+    {
+        if hash.map_or(false, |hash| !self.batch.maybe_contains_key_hash(hash)) {
+            return false;
+        }
+
+        if let Some(offset) = self.batch.keys.get(key).copied().map(OrdOffset::into_usize) {
+            self.start = self.batch.offsets[offset].into_usize();
+            self.end = self.batch.offsets[offset + 1].into_usize();
+            self.current = self.start;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Clone, SizeOf)]
+struct HashedKVBatch<K, V, R, O = usize> {
+    // Invariant: Each offset within `keys` and each offset within keys +1 are valid indices into
+    // `offsets`
+    keys: HashMap<K, O, Xxh3Builder>,
+    // Invariant: Each offset within `offsets` is a valid index into `values`
+    offsets: Vec<O>,
+    // The value+diff pairs associated with any given key can be fetched with
+    // `values[offsets[keys[&key]]..offsets[keys[&key] + 1]]`
+    values: ColumnLayer<V, R>,
+    // Bloom filter over `keys`, checked before hashing the key into `keys`
+    key_filter: Option<BloomFilter>,
+}
+
+impl<K, V, R, O> HashedKVBatch<K, V, R, O> {
+    fn probe(&self) -> HashedKVBatchProbe<'_, K, V, R, O> {
+        HashedKVBatchProbe::new(self)
+    }
+
+    /// Returns the range of `values` that holds the values of the key stored
+    /// at `offset`.
+    fn value_range(&self, offset: O) -> Range<usize>
+    where
+        O: OrdOffset,
+    {
+        let offset = offset.into_usize();
+        let start = self.offsets[offset].into_usize();
+        let end = self.offsets[offset + 1].into_usize();
+
+        // Builders only add a key along with at least one value.
+        debug_assert!(start < end && end <= self.values.len());
+        start..end
+    }
+
+    fn from_builder(builder: OrderedBuilder<K, ColumnLayerBuilder<V, R>, O>) -> Self
+    where
+        K: DBData,
+        V: DBData,
+        R: DBWeight,
+        O: OrdOffset,
+    {
+        // Finish the ordered layer and break it down to its components
+        let (layer_keys, offsets, values, _) = builder.done().into_parts();
+
+        // Within the OrderedLayer (and transitively within `layer_keys`) the start of a
+        // key's value range is implicit in the key's index in the vec. However, since
+        // we want to do hash lookups here we store our keys within a `HashMap` which
+        // doesn't allow us to store the offsets implicitly, so we have to store that
+        // start offset within the HashMap
+        let key_filter = BloomFilter::from_keys(layer_keys.iter());
+
+        let mut keys = HashMap::with_capacity_and_hasher(layer_keys.len(), Xxh3Builder::new());
+        for (idx, key) in layer_keys.into_iter().enumerate() {
+            debug_assert!(!keys.contains_key(&key));
+            debug_assert!(offsets.len() > idx);
+            keys.insert_unique_unchecked(key, O::from_usize(idx));
+        }
+
+        Self {
+            keys,
+            offsets,
+            values,
+            key_filter,
+        }
+    }
+}
+
+impl<K, V, R, O> NumEntries for HashedKVBatch<K, V, R, O> {
+    const CONST_NUM_ENTRIES: Option<usize> = None;
+
+    fn num_entries_shallow(&self) -> usize {
+        self.keys.len()
+    }
+
+    // Like `OrderedLayer`, counts all `(value, weight)` pairs.
+    fn num_entries_deep(&self) -> usize {
+        self.values.len()
+    }
+}
+
+impl<K, V, R, O> BatchReader for HashedKVBatch<K, V, R, O>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight,
+    O: OrdOffset,
+{
+    type Key = K;
+    type Val = V;
+    type Time = ();
+    type R = R;
+
+    type Cursor<'a> = HashedKVCursor<'a, K, V, R, O>;
+    type Consumer = HashedKVConsumer<K, V, R, O>;
+
+    fn cursor(&self) -> Self::Cursor<'_> {
+        HashedKVCursor::new(self)
+    }
+
+    fn consumer(self) -> Self::Consumer {
+        HashedKVConsumer::new(self)
+    }
+
+    fn key_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn lower(&self) -> AntichainRef<'_, Self::Time> {
+        AntichainRef::empty()
+    }
+
+    fn upper(&self) -> AntichainRef<'_, Self::Time> {
+        AntichainRef::new(&[()])
+    }
+
+    fn truncate_keys_below(&mut self, _lower_bound: &Self::Key) {}
+
+    fn maybe_contains_key(&self, key: &Self::Key) -> bool {
+        self.key_filter
+            .as_ref()
+            .map_or(true, |filter| filter.maybe_contains(key))
+    }
+
+    fn maybe_contains_key_hash(&self, hash: KeyHash) -> bool {
+        self.key_filter
+            .as_ref()
+            .map_or(true, |filter| filter.maybe_contains_hash(hash))
+    }
+}
+
+impl<K, V, R, O> Batch for HashedKVBatch<K, V, R, O>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight,
+    O: OrdOffset,
+{
+    type Item = (K, V);
+    type Batcher = HashedKVBatcher<K, V, R, O>;
+    type Builder = HashedKVBuilder<K, V, R, O>;
+    type Merger = HashedKVMerger<K, V, R, O>;
+
+    fn item_from(key: Self::Key, value: Self::Val) -> Self::Item {
+        (key, value)
+    }
+
+    fn from_keys(_time: Self::Time, mut inputs: Vec<(Self::Key, Self::R)>) -> Self
+    where
+        Self::Val: From<()>,
+    {
+        consolidation::consolidate(&mut inputs);
+
+        let mut keys = HashMap::with_capacity_and_hasher(inputs.len(), Xxh3Builder::new());
+        let mut values = <ColumnLayerBuilder<_, _> as TupleBuilder>::with_capacity(inputs.len());
+        let mut offsets = Vec::with_capacity(inputs.len() + 1);
+        offsets.push(O::zero());
+
+        for (key, diff) in inputs {
+            debug_assert!(
+                !diff.is_zero(),
+                "consolidation should take care of zeroed weights",
+            );
+
+            // Push the value+diff pair
+            values.push_tuple((Self::Val::from(()), diff));
+
+            // Add the key and the offset of the start of its value range to the keys map
+            debug_assert!(!keys.contains_key(&key));
+            keys.insert_unique_unchecked(key, O::from_usize(offsets.len() - 1));
+
+            // Record the end of the current key's values in offsets
+            offsets.push(O::from_usize(values.boundary()));
+        }
+
+        let key_filter = BloomFilter::from_keys(keys.keys());
+
+        Self {
+            keys,
+            offsets,
+            values: values.done(),
+            key_filter,
+        }
+    }
+
+    fn recede_to(&mut self, _frontier: &Self::Time) {}
+}
+
+impl<K, V, R, O> Debug for HashedKVBatch<K, V, R, O>
+where
+    K: Debug,
+    V: Debug,
+    R: Debug,
+    O: OrdOffset + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct KVBatch<'a, K, V, R, O>(&'a HashedKVBatch<K, V, R, O>);
+
+        impl<K, V, R, O> Debug for KVBatch<'_, K, V, R, O>
+        where
+            K: Debug,
+            V: Debug,
+            R: Debug,
+            O: OrdOffset + Debug,
+        {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let batch = self.0;
+
+                let mut map = f.debug_map();
+                for (key, &offset) in batch.keys.iter() {
+                    let offset = offset.into_usize();
+                    let start = batch.offsets[offset].into_usize();
+                    let end = batch.offsets[offset + 1].into_usize();
+
+                    map.entry(
+                        key,
+                        &ValDiffPairs(
+                            &batch.values.keys()[start..end],
+                            &batch.values.diffs()[start..end],
+                        ),
+                    );
+                }
+
+                map.finish()
+            }
+        }
+
+        struct ValDiffPairs<'a, V, R>(&'a [V], &'a [R]);
+
+        impl<V, R> Debug for ValDiffPairs<'_, V, R>
+        where
+            V: Debug,
+            R: Debug,
+        {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_map().entries(self.0.iter().zip(self.1)).finish()
+            }
+        }
+
+        f.debug_struct("HashedKVBatch")
+            .field("batch", &KVBatch(self))
+            .finish_non_exhaustive()
+    }
+}
+
+/// A cursor over a [`HashedKVBatch`].
+///
+/// Keys are visited in ascending order.  The key with index `i` in
+/// `HashedKVBatch::keys` owns the value range
+/// `offsets[i]..offsets[i + 1]`, and keys are assigned indices in ascending
+/// order by the builder, so the cursor recovers the order of keys by
+/// inverting the key map once, when it is created.
+struct HashedKVCursor<'a, K, V, R, O>
+where
+    V: Ord + Clone,
+    R: Clone,
+{
+    batch: &'a HashedKVBatch<K, V, R, O>,
+    // Keys of the batch in ascending order.
+    keys: Vec<&'a K>,
+    // Position of the current key in `keys`.  We use `isize`, so we can use
+    // `-1` to represent a cursor that rolled over the first key.
+    key_pos: isize,
+    vals: ColumnLayerCursor<'a, V, R>,
+}
+
+impl<'a, K, V, R, O> HashedKVCursor<'a, K, V, R, O>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight,
+    O: OrdOffset,
+{
+    fn new(batch: &'a HashedKVBatch<K, V, R, O>) -> Self {
+        let mut keys = vec![None; batch.keys.len()];
+        for (key, &offset) in batch.keys.iter() {
+            keys[offset.into_usize()] = Some(key);
+        }
+
+        let mut cursor = Self {
+            batch,
+            keys: keys.into_iter().map(Option::unwrap).collect(),
+            key_pos: 0,
+            vals: ColumnLayerCursor::new(0, &batch.values, (0, 0)),
+        };
+        cursor.update_vals();
+        cursor
+    }
+
+    /// Points the value cursor to the values of the current key.
+    fn update_vals(&mut self) {
+        if self.key_valid() {
+            let idx = self.key_pos as usize;
+            self.vals.reposition(
+                self.batch.offsets[idx].into_usize(),
+                self.batch.offsets[idx + 1].into_usize(),
+            );
+        } else {
+            self.vals.reposition(0, 0);
+        }
+    }
+}
+
+impl<'a, K, V, R, O> Cursor<K, V, (), R> for HashedKVCursor<'a, K, V, R, O>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight,
+    O: OrdOffset,
+{
+    fn key_valid(&self) -> bool {
+        self.key_pos >= 0 && (self.key_pos as usize) < self.keys.len()
+    }
+
+    fn val_valid(&self) -> bool {
+        self.vals.valid()
+    }
+
+    fn key(&self) -> &K {
+        self.keys[self.key_pos as usize]
+    }
+
+    fn val(&self) -> &V {
+        self.vals.current_key()
+    }
+
+    fn fold_times<F, U>(&mut self, init: U, mut fold: F) -> U
+    where
+        F: FnMut(U, &(), &R) -> U,
+    {
+        if self.vals.valid() {
+            fold(init, &(), self.vals.current_diff())
+        } else {
+            init
+        }
+    }
+
+    fn fold_times_through<F, U>(&mut self, _upper: &(), init: U, fold: F) -> U
+    where
+        F: FnMut(U, &(), &R) -> U,
+    {
+        self.fold_times(init, fold)
+    }
+
+    fn weight(&mut self) -> R {
+        debug_assert!(self.vals.valid());
+        self.vals.current_diff().clone()
+    }
+
+    fn step_key(&mut self) {
+        if self.key_pos < self.keys.len() as isize {
+            self.key_pos += 1;
+        }
+        self.update_vals();
+    }
+
+    fn step_key_reverse(&mut self) {
+        if self.key_pos >= 0 {
+            self.key_pos -= 1;
+        }
+        self.update_vals();
+    }
+
+    fn seek_key(&mut self, key: &K) {
+        if self.key_valid() {
+            self.key_pos += advance(&self.keys[self.key_pos as usize..], |k| *k < key) as isize;
+            self.update_vals();
+        }
+    }
+
+    fn seek_key_reverse(&mut self, key: &K) {
+        if self.key_valid() {
+            self.key_pos -= retreat(&self.keys[..=self.key_pos as usize], |k| *k > key) as isize;
+            self.update_vals();
+        }
+    }
+
+    fn step_val(&mut self) {
+        self.vals.step();
+    }
+
+    fn step_val_reverse(&mut self) {
+        self.vals.step_reverse();
+    }
+
+    fn seek_val(&mut self, value: &V) {
+        self.vals.seek(value);
+    }
+
+    fn seek_val_reverse(&mut self, value: &V) {
+        self.vals.seek_reverse(value);
+    }
+
+    fn seek_val_with<P>(&mut self, predicate: P)
+    where
+        P: Fn(&V) -> bool + Clone,
+    {
+        self.vals.seek_key_with(|v| !predicate(v));
+    }
+
+    fn seek_val_with_reverse<P>(&mut self, predicate: P)
+    where
+        P: Fn(&V) -> bool + Clone,
+    {
+        self.vals.seek_key_with_reverse(|v| !predicate(v));
+    }
+
+    fn rewind_keys(&mut self) {
+        self.key_pos = 0;
+        self.update_vals();
+    }
+
+    fn fast_forward_keys(&mut self) {
+        self.key_pos = self.keys.len() as isize - 1;
+        self.update_vals();
+    }
+
+    fn rewind_vals(&mut self) {
+        self.vals.rewind();
+    }
+
+    fn fast_forward_vals(&mut self) {
+        self.vals.fast_forward();
+    }
+}
+
+/// A consumer that takes ownership of the contents of a [`HashedKVBatch`],
+/// yielding keys in ascending order.
+struct HashedKVConsumer<K, V, R, O> {
+    // Keys in descending order along with the number of values of each key,
+    // so that the next key can be popped off the end.
+    keys: Vec<(K, usize)>,
+    // Values and diffs of all keys in ascending key order.
+    values: vec::IntoIter<V>,
+    diffs: vec::IntoIter<R>,
+    __type: PhantomData<*const O>,
+}
+
+impl<K, V, R, O> HashedKVConsumer<K, V, R, O>
+where
+    O: OrdOffset,
+{
+    fn new(batch: HashedKVBatch<K, V, R, O>) -> Self {
+        let HashedKVBatch {
+            keys,
+            offsets,
+            values,
+            ..
+        } = batch;
+
+        let mut keys: Vec<(K, usize, usize)> = keys
+            .into_iter()
+            .map(|(key, offset)| {
+                let offset = offset.into_usize();
+                let len = offsets[offset + 1].into_usize() - offsets[offset].into_usize();
+                (key, offset, len)
+            })
+            .collect();
+        keys.sort_unstable_by(|(_, offset1, _), (_, offset2, _)| offset2.cmp(offset1));
+
+        let (values, diffs, _) = values.into_parts();
+
+        Self {
+            keys: keys.into_iter().map(|(key, _, len)| (key, len)).collect(),
+            values: values.into_iter(),
+            diffs: diffs.into_iter(),
+            __type: PhantomData,
+        }
+    }
+
+    /// Drops the next `count` values.
+    fn skip_values(&mut self, count: usize) {
+        self.values.by_ref().take(count).for_each(drop);
+        self.diffs.by_ref().take(count).for_each(drop);
+    }
+}
+
+impl<K, V, R, O> Consumer<K, V, R, ()> for HashedKVConsumer<K, V, R, O>
+where
+    O: OrdOffset,
+{
+    type ValueConsumer<'a>
+        = HashedValueConsumer<'a, V, R>
+    where
+        Self: 'a;
+
+    fn key_valid(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    fn peek_key(&self) -> &K {
+        &self
+            .keys
+            .last()
+            .expect("called `peek_key` on an exhausted consumer")
+            .0
+    }
+
+    fn next_key(&mut self) -> (K, Self::ValueConsumer<'_>) {
+        let (key, remaining) = self
+            .keys
+            .pop()
+            .expect("called `next_key` on an exhausted consumer");
+
+        let values = HashedValueConsumer {
+            values: &mut self.values,
+            diffs: &mut self.diffs,
+            remaining,
+        };
+        (key, values)
+    }
+
+    fn seek_key(&mut self, key: &K)
+    where
+        K: Ord,
+    {
+        while let Some((next_key, len)) = self.keys.last() {
+            if next_key >= key {
+                break;
+            }
+
+            let len = *len;
+            self.keys.pop();
+            self.skip_values(len);
+        }
+    }
+}
+
+/// Consumes the values of a single key of a [`HashedKVConsumer`].
+///
+/// Values that haven't been consumed are dropped along with the value
+/// consumer, so that the next key starts at its own values.
+struct HashedValueConsumer<'a, V, R> {
+    values: &'a mut vec::IntoIter<V>,
+    diffs: &'a mut vec::IntoIter<R>,
+    remaining: usize,
+}
+
+impl<'a, V, R> ValueConsumer<'a, V, R, ()> for HashedValueConsumer<'a, V, R> {
+    fn value_valid(&self) -> bool {
+        self.remaining > 0
+    }
+
+    fn next_value(&mut self) -> (V, R, ()) {
+        assert!(
+            self.remaining > 0,
+            "called `next_value` on an exhausted consumer"
+        );
+        self.remaining -= 1;
+
+        (self.values.next().unwrap(), self.diffs.next().unwrap(), ())
+    }
+
+    fn remaining_values(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<'a, V, R> Drop for HashedValueConsumer<'a, V, R> {
+    fn drop(&mut self) {
+        self.values.by_ref().take(self.remaining).for_each(drop);
+        self.diffs.by_ref().take(self.remaining).for_each(drop);
+    }
+}
+
+type RawKVBuilder<K, V, R, O> = OrderedBuilder<K, ColumnLayerBuilder<V, R>, O>;
+
+#[derive(SizeOf)]
+struct HashedKVBuilder<K, V, R, O = usize>
+where
+    K: Ord,
+    O: OrdOffset,
+{
+    builder: RawKVBuilder<K, V, R, O>,
+}
+
+impl<K, V, R, O> Builder<(K, V), (), R, HashedKVBatch<K, V, R, O>> for HashedKVBuilder<K, V, R, O>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight,
+    O: OrdOffset,
+{
+    fn new_builder(_time: ()) -> Self {
+        Self {
+            builder: RawKVBuilder::new(),
+        }
+    }
+
+    fn with_capacity(_time: (), capacity: usize) -> Self {
+        Self {
+            builder: <RawKVBuilder<_, _, _, _> as TupleBuilder>::with_capacity(capacity),
+        }
+    }
+
+    fn push(&mut self, ((key, value), diff): ((K, V), R)) {
+        self.builder.push_tuple((key, (value, diff)));
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.builder.reserve(additional);
+    }
+
+    fn done(self) -> HashedKVBatch<K, V, R, O> {
+        HashedKVBatch::from_builder(self.builder)
+    }
+}
+
+#[derive(SizeOf)]
+struct HashedKVBatcher<K, V, R, O> {
+    // Tuples pushed so far, consolidated once when the batcher is sealed.
+    values: Vec<((K, V), R)>,
+    __type: PhantomData<*const O>,
+}
+
+impl<K, V, R, O> Batcher<(K, V), (), R, HashedKVBatch<K, V, R, O>> for HashedKVBatcher<K, V, R, O>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight,
+    O: OrdOffset,
+{
+    fn new_batcher(_time: ()) -> Self {
+        Self {
+            values: Vec::new(),
+            __type: PhantomData,
+        }
+    }
+
+    fn push_batch(&mut self, batch: &mut Vec<((K, V), R)>) {
+        self.values.append(batch);
+    }
+
+    fn push_consolidated_batch(&mut self, batch: &mut Vec<((K, V), R)>) {
+        self.push_batch(batch);
+    }
+
+    fn tuples(&self) -> usize {
+        self.values.len()
+    }
+
+    fn seal(mut self) -> HashedKVBatch<K, V, R, O> {
+        // Sort values within each key and drop values with zero weights, so that
+        // `HashedKVMerger` can merge the value runs of a key.
+        consolidation::consolidate(&mut self.values);
+
+        let mut builder = HashedKVBuilder::<K, V, R, O>::with_capacity((), self.values.len());
+        for tuple in self.values {
+            builder.push(tuple);
+        }
+
+        builder.done()
+    }
+}
+
+#[derive(Clone, Copy, SizeOf)]
+enum Side {
+    Left,
+    Right,
+}
+
+#[derive(SizeOf)]
+struct HashedKVMerger<K, V, R, O = usize>
+where
+    K: Ord,
+    O: OrdOffset,
+{
+    // Keys of both batches that haven't been merged yet, in descending order,
+    // so that a key present in both batches occurs twice in a row.  The keys
+    // have to be cloned, since the hash maps of the input batches can't be
+    // iterated incrementally; each clone is moved to the output batch.
+    keys: Vec<(K, O, Side)>,
+    builder: RawKVBuilder<K, V, R, O>,
+}
+
+impl<K, V, R, O> Merger<K, V, (), R, HashedKVBatch<K, V, R, O>> for HashedKVMerger<K, V, R, O>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight,
+    O: OrdOffset,
+{
+    fn new_merger(left: &HashedKVBatch<K, V, R, O>, right: &HashedKVBatch<K, V, R, O>) -> Self {
+        let mut keys = Vec::with_capacity(left.keys.len() + right.keys.len());
+        keys.extend(
+            left.keys
+                .iter()
+                .map(|(key, &offset)| (key.clone(), offset, Side::Left)),
+        );
+        keys.extend(
+            right
+                .keys
+                .iter()
+                .map(|(key, &offset)| (key.clone(), offset, Side::Right)),
+        );
+        keys.sort_unstable_by(|(key1, ..), (key2, ..)| key2.cmp(key1));
+
+        Self {
+            keys,
+            builder: <RawKVBuilder<K, V, R, O> as TupleBuilder>::with_capacity(
+                left.values.len() + right.values.len(),
+            ),
+        }
+    }
+
+    fn work(
+        &mut self,
+        left: &HashedKVBatch<K, V, R, O>,
+        right: &HashedKVBatch<K, V, R, O>,
+        _lower_val_bound: &Option<V>,
+        fuel: &mut isize,
+    ) {
+        let Self { keys, builder } = self;
+
+        while *fuel > 0 {
+            let (key, offset, side) = match keys.pop() {
+                Some(entry) => entry,
+                None => break,
+            };
+
+            // A key present in both batches occurs twice in a row in `keys`; merge
+            // its value runs.
+            if keys.last().map_or(false, |(next_key, ..)| next_key == &key) {
+                let (_, other_offset, _) = keys.pop().unwrap();
+                let (left_offset, right_offset) = match side {
+                    Side::Left => (offset, other_offset),
+                    Side::Right => (other_offset, offset),
+                };
+
+                builder.with_key(key, |mut values| {
+                    merge_values(
+                        left,
+                        left.value_range(left_offset),
+                        right,
+                        right.value_range(right_offset),
+                        |value, diff| values.push((value, diff)),
+                    );
+                });
+
+                *fuel -= 2;
+            } else {
+                let batch = match side {
+                    Side::Left => left,
+                    Side::Right => right,
+                };
+
+                builder.with_key(key, |mut values| {
+                    for idx in batch.value_range(offset) {
+                        values.push((
+                            batch.values.keys()[idx].clone(),
+                            batch.values.diffs()[idx].clone(),
+                        ));
+                    }
+                });
+
+                *fuel -= 1;
+            }
+        }
+    }
+
+    fn done(self) -> HashedKVBatch<K, V, R, O> {
+        HashedKVBatch::from_builder(self.builder)
+    }
+}
+
+/// Merge the sorted value runs `left[left_range]` and `right[right_range]` of
+/// the same key, passing merged values to `push` in order.
+///
+/// Like the merger of `ColumnLayer`s, adds up the weights of values present
+/// in both runs and drops values whose weights add up to zero.
+fn merge_values<K, V, R, O, F>(
+    left: &HashedKVBatch<K, V, R, O>,
+    left_range: Range<usize>,
+    right: &HashedKVBatch<K, V, R, O>,
+    right_range: Range<usize>,
+    mut push: F,
+) where
+    V: DBData,
+    R: DBWeight,
+    F: FnMut(V, R),
+{
+    let (left_values, left_diffs) = (left.values.keys(), left.values.diffs());
+    let (right_values, right_diffs) = (right.values.keys(), right.values.diffs());
+
+    let (mut lower1, upper1) = (left_range.start, left_range.end);
+    let (mut lower2, upper2) = (right_range.start, right_range.end);
+
+    while lower1 < upper1 && lower2 < upper2 {
+        match left_values[lower1].cmp(&right_values[lower2]) {
+            Ordering::Less => {
+                push(left_values[lower1].clone(), left_diffs[lower1].clone());
+                lower1 += 1;
+            }
+
+            Ordering::Equal => {
+                let mut sum = left_diffs[lower1].clone();
+                sum.add_assign_by_ref(&right_diffs[lower2]);
+
+                if !sum.is_zero() {
+                    push(left_values[lower1].clone(), sum);
+                }
+
+                lower1 += 1;
+                lower2 += 1;
+            }
+
+            Ordering::Greater => {
+                push(right_values[lower2].clone(), right_diffs[lower2].clone());
+                lower2 += 1;
+            }
+        }
+    }
+
+    for (value, diff) in left_values[lower1..upper1]
+        .iter()
+        .zip(&left_diffs[lower1..upper1])
+    {
+        push(value.clone(), diff.clone());
+    }
+    for (value, diff) in right_values[lower2..upper2]
+        .iter()
+        .zip(&right_diffs[lower2..upper2])
+    {
+        push(value.clone(), diff.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HashedKVBatch, HashedKVMerger};
+    use crate::{
+        trace::{Batch, BatchReader, Cursor, Merger},
+        zset, Runtime,
+    };
+
+    type Input = (String, usize);
+
+    fn hashjoin_test(workers: usize) {
+        let (mut dbsp, (mut left, mut right, hashjoin, join)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (left, left_handle) = circuit.add_input_indexed_zset::<String, usize, isize>();
+                let (right, right_handle) =
+                    circuit.add_input_indexed_zset::<String, usize, isize>();
+
+                let hashjoin = left
+                    .hashjoin(&right, |k: &String, v1, v2| (k.clone(), *v1, *v2))
+                    .integrate()
+                    .output();
+                let join = left
+                    .join(&right, |k: &String, v1, v2| (k.clone(), *v1, *v2))
+                    .integrate()
+                    .output();
+
+                (left_handle, right_handle, hashjoin, join)
+            })
+            .unwrap();
+
+        left.append(&mut vec![
+            ("a".to_string(), (1, 1)),
+            ("a".to_string(), (2, 2)),
+            ("b".to_string(), (3, 1)),
+        ]);
+        right.append(&mut vec![
+            ("a".to_string(), (10, 1)),
+            ("c".to_string(), (30, 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            hashjoin.consolidate(),
+            zset! { ("a".to_string(), 1, 10) => 1, ("a".to_string(), 2, 10) => 2 }
+        );
+
+        // Updates to both sides in the same step, including deletions.
+        left.append(&mut vec![
+            ("a".to_string(), (1, -1)),
+            ("c".to_string(), (4, 1)),
+        ]);
+        right.append(&mut vec![
+            ("b".to_string(), (20, 1)),
+            ("c".to_string(), (31, -1)),
+            ("c".to_string(), (32, 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(hashjoin.consolidate(), join.consolidate());
+
+        // Enough steps to merge batches in both traces.
+        for step in 0..50 {
+            let key = format!("key{}", step % 7);
+            left.push(key.clone(), (step, 1));
+            right.push(key, (step % 3, 1));
+            if step % 5 == 0 {
+                left.push(format!("key{}", (step + 1) % 7), (step / 2, -1));
+            }
+            dbsp.step().unwrap();
+            assert_eq!(hashjoin.consolidate(), join.consolidate());
+        }
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn hashjoin_test1() {
+        hashjoin_test(1);
+    }
+
+    #[test]
+    fn hashjoin_test4() {
+        hashjoin_test(4);
+    }
+
+    fn tuples(batch: &HashedKVBatch<u64, u64, isize>) -> Vec<((u64, u64), isize)> {
+        let mut tuples = Vec::new();
+        let mut cursor = batch.cursor();
+        while cursor.key_valid() {
+            while cursor.val_valid() {
+                tuples.push(((*cursor.key(), *cursor.val()), cursor.weight()));
+                cursor.step_val();
+            }
+            cursor.step_key();
+        }
+        tuples
+    }
+
+    // Value runs of keys present in both batches are merged, adding up the
+    // weights of identical values and dropping values and keys whose weights
+    // add up to zero.
+    #[test]
+    fn hashed_kv_merge() {
+        let left = HashedKVBatch::<u64, u64, isize>::from_tuples(
+            (),
+            vec![
+                ((1, 3), 1),
+                ((1, 1), 1),
+                ((2, 1), 1),
+                ((1, 2), 1),
+                ((1, 2), -1),
+                ((4, 1), 1),
+            ],
+        );
+        let right = HashedKVBatch::<u64, u64, isize>::from_tuples(
+            (),
+            vec![((1, 2), 2), ((3, 1), 1), ((1, 1), -1), ((2, 1), -1)],
+        );
+        let expected = vec![((1, 2), 2), ((1, 3), 1), ((3, 1), 1), ((4, 1), 1)];
+
+        let merged = left.merge(&right);
+        assert_eq!(tuples(&merged), expected);
+        assert_eq!(merged.key_count(), 3);
+        assert_eq!(merged.len(), 4);
+
+        // Merging with minimal fuel must not split the value runs of a key.
+        let mut merger = HashedKVMerger::new_merger(&left, &right);
+        while !merger.keys.is_empty() {
+            let mut fuel = 1;
+            merger.work(&left, &right, &None, &mut fuel);
+        }
+        assert_eq!(tuples(&merger.done()), expected);
+    }
+}
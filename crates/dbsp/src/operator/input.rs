@@ -1,25 +1,32 @@
 use crate::{
     algebra::ZRingValue,
     circuit::{
+        checkpoint::{decode_state, encode_state},
         operator_traits::{Operator, SourceOperator},
-        LocalStoreMarker, RootCircuit, Scope,
+        CheckpointError, LocalStoreMarker, RootCircuit, Scope,
     },
-    default_hash,
+    circuit_cache_key, default_hash,
     trace::Batch,
     Circuit, DBData, DBWeight, OrdIndexedZSet, OrdZSet, Runtime, Stream,
 };
 use std::{
+    any::{type_name, Any},
     borrow::Cow,
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
     hash::{Hash, Hasher},
     marker::PhantomData,
     mem::{swap, take},
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicU64, AtomicUsize, Ordering},
         Arc, Mutex,
     },
 };
 use typedmap::TypedMapKey;
 
+// Names of inputs registered in a circuit.
+circuit_cache_key!(InputNameId(String => ()));
+
 pub type IndexedZSetStream<K, V, R> = Stream<RootCircuit, OrdIndexedZSet<K, V, R>>;
 pub type ZSetStream<K, R> = Stream<RootCircuit, OrdZSet<K, R>>;
 
@@ -125,6 +132,108 @@ impl RootCircuit {
         (stream, zset_handle)
     }
 
+    /// Like [`add_input_zset`](`Self::add_input_zset`), but the input handle
+    /// partitions values across workers based on the hash of the key instead
+    /// of round robin.
+    ///
+    /// The handle uses the same partitioning as the
+    /// [`shard`](`Stream::shard`) operator, so the input stream is marked as
+    /// sharded and operators that shard their inputs, e.g., `join` and
+    /// `distinct`, consume it directly, without exchanging data among
+    /// workers.  This moves the cost of partitioning from the circuit to the
+    /// thread that feeds the input handle, which pays off when the input
+    /// stream is consumed by such operators.
+    pub fn add_input_zset_sharded<K, R>(
+        &self,
+    ) -> (Stream<Self, OrdZSet<K, R>>, CollectionHandle<K, R>)
+    where
+        K: DBData,
+        R: DBWeight,
+    {
+        let (input, input_handle) = Input::new(|tuples| OrdZSet::from_keys((), tuples));
+        let stream = self.add_source(input).mark_sharded();
+
+        let zset_handle = <CollectionHandle<K, R>>::with_sharding(input_handle, true);
+
+        (stream, zset_handle)
+    }
+
+    /// Like [`add_input_indexed_zset`](`Self::add_input_indexed_zset`), but
+    /// the input handle partitions values across workers based on the hash of
+    /// the key.
+    ///
+    /// See [`add_input_zset_sharded`](`Self::add_input_zset_sharded`).
+    #[allow(clippy::type_complexity)]
+    pub fn add_input_indexed_zset_sharded<K, V, R>(
+        &self,
+    ) -> (IndexedZSetStream<K, V, R>, CollectionHandle<K, (V, R)>)
+    where
+        K: DBData,
+        V: DBData,
+        R: DBWeight,
+    {
+        let (input, input_handle) = Input::new(|tuples: Vec<(K, (V, R))>| {
+            OrdIndexedZSet::from_tuples(
+                (),
+                tuples.into_iter().map(|(k, (v, w))| ((k, v), w)).collect(),
+            )
+        });
+        let stream = self.add_source(input).mark_sharded();
+
+        let zset_handle = <CollectionHandle<K, (V, R)>>::with_sharding(input_handle, true);
+
+        (stream, zset_handle)
+    }
+
+    /// Like [`add_input_zset`](`Self::add_input_zset`), but also registers
+    /// the input handle under `name`.
+    ///
+    /// When the circuit is instantiated using
+    /// [`Runtime::init_circuit`](`crate::Runtime::init_circuit`), named
+    /// inputs can be enumerated via
+    /// [`DBSPHandle::inputs`](`crate::DBSPHandle::inputs`) and updated
+    /// atomically via
+    /// [`DBSPHandle::transaction`](`crate::DBSPHandle::transaction`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the circuit already contains an input named `name`.
+    pub fn add_input_zset_named<K, R>(
+        &self,
+        name: &str,
+    ) -> (Stream<Self, OrdZSet<K, R>>, CollectionHandle<K, R>)
+    where
+        K: DBData,
+        R: DBWeight,
+    {
+        let (stream, handle) = self.add_input_zset();
+        register_named_input(self, name, &handle);
+        (stream, handle)
+    }
+
+    /// Like [`add_input_indexed_zset`](`Self::add_input_indexed_zset`), but
+    /// also registers the input handle under `name`.
+    ///
+    /// See [`add_input_zset_named`](`Self::add_input_zset_named`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the circuit already contains an input named `name`.
+    #[allow(clippy::type_complexity)]
+    pub fn add_input_indexed_zset_named<K, V, R>(
+        &self,
+        name: &str,
+    ) -> (IndexedZSetStream<K, V, R>, CollectionHandle<K, (V, R)>)
+    where
+        K: DBData,
+        V: DBData,
+        R: DBWeight,
+    {
+        let (stream, handle) = self.add_input_indexed_zset();
+        register_named_input(self, name, &handle);
+        (stream, handle)
+    }
+
     fn add_upsert<K, VI, V, F, B>(
         &self,
         input_stream: Stream<Self, Vec<(K, VI)>>,
@@ -379,6 +488,180 @@ where
 }
 */
 
+/// Register `handle` under `name` in the current runtime.
+///
+/// Panics if `circuit` already has an input named `name`, whether or not it
+/// runs inside a runtime.
+fn register_named_input<K, V>(circuit: &RootCircuit, name: &str, handle: &CollectionHandle<K, V>)
+where
+    K: DBData,
+    V: DBData,
+{
+    let name_id = InputNameId::new(name.to_string());
+    assert!(
+        !circuit.cache_contains(&name_id),
+        "duplicate input name '{name}'"
+    );
+    circuit.cache_insert(name_id, ());
+
+    // Input handles are shared by all workers, so only register them once.
+    if let Some(runtime) = Runtime::runtime() {
+        if Runtime::worker_index() == 0 {
+            runtime
+                .local_store()
+                .entry(NamedInputsId)
+                .or_insert_with(HashMap::new)
+                .insert(name.to_string(), NamedInputHandle::new(handle.clone()));
+        }
+    }
+}
+
+/// `TypedMapKey` entry used to collect input handles created by
+/// [`RootCircuit::add_input_zset_named`] and
+/// [`RootCircuit::add_input_indexed_zset_named`] in a runtime.
+#[derive(Hash, PartialEq, Eq)]
+pub(crate) struct NamedInputsId;
+
+impl TypedMapKey<LocalStoreMarker> for NamedInputsId {
+    type Value = HashMap<String, NamedInputHandle>;
+}
+
+/// A type-erased [`CollectionHandle`] registered under a name.
+///
+/// Use [`downcast`](`Self::downcast`) to recover the typed handle.
+#[derive(Clone)]
+pub struct NamedInputHandle {
+    handle: Arc<dyn Any + Send + Sync>,
+    type_name: &'static str,
+}
+
+impl NamedInputHandle {
+    fn new<K, V>(handle: CollectionHandle<K, V>) -> Self
+    where
+        K: DBData,
+        V: DBData,
+    {
+        Self {
+            handle: Arc::new(handle),
+            type_name: type_name::<(K, V)>(),
+        }
+    }
+
+    /// Name of the `(key, value)` type accepted by the input handle.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns the typed input handle, or `None` if the handle doesn't
+    /// accept `(K, V)` pairs.
+    pub fn downcast<K, V>(&self) -> Option<&CollectionHandle<K, V>>
+    where
+        K: 'static,
+        V: 'static,
+    {
+        self.handle.downcast_ref()
+    }
+}
+
+impl Debug for NamedInputHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamedInputHandle")
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+/// Updates to multiple named inputs that take effect atomically.
+///
+/// Created by [`DBSPHandle::transaction`](`crate::DBSPHandle::transaction`).
+/// Updates added to the transaction via [`Self::input`] are buffered inside
+/// the transaction and pushed to the circuit when the transaction commits,
+/// so the next clock cycle observes either all or none of them.
+pub struct Transaction<'a> {
+    inputs: &'a HashMap<String, NamedInputHandle>,
+    updates: Vec<Box<dyn FnOnce() + 'a>>,
+}
+
+impl<'a> Transaction<'a> {
+    pub(crate) fn new(inputs: &'a HashMap<String, NamedInputHandle>) -> Self {
+        Self {
+            inputs,
+            updates: Vec::new(),
+        }
+    }
+
+    /// Returns a handle used to add updates to the input named `name` to the
+    /// transaction.
+    pub fn input<'t>(&'t mut self, name: &'t str) -> TransactionInput<'t, 'a> {
+        TransactionInput { txn: self, name }
+    }
+
+    /// Push all buffered updates to their input handles.
+    pub(crate) fn commit(self) {
+        for update in self.updates {
+            update();
+        }
+    }
+}
+
+/// Adds updates to a named input to a [`Transaction`].
+pub struct TransactionInput<'t, 'a> {
+    txn: &'t mut Transaction<'a>,
+    name: &'t str,
+}
+
+impl<'t, 'a> TransactionInput<'t, 'a> {
+    /// Buffer `(key, value)` pairs to be pushed to the input when the
+    /// transaction commits.  See [`CollectionHandle::append`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no input with this name or if the input doesn't
+    /// accept `(K, V)` pairs.
+    pub fn append<K, V>(self, vals: &mut Vec<(K, V)>)
+    where
+        K: DBData,
+        V: DBData,
+    {
+        let name = self.name;
+        let input = self
+            .txn
+            .inputs
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown input '{name}'"));
+        let mut handle = input
+            .downcast::<K, V>()
+            .unwrap_or_else(|| {
+                panic!(
+                    "input '{name}' accepts values of type '{}', not '{}'",
+                    input.type_name(),
+                    type_name::<(K, V)>()
+                )
+            })
+            .clone();
+
+        let mut vals = take(vals);
+        self.txn
+            .updates
+            .push(Box::new(move || handle.append(&mut vals)));
+    }
+
+    /// Buffer a single `(key, value)` pair to be pushed to the input when the
+    /// transaction commits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no input with this name or if the input doesn't
+    /// accept `(K, V)` pairs.
+    pub fn push<K, V>(self, k: K, v: V)
+    where
+        K: DBData,
+        V: DBData,
+    {
+        self.append(&mut vec![(k, v)]);
+    }
+}
+
 /// `TypedMapKey` entry used to share InputHandle objects across workers in a
 /// runtime. The first worker to create the handle will store it in the map,
 /// subsequent workers will get a clone of the same handle.
@@ -446,6 +729,15 @@ where
         take(&mut *self.value.lock().unwrap())
     }
 
+    /// Call `f` with a reference to the contents of the mailbox without
+    /// removing it.
+    pub(super) fn peek<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        f(&*self.value.lock().unwrap())
+    }
+
     fn update<F>(&self, f: F)
     where
         F: FnOnce(&mut T),
@@ -460,6 +752,7 @@ where
 
 struct InputHandleInternal<T> {
     mailbox: Vec<Mailbox<T>>,
+    watermark: AtomicU64,
 }
 
 impl<T> InputHandleInternal<T>
@@ -474,7 +767,10 @@ where
             mailbox.push(Mailbox::new());
         }
 
-        Self { mailbox }
+        Self {
+            mailbox,
+            watermark: AtomicU64::new(0),
+        }
     }
 
     fn set_for_worker(&self, worker: usize, v: T) {
@@ -570,6 +866,28 @@ where
     pub fn clear_for_all(&self) {
         self.0.clear_for_all();
     }
+
+    /// Set the input watermark.
+    ///
+    /// The watermark is an opaque value that identifies the position of
+    /// the client in its source of inputs, e.g., an offset in a log of
+    /// input records.  It is read by the circuit at the next clock cycle,
+    /// together with the contents of the mailboxes, and saved as part of
+    /// a [checkpoint](`crate::DBSPHandle::checkpoint`).  After the
+    /// circuit is [restored](`crate::Runtime::restore_circuit`) from a
+    /// checkpoint, [`Self::watermark`] returns the watermark of the last
+    /// clock cycle included in the checkpoint, so the client knows where
+    /// to resume feeding inputs from.
+    pub fn set_watermark(&self, watermark: u64) {
+        self.0.watermark.store(watermark, Ordering::Release);
+    }
+
+    /// Returns the current input watermark.
+    ///
+    /// See [`Self::set_watermark`].
+    pub fn watermark(&self) -> u64 {
+        self.0.watermark.load(Ordering::Acquire)
+    }
 }
 
 /// A handle used to write data to an input stream created by
@@ -585,9 +903,12 @@ where
 ///
 /// Internally, the handle manages an array of mailboxes, one for
 /// each worker thread. It automatically partitions updates across
-/// mailboxes in a round robin fashion.  At the start of each clock
-/// cycle, the circuit consumes updates buffered in each mailbox,
-/// leaving the mailbox empty.
+/// mailboxes in a round robin fashion, or based on the hash of the key
+/// for handles created by
+/// [`add_input_zset_sharded`](`RootCircuit::add_input_zset_sharded`) and
+/// [`add_input_indexed_zset_sharded`](`RootCircuit::add_input_indexed_zset_sharded`).
+/// At the start of each clock cycle, the circuit consumes updates buffered
+/// in each mailbox, leaving the mailbox empty.
 pub struct CollectionHandle<K, V> {
     buffers: Vec<Vec<(K, V)>>,
     input_handle: InputHandle<Vec<(K, V)>>,
@@ -596,6 +917,11 @@ pub struct CollectionHandle<K, V> {
     // of the key; however this is more efficient than doing it here, as
     // the work will be evenly split across workers.
     next_worker: AtomicUsize,
+    // Partition tuples based on the hash of the key instead of round robin.
+    // Set for handles created by `add_input_zset_sharded` and
+    // `add_input_indexed_zset_sharded`, whose output streams are marked as
+    // sharded.
+    sharded: bool,
 }
 
 impl<K, V> Clone for CollectionHandle<K, V>
@@ -605,7 +931,7 @@ where
 {
     fn clone(&self) -> Self {
         // Don't clone buffers.
-        Self::new(self.input_handle.clone())
+        Self::with_sharding(self.input_handle.clone(), self.sharded)
     }
 }
 
@@ -615,10 +941,15 @@ where
     V: DBData,
 {
     fn new(input_handle: InputHandle<Vec<(K, V)>>) -> Self {
+        Self::with_sharding(input_handle, false)
+    }
+
+    fn with_sharding(input_handle: InputHandle<Vec<(K, V)>>, sharded: bool) -> Self {
         Self {
             buffers: vec![Vec::new(); input_handle.0.mailbox.len()],
             input_handle,
             next_worker: AtomicUsize::new(0),
+            sharded,
         }
     }
 
@@ -627,11 +958,28 @@ where
         self.buffers.len()
     }
 
+    /// Returns `true` if the handle partitions tuples across workers based on
+    /// the hash of the key.
+    pub fn is_sharded(&self) -> bool {
+        self.sharded
+    }
+
+    // Worker that `k` belongs to.  Must match the partitioning used by the
+    // `shard` operator.
+    #[inline]
+    fn worker_for_key(&self, k: &K) -> usize {
+        default_hash(k) as usize % self.num_partitions()
+    }
+
     /// Push a single `(key,value)` pair to the input stream.
     pub fn push(&self, k: K, v: V) {
         let num_partitions = self.num_partitions();
 
-        if num_partitions > 1 {
+        if num_partitions > 1 && self.sharded {
+            let worker = self.worker_for_key(&k);
+            self.input_handle
+                .update_for_worker(worker, |tuples| tuples.push((k, v)));
+        } else if num_partitions > 1 {
             let next_worker = self.next_worker.fetch_add(1, Ordering::AcqRel);
             self.input_handle
                 .update_for_worker(next_worker % num_partitions, |tuples| tuples.push((k, v)));
@@ -665,22 +1013,29 @@ where
         let num_partitions = self.num_partitions();
 
         if num_partitions > 1 {
-            let mut next_worker = self.next_worker.load(Ordering::Acquire);
-            let partition_size = vals.len() / num_partitions;
+            if self.sharded {
+                for (k, v) in vals.drain(..) {
+                    let worker = self.worker_for_key(&k);
+                    self.buffers[worker].push((k, v));
+                }
+            } else {
+                let mut next_worker = self.next_worker.load(Ordering::Acquire);
+                let partition_size = vals.len() / num_partitions;
 
-            for worker in 0..num_partitions {
-                if worker == num_partitions - 1 {
-                    self.buffers[next_worker % num_partitions].append(vals);
-                } else {
-                    let len = vals.len();
-                    // Draining from the end should be more efficient as it doesn't
-                    // require memcpy'ing the tail of the vector to the front.
-                    self.buffers[next_worker % num_partitions]
-                        .extend(vals.drain(len - partition_size..));
+                for worker in 0..num_partitions {
+                    if worker == num_partitions - 1 {
+                        self.buffers[next_worker % num_partitions].append(vals);
+                    } else {
+                        let len = vals.len();
+                        // Draining from the end should be more efficient as it doesn't
+                        // require memcpy'ing the tail of the vector to the front.
+                        self.buffers[next_worker % num_partitions]
+                            .extend(vals.drain(len - partition_size..));
+                    }
+                    next_worker += 1;
                 }
-                next_worker += 1;
+                self.next_worker.store(next_worker, Ordering::Release);
             }
-            self.next_worker.store(next_worker, Ordering::Release);
 
             for worker in 0..num_partitions {
                 self.input_handle.update_for_worker(worker, |tuples| {
@@ -702,6 +1057,34 @@ where
         }
     }
 
+    /// Push `(key,value)` pairs produced by a fallible iterator, e.g., a
+    /// parser, to the input stream.
+    ///
+    /// Pushes all `Ok` pairs to the stream as a single [`Self::append`] and
+    /// returns the errors in the order in which they were encountered, so
+    /// that a few bad records don't cause the whole batch to be dropped.
+    ///
+    /// # Concurrency
+    ///
+    /// See [`Self::append`].
+    pub fn append_iter<I, E>(&mut self, iter: I) -> Vec<E>
+    where
+        I: IntoIterator<Item = Result<(K, V), E>>,
+    {
+        let mut vals = Vec::new();
+        let mut errors = Vec::new();
+
+        for item in iter {
+            match item {
+                Ok(val) => vals.push(val),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        self.append(&mut vals);
+        errors
+    }
+
     /// Clear all inputs buffered since the start of the last clock cycle.
     ///
     /// # Concurrency
@@ -721,6 +1104,20 @@ where
     pub fn clear_input(&self) {
         self.input_handle.set_for_all(Vec::new());
     }
+
+    /// Set the input watermark.
+    ///
+    /// See [`InputHandle::set_watermark`].
+    pub fn set_watermark(&self, watermark: u64) {
+        self.input_handle.set_watermark(watermark);
+    }
+
+    /// Returns the current input watermark.
+    ///
+    /// See [`InputHandle::set_watermark`].
+    pub fn watermark(&self) -> u64 {
+        self.input_handle.watermark()
+    }
 }
 
 pub trait HashFunc<K>: Fn(&K) -> u32 + Send + Sync {}
@@ -879,6 +1276,20 @@ where
     pub fn clear_input(&self) {
         self.input_handle.set_for_all(Vec::new());
     }
+
+    /// Set the input watermark.
+    ///
+    /// See [`InputHandle::set_watermark`].
+    pub fn set_watermark(&self, watermark: u64) {
+        self.input_handle.set_watermark(watermark);
+    }
+
+    /// Returns the current input watermark.
+    ///
+    /// See [`InputHandle::set_watermark`].
+    pub fn watermark(&self) -> u64 {
+        self.input_handle.watermark()
+    }
 }
 
 /// Source operator that injects data received via `InputHandle` to the circuit.
@@ -895,6 +1306,9 @@ where
 /// ```
 struct Input<IT, OT, F> {
     mailbox: Mailbox<IT>,
+    input_handle: InputHandle<IT>,
+    // Watermark read from `input_handle` at the last clock cycle.
+    watermark: u64,
     input_func: F,
     phantom: PhantomData<OT>,
 }
@@ -909,6 +1323,8 @@ where
 
         let input = Self {
             mailbox,
+            input_handle: handle.clone(),
+            watermark: 0,
             input_func,
             phantom: PhantomData,
         };
@@ -919,7 +1335,7 @@ where
 
 impl<IT, OT, F> Operator for Input<IT, OT, F>
 where
-    IT: 'static,
+    IT: Default + Clone + Send + 'static,
     OT: 'static,
     F: 'static,
 {
@@ -930,15 +1346,30 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         false
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        if self.watermark == 0 {
+            Ok(None)
+        } else {
+            encode_state(&self.watermark).map(Some)
+        }
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        self.watermark = decode_state(state)?;
+        self.input_handle.set_watermark(self.watermark);
+        Ok(())
+    }
 }
 
 impl<IT, OT, F> SourceOperator<OT> for Input<IT, OT, F>
 where
-    IT: Default + 'static,
+    IT: Default + Clone + Send + 'static,
     OT: 'static,
     F: Fn(IT) -> OT + 'static,
 {
     fn eval(&mut self) -> OT {
+        self.watermark = self.input_handle.watermark();
         let v = self.mailbox.take();
         (self.input_func)(v)
     }
@@ -947,7 +1378,7 @@ where
 #[cfg(test)]
 mod test {
     use crate::{
-        indexed_zset,
+        default_hash, indexed_zset,
         trace::{cursor::Cursor, BatchReader},
         zset, CollectionHandle, InputHandle, OrdIndexedZSet, OrdZSet, RootCircuit, Runtime,
         UpsertHandle,
@@ -1099,6 +1530,22 @@ mod test {
         circuit.step().unwrap();
     }
 
+    #[test]
+    fn zset_append_iter_test() {
+        let (circuit, mut input_handle) =
+            RootCircuit::build(move |circuit| zset_test_circuit(circuit)).unwrap();
+
+        for vec in input_vecs().into_iter() {
+            let errors = input_handle.append_iter(
+                vec.into_iter()
+                    .map(Ok)
+                    .chain(once(Err("bad record".to_string()))),
+            );
+            assert_eq!(errors, vec!["bad record".to_string()]);
+            circuit.step().unwrap();
+        }
+    }
+
     fn zset_test_mt(workers: usize) {
         let (mut dbsp, mut input_handle) =
             Runtime::init_circuit(workers, |circuit| zset_test_circuit(circuit)).unwrap();
@@ -1136,6 +1583,62 @@ mod test {
         zset_test_mt(4);
     }
 
+    fn sharded_zset_test_mt(workers: usize) {
+        let (mut dbsp, mut input_handle) = Runtime::init_circuit(workers, |circuit| {
+            let (stream, handle) = circuit.add_input_zset_sharded::<usize, isize>();
+
+            // The stream is already sharded, so `shard` is a no-op.
+            assert_eq!(stream.shard().origin_node_id(), stream.origin_node_id());
+
+            stream.inspect(|batch| {
+                let num_workers = Runtime::runtime().unwrap().num_workers();
+                let mut cursor = batch.cursor();
+                while cursor.key_valid() {
+                    assert_eq!(
+                        default_hash(cursor.key()) as usize % num_workers,
+                        Runtime::worker_index()
+                    );
+                    cursor.step_key();
+                }
+            });
+
+            let mut expected_batches = input_batches().into_iter();
+            stream.gather(0).inspect(move |batch| {
+                if Runtime::worker_index() == 0 {
+                    assert_eq!(batch, &expected_batches.next().unwrap())
+                }
+            });
+
+            handle
+        })
+        .unwrap();
+
+        assert!(input_handle.is_sharded());
+
+        for (round, mut vec) in input_vecs().into_iter().enumerate() {
+            if round % 2 == 0 {
+                input_handle.append(&mut vec);
+            } else {
+                for (k, w) in vec.into_iter() {
+                    input_handle.push(k, w);
+                }
+            }
+            dbsp.step().unwrap();
+        }
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn sharded_zset_test_mt1() {
+        sharded_zset_test_mt(1);
+    }
+
+    #[test]
+    fn sharded_zset_test_mt4() {
+        sharded_zset_test_mt(4);
+    }
+
     fn input_indexed_batches() -> Vec<OrdIndexedZSet<usize, usize, isize>> {
         vec![
             indexed_zset! { 1 => {1 => 1, 2 => 1}, 2 => { 3 => 1 }, 3 => {4 => -1, 5 => 5} },
@@ -1327,6 +1830,8 @@ mod test {
                 (4, Some(5)),
             ],
             vec![(1, Some(5)), (1, Some(6)), (3, None), (4, Some(6))],
+            // Upserting the current value is a no-op.
+            vec![(1, Some(6)), (2, Some(2)), (3, None)],
         ]
     }
 
@@ -1335,6 +1840,7 @@ mod test {
             indexed_zset! { 1 => {2 => 1},  3 => {3 => 1}},
             indexed_zset! { 1 => {2 => -1}, 2 => {2 => 1}, 3 => {3 => -1, 4 => 1}, 4 => {5 => 1}},
             indexed_zset! { 1 => {6 => 1},  3 => {4 => -1}, 4 => {5 => -1, 6 => 1}},
+            indexed_zset! {},
         ]
     }
 
@@ -1406,4 +1912,14 @@ mod test {
     fn map_test_mt4() {
         map_test_mt(4);
     }
+
+    // Input names are checked with and without a runtime.
+    #[test]
+    #[should_panic(expected = "duplicate input name 'a'")]
+    fn duplicate_input_name() {
+        let _ = RootCircuit::build(|circuit| {
+            circuit.add_input_zset_named::<usize, isize>("a");
+            circuit.add_input_indexed_zset_named::<usize, usize, isize>("a");
+        });
+    }
 }
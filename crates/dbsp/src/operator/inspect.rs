@@ -3,7 +3,7 @@
 
 use crate::circuit::{
     operator_traits::{Operator, UnaryOperator},
-    Circuit, Scope, Stream,
+    CheckpointError, Circuit, Scope, Stream,
 };
 use std::{borrow::Cow, marker::PhantomData};
 
@@ -78,6 +78,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T, F> UnaryOperator<T, T> for Inspect<T, F>
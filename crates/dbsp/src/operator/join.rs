@@ -1,11 +1,13 @@
 //! Relational join operator.
 
 use crate::{
-    algebra::{IndexedZSet, Lattice, MulByRef, PartialOrder, ZRingValue, ZSet},
+    algebra::{
+        AddAssignByRef, HasZero, IndexedZSet, Lattice, MulByRef, PartialOrder, ZRingValue, ZSet,
+    },
     circuit::{
         metadata::{MetaItem, OperatorLocation, OperatorMeta},
         operator_traits::{BinaryOperator, Operator},
-        Circuit, GlobalNodeId, RootCircuit, Scope, Stream, WithClock,
+        CheckpointError, Circuit, GlobalNodeId, RootCircuit, Scope, Stream, WithClock,
     },
     circuit_cache_key,
     operator::FilterMap,
@@ -13,6 +15,7 @@ use crate::{
     trace::{cursor::Cursor as TraceCursor, Batch, BatchReader, Batcher, Builder, Spine, Trace},
     DBData, DBTimestamp, OrdIndexedZSet, OrdZSet,
 };
+use bincode::{Decode, Encode};
 use size_of::{Context, SizeOf};
 use std::{
     borrow::Cow,
@@ -24,6 +27,7 @@ use std::{
     panic::Location,
 };
 
+circuit_cache_key!(SemijoinId<C, D>((GlobalNodeId, GlobalNodeId) => Stream<C, D>));
 circuit_cache_key!(AntijoinId<C, D>((GlobalNodeId, GlobalNodeId) => Stream<C, D>));
 
 impl<C, I1> Stream<C, I1>
@@ -99,6 +103,41 @@ where
         )
     }
 
+    /// Join two streams of batches using sort-merge join.
+    ///
+    /// Computes the same result as [`Self::stream_join`], but walks the keys
+    /// of both input batches in lockstep instead of searching one batch for
+    /// each key of the other.  This is cheaper when the two batches have
+    /// similar sets of keys, e.g., when both inputs are generated from the
+    /// same range of keys, and more expensive when one batch is much smaller
+    /// than the other.
+    ///
+    /// Input streams that are already partitioned across workers, i.e.,
+    /// streams produced by [`shard`](`Self::shard`) or asserted to be
+    /// partitioned with [`mark_sharded`](`Self::mark_sharded`), are joined
+    /// directly.  Other input streams are sharded first.
+    #[track_caller]
+    #[allow(clippy::type_complexity)]
+    pub fn merge_join<F, I2, V>(
+        &self,
+        other: &Stream<C, I2>,
+        join: F,
+    ) -> Stream<C, OrdZSet<V, <I1::R as MulByRef<I2::R>>::Output>>
+    where
+        I1: Batch<Time = ()> + Send,
+        I2: Batch<Key = I1::Key, Time = ()> + Send,
+        I1::R: MulByRef<I2::R>,
+        <I1::R as MulByRef<I2::R>>::Output: DBData + ZRingValue,
+        F: Fn(&I1::Key, &I1::Val, &I2::Val) -> V + 'static,
+        V: DBData,
+    {
+        self.circuit().add_binary_operator(
+            MergeJoin::new(join, Location::caller()),
+            &self.shard(),
+            &other.shard(),
+        )
+    }
+
     fn stream_join_inner<F, I2, Z>(
         &self,
         other: &Stream<C, I2>,
@@ -153,6 +192,43 @@ impl<I1> Stream<RootCircuit, I1> {
             .stream_join_inner(&right, join_func.clone(), Location::caller())
             .plus(&left.stream_join_inner(&right.integrate_trace(), join_func, Location::caller()))
     }
+
+    /// Like [`join_incremental`](`Self::join_incremental`), but integrates
+    /// the inputs into [`PersistentSpine`](`crate::trace::PersistentSpine`)s,
+    /// which spill cold batches to disk (see
+    /// [`spilling_integrate_trace`](`Stream::spilling_integrate_trace`)).
+    ///
+    /// Use this instead of [`join`](`crate::circuit::Stream::join`) when the
+    /// integrals of the inputs may not fit in memory.
+    #[track_caller]
+    pub fn join_incremental_spilling<F, I2, Z>(
+        &self,
+        other: &Stream<RootCircuit, I2>,
+        join_func: F,
+    ) -> Stream<RootCircuit, Z>
+    where
+        I1: IndexedZSet + Send,
+        I1::Key: Encode + Decode,
+        I1::Val: Encode + Decode,
+        I1::R: Encode + Decode,
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+        I2::Val: Encode + Decode,
+        F: Clone + Fn(&I1::Key, &I1::Val, &I2::Val) -> Z::Key + 'static,
+        Z: ZSet<R = I1::R>,
+        Z::R: ZRingValue,
+    {
+        let left = self.shard();
+        let right = other.shard();
+
+        left.spilling_integrate_trace()
+            .delay_trace()
+            .stream_join_inner(&right, join_func.clone(), Location::caller())
+            .plus(&left.stream_join_inner(
+                &right.spilling_integrate_trace(),
+                join_func,
+                Location::caller(),
+            ))
+    }
 }
 
 impl<C, I1> Stream<C, I1>
@@ -212,6 +288,31 @@ where
         self.join_generic(other, join_func)
     }
 
+    /// Incrementally join two streams of batches, producing an output stream
+    /// indexed by the join key.
+    ///
+    /// This is a shortcut for [`Self::join_index`] with a join function that
+    /// returns `(key, join_func(key, v1, v2))`, which saves users from
+    /// threading the key through the output value.  Since the output is
+    /// indexed by the same key as the inputs, it is sharded in the same way,
+    /// so that per-key operators applied to it don't need to re-shard it.
+    #[track_caller]
+    pub fn join_keyed<I2, F, V>(
+        &self,
+        other: &Stream<C, I2>,
+        join_func: F,
+    ) -> Stream<C, OrdIndexedZSet<I1::Key, V, I1::R>>
+    where
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+        F: Fn(&I1::Key, &I1::Val, &I2::Val) -> V + Clone + 'static,
+        V: DBData,
+    {
+        self.join_generic(other, move |k, v1, v2| {
+            once((k.clone(), join_func(k, v1, v2)))
+        })
+        .mark_sharded()
+    }
+
     /// Like [`Self::join_index`], but can return any indexed Z-set type.
     #[track_caller]
     pub fn join_generic<I2, F, Z, It>(&self, other: &Stream<C, I2>, join_func: F) -> Stream<C, Z>
@@ -291,33 +392,281 @@ where
         left.plus(&right)
     }
 
-    /// Incremental anti-join operator.
+    /// Incremental semi-join operator with set semantics.
+    ///
+    /// Returns indexed Z-set consisting of the distinct `(key, value)` pairs
+    /// in `self` whose keys are present in `other`.  Each output pair has
+    /// weight `1`, regardless of its weight in `self`.  Use
+    /// [`semijoin_multiset`](`Self::semijoin_multiset`) to preserve
+    /// multiplicities of `self`.
+    pub fn semijoin<I2>(&self, other: &Stream<C, I2>) -> Stream<C, I1>
+    where
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+    {
+        self.semijoin_multiset(other).distinct()
+    }
+
+    /// Incremental semi-join operator with multiset semantics.
     ///
     /// Returns indexed Z-set consisting of the contents of `self`,
-    /// excluding keys that are not present in `other`.
-    pub fn antijoin<I2>(&self, other: &Stream<C, I2>) -> Stream<C, I1>
+    /// excluding keys that are not present in `other`.  Unlike
+    /// [`semijoin`](`Self::semijoin`), each `(key, value)` pair keeps its
+    /// weight in `self`: a pair with weight `3` in `self` whose key occurs in
+    /// `other` has weight `3` in the output.  The weights of `other` don't
+    /// matter, only the presence of keys: a key is present if the total
+    /// weight of its values in `other` is positive.
+    ///
+    /// Use this version when the output feeds operators that depend on exact
+    /// multiplicities, e.g., `COUNT` or `SUM` aggregates.
+    pub fn semijoin_multiset<I2>(&self, other: &Stream<C, I2>) -> Stream<C, I1>
     where
         I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
     {
         self.circuit()
             .cache_get_or_insert_with(
-                AntijoinId::new((
+                SemijoinId::new((
                     self.origin_node_id().clone(),
                     other.origin_node_id().clone(),
                 )),
                 move || {
                     let stream1 = self.shard();
-                    let stream2 = other.distinct().shard();
+                    let stream2 = other.present_keys();
 
                     stream1
-                        .minus(&stream1.join_generic(&stream2, |k, v1, _v2| {
+                        .join_generic(&stream2, |k, v1, _v2| {
                             std::iter::once((k.clone(), v1.clone()))
-                        }))
+                        })
+                        .mark_sharded()
+                },
+            )
+            .clone()
+    }
+
+    /// Incremental anti-join operator.
+    ///
+    /// Returns indexed Z-set consisting of the contents of `self`,
+    /// excluding keys that are present in `other`.  A key is present in
+    /// `other` if the total weight of its values is positive; keys whose
+    /// weights add up to zero, e.g., because they have been inserted and
+    /// deleted, count as absent.  Each `(key, value)` pair keeps its weight
+    /// in `self`.
+    ///
+    /// This operator is incremental and works in nested scopes.  It is
+    /// computed as `self - semijoin(self, keys)`, where `keys` is the set of
+    /// keys present in `other`, and the semi-join is evaluated the same way
+    /// as [`join`](`Self::join`): each input is joined with the delayed
+    /// trace of the other input.
+    #[track_caller]
+    pub fn antijoin<I2>(&self, other: &Stream<C, I2>) -> Stream<C, I1>
+    where
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+    {
+        let location = Location::caller();
+
+        self.circuit()
+            .cache_get_or_insert_with(
+                AntijoinId::new((
+                    self.origin_node_id().clone(),
+                    other.origin_node_id().clone(),
+                )),
+                move || {
+                    let left = self.shard();
+
+                    let keys = other.present_keys();
+
+                    let left_trace = left.trace::<Spine<
+                        <<C as WithClock>::Time as Timestamp>::OrdValBatch<I1::Key, I1::Val, I1::R>,
+                    >>();
+                    let keys_trace = keys.trace::<Spine<
+                        <<C as WithClock>::Time as Timestamp>::OrdValBatch<I1::Key, (), I1::R>,
+                    >>();
+
+                    let matched_left = self.circuit().add_binary_operator(
+                        JoinTrace::new(
+                            |k: &I1::Key, v: &I1::Val, _: &()| once((k.clone(), v.clone())),
+                            location,
+                            self.circuit().clone(),
+                        ),
+                        &left,
+                        &keys_trace,
+                    );
+
+                    let matched_right = self.circuit().add_binary_operator(
+                        JoinTrace::new(
+                            |k: &I1::Key, _: &(), v: &I1::Val| once((k.clone(), v.clone())),
+                            location,
+                            self.circuit().clone(),
+                        ),
+                        &keys,
+                        &left_trace.delay_trace(),
+                    );
+
+                    left.minus(&matched_left.plus(&matched_right))
                         .mark_sharded()
                 },
             )
             .clone()
     }
+
+    /// Incremental anti-join operator with multiset semantics.
+    ///
+    /// Same as [`antijoin`](`Self::antijoin`), which preserves the weights
+    /// of `self`.  This is the complement of
+    /// [`semijoin_multiset`](`Self::semijoin_multiset`): the two outputs add
+    /// up to `self`.
+    #[track_caller]
+    pub fn antijoin_multiset<I2>(&self, other: &Stream<C, I2>) -> Stream<C, I1>
+    where
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+    {
+        self.antijoin(other)
+    }
+
+    /// Incremental left outer join.
+    ///
+    /// Computes the same `(key, value)` pairs as [`join_index`], passing
+    /// `Some(v2)` to `join_func` for each matching value `v2` in `other`,
+    /// and, additionally, for each `(key, v1)` pair in `self` whose key is
+    /// not present in `other`, outputs `join_func(key, v1, None)`.  A key is
+    /// present in `other` if the total weight of its values is positive
+    /// (see [`antijoin`](`Self::antijoin`)).
+    ///
+    /// The output is updated incrementally: when a key first appears in
+    /// `other`, the `None`-padded rows of the key are retracted and replaced
+    /// with joined rows; when the key disappears from `other`, the padded
+    /// rows are inserted again.
+    ///
+    /// # Weights
+    ///
+    /// A pair of matching records with weights `w1` and `w2` in `self` and
+    /// `other` respectively produces an output record with weight `w1 * w2`,
+    /// as in [`join`](`Self::join`).  A padded record keeps the weight `w1`
+    /// of its input record.
+    ///
+    /// [`join_index`]: Self::join_index
+    #[track_caller]
+    pub fn join_left<I2, F, K, V>(
+        &self,
+        other: &Stream<C, I2>,
+        join_func: F,
+    ) -> Stream<C, OrdIndexedZSet<K, V, I1::R>>
+    where
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+        F: Fn(&I1::Key, &I1::Val, Option<&I2::Val>) -> (K, V) + Clone + 'static,
+        K: DBData,
+        V: DBData,
+    {
+        let join_func_left = join_func.clone();
+
+        let center = self.join_index(other, move |k, v1, v2| once(join_func(k, v1, Some(v2))));
+        let left = self
+            .antijoin(other)
+            .map_pairs(move |k, v1| join_func_left(k, v1, None));
+
+        center.plus(&left)
+    }
+
+    /// Incremental right outer join.
+    ///
+    /// Symmetric to [`join_left`](`Self::join_left`): outputs the joined
+    /// rows of both inputs, and, for each `(key, v2)` pair in `other` whose
+    /// key is not present in `self`, outputs `join_func(key, None, v2)`.
+    /// See [`join_left`](`Self::join_left`) for the treatment of weights.
+    #[track_caller]
+    pub fn join_right<I2, F, K, V>(
+        &self,
+        other: &Stream<C, I2>,
+        join_func: F,
+    ) -> Stream<C, OrdIndexedZSet<K, V, I1::R>>
+    where
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+        F: Fn(&I1::Key, Option<&I1::Val>, &I2::Val) -> (K, V) + Clone + 'static,
+        K: DBData,
+        V: DBData,
+    {
+        other.join_left(self, move |k, v2, v1| join_func(k, v1, v2))
+    }
+
+    /// Incremental full outer join.
+    ///
+    /// Outputs the joined rows of both inputs, the `None`-padded rows of
+    /// `self` computed by [`join_left`](`Self::join_left`), and the
+    /// `None`-padded rows of `other` computed by
+    /// [`join_right`](`Self::join_right`).  `join_func` is never invoked
+    /// with two `None` arguments.  See [`join_left`](`Self::join_left`) for
+    /// the treatment of weights.
+    #[track_caller]
+    pub fn join_full<I2, F, K, V>(
+        &self,
+        other: &Stream<C, I2>,
+        join_func: F,
+    ) -> Stream<C, OrdIndexedZSet<K, V, I1::R>>
+    where
+        I2: IndexedZSet<Key = I1::Key, R = I1::R> + Send,
+        F: Fn(&I1::Key, Option<&I1::Val>, Option<&I2::Val>) -> (K, V) + Clone + 'static,
+        K: DBData,
+        V: DBData,
+    {
+        let join_func_right = join_func.clone();
+
+        let left = self.join_left(other, move |k, v1, v2| join_func(k, Some(v1), v2));
+        let right = other
+            .antijoin(self)
+            .map_pairs(move |k, v2| join_func_right(k, None, Some(v2)));
+
+        left.plus(&right)
+    }
+
+    /// Applies `func` to each `(key, value)` pair in `self`, preserving its
+    /// weight.  Used to pad unmatched rows in outer joins.
+    fn map_pairs<F, K, V>(&self, func: F) -> Stream<C, OrdIndexedZSet<K, V, I1::R>>
+    where
+        F: Fn(&I1::Key, &I1::Val) -> (K, V) + 'static,
+        K: DBData,
+        V: DBData,
+    {
+        self.apply(move |batch: &I1| {
+            let mut tuples = Vec::with_capacity(batch.len());
+            let mut cursor = batch.cursor();
+
+            while cursor.key_valid() {
+                while cursor.val_valid() {
+                    tuples.push((func(cursor.key(), cursor.val()), cursor.weight()));
+                    cursor.step_val();
+                }
+                cursor.step_key();
+            }
+
+            OrdIndexedZSet::from_tuples((), tuples)
+        })
+    }
+
+    /// Returns the set of keys whose values have positive total weight in
+    /// `self`, used to compute semi-joins and anti-joins.
+    fn present_keys(&self) -> Stream<C, OrdIndexedZSet<I1::Key, (), I1::R>> {
+        self.apply(|batch: &I1| {
+            let mut builder = <OrdIndexedZSet<I1::Key, (), I1::R> as Batch>::Builder::with_capacity(
+                (),
+                batch.key_count(),
+            );
+            let mut cursor = batch.cursor();
+
+            while cursor.key_valid() {
+                let mut weight = I1::R::zero();
+                while cursor.val_valid() {
+                    weight.add_assign_by_ref(&cursor.weight());
+                    cursor.step_val();
+                }
+                if !weight.is_zero() {
+                    builder.push(((cursor.key().clone(), ()), weight));
+                }
+                cursor.step_key();
+            }
+
+            builder.done()
+        })
+        .distinct()
+    }
 }
 
 impl<C, Z> Stream<C, Z>
@@ -423,6 +772,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<F, I1, I2, Z> BinaryOperator<I1, I2, Z> for Join<F, I1, I2, Z>
@@ -509,6 +862,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<F, I1, I2, Z> BinaryOperator<I1, I2, Z> for MonotonicJoin<F, I1, I2, Z>
@@ -559,6 +916,97 @@ where
     }
 }
 
+/// Sort-merge join of two batches.
+///
+/// See [`Stream::merge_join`](`crate::circuit::Stream::merge_join`).
+pub struct MergeJoin<F, I1, I2, Z> {
+    join_func: F,
+    location: &'static Location<'static>,
+    _types: PhantomData<(I1, I2, Z)>,
+}
+
+impl<F, I1, I2, Z> MergeJoin<F, I1, I2, Z> {
+    pub fn new(join_func: F, location: &'static Location<'static>) -> Self {
+        Self {
+            join_func,
+            location,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<F, I1, I2, Z> Operator for MergeJoin<F, I1, I2, Z>
+where
+    I1: 'static,
+    I2: 'static,
+    F: 'static,
+    Z: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("MergeJoin")
+    }
+
+    fn location(&self) -> OperatorLocation {
+        Some(self.location)
+    }
+
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        true
+    }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
+}
+
+impl<F, I1, I2, Z> BinaryOperator<I1, I2, Z> for MergeJoin<F, I1, I2, Z>
+where
+    I1: BatchReader<Time = ()>,
+    I1::R: MulByRef<I2::R, Output = Z::R>,
+    I2: BatchReader<Key = I1::Key, Time = ()>,
+    F: Fn(&I1::Key, &I1::Val, &I2::Val) -> Z::Key + 'static,
+    Z: ZSet,
+{
+    fn eval(&mut self, i1: &I1, i2: &I2) -> Z {
+        let mut cursor1 = i1.cursor();
+        let mut cursor2 = i2.cursor();
+
+        // Choose capacity heuristically.
+        let mut batch = Vec::with_capacity(min(i1.len(), i2.len()));
+
+        while cursor1.key_valid() && cursor2.key_valid() {
+            match cursor1.key().cmp(cursor2.key()) {
+                Ordering::Less => cursor1.step_key(),
+                Ordering::Greater => cursor2.step_key(),
+                Ordering::Equal => {
+                    while cursor1.val_valid() {
+                        let w1 = cursor1.weight();
+                        let v1 = cursor1.val();
+                        while cursor2.val_valid() {
+                            let w2 = cursor2.weight();
+                            let v2 = cursor2.val();
+
+                            batch.push((
+                                (self.join_func)(cursor1.key(), v1, v2),
+                                w1.mul_by_ref(&w2),
+                            ));
+                            cursor2.step_val();
+                        }
+
+                        cursor2.rewind_vals();
+                        cursor1.step_val();
+                    }
+
+                    cursor1.step_key();
+                    cursor2.step_key();
+                }
+            }
+        }
+
+        Z::from_keys((), batch)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Default)]
 struct JoinStats {
     lhs_tuples: usize,
@@ -717,6 +1165,16 @@ where
                 .keys()
                 .all(|time| !time.less_equal(&epoch_end))
     }
+
+    // Updates scheduled for future timestamps only exist in nested circuits,
+    // where they span parent clock cycles, and are not serializable.
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        if self.output_batchers.is_empty() {
+            Ok(None)
+        } else {
+            Err(CheckpointError::Unsupported)
+        }
+    }
 }
 
 impl<F, I, T, Z, It, Clk> BinaryOperator<I, T, Z> for JoinTrace<F, I, T, Z, It, Clk>
@@ -751,6 +1209,10 @@ where
         while index_cursor.key_valid() && trace_cursor.key_valid() {
             match index_cursor.key().cmp(trace_cursor.key()) {
                 Ordering::Less => index_cursor.seek_key(trace_cursor.key()),
+                // Skip keys that are definitely not in the trace without seeking.
+                Ordering::Greater if !trace.maybe_contains_key(index_cursor.key()) => {
+                    index_cursor.step_key()
+                }
                 Ordering::Greater => trace_cursor.seek_key(index_cursor.key()),
                 Ordering::Equal => {
                     //println!("key: {}", index_cursor.key(index));
@@ -871,15 +1333,14 @@ mod test {
         operator::{DelayedFeedback, FilterMap, Generator},
         trace::{
             ord::{OrdIndexedZSet, OrdZSet},
-            Batch,
+            Batch, BatchReader, SpillPolicy,
         },
-        zset, Circuit, DBTimestamp, RootCircuit, Runtime, Stream, Timestamp,
+        zset, Circuit, DBTimestamp, RootCircuit, Runtime, RuntimeConfig, Stream, Timestamp,
     };
     use size_of::SizeOf;
     use std::{
         fmt::{Display, Formatter},
         hash::Hash,
-        sync::{Arc, Mutex},
         vec,
     };
 
@@ -916,7 +1377,7 @@ mod test {
                 zset! {},
             ]
             .into_iter();
-            let mut outputs = vec![
+            let outputs_vec = vec![
                 zset! {
                     (2, "c g".to_string()) => 9,
                     (2, "c h".to_string()) => 12,
@@ -933,8 +1394,10 @@ mod test {
                 zset! {},
                 zset! {},
                 zset! {},
-            ]
-            .into_iter();
+            ];
+
+            let mut outputs = outputs_vec.clone().into_iter();
+            let mut merge_outputs = outputs_vec.into_iter();
             let inc_outputs_vec = vec![
                 zset! {
                     (2, "c g".to_string()) => 9,
@@ -962,7 +1425,8 @@ mod test {
             ];
 
             let mut inc_outputs = inc_outputs_vec.clone().into_iter();
-            let mut inc_outputs2 = inc_outputs_vec.into_iter();
+            let mut inc_outputs2 = inc_outputs_vec.clone().into_iter();
+            let mut spilling_outputs = inc_outputs_vec.into_iter();
 
             let index1: Stream<_, OrdIndexedZSet<usize, String, isize>> = circuit
                 .add_source(Generator::new(move || {
@@ -990,6 +1454,14 @@ mod test {
                         assert_eq!(fm, &outputs.next().unwrap())
                     }
                 });
+            index1
+                .merge_join(&index2, |&k: &usize, s1, s2| (k, format!("{} {}", s1, s2)))
+                .gather(0)
+                .inspect(move |fm: &OrdZSet<(usize, String), _>| {
+                    if Runtime::worker_index() == 0 {
+                        assert_eq!(fm, &merge_outputs.next().unwrap())
+                    }
+                });
             index1
                 .join_incremental(&index2, |&k: &usize, s1, s2| (k, format!("{} {}", s1, s2)))
                 .gather(0)
@@ -1007,6 +1479,17 @@ mod test {
                         assert_eq!(fm, &inc_outputs2.next().unwrap())
                     }
                 });
+
+            index1
+                .join_incremental_spilling(&index2, |&k: &usize, s1, s2| {
+                    (k, format!("{} {}", s1, s2))
+                })
+                .gather(0)
+                .inspect(move |fm: &OrdZSet<(usize, String), _>| {
+                    if Runtime::worker_index() == 0 {
+                        assert_eq!(fm, &spilling_outputs.next().unwrap())
+                    }
+                });
         })
         .unwrap()
         .0;
@@ -1032,6 +1515,20 @@ mod test {
         do_join_test_mt(16);
     }
 
+    // Spill every batch inserted in a `PersistentSpine` to disk.
+    #[test]
+    fn join_test_spill() {
+        let directory =
+            std::env::temp_dir().join(format!("dbsp-join_test_spill-{}", std::process::id()));
+        let config = RuntimeConfig::default()
+            .with_spill_policy(SpillPolicy::new(0, &directory).with_page_size(2));
+
+        Runtime::run_with_config(2, config, join_test)
+            .join()
+            .unwrap();
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
     // Compute pairwise reachability relation between graph nodes as the
     // transitive closure of the edge relation.
     #[test]
@@ -1332,23 +1829,91 @@ mod test {
     }
 
     #[test]
-    fn antijoin_test() {
-        let output = Arc::new(Mutex::new(OrdIndexedZSet::empty(())));
-        let output_clone = output.clone();
+    fn join_keyed_test() {
+        let (mut circuit, (mut input1, mut input2, keyed, indexed)) =
+            Runtime::init_circuit(4, move |circuit| {
+                let (input1, input_handle1) =
+                    circuit.add_input_indexed_zset::<usize, usize, isize>();
+                let (input2, input_handle2) =
+                    circuit.add_input_indexed_zset::<usize, String, isize>();
+
+                let keyed = input1.join_keyed(&input2, |_k, v1, v2| (*v1, v2.clone()));
+                assert!(keyed.has_sharded_version());
+
+                let indexed = input1
+                    .join(&input2, |k, v1, v2| (*k, (*v1, v2.clone())))
+                    .index();
+
+                (
+                    input_handle1,
+                    input_handle2,
+                    keyed.output(),
+                    indexed.output(),
+                )
+            })
+            .unwrap();
+
+        input1.append(&mut vec![(1, (0, 1)), (1, (1, 2)), (2, (0, 1))]);
+        input2.append(&mut vec![
+            (1, ("a".to_string(), 1)),
+            (3, ("b".to_string(), 1)),
+        ]);
+        circuit.step().unwrap();
+        let expected =
+            indexed_zset! { 1 => { (0, "a".to_string()) => 1, (1, "a".to_string()) => 2 } };
+        assert_eq!(keyed.consolidate(), expected);
+        assert_eq!(indexed.consolidate(), expected);
 
-        let (mut circuit, (mut input1, mut input2)) = Runtime::init_circuit(4, move |circuit| {
-            let (input1, input_handle1) = circuit.add_input_indexed_zset::<usize, usize, isize>();
-            let (input2, input_handle2) = circuit.add_input_indexed_zset::<usize, usize, isize>();
+        input1.append(&mut vec![(3, (5, 1)), (1, (1, -2))]);
+        input2.append(&mut vec![(2, ("c".to_string(), -1))]);
+        circuit.step().unwrap();
+        let expected = indexed_zset! {
+            1 => { (1, "a".to_string()) => -2 },
+            2 => { (0, "c".to_string()) => -1 },
+            3 => { (5, "b".to_string()) => 1 }
+        };
+        assert_eq!(keyed.consolidate(), expected);
+        assert_eq!(indexed.consolidate(), expected);
+
+        circuit.kill().unwrap();
+    }
 
-            input1.antijoin(&input2).gather(0).inspect(move |batch| {
-                if Runtime::worker_index() == 0 {
-                    *output_clone.lock().unwrap() = batch.clone();
+    // `join_keyed` saves the operator that re-indexes the output of `join`.
+    #[test]
+    fn join_keyed_num_nodes() {
+        fn num_nodes(keyed: bool) -> usize {
+            RootCircuit::build(move |circuit| {
+                let (input1, _) = circuit.add_input_indexed_zset::<usize, usize, isize>();
+                let (input2, _) = circuit.add_input_indexed_zset::<usize, usize, isize>();
+
+                if keyed {
+                    input1.join_keyed(&input2, |_k, v1, v2| (*v1, *v2));
+                } else {
+                    input1.join(&input2, |k, v1, v2| (*k, (*v1, *v2))).index();
                 }
-            });
 
-            (input_handle1, input_handle2)
-        })
-        .unwrap();
+                circuit.num_nodes()
+            })
+            .unwrap()
+            .1
+        }
+
+        assert_eq!(num_nodes(true) + 1, num_nodes(false));
+    }
+
+    fn antijoin_test(workers: usize) {
+        let (mut circuit, (mut input1, mut input2, output)) =
+            Runtime::init_circuit(workers, move |circuit| {
+                let (input1, input_handle1) =
+                    circuit.add_input_indexed_zset::<usize, usize, isize>();
+                let (input2, input_handle2) =
+                    circuit.add_input_indexed_zset::<usize, usize, isize>();
+
+                let output = input1.antijoin(&input2).output();
+
+                (input_handle1, input_handle2, output)
+            })
+            .unwrap();
 
         input1.append(&mut vec![
             (1, (0, 1)),
@@ -1358,29 +1923,397 @@ mod test {
         ]);
         circuit.step().unwrap();
         assert_eq!(
-            &*output.lock().unwrap(),
-            &indexed_zset! { 1 => { 0 => 1, 1 => 2}, 2 => { 0 => 1, 1 => 1 } }
+            output.consolidate(),
+            indexed_zset! { 1 => { 0 => 1, 1 => 2}, 2 => { 0 => 1, 1 => 1 } }
         );
 
         input1.append(&mut vec![(3, (1, 1))]);
         circuit.step().unwrap();
-        assert_eq!(&*output.lock().unwrap(), &indexed_zset! { 3 => { 1 => 1 } });
+        assert_eq!(output.consolidate(), indexed_zset! { 3 => { 1 => 1 } });
 
         input2.append(&mut vec![(1, (1, 3))]);
         circuit.step().unwrap();
         assert_eq!(
-            &*output.lock().unwrap(),
-            &indexed_zset! { 1 => { 0 => -1, 1 => -2 } }
+            output.consolidate(),
+            indexed_zset! { 1 => { 0 => -1, 1 => -2 } }
         );
 
         input2.append(&mut vec![(2, (5, 1))]);
         input1.append(&mut vec![(2, (2, 1)), (4, (1, 1))]);
         circuit.step().unwrap();
         assert_eq!(
-            &*output.lock().unwrap(),
-            &indexed_zset! { 2 => { 0 => -1, 1 => -1 }, 4 => { 1 => 1 } }
+            output.consolidate(),
+            indexed_zset! { 2 => { 0 => -1, 1 => -1 }, 4 => { 1 => 1 } }
+        );
+
+        // Retracting a key from `other` brings back the matching values.
+        input2.append(&mut vec![(1, (1, -3))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { 0 => 1, 1 => 2 } }
+        );
+
+        // Retractions from `self`.
+        input1.append(&mut vec![(1, (1, -2)), (4, (1, -1))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { 1 => -2 }, 4 => { 1 => -1 } }
+        );
+
+        // Keys whose weights in `other` add up to zero count as absent.
+        input2.append(&mut vec![(3, (7, 1)), (3, (8, -1)), (2, (6, -1))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 2 => { 0 => 1, 1 => 1, 2 => 1 } }
+        );
+
+        input2.append(&mut vec![(3, (8, 1))]);
+        circuit.step().unwrap();
+        assert_eq!(output.consolidate(), indexed_zset! { 3 => { 1 => -1 } });
+
+        circuit.kill().unwrap();
+    }
+
+    #[test]
+    fn antijoin_test1() {
+        antijoin_test(1);
+    }
+
+    #[test]
+    fn antijoin_test4() {
+        antijoin_test(4);
+    }
+
+    // Nodes reachable from `roots` without going through `blocked` nodes.
+    // Exercises anti-join inside a recursive nested scope.
+    fn antijoin_nested_test(workers: usize) {
+        let (mut circuit, (mut edges, mut roots, mut blocked, output)) =
+            Runtime::init_circuit(workers, move |circuit| {
+                let (edges, edges_handle) = circuit.add_input_indexed_zset::<usize, usize, isize>();
+                let (roots, roots_handle) = circuit.add_input_zset::<usize, isize>();
+                let (blocked, blocked_handle) =
+                    circuit.add_input_indexed_zset::<usize, usize, isize>();
+
+                let reached = circuit
+                    .fixedpoint(|child| {
+                        let edges = edges.delta0(child);
+                        let roots = roots.delta0(child);
+                        let blocked = blocked.delta0(child);
+
+                        let reached = <DelayedFeedback<_, OrdZSet<usize, isize>>>::new(child);
+                        let next = reached
+                            .stream()
+                            .index_with(|&node| (node, ()))
+                            .join(&edges, |_from, _, &to| to);
+                        let result = roots
+                            .plus(&next)
+                            .index_with(|&node| (node, ()))
+                            .antijoin(&blocked)
+                            .map(|(&node, _)| node)
+                            .distinct();
+                        reached.connect(&result);
+
+                        Ok(result.integrate_trace().export())
+                    })
+                    .unwrap();
+
+                (
+                    edges_handle,
+                    roots_handle,
+                    blocked_handle,
+                    reached.consolidate().integrate().output(),
+                )
+            })
+            .unwrap();
+
+        edges.append(&mut vec![
+            (1, (2, 1)),
+            (2, (3, 1)),
+            (3, (4, 1)),
+            (1, (5, 1)),
+        ]);
+        roots.append(&mut vec![(1, 1)]);
+        blocked.append(&mut vec![(3, (0, 1))]);
+        circuit.step().unwrap();
+        assert_eq!(output.consolidate(), zset! { 1 => 1, 2 => 1, 5 => 1 });
+
+        blocked.append(&mut vec![(3, (0, -1))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! { 1 => 1, 2 => 1, 3 => 1, 4 => 1, 5 => 1 }
+        );
+
+        // Values of node 5 add up to zero, so it is not blocked.
+        blocked.append(&mut vec![(2, (0, 1)), (5, (1, 1)), (5, (2, -1))]);
+        circuit.step().unwrap();
+        assert_eq!(output.consolidate(), zset! { 1 => 1, 5 => 1 });
+
+        blocked.append(&mut vec![(2, (0, -1)), (5, (2, 1))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! { 1 => 1, 2 => 1, 3 => 1, 4 => 1 }
+        );
+
+        circuit.kill().unwrap();
+    }
+
+    #[test]
+    fn antijoin_nested_test1() {
+        antijoin_nested_test(1);
+    }
+
+    #[test]
+    fn antijoin_nested_test4() {
+        antijoin_nested_test(4);
+    }
+
+    fn outer_join_test(workers: usize) {
+        let (mut circuit, (mut input1, mut input2, left, right, full)) =
+            Runtime::init_circuit(workers, move |circuit| {
+                let (input1, input_handle1) =
+                    circuit.add_input_indexed_zset::<usize, usize, isize>();
+                let (input2, input_handle2) =
+                    circuit.add_input_indexed_zset::<usize, usize, isize>();
+
+                let left = input1
+                    .join_left(&input2, |&k, &v1, v2| (k, (v1, v2.cloned())))
+                    .integrate()
+                    .output();
+                let right = input1
+                    .join_right(&input2, |&k, v1, &v2| (k, (v1.cloned(), v2)))
+                    .integrate()
+                    .output();
+                let full = input1
+                    .join_full(&input2, |&k, v1, v2| (k, (v1.cloned(), v2.cloned())))
+                    .integrate()
+                    .output();
+
+                (input_handle1, input_handle2, left, right, full)
+            })
+            .unwrap();
+
+        input1.append(&mut vec![(1, (10, 1)), (2, (20, 2))]);
+        input2.append(&mut vec![(2, (200, 1)), (3, (300, 1))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            left.consolidate(),
+            indexed_zset! { 1 => { (10, None) => 1 }, 2 => { (20, Some(200)) => 2 } }
+        );
+        assert_eq!(
+            right.consolidate(),
+            indexed_zset! { 2 => { (Some(20), 200) => 2 }, 3 => { (None, 300) => 1 } }
+        );
+        assert_eq!(
+            full.consolidate(),
+            indexed_zset! {
+                1 => { (Some(10), None) => 1 },
+                2 => { (Some(20), Some(200)) => 2 },
+                3 => { (None, Some(300)) => 1 },
+            }
+        );
+
+        // Matches appear for keys 1 and 3: their padded rows are retracted.
+        input1.append(&mut vec![(3, (30, 1))]);
+        input2.append(&mut vec![(1, (100, 1))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            left.consolidate(),
+            indexed_zset! {
+                1 => { (10, Some(100)) => 1 },
+                2 => { (20, Some(200)) => 2 },
+                3 => { (30, Some(300)) => 1 },
+            }
+        );
+        assert_eq!(
+            right.consolidate(),
+            indexed_zset! {
+                1 => { (Some(10), 100) => 1 },
+                2 => { (Some(20), 200) => 2 },
+                3 => { (Some(30), 300) => 1 },
+            }
+        );
+        assert_eq!(
+            full.consolidate(),
+            indexed_zset! {
+                1 => { (Some(10), Some(100)) => 1 },
+                2 => { (Some(20), Some(200)) => 2 },
+                3 => { (Some(30), Some(300)) => 1 },
+            }
+        );
+
+        // Retracting the matching rows brings the padded rows back.
+        input1.append(&mut vec![(2, (20, -2))]);
+        input2.append(&mut vec![(1, (100, -1))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            left.consolidate(),
+            indexed_zset! { 1 => { (10, None) => 1 }, 3 => { (30, Some(300)) => 1 } }
+        );
+        assert_eq!(
+            right.consolidate(),
+            indexed_zset! { 2 => { (None, 200) => 1 }, 3 => { (Some(30), 300) => 1 } }
+        );
+        assert_eq!(
+            full.consolidate(),
+            indexed_zset! {
+                1 => { (Some(10), None) => 1 },
+                2 => { (None, Some(200)) => 1 },
+                3 => { (Some(30), Some(300)) => 1 },
+            }
+        );
+
+        // Weights of matching rows are multiplied.
+        input1.append(&mut vec![(3, (30, 1))]);
+        input2.append(&mut vec![(3, (301, 2))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            left.consolidate(),
+            indexed_zset! {
+                1 => { (10, None) => 1 },
+                3 => { (30, Some(300)) => 2, (30, Some(301)) => 4 },
+            }
+        );
+        assert_eq!(
+            full.consolidate(),
+            indexed_zset! {
+                1 => { (Some(10), None) => 1 },
+                2 => { (None, Some(200)) => 1 },
+                3 => { (Some(30), Some(300)) => 2, (Some(30), Some(301)) => 4 },
+            }
+        );
+
+        circuit.kill().unwrap();
+    }
+
+    #[test]
+    fn outer_join_test1() {
+        outer_join_test(1);
+    }
+
+    #[test]
+    fn outer_join_test4() {
+        outer_join_test(4);
+    }
+
+    fn semijoin_test(workers: usize) {
+        let (mut circuit, (mut input1, mut input2, semi, semi_multiset, anti_multiset)) =
+            Runtime::init_circuit(workers, move |circuit| {
+                let (input1, input_handle1) =
+                    circuit.add_input_indexed_zset::<usize, usize, isize>();
+                let (input2, input_handle2) =
+                    circuit.add_input_indexed_zset::<usize, usize, isize>();
+
+                let semi = input1.semijoin(&input2).integrate().output();
+                let semi_multiset = input1.semijoin_multiset(&input2).integrate().output();
+                let anti_multiset = input1.antijoin_multiset(&input2).integrate().output();
+
+                (
+                    input_handle1,
+                    input_handle2,
+                    semi,
+                    semi_multiset,
+                    anti_multiset,
+                )
+            })
+            .unwrap();
+
+        // Key 1 has a value with weight 3 that matches a key in `input2`.
+        input1.append(&mut vec![(1, (0, 3)), (1, (1, 1)), (2, (0, 2))]);
+        input2.append(&mut vec![(1, (5, 2))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            semi.consolidate(),
+            indexed_zset! { 1 => { 0 => 1, 1 => 1 } }
+        );
+        assert_eq!(
+            semi_multiset.consolidate(),
+            indexed_zset! { 1 => { 0 => 3, 1 => 1 } }
+        );
+        assert_eq!(
+            anti_multiset.consolidate(),
+            indexed_zset! { 2 => { 0 => 2 } }
+        );
+
+        // Key 2 starts matching; key 1 stops matching.
+        input2.append(&mut vec![(1, (5, -2)), (2, (7, 1))]);
+        input1.append(&mut vec![(2, (0, 1))]);
+        circuit.step().unwrap();
+        assert_eq!(semi.consolidate(), indexed_zset! { 2 => { 0 => 1 } });
+        assert_eq!(
+            semi_multiset.consolidate(),
+            indexed_zset! { 2 => { 0 => 3 } }
+        );
+        assert_eq!(
+            anti_multiset.consolidate(),
+            indexed_zset! { 1 => { 0 => 3, 1 => 1 } }
         );
 
         circuit.kill().unwrap();
     }
+
+    #[test]
+    fn semijoin_test1() {
+        semijoin_test(1);
+    }
+
+    #[test]
+    fn semijoin_test4() {
+        semijoin_test(4);
+    }
+
+    fn merge_join_test(workers: usize) {
+        let (mut circuit, (mut input1, mut input2, stream_output, merge_output)) =
+            Runtime::init_circuit(workers, move |circuit| {
+                let (input1, input_handle1) =
+                    circuit.add_input_indexed_zset::<usize, usize, isize>();
+                let (input2, input_handle2) =
+                    circuit.add_input_indexed_zset::<usize, usize, isize>();
+
+                let stream_output = input1
+                    .stream_join(&input2, |&k, &v1, &v2| (k, v1, v2))
+                    .output();
+                let merge_output = input1
+                    .merge_join(&input2, |&k, &v1, &v2| (k, v1, v2))
+                    .output();
+
+                (input_handle1, input_handle2, stream_output, merge_output)
+            })
+            .unwrap();
+
+        for step in 0..10usize {
+            // Overlapping, but not identical key ranges in both inputs.
+            input1.append(
+                &mut (0..50)
+                    .map(|i| (i * 2 + step, (i % 3, (i % 4) as isize - 1)))
+                    .collect(),
+            );
+            input2.append(
+                &mut (0..50)
+                    .map(|i| (i * 3 + step, (i % 5, (i % 3) as isize + 1)))
+                    .collect(),
+            );
+            circuit.step().unwrap();
+
+            let expected = stream_output.consolidate();
+            assert!(!expected.is_empty());
+            assert_eq!(merge_output.consolidate(), expected);
+        }
+
+        circuit.kill().unwrap();
+    }
+
+    #[test]
+    fn merge_join_test1() {
+        merge_join_test(1);
+    }
+
+    #[test]
+    fn merge_join_test4() {
+        merge_join_test(4);
+    }
 }
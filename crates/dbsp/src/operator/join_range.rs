@@ -13,17 +13,32 @@
 //! * For each `((k1, v1), w1)` in `z1` and `((k2, v2), w2)` in `z2` where `k2 ∈
 //!   join_range(k1)`, add all values in `join_func(k1,v1,k2,v2)` to the output
 //!   batch with weight `w1 * w2`.
+//!
+//! The `stream_join_range` family of operators joins the pair of batches
+//! received at each timestamp, while the `join_range` family incrementally
+//! computes the range-join of the integrals of two inputs that are
+//! partitioned by an additional equality key.
 
 use crate::{
-    algebra::{MulByRef, ZRingValue},
+    algebra::{IndexedZSet, Lattice, MulByRef, PartialOrder, ZRingValue},
     circuit::{
+        metadata::OperatorLocation,
         operator_traits::{BinaryOperator, Operator},
-        Circuit, Scope, Stream,
+        CheckpointError, Circuit, Scope, Stream, WithClock,
     },
-    trace::{cursor::Cursor, Batch, BatchReader},
-    DBData, OrdIndexedZSet, OrdZSet,
+    time::Timestamp,
+    trace::{cursor::Cursor, Batch, BatchReader, Batcher, Spine, Trace},
+    DBData, DBTimestamp, OrdIndexedZSet, OrdZSet,
 };
-use std::{borrow::Cow, marker::PhantomData};
+use std::{borrow::Cow, collections::HashMap, marker::PhantomData, panic::Location};
+
+/// Trace of a stream of batches with keys `K`, values `V` and weights `R` in
+/// circuit `C`.
+type CircuitTrace<C, K, V, R> = Spine<<<C as WithClock>::Time as Timestamp>::OrdValBatch<K, V, R>>;
+
+/// Callback used by [`JoinRangeTrace`] to output a `(key, value, weight)`
+/// tuple along with the time of the trace entry it was derived from.
+type Emit<'a, T, K, V, R> = dyn FnMut(&T, K, V, R) + 'a;
 
 impl<C, I1> Stream<C, I1>
 where
@@ -138,6 +153,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<RF, JF, It, I1, I2, O> BinaryOperator<I1, I2, O> for StreamJoinRange<RF, JF, It, I1, I2, O>
@@ -196,9 +215,440 @@ where
     }
 }
 
+impl<C, I1> Stream<C, I1>
+where
+    C: Circuit,
+    <C as WithClock>::Time: DBTimestamp,
+    I1: IndexedZSet + Send,
+    I1::R: ZRingValue,
+{
+    /// Incrementally range-join two partitioned streams into an `OrdZSet`.
+    ///
+    /// Given streams `self` and `other` of batches that represent changes to
+    /// relations `A` and `B`, computes a stream of changes to their
+    /// range-join.  Both relations are indexed by a partition key, and their
+    /// values are `(key, value)` pairs.  A record `(p, (k1, v1))` in `A`
+    /// matches all records `(p, (k2, v2))` in `B` with the same partition
+    /// key `p` and `k2` in the half-closed interval `[lower_func(k1),
+    /// upper_func(k1))`.  For each pair of matching records with weights `w1`
+    /// and `w2`, the output contains all values in `join_func(p, k1, v1, k2,
+    /// v2)` with weight `w1 * w2`.
+    ///
+    /// For example, to match bids with the auctions they were placed
+    /// during, partition both relations by auction id and range-join
+    /// auction lifetimes with bid times.
+    ///
+    /// Like [`join`](`Stream::join`), this operator joins each input batch
+    /// with the trace of the other input and works in nested scopes.
+    ///
+    /// # Performance
+    ///
+    /// Both inputs are sharded by partition key.  Changes to `self` are
+    /// joined with the matching range of `other` found with
+    /// [`Cursor::seek_val_with`].  Since the records of `self` that match a
+    /// key of `other` cannot be located with a single seek, changes to
+    /// `other` are joined by scanning the values of `self` in the same
+    /// partition.  Partition keys should therefore be chosen so that
+    /// partitions are small.  Relations that have no natural partition key
+    /// can use `()`, which evaluates the entire join on a single worker.
+    #[track_caller]
+    pub fn join_range<PK, K1, V1, K2, V2, I2, LF, UF, JF, It>(
+        &self,
+        other: &Stream<C, I2>,
+        lower_func: LF,
+        upper_func: UF,
+        join_func: JF,
+    ) -> Stream<C, OrdZSet<It::Item, I1::R>>
+    where
+        I1: IndexedZSet<Key = PK, Val = (K1, V1)>,
+        I2: IndexedZSet<Key = PK, Val = (K2, V2), R = I1::R> + Send,
+        K1: DBData,
+        V1: DBData,
+        K2: DBData,
+        V2: DBData,
+        LF: Fn(&K1) -> K2 + Clone + 'static,
+        UF: Fn(&K1) -> K2 + Clone + 'static,
+        JF: Fn(&PK, &K1, &V1, &K2, &V2) -> It + Clone + 'static,
+        It: IntoIterator + 'static,
+        It::Item: DBData,
+    {
+        self.join_range_generic(other, lower_func, upper_func, move |p, k1, v1, k2, v2| {
+            join_func(p, k1, v1, k2, v2).into_iter().map(|k| (k, ()))
+        })
+    }
+
+    /// Incrementally range-join two partitioned streams into an
+    /// `OrdIndexedZSet`.
+    ///
+    /// Like [`Self::join_range`], but the `join_func` closure returns an
+    /// iterator over `(key, value)` pairs used to assemble the output indexed
+    /// Z-set.
+    #[track_caller]
+    pub fn join_range_index<PK, K1, V1, K2, V2, I2, LF, UF, JF, It, K, V>(
+        &self,
+        other: &Stream<C, I2>,
+        lower_func: LF,
+        upper_func: UF,
+        join_func: JF,
+    ) -> Stream<C, OrdIndexedZSet<K, V, I1::R>>
+    where
+        I1: IndexedZSet<Key = PK, Val = (K1, V1)>,
+        I2: IndexedZSet<Key = PK, Val = (K2, V2), R = I1::R> + Send,
+        K1: DBData,
+        V1: DBData,
+        K2: DBData,
+        V2: DBData,
+        LF: Fn(&K1) -> K2 + Clone + 'static,
+        UF: Fn(&K1) -> K2 + Clone + 'static,
+        JF: Fn(&PK, &K1, &V1, &K2, &V2) -> It + Clone + 'static,
+        K: DBData,
+        V: DBData,
+        It: IntoIterator<Item = (K, V)> + 'static,
+    {
+        self.join_range_generic(other, lower_func, upper_func, join_func)
+    }
+
+    /// Like [`Self::join_range`], but can return any indexed Z-set type.
+    #[track_caller]
+    pub fn join_range_generic<PK, K1, V1, K2, V2, I2, LF, UF, JF, It, Z>(
+        &self,
+        other: &Stream<C, I2>,
+        lower_func: LF,
+        upper_func: UF,
+        join_func: JF,
+    ) -> Stream<C, Z>
+    where
+        I1: IndexedZSet<Key = PK, Val = (K1, V1)>,
+        I2: IndexedZSet<Key = PK, Val = (K2, V2), R = I1::R> + Send,
+        K1: DBData,
+        V1: DBData,
+        K2: DBData,
+        V2: DBData,
+        Z: IndexedZSet<R = I1::R>,
+        LF: Fn(&K1) -> K2 + Clone + 'static,
+        UF: Fn(&K1) -> K2 + Clone + 'static,
+        JF: Fn(&PK, &K1, &V1, &K2, &V2) -> It + Clone + 'static,
+        It: IntoIterator<Item = (Z::Key, Z::Val)> + 'static,
+    {
+        let location = Location::caller();
+
+        // Matching records belong to the same partition, so both inputs are
+        // sharded by partition key.
+        let left = self.shard();
+        let right = other.shard();
+
+        let left_trace = left.trace::<CircuitTrace<C, PK, (K1, V1), I1::R>>();
+        let right_trace = right.trace::<CircuitTrace<C, PK, (K2, V2), I1::R>>();
+
+        let (lower_func_right, upper_func_right, join_func_right) =
+            (lower_func.clone(), upper_func.clone(), join_func.clone());
+
+        let left = self.circuit().add_binary_operator(
+            JoinRangeTrace::new(
+                move |delta: &I1,
+                      trace: &CircuitTrace<C, PK, (K2, V2), I1::R>,
+                      emit: &mut Emit<'_, <C as WithClock>::Time, Z::Key, Z::Val, I1::R>| {
+                    join_delta_with_trace(delta, trace, &lower_func, &upper_func, &join_func, emit)
+                },
+                location,
+                self.circuit().clone(),
+            ),
+            &left,
+            &right_trace,
+        );
+
+        let right = self.circuit().add_binary_operator(
+            JoinRangeTrace::new(
+                move |delta: &I2,
+                      trace: &CircuitTrace<C, PK, (K1, V1), I1::R>,
+                      emit: &mut Emit<'_, <C as WithClock>::Time, Z::Key, Z::Val, I1::R>| {
+                    join_trace_with_delta(
+                        trace,
+                        delta,
+                        &lower_func_right,
+                        &upper_func_right,
+                        &join_func_right,
+                        emit,
+                    )
+                },
+                location,
+                self.circuit().clone(),
+            ),
+            &right,
+            &left_trace.delay_trace(),
+        );
+
+        left.plus(&right)
+    }
+}
+
+/// Range-joins the values of each partition in `delta` with the values of
+/// the same partition in `trace`.
+fn join_delta_with_trace<PK, K1, V1, K2, V2, I, T, LF, UF, JF, It, K, V>(
+    delta: &I,
+    trace: &T,
+    lower_func: &LF,
+    upper_func: &UF,
+    join_func: &JF,
+    emit: &mut Emit<'_, T::Time, K, V, I::R>,
+) where
+    PK: Ord,
+    K2: Ord,
+    I: BatchReader<Key = PK, Val = (K1, V1), Time = ()>,
+    I::R: MulByRef<Output = I::R>,
+    T: BatchReader<Key = PK, Val = (K2, V2), R = I::R>,
+    LF: Fn(&K1) -> K2,
+    UF: Fn(&K1) -> K2,
+    JF: Fn(&PK, &K1, &V1, &K2, &V2) -> It,
+    It: IntoIterator<Item = (K, V)>,
+    K: Clone,
+    V: Clone,
+{
+    let mut delta_cursor = delta.cursor();
+    let mut trace_cursor = trace.cursor();
+
+    while delta_cursor.key_valid() {
+        trace_cursor.seek_key(delta_cursor.key());
+        if !trace_cursor.key_valid() || trace_cursor.key() != delta_cursor.key() {
+            delta_cursor.step_key();
+            continue;
+        }
+
+        while delta_cursor.val_valid() {
+            let w1 = delta_cursor.weight();
+            let (k1, v1) = delta_cursor.val();
+            let lower = lower_func(k1);
+            let upper = upper_func(k1);
+
+            trace_cursor.rewind_vals();
+            trace_cursor.seek_val_with(|(k2, _)| k2 >= &lower);
+            while trace_cursor.val_valid() && trace_cursor.val().0 < upper {
+                let (k2, v2) = trace_cursor.val();
+                let output = join_func(delta_cursor.key(), k1, v1, k2, v2);
+                for (k, v) in output {
+                    trace_cursor
+                        .map_times(|ts, w2| emit(ts, k.clone(), v.clone(), w1.mul_by_ref(w2)));
+                }
+                trace_cursor.step_val();
+            }
+            delta_cursor.step_val();
+        }
+        delta_cursor.step_key();
+    }
+}
+
+/// Range-joins the values of each partition in `trace` with the values of
+/// the same partition in `delta`.
+fn join_trace_with_delta<PK, K1, V1, K2, V2, I, T, LF, UF, JF, It, K, V>(
+    trace: &T,
+    delta: &I,
+    lower_func: &LF,
+    upper_func: &UF,
+    join_func: &JF,
+    emit: &mut Emit<'_, T::Time, K, V, I::R>,
+) where
+    PK: Ord,
+    K1: Clone,
+    V1: Clone,
+    K2: Ord + Clone,
+    V2: Clone,
+    I: BatchReader<Key = PK, Val = (K2, V2), Time = ()>,
+    I::R: MulByRef<Output = I::R>,
+    T: BatchReader<Key = PK, Val = (K1, V1), R = I::R>,
+    LF: Fn(&K1) -> K2,
+    UF: Fn(&K1) -> K2,
+    JF: Fn(&PK, &K1, &V1, &K2, &V2) -> It,
+    It: IntoIterator<Item = (K, V)>,
+    K: Clone,
+    V: Clone,
+{
+    let mut delta_cursor = delta.cursor();
+    let mut trace_cursor = trace.cursor();
+
+    let mut delta_vals = Vec::new();
+    while delta_cursor.key_valid() {
+        trace_cursor.seek_key(delta_cursor.key());
+        if !trace_cursor.key_valid() || trace_cursor.key() != delta_cursor.key() {
+            delta_cursor.step_key();
+            continue;
+        }
+
+        // Values of the partition, ordered by key, so that the range of each
+        // value in `trace` can be located with a binary search.
+        delta_vals.clear();
+        while delta_cursor.val_valid() {
+            let w2 = delta_cursor.weight();
+            delta_vals.push((delta_cursor.val().clone(), w2));
+            delta_cursor.step_val();
+        }
+
+        while trace_cursor.val_valid() {
+            let (k1, v1) = trace_cursor.val().clone();
+            let lower = lower_func(&k1);
+            let upper = upper_func(&k1);
+
+            let from = delta_vals.partition_point(|((k2, _), _)| k2 < &lower);
+            for ((k2, v2), w2) in delta_vals[from..]
+                .iter()
+                .take_while(|((k2, _), _)| k2 < &upper)
+            {
+                let output = join_func(delta_cursor.key(), &k1, &v1, k2, v2);
+                for (k, v) in output {
+                    trace_cursor
+                        .map_times(|ts, w1| emit(ts, k.clone(), v.clone(), w1.mul_by_ref(w2)));
+                }
+            }
+            trace_cursor.step_val();
+        }
+        delta_cursor.step_key();
+    }
+}
+
+/// Incremental range-join of a batch of updates with a trace.
+///
+/// `match_func` finds matching pairs of records in the input batch and the
+/// trace; each output tuple is added at the least upper bound of the current
+/// time and the time of the matching trace entry.  Outputs that belong to
+/// future times are buffered until the clock reaches them.
+struct JoinRangeTrace<F, I, T, Z, Clk>
+where
+    T: BatchReader,
+    Z: IndexedZSet,
+{
+    clock: Clk,
+    match_func: F,
+    location: &'static Location<'static>,
+    // Future update batches computed ahead of time, indexed by time
+    // when each batch should be output.
+    output_batchers: HashMap<T::Time, Z::Batcher>,
+    // True if empty input batch was received at the current clock cycle.
+    empty_input: bool,
+    // True if empty output was produced at the current clock cycle.
+    empty_output: bool,
+    _types: PhantomData<(I, T, Z)>,
+}
+
+impl<F, I, T, Z, Clk> JoinRangeTrace<F, I, T, Z, Clk>
+where
+    T: BatchReader,
+    Z: IndexedZSet,
+{
+    fn new(match_func: F, location: &'static Location<'static>, clock: Clk) -> Self {
+        Self {
+            clock,
+            match_func,
+            location,
+            output_batchers: HashMap::new(),
+            empty_input: false,
+            empty_output: false,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<F, I, T, Z, Clk> Operator for JoinRangeTrace<F, I, T, Z, Clk>
+where
+    F: 'static,
+    I: 'static,
+    T: BatchReader,
+    Z: IndexedZSet,
+    Clk: WithClock<Time = T::Time> + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed("JoinRangeTrace")
+    }
+
+    fn location(&self) -> OperatorLocation {
+        Some(self.location)
+    }
+
+    fn clock_start(&mut self, scope: Scope) {
+        if scope == 0 {
+            self.empty_input = false;
+            self.empty_output = false;
+        }
+    }
+
+    fn clock_end(&mut self, _scope: Scope) {
+        debug_assert!(self
+            .output_batchers
+            .keys()
+            .all(|time| !time.less_equal(&self.clock.time())));
+    }
+
+    fn fixedpoint(&self, scope: Scope) -> bool {
+        let epoch_end = self.clock.time().epoch_end(scope);
+        self.empty_input
+            && self.empty_output
+            && self
+                .output_batchers
+                .keys()
+                .all(|time| !time.less_equal(&epoch_end))
+    }
+
+    // Updates scheduled for future timestamps only exist in nested circuits,
+    // where they span parent clock cycles, and are not serializable.
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        if self.output_batchers.is_empty() {
+            Ok(None)
+        } else {
+            Err(CheckpointError::Unsupported)
+        }
+    }
+}
+
+impl<F, I, T, Z, Clk> BinaryOperator<I, T, Z> for JoinRangeTrace<F, I, T, Z, Clk>
+where
+    I: BatchReader<Time = ()> + Clone,
+    T: Trace<R = I::R> + Clone,
+    Z: IndexedZSet<R = I::R>,
+    F: Fn(&I, &T, &mut Emit<'_, T::Time, Z::Key, Z::Val, Z::R>) + 'static,
+    Clk: WithClock<Time = T::Time> + 'static,
+{
+    fn eval(&mut self, delta: &I, trace: &T) -> Z {
+        self.empty_input = delta.is_empty();
+
+        let time = self.clock.time();
+        let mut output_tuples = Vec::new();
+        if !delta.is_empty() {
+            (self.match_func)(delta, trace, &mut |ts, k, v, w| {
+                output_tuples.push((ts.join(&time), (Z::item_from(k, v), w)))
+            });
+        }
+
+        // Push all tuples for each unique timestamp to the appropriate batcher.
+        output_tuples.sort_by(|(t1, _), (t2, _)| t1.cmp(t2));
+        let mut output_tuples = output_tuples.into_iter().peekable();
+        while let Some((batch_time, tuple)) = output_tuples.next() {
+            let mut batch = vec![tuple];
+            while let Some((_, tuple)) = output_tuples.next_if(|(t, _)| t == &batch_time) {
+                batch.push(tuple);
+            }
+
+            self.output_batchers
+                .entry(batch_time)
+                .or_insert_with(|| Z::Batcher::new_batcher(()))
+                .push_batch(&mut batch);
+        }
+
+        // Finalize the batch for the current timestamp and return it.
+        let result = self
+            .output_batchers
+            .remove(&time)
+            .map(|batcher| batcher.seal())
+            .unwrap_or_else(|| Z::empty(()));
+        self.empty_output = result.is_empty();
+
+        result
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::{operator::Generator, zset, Circuit, RootCircuit};
+    use crate::{
+        operator::{DelayedFeedback, Generator},
+        zset, Circuit, OrdZSet, RootCircuit, Runtime,
+    };
 
     #[test]
     fn stream_join_range_test() {
@@ -297,4 +747,182 @@ mod test {
             circuit.step().unwrap();
         }
     }
+
+    fn join_range_test(workers: usize) {
+        let (mut circuit, (mut input1, mut input2, output)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (input1, input_handle1) =
+                    circuit.add_input_indexed_zset::<u64, (i64, char), isize>();
+                let (input2, input_handle2) =
+                    circuit.add_input_indexed_zset::<u64, (i64, char), isize>();
+
+                let output = input1
+                    .join_range(
+                        &input2,
+                        |&k| k - 1,
+                        |&k| k + 2,
+                        |&p, &k1, &v1, &k2, &v2| Some((p, (k1, v1), (k2, v2))),
+                    )
+                    .integrate()
+                    .output();
+
+                (input_handle1, input_handle2, output)
+            })
+            .unwrap();
+
+        // Records in partition 1 only match each other.
+        input1.append(&mut vec![
+            (0, ((1, 'a'), 1)),
+            (0, ((5, 'b'), 2)),
+            (1, ((1, 'p'), 1)),
+        ]);
+        input2.append(&mut vec![
+            (0, ((0, 'x'), 1)),
+            (0, ((2, 'y'), 1)),
+            (0, ((3, 'z'), 1)),
+            (0, ((6, 'w'), 1)),
+            (1, ((2, 'q'), 1)),
+            (2, ((2, 'r'), 1)),
+        ]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! {
+                (0, (1, 'a'), (0, 'x')) => 1,
+                (0, (1, 'a'), (2, 'y')) => 1,
+                (0, (5, 'b'), (6, 'w')) => 2,
+                (1, (1, 'p'), (2, 'q')) => 1,
+            }
+        );
+
+        // Key 3 is just outside the range of key 1; key 4 is at the lower
+        // boundary of the range of key 5.
+        input1.append(&mut vec![(0, ((2, 'c'), 1))]);
+        input2.append(&mut vec![(0, ((3, 'u'), 1)), (0, ((4, 'v'), 3))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! {
+                (0, (1, 'a'), (0, 'x')) => 1,
+                (0, (1, 'a'), (2, 'y')) => 1,
+                (0, (2, 'c'), (2, 'y')) => 1,
+                (0, (2, 'c'), (3, 'z')) => 1,
+                (0, (2, 'c'), (3, 'u')) => 1,
+                (0, (5, 'b'), (4, 'v')) => 6,
+                (0, (5, 'b'), (6, 'w')) => 2,
+                (1, (1, 'p'), (2, 'q')) => 1,
+            }
+        );
+
+        // Retract keys at the lower boundaries of ranges.
+        input1.append(&mut vec![(0, ((5, 'b'), -2))]);
+        input2.append(&mut vec![(0, ((0, 'x'), -1)), (0, ((4, 'v'), -3))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! {
+                (0, (1, 'a'), (2, 'y')) => 1,
+                (0, (2, 'c'), (2, 'y')) => 1,
+                (0, (2, 'c'), (3, 'z')) => 1,
+                (0, (2, 'c'), (3, 'u')) => 1,
+                (1, (1, 'p'), (2, 'q')) => 1,
+            }
+        );
+
+        // Retract matching records from both sides at the same time.
+        input1.append(&mut vec![(0, ((1, 'a'), -1))]);
+        input2.append(&mut vec![(0, ((2, 'y'), -1)), (1, ((2, 'q'), -1))]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! {
+                (0, (2, 'c'), (3, 'z')) => 1,
+                (0, (2, 'c'), (3, 'u')) => 1,
+            }
+        );
+
+        circuit.kill().unwrap();
+    }
+
+    #[test]
+    fn join_range_test1() {
+        join_range_test(1);
+    }
+
+    #[test]
+    fn join_range_test4() {
+        join_range_test(4);
+    }
+
+    // Nodes reachable from `roots`, where each node `k` can jump to any
+    // existing node in `[k + 1, k + 3)`.  Exercises range-join inside a
+    // recursive nested scope.
+    fn join_range_nested_test(workers: usize) {
+        let (mut circuit, (mut nodes, mut roots, output)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (nodes, nodes_handle) = circuit.add_input_zset::<i64, isize>();
+                let (roots, roots_handle) = circuit.add_input_zset::<i64, isize>();
+                let nodes = nodes.index_with(|&node| ((), (node, ())));
+
+                let reached = circuit
+                    .fixedpoint(|child| {
+                        let nodes = nodes.delta0(child);
+                        let roots = roots.delta0(child);
+
+                        let reached = <DelayedFeedback<_, OrdZSet<i64, isize>>>::new(child);
+                        let next = reached
+                            .stream()
+                            .index_with(|&node| ((), (node, ())))
+                            .join_range(&nodes, |&k| k + 1, |&k| k + 3, |_, _, _, &to, _| Some(to));
+                        let result = roots.plus(&next).distinct();
+                        reached.connect(&result);
+
+                        Ok(result.integrate_trace().export())
+                    })
+                    .unwrap();
+
+                (
+                    nodes_handle,
+                    roots_handle,
+                    reached.consolidate().integrate().output(),
+                )
+            })
+            .unwrap();
+
+        nodes.append(&mut vec![(0, 1), (1, 1), (2, 1), (5, 1), (6, 1), (7, 1)]);
+        roots.append(&mut vec![(0, 1)]);
+        circuit.step().unwrap();
+        assert_eq!(output.consolidate(), zset! { 0 => 1, 1 => 1, 2 => 1 });
+
+        // Node 4 is at the upper boundary of the range of node 2.
+        nodes.append(&mut vec![(4, 1)]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! { 0 => 1, 1 => 1, 2 => 1, 4 => 1, 5 => 1, 6 => 1, 7 => 1 }
+        );
+
+        nodes.append(&mut vec![(2, -1)]);
+        circuit.step().unwrap();
+        assert_eq!(output.consolidate(), zset! { 0 => 1, 1 => 1 });
+
+        nodes.append(&mut vec![(3, 1)]);
+        circuit.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            zset! { 0 => 1, 1 => 1, 3 => 1, 4 => 1, 5 => 1, 6 => 1, 7 => 1 }
+        );
+
+        circuit.kill().unwrap();
+    }
+
+    #[test]
+    fn join_range_nested_test1() {
+        join_range_nested_test(1);
+    }
+
+    #[test]
+    fn join_range_nested_test4() {
+        join_range_nested_test(4);
+    }
 }
@@ -0,0 +1,274 @@
+//! Incremental `LAG` and `LEAD` window functions.
+
+use crate::{
+    algebra::{HasOne, ZRingValue, ZSet},
+    DBData, OrdZSet, RootCircuit, Stream,
+};
+use num::ToPrimitive;
+
+impl<Z> Stream<RootCircuit, Z>
+where
+    Z: ZSet + Send,
+    Z::R: ZRingValue + ToPrimitive,
+{
+    /// Incrementally compute the `LAG(value, offset, default)` window
+    /// function.
+    ///
+    /// Splits the input collection into partitions using `key_func`, orders
+    /// rows in each partition by the timestamp computed by `ts_func`, and, for
+    /// each row, outputs the value computed by `value_func` for the row
+    /// `offset` positions before it in the partition.  Rows that don't have
+    /// such a predecessor, e.g., the first `offset` rows of the partition,
+    /// get `default` instead.  To get the SQL behavior of `LAG` without a
+    /// default value, use `Option<V>` as the value type, wrap values returned
+    /// by `value_func` in `Some`, and pass `None` as the default.
+    ///
+    /// Rows with equal timestamps are ordered by the rows themselves, so that
+    /// the output is deterministic.  A row with weight `w > 0` counts as `w`
+    /// identical rows; rows with non-positive weights are ignored.
+    ///
+    /// Outputs a collection of `(row, lag)` pairs, where the weight of each
+    /// pair is the number of copies of the row with this lag value.
+    ///
+    /// # Performance
+    ///
+    /// Inserting or deleting a row shifts the position of all rows that
+    /// follow it in the partition.  The operator recomputes the entire
+    /// partition on every change, so each change to a partition is `O(n)`,
+    /// where `n` is the size of the partition, in terms of work, although
+    /// only rows whose lag value changed appear in the output.
+    #[allow(clippy::type_complexity)]
+    pub fn lag<PK, TS, V, KF, TF, VF>(
+        &self,
+        offset: usize,
+        default: V,
+        key_func: KF,
+        ts_func: TF,
+        value_func: VF,
+    ) -> Stream<RootCircuit, OrdZSet<(Z::Key, V), Z::R>>
+    where
+        PK: DBData,
+        TS: DBData,
+        V: DBData,
+        KF: Fn(&Z::Key) -> PK + Clone + 'static,
+        TF: Fn(&Z::Key) -> TS + 'static,
+        VF: Fn(&Z::Key) -> V + 'static,
+    {
+        self.recompute_partitions(key_func, move |rows| {
+            shift_partition(rows, &ts_func, &value_func, &default, |position| {
+                position.checked_sub(offset)
+            })
+        })
+    }
+
+    /// Incrementally compute the `LEAD(value, offset, default)` window
+    /// function.
+    ///
+    /// Like [`lag`](`Self::lag`), but outputs the value of the row `offset`
+    /// positions after each row in the partition, or `default` for the last
+    /// `offset` rows of the partition.
+    #[allow(clippy::type_complexity)]
+    pub fn lead<PK, TS, V, KF, TF, VF>(
+        &self,
+        offset: usize,
+        default: V,
+        key_func: KF,
+        ts_func: TF,
+        value_func: VF,
+    ) -> Stream<RootCircuit, OrdZSet<(Z::Key, V), Z::R>>
+    where
+        PK: DBData,
+        TS: DBData,
+        V: DBData,
+        KF: Fn(&Z::Key) -> PK + Clone + 'static,
+        TF: Fn(&Z::Key) -> TS + 'static,
+        VF: Fn(&Z::Key) -> V + 'static,
+    {
+        self.recompute_partitions(key_func, move |rows| {
+            shift_partition(rows, &ts_func, &value_func, &default, |position| {
+                position.checked_add(offset)
+            })
+        })
+    }
+}
+
+/// For each copy of each row with positive weight in a partition, output the
+/// value of the row at position `shift(position)` in the partition or
+/// `default` if there is no row at this position.
+fn shift_partition<K, TS, V, R, TF, VF, SF>(
+    rows: Vec<(K, R)>,
+    ts_func: &TF,
+    value_func: &VF,
+    default: &V,
+    shift: SF,
+) -> Vec<(K, V, R)>
+where
+    K: Ord + Clone,
+    TS: Ord,
+    V: Clone,
+    R: ZRingValue + ToPrimitive,
+    TF: Fn(&K) -> TS,
+    VF: Fn(&K) -> V,
+    SF: Fn(usize) -> Option<usize>,
+{
+    let mut rows: Vec<(TS, K, usize)> = rows
+        .into_iter()
+        .filter(|(_, weight)| weight.gt0())
+        .map(|(row, weight)| (ts_func(&row), row, weight.to_usize().unwrap_or_default()))
+        .collect();
+    // Break ties between equal timestamps using the rows themselves.
+    rows.sort_by(|(ts1, row1, _), (ts2, row2, _)| ts1.cmp(ts2).then_with(|| row1.cmp(row2)));
+
+    // Values of all rows in the partition, one per copy of each row.
+    let values: Vec<V> = rows
+        .iter()
+        .flat_map(|(_, row, count)| std::iter::repeat(value_func(row)).take(*count))
+        .collect();
+
+    let mut output = Vec::with_capacity(values.len());
+    let mut position = 0;
+
+    for (_, row, count) in rows {
+        for _ in 0..count {
+            let value = shift(position)
+                .and_then(|shifted| values.get(shifted))
+                .unwrap_or(default);
+            output.push((row.clone(), value.clone(), R::one()));
+            position += 1;
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{zset, Runtime};
+
+    // Rows are `(partition, timestamp, value)` tuples.
+    type Row = (usize, usize, i64);
+
+    fn lag_lead_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, lag_handle, lead_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (input, input_handle) = circuit.add_input_zset::<Row, isize>();
+
+                let lag = input
+                    .lag(1, 0, |(p, _, _)| *p, |(_, ts, _)| *ts, |(_, _, v)| *v)
+                    .integrate()
+                    .output();
+                let lead = input
+                    .lead(1, -1, |(p, _, _)| *p, |(_, ts, _)| *ts, |(_, _, v)| *v)
+                    .integrate()
+                    .output();
+
+                (input_handle, lag, lead)
+            })
+            .unwrap();
+
+        // The first row in the partition gets the default value.
+        input_handle.append(&mut vec![
+            ((0, 1, 100), 1),
+            ((0, 3, 300), 1),
+            ((1, 1, 5), 1),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            lag_handle.consolidate(),
+            zset! { ((0, 1, 100), 0) => 1, ((0, 3, 300), 100) => 1, ((1, 1, 5), 0) => 1 }
+        );
+        assert_eq!(
+            lead_handle.consolidate(),
+            zset! { ((0, 1, 100), 300) => 1, ((0, 3, 300), -1) => 1, ((1, 1, 5), -1) => 1 }
+        );
+
+        // Inserting a row in the middle of the partition shifts its successor.
+        input_handle.append(&mut vec![((0, 2, 200), 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            lag_handle.consolidate(),
+            zset! {
+                ((0, 1, 100), 0) => 1,
+                ((0, 2, 200), 100) => 1,
+                ((0, 3, 300), 200) => 1,
+                ((1, 1, 5), 0) => 1,
+            }
+        );
+        assert_eq!(
+            lead_handle.consolidate(),
+            zset! {
+                ((0, 1, 100), 200) => 1,
+                ((0, 2, 200), 300) => 1,
+                ((0, 3, 300), -1) => 1,
+                ((1, 1, 5), -1) => 1,
+            }
+        );
+
+        // Inserting a new first row moves the boundary: the old first row now
+        // lags the new one.  A row with weight 2 counts as two rows.
+        input_handle.append(&mut vec![((0, 0, 50), 1), ((1, 2, 7), 2)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            lag_handle.consolidate(),
+            zset! {
+                ((0, 0, 50), 0) => 1,
+                ((0, 1, 100), 50) => 1,
+                ((0, 2, 200), 100) => 1,
+                ((0, 3, 300), 200) => 1,
+                ((1, 1, 5), 0) => 1,
+                ((1, 2, 7), 5) => 1,
+                ((1, 2, 7), 7) => 1,
+            }
+        );
+        assert_eq!(
+            lead_handle.consolidate(),
+            zset! {
+                ((0, 0, 50), 100) => 1,
+                ((0, 1, 100), 200) => 1,
+                ((0, 2, 200), 300) => 1,
+                ((0, 3, 300), -1) => 1,
+                ((1, 1, 5), 7) => 1,
+                ((1, 2, 7), 7) => 1,
+                ((1, 2, 7), -1) => 1,
+            }
+        );
+
+        // Deleting the middle row connects its neighbors.
+        input_handle.append(&mut vec![((0, 2, 200), -1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            lag_handle.consolidate(),
+            zset! {
+                ((0, 0, 50), 0) => 1,
+                ((0, 1, 100), 50) => 1,
+                ((0, 3, 300), 100) => 1,
+                ((1, 1, 5), 0) => 1,
+                ((1, 2, 7), 5) => 1,
+                ((1, 2, 7), 7) => 1,
+            }
+        );
+        assert_eq!(
+            lead_handle.consolidate(),
+            zset! {
+                ((0, 0, 50), 100) => 1,
+                ((0, 1, 100), 300) => 1,
+                ((0, 3, 300), -1) => 1,
+                ((1, 1, 5), 7) => 1,
+                ((1, 2, 7), 7) => 1,
+                ((1, 2, 7), -1) => 1,
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn lag_lead_test1() {
+        lag_lead_test(1);
+    }
+
+    #[test]
+    fn lag_lead_test4() {
+        lag_lead_test(4);
+    }
+}
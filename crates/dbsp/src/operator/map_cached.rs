@@ -0,0 +1,157 @@
+//! Map operator that memoizes the results of an expensive transformation.
+
+use crate::{
+    circuit::{Circuit, Stream},
+    operator::FilterMap,
+    DBData, DBWeight, OrdZSet,
+};
+use hashbrown::HashMap;
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+impl<C, K, R> Stream<C, OrdZSet<K, R>>
+where
+    C: Circuit,
+    K: DBData,
+    R: DBWeight,
+{
+    /// Like [`map`](`FilterMap::map`), but memoizes the results of
+    /// `map_func` in a bounded least-recently-used cache, so that keys that
+    /// recur across clock cycles don't need to be recomputed.
+    ///
+    /// This is useful when `map_func` is expensive, e.g., performs parsing or
+    /// regular expression matching, and the input keys are drawn from a
+    /// low-cardinality domain.  The cache stores up to `cache_size` keys along
+    /// with their images in each worker; a `cache_size` of `0` disables
+    /// caching.
+    ///
+    /// `map_func` must be a pure function: its output must only depend on its
+    /// input, since a cached result may be returned instead of invoking the
+    /// function.
+    pub fn map_cached<F, V>(&self, cache_size: usize, map_func: F) -> Stream<C, OrdZSet<V, R>>
+    where
+        V: DBData,
+        F: Fn(&K) -> V + 'static,
+    {
+        let map_func = Rc::new(map_func);
+        let cache = Rc::new(RefCell::new(LruCache::new(cache_size)));
+
+        self.map(move |key: &K| cache.borrow_mut().get_or_insert_with(key, &*map_func))
+    }
+}
+
+/// A bounded map that evicts least recently used entries.
+struct LruCache<K, V> {
+    capacity: usize,
+    /// Counter used to timestamp cache accesses.
+    clock: u64,
+    /// Cached values along with the timestamp of their last access.
+    entries: HashMap<K, (V, u64)>,
+    /// Cached keys ordered by the timestamp of their last access.
+    lru: BTreeMap<u64, K>,
+}
+
+impl<K, V> LruCache<K, V>
+where
+    K: DBData,
+    V: Clone,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            clock: 0,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the value cached for `key`, computing it with `f` and
+    /// inserting it in the cache on a miss.
+    fn get_or_insert_with<F>(&mut self, key: &K, f: F) -> V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        if self.capacity == 0 {
+            return f(key);
+        }
+
+        self.clock += 1;
+        let now = self.clock;
+
+        if let Some((val, last_access)) = self.entries.get_mut(key) {
+            let key = self.lru.remove(last_access).unwrap();
+            self.lru.insert(now, key);
+            *last_access = now;
+            return val.clone();
+        }
+
+        if self.entries.len() >= self.capacity {
+            if let Some(&oldest) = self.lru.keys().next() {
+                let evicted = self.lru.remove(&oldest).unwrap();
+                self.entries.remove(&evicted);
+            }
+        }
+
+        let val = f(key);
+        self.entries.insert(key.clone(), (val.clone(), now));
+        self.lru.insert(now, key.clone());
+        val
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{zset, Runtime};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn map_cached_test() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+
+        let (mut dbsp, (mut input_handle, output_handle)) =
+            Runtime::init_circuit(1, move |circuit| {
+                let (input, input_handle) = circuit.add_input_zset::<usize, isize>();
+                let output_handle = input
+                    .map_cached(2, move |x| {
+                        calls_clone.fetch_add(1, Ordering::Relaxed);
+                        x * 10
+                    })
+                    .output();
+
+                (input_handle, output_handle)
+            })
+            .unwrap();
+
+        input_handle.append(&mut vec![(1, 1), (2, 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! { 10 => 1, 20 => 1 });
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+        // Repeated keys are served from the cache.
+        input_handle.append(&mut vec![(1, -1), (2, 2)]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! { 10 => -1, 20 => 2 });
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+        // A new key evicts the least recently used key (`1`).
+        input_handle.append(&mut vec![(3, 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! { 30 => 1 });
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        input_handle.append(&mut vec![(2, 1), (3, 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! { 20 => 1, 30 => 1 });
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        input_handle.append(&mut vec![(1, 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(output_handle.consolidate(), zset! { 10 => 1 });
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+
+        dbsp.kill().unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 //! Some basic operators.
 
 pub mod apply2;
+pub mod apply3;
 pub mod communication;
 pub mod recursive;
 
@@ -9,47 +10,70 @@ pub(crate) mod inspect;
 pub(crate) mod upsert;
 
 mod aggregate;
+mod asof_join;
+mod combine_latest;
 mod condition;
+mod connected_components;
 mod consolidate;
+mod count_distinct;
 #[cfg(feature = "with-csv")]
 mod csv;
 mod delta0;
 mod differentiate;
 mod distinct;
+mod emit_on_change;
+#[cfg(feature = "with-regex")]
+mod extract;
 mod filter_map;
+mod filter_with_state;
+mod first_last_value;
 mod generator;
+mod hashjoin;
 mod index;
 mod input;
 mod integrate;
 mod join;
 mod join_range;
+mod lag;
+mod map_cached;
 mod neg;
 mod output;
 mod plus;
+mod rank;
 mod semijoin;
+mod sort;
 mod stream_fold;
 mod sum;
 pub mod time_series;
 mod trace;
+mod transitive_closure;
 mod z1;
 
 #[cfg(feature = "with-csv")]
 pub use self::csv::CsvSource;
-pub use aggregate::{Aggregator, Avg, Fold, Max, MaxSemigroup, Min, MinSemigroup};
-pub use apply::Apply;
+pub use aggregate::{
+    Aggregator, Avg, Fold, Max, MaxSemigroup, Min, MinSemigroup, Percentile, Update,
+};
+pub use apply::{Apply, ApplyStateful};
 pub use condition::Condition;
+pub use count_distinct::ThresholdDirection;
 pub use delta0::Delta0;
 pub use distinct::Distinct;
 pub use filter_map::{FilterKeys, FilterMap, FilterVals, FlatMap, Map, MapKeys};
 pub use generator::{Generator, GeneratorNested};
 pub use index::Index;
 use input::Mailbox;
-pub use input::{CollectionHandle, InputHandle, UpsertHandle};
+pub(crate) use input::NamedInputsId;
+pub use input::{
+    CollectionHandle, InputHandle, NamedInputHandle, Transaction, TransactionInput, UpsertHandle,
+};
 pub use inspect::Inspect;
 pub use join::Join;
 pub use join_range::StreamJoinRange;
 pub use neg::UnaryMinus;
-pub use output::OutputHandle;
+pub(crate) use output::NamedOutputsId;
+pub use output::{NamedOutputHandle, OutputHandle};
 pub use plus::{Minus, Plus};
 pub use sum::Sum;
+pub use trace::TraceBound;
 pub use z1::{DelayedFeedback, DelayedNestedFeedback, Z1Nested, Z1};
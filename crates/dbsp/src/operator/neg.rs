@@ -4,7 +4,7 @@ use crate::{
     algebra::NegByRef,
     circuit::{
         operator_traits::{Operator, UnaryOperator},
-        Circuit, OwnershipPreference, Scope, Stream,
+        CheckpointError, Circuit, OwnershipPreference, Scope, Stream,
     },
 };
 use std::{borrow::Cow, marker::PhantomData, ops::Neg};
@@ -48,6 +48,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T> Default for UnaryMinus<T> {
@@ -2,19 +2,26 @@ use super::Mailbox;
 use crate::{
     circuit::{
         operator_traits::{Operator, SinkOperator},
-        LocalStoreMarker, OwnershipPreference, RootCircuit, Scope,
+        CheckpointError, LocalStoreMarker, OwnershipPreference, RootCircuit, Scope,
     },
+    circuit_cache_key,
     trace::{Batch, Spine, Trace},
     Circuit, Runtime, Stream,
 };
 use std::{
+    any::{type_name, Any},
     borrow::Cow,
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
     hash::{Hash, Hasher},
     marker::PhantomData,
     sync::Arc,
 };
 use typedmap::TypedMapKey;
 
+// Names of outputs registered in a circuit.
+circuit_cache_key!(OutputNameId(String => ()));
+
 impl<T> Stream<RootCircuit, T>
 where
     T: Clone + Send + 'static,
@@ -31,6 +38,120 @@ where
         self.circuit().add_sink(output, self);
         output_handle
     }
+
+    /// Like [`output`](`Self::output`), but also registers the handle
+    /// under `name`.
+    ///
+    /// When the circuit is instantiated using
+    /// [`Runtime::init_circuit`](`crate::Runtime::init_circuit`), all named
+    /// output handles can be enumerated and read via
+    /// [`DBSPHandle::outputs`](`crate::DBSPHandle::outputs`) and
+    /// [`DBSPHandle::take_output`](`crate::DBSPHandle::take_output`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the circuit already contains an output named `name`.
+    pub fn output_named(&self, name: &str) -> OutputHandle<T>
+    where
+        T: Debug,
+    {
+        let name_id = OutputNameId::new(name.to_string());
+        assert!(
+            !self.circuit().cache_contains(&name_id),
+            "duplicate output name '{name}'"
+        );
+        self.circuit().cache_insert(name_id, ());
+
+        let output_handle = self.output();
+
+        // Output handles are shared by all workers, so only register them once.
+        if let Some(runtime) = Runtime::runtime() {
+            if Runtime::worker_index() == 0 {
+                runtime
+                    .local_store()
+                    .entry(NamedOutputsId)
+                    .or_insert_with(HashMap::new)
+                    .insert(
+                        name.to_string(),
+                        NamedOutputHandle::new(output_handle.clone()),
+                    );
+            }
+        }
+
+        output_handle
+    }
+}
+
+/// `TypedMapKey` entry used to collect output handles created by
+/// [`Stream::output_named`] in a runtime.
+#[derive(Hash, PartialEq, Eq)]
+pub(crate) struct NamedOutputsId;
+
+impl TypedMapKey<LocalStoreMarker> for NamedOutputsId {
+    type Value = HashMap<String, NamedOutputHandle>;
+}
+
+/// A type-erased [`OutputHandle`] created by [`Stream::output_named`].
+///
+/// Use [`downcast`](`Self::downcast`) to recover the typed handle.
+#[derive(Clone)]
+pub struct NamedOutputHandle {
+    handle: Arc<dyn Any + Send + Sync>,
+    type_name: &'static str,
+    snapshot: Arc<dyn Fn() -> Vec<String> + Send + Sync>,
+}
+
+impl NamedOutputHandle {
+    fn new<T>(handle: OutputHandle<T>) -> Self
+    where
+        T: Debug + Send + 'static,
+    {
+        let snapshot_handle = handle.clone();
+
+        Self {
+            handle: Arc::new(handle),
+            type_name: type_name::<T>(),
+            snapshot: Arc::new(move || {
+                (0..snapshot_handle.0.mailbox.len())
+                    .map(|worker| {
+                        snapshot_handle.0.mailbox(worker).peek(|val| match val {
+                            Some(val) => format!("{val:?}"),
+                            None => "<none>".to_string(),
+                        })
+                    })
+                    .collect()
+            }),
+        }
+    }
+
+    /// Debug representations of the values produced by each worker during
+    /// the last clock cycle.  Unlike `take_from_worker`, this doesn't remove
+    /// values from the mailboxes.
+    pub(crate) fn snapshot(&self) -> Vec<String> {
+        (self.snapshot)()
+    }
+
+    /// Name of the type of values carried by the output stream.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns the typed output handle, or `None` if the output stream
+    /// doesn't carry values of type `T`.
+    pub fn downcast<T>(&self) -> Option<&OutputHandle<T>>
+    where
+        T: 'static,
+    {
+        self.handle.downcast_ref()
+    }
+}
+
+impl Debug for NamedOutputHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamedOutputHandle")
+            .field("type_name", &self.type_name)
+            .finish()
+    }
 }
 
 /// `TypedMapKey` entry used to share `OutputHandle` objects across workers in a
@@ -259,6 +380,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T> SinkOperator<T> for Output<T>
@@ -280,7 +405,7 @@ where
 
 #[cfg(test)]
 mod test {
-    use crate::{trace::Batch, OrdZSet, Runtime};
+    use crate::{trace::Batch, OrdZSet, RootCircuit, Runtime};
 
     #[test]
     fn test_output_handle() {
@@ -308,4 +433,15 @@ mod test {
 
         dbsp.kill().unwrap();
     }
+
+    // Output names are checked with and without a runtime.
+    #[test]
+    #[should_panic(expected = "duplicate output name 'a'")]
+    fn duplicate_output_name() {
+        let _ = RootCircuit::build(|circuit| {
+            let (zset, _) = circuit.add_input_zset::<u64, isize>();
+            zset.output_named("a");
+            zset.output_named("a");
+        });
+    }
 }
@@ -4,7 +4,7 @@ use crate::{
     algebra::{AddAssignByRef, AddByRef, NegByRef},
     circuit::{
         operator_traits::{BinaryOperator, Operator},
-        Circuit, OwnershipPreference, Scope, Stream,
+        CheckpointError, Circuit, OwnershipPreference, Scope, Stream,
     },
 };
 use std::{
@@ -118,6 +118,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<D> BinaryOperator<D, D, D> for Plus<D>
@@ -175,6 +179,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 // TODO: Add `subtract` operation to `GroupValue`, which
@@ -0,0 +1,548 @@
+//! Incremental rank-based window functions (`PERCENT_RANK`, `CUME_DIST`,
+//! `NTILE`).
+
+use crate::{
+    algebra::{ZRingValue, ZSet, F64},
+    trace::{consolidation::consolidate, Batch, BatchReader, Cursor, Spine},
+    DBData, OrdIndexedZSet, OrdZSet, RootCircuit, Stream,
+};
+use num::{FromPrimitive, ToPrimitive};
+use std::{cmp::min, ops::Neg};
+
+impl<Z> Stream<RootCircuit, Z>
+where
+    Z: ZSet + Send,
+    Z::R: ZRingValue + ToPrimitive,
+{
+    /// Incrementally compute the `PERCENT_RANK` window function.
+    ///
+    /// Splits the input collection into partitions using `key_func` and, for
+    /// each row in each partition, computes the fraction of other rows in the
+    /// partition whose value (computed by `value_func`) is strictly smaller
+    /// than the value of this row:
+    ///
+    /// ```text
+    /// percent_rank(row) = (rank(row) - 1) / (partition_size - 1)
+    /// ```
+    ///
+    /// where `rank(row)` is `1 +` the number of rows with smaller values.
+    /// Rows in single-row partitions have relative rank `0`.  A row with
+    /// weight `w > 0` counts as `w` identical rows; rows with non-positive
+    /// weights are ignored.
+    ///
+    /// Outputs a collection of `(row, percent_rank)` pairs.
+    ///
+    /// # Performance
+    ///
+    /// Inserting or deleting a single row changes the relative rank of other
+    /// rows in its partition, hence every change to a partition is `O(n)`,
+    /// where `n` is the size of the partition, both in terms of work and the
+    /// size of the output.
+    #[allow(clippy::type_complexity)]
+    pub fn percent_rank<PK, V, KF, VF>(
+        &self,
+        key_func: KF,
+        value_func: VF,
+    ) -> Stream<RootCircuit, OrdZSet<(Z::Key, F64), Z::R>>
+    where
+        PK: DBData,
+        V: DBData,
+        KF: Fn(&Z::Key) -> PK + Clone + 'static,
+        VF: Fn(&Z::Key) -> V + 'static,
+    {
+        self.relative_rank(key_func, value_func, |smaller, _, total| {
+            if total <= 1.0 {
+                0.0
+            } else {
+                smaller / (total - 1.0)
+            }
+        })
+    }
+
+    /// Incrementally compute the `CUME_DIST` window function.
+    ///
+    /// Splits the input collection into partitions using `key_func` and, for
+    /// each row in each partition, computes the fraction of rows in the
+    /// partition whose value (computed by `value_func`) is smaller than or
+    /// equal to the value of this row.
+    ///
+    /// Outputs a collection of `(row, cume_dist)` pairs.  See
+    /// [`percent_rank`](`Self::percent_rank`) for the treatment of weights and
+    /// performance considerations.
+    #[allow(clippy::type_complexity)]
+    pub fn cume_dist<PK, V, KF, VF>(
+        &self,
+        key_func: KF,
+        value_func: VF,
+    ) -> Stream<RootCircuit, OrdZSet<(Z::Key, F64), Z::R>>
+    where
+        PK: DBData,
+        V: DBData,
+        KF: Fn(&Z::Key) -> PK + Clone + 'static,
+        VF: Fn(&Z::Key) -> V + 'static,
+    {
+        self.relative_rank(key_func, value_func, |_, not_greater, total| {
+            not_greater / total
+        })
+    }
+
+    /// Incrementally compute the `NTILE(n)` window function.
+    ///
+    /// Splits the input collection into partitions using `key_func`, orders
+    /// rows in each partition by the timestamp computed by `ts_func`, and
+    /// divides each partition into `n` buckets numbered from `1` to `n`.
+    /// Bucket sizes differ by at most one, with larger buckets first: a
+    /// partition of `10` rows is split into buckets of sizes `3, 3, 2, 2` by
+    /// `ntile(4, ..)`.  If the partition has fewer than `n` rows, each row is
+    /// placed in its own bucket.
+    ///
+    /// Rows with equal timestamps are ordered by the rows themselves, so that
+    /// bucket assignment is deterministic.  A row with weight `w > 0` counts
+    /// as `w` identical rows, which may be split across adjacent buckets;
+    /// rows with non-positive weights are ignored.
+    ///
+    /// Outputs a collection of `(row, bucket)` pairs, where the weight of
+    /// each pair is the number of copies of the row in the bucket.
+    ///
+    /// # Performance
+    ///
+    /// Inserting or deleting a single row changes the size of its partition
+    /// and hence can move many other rows to a different bucket.  Every
+    /// change to a partition is therefore `O(n)`, where `n` is the size of
+    /// the partition, both in terms of work and the size of the output.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is `0`.
+    #[allow(clippy::type_complexity)]
+    pub fn ntile<PK, TS, KF, TF>(
+        &self,
+        n: usize,
+        key_func: KF,
+        ts_func: TF,
+    ) -> Stream<RootCircuit, OrdZSet<(Z::Key, usize), Z::R>>
+    where
+        PK: DBData,
+        TS: DBData,
+        KF: Fn(&Z::Key) -> PK + Clone + 'static,
+        TF: Fn(&Z::Key) -> TS + 'static,
+        Z::R: FromPrimitive,
+    {
+        assert!(n > 0, "the number of buckets in 'ntile' must be positive");
+
+        self.recompute_partitions(key_func, move |rows| ntile_partition(rows, n, &ts_func))
+    }
+
+    /// Shared implementation of `percent_rank` and `cume_dist`.
+    ///
+    /// `rank_func` computes the relative rank of a row given the number of
+    /// rows with smaller values, the number of rows with smaller or equal
+    /// values, and the total number of rows in the partition.
+    #[allow(clippy::type_complexity)]
+    fn relative_rank<PK, V, KF, VF>(
+        &self,
+        key_func: KF,
+        value_func: VF,
+        rank_func: fn(f64, f64, f64) -> f64,
+    ) -> Stream<RootCircuit, OrdZSet<(Z::Key, F64), Z::R>>
+    where
+        PK: DBData,
+        V: DBData,
+        KF: Fn(&Z::Key) -> PK + Clone + 'static,
+        VF: Fn(&Z::Key) -> V + 'static,
+    {
+        self.recompute_partitions(key_func, move |rows| {
+            rank_partition(rows, &value_func, rank_func)
+        })
+    }
+
+    /// Shared implementation of window functions that are recomputed for the
+    /// entire partition whenever the partition changes.
+    ///
+    /// `partition_func` takes the contents of a partition and computes the
+    /// window function for each of its rows.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn recompute_partitions<PK, O, KF, PF>(
+        &self,
+        key_func: KF,
+        partition_func: PF,
+    ) -> Stream<RootCircuit, OrdZSet<(Z::Key, O), Z::R>>
+    where
+        PK: DBData,
+        O: DBData,
+        KF: Fn(&Z::Key) -> PK + Clone + 'static,
+        PF: Fn(Vec<(Z::Key, Z::R)>) -> Vec<(Z::Key, O, Z::R)> + 'static,
+    {
+        let partitioned = self
+            .index_with(move |row| (key_func(row), row.clone()))
+            .shard();
+
+        partitioned.apply2(
+            &partitioned.integrate_trace().delay_trace(),
+            move |delta: &OrdIndexedZSet<PK, Z::Key, Z::R>,
+                  delayed_trace: &Spine<OrdIndexedZSet<PK, Z::Key, Z::R>>| {
+                let mut output = Vec::new();
+                let mut delta_cursor = delta.cursor();
+                let mut trace_cursor = delayed_trace.cursor();
+
+                // Recompute the window function for all rows in each modified
+                // partition before and after the update; retract old outputs and
+                // insert new ones.
+                while delta_cursor.key_valid() {
+                    let mut old_rows = Vec::new();
+
+                    trace_cursor.seek_key(delta_cursor.key());
+                    if trace_cursor.key_valid() && trace_cursor.key() == delta_cursor.key() {
+                        while trace_cursor.val_valid() {
+                            old_rows.push((trace_cursor.val().clone(), trace_cursor.weight()));
+                            trace_cursor.step_val();
+                        }
+                    }
+
+                    let mut new_rows = old_rows.clone();
+                    while delta_cursor.val_valid() {
+                        new_rows.push((delta_cursor.val().clone(), delta_cursor.weight()));
+                        delta_cursor.step_val();
+                    }
+                    consolidate(&mut new_rows);
+
+                    for (row, result, weight) in partition_func(old_rows) {
+                        output.push(((row, result), weight.neg()));
+                    }
+                    for (row, result, weight) in partition_func(new_rows) {
+                        output.push(((row, result), weight));
+                    }
+
+                    delta_cursor.step_key();
+                }
+
+                OrdZSet::from_keys((), output)
+            },
+        )
+    }
+}
+
+/// Compute relative ranks of all rows with positive weights in a partition.
+fn rank_partition<K, V, R, VF>(
+    rows: Vec<(K, R)>,
+    value_func: &VF,
+    rank_func: fn(f64, f64, f64) -> f64,
+) -> Vec<(K, F64, R)>
+where
+    V: Ord,
+    R: ZRingValue + ToPrimitive,
+    VF: Fn(&K) -> V,
+{
+    let mut rows: Vec<(V, K, R)> = rows
+        .into_iter()
+        .filter(|(_, weight)| weight.gt0())
+        .map(|(row, weight)| (value_func(&row), row, weight))
+        .collect();
+    rows.sort_by(|(v1, _, _), (v2, _, _)| v1.cmp(v2));
+
+    let count = |weight: &R| weight.to_f64().unwrap_or_default();
+    let total: f64 = rows.iter().map(|(_, _, weight)| count(weight)).sum();
+
+    let mut ranked = Vec::with_capacity(rows.len());
+    let mut smaller = 0.0;
+    let mut rows = rows.into_iter().peekable();
+
+    while let Some((value, row, weight)) = rows.next() {
+        // Collect all rows that share the same value; they have the same rank.
+        let mut group = vec![(row, weight)];
+        while let Some((_, row, weight)) = rows.next_if(|(next, _, _)| *next == value) {
+            group.push((row, weight));
+        }
+
+        let not_greater = smaller + group.iter().map(|(_, weight)| count(weight)).sum::<f64>();
+        let rank = F64::new(rank_func(smaller, not_greater, total));
+        ranked.extend(group.into_iter().map(|(row, weight)| (row, rank, weight)));
+        smaller = not_greater;
+    }
+
+    ranked
+}
+
+/// Assign `NTILE(n)` buckets to all rows with positive weights in a
+/// partition.
+fn ntile_partition<K, TS, R, TF>(rows: Vec<(K, R)>, n: usize, ts_func: &TF) -> Vec<(K, usize, R)>
+where
+    K: Ord + Clone,
+    TS: Ord,
+    R: ZRingValue + ToPrimitive + FromPrimitive,
+    TF: Fn(&K) -> TS,
+{
+    let mut rows: Vec<(TS, K, usize)> = rows
+        .into_iter()
+        .filter(|(_, weight)| weight.gt0())
+        .map(|(row, weight)| (ts_func(&row), row, weight.to_usize().unwrap_or_default()))
+        .collect();
+    // Break ties between equal timestamps using the rows themselves.
+    rows.sort_by(|(ts1, row1, _), (ts2, row2, _)| ts1.cmp(ts2).then_with(|| row1.cmp(row2)));
+
+    // The first `total % n` buckets contain `total / n + 1` rows; the remaining
+    // buckets contain `total / n` rows.
+    let total: usize = rows.iter().map(|(_, _, count)| count).sum();
+    let (size, larger) = (total / n, total % n);
+    let bucket_end = |bucket: usize| {
+        if bucket <= larger {
+            bucket * (size + 1)
+        } else {
+            larger * (size + 1) + (bucket - larger) * size
+        }
+    };
+
+    let mut output = Vec::with_capacity(rows.len());
+    let mut bucket = 1;
+    let mut position = 0;
+
+    for (_, row, count) in rows {
+        // Split the copies of the row across buckets.
+        let end = position + count;
+        while position < end {
+            while bucket_end(bucket) <= position {
+                bucket += 1;
+            }
+            let next = min(end, bucket_end(bucket));
+            output.push((row.clone(), bucket, R::from_usize(next - position).unwrap()));
+            position = next;
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algebra::F64,
+        trace::{consolidation::consolidate, Batch, BatchReader, Cursor},
+        OrdZSet, Runtime,
+    };
+
+    type Row = (usize, isize, usize);
+
+    // Brute-force reference implementation: for each row compute the number of
+    // rows in its partition with smaller and smaller-or-equal values.
+    fn brute_force(
+        rows: &[(Row, isize)],
+        rank_func: fn(f64, f64, f64) -> f64,
+    ) -> OrdZSet<(Row, F64), isize> {
+        let mut output = Vec::new();
+
+        for (row, weight) in rows.iter().filter(|(_, w)| *w > 0) {
+            let partition = rows
+                .iter()
+                .filter(|((p, _, _), w)| *p == row.0 && *w > 0)
+                .collect::<Vec<_>>();
+            let count = |pred: &dyn Fn(isize) -> bool| {
+                partition
+                    .iter()
+                    .filter(|((_, v, _), _)| pred(*v))
+                    .map(|(_, w)| *w as f64)
+                    .sum::<f64>()
+            };
+            let smaller = count(&|v| v < row.1);
+            let not_greater = count(&|v| v <= row.1);
+            let total = count(&|_| true);
+
+            output.push((
+                (*row, F64::new(rank_func(smaller, not_greater, total))),
+                *weight,
+            ));
+        }
+
+        OrdZSet::from_keys((), output)
+    }
+
+    fn percent_rank(smaller: f64, _not_greater: f64, total: f64) -> f64 {
+        if total <= 1.0 {
+            0.0
+        } else {
+            smaller / (total - 1.0)
+        }
+    }
+
+    fn cume_dist(_smaller: f64, not_greater: f64, total: f64) -> f64 {
+        not_greater / total
+    }
+
+    fn relative_rank_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, percent_rank_handle, cume_dist_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (input, input_handle) = circuit.add_input_zset::<Row, isize>();
+
+                let percent_rank = input
+                    .percent_rank(|(p, _, _)| *p, |(_, v, _)| *v)
+                    .integrate()
+                    .output();
+                let cume_dist = input
+                    .cume_dist(|(p, _, _)| *p, |(_, v, _)| *v)
+                    .integrate()
+                    .output();
+
+                (input_handle, percent_rank, cume_dist)
+            })
+            .unwrap();
+
+        let updates: Vec<Vec<(Row, isize)>> = vec![
+            vec![((0, 10, 0), 1)],
+            vec![((0, 20, 1), 1), ((0, 5, 2), 1), ((1, 1, 3), 2)],
+            // Duplicate value in the middle of the partition.
+            vec![((0, 10, 4), 1), ((1, 7, 5), 1)],
+            // Delete the smallest row.
+            vec![((0, 5, 2), -1)],
+            // Insert a new smallest row and delete from another partition.
+            vec![((0, 0, 6), 2), ((1, 1, 3), -1)],
+            // Empty a partition.
+            vec![((1, 1, 3), -1), ((1, 7, 5), -1)],
+        ];
+
+        let mut contents: Vec<(Row, isize)> = Vec::new();
+
+        for mut update in updates {
+            contents.extend(update.iter().cloned());
+            consolidate(&mut contents);
+
+            input_handle.append(&mut update);
+            dbsp.step().unwrap();
+
+            assert_eq!(
+                percent_rank_handle.consolidate(),
+                brute_force(&contents, percent_rank)
+            );
+            assert_eq!(
+                cume_dist_handle.consolidate(),
+                brute_force(&contents, cume_dist)
+            );
+        }
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn relative_rank_test1() {
+        relative_rank_test(1);
+    }
+
+    #[test]
+    fn relative_rank_test4() {
+        relative_rank_test(4);
+    }
+
+    // Reference implementation of `NTILE(4)`: expand rows into copies, sort
+    // them, and fill buckets in order.
+    fn ntile_brute_force(rows: &[(Row, isize)]) -> OrdZSet<(Row, usize), isize> {
+        let mut output = Vec::new();
+
+        for partition in [0, 1] {
+            let mut copies: Vec<Row> = rows
+                .iter()
+                .filter(|((p, _, _), w)| *p == partition && *w > 0)
+                .flat_map(|(row, w)| std::iter::repeat(*row).take(*w as usize))
+                .collect();
+            copies.sort_by_key(|(p, ts, id)| (*ts, *p, *id));
+
+            let total = copies.len();
+            let mut copies = copies.into_iter();
+            for bucket in 1..=4 {
+                let size = total / 4 + usize::from(bucket <= total % 4);
+                for row in copies.by_ref().take(size) {
+                    output.push(((row, bucket), 1));
+                }
+            }
+        }
+
+        OrdZSet::from_keys((), output)
+    }
+
+    fn bucket_sizes(output: &OrdZSet<(Row, usize), isize>, partition: usize) -> Vec<isize> {
+        let mut sizes = vec![0; 4];
+        let mut cursor = output.cursor();
+        while cursor.key_valid() {
+            let ((p, _, _), bucket) = cursor.key();
+            if *p == partition {
+                sizes[bucket - 1] += cursor.weight();
+            }
+            cursor.step_key();
+        }
+        sizes
+    }
+
+    fn buckets(output: &OrdZSet<(Row, usize), isize>, row: Row) -> Vec<usize> {
+        let mut buckets = Vec::new();
+        let mut cursor = output.cursor();
+        while cursor.key_valid() {
+            if cursor.key().0 == row {
+                buckets.push(cursor.key().1);
+            }
+            cursor.step_key();
+        }
+        buckets
+    }
+
+    fn ntile_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, output_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (input, input_handle) = circuit.add_input_zset::<Row, isize>();
+                let output_handle = input
+                    .ntile(4, |(p, _, _)| *p, |(_, ts, _)| *ts)
+                    .integrate()
+                    .output();
+
+                (input_handle, output_handle)
+            })
+            .unwrap();
+
+        let mut contents: Vec<(Row, isize)> = Vec::new();
+        let mut step = |mut update: Vec<(Row, isize)>| {
+            contents.extend(update.iter().cloned());
+            consolidate(&mut contents);
+
+            input_handle.append(&mut update);
+            dbsp.step().unwrap();
+
+            let output = output_handle.consolidate();
+            assert_eq!(output, ntile_brute_force(&contents));
+            output
+        };
+
+        // A partition of 10 rows.
+        let output = step((0..10).map(|i| ((0, i as isize * 10, i), 1)).collect());
+        assert_eq!(bucket_sizes(&output, 0), vec![3, 3, 2, 2]);
+        assert_eq!(buckets(&output, (0, 20, 2)), vec![1]);
+        assert_eq!(buckets(&output, (0, 30, 3)), vec![2]);
+
+        // Inserting a row rebalances the buckets and moves rows that follow
+        // it.
+        let output = step(vec![((0, 25, 10), 1)]);
+        assert_eq!(bucket_sizes(&output, 0), vec![3, 3, 3, 2]);
+        assert_eq!(buckets(&output, (0, 25, 10)), vec![2]);
+        assert_eq!(buckets(&output, (0, 30, 3)), vec![2]);
+        assert_eq!(buckets(&output, (0, 60, 6)), vec![3]);
+
+        // Deleting rows rebalances the buckets again.
+        let output = step(vec![((0, 0, 0), -1), ((0, 10, 1), -1), ((0, 20, 2), -1)]);
+        assert_eq!(bucket_sizes(&output, 0), vec![2, 2, 2, 2]);
+
+        // Ties are broken by row; a row with weight 3 is split across
+        // buckets.
+        step(vec![((1, 5, 0), 1), ((1, 5, 1), 3), ((1, 1, 2), 1)]);
+        // Fewer rows than buckets.
+        step(vec![((1, 5, 1), -3)]);
+        // Empty a partition.
+        step(vec![((1, 5, 0), -1), ((1, 1, 2), -1)]);
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn ntile_test1() {
+        ntile_test(1);
+    }
+
+    #[test]
+    fn ntile_test4() {
+        ntile_test(4);
+    }
+}
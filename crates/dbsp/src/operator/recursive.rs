@@ -5,7 +5,7 @@ use crate::{
     circuit::{schedule::Error as SchedulerError, ChildCircuit, Circuit, Stream, WithClock},
     operator::DelayedFeedback,
     trace::Spine,
-    DBTimestamp,
+    DBTimestamp, RootCircuit,
 };
 use impl_trait_for_tuples::impl_for_tuples;
 use size_of::SizeOf;
@@ -293,12 +293,77 @@ where
     }
 }
 
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+    Spine<B>: SizeOf,
+{
+    /// Iterate `f` starting from `self` until the result stops changing.
+    ///
+    /// Computes the least fixed point of
+    ///
+    /// ```text
+    /// x = distinct(self + f(x))
+    /// ```
+    ///
+    /// in a nested circuit.  `f` receives the recursive stream `x` and
+    /// returns the records derived from it.  Like all streams in a nested
+    /// circuit, `x` carries changes across iterations, so each iteration only
+    /// processes records discovered by the previous one.  Iteration stops as
+    /// soon as an iteration derives no new records, i.e., when all operators
+    /// in the nested circuit report that they have reached a fixed point.
+    /// Because `x` is a set, this happens even if `f` keeps rederiving
+    /// known records, e.g., when following cycles in a graph.
+    ///
+    /// Other streams used by `f` must be imported into the nested circuit
+    /// with [`delta0`](`Stream::delta0`), using the circuit of the stream
+    /// passed to `f`.
+    ///
+    /// This is a shorthand for the common case of
+    /// [`recursive`](`ChildCircuit::recursive`) with a single recursive stream
+    /// seeded by `self`.  Like `recursive`, the computation is maintained
+    /// incrementally as `self` and streams imported by `f` change.
+    ///
+    /// # Example
+    ///
+    /// Compute the set of nodes reachable from a set of root nodes:
+    ///
+    /// ```
+    /// # use dbsp::{OrdZSet, RootCircuit, Stream};
+    /// # fn test(
+    /// #     roots: Stream<RootCircuit, OrdZSet<usize, isize>>,
+    /// #     edges: Stream<RootCircuit, OrdZSet<(usize, usize), isize>>,
+    /// # ) {
+    /// let reachable = roots
+    ///     .iterate_to_fixedpoint(|reachable| {
+    ///         let edges = edges.delta0(reachable.circuit());
+    ///         Ok(reachable
+    ///             .index_with(|node| (*node, ()))
+    ///             .join(&edges.index(), |_from, _, to| *to))
+    ///     })
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn iterate_to_fixedpoint<F>(&self, f: F) -> Result<Stream<RootCircuit, B>, SchedulerError>
+    where
+        F: FnOnce(
+            &Stream<ChildCircuit<RootCircuit>, B>,
+        ) -> Result<Stream<ChildCircuit<RootCircuit>, B>, SchedulerError>,
+    {
+        self.circuit().recursive(|child, x: Stream<_, B>| {
+            let seed = self.delta0(child);
+            Ok(seed.plus(&f(&x)?))
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
         operator::{FilterMap, Generator},
         trace::ord::OrdZSet,
-        zset, Circuit, RootCircuit, Stream,
+        zset, Circuit, RootCircuit, Runtime, Stream,
     };
     use std::vec;
 
@@ -430,4 +495,55 @@ mod test {
             root.step().unwrap();
         }
     }
+
+    fn iterate_to_fixedpoint_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, output_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (edges, input_handle) = circuit.add_input_zset::<(usize, usize), isize>();
+
+                // Transitive closure: extend paths with edges until no new paths are found.
+                let paths = edges
+                    .iterate_to_fixedpoint(|paths| {
+                        let edges = edges.delta0(paths.circuit());
+                        Ok(paths
+                            .index_with(|&(from, via)| (via, from))
+                            .join(&edges.index(), |_via, from, to| (*from, *to)))
+                    })
+                    .unwrap();
+
+                (input_handle, paths.integrate().output())
+            })
+            .unwrap();
+
+        // A cycle: every node reaches every other node and itself.
+        input_handle.append(&mut vec![((1, 2), 1), ((2, 3), 1), ((3, 1), 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! {
+                (1, 1) => 1, (1, 2) => 1, (1, 3) => 1, (2, 1) => 1, (2, 2) => 1,
+                (2, 3) => 1, (3, 1) => 1, (3, 2) => 1, (3, 3) => 1,
+            }
+        );
+
+        // Break the cycle.
+        input_handle.append(&mut vec![((3, 1), -1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! { (1, 2) => 1, (1, 3) => 1, (2, 3) => 1 }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn iterate_to_fixedpoint_test1() {
+        iterate_to_fixedpoint_test(1);
+    }
+
+    #[test]
+    fn iterate_to_fixedpoint_test4() {
+        iterate_to_fixedpoint_test(4);
+    }
 }
@@ -2,7 +2,7 @@ use crate::{
     algebra::{MulByRef, ZSet},
     circuit::{
         operator_traits::{BinaryOperator, Operator},
-        Scope,
+        CheckpointError, Scope,
     },
     circuit::{GlobalNodeId, OwnershipPreference},
     circuit_cache_key,
@@ -88,6 +88,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<Pairs, Keys, Out> BinaryOperator<Pairs, Keys, Out> for SemiJoinStream<Pairs, Keys, Out>
@@ -0,0 +1,103 @@
+//! Ordering records by a computed sort key.
+
+use crate::{
+    circuit::{Circuit, Stream},
+    operator::FilterMap,
+    DBData, DBWeight, OrdIndexedZSet, OrdZSet,
+};
+
+impl<C, K, R> Stream<C, OrdZSet<K, R>>
+where
+    C: Circuit,
+    K: DBData,
+    R: DBWeight,
+{
+    /// Incrementally index records by the sort key computed by `key_func`.
+    ///
+    /// Outputs an indexed Z-set that maps the sort key of each record to the
+    /// record, so that cursoring through the output visits records in the
+    /// order of their sort keys.  Distinct records with the same sort key are
+    /// visited in the order of the records themselves, hence the output
+    /// defines a total order that doesn't depend on the order in which
+    /// records were inserted.
+    ///
+    /// Use [`OrdIndexedZSet::to_sorted_vec`] to extract the sorted records
+    /// from an output batch, e.g., in a sink.
+    ///
+    /// # Example
+    ///
+    /// Order people by age:
+    ///
+    /// ```
+    /// # use dbsp::{OrdIndexedZSet, OrdZSet, RootCircuit, Stream};
+    /// # fn test(people: Stream<RootCircuit, OrdZSet<(String, u32), isize>>) {
+    /// let by_age: Stream<_, OrdIndexedZSet<u32, (String, u32), _>> =
+    ///     people.sort_by(|(_name, age)| *age);
+    /// # }
+    /// ```
+    pub fn sort_by<KO, F>(&self, key_func: F) -> Stream<C, OrdIndexedZSet<KO, K, R>>
+    where
+        KO: DBData,
+        F: Fn(&K) -> KO + 'static,
+    {
+        self.map_index(move |record: &K| (key_func(record), record.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Runtime;
+
+    fn sort_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, output)) = Runtime::init_circuit(workers, |circuit| {
+            let (people, input_handle) = circuit.add_input_zset::<(String, u32), isize>();
+
+            let output = people.sort_by(|(_name, age)| *age).integrate().output();
+
+            (input_handle, output)
+        })
+        .unwrap();
+
+        input_handle.append(&mut vec![
+            (("carol".to_string(), 35), 1),
+            (("bob".to_string(), 25), 1),
+            (("alice".to_string(), 35), 1),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate().to_sorted_vec(),
+            vec![
+                (("bob".to_string(), 25), 1),
+                // Ties are ordered by record.
+                (("alice".to_string(), 35), 1),
+                (("carol".to_string(), 35), 1),
+            ]
+        );
+
+        input_handle.append(&mut vec![
+            (("bob".to_string(), 25), -1),
+            (("dave".to_string(), 30), 2),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate().to_sorted_vec(),
+            vec![
+                (("dave".to_string(), 30), 2),
+                (("alice".to_string(), 35), 1),
+                (("carol".to_string(), 35), 1),
+            ]
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn sort_test1() {
+        sort_test(1);
+    }
+
+    #[test]
+    fn sort_test4() {
+        sort_test(4);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    circuit::OwnershipPreference,
+    circuit::{Checkpoint, OwnershipPreference},
     operator::{z1::DelayedId, Z1},
     Circuit, NumEntries, RootCircuit, Stream,
 };
@@ -21,7 +21,7 @@ where
     pub fn stream_fold<A, F>(&self, init: A, fold_func: F) -> Stream<RootCircuit, A>
     where
         F: Fn(A, &T) -> A + 'static,
-        A: Eq + Clone + SizeOf + NumEntries + 'static,
+        A: Eq + Clone + SizeOf + NumEntries + Checkpoint + 'static,
     {
         let (prev_accumulator, feedback) = self.circuit().add_feedback(Z1::new(init));
         let new_accumulator = prev_accumulator.apply2_owned(self, fold_func);
@@ -4,7 +4,7 @@ use crate::{
     algebra::{AddAssignByRef, AddByRef, HasZero},
     circuit::{
         operator_traits::{NaryOperator, Operator},
-        Circuit, OwnershipPreference, Scope, Stream,
+        CheckpointError, Circuit, OwnershipPreference, Scope, Stream,
     },
     NumEntries,
 };
@@ -71,6 +71,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<D> NaryOperator<D, D> for Sum<D>
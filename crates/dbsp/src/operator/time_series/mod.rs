@@ -2,11 +2,14 @@ mod partitioned;
 mod radix_tree;
 mod range;
 mod rolling_aggregate;
+mod session;
 mod watermark;
 mod window;
+mod window_aggregate;
 
 pub use partitioned::{
     OrdPartitionedIndexedZSet, PartitionCursor, PartitionedBatch, PartitionedBatchReader,
     PartitionedIndexedZSet,
 };
 pub use range::{Range, RelOffset, RelRange};
+pub use window_aggregate::WindowKind;
@@ -3,7 +3,7 @@ use crate::{
     algebra::{HasOne, HasZero, Semigroup, ZRingValue},
     circuit::{
         operator_traits::{Operator, TernaryOperator},
-        GlobalNodeId, OwnershipPreference, Scope,
+        CheckpointError, GlobalNodeId, OwnershipPreference, Scope,
     },
     circuit_cache_key,
     operator::{
@@ -364,6 +364,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<TS, V, Z, IT, OT, Agg, O> TernaryOperator<Z, IT, OT, O>
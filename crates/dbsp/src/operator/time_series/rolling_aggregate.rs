@@ -2,7 +2,7 @@ use crate::{
     algebra::{DefaultSemigroup, GroupValue, HasOne, HasZero, IndexedZSet, MulByRef, ZRingValue},
     circuit::{
         operator_traits::{Operator, QuaternaryOperator},
-        OwnershipPreference, Scope,
+        CheckpointError, OwnershipPreference, Scope,
     },
     operator::{
         time_series::{
@@ -469,6 +469,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<TS, V, Agg, B, T, RT, OT, O> QuaternaryOperator<B, T, RT, OT, O>
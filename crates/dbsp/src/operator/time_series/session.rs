@@ -0,0 +1,228 @@
+//! Session windows.
+
+use crate::{
+    algebra::{HasOne, IndexedZSet, ZRingValue},
+    operator::Aggregator,
+    trace::{consolidation::consolidate, Batch, BatchReader, Cursor, Spine},
+    DBData, OrdIndexedZSet, OrdZSet, RootCircuit, Stream,
+};
+use num::PrimInt;
+use std::ops::Neg;
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Aggregate events into session windows.
+    ///
+    /// For each key in the input indexed Z-set, orders the values of the key
+    /// by the timestamp computed by `ts_func` and splits them into sessions:
+    /// maximal runs of values where consecutive timestamps are at most `gap`
+    /// apart.  Outputs one `(key, (start, end, aggregate))` row per session,
+    /// where `start` and `end` are the smallest and largest timestamps in
+    /// the session, and `aggregate` is computed by `aggregator` over the
+    /// values in the session.  Values with non-positive weights are ignored.
+    ///
+    /// The output is updated incrementally: when an event extends a session
+    /// or bridges the gap between several sessions, the operator retracts
+    /// the old session rows and inserts the new (merged) session.
+    /// Conversely, deleting an event can split a session in several.
+    ///
+    /// # Performance
+    ///
+    /// Sessions are recomputed for the entire key whenever any of its
+    /// values change, so the cost of an update is proportional to the
+    /// number of values of the key, not to the size of the affected
+    /// sessions.  This operator only works in the root scope.
+    #[allow(clippy::type_complexity)]
+    pub fn session_window<TS, A, TF>(
+        &self,
+        ts_func: TF,
+        gap: TS,
+        aggregator: A,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (TS, TS, A::Output), B::R>>
+    where
+        TS: DBData + PrimInt,
+        A: Aggregator<B::Val, (), B::R>,
+        TF: Fn(&B::Val) -> TS + 'static,
+    {
+        let stream = self.shard();
+
+        stream
+            .apply2(
+                &stream.integrate_trace().delay_trace(),
+                move |delta: &B, delayed_trace: &Spine<B>| {
+                    let mut output = Vec::new();
+                    let mut delta_cursor = delta.cursor();
+                    let mut trace_cursor = delayed_trace.cursor();
+
+                    // Recompute sessions of each modified key before and
+                    // after the update; retract old sessions and insert new
+                    // ones.  Sessions that didn't change cancel out.
+                    while delta_cursor.key_valid() {
+                        let key = delta_cursor.key();
+                        let mut old_vals = Vec::new();
+
+                        trace_cursor.seek_key(key);
+                        if trace_cursor.key_valid() && trace_cursor.key() == key {
+                            while trace_cursor.val_valid() {
+                                old_vals.push((trace_cursor.val().clone(), trace_cursor.weight()));
+                                trace_cursor.step_val();
+                            }
+                        }
+
+                        let mut new_vals = old_vals.clone();
+                        while delta_cursor.val_valid() {
+                            new_vals.push((delta_cursor.val().clone(), delta_cursor.weight()));
+                            delta_cursor.step_val();
+                        }
+                        consolidate(&mut new_vals);
+
+                        for session in sessions(old_vals, &ts_func, gap, &aggregator) {
+                            output.push(((key.clone(), session), B::R::one().neg()));
+                        }
+                        for session in sessions(new_vals, &ts_func, gap, &aggregator) {
+                            output.push(((key.clone(), session), B::R::one()));
+                        }
+
+                        delta_cursor.step_key();
+                    }
+
+                    OrdIndexedZSet::from_tuples((), output)
+                },
+            )
+            .mark_sharded()
+    }
+}
+
+/// Splits values with positive weights into sessions and computes the
+/// `(start, end, aggregate)` tuple of each session.
+fn sessions<V, R, TS, TF, A>(
+    vals: Vec<(V, R)>,
+    ts_func: &TF,
+    gap: TS,
+    aggregator: &A,
+) -> Vec<(TS, TS, A::Output)>
+where
+    V: DBData,
+    R: ZRingValue,
+    TS: DBData + PrimInt,
+    TF: Fn(&V) -> TS,
+    A: Aggregator<V, (), R>,
+{
+    let mut vals: Vec<(TS, V, R)> = vals
+        .into_iter()
+        .filter(|(_, weight)| weight.gt0())
+        .map(|(val, weight)| (ts_func(&val), val, weight))
+        .collect();
+    vals.sort_by(|(ts1, _, _), (ts2, _, _)| ts1.cmp(ts2));
+
+    let mut sessions = Vec::new();
+    let mut start = 0;
+
+    for end in 1..=vals.len() {
+        if end == vals.len() || vals[end].0 - vals[end - 1].0 > gap {
+            let session = &vals[start..end];
+            let batch = OrdZSet::from_keys(
+                (),
+                session
+                    .iter()
+                    .map(|(_, val, weight)| (val.clone(), weight.clone()))
+                    .collect(),
+            );
+
+            if let Some(aggregate) = aggregator.aggregate_and_finalize(&mut batch.cursor()) {
+                sessions.push((session[0].0, session[session.len() - 1].0, aggregate));
+            }
+            start = end;
+        }
+    }
+
+    sessions
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{algebra::DefaultSemigroup, indexed_zset, operator::Fold, Runtime};
+
+    // Events are `(timestamp, amount)` pairs.
+    type Event = (u64, i64);
+
+    fn session_window_test(workers: usize) {
+        let (mut dbsp, (mut input, output)) = Runtime::init_circuit(workers, |circuit| {
+            let (stream, handle) = circuit.add_input_indexed_zset::<u64, Event, isize>();
+
+            let total = <Fold<_, DefaultSemigroup<_>, _, _>>::new(
+                0,
+                |acc: &mut i64, (_ts, amount): &Event, w: isize| *acc += *amount * w as i64,
+            );
+            let sessions = stream.session_window(|(ts, _)| *ts, 10, total);
+
+            (handle, sessions.integrate().output())
+        })
+        .unwrap();
+
+        // Three separate sessions for user 1, one for user 2.
+        input.append(&mut vec![
+            (1, ((0, 1), 1)),
+            (1, ((5, 2), 1)),
+            (1, ((20, 4), 1)),
+            (1, ((35, 8), 1)),
+            (2, ((0, 100), 1)),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { (0, 5, 3) => 1, (20, 20, 4) => 1, (35, 35, 8) => 1 },
+                2 => { (0, 0, 100) => 1 },
+            }
+        );
+
+        // A late event extends the first session without merging it.
+        input.append(&mut vec![(1, ((8, 16), 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { (0, 8, 19) => 1, (20, 20, 4) => 1, (35, 35, 8) => 1 },
+                2 => { (0, 0, 100) => 1 },
+            }
+        );
+
+        // Late events bridge the gaps between all three sessions at once.
+        input.append(&mut vec![(1, ((14, 32), 1)), (1, ((28, 64), 1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { (0, 35, 127) => 1 },
+                2 => { (0, 0, 100) => 1 },
+            }
+        );
+
+        // Deleting a bridging event splits the session again.
+        input.append(&mut vec![(1, ((28, 64), -1))]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { (0, 20, 55) => 1, (35, 35, 8) => 1 },
+                2 => { (0, 0, 100) => 1 },
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn session_window_test1() {
+        session_window_test(1);
+    }
+
+    #[test]
+    fn session_window_test4() {
+        session_window_test(4);
+    }
+}
@@ -1,7 +1,10 @@
 use crate::{
+    algebra::{IndexedZSet, TotalOrder, ZRingValue},
+    circuit::Checkpoint,
     operator::communication::new_exchange_operators,
-    trace::{cursor::Cursor, BatchReader},
-    Circuit, NumEntries, RootCircuit, Runtime, Stream,
+    time::Antichain,
+    trace::{cursor::Cursor, Batch, BatchReader, Spine},
+    Circuit, DBData, NumEntries, OrdZSet, RootCircuit, Runtime, Stream,
 };
 use size_of::SizeOf;
 use std::{cmp::max, panic::Location};
@@ -33,7 +36,7 @@ where
     pub fn watermark_monotonic<W, TS>(&self, watermark_func: W) -> Stream<RootCircuit, TS>
     where
         W: Fn(&B::Key) -> TS + 'static,
-        TS: Ord + Clone + Default + SizeOf + NumEntries + Send + 'static,
+        TS: Ord + Clone + Default + SizeOf + NumEntries + Checkpoint + Send + 'static,
     {
         let local_watermark = self.stream_fold(TS::default(), move |old_watermark, batch| {
             let mut cursor = batch.cursor();
@@ -74,9 +77,111 @@ where
     }
 }
 
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet,
+    B::R: ZRingValue,
+{
+    /// Emit a watermark for the records still in flight at every clock
+    /// cycle.
+    ///
+    /// Treats `self` as a stream of changes to the set of records that are
+    /// still being processed, i.e., a record is inserted when it enters the
+    /// pipeline and deleted once all outputs derived from it have been
+    /// emitted.  `ts_func` extracts the event time of a record.  Outputs a
+    /// stream of watermarks that downstream consumers can use to trigger
+    /// their own windows: a watermark `W` guarantees that all data with
+    /// timestamps below `W` has been emitted.
+    ///
+    /// Each worker computes the [frontier](`Antichain`) of the timestamps it
+    /// holds in flight; the frontiers of all workers are combined into a
+    /// global frontier.  The watermark is the least element of the global
+    /// frontier, or the previous watermark if there are no records in
+    /// flight.  The watermark never moves backward: records whose timestamps
+    /// are below the current watermark are considered late and don't hold
+    /// back the watermark.  The initial watermark is `TS::default()`.
+    ///
+    /// This is a stateful operator that internally maintains the set of
+    /// timestamps in flight indexed by timestamp, so the frontier is
+    /// computed by looking up the smallest timestamp in the trace.
+    #[track_caller]
+    pub fn emit_watermark<F, TS>(&self, ts_func: F) -> Stream<RootCircuit, TS>
+    where
+        F: Fn(&B::Key, &B::Val) -> TS + 'static,
+        TS: DBData + TotalOrder + Default + NumEntries + Checkpoint,
+    {
+        let timestamps = self
+            .apply(move |batch: &B| {
+                let mut timestamps = Vec::with_capacity(batch.len());
+                let mut cursor = batch.cursor();
+
+                while cursor.key_valid() {
+                    while cursor.val_valid() {
+                        timestamps.push((ts_func(cursor.key(), cursor.val()), cursor.weight()));
+                        cursor.step_val();
+                    }
+                    cursor.step_key();
+                }
+
+                OrdZSet::from_keys((), timestamps)
+            })
+            // Co-locate insertions and deletions of the same timestamp.
+            .shard();
+
+        let local_frontier =
+            timestamps
+                .integrate_trace()
+                .apply(|trace: &Spine<OrdZSet<TS, B::R>>| {
+                    let mut cursor = trace.cursor();
+
+                    while cursor.key_valid() {
+                        let weight = cursor.weight();
+                        if weight.gt0() {
+                            return Antichain::from_elem(cursor.key().clone());
+                        }
+                        cursor.step_key();
+                    }
+
+                    Antichain::new()
+                });
+
+        let frontier = match Runtime::runtime() {
+            Some(runtime) if runtime.num_workers() > 1 => {
+                let num_workers = runtime.num_workers();
+
+                let (sender, receiver) = new_exchange_operators(
+                    &runtime,
+                    Runtime::worker_index(),
+                    Some(Location::caller()),
+                    move |frontier: Antichain<TS>, frontiers: &mut Vec<Antichain<TS>>| {
+                        for _ in 0..num_workers {
+                            frontiers.push(frontier.clone());
+                        }
+                    },
+                    |result: &mut Antichain<TS>, frontier| {
+                        result.extend(frontier);
+                    },
+                );
+
+                self.circuit()
+                    .add_exchange(sender, receiver, &local_frontier)
+            }
+            _ => local_frontier,
+        };
+
+        frontier.stream_fold(TS::default(), |watermark, frontier| {
+            match frontier.as_option() {
+                Some(ts) => max(watermark, ts.clone()),
+                None => watermark,
+            }
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::Runtime;
+    use std::collections::BTreeMap;
 
     fn test_watermark_monotonic(workers: usize) {
         let mut expected_watermarks = vec![115, 115, 125, 145].into_iter();
@@ -118,4 +223,67 @@ mod tests {
     fn test_watermark_monotonic4() {
         test_watermark_monotonic(4);
     }
+
+    fn test_emit_watermark(workers: usize) {
+        let (mut dbsp, (mut input_handle, output_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                // Events in flight: event id -> timestamp.
+                let (events, input_handle) = circuit.add_input_indexed_zset::<u64, u64, isize>();
+                let output_handle = events.emit_watermark(|_id, ts| *ts).output();
+
+                (input_handle, output_handle)
+            })
+            .unwrap();
+
+        let steps: Vec<Vec<(u64, (u64, isize))>> = vec![
+            vec![(1, (10, 1)), (2, (20, 1)), (3, (15, 1))],
+            // Event 1 has been processed.
+            vec![(1, (10, -1)), (4, (30, 1))],
+            vec![(2, (20, -1)), (5, (15, 1))],
+            vec![(3, (15, -1)), (5, (15, -1))],
+            // Nothing in flight: the watermark doesn't move.
+            vec![(4, (30, -1))],
+            vec![(6, (40, 1)), (7, (45, 1))],
+            vec![(6, (40, -1))],
+        ];
+
+        let mut in_flight = BTreeMap::new();
+        let mut watermarks = Vec::new();
+
+        for mut step in steps {
+            for (_id, (ts, weight)) in step.iter() {
+                *in_flight.entry(*ts).or_insert(0) += weight;
+            }
+            in_flight.retain(|_, weight| *weight != 0);
+
+            input_handle.append(&mut step);
+            dbsp.step().unwrap();
+
+            let watermark = output_handle.take_from_worker(0).unwrap();
+
+            // The watermark advances monotonically and never exceeds the
+            // minimum timestamp in flight.
+            if let Some(&previous) = watermarks.last() {
+                assert!(watermark >= previous);
+            }
+            if let Some(&min_ts) = in_flight.keys().next() {
+                assert!(watermark <= min_ts);
+            }
+            watermarks.push(watermark);
+        }
+
+        assert_eq!(watermarks, vec![10, 15, 15, 30, 30, 40, 45]);
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn test_emit_watermark1() {
+        test_emit_watermark(1);
+    }
+
+    #[test]
+    fn test_emit_watermark4() {
+        test_emit_watermark(4);
+    }
 }
@@ -1,10 +1,12 @@
 //! Operators to organize time series data into windows.
 
+#[cfg(feature = "persistence")]
+use crate::circuit::checkpoint::{decode_state, encode_state};
 use crate::{
     algebra::{IndexedZSet, NegByRef},
     circuit::{
         operator_traits::{Operator, TernaryOperator},
-        Circuit, OwnershipPreference, Scope, Stream,
+        CheckpointError, Circuit, OwnershipPreference, Scope, Stream,
     },
     operator::trace::TraceBound,
     trace::{cursor::Cursor, BatchReader, Spine},
@@ -125,6 +127,30 @@ where
         // Do we have meaningful examples of using windows inside nested scopes?
         panic!("'Window' operator used in fixedpoint iteration")
     }
+
+    #[cfg(feature = "persistence")]
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        match &self.window {
+            None => Ok(None),
+            Some(window) => encode_state(window).map(Some),
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        self.window = Some(decode_state(state)?);
+        Ok(())
+    }
+
+    // Window bounds can only be serialized when the `persistence` feature
+    // makes all data types serializable.
+    #[cfg(not(feature = "persistence"))]
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        match &self.window {
+            None => Ok(None),
+            Some(_) => Err(CheckpointError::Unsupported),
+        }
+    }
 }
 
 impl<B> TernaryOperator<Spine<B>, B, (B::Key, B::Key), B> for Window<B>
@@ -0,0 +1,509 @@
+//! Tumbling and sliding window aggregates.
+
+use crate::{
+    algebra::{HasOne, HasZero, IndexedZSet, Semigroup, ZRingValue},
+    circuit::{
+        operator_traits::{Operator, QuaternaryOperator},
+        CheckpointError, Scope,
+    },
+    operator::{
+        time_series::{
+            radix_tree::{PartitionedRadixTreeReader, Prefix, RadixTreeCursor, TreeNode},
+            PartitionCursor, PartitionedBatchReader, Range,
+        },
+        Aggregator, FilterMap,
+    },
+    trace::{Batch, BatchReader, Builder, Cursor},
+    Circuit, DBData, OrdIndexedZSet, RootCircuit, Stream,
+};
+use num::PrimInt;
+use std::{borrow::Cow, collections::BTreeSet, iter::successors, marker::PhantomData, ops::Neg};
+
+/// Shape of the windows computed by
+/// [`window_aggregate`](`Stream::window_aggregate`).
+///
+/// Windows are right-open time ranges `[start, start + size)` aligned to
+/// multiples of their step, i.e., `start % step == 0`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowKind<TS> {
+    /// Non-overlapping windows of length `size`: each timestamp belongs to
+    /// exactly one window.
+    Tumbling { size: TS },
+    /// Windows of length `size` that start every `step` time units.  When
+    /// `step < size`, windows overlap and each timestamp belongs to up to
+    /// `size / step` windows (rounded up).
+    Sliding { size: TS, step: TS },
+}
+
+impl<TS> WindowKind<TS>
+where
+    TS: PrimInt,
+{
+    /// Length of each window.
+    pub fn size(&self) -> TS {
+        match *self {
+            Self::Tumbling { size } => size,
+            Self::Sliding { size, .. } => size,
+        }
+    }
+
+    /// Distance between the starts of consecutive windows.
+    pub fn step(&self) -> TS {
+        match *self {
+            Self::Tumbling { size } => size,
+            Self::Sliding { step, .. } => step,
+        }
+    }
+
+    /// Start times of all windows that contain `ts`, latest first.
+    fn starts(&self, ts: TS) -> impl Iterator<Item = TS> {
+        let size = self.size();
+        let step = self.step();
+
+        // Round `ts` down to a multiple of `step`.
+        let rem = ts % step;
+        let last = if rem < TS::zero() {
+            ts - rem - step
+        } else {
+            ts - rem
+        };
+
+        successors(Some(last), move |start| start.checked_sub(&step))
+            .take_while(move |start| ts - *start < size)
+    }
+
+    /// Smallest start time of a window that is still open, i.e., such that
+    /// `watermark < start + size + lateness`.
+    fn first_open(&self, watermark: TS, lateness: TS) -> TS {
+        watermark
+            .checked_sub(&(self.size() + lateness))
+            .map(|start| start + TS::one())
+            .unwrap_or_else(TS::min_value)
+    }
+}
+
+impl<B> Stream<RootCircuit, B>
+where
+    B: IndexedZSet + Send,
+    B::R: ZRingValue,
+{
+    /// Aggregate events into tumbling or sliding time windows.
+    ///
+    /// For each key in the input indexed Z-set, assigns its values to
+    /// windows based on the timestamp computed by `ts_func` (see
+    /// [`WindowKind`]) and outputs one `(key, (start, end, aggregate))` row
+    /// for each non-empty window, where `[start, end)` is the time range of
+    /// the window and `aggregate` is computed by `aggregator` over the values
+    /// of the key in the window.
+    ///
+    /// The output is updated incrementally: when the contents of a window
+    /// change, the operator retracts its old aggregate and inserts the new
+    /// one.
+    ///
+    /// Each value is stored once, regardless of the number of windows it
+    /// belongs to.  The operator maintains a radix tree of partial
+    /// aggregates over the timeline of each key (see
+    /// [`partitioned_tree_aggregate`](`Stream::partitioned_tree_aggregate`))
+    /// and computes the aggregate of a window by combining the partial
+    /// aggregates that cover it using `A::Semigroup`, which therefore must
+    /// not be [`UnimplementedSemigroup`](`crate::algebra::UnimplementedSemigroup`).
+    /// The input is sharded by key.
+    ///
+    /// # Lateness
+    ///
+    /// The `watermark` stream provides a monotonically growing lower bound
+    /// on timestamps in the input stream, e.g., computed by
+    /// [`watermark_monotonic`](`Stream::watermark_monotonic`).  A window
+    /// expires once the watermark reaches `end + lateness`.  The aggregate of
+    /// an expired window is final: updates to it are dropped and its contents
+    /// are removed from the state of the operator.  Records that only belong
+    /// to expired windows are therefore ignored, so `lateness` must be
+    /// chosen to cover the expected out-of-orderness of the input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size or the step of `window` is not positive.
+    #[allow(clippy::type_complexity)]
+    pub fn window_aggregate<TS, A, TF>(
+        &self,
+        watermark: &Stream<RootCircuit, TS>,
+        ts_func: TF,
+        window: WindowKind<TS>,
+        lateness: TS,
+        aggregator: A,
+    ) -> Stream<RootCircuit, OrdIndexedZSet<B::Key, (TS, TS, A::Output), B::R>>
+    where
+        Self: for<'a> FilterMap<RootCircuit, ItemRef<'a> = (&'a B::Key, &'a B::Val), R = B::R>,
+        TS: DBData + PrimInt,
+        A: Aggregator<B::Val, (), B::R>,
+        A::Accumulator: Default,
+        TF: Fn(&B::Val) -> TS + Clone + 'static,
+    {
+        assert!(window.size() > TS::zero(), "window size must be positive");
+        assert!(window.step() > TS::zero(), "window step must be positive");
+
+        self.circuit().region("window_aggregate", || {
+            // Start of the earliest window that is still open.
+            let first_open =
+                watermark.apply(move |watermark| window.first_open(*watermark, lateness));
+
+            // Values in expired windows are no longer needed.  Restrict the
+            // input to open windows, which requires indexing it by time, and
+            // build a radix tree over the timeline of each key.
+            let ts_func_clone = ts_func.clone();
+            let tree = self
+                .map_index(move |(key, val)| (ts_func_clone(val), (key.clone(), val.clone())))
+                .window(&first_open.apply(|first_open| (*first_open, TS::max_value())))
+                .map_index(|(ts, (key, val))| (key.clone(), (*ts, val.clone())))
+                .partitioned_tree_aggregate::<TS, B::Val, A>(aggregator.clone())
+                .integrate_trace();
+
+            // Updates to the input identify the windows to recompute.
+            let delta = self
+                .map_index(move |(key, val)| (key.clone(), (ts_func(val), val.clone())))
+                .shard();
+
+            self.circuit()
+                .add_quaternary_operator(
+                    <WindowAggregate<TS, B::Val, A>>::new(window, aggregator),
+                    &delta,
+                    &tree,
+                    &tree.delay_trace(),
+                    &first_open,
+                )
+                .mark_sharded()
+        })
+    }
+}
+
+/// Quaternary operator that implements the internals of `window_aggregate`.
+///
+/// * Input stream 1: updates to the input stream indexed by key and time.
+///   Used to identify affected windows.
+/// * Input stream 2: trace of the partitioned radix tree over values in open
+///   windows.
+/// * Input stream 3: the same trace delayed by one clock cycle.  Used to
+///   compute retractions.
+/// * Input stream 4: start of the earliest open window.
+struct WindowAggregate<TS, V, A> {
+    window: WindowKind<TS>,
+    aggregator: A,
+    phantom: PhantomData<V>,
+}
+
+impl<TS, V, A> WindowAggregate<TS, V, A> {
+    fn new(window: WindowKind<TS>, aggregator: A) -> Self {
+        Self {
+            window,
+            aggregator,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<TS, V, A> Operator for WindowAggregate<TS, V, A>
+where
+    TS: 'static,
+    V: 'static,
+    A: 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::from("WindowAggregate")
+    }
+
+    fn fixedpoint(&self, _scope: Scope) -> bool {
+        true
+    }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
+}
+
+impl<TS, V, A, B, T, O> QuaternaryOperator<B, T, T, TS, O> for WindowAggregate<TS, V, A>
+where
+    TS: DBData + PrimInt,
+    V: DBData,
+    A: Aggregator<V, (), B::R>,
+    B: PartitionedBatchReader<TS, V> + Clone,
+    B::R: ZRingValue,
+    T: PartitionedRadixTreeReader<TS, A::Accumulator, Key = B::Key> + Clone,
+    O: IndexedZSet<Key = B::Key, Val = (TS, TS, A::Output), R = B::R>,
+{
+    fn eval<'a>(
+        &mut self,
+        delta: Cow<'a, B>,
+        tree: Cow<'a, T>,
+        delayed_tree: Cow<'a, T>,
+        first_open: Cow<'a, TS>,
+    ) -> O {
+        let first_open = *first_open;
+
+        let mut delta_cursor = delta.cursor();
+        let mut tree_cursor = tree.cursor();
+        let mut delayed_tree_cursor = delayed_tree.cursor();
+
+        let mut retraction_builder = O::Builder::new_builder(());
+        let mut insertion_builder = O::Builder::with_capacity((), delta.len());
+
+        while delta_cursor.key_valid() {
+            let key = delta_cursor.key().clone();
+
+            // Open windows that contain updated values.  Updates to expired
+            // windows are dropped.
+            let mut starts = BTreeSet::new();
+            while delta_cursor.val_valid() {
+                let ts = delta_cursor.val().0;
+                starts.extend(
+                    self.window
+                        .starts(ts)
+                        .take_while(|start| *start >= first_open),
+                );
+                delta_cursor.step_val();
+            }
+
+            tree_cursor.seek_key(&key);
+            delayed_tree_cursor.seek_key(&key);
+
+            for start in starts {
+                let end = start.saturating_add(self.window.size());
+                let range = Range::new(start, end - TS::one());
+
+                let old = aggregate_range::<A::Semigroup, _, _, _, _, _>(
+                    &mut delayed_tree_cursor,
+                    &key,
+                    &range,
+                )
+                .map(|acc| self.aggregator.finalize(acc));
+                let new =
+                    aggregate_range::<A::Semigroup, _, _, _, _, _>(&mut tree_cursor, &key, &range)
+                        .map(|acc| self.aggregator.finalize(acc));
+                if old == new {
+                    continue;
+                }
+
+                if let Some(old) = old {
+                    retraction_builder.push((
+                        O::item_from(key.clone(), (start, end, old)),
+                        B::R::one().neg(),
+                    ));
+                }
+                if let Some(new) = new {
+                    insertion_builder
+                        .push((O::item_from(key.clone(), (start, end, new)), B::R::one()));
+                }
+            }
+
+            delta_cursor.step_key();
+        }
+
+        let retractions = retraction_builder.done();
+        let insertions = insertion_builder.done();
+        retractions.add(insertions)
+    }
+}
+
+/// Computes the partial aggregate over `range` in the radix tree of
+/// partition `key`, or `None` if the range is empty.
+///
+/// `cursor` must not be positioned past `key`.
+fn aggregate_range<S, PK, TS, A, R, C>(cursor: &mut C, key: &PK, range: &Range<TS>) -> Option<A>
+where
+    S: Semigroup<A>,
+    PK: Eq,
+    TS: DBData + PrimInt,
+    A: DBData,
+    R: HasZero,
+    C: Cursor<PK, (Prefix<TS>, TreeNode<TS, A>), (), R>,
+{
+    if !cursor.key_valid() || cursor.key() != key {
+        return None;
+    }
+
+    let mut partition_cursor = PartitionCursor::new(cursor);
+    partition_cursor.rewind_keys();
+    partition_cursor.aggregate_range::<S>(range)
+}
+
+#[cfg(test)]
+mod test {
+    use super::WindowKind;
+    use crate::{algebra::DefaultSemigroup, indexed_zset, operator::Fold, Runtime};
+
+    // Events are `(timestamp, amount)` pairs.
+    type Event = (u64, i64);
+
+    #[test]
+    fn window_starts() {
+        let tumbling = WindowKind::Tumbling { size: 10i64 };
+        assert_eq!(tumbling.starts(0).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(tumbling.starts(19).collect::<Vec<_>>(), vec![10]);
+        assert_eq!(tumbling.starts(-1).collect::<Vec<_>>(), vec![-10]);
+
+        let sliding = WindowKind::Sliding {
+            size: 10i64,
+            step: 4,
+        };
+        assert_eq!(sliding.starts(9).collect::<Vec<_>>(), vec![8, 4, 0]);
+        assert_eq!(sliding.starts(12).collect::<Vec<_>>(), vec![12, 8, 4]);
+        assert_eq!(sliding.starts(-3).collect::<Vec<_>>(), vec![-4, -8]);
+
+        // Windows with gaps between them.
+        let hopping = WindowKind::Sliding {
+            size: 2u64,
+            step: 5,
+        };
+        assert_eq!(hopping.starts(6).collect::<Vec<_>>(), vec![5]);
+        assert_eq!(hopping.starts(7).collect::<Vec<_>>(), Vec::<u64>::new());
+        assert_eq!(hopping.starts(1).collect::<Vec<_>>(), vec![0]);
+    }
+
+    fn tumbling_window_test(workers: usize) {
+        let (mut dbsp, (mut input, watermark, output)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (stream, handle) = circuit.add_input_indexed_zset::<u64, Event, isize>();
+                let (watermark, watermark_handle) = circuit.add_input_stream::<u64>();
+
+                let total = <Fold<_, DefaultSemigroup<_>, _, _>>::new(
+                    0,
+                    |acc: &mut i64, (_ts, amount): &Event, w: isize| *acc += *amount * w as i64,
+                );
+
+                let windows = stream.window_aggregate(
+                    &watermark,
+                    |(ts, _)| *ts,
+                    WindowKind::Tumbling { size: 10 },
+                    5,
+                    total,
+                );
+
+                (handle, watermark_handle, windows.integrate().output())
+            })
+            .unwrap();
+
+        input.append(&mut vec![
+            (1, ((0, 1), 1)),
+            (1, ((5, 2), 1)),
+            (1, ((12, 4), 1)),
+            (2, ((3, 100), 1)),
+        ]);
+        watermark.set_for_all(0);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { (0, 10, 3) => 1, (10, 20, 4) => 1 },
+                2 => { (0, 10, 100) => 1 },
+            }
+        );
+
+        // A late event within the allowed lateness updates its window;
+        // retracting the only event of a window removes the window.
+        input.append(&mut vec![(1, ((9, 8), 1)), (2, ((3, 100), -1))]);
+        watermark.set_for_all(14);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { (0, 10, 11) => 1, (10, 20, 4) => 1 } }
+        );
+
+        // Once the watermark reaches `end + lateness`, the window expires and
+        // updates to it are dropped.
+        input.append(&mut vec![
+            (1, ((7, 16), 1)),
+            (1, ((0, 1), -1)),
+            (1, ((15, 32), 1)),
+        ]);
+        watermark.set_for_all(15);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! { 1 => { (0, 10, 11) => 1, (10, 20, 36) => 1 } }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn tumbling_window_test1() {
+        tumbling_window_test(1);
+    }
+
+    #[test]
+    fn tumbling_window_test4() {
+        tumbling_window_test(4);
+    }
+
+    fn sliding_window_test(workers: usize) {
+        let (mut dbsp, (mut input, watermark, output)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (stream, handle) = circuit.add_input_indexed_zset::<u64, Event, isize>();
+                let (watermark, watermark_handle) = circuit.add_input_stream::<u64>();
+
+                let total = <Fold<_, DefaultSemigroup<_>, _, _>>::new(
+                    0,
+                    |acc: &mut i64, (_ts, amount): &Event, w: isize| *acc += *amount * w as i64,
+                );
+
+                let windows = stream.window_aggregate(
+                    &watermark,
+                    |(ts, _)| *ts,
+                    WindowKind::Sliding { size: 10, step: 5 },
+                    0,
+                    total,
+                );
+
+                (handle, watermark_handle, windows.integrate().output())
+            })
+            .unwrap();
+
+        // Each event belongs to two overlapping windows.
+        input.append(&mut vec![
+            (1, ((3, 1), 1)),
+            (1, ((7, 2), 1)),
+            (1, ((12, 4), 1)),
+        ]);
+        watermark.set_for_all(0);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { (0, 10, 3) => 1, (5, 15, 6) => 1, (10, 20, 4) => 1 },
+            }
+        );
+
+        // Retraction updates all windows that contain the event.
+        input.append(&mut vec![(1, ((7, 2), -1))]);
+        watermark.set_for_all(9);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { (0, 10, 1) => 1, (5, 15, 4) => 1, (10, 20, 4) => 1 },
+            }
+        );
+
+        // Window `[0, 10)` has expired; the late event only updates `[5, 15)`.
+        input.append(&mut vec![(1, ((8, 16), 1))]);
+        watermark.set_for_all(10);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output.consolidate(),
+            indexed_zset! {
+                1 => { (0, 10, 1) => 1, (5, 15, 20) => 1, (10, 20, 4) => 1 },
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn sliding_window_test1() {
+        sliding_window_test(1);
+    }
+
+    #[test]
+    fn sliding_window_test4() {
+        sliding_window_test(4);
+    }
+}
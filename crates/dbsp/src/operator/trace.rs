@@ -1,15 +1,20 @@
+#[cfg(feature = "persistence")]
+use crate::circuit::checkpoint::{decode_state, encode_state};
 use crate::{
     circuit::{
         metadata::{MetaItem, OperatorMeta},
         operator_traits::{BinaryOperator, Operator, StrictOperator, StrictUnaryOperator},
-        Circuit, ExportId, ExportStream, GlobalNodeId, OwnershipPreference, Scope, Stream,
-        WithClock,
+        CheckpointError, Circuit, ExportId, ExportStream, GlobalNodeId, OwnershipPreference, Scope,
+        Stream, WithClock,
     },
     circuit_cache_key,
-    trace::{cursor::Cursor, Batch, BatchReader, Builder, Spine, Trace},
+    trace::{cursor::Cursor, Batch, BatchReader, Builder, PersistentSpine, Spine, Trace},
     DBData, Timestamp,
 };
+use bincode::{Decode, Encode};
 use size_of::SizeOf;
+#[cfg(feature = "persistence")]
+use std::collections::BTreeMap;
 use std::{borrow::Cow, cell::RefCell, marker::PhantomData, ops::DerefMut, rc::Rc};
 
 circuit_cache_key!(TraceId<B, D, K, V>(GlobalNodeId => (Stream<B, D>, TraceBounds<K, V>)));
@@ -252,6 +257,38 @@ where
     where
         B: Batch,
         Spine<B>: SizeOf,
+    {
+        self.integrate_trace_generic(lower_key_bound, lower_val_bound)
+    }
+
+    /// Like [`integrate_trace`](`Self::integrate_trace`), but integrates the
+    /// stream into a [`PersistentSpine`], which spills cold batches to disk
+    /// according to the [`SpillPolicy`](`crate::trace::SpillPolicy`) of the
+    /// runtime.
+    ///
+    /// Use this for traces that may not fit in memory.  The trace can be
+    /// consumed by any operator that accepts a trace of arbitrary type, e.g.,
+    /// [`join_incremental_spilling`](`Stream::join_incremental_spilling`).
+    #[track_caller]
+    pub fn spilling_integrate_trace(&self) -> Stream<C, PersistentSpine<B>>
+    where
+        B: Batch<Time = ()>,
+        B::Key: Encode + Decode,
+        B::Val: Encode + Decode,
+        B::R: Encode + Decode,
+    {
+        self.integrate_trace_generic(TraceBound::new(), TraceBound::new())
+    }
+
+    /// Integrates the stream into a trace of type `T`.
+    fn integrate_trace_generic<T>(
+        &self,
+        lower_key_bound: TraceBound<B::Key>,
+        lower_val_bound: TraceBound<B::Val>,
+    ) -> Stream<C, T>
+    where
+        B: Batch,
+        T: Trace<Key = B::Key, Val = B::Val, Time = B::Time, R = B::R, Batch = B> + Clone,
     {
         let mut trace_bounds = self.circuit().cache_get_or_insert_with(
             IntegrateTraceId::new(self.origin_node_id().clone()),
@@ -268,7 +305,7 @@ where
                         ));
 
                     let trace = circuit.add_binary_operator_with_preference(
-                        UntimedTraceAppend::<Spine<B>>::new(),
+                        UntimedTraceAppend::<T>::new(),
                         (&local, OwnershipPreference::STRONGLY_PREFER_OWNED),
                         (
                             &self.try_sharded_version(),
@@ -302,6 +339,39 @@ where
 
         trace.clone()
     }
+
+    /// Integrate the stream into a trace, discarding updates whose keys fall
+    /// below a moving retention bound.
+    ///
+    /// This is useful for append-only streams, e.g., streams of events
+    /// indexed by event time, where old records are no longer needed once
+    /// they fall out of the window of interest.  At each clock cycle, the
+    /// `bound` stream contains the smallest key to retain, e.g., the current
+    /// watermark minus the retention window.  The bound must grow
+    /// monotonically.
+    ///
+    /// Records with keys below the bound are physically removed from the
+    /// trace at the end of the clock cycle in which the bound is raised.
+    /// Updates with keys below the bound that arrive later, including
+    /// retractions of records that have already been removed, are ignored
+    /// rather than added to the trace as negative entries.
+    ///
+    /// The trace is shared with other consumers of
+    /// [`integrate_trace`](`Self::integrate_trace`) over the same stream.  Only
+    /// records below the bounds of all consumers are removed, so the trace is
+    /// not truncated if any of them uses an unbounded trace.
+    #[track_caller]
+    pub fn integrate_with_bound(&self, bound: &Stream<C, B::Key>) -> Stream<C, Spine<B>>
+    where
+        B: Batch,
+        Spine<B>: SizeOf,
+    {
+        let key_bound = TraceBound::new();
+        let key_bound_clone = key_bound.clone();
+        bound.apply(move |bound| key_bound_clone.set(bound.clone()));
+
+        self.integrate_trace_with_bound(key_bound, TraceBound::new())
+    }
 }
 
 impl<C, T> Stream<C, T>
@@ -358,6 +428,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T> BinaryOperator<T, T::Batch, T> for UntimedTraceAppend<T>
@@ -420,6 +494,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T, B, Clk> BinaryOperator<T, B, T> for TraceAppend<T, B, Clk>
@@ -546,6 +624,64 @@ where
     fn fixedpoint(&self, scope: Scope) -> bool {
         !self.dirty[scope as usize]
     }
+
+    // Saves the contents of the trace as a list of `(key, val, time, diff)`
+    // updates, which `restore` groups into one batch per timestamp.
+    #[cfg(feature = "persistence")]
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        let trace = match &self.trace {
+            Some(trace) => trace,
+            None => return Ok(None),
+        };
+
+        let mut updates = Vec::with_capacity(trace.len());
+        let mut cursor = trace.cursor();
+        while cursor.key_valid() {
+            while cursor.val_valid() {
+                let key = cursor.key().clone();
+                let val = cursor.val().clone();
+                cursor.map_times(|time, diff| {
+                    updates.push((key.clone(), val.clone(), time.clone(), diff.clone()))
+                });
+                cursor.step_val();
+            }
+            cursor.step_key();
+        }
+
+        encode_state(&(self.time.clone(), updates)).map(Some)
+    }
+
+    // Trace contents can only be serialized when the `persistence` feature
+    // makes all data types serializable.
+    #[cfg(not(feature = "persistence"))]
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        match &self.trace {
+            Some(trace) if !trace.is_empty() => Err(CheckpointError::Unsupported),
+            _ => Ok(None),
+        }
+    }
+
+    #[cfg(feature = "persistence")]
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        let (time, updates): (T::Time, Vec<(T::Key, T::Val, T::Time, T::R)>) = decode_state(state)?;
+
+        let mut batches = BTreeMap::new();
+        for (key, val, time, diff) in updates {
+            batches
+                .entry(time)
+                .or_insert_with(Vec::new)
+                .push((T::Batch::item_from(key, val), diff));
+        }
+
+        let mut trace = T::new(None);
+        for (time, tuples) in batches {
+            trace.insert(T::Batch::from_tuples(time, tuples));
+        }
+
+        self.time = time;
+        self.trace = Some(trace);
+        Ok(())
+    }
 }
 
 impl<T> StrictOperator<T> for Z1Trace<T>
@@ -608,3 +744,66 @@ where
         OwnershipPreference::PREFER_OWNED
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        operator::Generator,
+        trace::{cursor::Cursor, BatchReader, Spine},
+        zset, Circuit, OrdZSet, RootCircuit, Stream,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    // Contents of a trace as `(key, weight)` pairs.
+    fn contents(trace: &Spine<OrdZSet<usize, isize>>) -> Vec<(usize, isize)> {
+        let mut result = Vec::new();
+        let mut cursor = trace.cursor();
+
+        while cursor.key_valid() {
+            let mut weight = 0;
+            cursor.map_times(|_, w| weight += w);
+            if weight != 0 {
+                result.push((*cursor.key(), weight));
+            }
+            cursor.step_key();
+        }
+
+        result
+    }
+
+    #[test]
+    fn integrate_with_bound() {
+        let (circuit, outputs) = RootCircuit::build(|circuit| {
+            let mut inputs = vec![
+                zset! { 1 => 1, 5 => 1, 12 => 1 },
+                zset! {},
+                zset! {},
+                // `5` has been discarded: retracting it is a no-op.
+                zset! { 5 => -1, 15 => 1 },
+            ]
+            .into_iter();
+            let mut bounds = vec![0, 10, 10, 10].into_iter();
+
+            let input: Stream<_, OrdZSet<usize, isize>> =
+                circuit.add_source(Generator::new(move || inputs.next().unwrap()));
+            let bound = circuit.add_source(Generator::new(move || bounds.next().unwrap()));
+
+            let outputs = Rc::new(RefCell::new(Vec::new()));
+            let outputs_clone = outputs.clone();
+            input
+                .integrate_with_bound(&bound)
+                .inspect(move |trace| outputs_clone.borrow_mut().push(contents(trace)));
+
+            outputs
+        })
+        .unwrap();
+
+        for _ in 0..4 {
+            circuit.step().unwrap();
+        }
+
+        let outputs = outputs.borrow();
+        assert_eq!(outputs[0], vec![(1, 1), (5, 1), (12, 1)]);
+        assert_eq!(outputs[3], vec![(12, 1), (15, 1)]);
+    }
+}
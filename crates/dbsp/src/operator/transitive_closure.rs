@@ -0,0 +1,121 @@
+//! Transitive closure of a graph.
+
+use crate::{
+    algebra::ZRingValue, circuit::Stream, operator::FilterMap, DBData, OrdZSet, RootCircuit,
+};
+
+impl<N, R> Stream<RootCircuit, OrdZSet<(N, N), R>>
+where
+    N: DBData,
+    R: ZRingValue,
+{
+    /// Incrementally compute the transitive closure of a graph.
+    ///
+    /// Treats `self` as a stream of changes to a relation that contains
+    /// `(from, to)` edges of a directed graph and outputs a stream of
+    /// changes to the transitive closure of this relation, i.e., the set of
+    /// `(from, to)` pairs such that node `to` is reachable from node `from`
+    /// via one or more edges.  A node is reachable from itself only if it
+    /// belongs to a cycle.  The output is a set, i.e., each path has weight
+    /// `1` regardless of the number of distinct paths between two nodes.
+    ///
+    /// This operator uses [`recursive`](`crate::ChildCircuit::recursive`) to
+    /// build a nested circuit that computes the fixed point of
+    ///
+    /// ```text
+    /// paths = distinct(edges + paths ⋈ edges)
+    /// ```
+    ///
+    /// semi-naively: each iteration only joins paths discovered by the
+    /// previous iteration with the edges relation.  The computation is
+    /// incrementally maintained as edges are added and removed.
+    pub fn transitive_closure(&self) -> Stream<RootCircuit, OrdZSet<(N, N), R>> {
+        self.circuit()
+            .recursive(|child, paths: Stream<_, OrdZSet<(N, N), R>>| {
+                let edges = self.delta0(child);
+
+                // Extend each path `from -> via` with each edge `via -> to`.
+                let paths_indexed = paths.map_index(|(from, via)| (via.clone(), from.clone()));
+                let edges_indexed = edges.index();
+
+                Ok(edges.plus(
+                    &paths_indexed
+                        .join(&edges_indexed, |_via, from, to| (from.clone(), to.clone())),
+                ))
+            })
+            // The nested circuit is fixed and never violates scheduler constraints.
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{zset, Runtime};
+
+    fn transitive_closure_test(workers: usize) {
+        let (mut dbsp, (mut input_handle, output_handle)) =
+            Runtime::init_circuit(workers, |circuit| {
+                let (edges, input_handle) = circuit.add_input_zset::<(usize, usize), isize>();
+                let output_handle = edges.transitive_closure().integrate().output();
+
+                (input_handle, output_handle)
+            })
+            .unwrap();
+
+        // DAG with two paths from 1 to 4.
+        input_handle.append(&mut vec![
+            ((1, 2), 1),
+            ((2, 4), 1),
+            ((1, 3), 1),
+            ((3, 4), 1),
+        ]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! { (1, 2) => 1, (1, 3) => 1, (1, 4) => 1, (2, 4) => 1, (3, 4) => 1 }
+        );
+
+        // Removing one of the paths from 1 to 4 doesn't affect reachability.
+        input_handle.append(&mut vec![((2, 4), -1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! { (1, 2) => 1, (1, 3) => 1, (1, 4) => 1, (3, 4) => 1 }
+        );
+
+        // Close a cycle 1 -> 3 -> 4 -> 1.
+        input_handle.append(&mut vec![((4, 1), 1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! {
+                (1, 1) => 1, (1, 2) => 1, (1, 3) => 1, (1, 4) => 1,
+                (3, 1) => 1, (3, 2) => 1, (3, 3) => 1, (3, 4) => 1,
+                (4, 1) => 1, (4, 2) => 1, (4, 3) => 1, (4, 4) => 1
+            }
+        );
+
+        // Break the cycle.
+        input_handle.append(&mut vec![((3, 4), -1)]);
+        dbsp.step().unwrap();
+        assert_eq!(
+            output_handle.consolidate(),
+            zset! {
+                (1, 2) => 1, (1, 3) => 1,
+                (4, 1) => 1, (4, 2) => 1, (4, 3) => 1
+            }
+        );
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn transitive_closure_test1() {
+        transitive_closure_test(1);
+    }
+
+    #[test]
+    fn transitive_closure_test4() {
+        transitive_closure_test(4);
+    }
+}
@@ -2,7 +2,7 @@ use crate::{
     algebra::{AddAssignByRef, HasOne, HasZero, PartialOrder, ZRingValue},
     circuit::{
         operator_traits::{BinaryOperator, Operator},
-        ExportId, ExportStream, OwnershipPreference, Scope, WithClock,
+        CheckpointError, ExportId, ExportStream, OwnershipPreference, Scope, WithClock,
     },
     operator::trace::{DelayedTraceId, TraceAppend, TraceBounds, TraceId, Z1Trace},
     trace::{
@@ -150,6 +150,10 @@ where
     fn fixedpoint(&self, _scope: Scope) -> bool {
         true
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        Ok(None)
+    }
 }
 
 impl<T, B> BinaryOperator<T, Vec<(T::Key, Option<T::Val>)>, B> for Upsert<T, B>
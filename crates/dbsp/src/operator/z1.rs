@@ -3,10 +3,11 @@
 use crate::{
     algebra::HasZero,
     circuit::{
+        checkpoint::{decode_state, encode_state},
         metadata::{MetaItem, OperatorMeta},
         operator_traits::{Operator, StrictOperator, StrictUnaryOperator, UnaryOperator},
-        Circuit, ExportId, ExportStream, FeedbackConnector, GlobalNodeId, OwnershipPreference,
-        Scope, Stream,
+        Checkpoint, CheckpointError, Circuit, ExportId, ExportStream, FeedbackConnector,
+        GlobalNodeId, OwnershipPreference, Scope, Stream,
     },
     circuit_cache_key, NumEntries,
 };
@@ -33,7 +34,7 @@ where
 impl<C, D> DelayedFeedback<C, D>
 where
     C: Circuit,
-    D: Eq + SizeOf + NumEntries + Clone + HasZero + 'static,
+    D: Eq + SizeOf + NumEntries + Checkpoint + Clone + HasZero + 'static,
 {
     /// Create a feedback loop with `Z1` operator.  Use [`Self::connect`] to
     /// close the loop.
@@ -82,7 +83,7 @@ pub struct DelayedNestedFeedback<C, D> {
 impl<C, D> DelayedNestedFeedback<C, D>
 where
     C: Circuit,
-    D: Eq + SizeOf + NumEntries + Clone + 'static,
+    D: Eq + SizeOf + NumEntries + Checkpoint + Clone + 'static,
 {
     /// Create a feedback loop with `Z1` operator.  Use [`Self::connect`] to
     /// close the loop.
@@ -116,7 +117,7 @@ where
     /// Applies [`Z1`] operator to `self`.
     pub fn delay(&self) -> Stream<C, D>
     where
-        D: Eq + SizeOf + NumEntries + Clone + HasZero + 'static,
+        D: Eq + SizeOf + NumEntries + Checkpoint + Clone + HasZero + 'static,
     {
         self.circuit()
             .cache_get_or_insert_with(DelayedId::new(self.origin_node_id().clone()), || {
@@ -128,7 +129,7 @@ where
     /// Applies [`Z1Nested`] operator to `self`.
     pub fn delay_nested(&self) -> Stream<C, D>
     where
-        D: Eq + Clone + HasZero + SizeOf + NumEntries + 'static,
+        D: Eq + Clone + HasZero + SizeOf + NumEntries + Checkpoint + 'static,
     {
         self.circuit()
             .cache_get_or_insert_with(NestedDelayedId::new(self.origin_node_id().clone()), || {
@@ -181,7 +182,7 @@ where
 
 impl<T> Operator for Z1<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Checkpoint + Clone + 'static,
 {
     fn name(&self) -> Cow<'static, str> {
         Cow::from("Z^-1")
@@ -211,11 +212,26 @@ where
             true
         }
     }
+
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        if self.values == self.zero && !self.empty_output {
+            return Ok(None);
+        }
+
+        encode_state(&(self.empty_output, self.values.checkpoint()?)).map(Some)
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        let (empty_output, values): (bool, Vec<u8>) = decode_state(state)?;
+
+        self.empty_output = empty_output;
+        self.values.restore(&values)
+    }
 }
 
 impl<T> UnaryOperator<T, T> for Z1<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Checkpoint + Clone + 'static,
 {
     fn eval(&mut self, i: &T) -> T {
         replace(&mut self.values, i.clone())
@@ -232,7 +248,7 @@ where
 
 impl<T> StrictOperator<T> for Z1<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Checkpoint + Clone + 'static,
 {
     fn get_output(&mut self) -> T {
         self.empty_output = self.values.num_entries_shallow() == 0;
@@ -246,7 +262,7 @@ where
 
 impl<T> StrictUnaryOperator<T, T> for Z1<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Checkpoint + Clone + 'static,
 {
     fn eval_strict(&mut self, i: &T) {
         self.values = i.clone();
@@ -318,7 +334,7 @@ impl<T> Z1Nested<T> {
 
 impl<T> Operator for Z1Nested<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Checkpoint + Clone + 'static,
 {
     fn name(&self) -> Cow<'static, str> {
         Cow::from("Z^-1 (nested)")
@@ -378,11 +394,41 @@ where
             false
         }
     }
+
+    // Saves the nested stream received at the last parent clock cycle, one
+    // value per nested timestamp, along with the current nested timestamp.
+    fn checkpoint(&self) -> Result<Option<Vec<u8>>, CheckpointError> {
+        if self.timestamp == 0 && self.values.is_empty() {
+            return Ok(None);
+        }
+
+        let values = self
+            .values
+            .iter()
+            .map(T::checkpoint)
+            .collect::<Result<Vec<_>, _>>()?;
+        encode_state(&(self.timestamp, values)).map(Some)
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), CheckpointError> {
+        let (timestamp, values): (usize, Vec<Vec<u8>>) = decode_state(state)?;
+
+        self.timestamp = timestamp;
+        self.values = values
+            .iter()
+            .map(|state| {
+                let mut value = self.zero.clone();
+                value.restore(state)?;
+                Ok(value)
+            })
+            .collect::<Result<Vec<_>, CheckpointError>>()?;
+        Ok(())
+    }
 }
 
 impl<T> UnaryOperator<T, T> for Z1Nested<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Checkpoint + Clone + 'static,
 {
     fn eval(&mut self, i: &T) -> T {
         debug_assert!(self.timestamp <= self.values.len());
@@ -421,7 +467,7 @@ where
 
 impl<T> StrictOperator<T> for Z1Nested<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Checkpoint + Clone + 'static,
 {
     fn get_output(&mut self) -> T {
         if self.timestamp >= self.values.len() {
@@ -445,7 +491,7 @@ where
 
 impl<T> StrictUnaryOperator<T, T> for Z1Nested<T>
 where
-    T: Eq + SizeOf + NumEntries + Clone + 'static,
+    T: Eq + SizeOf + NumEntries + Checkpoint + Clone + 'static,
 {
     fn eval_strict(&mut self, i: &T) {
         debug_assert!(self.timestamp < self.values.len());
@@ -0,0 +1,679 @@
+//! Serializable circuit plans.
+//!
+//! A [`CircuitPlan`] is a declarative description of a circuit built from a
+//! fixed vocabulary of operators: filter, map, join, and aggregate.  Instead
+//! of containing code, plan nodes refer to functions by name.  Names are
+//! resolved against a [`FunctionRegistry`] when the plan is instantiated
+//! using [`CircuitPlan::instantiate`].  A process that registers a library of
+//! functions can therefore run any plan composed from these functions
+//! without recompiling, e.g., a plan loaded from a configuration file.
+//!
+//! All streams in a plan are Z-sets of the same record type `T` with `isize`
+//! weights.  Keys used to join and aggregate records and aggregate values
+//! are also of type `T`.  Use an enum or a generic row type as `T` to
+//! support records of different shapes.
+//!
+//! When the `with-serde` feature is enabled, plans can be serialized using
+//! any `serde` format, e.g., JSON:
+//!
+//! ```text
+//! {
+//!   "nodes": [
+//!     { "input": { "name": "numbers" } },
+//!     { "filter": { "input": 0, "predicate": "positive" } },
+//!     { "output": { "input": 1, "name": "positive_numbers" } }
+//!   ]
+//! }
+//! ```
+
+use crate::{
+    algebra::UnimplementedSemigroup,
+    operator::{FilterMap, Fold},
+    CollectionHandle, DBData, OrdZSet, OutputHandle, RootCircuit, Stream,
+};
+#[cfg(feature = "with-serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{Display, Error as FmtError, Formatter},
+    sync::Arc,
+};
+
+/// Named predicate used by [`PlanNode::Filter`].
+pub type PlanPredicate<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// Named function used by [`PlanNode::Map`] and to extract keys in
+/// [`PlanNode::Join`] and [`PlanNode::Aggregate`].
+pub type PlanFunction<T> = Arc<dyn Fn(&T) -> T + Send + Sync>;
+
+/// Named function that combines two values into a record.  Used by
+/// [`PlanNode::Join`] to combine matching records and by
+/// [`PlanNode::Aggregate`] to combine a key with its aggregate.
+pub type PlanCombiner<T> = Arc<dyn Fn(&T, &T) -> T + Send + Sync>;
+
+/// Step function of a named aggregator, see
+/// [`FunctionRegistry::register_aggregator`].
+pub type PlanAggregateStep<T> = Arc<dyn Fn(&mut T, &T, isize) + Send + Sync>;
+
+/// Functions that can be referenced by name from a [`CircuitPlan`].
+pub struct FunctionRegistry<T> {
+    predicates: BTreeMap<String, PlanPredicate<T>>,
+    functions: BTreeMap<String, PlanFunction<T>>,
+    combiners: BTreeMap<String, PlanCombiner<T>>,
+    aggregators: BTreeMap<String, (T, PlanAggregateStep<T>)>,
+}
+
+impl<T> Default for FunctionRegistry<T> {
+    fn default() -> Self {
+        Self {
+            predicates: BTreeMap::new(),
+            functions: BTreeMap::new(),
+            combiners: BTreeMap::new(),
+            aggregators: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> FunctionRegistry<T> {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a predicate that can be used to filter records.
+    pub fn register_predicate<F>(&mut self, name: &str, predicate: F) -> &mut Self
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.predicates
+            .insert(name.to_string(), Arc::new(predicate));
+        self
+    }
+
+    /// Register a function that can be used to map records or to extract
+    /// keys from records.
+    pub fn register_function<F>(&mut self, name: &str, function: F) -> &mut Self
+    where
+        F: Fn(&T) -> T + Send + Sync + 'static,
+    {
+        self.functions.insert(name.to_string(), Arc::new(function));
+        self
+    }
+
+    /// Register a function that combines two values into a record.
+    pub fn register_combiner<F>(&mut self, name: &str, combiner: F) -> &mut Self
+    where
+        F: Fn(&T, &T) -> T + Send + Sync + 'static,
+    {
+        self.combiners.insert(name.to_string(), Arc::new(combiner));
+        self
+    }
+
+    /// Register an aggregator.
+    ///
+    /// The aggregate of a group of records is computed by folding the
+    /// records of the group into `init` using `step(accumulator, record,
+    /// weight)`, which is invoked once for each record with non-zero weight.
+    pub fn register_aggregator<F>(&mut self, name: &str, init: T, step: F) -> &mut Self
+    where
+        F: Fn(&mut T, &T, isize) + Send + Sync + 'static,
+    {
+        self.aggregators
+            .insert(name.to_string(), (init, Arc::new(step)));
+        self
+    }
+}
+
+/// Index of a node in a [`CircuitPlan`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "with-serde", serde(transparent))]
+pub struct PlanNodeId(pub usize);
+
+impl Display for PlanNodeId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A node in a [`CircuitPlan`].
+///
+/// Nodes refer to their inputs by [`PlanNodeId`], which must identify a
+/// preceding non-output node in the plan.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "with-serde", serde(rename_all = "snake_case"))]
+pub enum PlanNode {
+    /// Input Z-set fed using the handle stored under `name` in
+    /// [`PlanHandles::inputs`].
+    Input { name: String },
+    /// Records of `input` that satisfy `predicate`.
+    Filter {
+        input: PlanNodeId,
+        predicate: String,
+    },
+    /// Applies `function` to each record of `input`.
+    Map { input: PlanNodeId, function: String },
+    /// Joins records of `left` and `right` with equal keys, computed by the
+    /// `left_key` and `right_key` functions, and combines each pair of
+    /// matching records using `combiner`.
+    Join {
+        left: PlanNodeId,
+        right: PlanNodeId,
+        left_key: String,
+        right_key: String,
+        combiner: String,
+    },
+    /// Groups records of `input` by the `key` function, aggregates each group
+    /// using `aggregator`, and combines each key with its aggregate using
+    /// `combiner`.
+    Aggregate {
+        input: PlanNodeId,
+        key: String,
+        aggregator: String,
+        combiner: String,
+    },
+    /// Makes `input` available through the handle stored under `name` in
+    /// [`PlanHandles::outputs`].
+    Output { input: PlanNodeId, name: String },
+}
+
+/// Error instantiating a [`CircuitPlan`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlanError {
+    /// A node refers to an input that is not a preceding non-output node.
+    InvalidInput { node: PlanNodeId, input: PlanNodeId },
+    /// A node refers to a function not found in the [`FunctionRegistry`].
+    UnknownFunction {
+        node: PlanNodeId,
+        kind: &'static str,
+        name: String,
+    },
+    /// Two input nodes have the same name.
+    DuplicateInput(String),
+    /// Two output nodes have the same name.
+    DuplicateOutput(String),
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::InvalidInput { node, input } => {
+                write!(f, "node {node} refers to invalid input node {input}")
+            }
+            Self::UnknownFunction { node, kind, name } => {
+                write!(f, "node {node} refers to unknown {kind} '{name}'")
+            }
+            Self::DuplicateInput(name) => write!(f, "duplicate input name '{name}'"),
+            Self::DuplicateOutput(name) => write!(f, "duplicate output name '{name}'"),
+        }
+    }
+}
+
+/// Input and output handles of an instantiated [`CircuitPlan`], indexed by
+/// the names of the corresponding plan nodes.
+pub struct PlanHandles<T> {
+    pub inputs: BTreeMap<String, CollectionHandle<T, isize>>,
+    pub outputs: BTreeMap<String, OutputHandle<OrdZSet<T, isize>>>,
+}
+
+/// A declarative description of a circuit, see [module documentation](self).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "with-serde", derive(Serialize, Deserialize))]
+pub struct CircuitPlan {
+    nodes: Vec<PlanNode>,
+}
+
+impl CircuitPlan {
+    /// Create an empty plan.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Nodes of the plan in the order they were added.
+    pub fn nodes(&self) -> &[PlanNode] {
+        &self.nodes
+    }
+
+    /// Add a node to the plan.
+    pub fn add_node(&mut self, node: PlanNode) -> PlanNodeId {
+        self.nodes.push(node);
+        PlanNodeId(self.nodes.len() - 1)
+    }
+
+    /// Add an [`Input`](`PlanNode::Input`) node.
+    pub fn input(&mut self, name: &str) -> PlanNodeId {
+        self.add_node(PlanNode::Input {
+            name: name.to_string(),
+        })
+    }
+
+    /// Add a [`Filter`](`PlanNode::Filter`) node.
+    pub fn filter(&mut self, input: PlanNodeId, predicate: &str) -> PlanNodeId {
+        self.add_node(PlanNode::Filter {
+            input,
+            predicate: predicate.to_string(),
+        })
+    }
+
+    /// Add a [`Map`](`PlanNode::Map`) node.
+    pub fn map(&mut self, input: PlanNodeId, function: &str) -> PlanNodeId {
+        self.add_node(PlanNode::Map {
+            input,
+            function: function.to_string(),
+        })
+    }
+
+    /// Add a [`Join`](`PlanNode::Join`) node.
+    pub fn join(
+        &mut self,
+        left: PlanNodeId,
+        right: PlanNodeId,
+        left_key: &str,
+        right_key: &str,
+        combiner: &str,
+    ) -> PlanNodeId {
+        self.add_node(PlanNode::Join {
+            left,
+            right,
+            left_key: left_key.to_string(),
+            right_key: right_key.to_string(),
+            combiner: combiner.to_string(),
+        })
+    }
+
+    /// Add an [`Aggregate`](`PlanNode::Aggregate`) node.
+    pub fn aggregate(
+        &mut self,
+        input: PlanNodeId,
+        key: &str,
+        aggregator: &str,
+        combiner: &str,
+    ) -> PlanNodeId {
+        self.add_node(PlanNode::Aggregate {
+            input,
+            key: key.to_string(),
+            aggregator: aggregator.to_string(),
+            combiner: combiner.to_string(),
+        })
+    }
+
+    /// Add an [`Output`](`PlanNode::Output`) node.
+    pub fn output(&mut self, input: PlanNodeId, name: &str) -> PlanNodeId {
+        self.add_node(PlanNode::Output {
+            input,
+            name: name.to_string(),
+        })
+    }
+
+    /// Instantiate the plan in `circuit`, resolving function names in
+    /// `registry`.
+    ///
+    /// The plan is validated before adding any operators to `circuit`, so
+    /// the circuit is left unmodified if this method returns an error.
+    pub fn instantiate<T>(
+        &self,
+        circuit: &RootCircuit,
+        registry: &FunctionRegistry<T>,
+    ) -> Result<PlanHandles<T>, PlanError>
+    where
+        T: DBData,
+    {
+        let resolved = self.resolve(registry)?;
+
+        let mut streams: Vec<Option<Stream<RootCircuit, OrdZSet<T, isize>>>> =
+            Vec::with_capacity(resolved.len());
+        let mut handles = PlanHandles {
+            inputs: BTreeMap::new(),
+            outputs: BTreeMap::new(),
+        };
+
+        // `resolve` guarantees that inputs of all nodes refer to preceding
+        // streams.
+        for node in resolved {
+            let stream = |id: PlanNodeId| streams[id.0].clone().unwrap();
+
+            let output = match node {
+                ResolvedNode::Input { name } => {
+                    let (stream, handle) = circuit.add_input_zset::<T, isize>();
+                    handles.inputs.insert(name, handle);
+                    Some(stream)
+                }
+                ResolvedNode::Filter { input, predicate } => {
+                    Some(stream(input).filter(move |record| predicate(record)))
+                }
+                ResolvedNode::Map { input, function } => {
+                    Some(stream(input).map(move |record| function(record)))
+                }
+                ResolvedNode::Join {
+                    left,
+                    right,
+                    left_key,
+                    right_key,
+                    combiner,
+                } => {
+                    let left =
+                        stream(left).index_with(move |record| (left_key(record), record.clone()));
+                    let right =
+                        stream(right).index_with(move |record| (right_key(record), record.clone()));
+                    Some(left.join(&right, move |_key, left, right| combiner(left, right)))
+                }
+                ResolvedNode::Aggregate {
+                    input,
+                    key,
+                    init,
+                    step,
+                    combiner,
+                } => Some(
+                    stream(input)
+                        .index_with(move |record| (key(record), record.clone()))
+                        .aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::new(
+                            init,
+                            move |acc: &mut T, record: &T, weight: isize| step(acc, record, weight),
+                        ))
+                        .map(move |(key, aggregate)| combiner(key, aggregate)),
+                ),
+                ResolvedNode::Output { input, name } => {
+                    handles.outputs.insert(name, stream(input).output());
+                    None
+                }
+            };
+
+            streams.push(output);
+        }
+
+        Ok(handles)
+    }
+
+    // Validates the plan, resolving function names in `registry`.
+    fn resolve<T>(&self, registry: &FunctionRegistry<T>) -> Result<Vec<ResolvedNode<T>>, PlanError>
+    where
+        T: Clone,
+    {
+        let mut inputs = BTreeSet::new();
+        let mut outputs = BTreeSet::new();
+
+        let mut resolved = Vec::with_capacity(self.nodes.len());
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            let node_id = PlanNodeId(index);
+
+            // Inputs must refer to preceding nodes that produce a stream.
+            let check_input = |input: &PlanNodeId| {
+                if input.0 < index && !matches!(self.nodes[input.0], PlanNode::Output { .. }) {
+                    Ok(*input)
+                } else {
+                    Err(PlanError::InvalidInput {
+                        node: node_id,
+                        input: *input,
+                    })
+                }
+            };
+
+            let node = match node {
+                PlanNode::Input { name } => {
+                    if !inputs.insert(name.clone()) {
+                        return Err(PlanError::DuplicateInput(name.clone()));
+                    }
+                    ResolvedNode::Input { name: name.clone() }
+                }
+                PlanNode::Filter { input, predicate } => ResolvedNode::Filter {
+                    input: check_input(input)?,
+                    predicate: lookup(&registry.predicates, node_id, "predicate", predicate)?,
+                },
+                PlanNode::Map { input, function } => ResolvedNode::Map {
+                    input: check_input(input)?,
+                    function: lookup(&registry.functions, node_id, "function", function)?,
+                },
+                PlanNode::Join {
+                    left,
+                    right,
+                    left_key,
+                    right_key,
+                    combiner,
+                } => ResolvedNode::Join {
+                    left: check_input(left)?,
+                    right: check_input(right)?,
+                    left_key: lookup(&registry.functions, node_id, "function", left_key)?,
+                    right_key: lookup(&registry.functions, node_id, "function", right_key)?,
+                    combiner: lookup(&registry.combiners, node_id, "combiner", combiner)?,
+                },
+                PlanNode::Aggregate {
+                    input,
+                    key,
+                    aggregator,
+                    combiner,
+                } => {
+                    let (init, step) =
+                        lookup(&registry.aggregators, node_id, "aggregator", aggregator)?;
+
+                    ResolvedNode::Aggregate {
+                        input: check_input(input)?,
+                        key: lookup(&registry.functions, node_id, "function", key)?,
+                        init,
+                        step,
+                        combiner: lookup(&registry.combiners, node_id, "combiner", combiner)?,
+                    }
+                }
+                PlanNode::Output { input, name } => {
+                    if !outputs.insert(name.clone()) {
+                        return Err(PlanError::DuplicateOutput(name.clone()));
+                    }
+                    ResolvedNode::Output {
+                        input: check_input(input)?,
+                        name: name.clone(),
+                    }
+                }
+            };
+
+            resolved.push(node);
+        }
+
+        Ok(resolved)
+    }
+}
+
+// Looks up function `name` of the given `kind` referenced by `node`.
+fn lookup<F: Clone>(
+    functions: &BTreeMap<String, F>,
+    node: PlanNodeId,
+    kind: &'static str,
+    name: &str,
+) -> Result<F, PlanError> {
+    functions
+        .get(name)
+        .cloned()
+        .ok_or_else(|| PlanError::UnknownFunction {
+            node,
+            kind,
+            name: name.to_string(),
+        })
+}
+
+/// A plan node with function names resolved in a `FunctionRegistry`.
+enum ResolvedNode<T> {
+    Input {
+        name: String,
+    },
+    Filter {
+        input: PlanNodeId,
+        predicate: PlanPredicate<T>,
+    },
+    Map {
+        input: PlanNodeId,
+        function: PlanFunction<T>,
+    },
+    Join {
+        left: PlanNodeId,
+        right: PlanNodeId,
+        left_key: PlanFunction<T>,
+        right_key: PlanFunction<T>,
+        combiner: PlanCombiner<T>,
+    },
+    Aggregate {
+        input: PlanNodeId,
+        key: PlanFunction<T>,
+        init: T,
+        step: PlanAggregateStep<T>,
+        combiner: PlanCombiner<T>,
+    },
+    Output {
+        input: PlanNodeId,
+        name: String,
+    },
+}
+
+#[cfg(all(test, feature = "with-serde"))]
+mod test {
+    use super::{CircuitPlan, FunctionRegistry, PlanError, PlanNodeId};
+    use crate::{
+        algebra::DefaultSemigroup,
+        operator::{FilterMap, Fold},
+        zset, OrdZSet, RootCircuit, Runtime,
+    };
+    use std::sync::Arc;
+
+    fn registry() -> FunctionRegistry<i64> {
+        let mut registry = FunctionRegistry::new();
+        registry
+            .register_predicate("positive", |x| *x > 0)
+            .register_function("double", |x| x * 2)
+            .register_function("mod3", |x| x % 3)
+            .register_aggregator("sum", 0, |acc, x, weight| *acc += x * weight as i64)
+            .register_combiner("encode", |key, sum| key * 1000 + sum);
+        registry
+    }
+
+    // input -> filter -> map -> aggregate -> output
+    fn plan() -> CircuitPlan {
+        let mut plan = CircuitPlan::new();
+        let input = plan.input("numbers");
+        let positive = plan.filter(input, "positive");
+        let doubled = plan.map(positive, "double");
+        let sums = plan.aggregate(doubled, "mod3", "sum", "encode");
+        plan.output(sums, "sums");
+        plan
+    }
+
+    fn plan_test(workers: usize) {
+        // Round-trip the plan through JSON.
+        let json = serde_json::to_string(&plan()).unwrap();
+        let plan: CircuitPlan = serde_json::from_str(&json).unwrap();
+        assert_eq!(plan, self::plan());
+
+        let registry = Arc::new(registry());
+
+        let (mut dbsp, (mut handles, mut expected_input, expected_output)) =
+            Runtime::init_circuit(workers, move |circuit| {
+                let handles = plan.instantiate(circuit, &registry).unwrap();
+
+                // Hand-built equivalent of the plan.
+                let (input, input_handle) = circuit.add_input_zset::<i64, isize>();
+                let output_handle = input
+                    .filter(|x| *x > 0)
+                    .map(|x| x * 2)
+                    .index_with(|x| (x % 3, *x))
+                    .aggregate(<Fold<_, DefaultSemigroup<_>, _, _>>::new(
+                        0,
+                        |acc: &mut i64, x: &i64, weight: isize| *acc += x * weight as i64,
+                    ))
+                    .map(|(key, sum)| key * 1000 + sum)
+                    .output();
+
+                (handles, input_handle, output_handle)
+            })
+            .unwrap();
+
+        let steps: Vec<Vec<(i64, isize)>> = vec![
+            vec![(1, 1), (2, 1), (3, 1), (-4, 1), (5, 2)],
+            vec![(2, -1), (7, 1), (-1, 1)],
+            vec![(3, -1), (0, 1)],
+        ];
+
+        for mut step in steps {
+            handles
+                .inputs
+                .get_mut("numbers")
+                .unwrap()
+                .append(&mut step.clone());
+            expected_input.append(&mut step);
+            dbsp.step().unwrap();
+
+            let actual: OrdZSet<i64, isize> = handles.outputs["sums"].consolidate();
+            assert_eq!(actual, expected_output.consolidate());
+        }
+
+        dbsp.kill().unwrap();
+    }
+
+    #[test]
+    fn plan_test1() {
+        plan_test(1);
+    }
+
+    #[test]
+    fn plan_test4() {
+        plan_test(4);
+    }
+
+    #[test]
+    fn plan_join() {
+        let mut plan = CircuitPlan::new();
+        let left = plan.input("left");
+        let right = plan.input("right");
+        let joined = plan.join(left, right, "mod3", "mod3", "encode");
+        plan.output(joined, "joined");
+
+        let (mut circuit, mut handles) =
+            RootCircuit::build(move |circuit| plan.instantiate(circuit, &registry()).unwrap())
+                .unwrap();
+
+        handles
+            .inputs
+            .get_mut("left")
+            .unwrap()
+            .append(&mut vec![(1, 1), (3, 1)]);
+        handles
+            .inputs
+            .get_mut("right")
+            .unwrap()
+            .append(&mut vec![(4, 1), (5, 1)]);
+        circuit.step().unwrap();
+
+        assert_eq!(handles.outputs["joined"].consolidate(), zset! { 1004 => 1 });
+    }
+
+    #[test]
+    fn plan_errors() {
+        let mut plan = CircuitPlan::new();
+        let input = plan.input("numbers");
+        plan.map(input, "triple");
+        let (_, error) =
+            RootCircuit::build(move |circuit| plan.instantiate(circuit, &registry()).err())
+                .unwrap();
+        assert_eq!(
+            error,
+            Some(PlanError::UnknownFunction {
+                node: PlanNodeId(1),
+                kind: "function",
+                name: "triple".to_string(),
+            })
+        );
+
+        // Output nodes cannot be used as inputs.
+        let mut plan = CircuitPlan::new();
+        let input = plan.input("numbers");
+        let output = plan.output(input, "numbers");
+        plan.filter(output, "positive");
+        let (_, error) =
+            RootCircuit::build(move |circuit| plan.instantiate(circuit, &registry()).err())
+                .unwrap();
+        assert_eq!(
+            error,
+            Some(PlanError::InvalidInput {
+                node: PlanNodeId(2),
+                input: PlanNodeId(1),
+            })
+        );
+    }
+}
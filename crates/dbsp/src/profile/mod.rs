@@ -7,19 +7,105 @@ use crate::{
         GlobalNodeId,
     },
     monitor::TraceMonitor,
-    RootCircuit,
+    RootCircuit, Runtime,
 };
-use std::{borrow::Cow, collections::HashMap, fmt::Write};
+use std::{borrow::Cow, collections::HashMap, fmt::Write, time::Duration};
 
 mod cpu;
+mod schedule_trace;
+
 pub use cpu::CPUProfiler;
+pub use schedule_trace::{ScheduleEvent, ScheduleEventKind, ScheduleTrace, ScheduleTraceRecorder};
+
+/// Label of the operator metadata entry that reports the amount of memory
+/// allocated by the operator.
+const ALLOCATED_BYTES_LABEL: &str = "allocated bytes";
+
+/// Name of the scheduler event handler installed by the schedule trace
+/// recorder.
+const SCHEDULE_TRACE_HANDLER: &str = "schedule_trace";
+
+/// Returns the value of the `allocated bytes` metadata entry of `node`, or
+/// `None` if the operator doesn't report it.
+fn node_allocated_bytes(node: &dyn Node) -> Option<usize> {
+    let mut meta = OperatorMeta::new();
+    node.metadata(&mut meta);
+
+    let mut allocated = None;
+    for (label, item) in meta.iter() {
+        if let (ALLOCATED_BYTES_LABEL, MetaItem::Bytes(bytes)) = (label.as_ref(), item) {
+            *allocated.get_or_insert(0) += bytes.bytes as usize;
+        }
+    }
+
+    allocated
+}
+
+/// CPU profile of an operator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OperatorProfile {
+    /// Global id of the operator node.
+    pub node_id: GlobalNodeId,
+    /// Operator name.
+    pub name: Cow<'static, str>,
+    /// The number of times the operator has been evaluated.
+    pub invocations: usize,
+    /// Total time spent evaluating the operator.
+    pub total_time: Duration,
+}
+
+/// Per-operator CPU profile of a circuit.
+///
+/// Returned by [`DBSPHandle::profile`](`crate::DBSPHandle::profile`).
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    operators: Vec<OperatorProfile>,
+}
+
+impl Profile {
+    /// Profiles of all operators evaluated at least once, in the order in
+    /// which they were added to the circuit.
+    pub fn operators(&self) -> &[OperatorProfile] {
+        &self.operators
+    }
+
+    /// Returns the profile of operator `node_id` or `None` if the operator
+    /// has not been evaluated.
+    pub fn operator(&self, node_id: &GlobalNodeId) -> Option<&OperatorProfile> {
+        self.operators
+            .iter()
+            .find(|profile| &profile.node_id == node_id)
+    }
+
+    /// Adds up profiles of the same circuit collected by different workers.
+    pub(crate) fn merge(&mut self, other: Profile) {
+        let nodes: HashMap<GlobalNodeId, usize> = self
+            .operators
+            .iter()
+            .enumerate()
+            .map(|(index, profile)| (profile.node_id.clone(), index))
+            .collect();
+
+        for profile in other.operators {
+            match nodes.get(&profile.node_id) {
+                Some(&index) => {
+                    self.operators[index].invocations += profile.invocations;
+                    self.operators[index].total_time += profile.total_time;
+                }
+                None => self.operators.push(profile),
+            }
+        }
+    }
+}
 
 /// Rudimentary circuit profiler.
 ///
 /// Records circuit topology, operator metadata, and optionally CPU usage, and
-/// dumps them in graphviz (dot) format.
+/// dumps them in graphviz (dot) format.  Can optionally record a trace of
+/// scheduler events.
 pub struct Profiler {
     cpu_profiler: CPUProfiler,
+    schedule_trace: ScheduleTraceRecorder,
     monitor: TraceMonitor,
     circuit: RootCircuit,
 }
@@ -36,6 +122,7 @@ impl Profiler {
 
         Self {
             cpu_profiler,
+            schedule_trace: ScheduleTraceRecorder::new(),
             monitor,
             circuit: circuit.clone(),
         }
@@ -46,6 +133,73 @@ impl Profiler {
         self.cpu_profiler.attach(&self.circuit, "cpu_profiler");
     }
 
+    /// Start recording scheduler events.
+    pub fn enable_schedule_trace(&self) {
+        let worker = Runtime::runtime()
+            .map(|_| Runtime::worker_index())
+            .unwrap_or(0);
+        self.schedule_trace
+            .attach(&self.circuit, worker, SCHEDULE_TRACE_HANDLER);
+    }
+
+    /// Stop recording scheduler events and return events recorded so far.
+    pub fn take_schedule_trace(&self) -> Vec<ScheduleEvent> {
+        self.circuit
+            .unregister_scheduler_event_handler(SCHEDULE_TRACE_HANDLER);
+        self.schedule_trace.take_events()
+    }
+
+    /// Returns the total number of bytes allocated by all operators in the
+    /// circuit, as reported in the `allocated bytes` metadata entry of each
+    /// operator, e.g., traces and delay operators.
+    ///
+    /// The memory usage is computed using [`SizeOf`](`size_of::SizeOf`),
+    /// which traverses all data stored by each operator, so this method is
+    /// relatively expensive.
+    pub fn allocated_bytes(&self) -> usize {
+        let mut allocated = 0;
+
+        self.circuit.map_nodes_recursive(&mut |node: &dyn Node| {
+            allocated += node_allocated_bytes(node).unwrap_or(0);
+        });
+
+        allocated
+    }
+
+    /// Returns the number of bytes allocated by each operator in the circuit
+    /// that reports it (see [`Self::allocated_bytes`]) as `(node id, operator
+    /// name, bytes)` triples.
+    pub fn operator_allocated_bytes(&self) -> Vec<(GlobalNodeId, Cow<'static, str>, usize)> {
+        let mut allocated = Vec::new();
+
+        self.circuit.map_nodes_recursive(&mut |node: &dyn Node| {
+            if let Some(bytes) = node_allocated_bytes(node) {
+                allocated.push((node.global_id().clone(), node.name(), bytes));
+            }
+        });
+
+        allocated
+    }
+
+    /// Returns the CPU profile of all operators evaluated since the CPU
+    /// profiler was enabled.
+    pub fn profile(&self) -> Profile {
+        let mut operators = Vec::new();
+
+        self.circuit.map_nodes_recursive(&mut |node: &dyn Node| {
+            if let Some(profile) = self.cpu_profiler.operator_profile(node.global_id()) {
+                operators.push(OperatorProfile {
+                    node_id: node.global_id().clone(),
+                    name: node.name(),
+                    invocations: profile.invocations(),
+                    total_time: profile.total_time(),
+                });
+            }
+        });
+
+        Profile { operators }
+    }
+
     /// Dump profile in graphviz format.
     pub fn dump_profile(&self) -> String {
         let mut metadata = HashMap::<GlobalNodeId, OperatorMeta>::new();
@@ -0,0 +1,223 @@
+//! Scheduler event traces.
+
+use crate::circuit::{trace::SchedulerEvent, GlobalNodeId, RootCircuit};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt::{self, Display, Write},
+    rc::Rc,
+    time::Instant,
+};
+
+/// Type of a scheduler event recorded in a [`ScheduleTrace`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScheduleEventKind {
+    ClockStart,
+    ClockEnd,
+    StepStart,
+    StepEnd,
+    WaitStart,
+    WaitEnd,
+    EvalStart,
+    EvalEnd,
+}
+
+impl ScheduleEventKind {
+    /// `true` for events that start an interval, i.e., `ClockStart`,
+    /// `StepStart`, `WaitStart`, and `EvalStart`.
+    pub fn is_start(&self) -> bool {
+        matches!(
+            self,
+            Self::ClockStart | Self::StepStart | Self::WaitStart | Self::EvalStart
+        )
+    }
+}
+
+impl Display for ScheduleEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A scheduler event recorded in a [`ScheduleTrace`].
+#[derive(Clone, Debug)]
+pub struct ScheduleEvent {
+    /// Index of the worker thread that generated the event.
+    pub worker: usize,
+    pub kind: ScheduleEventKind,
+    /// The operator (for `EvalStart`/`EvalEnd` events) or circuit (for
+    /// `StepStart`/`StepEnd`/`WaitStart`/`WaitEnd` events) that generated
+    /// the event.
+    pub node_id: Option<GlobalNodeId>,
+    /// Operator name (for `EvalStart`/`EvalEnd` events).
+    pub name: Option<Cow<'static, str>>,
+    /// Time when the event was recorded.
+    pub time: Instant,
+}
+
+impl ScheduleEvent {
+    fn new(worker: usize, event: &SchedulerEvent<'_>) -> Self {
+        let (kind, node_id, name) = match event {
+            SchedulerEvent::ClockStart => (ScheduleEventKind::ClockStart, None, None),
+            SchedulerEvent::ClockEnd => (ScheduleEventKind::ClockEnd, None, None),
+            SchedulerEvent::StepStart { circuit_id } => (
+                ScheduleEventKind::StepStart,
+                Some((*circuit_id).clone()),
+                None,
+            ),
+            SchedulerEvent::StepEnd { circuit_id } => (
+                ScheduleEventKind::StepEnd,
+                Some((*circuit_id).clone()),
+                None,
+            ),
+            SchedulerEvent::WaitStart { circuit_id } => (
+                ScheduleEventKind::WaitStart,
+                Some((*circuit_id).clone()),
+                None,
+            ),
+            SchedulerEvent::WaitEnd { circuit_id } => (
+                ScheduleEventKind::WaitEnd,
+                Some((*circuit_id).clone()),
+                None,
+            ),
+            SchedulerEvent::EvalStart { node } => (
+                ScheduleEventKind::EvalStart,
+                Some(node.global_id().clone()),
+                Some(node.name()),
+            ),
+            SchedulerEvent::EvalEnd { node } => (
+                ScheduleEventKind::EvalEnd,
+                Some(node.global_id().clone()),
+                Some(node.name()),
+            ),
+        };
+
+        Self {
+            worker,
+            kind,
+            node_id,
+            name,
+            time: Instant::now(),
+        }
+    }
+
+    /// Label used for the interval that the event starts or ends when
+    /// exporting the trace.
+    fn label(&self) -> String {
+        match self.kind {
+            ScheduleEventKind::ClockStart | ScheduleEventKind::ClockEnd => "clock".to_string(),
+            ScheduleEventKind::StepStart | ScheduleEventKind::StepEnd => {
+                format!("step {}", self.node_id.as_ref().unwrap())
+            }
+            ScheduleEventKind::WaitStart | ScheduleEventKind::WaitEnd => {
+                format!("wait {}", self.node_id.as_ref().unwrap())
+            }
+            ScheduleEventKind::EvalStart | ScheduleEventKind::EvalEnd => format!(
+                "{} {}",
+                self.name.as_deref().unwrap_or_default(),
+                self.node_id.as_ref().unwrap()
+            ),
+        }
+    }
+}
+
+/// A sequence of scheduler events collected from one or more workers.
+///
+/// See [`DBSPHandle::capture_schedule_trace`](`crate::DBSPHandle::capture_schedule_trace`).
+#[derive(Clone, Debug, Default)]
+pub struct ScheduleTrace {
+    events: Vec<ScheduleEvent>,
+}
+
+impl ScheduleTrace {
+    pub fn new(events: Vec<ScheduleEvent>) -> Self {
+        Self { events }
+    }
+
+    /// Recorded events in the order in which they were generated by each
+    /// worker.
+    pub fn events(&self) -> &[ScheduleEvent] {
+        &self.events
+    }
+
+    /// Events generated by `worker`.
+    pub fn worker_events(&self, worker: usize) -> impl Iterator<Item = &ScheduleEvent> {
+        self.events
+            .iter()
+            .filter(move |event| event.worker == worker)
+    }
+
+    /// Export the trace in the JSON-based [Trace Event
+    /// Format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU),
+    /// which can be visualized using `chrome://tracing` or
+    /// [Perfetto](https://ui.perfetto.dev).
+    ///
+    /// Each worker is represented as a separate thread.  Timestamps are in
+    /// microseconds relative to the earliest event in the trace.
+    pub fn to_json(&self) -> String {
+        let start = self.events.iter().map(|event| event.time).min();
+        let mut json = String::from("{\"traceEvents\":[");
+
+        for (i, event) in self.events.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+
+            let ts = event.time.duration_since(start.unwrap()).as_micros();
+            let phase = if event.kind.is_start() { "B" } else { "E" };
+
+            json.push_str("{\"name\":\"");
+            escape_json(&event.label(), &mut json);
+            write!(
+                json,
+                "\",\"cat\":\"{}\",\"ph\":\"{phase}\",\"ts\":{ts},\"pid\":0,\"tid\":{}}}",
+                event.kind, event.worker
+            )
+            .unwrap();
+        }
+
+        json.push_str("]}");
+        json
+    }
+}
+
+fn escape_json(s: &str, output: &mut String) {
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            c if c.is_control() => write!(output, "\\u{:04x}", c as u32).unwrap(),
+            c => output.push(c),
+        }
+    }
+}
+
+/// Records scheduler events generated by a circuit.
+///
+/// The recorder doesn't introduce any overhead until it is attached to the
+/// circuit.
+#[derive(Clone, Default, Debug)]
+pub struct ScheduleTraceRecorder(Rc<RefCell<Vec<ScheduleEvent>>>);
+
+impl ScheduleTraceRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start recording scheduler events generated by `circuit` running in
+    /// `worker`.
+    pub fn attach(&self, circuit: &RootCircuit, worker: usize, handler_name: &str) {
+        let self_clone = self.clone();
+
+        circuit.register_scheduler_event_handler(handler_name, move |event| {
+            if let Ok(mut events) = self_clone.0.try_borrow_mut() {
+                events.push(ScheduleEvent::new(worker, event));
+            }
+        });
+    }
+
+    /// Remove all events recorded so far.
+    pub fn take_events(&self) -> Vec<ScheduleEvent> {
+        self.0.take()
+    }
+}
@@ -0,0 +1,179 @@
+//! Bloom filters over the keys of a batch.
+//!
+//! Batches that build a filter use it to implement
+//! [`BatchReader::maybe_contains_key`](`crate::trace::BatchReader::maybe_contains_key`),
+//! which lets joins skip batches that don't contain a key instead of
+//! searching them.  Filters are only built when the opt-in `bloom-filter`
+//! feature is enabled.
+
+use size_of::SizeOf;
+use std::hash::{Hash, Hasher};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Seed of the hash function used by the filter.  Must differ from the seed
+/// used to shard records across workers, or all keys in a shard would map to
+/// correlated bits.
+const SEED: u64 = 0x2c1b_3c6d_4a5b_9e17u64;
+
+/// Number of filter bits per key.  With the optimal number of hash functions
+/// below, this gives a false positive rate of about 1%.
+const BITS_PER_KEY: usize = 10;
+
+/// Number of bits set for each key.
+const NUM_HASHES: u64 = 7;
+
+/// Hash of a key, used to look the key up in Bloom filters.
+///
+/// All filters hash keys the same way, so a key that is looked up in several
+/// batches, e.g., in all batches of a spine, only needs to be hashed once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyHash(u64);
+
+impl KeyHash {
+    /// Hashes `key`.
+    pub fn new<K>(key: &K) -> Self
+    where
+        K: Hash,
+    {
+        let mut hasher = Xxh3::with_seed(SEED);
+        key.hash(&mut hasher);
+        Self(hasher.finish())
+    }
+}
+
+/// A Bloom filter over a set of keys.
+///
+/// The size of the filter is fixed when it is created, based on the number of
+/// keys it is expected to hold.  Inserting more keys raises the false positive
+/// rate.
+#[derive(Clone, Debug, SizeOf)]
+pub(crate) struct BloomFilter {
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Creates an empty filter sized for `num_keys` keys.
+    ///
+    /// Returns `None` if `num_keys` is zero or if the `bloom-filter` feature
+    /// is disabled.
+    pub(crate) fn with_capacity(num_keys: usize) -> Option<Self> {
+        if !cfg!(feature = "bloom-filter") || num_keys == 0 {
+            return None;
+        }
+
+        let num_words = (num_keys * BITS_PER_KEY + 63) / 64;
+        Some(Self {
+            bits: vec![0; num_words],
+        })
+    }
+
+    /// Builds a filter over `keys`.
+    ///
+    /// Returns `None` if `keys` is empty or if the `bloom-filter` feature is
+    /// disabled.
+    pub(crate) fn from_keys<'a, K, I>(keys: I) -> Option<Self>
+    where
+        K: Hash + 'a,
+        I: ExactSizeIterator<Item = &'a K>,
+    {
+        let mut filter = Self::with_capacity(keys.len())?;
+        for key in keys {
+            filter.insert(key);
+        }
+
+        Some(filter)
+    }
+
+    /// Adds `key` to the filter.
+    pub(crate) fn insert<K>(&mut self, key: &K)
+    where
+        K: Hash,
+    {
+        for bit in self.bit_indexes(KeyHash::new(key)) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Returns `false` if `key` is definitely not in the set the filter was
+    /// built from.
+    pub(crate) fn maybe_contains<K>(&self, key: &K) -> bool
+    where
+        K: Hash,
+    {
+        self.maybe_contains_hash(KeyHash::new(key))
+    }
+
+    /// Like [`Self::maybe_contains`], but takes the hash of the key.
+    pub(crate) fn maybe_contains_hash(&self, hash: KeyHash) -> bool {
+        self.bit_indexes(hash)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Derives `NUM_HASHES` bit indexes from the hash of a key using double
+    /// hashing.
+    fn bit_indexes(&self, hash: KeyHash) -> impl Iterator<Item = usize> {
+        let hash1 = hash.0;
+        let hash2 = hash1.rotate_left(32) | 1;
+
+        let num_bits = self.bits.len() as u64 * 64;
+        (0..NUM_HASHES)
+            .map(move |i| (hash1.wrapping_add(i.wrapping_mul(hash2)) % num_bits) as usize)
+    }
+}
+
+#[cfg(all(test, feature = "bloom-filter"))]
+mod test {
+    use super::{BloomFilter, KeyHash};
+    use crate::{
+        trace::{spine_fueled::Spine, Batch, BatchReader, Trace},
+        OrdIndexedZSet,
+    };
+
+    #[test]
+    fn empty() {
+        assert!(BloomFilter::from_keys::<u64, _>([].iter()).is_none());
+    }
+
+    #[test]
+    fn false_positive_rate() {
+        let keys = (0..10_000u64).map(|i| i * 2).collect::<Vec<_>>();
+        let filter = BloomFilter::from_keys(keys.iter()).unwrap();
+
+        // No false negatives.
+        assert!(keys.iter().all(|key| filter.maybe_contains(key)));
+
+        let false_positives = (0..10_000u64)
+            .filter(|i| filter.maybe_contains(&(i * 2 + 1)))
+            .count();
+        assert!(false_positives < 300, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn batch_key_filter() {
+        let batch1 = OrdIndexedZSet::from_tuples((), (0..1000).map(|i| ((i * 2, i), 1)).collect());
+        assert!((0..1000).all(|i| batch1.maybe_contains_key(&(i * 2))));
+        assert!(
+            (0..1000)
+                .filter(|i| batch1.maybe_contains_key(&(i * 2 + 1)))
+                .count()
+                < 30
+        );
+
+        // Merged batches get a new filter.
+        let batch2 =
+            OrdIndexedZSet::from_tuples((), (0..1000).map(|i| ((i * 2 + 1, i), 1)).collect());
+        let merged = batch1.merge(&batch2);
+        assert!((0..2000).all(|i| merged.maybe_contains_key(&i)));
+
+        let mut spine = Spine::new(None);
+        spine.insert(batch1);
+        spine.insert(batch2);
+        assert!((0..2000).all(|i| spine.maybe_contains_key(&i)));
+        assert!((2000..3000).filter(|i| spine.maybe_contains_key(i)).count() < 60);
+
+        // Looking up a precomputed hash gives the same answer as hashing the key.
+        assert!((0..3000).all(|i| {
+            spine.maybe_contains_key(&i) == spine.maybe_contains_key_hash(KeyHash::new(&i))
+        }));
+    }
+}
@@ -5,7 +5,10 @@
 //! each record occurs at most once, with the accumulated weights. These methods
 //! supply that functionality.
 
+#[cfg(feature = "parallel-consolidation")]
+mod parallel;
 mod quicksort;
+mod radix;
 mod tests;
 
 // Public for benchmarks
@@ -24,6 +27,10 @@ use std::{
 };
 use utils::{dedup_payload_starting_at, retain_payload_starting_at, retain_starting_at};
 
+#[cfg(feature = "parallel-consolidation")]
+pub use parallel::{consolidate_parallel, PARALLEL_CONSOLIDATION_THRESHOLD};
+pub use radix::{consolidate_primitive, consolidate_specialized, RadixKey};
+
 /// Sorts and consolidates `vec`.
 ///
 /// This method will sort `vec` and then consolidate runs of more than one entry
@@ -0,0 +1,79 @@
+//! Multi-threaded consolidation of large vectors.
+
+use super::consolidate_slice_inner;
+use crate::algebra::{AddAssignByRef, HasZero};
+use rayon::{current_num_threads, prelude::*};
+
+/// Batches with at least this many tuples are consolidated using
+/// [`consolidate_parallel`] by the merge batcher.  Below this size, the
+/// overhead of distributing work across threads outweighs the gains.
+pub const PARALLEL_CONSOLIDATION_THRESHOLD: usize = 1 << 20;
+
+/// Sorts and consolidates `vec` using the rayon thread pool.
+///
+/// Produces the same result as [`consolidate`](`super::consolidate`):
+/// `vec` is sorted by key, weights of equal keys are added up, and keys
+/// whose accumulated weight is zero are discarded.
+///
+/// The vector is sorted with a parallel merge sort, which sorts chunks of
+/// the vector in parallel and then merges them in parallel.  The sorted
+/// vector is then split into one chunk per thread at key boundaries, so
+/// that all occurrences of a key land in the same chunk, and chunks are
+/// consolidated in parallel.  Finally, the consolidated chunks are
+/// compacted into a contiguous prefix of the vector.
+pub fn consolidate_parallel<T, R>(vec: &mut Vec<(T, R)>)
+where
+    T: Ord + Send,
+    R: AddAssignByRef + HasZero + Send,
+{
+    if vec.is_empty() {
+        return;
+    }
+
+    vec.par_sort_by(|(key1, _), (key2, _)| key1.cmp(key2));
+
+    // Split the vector into chunks that don't share any keys.
+    let chunk_len = (vec.len() + current_num_threads() - 1) / current_num_threads();
+    let mut bounds = Vec::with_capacity(current_num_threads() + 1);
+    bounds.push(0);
+
+    let mut end = 0;
+    while end < vec.len() {
+        end = (end + chunk_len).min(vec.len());
+        while end < vec.len() && vec[end].0 == vec[end - 1].0 {
+            end += 1;
+        }
+        bounds.push(end);
+    }
+
+    let mut chunks = Vec::with_capacity(bounds.len() - 1);
+    let mut rest = vec.as_mut_slice();
+    for window in bounds.windows(2) {
+        let (chunk, tail) = rest.split_at_mut(window[1] - window[0]);
+        chunks.push(chunk);
+        rest = tail;
+    }
+
+    // Consolidate chunks in parallel; each chunk is left with a consolidated
+    // prefix of length `lengths[i]`.
+    let lengths: Vec<usize> = chunks
+        .into_par_iter()
+        .map(|chunk| {
+            consolidate_slice_inner(
+                chunk,
+                |(key1, _), (key2, _)| key1 == key2,
+                |(_, diff1), (_, diff2)| diff1.add_assign_by_ref(diff2),
+                |(_, diff)| diff.is_zero(),
+            )
+        })
+        .collect();
+
+    // Move consolidated prefixes to the front of the vector.
+    let mut offset = 0;
+    for (start, len) in bounds.into_iter().zip(lengths) {
+        vec[offset..start + len].rotate_left(start - offset);
+        offset += len;
+    }
+
+    vec.truncate(offset);
+}
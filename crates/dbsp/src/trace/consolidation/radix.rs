@@ -0,0 +1,167 @@
+//! Consolidation of vectors with primitive integer keys using radix sort.
+
+use crate::algebra::{HasZero, MonoidValue};
+use std::{
+    any::TypeId,
+    mem::{replace, swap},
+    ops::AddAssign,
+    ptr,
+};
+
+/// Inputs shorter than this are sorted using comparison sort, which is
+/// faster than radix sort for small inputs.
+const RADIX_SORT_THRESHOLD: usize = 256;
+
+/// Keys that can be sorted using radix sort.
+///
+/// A key is mapped to an unsigned integer whose ordering matches the
+/// ordering of keys.
+pub trait RadixKey: Ord {
+    /// Number of significant bytes in [`Self::radix_key`].
+    const BYTES: usize;
+
+    /// Unsigned integer representation of the key that preserves ordering,
+    /// i.e., `a.cmp(b) == a.radix_key().cmp(&b.radix_key())`.
+    fn radix_key(&self) -> u64;
+}
+
+macro_rules! unsigned_radix_key {
+    ($($type:ty),* $(,)?) => {
+        $(
+            impl RadixKey for $type {
+                const BYTES: usize = std::mem::size_of::<$type>();
+
+                #[inline]
+                fn radix_key(&self) -> u64 {
+                    *self as u64
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! signed_radix_key {
+    ($($type:ty => $unsigned:ty),* $(,)?) => {
+        $(
+            impl RadixKey for $type {
+                const BYTES: usize = std::mem::size_of::<$type>();
+
+                #[inline]
+                fn radix_key(&self) -> u64 {
+                    // Flip the sign bit, so that negative numbers are ordered
+                    // before positive ones.
+                    (*self as $unsigned ^ (1 << (<$unsigned>::BITS - 1))) as u64
+                }
+            }
+        )*
+    };
+}
+
+unsigned_radix_key!(u8, u16, u32, u64, usize);
+signed_radix_key!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, isize => usize);
+
+/// Sorts and consolidates `vec` using radix sort.
+///
+/// Produces the same result as [`consolidate`](`super::consolidate`):
+/// `vec` is sorted by key, weights of equal keys are added up, and keys
+/// whose accumulated weight is zero are discarded.
+pub fn consolidate_primitive<K, R>(vec: &mut Vec<(K, R)>)
+where
+    K: RadixKey,
+    R: MonoidValue,
+{
+    if vec.is_empty() {
+        return;
+    }
+
+    if vec.len() < RADIX_SORT_THRESHOLD {
+        vec.sort_unstable_by(|(key1, _), (key2, _)| key1.cmp(key2));
+    } else {
+        radix_sort(vec);
+    }
+
+    vec.dedup_by(|(key1, data1), (key2, data2)| {
+        if key1 == key2 {
+            data2.add_assign(replace(data1, R::zero()));
+            true
+        } else {
+            false
+        }
+    });
+    vec.retain(|(_, data)| !data.is_zero());
+}
+
+/// Sorts and consolidates `vec`, using [`consolidate_primitive`] if `T` is a
+/// primitive integer type and [`consolidate`](`super::consolidate`)
+/// otherwise.
+pub fn consolidate_specialized<T, R>(vec: &mut Vec<(T, R)>)
+where
+    T: Ord + 'static,
+    R: MonoidValue,
+{
+    macro_rules! dispatch {
+        ($($type:ty),*) => {
+            $(
+                if TypeId::of::<T>() == TypeId::of::<$type>() {
+                    // Safety: `T` and `$type` are the same type.
+                    let vec = unsafe { &mut *(vec as *mut Vec<(T, R)> as *mut Vec<($type, R)>) };
+                    consolidate_primitive(vec);
+                    return;
+                }
+            )*
+        };
+    }
+
+    dispatch!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+    super::consolidate(vec);
+}
+
+/// Stable least significant digit radix sort of `vec` by key.
+fn radix_sort<K, R>(vec: &mut Vec<(K, R)>)
+where
+    K: RadixKey,
+{
+    let len = vec.len();
+    let mut scratch: Vec<(K, R)> = Vec::with_capacity(len);
+
+    for byte in 0..K::BYTES {
+        let shift = byte * 8;
+        let digit = |key: &K| ((key.radix_key() >> shift) & 0xff) as usize;
+
+        let mut offsets = [0usize; 256];
+        for (key, _) in vec.iter() {
+            offsets[digit(key)] += 1;
+        }
+
+        // All keys have the same digit: the pass wouldn't change the order.
+        if offsets.contains(&len) {
+            continue;
+        }
+
+        let mut sum = 0;
+        for offset in offsets.iter_mut() {
+            sum += replace(offset, sum);
+        }
+
+        // Safety: every element of `vec` is moved to a distinct position in
+        // `scratch`, since `offsets` partition `0..len`.  The lengths of
+        // both vectors are only updated once all elements have been moved,
+        // so if `radix_key` panics, the elements are still owned by `vec`.
+        unsafe {
+            let src = vec.as_ptr();
+            let dst = scratch.as_mut_ptr();
+
+            for index in 0..len {
+                let element = src.add(index);
+                let offset = &mut offsets[digit(&(*element).0)];
+                ptr::copy_nonoverlapping(element, dst.add(*offset), 1);
+                *offset += 1;
+            }
+
+            vec.set_len(0);
+            scratch.set_len(len);
+        }
+
+        swap(vec, &mut scratch);
+    }
+}
@@ -8,7 +8,8 @@ use itertools::Itertools;
 
 use crate::trace::consolidation::{
     consolidate, consolidate_from, consolidate_paired_slices, consolidate_payload_from,
-    consolidate_slice, dedup_payload_starting_at, quicksort::quicksort, retain_starting_at,
+    consolidate_primitive, consolidate_slice, dedup_payload_starting_at, quicksort::quicksort,
+    retain_starting_at,
 };
 
 #[test]
@@ -27,6 +28,24 @@ fn test_consolidate() {
     }
 }
 
+#[test]
+fn test_consolidate_primitive() {
+    // Enough tuples to use radix sort.
+    let mut input: Vec<(i64, isize)> = (-500..500).rev().map(|key| (key, 1)).collect();
+    input.extend((-500..500).step_by(2).map(|key| (key, 1)));
+    input.extend((-500..500).step_by(3).map(|key| (key, -1)));
+    input.extend([(i64::MIN, 1), (i64::MAX, 1), (i64::MIN, 1)]);
+
+    let mut expected = input.clone();
+    consolidate(&mut expected);
+    assert!(expected.len() > 256);
+
+    consolidate_primitive(&mut input);
+    assert_eq!(input, expected);
+    assert_eq!(input[0], (i64::MIN, 2));
+    assert_eq!(input[input.len() - 1], (i64::MAX, 1));
+}
+
 #[test]
 fn test_consolidate_from_start() {
     let test_cases = vec![
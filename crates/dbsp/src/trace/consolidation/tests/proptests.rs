@@ -1,9 +1,11 @@
 #![cfg_attr(miri, ignore)]
 
+#[cfg(feature = "parallel-consolidation")]
+use crate::trace::consolidation::consolidate_parallel;
 use crate::{
     trace::consolidation::{
         consolidate, consolidate_from, consolidate_paired_slices, consolidate_payload_from,
-        consolidate_slice,
+        consolidate_primitive, consolidate_slice, consolidate_specialized,
         quicksort::quicksort,
         utils::{dedup_payload_starting_at, retain_starting_at},
     },
@@ -128,6 +130,17 @@ proptest! {
         prop_assert_eq!(&vec, &slice);
     }
 
+    #[test]
+    #[cfg(feature = "parallel-consolidation")]
+    fn consolidate_parallel_is_equivalent(batch in batch()) {
+        let mut expected = batch.clone();
+        consolidate(&mut expected);
+
+        let mut vec = batch;
+        consolidate_parallel(&mut vec);
+        prop_assert_eq!(vec, expected);
+    }
+
     #[test]
     fn consolidate_pair_is_equivalent(batch in batch()) {
         let expected = batch_data(&batch);
@@ -168,6 +181,38 @@ proptest! {
         prop_assert_eq!(consolidated_diffs, diffs);
     }
 
+    #[test]
+    fn consolidate_primitive_is_equivalent(
+        unsigned in vec((0..1_000u64, -10..=10isize), 0..5_000),
+        signed in vec((any::<i64>(), -10..=10isize), 0..5_000),
+        small in vec((any::<i16>(), -10..=10isize), 0..5_000),
+    ) {
+        let mut expected = unsigned.clone();
+        consolidate(&mut expected);
+        let mut output = unsigned.clone();
+        consolidate_primitive(&mut output);
+        prop_assert_eq!(&expected, &output);
+        let mut output = unsigned;
+        consolidate_specialized(&mut output);
+        prop_assert_eq!(&expected, &output);
+
+        // Repeat some of the keys.
+        let mut signed_dups = signed.clone();
+        signed_dups.extend(signed.iter().take(100).map(|&(key, diff)| (key, -diff)));
+        signed_dups.extend(signed.iter().take(200).map(|&(key, diff)| (key / 2, diff)));
+        let mut expected = signed_dups.clone();
+        consolidate(&mut expected);
+        let mut output = signed_dups;
+        consolidate_primitive(&mut output);
+        prop_assert_eq!(&expected, &output);
+
+        let mut expected = small.clone();
+        consolidate(&mut expected);
+        let mut output = small;
+        consolidate_primitive(&mut output);
+        prop_assert_eq!(&expected, &output);
+    }
+
     #[test]
     fn dual_quicksort_smoke(mut data in vec(any::<(u32, u32)>(), 0..=5000)) {
         let (mut keys, mut values): (Vec<_>, Vec<_>) = data.clone().into_iter().unzip();
@@ -75,6 +75,29 @@ where
         debug_assert!(self.pos >= 0);
         &self.storage.diffs[self.pos as usize]
     }
+
+    /// Returns the keys from the current position to the end of the cursor's
+    /// range, or an empty slice if the cursor is not valid.
+    pub fn remaining_keys(&self) -> &'s [K] {
+        if self.valid() {
+            &self.storage.keys[self.pos as usize..self.bounds.1]
+        } else {
+            &[]
+        }
+    }
+
+    /// Returns the diffs from the current position to the end of the cursor's
+    /// range, or an empty slice if the cursor is not valid.
+    ///
+    /// Since diffs are stored in their own column, this allows aggregating
+    /// weights without touching the keys.
+    pub fn remaining_diffs(&self) -> &'s [R] {
+        if self.valid() {
+            &self.storage.diffs[self.pos as usize..self.bounds.1]
+        } else {
+            &[]
+        }
+    }
 }
 
 impl<'s, K, R> Cursor<'s> for ColumnLayerCursor<'s, K, R>
@@ -381,6 +381,11 @@ where
             self.lower_bound = min(lower_bound, self.keys.len());
         }
     }
+
+    fn shrink_to_fit(&mut self) {
+        self.keys.shrink_to_fit();
+        self.diffs.shrink_to_fit();
+    }
 }
 
 impl<K, R> Display for ColumnLayer<K, R>
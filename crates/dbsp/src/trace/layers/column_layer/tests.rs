@@ -5,7 +5,7 @@ use crate::{
     trace::{
         layers::{
             column_layer::{ColumnLayerBuilder, ColumnLayerConsumer},
-            Builder, TupleBuilder,
+            Builder, Cursor, Trie, TupleBuilder,
         },
         Consumer, ValueConsumer,
     },
@@ -237,3 +237,23 @@ mod proptests {
         }
     }
 }
+
+#[test]
+fn cursor_remaining_columns() {
+    let mut builder = <ColumnLayerBuilder<usize, i32> as TupleBuilder>::new();
+    builder.extend_tuples((0..10).map(|i| (i, i as i32)));
+    let layer = builder.done();
+
+    let mut cursor = layer.cursor_from(2, 8);
+    assert_eq!(cursor.remaining_keys(), &[2, 3, 4, 5, 6, 7]);
+    assert_eq!(cursor.remaining_diffs().iter().sum::<i32>(), 27);
+
+    cursor.seek(&6);
+    assert_eq!(cursor.remaining_keys(), &[6, 7]);
+    assert_eq!(cursor.remaining_diffs(), &[6, 7]);
+
+    cursor.seek(&8);
+    assert!(!cursor.valid());
+    assert!(cursor.remaining_keys().is_empty());
+    assert!(cursor.remaining_diffs().is_empty());
+}
@@ -100,6 +100,15 @@ pub trait Trie: Sized {
     /// The result is equal to the largest bound specified via
     /// [`Self::truncate_below`] or 0 if `truncate_below` was never called.
     fn lower_bound(&self) -> usize;
+
+    /// Releases excess capacity held by the trie's internal buffers.
+    ///
+    /// Builders allocate space pessimistically, e.g., merging two tries
+    /// reserves room for the sum of their sizes even if many tuples cancel
+    /// out.  Long-lived collections can call this method to shrink their
+    /// allocations to fit their contents.  The default implementation does
+    /// nothing.
+    fn shrink_to_fit(&mut self) {}
 }
 
 /// A type used to assemble collections.
@@ -235,6 +244,11 @@ pub trait Cursor<'s> {
 /// Trait for types used as offsets into an ordered layer.
 /// This is usually `usize`, but `u32` can also be used in applications
 /// where huge batches do not occur to reduce metadata size.
+///
+/// The offset type is selected statically via the `O` type argument of
+/// [`OrderedLayer`](`ordered::OrderedLayer`) and the batch types built on top
+/// of it, e.g., `OrdIndexedZSet<K, V, R, u32>`.  Building a layer with more
+/// values than the offset type can address panics.
 pub trait OrdOffset:
     Copy
     + PartialEq
@@ -269,7 +283,12 @@ where
 {
     #[inline]
     fn from_usize(offset: usize) -> Self {
-        offset.try_into().unwrap()
+        offset.try_into().unwrap_or_else(|error| {
+            panic!(
+                "offset {offset} does not fit into a {}-byte offset type: {error:?}",
+                std::mem::size_of::<O>(),
+            )
+        })
     }
 
     #[inline]
@@ -298,6 +298,12 @@ where
         let vals_bound = self.offs[self.lower_bound];
         self.vals.truncate_below(vals_bound.into_usize());
     }
+
+    fn shrink_to_fit(&mut self) {
+        self.keys.shrink_to_fit();
+        self.offs.shrink_to_fit();
+        self.vals.shrink_to_fit();
+    }
 }
 
 impl<K, L, O> Default for OrderedLayer<K, L, O>
@@ -834,6 +840,31 @@ where
     }
 }
 
+impl<K, L, O> OrderedBuilder<K, L, O>
+where
+    K: Ord + Clone,
+    L: TupleBuilder,
+    O: OrdOffset,
+{
+    /// Creates a builder with room for exactly `keys` distinct keys and
+    /// `tuples` values.
+    ///
+    /// Unlike [`TupleBuilder::with_capacity`], which assumes that each key
+    /// has a single value and allocates `capacity` slots in both layers, this
+    /// constructor sizes each layer separately, avoiding slack in the key
+    /// and offset arrays when keys have many values.
+    pub fn with_exact_capacity(keys: usize, tuples: usize) -> Self {
+        let mut offs = Vec::with_capacity(keys + 1);
+        offs.push(O::zero());
+
+        Self {
+            keys: Vec::with_capacity(keys),
+            offs,
+            vals: L::with_capacity(tuples),
+        }
+    }
+}
+
 impl<K, L, O> TupleBuilder for OrderedBuilder<K, L, O>
 where
     K: Ord + Clone,
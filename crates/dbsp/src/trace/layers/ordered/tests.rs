@@ -1,13 +1,18 @@
 #![cfg(test)]
 
-use crate::trace::{
-    layers::{
-        column_layer::ColumnLayerBuilder,
-        ordered::{OrderedBuilder, OrderedLayerConsumer},
-        Builder, TupleBuilder,
+use crate::{
+    algebra::NegByRef,
+    trace::{
+        layers::{
+            column_layer::{ColumnLayer, ColumnLayerBuilder},
+            ordered::{OrderedBuilder, OrderedLayer, OrderedLayerConsumer},
+            Builder, Cursor, OrdOffset, Trie, TupleBuilder,
+        },
+        Batch, BatchReader, Consumer, Cursor as _, ValueConsumer,
     },
-    Consumer, ValueConsumer,
+    OrdIndexedZSet, OrdZSet,
 };
+use size_of::SizeOf;
 
 fn empty_consumer() -> OrderedLayerConsumer<usize, usize, isize, usize> {
     OrderedLayerConsumer::from(
@@ -107,3 +112,196 @@ fn next_empty_values() {
 
     let _ = values.next_value();
 }
+
+fn build_layer<O>(keys: usize, vals: usize) -> OrderedLayer<usize, ColumnLayer<usize, isize>, O>
+where
+    O: OrdOffset,
+{
+    let mut builder = OrderedBuilder::<usize, ColumnLayerBuilder<usize, isize>, O>::new();
+    for key in 0..keys {
+        for val in 0..vals {
+            builder.push_tuple((key * 2, (val, 1)));
+        }
+    }
+    builder.done()
+}
+
+#[test]
+fn u32_offsets_match_usize() {
+    let wide = build_layer::<usize>(1000, 5);
+    let narrow = build_layer::<u32>(1000, 5);
+
+    let mut wide_cursor = wide.cursor();
+    let mut narrow_cursor = narrow.cursor();
+    assert_eq!(wide_cursor.keys(), narrow_cursor.keys());
+
+    while wide_cursor.valid() {
+        assert!(narrow_cursor.valid());
+        assert_eq!(wide_cursor.item(), narrow_cursor.item());
+
+        let mut wide_vals = wide_cursor.values();
+        let mut narrow_vals = narrow_cursor.values();
+        while wide_vals.valid() {
+            assert!(narrow_vals.valid());
+            assert_eq!(wide_vals.item(), narrow_vals.item());
+            wide_vals.step();
+            narrow_vals.step();
+        }
+        assert!(!narrow_vals.valid());
+
+        wide_cursor.step();
+        narrow_cursor.step();
+    }
+    assert!(!narrow_cursor.valid());
+
+    // Seeking lands on the same key, including keys that are not present.
+    for key in [0, 1, 500, 1001, 1998, 1999] {
+        wide_cursor.rewind();
+        narrow_cursor.rewind();
+        wide_cursor.seek(&key);
+        narrow_cursor.seek(&key);
+        assert_eq!(wide_cursor.valid(), narrow_cursor.valid());
+        if wide_cursor.valid() {
+            assert_eq!(wide_cursor.item(), narrow_cursor.item());
+            assert_eq!(wide_cursor.values().keys(), narrow_cursor.values().keys());
+        }
+    }
+
+    // Merging preserves the equivalence.
+    let wide_merged = wide.merge(&build_layer::<usize>(10, 2));
+    let narrow_merged = narrow.merge(&build_layer::<u32>(10, 2));
+    assert_eq!(wide_merged.keys(), narrow_merged.keys());
+    assert_eq!(wide_merged.tuples(), narrow_merged.tuples());
+}
+
+#[test]
+fn u32_offsets_use_less_memory() {
+    let wide = build_layer::<usize>(100_000, 1);
+    let narrow = build_layer::<u32>(100_000, 1);
+
+    let wide_offsets = wide.offs.size_of().total_bytes();
+    let narrow_offsets = narrow.offs.size_of().total_bytes();
+
+    // The offset array shrinks by half, everything else stays the same.
+    assert_eq!(narrow_offsets * 2, wide_offsets);
+    assert_eq!(
+        wide.size_of().total_bytes() - wide_offsets,
+        narrow.size_of().total_bytes() - narrow_offsets,
+    );
+}
+
+#[test]
+#[should_panic(expected = "does not fit into a 1-byte offset type")]
+fn offset_overflow() {
+    build_layer::<u8>(1, 256);
+}
+
+#[test]
+fn shrink_to_fit() {
+    let mut builder =
+        OrderedBuilder::<usize, ColumnLayerBuilder<usize, isize>, usize>::with_capacity(1000);
+    for key in 0..10 {
+        for val in 0..10 {
+            builder.push_tuple((key, (val, 1)));
+        }
+    }
+    let mut layer = builder.done();
+
+    let before = layer.size_of();
+    assert!(before.excess_bytes() > 0);
+
+    layer.shrink_to_fit();
+    let after = layer.size_of();
+    assert_eq!(after.excess_bytes(), 0);
+    assert_eq!(after.used_bytes(), before.used_bytes());
+    assert!(after.total_bytes() < before.total_bytes());
+
+    // The layer is still navigable.
+    let mut cursor = layer.cursor();
+    for key in 0..10 {
+        assert!(cursor.valid());
+        assert_eq!(*cursor.item(), key);
+        assert_eq!(cursor.values().keys(), 10);
+        cursor.step();
+    }
+    assert!(!cursor.valid());
+}
+
+#[test]
+fn with_exact_capacity() {
+    let mut builder =
+        OrderedBuilder::<usize, ColumnLayerBuilder<usize, isize>, usize>::with_exact_capacity(
+            10, 100,
+        );
+    for key in 0..10 {
+        for val in 0..10 {
+            builder.push_tuple((key, (val, 1)));
+        }
+    }
+    let layer = builder.done();
+
+    assert_eq!(layer.keys(), 10);
+    assert_eq!(layer.tuples(), 100);
+    assert_eq!(layer.size_of().excess_bytes(), 0);
+}
+
+#[test]
+fn shrink_batches_to_fit() {
+    // Merging a batch with its negation yields an empty batch that still
+    // holds on to the memory reserved for the merge.
+    let zset =
+        OrdZSet::<usize, isize>::from_keys((), (0..1000).map(|x| (x, 1)).collect::<Vec<_>>());
+    let mut merged = zset.merge(&zset.neg_by_ref());
+    assert!(merged.is_empty());
+
+    let before = merged.size_of();
+    assert!(before.excess_bytes() > 0);
+
+    merged.shrink_to_fit();
+    let after = merged.size_of();
+    assert_eq!(after.excess_bytes(), 0);
+    assert!(after.total_bytes() < before.total_bytes());
+
+    let zset =
+        OrdZSet::<usize, isize>::from_keys((), (0..1000).map(|x| (x, 1)).collect::<Vec<_>>());
+    let mut merged = zset.merge(&OrdZSet::from_keys(
+        (),
+        (0..900).map(|x| (x, -1)).collect::<Vec<_>>(),
+    ));
+    merged.shrink_to_fit();
+    assert_eq!(merged.size_of().excess_bytes(), 0);
+
+    let mut cursor = merged.cursor();
+    for key in 900..1000 {
+        assert!(cursor.key_valid());
+        assert_eq!(*cursor.key(), key);
+        assert_eq!(cursor.weight(), 1);
+        cursor.step_key();
+    }
+    assert!(!cursor.key_valid());
+
+    let indexed = OrdIndexedZSet::<usize, usize, isize>::from_tuples(
+        (),
+        (0..100)
+            .flat_map(|k| (0..10).map(move |v| ((k, v), 1)))
+            .collect::<Vec<_>>(),
+    );
+    let mut merged = indexed.merge(&OrdIndexedZSet::from_tuples(
+        (),
+        (0..100)
+            .flat_map(|k| (0..9).map(move |v| ((k, v), -1)))
+            .collect::<Vec<_>>(),
+    ));
+    merged.shrink_to_fit();
+    assert_eq!(merged.size_of().excess_bytes(), 0);
+    assert_eq!(merged.len(), 100);
+
+    let mut cursor = merged.cursor();
+    for key in 0..100 {
+        assert!(cursor.key_valid());
+        assert_eq!(*cursor.key(), key);
+        assert_eq!(*cursor.val(), 9);
+        cursor.step_key();
+    }
+    assert!(!cursor.key_valid());
+}
@@ -76,6 +76,10 @@ where
     fn truncate_below(&mut self, lower_bound: usize) {
         self.truncate(lower_bound);
     }
+
+    fn shrink_to_fit(&mut self) {
+        self.vals.shrink_to_fit();
+    }
 }
 
 impl<K, R> Display for OrderedLeaf<K, R>
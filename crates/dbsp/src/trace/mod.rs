@@ -10,6 +10,7 @@
 //! and allows various data structures to be interpretable as multiple different
 //! types of trace.
 
+pub(crate) mod bloom_filter;
 pub mod consolidation;
 pub mod cursor;
 pub mod layers;
@@ -17,12 +18,16 @@ pub mod ord;
 #[cfg(feature = "persistence")]
 pub mod persistent;
 pub mod spine_fueled;
+pub mod spine_persistent;
 
+pub use bloom_filter::KeyHash;
 pub use cursor::{Consumer, Cursor, ValueConsumer};
 #[cfg(feature = "persistence")]
 pub use persistent::PersistentTrace as Spine;
+pub use spine_fueled::CompactionPolicy;
 #[cfg(not(feature = "persistence"))]
 pub use spine_fueled::Spine;
+pub use spine_persistent::{PersistentSpine, SpillPolicy};
 
 #[cfg(test)]
 mod test_batch;
@@ -231,6 +236,24 @@ where
     /// The removed tuples may not get deallocated instantly but they won't
     /// appear when iterating over the batch.
     fn truncate_keys_below(&mut self, lower_bound: &Self::Key);
+
+    /// Returns `false` if the batch definitely doesn't contain `key` and
+    /// `true` if it may contain it.
+    ///
+    /// Lets operators that look up individual keys, e.g., joins, skip the
+    /// lookup.  Batches that keep a Bloom filter over their keys (see the
+    /// `bloom-filter` feature) answer from the filter; others always return
+    /// `true`.
+    fn maybe_contains_key(&self, _key: &Self::Key) -> bool {
+        true
+    }
+
+    /// Like [`Self::maybe_contains_key`], but takes the hash of the key, so
+    /// that callers that look up the same key in several batches only hash
+    /// it once.
+    fn maybe_contains_key_hash(&self, _hash: KeyHash) -> bool {
+        true
+    }
 }
 
 /// An immutable collection of updates.
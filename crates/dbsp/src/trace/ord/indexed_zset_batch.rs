@@ -2,6 +2,7 @@ use crate::{
     algebra::{AddAssignByRef, AddByRef, MonoidValue, NegByRef},
     time::AntichainRef,
     trace::{
+        bloom_filter::{BloomFilter, KeyHash},
         layers::{
             column_layer::{ColumnLayer, ColumnLayerBuilder},
             ordered::{
@@ -19,6 +20,7 @@ use crate::{
 use size_of::SizeOf;
 use std::{
     fmt::{self, Debug, Display},
+    hash::Hash,
     marker::PhantomData,
     ops::{Add, AddAssign, Neg},
     rc::Rc,
@@ -27,7 +29,7 @@ use std::{
 type Layers<K, V, R, O> = OrderedLayer<K, ColumnLayer<V, R>, O>;
 
 /// An immutable collection of update tuples.
-#[derive(Debug, Clone, Eq, PartialEq, SizeOf)]
+#[derive(Debug, Clone, SizeOf)]
 pub struct OrdIndexedZSet<K, V, R, O = usize>
 where
     K: Ord,
@@ -38,6 +40,81 @@ where
     /// Where all the data is.
     #[doc(hidden)]
     pub layer: Layers<K, V, R, O>,
+    /// Bloom filter over the keys of `layer`.  Only built by builders and
+    /// mergers; batches constructed in other ways don't have a filter.
+    key_filter: Option<BloomFilter>,
+}
+
+impl<K, V, R, O> OrdIndexedZSet<K, V, R, O>
+where
+    K: Ord + Hash,
+    V: Ord,
+    R: Clone,
+    O: OrdOffset,
+{
+    /// Creates a batch from `layer` with a Bloom filter over its keys.
+    fn with_key_filter(layer: Layers<K, V, R, O>) -> Self {
+        let key_filter = BloomFilter::from_keys(layer.as_parts().0.iter());
+        Self { layer, key_filter }
+    }
+}
+
+// The key filter doesn't affect the contents of the batch.
+impl<K, V, R, O> PartialEq for OrdIndexedZSet<K, V, R, O>
+where
+    K: Ord,
+    V: Ord,
+    R: Clone + PartialEq,
+    O: OrdOffset,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.layer == other.layer
+    }
+}
+
+impl<K, V, R, O> Eq for OrdIndexedZSet<K, V, R, O>
+where
+    K: Ord,
+    V: Ord,
+    R: Clone + Eq,
+    O: OrdOffset + Eq,
+{
+}
+
+impl<K, V, R, O> OrdIndexedZSet<K, V, R, O>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight,
+    O: OrdOffset,
+{
+    /// Shrinks the capacity of the underlying storage to fit its contents.
+    ///
+    /// See [`OrdZSet::shrink_to_fit`](`crate::OrdZSet::shrink_to_fit`).
+    pub fn shrink_to_fit(&mut self) {
+        self.layer.shrink_to_fit();
+    }
+
+    /// Returns all values in the batch along with their weights, ordered by
+    /// key and then by value.
+    ///
+    /// Useful for emitting the output of
+    /// [`Stream::sort_by`](`crate::Stream::sort_by`), which indexes records by
+    /// their sort key.
+    pub fn to_sorted_vec(&self) -> Vec<(V, R)> {
+        let mut result = Vec::with_capacity(self.len());
+        let mut cursor = self.cursor();
+
+        while cursor.key_valid() {
+            while cursor.val_valid() {
+                result.push((cursor.val().clone(), cursor.weight()));
+                cursor.step_val();
+            }
+            cursor.step_key();
+        }
+
+        result
+    }
 }
 
 impl<K, V, R, O> Display for OrdIndexedZSet<K, V, R, O>
@@ -79,7 +156,10 @@ where
 {
     #[inline]
     fn from(layer: Layers<K, V, R, O>) -> Self {
-        Self { layer }
+        Self {
+            layer,
+            key_filter: None,
+        }
     }
 }
 
@@ -127,6 +207,7 @@ where
     fn neg_by_ref(&self) -> Self {
         Self {
             layer: self.layer.neg_by_ref(),
+            key_filter: self.key_filter.clone(),
         }
     }
 }
@@ -144,6 +225,7 @@ where
     fn neg(self) -> Self {
         Self {
             layer: self.layer.neg(),
+            key_filter: self.key_filter,
         }
     }
 }
@@ -162,6 +244,7 @@ where
     fn add(self, rhs: Self) -> Self::Output {
         Self {
             layer: self.layer.add(rhs.layer),
+            key_filter: None,
         }
     }
 }
@@ -176,6 +259,7 @@ where
     #[inline]
     fn add_assign(&mut self, rhs: Self) {
         self.layer.add_assign(rhs.layer);
+        self.key_filter = None;
     }
 }
 
@@ -189,6 +273,7 @@ where
     #[inline]
     fn add_assign_by_ref(&mut self, rhs: &Self) {
         self.layer.add_assign_by_ref(&rhs.layer);
+        self.key_filter = None;
     }
 }
 
@@ -203,6 +288,7 @@ where
     fn add_by_ref(&self, rhs: &Self) -> Self {
         Self {
             layer: self.layer.add_by_ref(&rhs.layer),
+            key_filter: None,
         }
     }
 }
@@ -218,7 +304,8 @@ where
     type Val = V;
     type Time = ();
     type R = R;
-    type Cursor<'s> = OrdIndexedZSetCursor<'s, K, V, R, O>
+    type Cursor<'s>
+        = OrdIndexedZSetCursor<'s, K, V, R, O>
     where
         V: 's,
         O: 's;
@@ -261,6 +348,18 @@ where
     fn truncate_keys_below(&mut self, lower_bound: &Self::Key) {
         self.layer.truncate_keys_below(lower_bound);
     }
+
+    fn maybe_contains_key(&self, key: &Self::Key) -> bool {
+        self.key_filter
+            .as_ref()
+            .map_or(true, |filter| filter.maybe_contains(key))
+    }
+
+    fn maybe_contains_key_hash(&self, hash: KeyHash) -> bool {
+        self.key_filter
+            .as_ref()
+            .map_or(true, |filter| filter.maybe_contains_hash(hash))
+    }
 }
 
 impl<K, V, R, O> Batch for OrdIndexedZSet<K, V, R, O>
@@ -300,6 +399,7 @@ where
     fn empty(_time: Self::Time) -> Self {
         Self {
             layer: OrderedLayer::default(),
+            key_filter: None,
         }
     }
 }
@@ -351,9 +451,7 @@ where
 
     #[inline]
     fn done(self) -> OrdIndexedZSet<K, V, R, O> {
-        OrdIndexedZSet {
-            layer: self.result.done(),
-        }
+        OrdIndexedZSet::with_key_filter(self.result.done())
     }
 
     fn work(
@@ -517,6 +615,24 @@ where
     builder: IndexBuilder<K, V, R, O>,
 }
 
+impl<K, V, R, O> OrdIndexedZSetBuilder<K, V, R, O>
+where
+    K: DBData,
+    V: DBData,
+    R: DBWeight,
+    O: OrdOffset,
+{
+    /// Creates a builder with room for exactly `keys` distinct keys and
+    /// `tuples` updates.
+    ///
+    /// See [`OrderedBuilder::with_exact_capacity`].
+    pub fn with_exact_capacity(keys: usize, tuples: usize) -> Self {
+        Self {
+            builder: IndexBuilder::<K, V, R, O>::with_exact_capacity(keys, tuples),
+        }
+    }
+}
+
 impl<K, V, R, O> Builder<(K, V), (), R, OrdIndexedZSet<K, V, R, O>>
     for OrdIndexedZSetBuilder<K, V, R, O>
 where
@@ -552,9 +668,7 @@ where
 
     #[inline(never)]
     fn done(self) -> OrdIndexedZSet<K, V, R, O> {
-        OrdIndexedZSet {
-            layer: self.builder.done(),
-        }
+        OrdIndexedZSet::with_key_filter(self.builder.done())
     }
 }
 
@@ -569,7 +683,8 @@ impl<K, V, R, O> Consumer<K, V, R, ()> for OrdIndexedZSetConsumer<K, V, R, O>
 where
     O: OrdOffset,
 {
-    type ValueConsumer<'a> = OrdIndexedZSetValueConsumer<'a, K, V,  R, O>
+    type ValueConsumer<'a>
+        = OrdIndexedZSetValueConsumer<'a, K, V, R, O>
     where
         Self: 'a;
 
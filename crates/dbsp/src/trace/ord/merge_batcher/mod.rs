@@ -25,61 +25,81 @@ pub struct MergeBatcher<I: Ord, T: Ord, R: MonoidValue, B: Batch<Item = I, Time
     phantom: PhantomData<B>,
 }
 
-impl<I, T, R, B> Batcher<I, T, R, B> for MergeBatcher<I, T, R, B>
-where
-    Self: SizeOf,
-    I: Ord + Clone,
-    T: DBTimestamp,
-    R: MonoidValue,
-    B: Batch<Item = I, Time = T, R = R>,
-{
-    fn new_batcher(time: T) -> Self {
-        Self {
-            sorter: MergeSorter::new(),
-            time,
-            phantom: PhantomData,
-        }
-    }
+// Parallel consolidation sends tuples across threads, which requires them to
+// be `Send`.  Without the `parallel-consolidation` feature, the batcher
+// doesn't impose this bound.
+macro_rules! impl_batcher {
+    ($($send:ident)?) => {
+        impl<I, T, R, B> Batcher<I, T, R, B> for MergeBatcher<I, T, R, B>
+        where
+            Self: SizeOf,
+            I: Ord + Clone $(+ $send)? + 'static,
+            T: DBTimestamp,
+            R: MonoidValue $(+ $send)?,
+            B: Batch<Item = I, Time = T, R = R>,
+        {
+            fn new_batcher(time: T) -> Self {
+                Self {
+                    sorter: MergeSorter::new(),
+                    time,
+                    phantom: PhantomData,
+                }
+            }
 
-    fn push_batch(&mut self, batch: &mut Vec<(I, R)>) {
-        self.sorter.push(batch);
-    }
+            fn push_batch(&mut self, batch: &mut Vec<(I, R)>) {
+                // Consolidate very large batches using multiple threads.
+                #[cfg(feature = "parallel-consolidation")]
+                if batch.len() >= consolidation::PARALLEL_CONSOLIDATION_THRESHOLD {
+                    consolidation::consolidate_parallel(batch);
+                    self.sorter.push_consolidated(batch);
+                    return;
+                }
 
-    fn push_consolidated_batch(&mut self, batch: &mut Vec<(I, R)>) {
-        self.sorter.push_consolidated(batch);
-    }
+                self.sorter.push(batch);
+            }
 
-    fn tuples(&self) -> usize {
-        self.sorter.tuples()
-    }
+            fn push_consolidated_batch(&mut self, batch: &mut Vec<(I, R)>) {
+                self.sorter.push_consolidated(batch);
+            }
 
-    // Sealing a batch means finding those updates with times not greater or equal
-    // to any time in `upper`. All updates must have time greater or equal to
-    // the previously used `upper`, which we call `lower`, by assumption that
-    // after sealing a batcher we receive no more updates with times not greater
-    // or equal to `upper`.
-    // TODO: Since sealing takes self by value all of the buffers we've collected
-    //       are just discarded, which isn't ideal
-    // TODO: Should we just merge batches until completion instead of having
-    //       the inner builder do it?
-    fn seal(mut self) -> B {
-        let mut merged = Vec::new();
-        self.sorter.finish_into(&mut merged);
-
-        // Try and pre-allocate our builder a little bit
-        let mut builder = B::Builder::with_capacity(
-            self.time.clone(),
-            merged.iter().map(|batch| batch.len()).sum(),
-        );
+            fn tuples(&self) -> usize {
+                self.sorter.tuples()
+            }
 
-        for buffer in merged.drain(..) {
-            builder.extend(buffer.into_iter());
-        }
+            // Sealing a batch means finding those updates with times not greater or equal
+            // to any time in `upper`. All updates must have time greater or equal to
+            // the previously used `upper`, which we call `lower`, by assumption that
+            // after sealing a batcher we receive no more updates with times not greater
+            // or equal to `upper`.
+            // TODO: Since sealing takes self by value all of the buffers we've collected
+            //       are just discarded, which isn't ideal
+            // TODO: Should we just merge batches until completion instead of having
+            //       the inner builder do it?
+            fn seal(mut self) -> B {
+                let mut merged = Vec::new();
+                self.sorter.finish_into(&mut merged);
+
+                // Try and pre-allocate our builder a little bit
+                let mut builder = B::Builder::with_capacity(
+                    self.time.clone(),
+                    merged.iter().map(|batch| batch.len()).sum(),
+                );
+
+                for buffer in merged.drain(..) {
+                    builder.extend(buffer.into_iter());
+                }
 
-        builder.done()
-    }
+                builder.done()
+            }
+        }
+    };
 }
 
+#[cfg(feature = "parallel-consolidation")]
+impl_batcher!(Send);
+#[cfg(not(feature = "parallel-consolidation"))]
+impl_batcher!();
+
 #[derive(Debug, SizeOf)]
 struct MergeSorter<D: Ord, R: MonoidValue> {
     /// Queue's invariant is not that every `Vec<Vec<(D, R)>>` is sorted
@@ -90,7 +110,7 @@ struct MergeSorter<D: Ord, R: MonoidValue> {
     stash: Vec<Vec<(D, R)>>,
 }
 
-impl<D: Ord, R: MonoidValue> MergeSorter<D, R> {
+impl<D: Ord + 'static, R: MonoidValue> MergeSorter<D, R> {
     /// The maximum number of bytes we'd like our buffers to contain
     ///
     /// Note that this isn't a hard limit or something that can be
@@ -170,8 +190,9 @@ impl<D: Ord, R: MonoidValue> MergeSorter<D, R> {
                 take(batch)
             };
 
-            // Consolidate and push the batch we were given
-            consolidation::consolidate(&mut batch);
+            // Consolidate and push the batch we were given, using radix sort for
+            // primitive keys
+            consolidation::consolidate_specialized(&mut batch);
             if !batch.is_empty() {
                 self.queue.push(vec![batch]);
 
@@ -2,6 +2,7 @@ use crate::{
     algebra::{Lattice, MonoidValue},
     time::{Antichain, AntichainRef},
     trace::{
+        bloom_filter::{BloomFilter, KeyHash},
         layers::{
             column_layer::{ColumnLayer, ColumnLayerBuilder},
             ordered::{OrderedBuilder, OrderedCursor, OrderedLayer},
@@ -36,6 +37,8 @@ where
     pub layer: OrdValBatchLayer<K, V, T, R, O>,
     pub lower: Antichain<T>,
     pub upper: Antichain<T>,
+    /// Bloom filter over the keys of `layer`.
+    key_filter: Option<BloomFilter>,
 }
 
 impl<K, V, T, R, O> NumEntries for OrdValBatch<K, V, T, R, O>
@@ -91,7 +94,8 @@ where
     type Time = T;
     type R = R;
 
-    type Cursor<'s> = OrdValCursor<'s, K, V, T, R, O>
+    type Cursor<'s>
+        = OrdValCursor<'s, K, V, T, R, O>
     where
         O: 's;
 
@@ -126,6 +130,18 @@ where
     fn truncate_keys_below(&mut self, lower_bound: &Self::Key) {
         self.layer.truncate_keys_below(lower_bound);
     }
+
+    fn maybe_contains_key(&self, key: &Self::Key) -> bool {
+        self.key_filter
+            .as_ref()
+            .map_or(true, |filter| filter.maybe_contains(key))
+    }
+
+    fn maybe_contains_key_hash(&self, hash: KeyHash) -> bool {
+        self.key_filter
+            .as_ref()
+            .map_or(true, |filter| filter.maybe_contains_hash(hash))
+    }
 }
 
 impl<K, V, T, R, O> Batch for OrdValBatch<K, V, T, R, O>
@@ -320,8 +336,10 @@ where
         assert!(self.lower1 == self.upper1);
         assert!(self.lower2 == self.upper2);
 
+        let layer = self.result.done();
         OrdValBatch {
-            layer: self.result.done(),
+            key_filter: BloomFilter::from_keys(layer.as_parts().0.iter()),
+            layer,
             lower: self.lower,
             upper: self.upper,
         }
@@ -548,8 +566,10 @@ where
         } else {
             Antichain::from_elem(time_next)
         };
+        let layer = self.builder.done();
         OrdValBatch {
-            layer: self.builder.done(),
+            key_filter: BloomFilter::from_keys(layer.as_parts().0.iter()),
+            layer,
             lower: Antichain::from_elem(self.time),
             upper,
         }
@@ -561,7 +581,8 @@ pub struct OrdValConsumer<K, V, T, R, O> {
 }
 
 impl<K, V, T, R, O> Consumer<K, V, R, T> for OrdValConsumer<K, V, T, R, O> {
-    type ValueConsumer<'a> = OrdValValueConsumer<'a, K, V, T, R, O>
+    type ValueConsumer<'a>
+        = OrdValValueConsumer<'a, K, V, T, R, O>
     where
         Self: 'a;
 
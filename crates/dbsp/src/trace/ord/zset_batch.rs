@@ -66,6 +66,21 @@ impl<K, R> OrdZSet<K, R> {
     }
 }
 
+impl<K, R> OrdZSet<K, R>
+where
+    K: DBData,
+    R: DBWeight,
+{
+    /// Shrinks the capacity of the underlying storage to fit its contents.
+    ///
+    /// Batches produced by merges and builders may keep excess capacity
+    /// around; calling this method on long-lived collections returns it to
+    /// the allocator.
+    pub fn shrink_to_fit(&mut self) {
+        self.layer.shrink_to_fit();
+    }
+}
+
 impl<K, R> Display for OrdZSet<K, R>
 where
     K: DBData,
@@ -80,11 +80,11 @@
 //! layers by continuing to provide fuel as updates arrive.
 
 use crate::{
-    circuit::Activator,
+    circuit::{Activator, Runtime},
     time::{Antichain, AntichainRef, Timestamp},
     trace::{
         cursor::{Cursor, CursorList},
-        Batch, BatchReader, Consumer, Merger, Trace, ValueConsumer,
+        Batch, BatchReader, Consumer, KeyHash, Merger, Trace, ValueConsumer,
     },
     NumEntries,
 };
@@ -97,6 +97,49 @@ use std::{
 };
 use textwrap::indent;
 
+/// Controls how eagerly a [`Spine`] merges its batches.
+///
+/// Each batch inserted into the spine contributes an amount of fuel
+/// proportional to its size to in-progress merges.  The compaction policy
+/// scales this amount by an effort factor.
+///
+/// The default effort of `1` is the minimal amount sufficient to complete
+/// each merge before its output is needed by the next merge, so it spends
+/// the least CPU time on merging per step.  Larger values complete merges
+/// sooner, so that the spine holds fewer batches at any time: memory held
+/// by batches being merged is released earlier, and cursors over the trace
+/// have fewer batches to consult, at the cost of more merging work in each
+/// step.  The policy only affects the internal layout of the trace and not
+/// its contents.
+///
+/// The policy is configured for all traces in a runtime via
+/// [`RuntimeConfig`](`crate::circuit::RuntimeConfig`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompactionPolicy {
+    effort: usize,
+}
+
+impl CompactionPolicy {
+    /// Create a policy with the given merge effort factor.  An effort of `0`
+    /// is treated as `1`.
+    pub const fn with_effort(effort: usize) -> Self {
+        Self {
+            effort: if effort == 0 { 1 } else { effort },
+        }
+    }
+
+    /// Returns the merge effort factor.
+    pub const fn effort(&self) -> usize {
+        self.effort
+    }
+}
+
+impl Default for CompactionPolicy {
+    fn default() -> Self {
+        Self::with_effort(1)
+    }
+}
+
 /// An append-only collection of update tuples.
 ///
 /// A spine maintains a small number of immutable collections of update tuples,
@@ -187,33 +230,7 @@ where
     }
 
     fn cursor(&self) -> Self::Cursor<'_> {
-        let mut cursors = Vec::with_capacity(self.merging.len());
-        for merge_state in self.merging.iter().rev() {
-            match merge_state {
-                MergeState::Double(MergeVariant::InProgress(batch1, batch2, _)) => {
-                    if !batch1.is_empty() {
-                        cursors.push(batch1.cursor());
-                    }
-
-                    if !batch2.is_empty() {
-                        cursors.push(batch2.cursor());
-                    }
-                }
-
-                MergeState::Double(MergeVariant::Complete(Some(batch)))
-                | MergeState::Single(Some(batch)) => {
-                    if !batch.is_empty() {
-                        cursors.push(batch.cursor());
-                    }
-                }
-
-                MergeState::Double(MergeVariant::Complete(None))
-                | MergeState::Single(None)
-                | MergeState::Vacant => {}
-            }
-        }
-
-        SpineCursor::new(cursors)
+        SpineCursor::new(self.batch_cursors())
     }
 
     fn consumer(self) -> Self::Consumer {
@@ -231,6 +248,17 @@ where
         self.lower_key_bound = Some(bound.clone());
         self.map_batches_mut(|batch| batch.truncate_keys_below(&bound));
     }
+
+    fn maybe_contains_key(&self, key: &Self::Key) -> bool {
+        // Hash the key once for the filters of all batches.
+        !cfg!(feature = "bloom-filter") || self.maybe_contains_key_hash(KeyHash::new(key))
+    }
+
+    fn maybe_contains_key_hash(&self, hash: KeyHash) -> bool {
+        self.fold_batches(false, |acc, batch| {
+            acc || batch.maybe_contains_key_hash(hash)
+        })
+    }
 }
 
 impl<B> Spine<B>
@@ -275,6 +303,44 @@ where
         s
     }
 
+    /// Returns cursors over all non-empty batches in the spine, including
+    /// batches that are being merged.
+    pub(crate) fn batch_cursors(&self) -> Vec<B::Cursor<'_>> {
+        let mut cursors = Vec::with_capacity(self.merging.len());
+        for merge_state in self.merging.iter().rev() {
+            match merge_state {
+                MergeState::Double(MergeVariant::InProgress(batch1, batch2, _)) => {
+                    if !batch1.is_empty() {
+                        cursors.push(batch1.cursor());
+                    }
+
+                    if !batch2.is_empty() {
+                        cursors.push(batch2.cursor());
+                    }
+                }
+
+                MergeState::Double(MergeVariant::Complete(Some(batch)))
+                | MergeState::Single(Some(batch)) => {
+                    if !batch.is_empty() {
+                        cursors.push(batch.cursor());
+                    }
+                }
+
+                MergeState::Double(MergeVariant::Complete(None))
+                | MergeState::Single(None)
+                | MergeState::Vacant => {}
+            }
+        }
+
+        cursors
+    }
+
+    /// Returns the number of non-empty batches in the spine, including
+    /// batches that are being merged.
+    pub(crate) fn num_batches(&self) -> usize {
+        self.fold_batches(0, |acc, batch| if batch.is_empty() { acc } else { acc + 1 })
+    }
+
     #[allow(dead_code)]
     fn map_batches<F>(&self, mut map: F)
     where
@@ -293,7 +359,9 @@ where
         }
     }
 
-    fn fold_batches<T, F>(&self, init: T, mut fold: F) -> T
+    /// Folds `fold` over all batches in the spine, including batches that
+    /// are being merged.
+    pub(crate) fn fold_batches<T, F>(&self, init: T, mut fold: F) -> T
     where
         F: FnMut(T, &B) -> T,
     {
@@ -341,7 +409,7 @@ where
     B::Key: Ord,
     B::Val: Ord,
 {
-    fn new(cursors: Vec<B::Cursor<'s>>) -> Self {
+    pub(crate) fn new(cursors: Vec<B::Cursor<'s>>) -> Self {
         Self {
             cursor: CursorList::new(cursors),
         }
@@ -478,7 +546,8 @@ impl<B> Consumer<B::Key, B::Val, B::R, B::Time> for SpineConsumer<B>
 where
     B: Batch,
 {
-    type ValueConsumer<'a> = SpineValueConsumer<'a, B>
+    type ValueConsumer<'a>
+        = SpineValueConsumer<'a, B>
     where
         Self: 'a;
 
@@ -543,7 +612,12 @@ where
     type Batch = B;
 
     fn new(activator: Option<Activator>) -> Self {
-        Self::with_effort(1, activator)
+        // Use the compaction policy of the current runtime, if any.
+        let policy = Runtime::runtime()
+            .map(|runtime| runtime.config().compaction_policy)
+            .unwrap_or_default();
+
+        Self::with_effort(policy.effort(), activator)
     }
 
     fn recede_to(&mut self, frontier: &B::Time) {
@@ -1207,13 +1281,15 @@ where
 
 #[cfg(test)]
 mod test {
+    use super::CompactionPolicy;
     use crate::{
         trace::{
             ord::{OrdKeyBatch, OrdValBatch},
+            spine_fueled::Spine as FueledSpine,
             test_batch::{assert_batch_cursors_eq, assert_batch_eq, assert_trace_eq, TestBatch},
             Batch, BatchReader, Spine, Trace,
         },
-        OrdIndexedZSet, OrdZSet,
+        OrdIndexedZSet, OrdZSet, Runtime, RuntimeConfig,
     };
     use proptest::{collection::vec, prelude::*};
     use size_of::SizeOf;
@@ -1397,5 +1473,43 @@ mod test {
                 assert_batch_cursors_eq(&trace, &ref_trace, seed);
             }
         }
+
+        #[test]
+        fn test_spine_compaction_policy(batches in kvr_batches(100, 5, 2, 300, 20), seed in 0..u64::max_value()) {
+            // Compaction effort must not affect the contents of the trace.
+            let mut lazy: FueledSpine<OrdIndexedZSet<i32, i32, i32>> = FueledSpine::with_effort(1, None);
+            let mut eager: FueledSpine<OrdIndexedZSet<i32, i32, i32>> = FueledSpine::with_effort(16, None);
+
+            for (tuples, key_bound, _val_bound) in batches.into_iter() {
+                lazy.insert(OrdIndexedZSet::from_tuples((), tuples.clone()));
+                eager.insert(OrdIndexedZSet::from_tuples((), tuples));
+
+                assert_batch_eq(&lazy, &eager);
+                assert_batch_cursors_eq(&lazy, &eager, seed);
+
+                lazy.truncate_keys_below(&key_bound);
+                eager.truncate_keys_below(&key_bound);
+
+                assert_batch_eq(&lazy, &eager);
+            }
+        }
+    }
+
+    #[test]
+    fn test_runtime_compaction_policy() {
+        Runtime::run_with_config(
+            2,
+            RuntimeConfig::default().with_compaction_policy(CompactionPolicy::with_effort(16)),
+            || {
+                let trace: FueledSpine<OrdZSet<i32, i32>> = FueledSpine::new(None);
+                assert_eq!(trace.effort, 16);
+            },
+        )
+        .join()
+        .unwrap();
+
+        // Outside of a runtime traces use the default policy.
+        let trace: FueledSpine<OrdZSet<i32, i32>> = FueledSpine::new(None);
+        assert_eq!(trace.effort, CompactionPolicy::default().effort());
     }
 }
@@ -0,0 +1,1673 @@
+//! A trace that spills cold batches to disk.
+//!
+//! [`PersistentSpine`] bounds the number of batches a trace keeps in memory.
+//! New batches are inserted into an in-memory [`Spine`], which merges them as
+//! usual.  Once the in-memory spine holds more than
+//! [`SpillPolicy::max_in_memory_batches`] batches, the spine is consolidated
+//! into a single batch, which is written to a file in
+//! [`SpillPolicy::directory`] and dropped from memory.  Batches on disk are
+//! merged LSM-style: before a batch is written, it is merged with all batches
+//! on disk that are not larger than it, so the number of files grows
+//! logarithmically with the size of the trace.
+//!
+//! Batches on disk are read back lazily, one page at a time.  A cursor over
+//! the trace holds at most one page of each spilled batch in memory: moving
+//! the cursor to a key in another page replaces the page, and dropping the
+//! cursor releases it.  The memory footprint of an operator that scans or
+//! probes the trace is thus bounded by the in-memory batches plus one page
+//! per spilled batch, regardless of the size of the trace.  Spilling merges
+//! the in-memory batches with spilled batches the same way, streaming the
+//! result to a new file one page at a time, so only
+//! [consolidating](`Trace::consolidate`) the trace reads it into memory.
+//!
+//! ## On-disk format
+//!
+//! Each batch is stored in a separate file as a sequence of pages.  A page
+//! holds all updates of a range of keys, and is closed once it holds at least
+//! [`SpillPolicy::page_size`] updates, so the values of a key are never split
+//! across pages.  Pages use the column layout of
+//! [`OrderedLayer`](`crate::trace::layers::ordered::OrderedLayer`) and
+//! [`OrderedLeaf`](`crate::trace::layers::ordered_leaf::OrderedLeaf`): a
+//! vector of keys, a vector of offsets that delimits the values of each key,
+//! a vector of values, and a vector of weights, each encoded with `bincode`.
+//! Keys and values are generic and need not have a fixed-size representation,
+//! so pages are decoded instead of being memory-mapped.  The first key and
+//! the location of each page are kept in memory, so that a cursor can seek
+//! to a key by reading a single page.
+//!
+//! Files are deleted when the batch they contain is merged or the trace is
+//! dropped.  Spilled batches are not meant to outlive the process that wrote
+//! them; use `PersistentTrace` (`persistence` feature) for traces that must
+//! be recovered after a restart.
+//!
+//! ## Errors
+//!
+//! [`PersistentSpine::try_insert`], [`PersistentSpine::try_consolidate`], and
+//! [`PersistentSpine::try_clone`] return I/O errors.  The [`Trace`] methods
+//! they implement can't return errors:
+//!
+//! * [`Trace::insert`] keeps the batches in memory if spilling them fails,
+//!   and tries again at the next insertion.
+//! * [`Trace::consolidate`] and [`Clone::clone`] panic.
+//! * Cursors panic if they fail to read a page, as the [`Cursor`] API can't
+//!   report errors.
+
+use crate::{
+    algebra::HasZero,
+    circuit::{Activator, Runtime},
+    time::{Antichain, AntichainRef, Timestamp},
+    trace::{
+        bloom_filter::{BloomFilter, KeyHash},
+        cursor::CursorList,
+        layers::{advance, retreat},
+        spine_fueled::{CompactionPolicy, Spine},
+        Batch, BatchReader, Builder, Cursor, Trace,
+    },
+    NumEntries,
+};
+use bincode::{config::standard, Decode, Encode};
+use size_of::{Context, SizeOf};
+use std::{
+    cell::RefCell,
+    cmp::{max, min},
+    fs::{copy, create_dir_all, remove_file, File},
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Controls when a [`PersistentSpine`] spills batches to disk.
+///
+/// The policy is configured for all traces in a runtime via
+/// [`RuntimeConfig`](`crate::circuit::RuntimeConfig`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpillPolicy {
+    max_in_memory_batches: usize,
+    directory: PathBuf,
+    page_size: usize,
+}
+
+impl SpillPolicy {
+    /// The default number of updates in a page of a spilled batch.
+    pub const DEFAULT_PAGE_SIZE: usize = 4096;
+
+    /// Create a policy that spills batches to `directory` once a trace
+    /// holds more than `max_in_memory_batches` batches in memory.
+    pub fn new<P>(max_in_memory_batches: usize, directory: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            max_in_memory_batches,
+            directory: directory.into(),
+            page_size: Self::DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    /// Sets the number of updates after which a page of a spilled batch is
+    /// closed.
+    ///
+    /// Larger pages make scans cheaper, smaller pages reduce the memory used
+    /// by cursors and the cost of key lookups.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = max(page_size, 1);
+        self
+    }
+
+    /// Returns the maximal number of batches a trace holds in memory.
+    pub const fn max_in_memory_batches(&self) -> usize {
+        self.max_in_memory_batches
+    }
+
+    /// Returns the directory that spilled batches are written to.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns the number of updates after which a page of a spilled batch
+    /// is closed.
+    pub const fn page_size(&self) -> usize {
+        self.page_size
+    }
+}
+
+impl Default for SpillPolicy {
+    /// Spill to the system's temporary directory once a trace holds more than
+    /// 16 batches in memory.
+    fn default() -> Self {
+        Self::new(16, std::env::temp_dir())
+    }
+}
+
+/// Used to generate unique names of spill files within the process.
+static NEXT_FILE_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the path of a new spill file in `directory`.
+fn spill_file_path(directory: &Path) -> PathBuf {
+    directory.join(format!(
+        "dbsp-spine-{}-{}.batch",
+        process::id(),
+        NEXT_FILE_ID.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Location of a page in a spill file.
+#[derive(Clone, SizeOf)]
+struct PageInfo<K> {
+    /// The first key in the page, used to find the page that contains a key
+    /// without reading the file.
+    first_key: K,
+    /// Offset of the page in the file.
+    offset: u64,
+    /// Size of the encoded page in bytes.
+    len: usize,
+}
+
+/// A page of a spilled batch, decoded in memory.
+struct Page<B>
+where
+    B: Batch,
+{
+    keys: Vec<B::Key>,
+    /// `offs[i]..offs[i + 1]` are the indexes of the values of `keys[i]` in
+    /// `vals` and `diffs`.
+    offs: Vec<usize>,
+    vals: Vec<B::Val>,
+    diffs: Vec<B::R>,
+}
+
+impl<B> Page<B>
+where
+    B: Batch,
+{
+    fn new() -> Self {
+        Self {
+            keys: Vec::new(),
+            offs: vec![0],
+            vals: Vec::new(),
+            diffs: Vec::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.keys.clear();
+        self.offs.truncate(1);
+        self.vals.clear();
+        self.diffs.clear();
+    }
+}
+
+/// A batch written to disk.
+struct SpilledBatch<B>
+where
+    B: Batch,
+{
+    path: PathBuf,
+    /// Number of updates in the batch at the time it was written.
+    len: usize,
+    /// Number of keys in the batch at the time it was written.
+    key_count: usize,
+    /// Bloom filter over the keys of the batch, kept in memory so that key
+    /// lookups don't need to read the batch.
+    key_filter: Option<BloomFilter>,
+    /// Pages of the batch, in the order of their keys.
+    pages: Vec<PageInfo<B::Key>>,
+}
+
+impl<B> SpilledBatch<B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Encode + Decode,
+    B::Val: Encode + Decode,
+    B::R: Encode + Decode,
+{
+    /// Writes the updates of `cursor` to a new file in `directory`, in pages
+    /// of at least `page_size` updates.
+    ///
+    /// Reads `cursor` one key at a time, so that `cursor` can merge several
+    /// batches without reading them into memory.  Drops keys below
+    /// `lower_key_bound`, values below `lower_val_bound`, and updates whose
+    /// weights add up to zero.  `max_keys` is an upper bound on the number of
+    /// keys in `cursor`, used to size the key filter.
+    fn write<C>(
+        cursor: &mut C,
+        max_keys: usize,
+        lower_key_bound: &Option<B::Key>,
+        lower_val_bound: &Option<B::Val>,
+        directory: &Path,
+        page_size: usize,
+    ) -> io::Result<Self>
+    where
+        C: Cursor<B::Key, B::Val, (), B::R>,
+    {
+        create_dir_all(directory)?;
+
+        // Dropping `spilled` deletes the file if writing fails.
+        let mut spilled = Self {
+            path: spill_file_path(directory),
+            len: 0,
+            key_count: 0,
+            key_filter: BloomFilter::with_capacity(max_keys),
+            pages: Vec::new(),
+        };
+        let mut writer = BufWriter::new(File::create(&spilled.path)?);
+
+        let mut page = Page::<B>::new();
+        let mut offset = 0;
+
+        if let Some(bound) = lower_key_bound {
+            cursor.seek_key(bound);
+        }
+        while cursor.key_valid() {
+            if let Some(bound) = lower_val_bound {
+                cursor.seek_val(bound);
+            }
+            let first_val = page.vals.len();
+            while cursor.val_valid() {
+                let weight = cursor.weight();
+                if !weight.is_zero() {
+                    page.vals.push(cursor.val().clone());
+                    page.diffs.push(weight);
+                }
+                cursor.step_val();
+            }
+            if page.vals.len() > first_val {
+                if let Some(filter) = &mut spilled.key_filter {
+                    filter.insert(cursor.key());
+                }
+                page.keys.push(cursor.key().clone());
+                page.offs.push(page.vals.len());
+            }
+            cursor.step_key();
+
+            if !page.keys.is_empty() && (page.vals.len() >= page_size || !cursor.key_valid()) {
+                let len = bincode::encode_into_std_write(
+                    (&page.keys, &page.offs, &page.vals, &page.diffs),
+                    &mut writer,
+                    standard(),
+                )
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+                spilled.pages.push(PageInfo {
+                    first_key: page.keys[0].clone(),
+                    offset,
+                    len,
+                });
+                spilled.len += page.vals.len();
+                spilled.key_count += page.keys.len();
+                offset += len as u64;
+                page.clear();
+            }
+        }
+        writer.flush()?;
+
+        Ok(spilled)
+    }
+
+    /// Adds the path of the batch to an I/O error.
+    fn error_context(&self, error: io::Error) -> io::Error {
+        io::Error::new(
+            error.kind(),
+            format!("spilled trace batch '{}': {error}", self.path.display()),
+        )
+    }
+
+    /// Opens the file of the batch.
+    fn open(&self) -> io::Result<File> {
+        File::open(&self.path).map_err(|e| self.error_context(e))
+    }
+
+    /// Reads page `index` from `file`, dropping keys below
+    /// `lower_key_bound`.
+    fn read_page(
+        &self,
+        file: &mut File,
+        index: usize,
+        lower_key_bound: &Option<B::Key>,
+    ) -> io::Result<Page<B>> {
+        let info = &self.pages[index];
+        let mut bytes = vec![0; info.len];
+        file.seek(SeekFrom::Start(info.offset))
+            .and_then(|_| file.read_exact(&mut bytes))
+            .map_err(|e| self.error_context(e))?;
+
+        let ((mut keys, mut offs, vals, diffs), _): ((Vec<_>, Vec<_>, _, _), _) =
+            bincode::decode_from_slice(&bytes, standard())
+                .map_err(|e| self.error_context(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+
+        // The bound may have been raised after the batch was written.  The
+        // values of dropped keys stay in `vals`, but are no longer reachable.
+        if let Some(bound) = lower_key_bound {
+            let start = advance(&keys, |key| key < bound);
+            keys.drain(..start);
+            offs.drain(..start);
+        }
+
+        Ok(Page {
+            keys,
+            offs,
+            vals,
+            diffs,
+        })
+    }
+
+    /// Copies the batch to a new file in the same directory.
+    fn try_clone(&self) -> io::Result<Self> {
+        let path = spill_file_path(self.path.parent().unwrap_or_else(|| Path::new("")));
+        copy(&self.path, &path).map_err(|e| self.error_context(e))?;
+
+        Ok(Self {
+            path,
+            len: self.len,
+            key_count: self.key_count,
+            key_filter: self.key_filter.clone(),
+            pages: self.pages.clone(),
+        })
+    }
+
+    fn maybe_contains_key_hash(&self, hash: KeyHash) -> bool {
+        self.key_filter
+            .as_ref()
+            .map_or(true, |filter| filter.maybe_contains_hash(hash))
+    }
+
+    /// Returns the number of updates in the batch.  Overestimates the size of
+    /// batches that were truncated after being spilled.
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of keys in the batch, with the same caveat as
+    /// [`Self::len`].
+    fn key_count(&self) -> usize {
+        self.key_count
+    }
+}
+
+impl<B> Drop for SpilledBatch<B>
+where
+    B: Batch,
+{
+    fn drop(&mut self) {
+        // The file is only a cache for this trace, so there's nothing we can
+        // do about failures other than leaving it behind.
+        let _ = remove_file(&self.path);
+    }
+}
+
+/// Collects the first error encountered by [`SpilledBatchCursor`]s.
+type ReadError = Rc<RefCell<Option<io::Error>>>;
+
+/// A cursor over a spilled batch that holds one page of the batch in memory.
+struct SpilledBatchCursor<'s, B>
+where
+    B: Batch,
+{
+    batch: &'s SpilledBatch<B>,
+    lower_key_bound: &'s Option<B::Key>,
+    /// If set, failing to read a page is reported here and the page is
+    /// treated as empty.  Otherwise it is a fatal error, as the [`Cursor`] API
+    /// can't report errors.
+    read_error: Option<ReadError>,
+    file: File,
+    /// Index of `page` in `batch.pages`.
+    page_index: usize,
+    page: Page<B>,
+    /// Position of the current key in `page.keys`.  Negative or past the end
+    /// of the page when the cursor is exhausted.
+    key_pos: isize,
+    /// Position of the current value in `page.vals`.
+    val_pos: isize,
+}
+
+impl<'s, B> SpilledBatchCursor<'s, B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Encode + Decode,
+    B::Val: Encode + Decode,
+    B::R: Encode + Decode,
+{
+    fn new(
+        batch: &'s SpilledBatch<B>,
+        lower_key_bound: &'s Option<B::Key>,
+        read_error: Option<ReadError>,
+    ) -> io::Result<Self> {
+        let mut file = batch.open()?;
+        let page = if batch.pages.is_empty() {
+            Page::new()
+        } else {
+            batch.read_page(&mut file, 0, lower_key_bound)?
+        };
+
+        let mut cursor = Self {
+            batch,
+            lower_key_bound,
+            read_error,
+            file,
+            page_index: 0,
+            page,
+            key_pos: 0,
+            val_pos: 0,
+        };
+        cursor.rewind_keys();
+        Ok(cursor)
+    }
+
+    /// Replaces the page in memory with page `index`.
+    fn load_page(&mut self, index: usize) {
+        if index == self.page_index {
+            return;
+        }
+
+        self.page_index = index;
+        self.page = match self
+            .batch
+            .read_page(&mut self.file, index, self.lower_key_bound)
+        {
+            Ok(page) => page,
+            Err(error) => match &self.read_error {
+                Some(read_error) => {
+                    read_error.borrow_mut().get_or_insert(error);
+                    Page::new()
+                }
+                None => panic!("failed to read {error}"),
+            },
+        };
+    }
+
+    fn set_key(&mut self, key_pos: usize) {
+        self.key_pos = key_pos as isize;
+        self.val_pos = self.page.offs[key_pos] as isize;
+    }
+
+    /// Moves to the first key in pages `index..`.
+    fn first_key_from(&mut self, index: usize) {
+        for index in index..self.batch.pages.len() {
+            self.load_page(index);
+            if !self.page.keys.is_empty() {
+                self.set_key(0);
+                return;
+            }
+        }
+        self.key_pos = self.page.keys.len() as isize;
+    }
+
+    /// Moves to the last key in pages `..end`.
+    fn last_key_before(&mut self, end: usize) {
+        for index in (0..end).rev() {
+            self.load_page(index);
+            if !self.page.keys.is_empty() {
+                self.set_key(self.page.keys.len() - 1);
+                return;
+            }
+        }
+        self.key_pos = -1;
+    }
+
+    /// Returns the range of the values of the current key in `page.vals`.
+    fn val_bounds(&self) -> (usize, usize) {
+        let key_pos = self.key_pos as usize;
+        (self.page.offs[key_pos], self.page.offs[key_pos + 1])
+    }
+}
+
+impl<'s, B> Cursor<B::Key, B::Val, (), B::R> for SpilledBatchCursor<'s, B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Encode + Decode,
+    B::Val: Encode + Decode,
+    B::R: Encode + Decode,
+{
+    fn key_valid(&self) -> bool {
+        self.key_pos >= 0 && (self.key_pos as usize) < self.page.keys.len()
+    }
+
+    fn val_valid(&self) -> bool {
+        if !self.key_valid() {
+            return false;
+        }
+        let (lower, upper) = self.val_bounds();
+        self.val_pos >= lower as isize && self.val_pos < upper as isize
+    }
+
+    fn key(&self) -> &B::Key {
+        &self.page.keys[self.key_pos as usize]
+    }
+
+    fn val(&self) -> &B::Val {
+        &self.page.vals[self.val_pos as usize]
+    }
+
+    fn fold_times<F, U>(&mut self, init: U, mut fold: F) -> U
+    where
+        F: FnMut(U, &(), &B::R) -> U,
+    {
+        if self.val_valid() {
+            fold(init, &(), &self.page.diffs[self.val_pos as usize])
+        } else {
+            init
+        }
+    }
+
+    fn fold_times_through<F, U>(&mut self, _upper: &(), init: U, fold: F) -> U
+    where
+        F: FnMut(U, &(), &B::R) -> U,
+    {
+        self.fold_times(init, fold)
+    }
+
+    fn weight(&mut self) -> B::R {
+        self.page.diffs[self.val_pos as usize].clone()
+    }
+
+    fn step_key(&mut self) {
+        self.key_pos += 1;
+        if self.key_valid() {
+            self.set_key(self.key_pos as usize);
+        } else {
+            self.first_key_from(self.page_index + 1);
+        }
+    }
+
+    fn step_key_reverse(&mut self) {
+        self.key_pos -= 1;
+        if self.key_valid() {
+            self.set_key(self.key_pos as usize);
+        } else {
+            self.last_key_before(self.page_index);
+        }
+    }
+
+    fn seek_key(&mut self, key: &B::Key) {
+        // The last page whose first key is not greater than `key`.
+        let index = self
+            .batch
+            .pages
+            .partition_point(|page| &page.first_key <= key)
+            .saturating_sub(1);
+
+        if index > self.page_index {
+            self.load_page(index);
+            self.key_pos = 0;
+        }
+
+        let len = self.page.keys.len();
+        let start = min(max(self.key_pos, 0) as usize, len);
+        let key_pos = start + advance(&self.page.keys[start..], |k| k < key);
+        if key_pos < len {
+            self.set_key(key_pos);
+        } else {
+            // All keys in the following pages are greater than `key`.
+            self.first_key_from(self.page_index + 1);
+        }
+    }
+
+    fn seek_key_reverse(&mut self, key: &B::Key) {
+        // The number of pages whose first key is not greater than `key`.
+        let end = self
+            .batch
+            .pages
+            .partition_point(|page| &page.first_key <= key);
+
+        if end == 0 {
+            self.load_page(0);
+            self.key_pos = -1;
+            return;
+        }
+
+        if end - 1 < self.page_index {
+            self.load_page(end - 1);
+            self.key_pos = self.page.keys.len() as isize - 1;
+        }
+
+        let end = min(self.key_pos + 1, self.page.keys.len() as isize);
+        if end <= 0 {
+            self.last_key_before(self.page_index);
+            return;
+        }
+
+        let end = end as usize;
+        let key_pos = end - retreat(&self.page.keys[..end], |k| k > key);
+        if key_pos > 0 {
+            self.set_key(key_pos - 1);
+        } else {
+            // All keys in the preceding pages are smaller than `key`.
+            self.last_key_before(self.page_index);
+        }
+    }
+
+    fn step_val(&mut self) {
+        self.val_pos += 1;
+    }
+
+    fn step_val_reverse(&mut self) {
+        self.val_pos -= 1;
+    }
+
+    fn seek_val(&mut self, val: &B::Val) {
+        self.seek_val_with(|v| v >= val);
+    }
+
+    fn seek_val_reverse(&mut self, val: &B::Val) {
+        self.seek_val_with_reverse(|v| v <= val);
+    }
+
+    fn seek_val_with<P>(&mut self, predicate: P)
+    where
+        P: Fn(&B::Val) -> bool + Clone,
+    {
+        if self.val_valid() {
+            let (_, upper) = self.val_bounds();
+            let start = self.val_pos as usize;
+            self.val_pos += advance(&self.page.vals[start..upper], |v| !predicate(v)) as isize;
+        }
+    }
+
+    fn seek_val_with_reverse<P>(&mut self, predicate: P)
+    where
+        P: Fn(&B::Val) -> bool + Clone,
+    {
+        if self.val_valid() {
+            let (lower, _) = self.val_bounds();
+            let end = self.val_pos as usize;
+            self.val_pos -= retreat(&self.page.vals[lower..=end], |v| !predicate(v)) as isize;
+        }
+    }
+
+    fn rewind_keys(&mut self) {
+        self.first_key_from(0);
+    }
+
+    fn fast_forward_keys(&mut self) {
+        self.last_key_before(self.batch.pages.len());
+    }
+
+    fn rewind_vals(&mut self) {
+        if self.key_valid() {
+            self.val_pos = self.val_bounds().0 as isize;
+        }
+    }
+
+    fn fast_forward_vals(&mut self) {
+        if self.key_valid() {
+            self.val_pos = self.val_bounds().1 as isize - 1;
+        }
+    }
+}
+
+/// A cursor over an in-memory or a spilled batch of a [`PersistentSpine`].
+enum BatchCursor<'s, B>
+where
+    B: Batch + 's,
+{
+    Hot(B::Cursor<'s>),
+    Cold(SpilledBatchCursor<'s, B>),
+}
+
+impl<'s, B> Cursor<B::Key, B::Val, (), B::R> for BatchCursor<'s, B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Encode + Decode,
+    B::Val: Encode + Decode,
+    B::R: Encode + Decode,
+{
+    fn key_valid(&self) -> bool {
+        match self {
+            Self::Hot(cursor) => cursor.key_valid(),
+            Self::Cold(cursor) => cursor.key_valid(),
+        }
+    }
+
+    fn val_valid(&self) -> bool {
+        match self {
+            Self::Hot(cursor) => cursor.val_valid(),
+            Self::Cold(cursor) => cursor.val_valid(),
+        }
+    }
+
+    fn key(&self) -> &B::Key {
+        match self {
+            Self::Hot(cursor) => cursor.key(),
+            Self::Cold(cursor) => cursor.key(),
+        }
+    }
+
+    fn val(&self) -> &B::Val {
+        match self {
+            Self::Hot(cursor) => cursor.val(),
+            Self::Cold(cursor) => cursor.val(),
+        }
+    }
+
+    fn fold_times<F, U>(&mut self, init: U, fold: F) -> U
+    where
+        F: FnMut(U, &(), &B::R) -> U,
+    {
+        match self {
+            Self::Hot(cursor) => cursor.fold_times(init, fold),
+            Self::Cold(cursor) => cursor.fold_times(init, fold),
+        }
+    }
+
+    fn fold_times_through<F, U>(&mut self, upper: &(), init: U, fold: F) -> U
+    where
+        F: FnMut(U, &(), &B::R) -> U,
+    {
+        match self {
+            Self::Hot(cursor) => cursor.fold_times_through(upper, init, fold),
+            Self::Cold(cursor) => cursor.fold_times_through(upper, init, fold),
+        }
+    }
+
+    fn weight(&mut self) -> B::R {
+        match self {
+            Self::Hot(cursor) => cursor.weight(),
+            Self::Cold(cursor) => cursor.weight(),
+        }
+    }
+
+    fn step_key(&mut self) {
+        match self {
+            Self::Hot(cursor) => cursor.step_key(),
+            Self::Cold(cursor) => cursor.step_key(),
+        }
+    }
+
+    fn step_key_reverse(&mut self) {
+        match self {
+            Self::Hot(cursor) => cursor.step_key_reverse(),
+            Self::Cold(cursor) => cursor.step_key_reverse(),
+        }
+    }
+
+    fn seek_key(&mut self, key: &B::Key) {
+        match self {
+            Self::Hot(cursor) => cursor.seek_key(key),
+            Self::Cold(cursor) => cursor.seek_key(key),
+        }
+    }
+
+    fn seek_key_reverse(&mut self, key: &B::Key) {
+        match self {
+            Self::Hot(cursor) => cursor.seek_key_reverse(key),
+            Self::Cold(cursor) => cursor.seek_key_reverse(key),
+        }
+    }
+
+    fn step_val(&mut self) {
+        match self {
+            Self::Hot(cursor) => cursor.step_val(),
+            Self::Cold(cursor) => cursor.step_val(),
+        }
+    }
+
+    fn step_val_reverse(&mut self) {
+        match self {
+            Self::Hot(cursor) => cursor.step_val_reverse(),
+            Self::Cold(cursor) => cursor.step_val_reverse(),
+        }
+    }
+
+    fn seek_val(&mut self, val: &B::Val) {
+        match self {
+            Self::Hot(cursor) => cursor.seek_val(val),
+            Self::Cold(cursor) => cursor.seek_val(val),
+        }
+    }
+
+    fn seek_val_reverse(&mut self, val: &B::Val) {
+        match self {
+            Self::Hot(cursor) => cursor.seek_val_reverse(val),
+            Self::Cold(cursor) => cursor.seek_val_reverse(val),
+        }
+    }
+
+    fn seek_val_with<P>(&mut self, predicate: P)
+    where
+        P: Fn(&B::Val) -> bool + Clone,
+    {
+        match self {
+            Self::Hot(cursor) => cursor.seek_val_with(predicate),
+            Self::Cold(cursor) => cursor.seek_val_with(predicate),
+        }
+    }
+
+    fn seek_val_with_reverse<P>(&mut self, predicate: P)
+    where
+        P: Fn(&B::Val) -> bool + Clone,
+    {
+        match self {
+            Self::Hot(cursor) => cursor.seek_val_with_reverse(predicate),
+            Self::Cold(cursor) => cursor.seek_val_with_reverse(predicate),
+        }
+    }
+
+    fn rewind_keys(&mut self) {
+        match self {
+            Self::Hot(cursor) => cursor.rewind_keys(),
+            Self::Cold(cursor) => cursor.rewind_keys(),
+        }
+    }
+
+    fn fast_forward_keys(&mut self) {
+        match self {
+            Self::Hot(cursor) => cursor.fast_forward_keys(),
+            Self::Cold(cursor) => cursor.fast_forward_keys(),
+        }
+    }
+
+    fn rewind_vals(&mut self) {
+        match self {
+            Self::Hot(cursor) => cursor.rewind_vals(),
+            Self::Cold(cursor) => cursor.rewind_vals(),
+        }
+    }
+
+    fn fast_forward_vals(&mut self) {
+        match self {
+            Self::Hot(cursor) => cursor.fast_forward_vals(),
+            Self::Cold(cursor) => cursor.fast_forward_vals(),
+        }
+    }
+}
+
+/// A trace that holds a bounded number of batches in memory and spills the
+/// rest to disk.
+///
+/// See the [module-level documentation](`self`) for details.
+pub struct PersistentSpine<B>
+where
+    B: Batch,
+{
+    /// Recently inserted batches.
+    hot: Spine<B>,
+    /// Spilled batches, in decreasing order of size.
+    cold: Vec<SpilledBatch<B>>,
+    policy: SpillPolicy,
+    effort: usize,
+    activator: Option<Activator>,
+    lower: Antichain<B::Time>,
+    upper: Antichain<B::Time>,
+    dirty: bool,
+    lower_key_bound: Option<B::Key>,
+    lower_val_bound: Option<B::Val>,
+}
+
+impl<B> PersistentSpine<B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Encode + Decode,
+    B::Val: Encode + Decode,
+    B::R: Encode + Decode,
+{
+    /// Allocates a trace with the specified spill policy and merge effort
+    /// multiplier (see [`Spine::with_effort`]).
+    pub fn with_policy(policy: SpillPolicy, effort: usize, activator: Option<Activator>) -> Self {
+        Self {
+            hot: Spine::with_effort(effort, activator.clone()),
+            cold: Vec::new(),
+            policy,
+            effort,
+            activator,
+            lower: Antichain::from_elem(B::Time::minimum()),
+            upper: Antichain::new(),
+            dirty: false,
+            lower_key_bound: None,
+            lower_val_bound: None,
+        }
+    }
+
+    /// Returns the number of batches written to disk.
+    pub fn num_spilled_batches(&self) -> usize {
+        self.cold.len()
+    }
+
+    /// Creates an empty in-memory spine with the bounds of this trace.
+    fn new_hot(&self) -> Spine<B> {
+        let mut hot = Spine::with_effort(self.effort, self.activator.clone());
+        if let Some(bound) = &self.lower_key_bound {
+            hot.truncate_keys_below(bound);
+        }
+        if let Some(bound) = &self.lower_val_bound {
+            hot.truncate_values_below(bound);
+        }
+        hot
+    }
+
+    /// Moves the contents of the in-memory spine to disk.
+    ///
+    /// The in-memory batches are merged with the spilled batches that are not
+    /// larger than the result, and the merged batch is written to a new file.
+    /// Spilled batches are read one page at a time during the merge.  The
+    /// trace is unchanged if spilling fails.
+    fn spill(&mut self) -> io::Result<()> {
+        let mut len = self.hot.len();
+        let mut first_merged = self.cold.len();
+        while first_merged > 0 && self.cold[first_merged - 1].len() <= len {
+            first_merged -= 1;
+            len += self.cold[first_merged].len();
+        }
+
+        let read_error = ReadError::default();
+        let mut max_keys = self.hot.key_count();
+        let mut cursors: Vec<_> = self
+            .hot
+            .batch_cursors()
+            .into_iter()
+            .map(BatchCursor::Hot)
+            .collect();
+        for spilled in self.cold[first_merged..].iter() {
+            max_keys += spilled.key_count();
+            cursors.push(BatchCursor::Cold(SpilledBatchCursor::new(
+                spilled,
+                &self.lower_key_bound,
+                Some(read_error.clone()),
+            )?));
+        }
+
+        let spilled = SpilledBatch::write(
+            &mut CursorList::new(cursors),
+            max_keys,
+            &self.lower_key_bound,
+            &self.lower_val_bound,
+            &self.policy.directory,
+            self.policy.page_size,
+        )?;
+        if let Some(error) = read_error.borrow_mut().take() {
+            return Err(error);
+        }
+
+        self.hot = self.new_hot();
+        self.cold.truncate(first_merged);
+        if spilled.len() > 0 {
+            self.cold.push(spilled);
+        }
+
+        Ok(())
+    }
+
+    /// Returns a cursor over the trace.  If `read_error` is set, failures to
+    /// read spilled batches are reported there instead of panicking.
+    fn cursor_with_read_error(
+        &self,
+        read_error: Option<ReadError>,
+    ) -> io::Result<PersistentSpineCursor<'_, B>> {
+        let mut cursors: Vec<_> = self
+            .hot
+            .batch_cursors()
+            .into_iter()
+            .map(BatchCursor::Hot)
+            .collect();
+        for spilled in self.cold.iter() {
+            let cursor =
+                SpilledBatchCursor::new(spilled, &self.lower_key_bound, read_error.clone())?;
+            if cursor.key_valid() {
+                cursors.push(BatchCursor::Cold(cursor));
+            }
+        }
+
+        Ok(PersistentSpineCursor {
+            cursor: CursorList::new(cursors),
+        })
+    }
+
+    /// Inserts `batch` into the trace, like [`Trace::insert`], but reports
+    /// errors spilling the in-memory batches to disk.
+    ///
+    /// The batch is part of the trace even if spilling fails: in that case,
+    /// the in-memory batches stay in memory and spilling them is retried at
+    /// the next insertion.
+    pub fn try_insert(&mut self, batch: B) -> io::Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        self.dirty = true;
+        self.lower = self.lower.as_ref().meet(batch.lower());
+        self.upper = self.upper.as_ref().join(batch.upper());
+
+        self.hot.insert(batch);
+        if self.hot.num_batches() > self.policy.max_in_memory_batches {
+            self.spill()?;
+        }
+
+        Ok(())
+    }
+
+    /// Merges all batches into a single batch in memory, like
+    /// [`Trace::consolidate`], but reports errors reading spilled batches.
+    pub fn try_consolidate(self) -> io::Result<Option<B>> {
+        let read_error = ReadError::default();
+        let mut builder = B::Builder::with_capacity((), self.len());
+
+        let mut cursor = self.cursor_with_read_error(Some(read_error.clone()))?;
+        while cursor.key_valid() {
+            if let Some(bound) = &self.lower_val_bound {
+                cursor.seek_val(bound);
+            }
+            while cursor.val_valid() {
+                let weight = cursor.weight();
+                if !weight.is_zero() {
+                    builder.push((
+                        B::item_from(cursor.key().clone(), cursor.val().clone()),
+                        weight,
+                    ));
+                }
+                cursor.step_val();
+            }
+            cursor.step_key();
+        }
+        drop(cursor);
+
+        if let Some(error) = read_error.borrow_mut().take() {
+            return Err(error);
+        }
+
+        let batch = builder.done();
+        Ok(if batch.is_empty() { None } else { Some(batch) })
+    }
+
+    /// Copies the trace, like [`Clone::clone`], but reports errors copying the
+    /// files of spilled batches.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let mut hot = self.new_hot();
+        self.hot
+            .fold_batches((), |(), batch| hot.insert(batch.clone()));
+
+        let cold = self
+            .cold
+            .iter()
+            .map(SpilledBatch::try_clone)
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            hot,
+            cold,
+            policy: self.policy.clone(),
+            effort: self.effort,
+            activator: self.activator.clone(),
+            lower: self.lower.clone(),
+            upper: self.upper.clone(),
+            dirty: self.dirty,
+            lower_key_bound: self.lower_key_bound.clone(),
+            lower_val_bound: self.lower_val_bound.clone(),
+        })
+    }
+}
+
+impl<B> Clone for PersistentSpine<B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Encode + Decode,
+    B::Val: Encode + Decode,
+    B::R: Encode + Decode,
+{
+    /// Copies the in-memory batches and the files of spilled batches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if copying a file fails; see [`PersistentSpine::try_clone`].
+    fn clone(&self) -> Self {
+        self.try_clone()
+            .unwrap_or_else(|e| panic!("failed to copy {e}"))
+    }
+}
+
+impl<B> NumEntries for PersistentSpine<B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Encode + Decode,
+    B::Val: Encode + Decode,
+    B::R: Encode + Decode,
+{
+    const CONST_NUM_ENTRIES: Option<usize> = None;
+
+    fn num_entries_shallow(&self) -> usize {
+        self.len()
+    }
+
+    fn num_entries_deep(&self) -> usize {
+        self.num_entries_shallow()
+    }
+}
+
+/// Only accounts for memory: spilled batches only contribute the size of
+/// their key filters and page indexes.  Pages read by cursors are not
+/// included.
+impl<B> SizeOf for PersistentSpine<B>
+where
+    B: Batch,
+{
+    fn size_of_children(&self, context: &mut Context) {
+        self.hot.size_of_children(context);
+        for spilled in self.cold.iter() {
+            spilled.key_filter.size_of_children(context);
+            spilled.pages.size_of_children(context);
+        }
+        self.activator.size_of_children(context);
+        self.lower_key_bound.size_of_children(context);
+        self.lower_val_bound.size_of_children(context);
+    }
+}
+
+/// A cursor over a [`PersistentSpine`].
+///
+/// Holds one page of each spilled batch in memory, which is released when
+/// the cursor is dropped.
+pub struct PersistentSpineCursor<'s, B>
+where
+    B: Batch + 's,
+{
+    #[allow(clippy::type_complexity)]
+    cursor: CursorList<B::Key, B::Val, (), B::R, BatchCursor<'s, B>>,
+}
+
+impl<'s, B> Cursor<B::Key, B::Val, (), B::R> for PersistentSpineCursor<'s, B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Encode + Decode,
+    B::Val: Encode + Decode,
+    B::R: Encode + Decode,
+{
+    fn key_valid(&self) -> bool {
+        self.cursor.key_valid()
+    }
+
+    fn val_valid(&self) -> bool {
+        self.cursor.val_valid()
+    }
+
+    fn key(&self) -> &B::Key {
+        self.cursor.key()
+    }
+
+    fn val(&self) -> &B::Val {
+        self.cursor.val()
+    }
+
+    fn fold_times<F, U>(&mut self, init: U, fold: F) -> U
+    where
+        F: FnMut(U, &(), &B::R) -> U,
+    {
+        self.cursor.fold_times(init, fold)
+    }
+
+    fn fold_times_through<F, U>(&mut self, upper: &(), init: U, fold: F) -> U
+    where
+        F: FnMut(U, &(), &B::R) -> U,
+    {
+        self.cursor.fold_times_through(upper, init, fold)
+    }
+
+    fn weight(&mut self) -> B::R {
+        self.cursor.weight()
+    }
+
+    fn step_key(&mut self) {
+        self.cursor.step_key();
+    }
+
+    fn step_key_reverse(&mut self) {
+        self.cursor.step_key_reverse();
+    }
+
+    fn seek_key(&mut self, key: &B::Key) {
+        self.cursor.seek_key(key);
+    }
+
+    fn seek_key_reverse(&mut self, key: &B::Key) {
+        self.cursor.seek_key_reverse(key);
+    }
+
+    fn step_val(&mut self) {
+        self.cursor.step_val();
+    }
+
+    fn step_val_reverse(&mut self) {
+        self.cursor.step_val_reverse();
+    }
+
+    fn seek_val(&mut self, val: &B::Val) {
+        self.cursor.seek_val(val);
+    }
+
+    fn seek_val_reverse(&mut self, val: &B::Val) {
+        self.cursor.seek_val_reverse(val);
+    }
+
+    fn seek_val_with<P>(&mut self, predicate: P)
+    where
+        P: Fn(&B::Val) -> bool + Clone,
+    {
+        self.cursor.seek_val_with(predicate);
+    }
+
+    fn seek_val_with_reverse<P>(&mut self, predicate: P)
+    where
+        P: Fn(&B::Val) -> bool + Clone,
+    {
+        self.cursor.seek_val_with_reverse(predicate);
+    }
+
+    fn rewind_keys(&mut self) {
+        self.cursor.rewind_keys();
+    }
+
+    fn fast_forward_keys(&mut self) {
+        self.cursor.fast_forward_keys();
+    }
+
+    fn rewind_vals(&mut self) {
+        self.cursor.rewind_vals();
+    }
+
+    fn fast_forward_vals(&mut self) {
+        self.cursor.fast_forward_vals();
+    }
+}
+
+impl<B> BatchReader for PersistentSpine<B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Encode + Decode,
+    B::Val: Encode + Decode,
+    B::R: Encode + Decode,
+{
+    type Key = B::Key;
+    type Val = B::Val;
+    type Time = B::Time;
+    type R = B::R;
+
+    type Cursor<'s> = PersistentSpineCursor<'s, B>;
+    type Consumer = B::Consumer;
+
+    fn key_count(&self) -> usize {
+        self.cold.iter().fold(self.hot.key_count(), |acc, spilled| {
+            acc + spilled.key_count()
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.cold
+            .iter()
+            .fold(self.hot.len(), |acc, spilled| acc + spilled.len())
+    }
+
+    fn lower(&self) -> AntichainRef<'_, Self::Time> {
+        self.lower.as_ref()
+    }
+
+    fn upper(&self) -> AntichainRef<'_, Self::Time> {
+        self.upper.as_ref()
+    }
+
+    fn cursor(&self) -> Self::Cursor<'_> {
+        self.cursor_with_read_error(None)
+            .unwrap_or_else(|e| panic!("failed to read {e}"))
+    }
+
+    /// Reads all spilled batches back into memory and consumes the result of
+    /// merging them with the in-memory batches.
+    fn consumer(self) -> Self::Consumer {
+        self.consolidate()
+            .unwrap_or_else(|| B::empty(()))
+            .consumer()
+    }
+
+    fn truncate_keys_below(&mut self, lower_bound: &Self::Key) {
+        let bound = if let Some(bound) = &self.lower_key_bound {
+            max(bound, lower_bound).clone()
+        } else {
+            lower_bound.clone()
+        };
+
+        self.hot.truncate_keys_below(&bound);
+        // Spilled batches are truncated when their pages are read.
+        self.lower_key_bound = Some(bound);
+    }
+
+    fn maybe_contains_key(&self, key: &Self::Key) -> bool {
+        // Hash the key once for the filters of all batches.
+        !cfg!(feature = "bloom-filter") || self.maybe_contains_key_hash(KeyHash::new(key))
+    }
+
+    fn maybe_contains_key_hash(&self, hash: KeyHash) -> bool {
+        self.hot.maybe_contains_key_hash(hash)
+            || self
+                .cold
+                .iter()
+                .any(|spilled| spilled.maybe_contains_key_hash(hash))
+    }
+}
+
+impl<B> Default for PersistentSpine<B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Encode + Decode,
+    B::Val: Encode + Decode,
+    B::R: Encode + Decode,
+{
+    fn default() -> Self {
+        <Self as Trace>::new(None)
+    }
+}
+
+impl<B> Trace for PersistentSpine<B>
+where
+    B: Batch<Time = ()>,
+    B::Key: Encode + Decode,
+    B::Val: Encode + Decode,
+    B::R: Encode + Decode,
+{
+    type Batch = B;
+
+    fn new(activator: Option<Activator>) -> Self {
+        // Use the spill and compaction policies of the current runtime, if any.
+        let (policy, effort) = Runtime::runtime()
+            .map(|runtime| {
+                let config = runtime.config();
+                (
+                    config.spill_policy.clone(),
+                    config.compaction_policy.effort(),
+                )
+            })
+            .unwrap_or_else(|| (SpillPolicy::default(), CompactionPolicy::default().effort()));
+
+        Self::with_policy(policy, effort, activator)
+    }
+
+    fn recede_to(&mut self, frontier: &B::Time) {
+        // Spilled batches are at time `()`, which can't recede any further.
+        self.hot.recede_to(frontier);
+    }
+
+    fn exert(&mut self, effort: &mut isize) {
+        self.hot.exert(effort);
+    }
+
+    fn consolidate(self) -> Option<B> {
+        self.try_consolidate()
+            .unwrap_or_else(|e| panic!("failed to read {e}"))
+    }
+
+    fn insert(&mut self, batch: Self::Batch) {
+        // If spilling fails, the trace keeps the batches in memory, where they
+        // remain part of the trace, and tries again at the next insertion.
+        let _ = self.try_insert(batch);
+    }
+
+    fn clear_dirty_flag(&mut self) {
+        self.dirty = false;
+    }
+
+    fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn truncate_values_below(&mut self, lower_bound: &Self::Val) {
+        // Like `Spine`, we postpone the actual GC till when we merge batches.
+        let bound = if let Some(bound) = &self.lower_val_bound {
+            max(bound, lower_bound).clone()
+        } else {
+            lower_bound.clone()
+        };
+
+        self.hot.truncate_values_below(&bound);
+        self.lower_val_bound = Some(bound);
+    }
+
+    fn lower_value_bound(&self) -> &Option<Self::Val> {
+        &self.lower_val_bound
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PersistentSpine, SpillPolicy};
+    use crate::{
+        trace::{
+            spine_fueled::Spine,
+            test_batch::{assert_batch_cursors_eq, assert_batch_eq, assert_trace_eq, TestBatch},
+            Batch, BatchReader, Consumer, Cursor, Trace,
+        },
+        OrdIndexedZSet, OrdZSet, Runtime, RuntimeConfig,
+    };
+    use proptest::{collection::vec, prelude::*};
+    use std::{
+        fs::read_dir,
+        path::{Path, PathBuf},
+    };
+
+    /// Returns a fresh directory for the spill files of a test.
+    fn spill_directory(test: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("dbsp-{test}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn num_files(directory: &Path) -> usize {
+        read_dir(directory).map_or(0, |entries| entries.count())
+    }
+
+    fn kvr_batches(
+        max_key: i32,
+        max_val: i32,
+        max_weight: i32,
+        max_tuples: usize,
+        max_batches: usize,
+    ) -> BoxedStrategy<Vec<(Vec<((i32, i32), i32)>, i32, i32)>> {
+        vec(
+            (
+                vec(
+                    ((0..max_key, 0..max_val), -max_weight..max_weight),
+                    0..max_tuples,
+                ),
+                (0..max_key),
+                (0..max_val),
+            ),
+            0..max_batches,
+        )
+        .boxed()
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn test_zset_persistent_spine(batches in vec(vec((0..50i32, -2..2i32), 0..100), 0..40), max_in_memory_batches in 0..4usize, page_size in 1..8usize) {
+            let directory = spill_directory("test_zset_persistent_spine");
+            let policy = SpillPolicy::new(max_in_memory_batches, &directory).with_page_size(page_size);
+            let mut trace: PersistentSpine<OrdZSet<i32, i32>> =
+                PersistentSpine::with_policy(policy, 1, None);
+            let mut ref_trace: Spine<OrdZSet<i32, i32>> = Spine::new(None);
+
+            for tuples in batches.into_iter() {
+                trace.insert(OrdZSet::from_keys((), tuples.clone()));
+                ref_trace.insert(OrdZSet::from_keys((), tuples));
+
+                assert_batch_eq(&trace, &ref_trace);
+            }
+
+            assert_eq!(num_files(&directory), trace.num_spilled_batches());
+            assert_batch_eq(&trace.consolidate().unwrap_or_else(|| OrdZSet::empty(())), &ref_trace.consolidate().unwrap_or_else(|| OrdZSet::empty(())));
+            assert_eq!(num_files(&directory), 0);
+        }
+
+        #[test]
+        fn test_indexed_zset_persistent_spine(batches in kvr_batches(100, 5, 2, 200, 40), max_in_memory_batches in 0..4usize, page_size in 1..8usize, seed in 0..u64::max_value()) {
+            let directory = spill_directory("test_indexed_zset_persistent_spine");
+            let policy = SpillPolicy::new(max_in_memory_batches, &directory).with_page_size(page_size);
+            let mut trace: PersistentSpine<OrdIndexedZSet<i32, i32, i32>> =
+                PersistentSpine::with_policy(policy, 1, None);
+            let mut ref_trace: TestBatch<i32, i32, (), i32> = TestBatch::new(None);
+
+            for (tuples, key_bound, val_bound) in batches.into_iter() {
+                trace.insert(OrdIndexedZSet::from_tuples((), tuples.clone()));
+                ref_trace.insert(TestBatch::from_tuples((), tuples));
+
+                assert_trace_eq(&trace, &ref_trace);
+                assert_batch_cursors_eq(&trace, &ref_trace, seed);
+
+                trace.truncate_keys_below(&key_bound);
+                ref_trace.truncate_keys_below(&key_bound);
+
+                trace.truncate_values_below(&val_bound);
+                ref_trace.truncate_values_below(&val_bound);
+
+                assert_trace_eq(&trace, &ref_trace);
+                assert_batch_cursors_eq(&trace, &ref_trace, seed);
+            }
+
+            drop(trace);
+            assert_eq!(num_files(&directory), 0);
+        }
+    }
+
+    #[test]
+    fn test_spill() {
+        let directory = spill_directory("test_spill");
+        let mut trace: PersistentSpine<OrdZSet<i32, i32>> =
+            PersistentSpine::with_policy(SpillPolicy::new(2, &directory), 1, None);
+
+        for i in 0..100 {
+            trace.insert(OrdZSet::from_keys((), vec![(i, 1)]));
+            assert!(trace.hot.num_batches() <= 2);
+        }
+        assert_eq!(trace.len(), 100);
+        assert!(trace.num_spilled_batches() > 0);
+        assert_eq!(num_files(&directory), trace.num_spilled_batches());
+
+        // Retract everything.
+        for i in 0..100 {
+            trace.insert(OrdZSet::from_keys((), vec![(i, -1)]));
+        }
+        assert_eq!(trace.consolidate(), None);
+        assert_eq!(num_files(&directory), 0);
+    }
+
+    // Failing to spill keeps batches in memory.
+    #[test]
+    fn test_spill_error() {
+        // A file where the spill directory should be.
+        let path = spill_directory("test_spill_error");
+        std::fs::write(&path, b"").unwrap();
+
+        let mut trace: PersistentSpine<OrdZSet<i32, i32>> =
+            PersistentSpine::with_policy(SpillPolicy::new(0, &path), 1, None);
+        assert!(trace
+            .try_insert(OrdZSet::from_keys((), vec![(1, 1)]))
+            .is_err());
+        trace.insert(OrdZSet::from_keys((), vec![(2, 1)]));
+        assert_eq!(trace.num_spilled_batches(), 0);
+
+        assert_eq!(
+            trace.try_consolidate().unwrap(),
+            Some(OrdZSet::from_keys((), vec![(1, 1), (2, 1)]))
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_paged_cursor() {
+        let directory = spill_directory("test_paged_cursor");
+        let policy = SpillPolicy::new(0, &directory).with_page_size(4);
+        let mut trace: PersistentSpine<OrdIndexedZSet<i32, i32, i32>> =
+            PersistentSpine::with_policy(policy, 1, None);
+
+        trace.insert(OrdIndexedZSet::from_tuples(
+            (),
+            (0..100)
+                .flat_map(|k| (0..k % 7).map(move |v| ((k, v), 1)))
+                .collect(),
+        ));
+        assert_eq!(trace.num_spilled_batches(), 1);
+        assert!(trace.cold[0].pages.len() > 1);
+
+        // Seek forward and backward across page boundaries.
+        let mut cursor = trace.cursor();
+        cursor.seek_key(&55);
+        assert_eq!(cursor.key(), &55);
+        cursor.seek_val(&3);
+        assert_eq!(cursor.val(), &3);
+        cursor.seek_key(&98);
+        assert_eq!(cursor.key(), &99);
+        cursor.seek_key_reverse(&14);
+        assert_eq!(cursor.key(), &13);
+        cursor.fast_forward_vals();
+        assert_eq!(cursor.val(), &5);
+        cursor.seek_key_reverse(&7);
+        assert_eq!(cursor.key(), &6);
+        cursor.step_key_reverse();
+        assert_eq!(cursor.key(), &5);
+        cursor.seek_key(&1000);
+        assert!(!cursor.key_valid());
+        cursor.fast_forward_keys();
+        assert_eq!(cursor.key(), &99);
+        cursor.rewind_keys();
+        assert_eq!(cursor.key(), &1);
+        drop(cursor);
+
+        // Keys below the bound are dropped when pages are read.
+        trace.truncate_keys_below(&50);
+        let mut cursor = trace.cursor();
+        assert_eq!(cursor.key(), &50);
+        cursor.seek_key_reverse(&20);
+        assert!(!cursor.key_valid());
+        cursor.seek_key(&0);
+        assert_eq!(cursor.key(), &50);
+    }
+
+    #[test]
+    fn test_clone_and_consumer() {
+        let directory = spill_directory("test_clone_and_consumer");
+        let mut trace: PersistentSpine<OrdIndexedZSet<i32, i32, i32>> =
+            PersistentSpine::with_policy(
+                SpillPolicy::new(2, &directory).with_page_size(3),
+                1,
+                None,
+            );
+        let mut ref_trace: Spine<OrdIndexedZSet<i32, i32, i32>> = Spine::new(None);
+
+        for i in 0..20 {
+            let tuples = vec![((i, i % 3), 1), ((i / 2, i), 2)];
+            trace.insert(OrdIndexedZSet::from_tuples((), tuples.clone()));
+            ref_trace.insert(OrdIndexedZSet::from_tuples((), tuples));
+        }
+        assert!(trace.num_spilled_batches() > 0);
+
+        // The clone owns copies of the spill files.
+        let clone = trace.clone();
+        assert_eq!(num_files(&directory), 2 * trace.num_spilled_batches());
+        assert_batch_eq(&clone, &ref_trace);
+        drop(trace);
+        assert_eq!(num_files(&directory), clone.num_spilled_batches());
+        assert_batch_eq(&clone, &ref_trace);
+
+        let ref_batch = ref_trace.consolidate().unwrap();
+        assert_eq!(clone.consumer().to_vec(), ref_batch.consumer().to_vec());
+        assert_eq!(num_files(&directory), 0);
+    }
+
+    #[test]
+    fn test_runtime_spill_policy() {
+        let directory = spill_directory("test_runtime_spill_policy");
+        let policy = SpillPolicy::new(4, &directory);
+
+        Runtime::run_with_config(
+            2,
+            RuntimeConfig::default().with_spill_policy(policy.clone()),
+            move || {
+                let trace: PersistentSpine<OrdZSet<i32, i32>> = PersistentSpine::new(None);
+                assert_eq!(trace.policy, policy);
+            },
+        )
+        .join()
+        .unwrap();
+
+        // Outside of a runtime traces use the default policy.
+        let trace: PersistentSpine<OrdZSet<i32, i32>> = PersistentSpine::new(None);
+        assert_eq!(trace.policy, SpillPolicy::default());
+    }
+}
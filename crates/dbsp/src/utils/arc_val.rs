@@ -0,0 +1,208 @@
+use size_of::SizeOf;
+use std::{
+    borrow::Borrow,
+    fmt::{self, Debug, Display},
+    ops::Deref,
+    sync::Arc,
+};
+
+#[cfg(feature = "with-serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A reference-counted wrapper that makes cloning large values cheap.
+///
+/// Operators clone keys and values whenever they copy a record to their
+/// output: `map`, `filter`, `flat_map`, joins, etc., all clone
+/// `cursor.key()` or `cursor.val()`.  For large values, e.g., vectors of
+/// strings, these clones can dominate the cost of the operator (see the
+/// `arc_val` benchmark).  Storing such values as `ArcVal<V>` turns every
+/// clone into a reference count increment, while all copies of the value
+/// share the same allocation.
+///
+/// `ArcVal<V>` dereferences to `V`, so closures that receive `&ArcVal<V>`
+/// from a cursor can use it as `&V`.  Comparisons, hashing, and
+/// serialization delegate to the inner value, so batches of `ArcVal<V>` are
+/// ordered exactly like batches of `V`.
+///
+/// Values are immutable once wrapped.  Use [`make_mut`](`Self::make_mut`) to
+/// modify a value, which clones the inner value if it is shared.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, SizeOf)]
+#[repr(transparent)]
+pub struct ArcVal<V>(Arc<V>);
+
+impl<V> ArcVal<V> {
+    pub fn new(val: V) -> Self {
+        Self(Arc::new(val))
+    }
+
+    /// Returns `true` if `this` and `other` share the same allocation.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+
+    /// Returns a mutable reference to the inner value, cloning it first if
+    /// it is shared with other `ArcVal`s.
+    pub fn make_mut(&mut self) -> &mut V
+    where
+        V: Clone,
+    {
+        Arc::make_mut(&mut self.0)
+    }
+
+    /// Unwraps the inner value, cloning it if it is shared with other
+    /// `ArcVal`s.
+    pub fn into_inner(self) -> V
+    where
+        V: Clone,
+    {
+        Arc::try_unwrap(self.0).unwrap_or_else(|arc| (*arc).clone())
+    }
+}
+
+impl<V> From<V> for ArcVal<V> {
+    fn from(val: V) -> Self {
+        Self::new(val)
+    }
+}
+
+impl<V> Deref for ArcVal<V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.0
+    }
+}
+
+impl<V> AsRef<V> for ArcVal<V> {
+    fn as_ref(&self) -> &V {
+        &self.0
+    }
+}
+
+impl<V> Borrow<V> for ArcVal<V> {
+    fn borrow(&self) -> &V {
+        &self.0
+    }
+}
+
+impl<V: Debug> Debug for ArcVal<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&*self.0, f)
+    }
+}
+
+impl<V: Display> Display for ArcVal<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&*self.0, f)
+    }
+}
+
+impl<V: bincode::Encode> bincode::Encode for ArcVal<V> {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        encoder: &mut E,
+    ) -> core::result::Result<(), bincode::error::EncodeError> {
+        bincode::Encode::encode(&*self.0, encoder)
+    }
+}
+
+impl<V: bincode::Decode> bincode::Decode for ArcVal<V> {
+    fn decode<D: bincode::de::Decoder>(
+        decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        let val: V = bincode::Decode::decode(decoder)?;
+        Ok(Self::new(val))
+    }
+}
+
+impl<'de, V: bincode::BorrowDecode<'de>> bincode::BorrowDecode<'de> for ArcVal<V> {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        let val: V = bincode::BorrowDecode::borrow_decode(decoder)?;
+        Ok(Self::new(val))
+    }
+}
+
+#[cfg(feature = "with-serde")]
+impl<V: Serialize> Serialize for ArcVal<V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.as_ref().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "with-serde")]
+impl<'de, V: Deserialize<'de>> Deserialize<'de> for ArcVal<V> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        V::deserialize(deserializer).map(Self::new)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ArcVal;
+    use crate::{
+        operator::{FilterMap, Generator},
+        trace::{BatchReader, Cursor},
+        zset, Circuit, OrdZSet, RootCircuit,
+    };
+
+    type People = ArcVal<Vec<String>>;
+
+    fn people(names: &[&str]) -> People {
+        ArcVal::new(names.iter().map(|name| name.to_string()).collect())
+    }
+
+    // Records copied by `filter` and `map` share their allocation with the
+    // input records.
+    #[test]
+    fn arc_val_no_deep_clones() {
+        let input: OrdZSet<People, isize> = zset! {
+            people(&["alice", "bob"]) => 1,
+            people(&["carol"]) => 1,
+            people(&["dave", "erin", "frank"]) => 1,
+        };
+        let input_clone = input.clone();
+
+        let (circuit, ()) = RootCircuit::build(move |circuit| {
+            let source = circuit.add_source(Generator::new(move || input_clone.clone()));
+
+            source
+                .filter(|people| people.len() > 1)
+                .map(|people| people.clone())
+                .inspect(move |output: &OrdZSet<People, isize>| {
+                    assert_eq!(output.len(), 2);
+
+                    let mut cursor = output.cursor();
+                    while cursor.key_valid() {
+                        let mut input_cursor = input.cursor();
+                        input_cursor.seek_key(cursor.key());
+                        assert!(ArcVal::ptr_eq(cursor.key(), input_cursor.key()));
+                        cursor.step_key();
+                    }
+                });
+        })
+        .unwrap();
+
+        circuit.step().unwrap();
+    }
+
+    #[test]
+    fn arc_val_make_mut() {
+        let mut x = people(&["alice"]);
+        let y = x.clone();
+        assert!(ArcVal::ptr_eq(&x, &y));
+
+        // Modifying a shared value clones it.
+        x.make_mut().push("bob".to_string());
+        assert!(!ArcVal::ptr_eq(&x, &y));
+        assert_eq!(*y, vec!["alice".to_string()]);
+        assert_eq!(x.into_inner(), vec!["alice".to_string(), "bob".to_string()]);
+    }
+}
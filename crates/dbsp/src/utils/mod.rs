@@ -1,7 +1,9 @@
+mod arc_val;
 mod dyn_vec;
 pub(crate) mod tests;
 mod vec_ext;
 
+pub use arc_val::ArcVal;
 pub use dyn_vec::{DynIter, DynVec, DynVecVTable};
 
 pub(crate) use vec_ext::VecExt;
@@ -18,7 +18,7 @@ use dbsp_nexmark::{
     model::Event,
     queries::{
         q0, q1, q12, q13, q13_side_input, q14, q15, q16, q17, q18, q19, q2, q20, q21, q22, q3, q4,
-        q5, q6, q7, q8, q9,
+        q5, q6, q6_unfused, q7, q8, q9,
     },
     NexmarkSource,
 };
@@ -58,6 +58,8 @@ struct InputStats {
 enum StepCompleted {
     Dbsp,
     Source(usize),
+    // The source pushed its last (possibly empty) batch of the given size.
+    SourceExhausted(usize, InputStats),
 }
 
 fn spawn_dbsp_consumer(
@@ -93,7 +95,6 @@ fn spawn_source_producer(
     mut input_handle: CollectionHandle<Event, isize>,
     step_do_rx: mpsc::Receiver<()>,
     step_done_tx: mpsc::SyncSender<StepCompleted>,
-    source_exhausted_tx: mpsc::SyncSender<InputStats>,
 ) {
     thread::Builder::new()
         .name("benchmark producer".into())
@@ -117,19 +118,21 @@ fn spawn_source_producer(
                 input_handle.append(&mut events);
                 num_events += batch_count as u64;
 
-                step_done_tx
-                    .send(StepCompleted::Source(batch_count))
-                    .unwrap();
                 // If we're unable to fetch a full batch, then we're done.
                 if batch_count < batch_size {
                     break batch_count;
                 }
+                step_done_tx
+                    .send(StepCompleted::Source(batch_count))
+                    .unwrap();
                 step_do_rx.recv().unwrap();
             };
 
-            source_exhausted_tx.send(InputStats { num_events }).unwrap();
             step_done_tx
-                .send(StepCompleted::Source(last_batch_count))
+                .send(StepCompleted::SourceExhausted(
+                    last_batch_count,
+                    InputStats { num_events },
+                ))
                 .unwrap();
         })
         .unwrap();
@@ -140,7 +143,6 @@ fn coordinate_input_and_steps(
     dbsp_step_tx: mpsc::SyncSender<()>,
     source_step_tx: mpsc::SyncSender<()>,
     step_done_rx: mpsc::Receiver<StepCompleted>,
-    source_exhausted_rx: mpsc::Receiver<InputStats>,
     dbsp_join_handle: JoinHandle<()>,
 ) -> Result<InputStats> {
     // The producer should have already loaded up the first batch ready for
@@ -155,37 +157,51 @@ fn coordinate_input_and_steps(
         .progress_chars("=>-"),
     );
 
-    if let Ok(StepCompleted::Dbsp) = step_done_rx.recv() {
-        return Err(anyhow!("Expected initial source step, got DBSP step"));
-    }
-
-    // Continue until the source is exhausted.
-    loop {
-        if let Ok(input_stats) = source_exhausted_rx.try_recv() {
-            // Wait for the processing to complete. We explicitly do one more step
-            // to ensure the last input is processed, before dropping the dbsp_step_tx
-            // half of the channel to ensure the dbsp thread terminates.
-            dbsp_step_tx.send(())?;
-            step_done_rx.recv()?;
-            drop(dbsp_step_tx);
-            dbsp_join_handle
-                .join()
-                .expect("DBSP consumer thread panicked");
-            progress_bar.finish_with_message("Done");
-            return Ok(input_stats);
+    // Set once the source has pushed its last batch.
+    let mut input_stats = match step_done_rx.recv()? {
+        StepCompleted::Dbsp => {
+            return Err(anyhow!("Expected initial source step, got DBSP step"));
+        }
+        StepCompleted::Source(num_events) => {
+            progress_bar.inc(num_events as u64);
+            None
+        }
+        StepCompleted::SourceExhausted(num_events, input_stats) => {
+            progress_bar.inc(num_events as u64);
+            Some(input_stats)
         }
+    };
 
+    // Continue until the source is exhausted.
+    while input_stats.is_none() {
         // Trigger the step and the input of the next batch.
         dbsp_step_tx.send(())?;
         source_step_tx.send(())?;
 
         // Ensure both the dbsp and source finish before continuing.
         for _ in 0..2 {
-            if let Ok(StepCompleted::Source(num_events)) = step_done_rx.recv() {
-                progress_bar.inc(num_events as u64);
+            match step_done_rx.recv()? {
+                StepCompleted::Dbsp => {}
+                StepCompleted::Source(num_events) => progress_bar.inc(num_events as u64),
+                StepCompleted::SourceExhausted(num_events, stats) => {
+                    progress_bar.inc(num_events as u64);
+                    input_stats = Some(stats);
+                }
             }
         }
     }
+
+    // Wait for the processing to complete. We explicitly do one more step
+    // to ensure the last input is processed, before dropping the dbsp_step_tx
+    // half of the channel to ensure the dbsp thread terminates.
+    dbsp_step_tx.send(())?;
+    step_done_rx.recv()?;
+    drop(dbsp_step_tx);
+    dbsp_join_handle
+        .join()
+        .expect("DBSP consumer thread panicked");
+    progress_bar.finish_with_message("Done");
+    Ok(input_stats.unwrap())
 }
 
 fn create_ascii_table() -> AsciiTable {
@@ -244,6 +260,7 @@ fn main() -> Result<()> {
             q4,
             q5,
             q6,
+            q6_unfused,
             q7,
             q8,
             q9,
@@ -21,13 +21,11 @@ macro_rules! run_queries {
         // whenever it receives a message.
         let (source_step_tx, source_step_rx): (mpsc::SyncSender<()>, mpsc::Receiver<()>) =
             mpsc::sync_channel(1);
-        let (source_exhausted_tx, source_exhausted_rx) = mpsc::sync_channel(1);
         spawn_source_producer(
             $nexmark_config,
             input_handle,
             source_step_rx,
             step_done_tx,
-            source_exhausted_tx,
         );
 
         ALLOC.reset_stats();
@@ -39,7 +37,6 @@ macro_rules! run_queries {
             dbsp_step_tx,
             source_step_tx,
             step_done_rx,
-            source_exhausted_rx,
             dbsp_join_handle,
         )
         .unwrap();
@@ -95,6 +95,16 @@ pub struct Config {
     #[clap(long, env = "NEXMARK_PROFILE_PATH")]
     pub profile_path: Option<String>,
 
+    /// Seed for the random number generators used to generate event data.
+    ///
+    /// When set, the same seed produces identical event streams, including
+    /// event timestamps, which are relative to the Unix epoch instead of the
+    /// current time.  Streams are only guaranteed to be identical across runs
+    /// of the same version of the generator.  By default, each run generates
+    /// different data.
+    #[clap(long, env = "NEXMARK_SEED")]
+    pub seed: Option<u64>,
+
     /// Queries to run, all by default.
     #[clap(long, env = "NEXMARK_QUERIES", value_enum)]
     pub query: Vec<Query>,
@@ -142,6 +152,7 @@ impl Default for Config {
             out_of_order_group_size: 1,
             person_proportion: 1,
             profile_path: None,
+            seed: None,
             query: Vec::new(),
             source_buffer_size: 10_000,
             input_batch_size: 40_000,
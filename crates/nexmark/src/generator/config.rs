@@ -40,6 +40,10 @@ pub struct Config {
     /// entry then the rate is changed every {@link #stepLengthSec}, and wraps
     /// around.
     pub inter_event_delay_us: [f64; 1],
+
+    /// Seed for the random number generator of this generator, or `None` to
+    /// use a non-deterministic random number generator.
+    pub seed: Option<u64>,
 }
 
 /// Implementation of config methods based on the Java implementation at
@@ -85,9 +89,21 @@ impl Config {
             max_events,
             first_event_number,
             inter_event_delay_us: [inter_event_delay],
+            seed: None,
         }
     }
 
+    /// Generate event data with a random number generator seeded with `seed`.
+    ///
+    /// Generators with the same configuration and seed generate identical
+    /// events.  This only holds for the same version of the generator: any
+    /// change to the generator logic may change the events generated for a
+    /// seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
     /// Return the next event number for a generator which has so far emitted
     /// `num_events`.
     pub fn next_event_number(&self, num_events: u64) -> u64 {
@@ -20,7 +20,10 @@ use dbsp::{
     circuit::operator_traits::Data,
     OrdZSet,
 };
-use rand::{rngs::ThreadRng, Rng};
+use rand::{
+    rngs::{StdRng, ThreadRng},
+    Rng, SeedableRng,
+};
 use std::{
     collections::VecDeque,
     marker::PhantomData,
@@ -119,8 +122,19 @@ pub struct NexmarkSource<W, C> {
     _t: PhantomData<(C, W)>,
 }
 
+// Sends all events generated by `generator` to `tx`.
+fn run_generator<R: Rng>(mut generator: NexmarkGenerator<R>, mut tx: BatchedSender<NextEvent>) {
+    while let Ok(Some(event)) = generator.next_event() {
+        tx.send(event).unwrap();
+    }
+    tx.flush().unwrap();
+}
+
 // Creates and spawns the generators according to the nexmark config, returning
 // the receiver to listen on for next events.
+//
+// Generators use random number generators of type `R`, unless the config
+// specifies a seed.
 fn create_generators_for_config<R: Rng + Default>(
     nexmark_config: NexmarkConfig,
     // TODO: I originally planned for this function to be generic for Rng, so I could test
@@ -133,28 +147,41 @@ fn create_generators_for_config<R: Rng + Default>(
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
         .as_millis() as u64;
+    // Event timestamps are relative to the current time, unless the events must
+    // be reproducible.
+    let base_time = match nexmark_config.seed {
+        Some(_) => 0,
+        None => wallclock_base_time,
+    };
     let buffer_size = nexmark_config.source_buffer_size;
     let mut next_event_rxs: Vec<BatchedReceiver<NextEvent>> = (0..nexmark_config
         .num_event_generators)
         .map(|generator_num| {
-            GeneratorConfig::new(
-                nexmark_config.clone(),
-                wallclock_base_time,
-                0,
-                generator_num,
-            )
+            let generator_config =
+                GeneratorConfig::new(nexmark_config.clone(), base_time, 0, generator_num);
+            match nexmark_config.seed {
+                // Each generator needs its own seed to avoid generating the same data.
+                Some(seed) => generator_config.with_seed(seed.wrapping_add(generator_num as u64)),
+                None => generator_config,
+            }
         })
         .map(|generator_config| {
-            let (mut tx, rx) = batched_channel(buffer_size);
+            let (tx, rx) = batched_channel(buffer_size);
             thread::Builder::new()
                 .name(format!("generator-{}", generator_config.first_event_number))
-                .spawn(move || {
-                    let mut generator =
-                        NexmarkGenerator::new(generator_config, R::default(), wallclock_base_time);
-                    while let Ok(Some(event)) = generator.next_event() {
-                        tx.send(event).unwrap();
-                    }
-                    tx.flush().unwrap();
+                .spawn(move || match generator_config.seed {
+                    Some(seed) => run_generator(
+                        NexmarkGenerator::new(
+                            generator_config,
+                            StdRng::seed_from_u64(seed),
+                            wallclock_base_time,
+                        ),
+                        tx,
+                    ),
+                    None => run_generator(
+                        NexmarkGenerator::new(generator_config, R::default(), wallclock_base_time),
+                        tx,
+                    ),
                 })
                 .unwrap();
             rx
@@ -355,6 +382,27 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_source_with_seed() {
+        let events = |seed| {
+            let nexmark_config = NexmarkConfig {
+                num_event_generators: 3,
+                first_event_rate: 1_000_000,
+                max_events: 100,
+                seed: Some(seed),
+                ..NexmarkConfig::default()
+            };
+            let receiver = create_generators_for_config::<ThreadRng>(nexmark_config);
+            NexmarkSource::<isize, OrdZSet<Event, isize>>::from_next_events(receiver)
+                .collect::<Vec<_>>()
+        };
+
+        let events1 = events(1);
+        assert_eq!(events1.len(), 100);
+        assert_eq!(events1, events(1));
+        assert_ne!(events1, events(2));
+    }
+
     #[rstest]
     #[case::two_batches_of_4(vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]])]
     #[case::four_batches_of_2(vec![vec![0, 1], vec![2, 3], vec![4, 5], vec![6, 7]])]
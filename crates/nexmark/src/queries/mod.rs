@@ -11,18 +11,25 @@ type OrdinalDate = (i32, u16);
 // Based on the WATERMARK FOR definition in the original [ddl_gen.sql](https://github.com/nexmark/nexmark/blob/54974ef36a0d01ef8ebc0b4ba39cfc50136af0f6/nexmark-flink/src/main/resources/queries/ddl_gen.sql#L37)
 const WATERMARK_INTERVAL_SECONDS: u64 = 4;
 
+// Queries after the `;` are variants of other queries defined in the
+// module of the original query, e.g., for benchmarking alternative plans.
 macro_rules! declare_queries {
-    ($($query:ident),* $(,)?) => {
+    ($($query:ident),* $(,)? ; $($variant:ident in $module:ident),* $(,)?) => {
         $(
             mod $query;
             pub use $query::$query;
         )*
 
+        $(
+            pub use $module::$variant;
+        )*
+
         paste::paste! {
             /// All available nexmark queries
             #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
             pub enum Query {
                 $([<$query:upper>],)*
+                $([<$variant:upper>],)*
             }
         }
     };
@@ -49,7 +56,8 @@ declare_queries! {
     q19,
     q20,
     q21,
-    q22,
+    q22;
+    q6_unfused in q6,
 }
 
 pub use q13::q13_side_input;
@@ -1,8 +1,8 @@
 use super::NexmarkStream;
 use dbsp::{
     algebra::UnimplementedSemigroup,
-    operator::{FilterMap, Fold, Max},
-    RootCircuit, OrdIndexedZSet, OrdZSet, Stream,
+    operator::{Aggregator, FilterMap, Fold, Max},
+    RootCircuit, OrdIndexedZSet, Stream,
 };
 use crate::model::Event;
 use std::collections::VecDeque;
@@ -43,58 +43,67 @@ type Q6Stream = Stream<RootCircuit, OrdIndexedZSet<u64, usize, isize>>;
 const NUM_AUCTIONS_PER_SELLER: usize = 10;
 
 pub fn q6(input: NexmarkStream) -> Q6Stream {
+    // Once we have the winning bids, we don't need the auction ids anymore:
+    // re-index winning bids by seller and calculate the average winning bid
+    // per seller, sharding the re-indexed bids as part of re-indexing them.
+    winning_bids(input).map_aggregate(
+        |(key, max)| (key.1, (key.0, *max)),
+        average_of_last_auctions(),
+    )
+}
+
+/// Query 6 without fusing re-indexing by seller with the final aggregate.
+///
+/// Produces the same output as [`q6`].  Used to measure the benefit of
+/// [`map_aggregate`](`Stream::map_aggregate`), e.g., with `cargo bench --bench
+/// nexmark --features with-nexmark -- --query q6 --query q6-unfused
+/// --cpu-cores 8`.
+pub fn q6_unfused(input: NexmarkStream) -> Q6Stream {
+    // winning_bids_by_seller: once we have the winning bids, we don't
+    // need the auction ids anymore.
+    type WinningBidsBySeller = Stream<RootCircuit, OrdIndexedZSet<u64, (u64, usize), isize>>;
+    let winning_bids_by_seller_indexed: WinningBidsBySeller =
+        winning_bids(input).map_index(|(key, max)| (key.1, (key.0, *max)));
+
+    winning_bids_by_seller_indexed.aggregate(average_of_last_auctions())
+}
+
+/// Computes the winning bid for each auction, indexed by `(auction_id,
+/// seller)`.
+fn winning_bids(
+    input: NexmarkStream,
+) -> Stream<RootCircuit, OrdIndexedZSet<(u64, u64), usize, isize>> {
     // Select auctions sellers and index by auction id.
     let auctions_by_id = input.flat_map_index(|event| match event {
-        Event::Auction(a) => Some((a.id, (a.seller, a.date_time, a.expires))),
+        Event::Auction(a) => Some((a.id, ((a.date_time, a.expires), a.seller))),
         _ => None,
     });
 
     // Select bids and index by auction id.
     let bids_by_auction = input.flat_map_index(|event| match event {
-        Event::Bid(b) => Some((b.auction, (b.price, b.date_time))),
+        Event::Bid(b) => Some((b.auction, (b.date_time, b.price))),
         _ => None,
     });
 
-    type BidsAuctionsJoin =
-        Stream<RootCircuit, OrdZSet<((u64, u64, u64, u64), (usize, u64)), isize>>;
-
-    // Join to get bids for each auction.
-    let bids_for_auctions: BidsAuctionsJoin = auctions_by_id.join(
+    // Join each auction with the bids placed between the start and the
+    // expiration of the auction (inclusive).
+    let bids_for_auctions_indexed = auctions_by_id.join_range_index(
         &bids_by_auction,
-        |&auction_id, &(seller, a_date_time, a_expires), &(bid_price, bid_date_time)| {
-            (
-                (auction_id, seller, a_date_time, a_expires),
-                (bid_price, bid_date_time),
-            )
-        },
-    );
-
-    // Filter out the invalid bids while indexing.
-    // TODO: update to use incremental version of `join_range` once implemented
-    // (#137).
-    let bids_for_auctions_indexed = bids_for_auctions.flat_map_index(
-        |&((auction_id, seller, a_date_time, a_expires), (bid_price, bid_date_time))| {
-            if bid_date_time >= a_date_time && bid_date_time <= a_expires {
-                Some(((auction_id, seller), bid_price))
-            } else {
-                None
-            }
-        },
+        |&(a_date_time, _)| a_date_time,
+        |&(_, a_expires)| a_expires + 1,
+        |&auction_id, _, &seller, _, &bid_price| Some(((auction_id, seller), bid_price)),
     );
 
-    // winning_bids_by_seller: once we have the winning bids, we don't
-    // need the auction ids anymore.
     // TODO: We can optimize this given that there are no deletions, as DBSP
     // doesn't need to keep records of the bids for future max calculations.
-    type WinningBidsBySeller = Stream<RootCircuit, OrdIndexedZSet<u64, (u64, usize), isize>>;
-    let winning_bids_by_seller_indexed: WinningBidsBySeller = bids_for_auctions_indexed
-        .aggregate(Max)
-        .map_index(|(key, max)| (key.1, (key.0, *max)));
-
-    // Finally, calculate the average winning bid per seller, using the last
-    // 10 closed auctions.
-    // TODO: use linear aggregation when ready (#138).
-    winning_bids_by_seller_indexed.aggregate(<Fold<_, UnimplementedSemigroup<_>, _, _>>::with_output(
+    bids_for_auctions_indexed.aggregate(Max)
+}
+
+/// Calculates the average winning bid per seller, using the last 10 closed
+/// auctions.
+// TODO: use linear aggregation when ready (#138).
+fn average_of_last_auctions() -> impl Aggregator<(u64, usize), (), isize, Output = usize> {
+    <Fold<_, UnimplementedSemigroup<_>, _, _>>::with_output(
         VecDeque::with_capacity(NUM_AUCTIONS_PER_SELLER),
         |top: &mut VecDeque<usize>, val: &(u64, usize), _w| {
             if top.len() >= NUM_AUCTIONS_PER_SELLER {
@@ -107,7 +116,7 @@ pub fn q6(input: NexmarkStream) -> Q6Stream {
             let sum: usize = Iterator::sum(top.into_iter());
             sum / len
         },
-    ))
+    )
 }
 
 #[cfg(test)]
@@ -190,7 +199,10 @@ mod tests {
             ]
             .into_iter();
 
-            let output = q6(stream);
+            let output = q6(stream.clone());
+
+            // The unfused version of the query must produce identical outputs.
+            q6_unfused(stream).apply2(&output, |unfused, fused| assert_eq!(unfused, fused));
 
             output.inspect(move |batch| assert_eq!(batch, &expected_output.next().unwrap()));
 
@@ -264,7 +276,10 @@ mod tests {
             ]
             .into_iter();
 
-            let output = q6(stream);
+            let output = q6(stream.clone());
+
+            // The unfused version of the query must produce identical outputs.
+            q6_unfused(stream).apply2(&output, |unfused, fused| assert_eq!(unfused, fused));
 
             output.inspect(move |batch| assert_eq!(batch, &expected_output.next().unwrap()));
 
@@ -506,7 +521,10 @@ mod tests {
             ]
             .into_iter();
 
-            let output = q6(stream);
+            let output = q6(stream.clone());
+
+            // The unfused version of the query must produce identical outputs.
+            q6_unfused(stream).apply2(&output, |unfused, fused| assert_eq!(unfused, fused));
 
             output.inspect(move |batch| assert_eq!(batch, &expected_output.next().unwrap()));
 
@@ -625,7 +643,10 @@ mod tests {
             ]
             .into_iter();
 
-            let output = q6(stream);
+            let output = q6(stream.clone());
+
+            // The unfused version of the query must produce identical outputs.
+            q6_unfused(stream).apply2(&output, |unfused, fused| assert_eq!(unfused, fused));
 
             output.inspect(move |batch| assert_eq!(batch, &expected_output.next().unwrap()));
             input_handle
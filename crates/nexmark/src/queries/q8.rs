@@ -45,6 +45,11 @@ type Q8Stream = Stream<RootCircuit, OrdZSet<(u64, ArcStr, u64), isize>>;
 
 const TUMBLE_SECONDS: u64 = 10;
 
+/// Returns the start of the tumbling window that contains `date_time`.
+fn window_start(date_time: u64) -> u64 {
+    date_time - (date_time % (TUMBLE_SECONDS * 1000))
+}
+
 pub fn q8(input: NexmarkStream) -> Q8Stream {
     // People indexed by the date they entered the system.
     let people_by_time = input.flat_map_index(|event| match event {
@@ -78,15 +83,15 @@ pub fn q8(input: NexmarkStream) -> Q8Stream {
     let windowed_people = people_by_time.window(&window_bounds);
     let windowed_auctions = auctions_by_time.window(&window_bounds);
 
-    let people_by_id = windowed_people.map_index(|(date_time, (id, name))| (*id, (name.clone(), *date_time)));
+    // Include the window in the join key, so that a person and an auction
+    // in different windows never join, even if the window bounds used above
+    // span more than one window.
+    let people_by_id = windowed_people
+        .map_index(|(date_time, (id, name))| ((*id, window_start(*date_time)), name.clone()));
+    let sellers = windowed_auctions.map(|(date_time, seller)| (*seller, window_start(*date_time)));
 
-    // Re-calculate the window start-time to include in the output.
-    people_by_id.join(&windowed_auctions.map(|(_date_time, seller)| *seller), |&p_id, (p_name, p_date_time), ()| {
-        (
-            p_id,
-            p_name.clone(),
-            *p_date_time - (*p_date_time % (TUMBLE_SECONDS * 1000)),
-        )
+    people_by_id.join(&sellers, |&(p_id, starttime), p_name, ()| {
+        (p_id, p_name.clone(), starttime)
     })
 }
 
@@ -153,6 +158,28 @@ mod tests {
             (3, String::from("Harry Potter").into(), 20_000) => 1,
         }]
     )]
+    // Person 1 enters the system at the end of the 10-20 window and creates
+    // an auction at the start of the next window, person 2 vice versa.  Only
+    // person 3 creates an auction within the same window.
+    #[case::matching_ids_straddling_window_boundary(
+        vec![
+            vec![
+                (1, String::from("James Potter").into(), 19_999),
+                (2, String::from("Lily Potter").into(), 20_000),
+                (3, String::from("Harry Potter").into(), 10_000),
+            ],
+            vec![],
+        ],
+        vec![
+            vec![(1, 20_000), (2, 19_999), (3, 19_999), (99, 32_000)],
+            vec![(101, 42_000)],
+        ],
+        vec![zset! {
+            (3, String::from("Harry Potter").into(), 10_000) => 1,
+        }, zset! {
+            (3, String::from("Harry Potter").into(), 10_000) => -1,
+        }]
+    )]
     fn test_q8(
         #[case] input_people_batches: Vec<Vec<(u64, ArcStr, u64)>>,
         #[case] input_auction_batches: Vec<Vec<(u64, u64)>>,
@@ -83,15 +83,16 @@ pub fn q9(input: NexmarkStream) -> Q9Stream {
         Event::Auction(a) => Some((
             a.id,
             (
-                a.item_name.clone(),
-                a.description.clone(),
-                a.initial_bid,
-                a.reserve,
-                a.date_time,
-                a.expires,
-                a.seller,
-                a.category,
-                a.extra.clone(),
+                (a.date_time, a.expires),
+                (
+                    a.item_name.clone(),
+                    a.description.clone(),
+                    a.initial_bid,
+                    a.reserve,
+                    a.seller,
+                    a.category,
+                    a.extra.clone(),
+                ),
             ),
         )),
         _ => None,
@@ -99,106 +100,41 @@ pub fn q9(input: NexmarkStream) -> Q9Stream {
 
     // Select bids and index by auction id.
     let bids_by_auction = input.flat_map_index(|event| match event {
-        Event::Bid(b) => Some((b.auction, (b.bidder, b.price, b.date_time, b.extra.clone()))),
+        Event::Bid(b) => Some((
+            b.auction,
+            (b.date_time, (b.bidder, b.price, b.extra.clone())),
+        )),
         _ => None,
     });
 
-    type BidsAuctionsJoin = Stream<
-        RootCircuit,
-        OrdZSet<
-            (
-                (
-                    u64,
-                    ArcStr,
-                    ArcStr,
-                    usize,
-                    usize,
-                    u64,
-                    u64,
-                    u64,
-                    usize,
-                    ArcStr,
-                ),
-                (u64, usize, u64, ArcStr),
-            ),
-            isize,
-        >,
-    >;
-
-    // Join to get bids for each auction.
-    let bids_for_auctions: BidsAuctionsJoin = auctions_by_id.join(
+    // Join each auction with the bids placed between the start and the
+    // expiration of the auction (inclusive).
+    let bids_for_auctions_indexed = auctions_by_id.join_range_index(
         &bids_by_auction,
+        |&(a_date_time, _)| a_date_time,
+        |&(_, a_expires)| a_expires + 1,
         |&auction_id,
-         (
-            a_item_name,
-            a_description,
-            a_initial_bid,
-            a_reserve,
-            a_date_time,
-            a_expires,
-            a_seller,
-            a_category,
-            a_extra,
-        ),
-         (b_bidder, b_price, b_date_time, b_extra)| {
-            (
+         &(a_date_time, a_expires),
+         (a_item_name, a_description, a_initial_bid, a_reserve, a_seller, a_category, a_extra),
+         &b_date_time,
+         (b_bidder, b_price, b_extra)| {
+            Some((
                 (
                     auction_id,
                     a_item_name.clone(),
                     a_description.clone(),
                     *a_initial_bid,
                     *a_reserve,
-                    *a_date_time,
-                    *a_expires,
+                    a_date_time,
+                    a_expires,
                     *a_seller,
                     *a_category,
                     a_extra.clone(),
                 ),
-                (*b_bidder, *b_price, *b_date_time, b_extra.clone()),
-            )
-        },
-    );
-
-    // Filter out the invalid bids while indexing.
-    // TODO: update to use incremental version of `join_range` once implemented
-    // (#137).
-    let bids_for_auctions_indexed = bids_for_auctions.flat_map_index(
-        |(
-            (
-                auction_id,
-                a_item_name,
-                a_description,
-                a_initial_bid,
-                a_reserve,
-                a_date_time,
-                a_expires,
-                a_seller,
-                a_category,
-                a_extra,
-            ),
-            (b_bidder, b_price, b_date_time, b_extra),
-        )| {
-            if b_date_time >= a_date_time && b_date_time <= a_expires {
-                Some((
-                    (
-                        *auction_id,
-                        a_item_name.clone(),
-                        a_description.clone(),
-                        *a_initial_bid,
-                        *a_reserve,
-                        *a_date_time,
-                        *a_expires,
-                        *a_seller,
-                        *a_category,
-                        a_extra.clone(),
-                    ),
-                    // Note that the price of the bid is first in the tuple here to ensure that the
-                    // default lexicographic Ord of tuples does what we want below.
-                    (*b_price, *b_bidder, *b_date_time, b_extra.clone()),
-                ))
-            } else {
-                None
-            }
+                // Note that the price of the bid is first in the tuple here to ensure that the
+                // default lexicographic Ord of tuples does what we want below.
+                (*b_price, *b_bidder, b_date_time, b_extra.clone()),
+            ))
         },
     );
 
@@ -1,9 +1,10 @@
-use crate::db::storage::Storage;
+use crate::db::{code_hash, storage::Storage};
 use crate::{ManagerConfig, ProjectDB, ProjectId, Version};
 use anyhow::{Error as AnyError, Result as AnyResult};
 use fs_extra::{dir, dir::CopyOptions};
 use log::{debug, error, trace};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
     process::{ExitStatus, Stdio},
     sync::Arc,
@@ -124,10 +125,71 @@ impl Compiler {
         copy_options.copy_inside = true;
         dir::copy(config.sql_lib_path(), config.workspace_dir(), &copy_options)?;
 
+        // Binaries built by a different toolchain can't be reused.
+        let fingerprint = Self::build_fingerprint(config).await?;
+        let old_fingerprint = fs::read_to_string(config.build_fingerprint_path())
+            .await
+            .ok();
+        if old_fingerprint.as_ref() != Some(&fingerprint) {
+            debug!("Build fingerprint changed, clearing build cache");
+            db.lock().await.clear_build_cache().await?;
+            fs::write(config.build_fingerprint_path(), &fingerprint)
+                .await
+                .map_err(|e| {
+                    AnyError::msg(format!(
+                        "failed to write '{}': {e}",
+                        config.build_fingerprint_path().display()
+                    ))
+                })?;
+        }
+
+        // Forget builds whose binaries have been removed from the workspace.
+        {
+            let db = db.lock().await;
+            for project in db.list_projects().await? {
+                if !config.project_executable(project.project_id).exists() {
+                    db.invalidate_cached_build(project.project_id).await?;
+                }
+            }
+        }
+
         let compiler_task = spawn(Self::compiler_task(config.clone(), db));
         Ok(Self { compiler_task })
     }
 
+    /// Compute a fingerprint of everything besides project code that
+    /// affects project binaries: manager version, Rust toolchain, SQL
+    /// compiler, and build settings.
+    ///
+    /// Changes to the DBSP sources in `dbsp_override_path` are not tracked.
+    async fn build_fingerprint(config: &ManagerConfig) -> AnyResult<String> {
+        let mut hasher = Sha256::new();
+
+        hasher.update(env!("CARGO_PKG_VERSION"));
+
+        let rustc_version = Command::new("rustc")
+            .arg("--version")
+            .output()
+            .await
+            .map_err(|e| AnyError::msg(format!("failed to run 'rustc --version': '{e}'")))?;
+        hasher.update(&rustc_version.stdout);
+
+        let sql_compiler_modified = fs::metadata(config.sql_compiler_path())
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        hasher.update(format!("{sql_compiler_modified:?}"));
+
+        if let Ok(template_toml) = fs::read(config.project_toml_template_path()).await {
+            hasher.update(template_toml);
+        }
+
+        hasher.update(format!("{:?}", config.dbsp_override_path));
+        hasher.update(format!("{}", config.debug));
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
     async fn compiler_task(config: ManagerConfig, db: Arc<Mutex<ProjectDB>>) -> AnyResult<()> {
         Self::do_compiler_task(config, db).await.map_err(|e| {
             error!("compiler task failed; error: '{e}'");
@@ -187,6 +249,9 @@ impl Compiler {
                                 ProjectStatus::CompilingRust,
                             ).await?;
 
+                            // The Rust job overwrites the project binary.
+                            db.invalidate_cached_build(project_id).await?;
+
                             // Read the schema so we can store it in the DB.
                             //
                             // - We trust the compiler that it put the file
@@ -198,12 +263,18 @@ impl Compiler {
                             db.set_project_schema(project_id, schema_json).await?;
 
                             debug!("Set ProjectStatus::CompilingRust '{project_id}', version '{version}'");
-                            job = Some(CompilationJob::rust(&config, project_id, version).await?);
+                            let code_hash = job.as_ref().unwrap().code_hash.clone();
+                            job = Some(CompilationJob::rust(&config, project_id, version, code_hash).await?);
                         }
                         Ok(status) if status.success() && job.as_ref().unwrap().is_rust() => {
                             // Rust compiler succeeded -- declare victory.
                             db.set_project_status_guarded(project_id, version, ProjectStatus::Success).await?;
                             debug!("Set ProjectStatus::Success '{project_id}', version '{version}'");
+
+                            // Remember the code the binary was built from, so that
+                            // compiling the same code again can be skipped.
+                            let schema_json = fs::read_to_string(config.schema_path(project_id)).await?;
+                            db.set_cached_build(project_id, &job.as_ref().unwrap().code_hash, &schema_json).await?;
                             job = None;
                         }
                         Ok(status) => {
@@ -275,6 +346,8 @@ struct CompilationJob {
     stage: Stage,
     project_id: ProjectId,
     version: Version,
+    /// Hash of the project code being compiled.
+    code_hash: String,
     compiler_process: Child,
 }
 
@@ -351,6 +424,7 @@ impl CompilationJob {
             stage: Stage::Sql,
             project_id,
             version,
+            code_hash: code_hash(code),
             compiler_process,
         })
     }
@@ -360,6 +434,7 @@ impl CompilationJob {
         config: &ManagerConfig,
         project_id: ProjectId,
         version: Version,
+        code_hash: String,
     ) -> AnyResult<Self> {
         debug!("Running Rust compiler on project '{project_id}', version '{version}'");
 
@@ -463,6 +538,7 @@ impl CompilationJob {
             stage: Stage::Rust,
             project_id,
             version,
+            code_hash,
             compiler_process,
         })
     }
@@ -304,6 +304,12 @@ impl ManagerConfig {
         self.workspace_dir().join("Cargo.toml")
     }
 
+    /// File that stores the fingerprint of the toolchain that built the
+    /// project binaries in the workspace.
+    pub(crate) fn build_fingerprint_path(&self) -> PathBuf {
+        self.workspace_dir().join("build_fingerprint")
+    }
+
     /// Location of the compiled executable for the project.
     pub(crate) fn project_executable(&self, project_id: ProjectId) -> PathBuf {
         Path::new(&self.workspace_dir())
@@ -4,6 +4,7 @@ use async_trait::async_trait;
 use chrono::{DateTime, NaiveDateTime, Utc};
 use log::{debug, error};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{error::Error as StdError, fmt, fmt::Display};
 use storage::Storage;
 use tokio_postgres::{Client, NoTls};
@@ -149,6 +150,11 @@ impl Display for DBError {
 
 impl StdError for DBError {}
 
+/// Hash of project code used to identify cached builds.
+pub(crate) fn code_hash(code: &str) -> String {
+    format!("{:x}", Sha256::digest(code.as_bytes()))
+}
+
 /// The database encodes project status using two columns: `status`, which has
 /// type `string`, but acts as an enum, and `error`, only used if `status` is
 /// one of `"sql_error"` or `"rust_error"`.
@@ -590,6 +596,53 @@ impl Storage for ProjectDB {
         }
     }
 
+    async fn cached_build(&self, project_id: ProjectId) -> AnyResult<Option<(String, String)>> {
+        let row = self
+            .conn
+            .query_opt(
+                "SELECT code_hash, schema FROM build_cache WHERE project_id = $1",
+                &[&project_id.0],
+            )
+            .await?;
+
+        Ok(row.map(|row| (row.get(0), row.get(1))))
+    }
+
+    async fn set_cached_build(
+        &self,
+        project_id: ProjectId,
+        code_hash: &str,
+        schema: &str,
+    ) -> AnyResult<()> {
+        self.conn
+            .execute(
+                "INSERT INTO build_cache (project_id, code_hash, schema) VALUES($1, $2, $3)
+                    ON CONFLICT (project_id) DO UPDATE SET code_hash = EXCLUDED.code_hash, schema = EXCLUDED.schema",
+                &[&project_id.0, &code_hash, &schema],
+            )
+            .await
+            .map_err(|e| ProjectDB::maybe_build_cache_foreign_key_constraint_err(e, project_id))?;
+
+        Ok(())
+    }
+
+    async fn invalidate_cached_build(&self, project_id: ProjectId) -> AnyResult<()> {
+        self.conn
+            .execute(
+                "DELETE FROM build_cache WHERE project_id = $1",
+                &[&project_id.0],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn clear_build_cache(&self) -> AnyResult<()> {
+        self.conn.execute("DELETE FROM build_cache", &[]).await?;
+
+        Ok(())
+    }
+
     async fn next_job(&self) -> AnyResult<Option<(ProjectId, Version)>> {
         // Find the oldest pending project.
         let res = self.conn.query_one("SELECT id, version FROM project WHERE status = 'pending' AND status_since = (SELECT min(status_since) FROM project WHERE status = 'pending')", &[])
@@ -1087,6 +1140,18 @@ impl ProjectDB {
             )
             .await?;
 
+        client
+            .execute(
+                "
+        CREATE TABLE IF NOT EXISTS build_cache (
+            project_id bigint PRIMARY KEY,
+            code_hash varchar NOT NULL,
+            schema varchar NOT NULL,
+            FOREIGN KEY (project_id) REFERENCES project(id) ON DELETE CASCADE)",
+                &[],
+            )
+            .await?;
+
         client
             .execute(
                 "
@@ -1269,6 +1334,24 @@ impl ProjectDB {
         anyhow!(e)
     }
 
+    /// Helper to convert build_cache project_id foreign key constraint error
+    /// into an user-friendly error message.
+    fn maybe_build_cache_foreign_key_constraint_err(
+        e: tokio_postgres::Error,
+        project_id: ProjectId,
+    ) -> AnyError {
+        let db_err = e.as_db_error();
+        if let Some(db_err) = db_err {
+            if db_err.code() == &tokio_postgres::error::SqlState::FOREIGN_KEY_VIOLATION
+                && db_err.constraint() == Some("build_cache_project_id_fkey")
+            {
+                return anyhow!(DBError::UnknownProject(project_id));
+            }
+        }
+
+        anyhow!(e)
+    }
+
     /// Helper to convert config_id foreign key constraint error into an
     /// user-friendly error message.
     fn maybe_config_id_foreign_key_constraint_err(
@@ -1,6 +1,6 @@
 use super::{
-    AttachedConnector, ConfigDescr, ConfigId, ConnectorDescr, ConnectorId, ConnectorType, DBError,
-    PipelineDescr, PipelineId, ProjectDescr, ProjectId, Version,
+    code_hash, AttachedConnector, ConfigDescr, ConfigId, ConnectorDescr, ConnectorId,
+    ConnectorType, DBError, PipelineDescr, PipelineId, ProjectDescr, ProjectId, Version,
 };
 use crate::{Direction, ProjectStatus};
 use anyhow::{anyhow, Result as AnyResult};
@@ -47,7 +47,10 @@ pub(crate) trait Storage {
     /// Queue project for compilation by setting its status to
     /// [`ProjectStatus::Pending`].
     ///
-    /// Change project status to [`ProjectStatus::Pending`].
+    /// Change project status to [`ProjectStatus::Pending`].  If the project's
+    /// binary was built from the same code (see [`Self::cached_build`]),
+    /// skips compilation and changes project status to
+    /// [`ProjectStatus::Success`] instead.
     async fn set_project_pending(
         &self,
         project_id: ProjectId,
@@ -64,6 +67,16 @@ pub(crate) trait Storage {
             return Ok(());
         }
 
+        if let Some((cached_hash, schema)) = self.cached_build(project_id).await? {
+            let (_descr, code) = self.project_code(project_id).await?;
+            if cached_hash == code_hash(&code) {
+                self.set_project_status(project_id, ProjectStatus::Success)
+                    .await?;
+                self.set_project_schema(project_id, schema).await?;
+                return Ok(());
+            }
+        }
+
         self.set_project_status(project_id, ProjectStatus::Pending)
             .await?;
 
@@ -164,6 +177,33 @@ pub(crate) trait Storage {
     /// This will delete all project configs and pipelines.
     async fn delete_project(&self, project_id: ProjectId) -> AnyResult<()>;
 
+    /// Retrieve the build cache entry of a project.
+    ///
+    /// Returns the hash of the code (see [`code_hash`]) that the current
+    /// binary of the project was built from along with the project schema,
+    /// or `None` if the project doesn't have a valid binary.
+    async fn cached_build(&self, project_id: ProjectId) -> AnyResult<Option<(String, String)>>;
+
+    /// Record that the binary of the project was successfully built from
+    /// code with hash `code_hash`.
+    ///
+    /// Returns `DBError::UnknownProject` if `project_id` is not found in the
+    /// database.
+    async fn set_cached_build(
+        &self,
+        project_id: ProjectId,
+        code_hash: &str,
+        schema: &str,
+    ) -> AnyResult<()>;
+
+    /// Remove the build cache entry of a project, e.g., because its binary
+    /// is about to be overwritten.
+    async fn invalidate_cached_build(&self, project_id: ProjectId) -> AnyResult<()>;
+
+    /// Remove all build cache entries, e.g., because the compiler or
+    /// dependencies have changed.
+    async fn clear_build_cache(&self) -> AnyResult<()>;
+
     /// Retrieves the first pending project from the queue.
     ///
     /// Returns a pending project with the most recent `status_since` or `None`
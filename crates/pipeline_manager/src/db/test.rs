@@ -3,7 +3,7 @@ use super::{
     storage::Storage, AttachedConnector, ConfigDescr, ConfigId, ConnectorDescr, ConnectorId,
     ConnectorType, PipelineId, ProjectDB, ProjectDescr, ProjectId, ProjectStatus, Version,
 };
-use crate::db::{pg_setup, DBError};
+use crate::db::{code_hash, pg_setup, DBError};
 use anyhow::Result as AnyResult;
 use async_trait::async_trait;
 use chrono::DateTime;
@@ -220,6 +220,108 @@ async fn update_status() {
     assert_eq!(ProjectStatus::CompilingRust, desc.status);
 }
 
+/// Mimic the compiler: pick the next job from the queue, build it, and
+/// record the build in the cache.
+///
+/// Returns `false` if there are no pending projects.
+async fn compile_next_job(db: &ProjectDB) -> bool {
+    let (project_id, version) = match db.next_job().await.unwrap() {
+        Some(job) => job,
+        None => return false,
+    };
+    let (_descr, code) = db.project_code(project_id).await.unwrap();
+
+    db.set_project_status_guarded(project_id, version, ProjectStatus::CompilingRust)
+        .await
+        .unwrap();
+    db.set_project_schema(project_id, "schema".to_string())
+        .await
+        .unwrap();
+    db.invalidate_cached_build(project_id).await.unwrap();
+    db.set_project_status_guarded(project_id, version, ProjectStatus::Success)
+        .await
+        .unwrap();
+    db.set_cached_build(project_id, &code_hash(&code), "schema")
+        .await
+        .unwrap();
+
+    true
+}
+
+#[tokio::test]
+async fn build_cache() {
+    let handle = test_setup().await;
+    let code = "create table t1(c1 integer);";
+    let (project_id, version) = handle
+        .db
+        .new_project("test1", "project desc", code)
+        .await
+        .unwrap();
+
+    handle
+        .db
+        .set_project_pending(project_id, version)
+        .await
+        .unwrap();
+    assert!(compile_next_job(&handle.db).await);
+
+    // Change the code and change it back: the project needs to be compiled
+    // again, but its binary matches the code.
+    handle
+        .db
+        .update_project(project_id, "test1", "project desc", &Some("".to_string()))
+        .await
+        .unwrap();
+    let version = handle
+        .db
+        .update_project(project_id, "test1", "project desc", &Some(code.to_string()))
+        .await
+        .unwrap();
+    let descr = handle.db.get_project(project_id).await.unwrap();
+    assert_eq!(ProjectStatus::None, descr.status);
+    assert_eq!(None, descr.schema);
+
+    // Compiling identical code resolves from the cache without a compilation.
+    handle
+        .db
+        .set_project_pending(project_id, version)
+        .await
+        .unwrap();
+    let descr = handle.db.get_project(project_id).await.unwrap();
+    assert_eq!(ProjectStatus::Success, descr.status);
+    assert_eq!(Some("schema".to_string()), descr.schema);
+    assert!(!compile_next_job(&handle.db).await);
+
+    // Different code must be compiled.
+    let version = handle
+        .db
+        .update_project(project_id, "test1", "project desc", &Some("".to_string()))
+        .await
+        .unwrap();
+    handle
+        .db
+        .set_project_pending(project_id, version)
+        .await
+        .unwrap();
+    assert!(compile_next_job(&handle.db).await);
+
+    // Clearing the cache forces recompilation.
+    handle.db.clear_build_cache().await.unwrap();
+    handle
+        .db
+        .set_project_status(project_id, ProjectStatus::None)
+        .await
+        .unwrap();
+    handle
+        .db
+        .set_project_pending(project_id, version)
+        .await
+        .unwrap();
+    let descr = handle.db.get_project(project_id).await.unwrap();
+    assert_eq!(ProjectStatus::Pending, descr.status);
+    assert!(compile_next_job(&handle.db).await);
+}
+
 /// Actions we can do on the Storage trait.
 #[derive(Debug, Clone, Arbitrary)]
 enum StorageAction {
@@ -234,6 +336,10 @@ enum StorageAction {
     SetProjectStatusGuarded(ProjectId, Version, ProjectStatus),
     SetProjectSchema(ProjectId, String),
     DeleteProject(ProjectId),
+    CachedBuild(ProjectId),
+    SetCachedBuild(ProjectId, String, String),
+    InvalidateCachedBuild(ProjectId),
+    ClearBuildCache,
     NextJob,
     ListConfigs,
     GetConfig(ConfigId),
@@ -443,6 +549,31 @@ fn db_impl_behaves_like_model() {
                                 let impl_response = handle.db.delete_project(project_id).await;
                                 check_responses(i, model_response, impl_response);
                             }
+                            StorageAction::CachedBuild(project_id) => {
+                                let model_response = model.cached_build(project_id).await;
+                                let impl_response = handle.db.cached_build(project_id).await;
+                                check_responses(i, model_response, impl_response);
+                            }
+                            StorageAction::SetCachedBuild(project_id, code_hash, schema) => {
+                                let model_response =
+                                    model.set_cached_build(project_id, &code_hash, &schema).await;
+                                let impl_response = handle
+                                    .db
+                                    .set_cached_build(project_id, &code_hash, &schema)
+                                    .await;
+                                check_responses(i, model_response, impl_response);
+                            }
+                            StorageAction::InvalidateCachedBuild(project_id) => {
+                                let model_response = model.invalidate_cached_build(project_id).await;
+                                let impl_response =
+                                    handle.db.invalidate_cached_build(project_id).await;
+                                check_responses(i, model_response, impl_response);
+                            }
+                            StorageAction::ClearBuildCache => {
+                                let model_response = model.clear_build_cache().await;
+                                let impl_response = handle.db.clear_build_cache().await;
+                                check_responses(i, model_response, impl_response);
+                            }
                             StorageAction::NextJob => {
                                 let model_response = model.next_job().await;
                                 let impl_response = handle.db.next_job().await;
@@ -577,6 +708,8 @@ struct DbModel {
 
     // `projects` Format is: (project, code, created)
     pub projects: BTreeMap<ProjectId, (ProjectDescr, String, SystemTime)>,
+    // `build_cache` Format is: (code_hash, schema)
+    pub build_cache: BTreeMap<ProjectId, (String, String)>,
     pub configs: BTreeMap<ConfigId, ConfigDescr>,
     pub connectors: BTreeMap<ConnectorId, ConnectorDescr>,
     pub pipelines: BTreeMap<PipelineId, PipelineDescr>,
@@ -784,6 +917,43 @@ impl Storage for Mutex<DbModel> {
             .ok_or(anyhow::anyhow!(DBError::UnknownProject(project_id)))?;
         // Foreign key delete:
         s.configs.retain(|_, c| c.project_id != Some(project_id));
+        s.build_cache.remove(&project_id);
+
+        Ok(())
+    }
+
+    async fn cached_build(
+        &self,
+        project_id: super::ProjectId,
+    ) -> anyhow::Result<Option<(String, String)>> {
+        Ok(self.lock().await.build_cache.get(&project_id).cloned())
+    }
+
+    async fn set_cached_build(
+        &self,
+        project_id: super::ProjectId,
+        code_hash: &str,
+        schema: &str,
+    ) -> anyhow::Result<()> {
+        let mut s = self.lock().await;
+        if !s.projects.contains_key(&project_id) {
+            return Err(anyhow::anyhow!(DBError::UnknownProject(project_id)));
+        }
+
+        s.build_cache
+            .insert(project_id, (code_hash.to_owned(), schema.to_owned()));
+
+        Ok(())
+    }
+
+    async fn invalidate_cached_build(&self, project_id: super::ProjectId) -> anyhow::Result<()> {
+        self.lock().await.build_cache.remove(&project_id);
+
+        Ok(())
+    }
+
+    async fn clear_build_cache(&self) -> anyhow::Result<()> {
+        self.lock().await.build_cache.clear();
 
         Ok(())
     }
@@ -50,6 +50,7 @@ use clap::Parser;
 use daemonize::Daemonize;
 use env_logger::Env;
 use log::{debug, warn};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use serde::{Deserialize, Serialize};
 use std::{
     fs::{read, write},
@@ -64,6 +65,7 @@ mod compiler;
 mod config;
 mod db;
 mod runner;
+mod validation;
 
 pub(crate) use compiler::{Compiler, ProjectStatus};
 pub(crate) use config::ManagerConfig;
@@ -72,6 +74,7 @@ use db::{
     DBError, PipelineId, ProjectDB, ProjectDescr, ProjectId, Version,
 };
 use runner::{LocalRunner, Runner, RunnerError};
+use validation::{validate_config, ConfigValidationError};
 
 #[derive(OpenApi)]
 #[openapi(
@@ -120,6 +123,7 @@ observed by the user is outdated, so the request is rejected."
         delete_config,
         list_configs,
         config_status,
+        config_validate,
         new_pipeline,
         list_pipelines,
         pipeline_status,
@@ -133,29 +137,40 @@ observed by the user is outdated, so the request is rejected."
         update_connector,
         connector_status,
         delete_connector,
-        http_input
+        http_input,
+        pipeline_output_stream
     ),
     components(schemas(
         compiler::SqlCompilerMessage,
         db::AttachedConnector,
         db::ProjectDescr,
+        validation::ConfigValidationError,
         db::ConnectorDescr,
         db::ConnectorType,
         db::ConfigDescr,
         db::PipelineDescr,
         dbsp_adapters::PipelineConfig,
         dbsp_adapters::InputEndpointConfig,
+        dbsp_adapters::OnErrorPolicy,
         dbsp_adapters::OutputEndpointConfig,
         dbsp_adapters::TransportConfig,
         dbsp_adapters::FormatConfig,
+        dbsp_adapters::WalConfig,
         dbsp_adapters::transport::FileInputConfig,
+        dbsp_adapters::transport::FileInputMode,
         dbsp_adapters::transport::FileOutputConfig,
         dbsp_adapters::transport::KafkaInputConfig,
         dbsp_adapters::transport::KafkaOutputConfig,
         dbsp_adapters::transport::KafkaLogLevel,
+        dbsp_adapters::transport::ParquetOutputConfig,
         dbsp_adapters::transport::KafkaOutputConfig,
+        dbsp_adapters::transport::ParquetOutputConfig,
+        dbsp_adapters::format::AvroEncoderConfig,
+        dbsp_adapters::format::AvroParserConfig,
         dbsp_adapters::format::CsvEncoderConfig,
         dbsp_adapters::format::CsvParserConfig,
+        dbsp_adapters::format::JsonEncoderConfig,
+        dbsp_adapters::format::JsonParserConfig,
         Direction,
         ProjectId,
         PipelineId,
@@ -292,7 +307,9 @@ fn run(config: ManagerConfig) -> AnyResult<()> {
 
         // Since we don't trust any file system state after restart,
         // reset all projects to `ProjectStatus::None`, which will force
-        // us to recompile projects before running them.
+        // us to recompile projects before running them.  Projects whose
+        // binaries are still valid are resolved from the build cache
+        // without invoking the compiler.
         db.lock().await.reset_project_status().await?;
         let openapi = ApiDoc::openapi();
 
@@ -344,6 +361,7 @@ where
         .service(delete_config)
         .service(list_configs)
         .service(config_status)
+        .service(config_validate)
         .service(new_pipeline)
         .service(list_pipelines)
         .service(pipeline_status)
@@ -358,6 +376,7 @@ where
         .service(connector_status)
         .service(delete_connector)
         .service(http_input)
+        .service(pipeline_output_stream)
         .service(SwaggerUi::new("/swagger-ui/{_:.*}").url("/api-doc/openapi.json", openapi));
 
     if let Some(static_html) = &state.config.static_html {
@@ -1105,6 +1124,70 @@ async fn config_status(state: WebData<ServerState>, req: HttpRequest) -> impl Re
         .unwrap_or_else(|e| http_resp_from_error(&e))
 }
 
+/// Validate a config against its project.
+///
+/// Assembles the pipeline configuration, including all attached connectors,
+/// and checks that it parses and that every connector is attached to an
+/// existing input or output stream of the project and uses a known transport
+/// and format.  Returns the list of errors found, which is empty if the config
+/// is valid.
+#[utoipa::path(
+    responses(
+        (status = OK
+            , description = "Config validated; the response lists all errors found."
+            , body = [ConfigValidationError]
+            , example = json!([{"path": "inputs.my_input.stream", "message": "unknown input stream 'USER': expected one of USERS"}])),
+        (status = NOT_FOUND
+            , description = "Specified `config_id` does not exist in the database."
+            , body = ErrorResponse
+            , example = json!(ErrorResponse::new("Unknown config id '5'"))),
+        (status = BAD_REQUEST
+            , description = "The config does not have a project set."
+            , body = String),
+        (status = CONFLICT
+            , description = "The project hasn't been compiled yet."
+            , body = String),
+    ),
+    params(
+        ("config_id" = i64, Path, description = "Unique configuration identifier")
+    ),
+    tag = "Config"
+)]
+#[get("/configs/{config_id}/validate")]
+async fn config_validate(state: WebData<ServerState>, req: HttpRequest) -> impl Responder {
+    let config_id = match parse_config_id_param(&req) {
+        Err(e) => {
+            return e;
+        }
+        Ok(config_id) => config_id,
+    };
+
+    async fn do_validate(db: &ProjectDB, config_id: ConfigId) -> AnyResult<HttpResponse> {
+        let config_descr = db.get_config(config_id).await?;
+        let project_id = match config_descr.project_id {
+            None => {
+                return Ok(HttpResponse::BadRequest()
+                    .body(format!("Config '{config_id}' does not have a project set")))
+            }
+            Some(project_id) => project_id,
+        };
+
+        let project_descr = db.get_project(project_id).await?;
+        if project_descr.status != ProjectStatus::Success {
+            return Ok(HttpResponse::Conflict().body("Project hasn't been compiled yet"));
+        }
+
+        let errors = validate_config(db, &config_descr, &project_descr).await?;
+        Ok(HttpResponse::Ok()
+            .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+            .json(errors))
+    }
+
+    do_validate(&*state.db.lock().await, config_id)
+        .await
+        .unwrap_or_else(|e| http_resp_from_error(&e))
+}
+
 /// Request to create a new pipeline.
 #[derive(Deserialize, ToSchema)]
 pub(self) struct NewPipelineRequest {
@@ -1141,7 +1224,7 @@ struct NewPipelineResponse {
             , body = ErrorResponse
             , example = json!(ErrorResponse::new("Outdated project version '{3}'"))),
         (status = BAD_REQUEST
-            , description = "`config_id` refers to a config that does not belong to `project_id`."
+            , description = "`config_id` refers to a config that does not belong to `project_id`, or the config is invalid.  The error message of an invalid config lists the errors returned by `/configs/{config_id}/validate`."
             , body = ErrorResponse
             , example = json!(ErrorResponse::new("Config '9' does not belong to project '15'"))),
         (status = INTERNAL_SERVER_ERROR
@@ -1691,3 +1774,65 @@ async fn http_input(
         .await
         .unwrap_or_else(|e| http_resp_from_error(&e))
 }
+
+/// Subscribe to an output stream of a running pipeline.
+///
+/// Streams changes to the output stream as server-sent events, one event
+/// per buffer produced by the encoder.  The subscription ends when the
+/// client disconnects or the pipeline shuts down.
+#[utoipa::path(
+    responses(
+        (status = OK
+            , description = "Connection established; changes to the stream follow as server-sent events."
+            , content_type = "text/event-stream"
+            , body = String),
+        (status = NOT_FOUND
+            , description = "Specified `pipeline_id` does not exist in the database."
+            , body = ErrorResponse
+            , example = json!(ErrorResponse::new("Unknown pipeline id '64'"))),
+        (status = BAD_REQUEST
+            , description = "Specified `stream_name` is not an output stream of the pipeline or `format` is not a valid output format."
+            , body = ErrorResponse
+            , example = json!(ErrorResponse::new("Failed to subscribe to output stream: unknown output stream 'MyTable'"))),
+        (status = CONFLICT
+            , description = "The pipeline has been terminated."
+            , body = ErrorResponse
+            , example = json!(ErrorResponse::new("The pipeline has been terminated"))),
+    ),
+    params(
+        ("pipeline_id" = i64, Path, description = "Unique pipeline identifier"),
+        ("stream_name" = String, Path, description = "Output stream name"),
+        ("format" = Option<String>, Query, description = "Output format: `json` (default) or `csv`"),
+        ("backpressure" = Option<String>, Query, description = "What to do when the client falls behind the pipeline: `drop` (default) discards outputs, `buffer` queues them in memory"),
+    ),
+    tag = "Pipeline"
+)]
+#[get("/pipelines/{pipeline_id}/output_stream/{stream_name}")]
+async fn pipeline_output_stream(state: WebData<ServerState>, req: HttpRequest) -> impl Responder {
+    let pipeline_id = match parse_pipeline_id_param(&req) {
+        Err(e) => {
+            return e;
+        }
+        Ok(pipeline_id) => pipeline_id,
+    };
+
+    let stream_name = match req.match_info().get("stream_name") {
+        None => return HttpResponse::BadRequest().body("missing stream_name argument"),
+        Some(stream_name) => stream_name,
+    };
+
+    // `match_info` returns the decoded path segment; re-encode it so that
+    // stream names containing `/`, `?`, etc. survive the trip to the pipeline.
+    let stream_name = utf8_percent_encode(stream_name, NON_ALPHANUMERIC);
+    let endpoint = if req.query_string().is_empty() {
+        format!("output_stream/{stream_name}")
+    } else {
+        format!("output_stream/{stream_name}?{}", req.query_string())
+    };
+
+    state
+        .runner
+        .forward_to_pipeline_streaming(pipeline_id, &endpoint)
+        .await
+        .unwrap_or_else(|e| http_resp_from_error(&e))
+}
@@ -1,16 +1,19 @@
 use crate::{
-    db::storage::Storage, db::AttachedConnector, db::ConfigDescr, Direction, ErrorResponse,
-    ManagerConfig, NewPipelineRequest, NewPipelineResponse, PipelineId, ProjectDB, ProjectId,
-    ProjectStatus, Version,
+    db::storage::Storage,
+    db::AttachedConnector,
+    db::ConfigDescr,
+    validation::{validate_config, ConfigValidationError},
+    Direction, ErrorResponse, ManagerConfig, NewPipelineRequest, NewPipelineResponse, PipelineId,
+    ProjectDB, ProjectId, ProjectStatus, Version,
 };
 use actix_web::{
     http::{Error, Method},
     web::BytesMut,
-    HttpRequest, HttpResponse,
+    HttpRequest, HttpResponse, HttpResponseBuilder,
 };
 use actix_web_actors::ws::handshake;
 use anyhow::{Error as AnyError, Result as AnyResult};
-use awc::Client;
+use awc::{Client, ClientResponse};
 use futures_util::StreamExt;
 use regex::Regex;
 use serde::Serialize;
@@ -150,6 +153,20 @@ impl Runner {
         }
     }
 
+    pub(crate) async fn forward_to_pipeline_streaming(
+        &self,
+        pipeline_id: PipelineId,
+        endpoint: &str,
+    ) -> AnyResult<HttpResponse> {
+        match self {
+            Self::Local(local) => {
+                local
+                    .forward_to_pipeline_streaming(pipeline_id, endpoint)
+                    .await
+            }
+        }
+    }
+
     pub(crate) async fn forward_to_pipeline_as_stream(
         &self,
         pipeline_id: PipelineId,
@@ -166,6 +183,61 @@ impl Runner {
     }
 }
 
+/// Assembles the configuration of a pipeline from the project configuration
+/// and all attached connectors.
+pub(crate) async fn generate_pipeline_config(
+    db: &ProjectDB,
+    config_descr: &ConfigDescr,
+) -> AnyResult<String> {
+    // Assemble the final config by including all attached connectors.
+    async fn generate_attached_connector_config(
+        db: &ProjectDB,
+        config: &mut String,
+        ac: &AttachedConnector,
+    ) -> AnyResult<()> {
+        let ident = 4;
+        config.push_str(format!("{:ident$}{}:\n", "", ac.uuid.as_str()).as_str());
+        let ident = 8;
+        config.push_str(format!("{:ident$}stream: {}\n", "", ac.config.as_str()).as_str());
+        let connector = db.get_connector(ac.connector_id).await?;
+        for config_line in connector.config.lines() {
+            config.push_str(format!("{:ident$}{config_line}\n", "").as_str());
+        }
+        Ok(())
+    }
+    async fn add_debug_websocket(config: &mut String, ac: &AttachedConnector) -> AnyResult<()> {
+        let ident = 4;
+        config.push_str(format!("{:ident$}debug-{}:\n", "", ac.uuid.as_str()).as_str());
+        let ident = 8;
+        config.push_str(format!("{:ident$}stream: {}\n", "", ac.config.as_str()).as_str());
+        for config_line in ["transport:", "     name: http", "format:", "    name: csv"].iter() {
+            config.push_str(format!("{:ident$}{config_line}\n", "").as_str());
+        }
+        Ok(())
+    }
+
+    let mut config = config_descr.config.clone();
+    config.push_str("inputs:\n");
+    for ac in config_descr
+        .attached_connectors
+        .iter()
+        .filter(|ac| ac.direction == Direction::Input)
+    {
+        generate_attached_connector_config(db, &mut config, ac).await?;
+    }
+    config.push_str("outputs:\n");
+    for ac in config_descr
+        .attached_connectors
+        .iter()
+        .filter(|ac| ac.direction == Direction::Output)
+    {
+        generate_attached_connector_config(db, &mut config, ac).await?;
+        add_debug_websocket(&mut config, ac).await?;
+    }
+
+    Ok(config)
+}
+
 impl LocalRunner {
     pub(crate) fn new(db: Arc<Mutex<ProjectDB>>, config: &ManagerConfig) -> AnyResult<Self> {
         Ok(Self {
@@ -196,6 +268,17 @@ impl LocalRunner {
             return Ok(HttpResponse::Conflict().body("Project hasn't been compiled yet"));
         };
 
+        // Catch configuration errors here rather than waiting for the
+        // pipeline process to fail during initialization.
+        let errors = validate_config(&db, &config_descr, &project_descr).await?;
+        if !errors.is_empty() {
+            return Ok(HttpResponse::BadRequest().json(ErrorResponse::new(&format!(
+                "Config '{}' is invalid: {}",
+                request.config_id,
+                ConfigValidationError::describe_all(&errors)
+            ))));
+        }
+
         let pipeline_id = db
             .new_pipeline(request.config_id, request.config_version)
             .await?;
@@ -269,40 +352,73 @@ impl LocalRunner {
         method: Method,
         endpoint: &str,
     ) -> AnyResult<HttpResponse> {
-        let pipeline_descr = self.db.lock().await.get_pipeline(pipeline_id).await?;
-
-        if pipeline_descr.shutdown {
-            return Err(AnyError::from(RunnerError::PipelineShutdown(pipeline_id)));
-        }
+        let url = self.pipeline_url(pipeline_id, endpoint).await?;
 
         let client = Client::default();
-        let request = client.request(
-            method,
-            &format!(
-                "http://localhost:{port}/{endpoint}",
-                port = pipeline_descr.port
-            ),
-        );
-
-        let mut response = request
+        let mut response = client
+            .request(method, &url)
             .send()
             .await
             .map_err(|e| AnyError::msg(format!("Failed to connect to pipeline: {e}")))?;
 
         let response_body = response.body().await?;
 
+        Ok(Self::response_builder(&response).body(response_body))
+    }
+
+    /// Like `forward_to_pipeline`, but streams the response body to the
+    /// client as it arrives instead of waiting for the pipeline to complete
+    /// the response.  Used for long-running responses such as output stream
+    /// subscriptions.
+    pub(crate) async fn forward_to_pipeline_streaming(
+        &self,
+        pipeline_id: PipelineId,
+        endpoint: &str,
+    ) -> AnyResult<HttpResponse> {
+        let url = self.pipeline_url(pipeline_id, endpoint).await?;
+
+        // Don't time out long-lived streams.
+        let client = Client::builder().disable_timeout().finish();
+        let response = client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| AnyError::msg(format!("Failed to connect to pipeline: {e}")))?;
+
+        Ok(Self::response_builder(&response).streaming(response))
+    }
+
+    /// Returns the URL of `endpoint` of a running pipeline.
+    async fn pipeline_url(&self, pipeline_id: PipelineId, endpoint: &str) -> AnyResult<String> {
+        let pipeline_descr = self.db.lock().await.get_pipeline(pipeline_id).await?;
+
+        if pipeline_descr.shutdown {
+            return Err(AnyError::from(RunnerError::PipelineShutdown(pipeline_id)));
+        }
+
+        Ok(format!(
+            "http://localhost:{port}/{endpoint}",
+            port = pipeline_descr.port
+        ))
+    }
+
+    /// Creates a response builder with the status and headers of a response
+    /// received from the pipeline.
+    fn response_builder<S>(response: &ClientResponse<S>) -> HttpResponseBuilder {
         let mut response_builder = HttpResponse::build(response.status());
         // Remove `Connection` as per
         // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Connection#Directives
+        // `Transfer-Encoding` is hop-by-hop as well; the server sets it for
+        // streaming responses.
         for (header_name, header_value) in response
             .headers()
             .iter()
-            .filter(|(h, _)| *h != "connection")
+            .filter(|(h, _)| *h != "connection" && *h != "transfer-encoding")
         {
             response_builder.insert_header((header_name.clone(), header_value.clone()));
         }
 
-        Ok(response_builder.body(response_body))
+        response_builder
     }
 
     pub(crate) async fn forward_to_pipeline_as_stream(
@@ -370,52 +486,7 @@ impl LocalRunner {
         );
         let project_id = config_descr.project_id.unwrap();
 
-        // Assemble the final config by including all attached connectors.
-        async fn generate_attached_connector_config(
-            db: &ProjectDB,
-            config: &mut String,
-            ac: &AttachedConnector,
-        ) -> AnyResult<()> {
-            let ident = 4;
-            config.push_str(format!("{:ident$}{}:\n", "", ac.uuid.as_str()).as_str());
-            let ident = 8;
-            config.push_str(format!("{:ident$}stream: {}\n", "", ac.config.as_str()).as_str());
-            let connector = db.get_connector(ac.connector_id).await?;
-            for config_line in connector.config.lines() {
-                config.push_str(format!("{:ident$}{config_line}\n", "").as_str());
-            }
-            Ok(())
-        }
-        async fn add_debug_websocket(config: &mut String, ac: &AttachedConnector) -> AnyResult<()> {
-            let ident = 4;
-            config.push_str(format!("{:ident$}debug-{}:\n", "", ac.uuid.as_str()).as_str());
-            let ident = 8;
-            config.push_str(format!("{:ident$}stream: {}\n", "", ac.config.as_str()).as_str());
-            for config_line in ["transport:", "     name: http", "format:", "    name: csv"].iter()
-            {
-                config.push_str(format!("{:ident$}{config_line}\n", "").as_str());
-            }
-            Ok(())
-        }
-
-        let mut config = config_descr.config.clone();
-        config.push_str("inputs:\n");
-        for ac in config_descr
-            .attached_connectors
-            .iter()
-            .filter(|ac| ac.direction == Direction::Input)
-        {
-            generate_attached_connector_config(db, &mut config, ac).await?;
-        }
-        config.push_str("outputs:\n");
-        for ac in config_descr
-            .attached_connectors
-            .iter()
-            .filter(|ac| ac.direction == Direction::Output)
-        {
-            generate_attached_connector_config(db, &mut config, ac).await?;
-            add_debug_websocket(&mut config, ac).await?;
-        }
+        let config = generate_pipeline_config(db, config_descr).await?;
         log::debug!("Pipeline config is '{}'", config);
 
         // Create pipeline directory (delete old directory if exists); write metadata
@@ -0,0 +1,295 @@
+//! Validation of pipeline configurations against compiled projects.
+//!
+//! Catches configuration errors, such as connectors attached to streams that
+//! don't exist in the project, before the pipeline process is started, which
+//! would otherwise fail during initialization.
+
+use crate::{
+    db::{ConfigDescr, ProjectDescr},
+    runner::generate_pipeline_config,
+    ProjectDB,
+};
+use anyhow::{Error as AnyError, Result as AnyResult};
+use dbsp_adapters::{InputFormat, InputTransport, OutputFormat, OutputTransport, PipelineConfig};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use utoipa::ToSchema;
+
+/// Input and output streams of a compiled project.
+///
+/// Parsed from [`ProjectDescr::schema`]; only the names of the streams are
+/// retained.
+#[derive(Deserialize, Debug)]
+pub(crate) struct ProjectSchema {
+    inputs: Vec<Relation>,
+    outputs: Vec<Relation>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Relation {
+    name: String,
+}
+
+impl ProjectSchema {
+    pub(crate) fn parse(schema: &str) -> AnyResult<Self> {
+        serde_json::from_str(schema)
+            .map_err(|e| AnyError::msg(format!("error parsing project schema: {e}")))
+    }
+
+    /// Names of the input streams of the project.
+    pub(crate) fn input_names(&self) -> impl Iterator<Item = &str> {
+        self.inputs.iter().map(|relation| relation.name.as_str())
+    }
+
+    /// Names of the output streams of the project.
+    pub(crate) fn output_names(&self) -> impl Iterator<Item = &str> {
+        self.outputs.iter().map(|relation| relation.name.as_str())
+    }
+}
+
+/// An error in a pipeline configuration.
+#[derive(Serialize, ToSchema, Debug, PartialEq, Eq)]
+pub(crate) struct ConfigValidationError {
+    /// Path to the invalid field, e.g., `inputs.my_input.stream`.  Empty if
+    /// the configuration could not be parsed.
+    pub path: String,
+    /// Error description.
+    pub message: String,
+}
+
+impl ConfigValidationError {
+    fn new(path: String, message: String) -> Self {
+        Self { path, message }
+    }
+
+    /// Describes a list of errors in a single line, for use in an
+    /// [`ErrorResponse`](crate::ErrorResponse).
+    pub(crate) fn describe_all(errors: &[Self]) -> String {
+        errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+impl Display for ConfigValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Assembles the pipeline configuration from `config_descr` and all attached
+/// connectors and validates it against the schema of the compiled project.
+///
+/// Returns an empty vector if the configuration is valid.
+pub(crate) async fn validate_config(
+    db: &ProjectDB,
+    config_descr: &ConfigDescr,
+    project_descr: &ProjectDescr,
+) -> AnyResult<Vec<ConfigValidationError>> {
+    let schema = project_descr
+        .schema
+        .as_deref()
+        .filter(|schema| !schema.is_empty())
+        .ok_or_else(|| {
+            AnyError::msg(format!(
+                "project '{}' has no schema",
+                project_descr.project_id
+            ))
+        })?;
+    let schema = ProjectSchema::parse(schema)?;
+
+    let config = generate_pipeline_config(db, config_descr).await?;
+    Ok(validate_pipeline_config(&config, &schema))
+}
+
+/// Validates a pipeline configuration YAML against a project schema.
+///
+/// Checks that the configuration parses and that every endpoint refers to
+/// an existing stream of the right direction, a known transport, and a known
+/// format.
+pub(crate) fn validate_pipeline_config(
+    config: &str,
+    schema: &ProjectSchema,
+) -> Vec<ConfigValidationError> {
+    let config: PipelineConfig = match serde_yaml::from_str(config) {
+        Ok(config) => config,
+        Err(e) => {
+            return vec![ConfigValidationError::new(
+                String::new(),
+                format!("error parsing pipeline configuration: {e}"),
+            )]
+        }
+    };
+
+    let mut errors = Vec::new();
+
+    for (name, input) in config.inputs.iter() {
+        let path = format!("inputs.{name}");
+
+        if !schema.input_names().any(|stream| stream == input.stream) {
+            errors.push(ConfigValidationError::new(
+                format!("{path}.stream"),
+                unknown_stream_message("input", &input.stream, schema.input_names()),
+            ));
+        }
+        if <dyn InputTransport>::get_transport(&input.transport.name).is_none() {
+            errors.push(ConfigValidationError::new(
+                format!("{path}.transport.name"),
+                format!("unknown input transport '{}'", input.transport.name),
+            ));
+        }
+        if <dyn InputFormat>::get_format(&input.format.name).is_none() {
+            errors.push(ConfigValidationError::new(
+                format!("{path}.format.name"),
+                format!("unknown input format '{}'", input.format.name),
+            ));
+        }
+    }
+
+    for (name, output) in config.outputs.iter() {
+        let path = format!("outputs.{name}");
+
+        if !schema.output_names().any(|stream| stream == output.stream) {
+            errors.push(ConfigValidationError::new(
+                format!("{path}.stream"),
+                unknown_stream_message("output", &output.stream, schema.output_names()),
+            ));
+        }
+        if <dyn OutputTransport>::get_transport(&output.transport.name).is_none() {
+            errors.push(ConfigValidationError::new(
+                format!("{path}.transport.name"),
+                format!("unknown output transport '{}'", output.transport.name),
+            ));
+        }
+        if <dyn OutputFormat>::get_format(&output.format.name).is_none() {
+            errors.push(ConfigValidationError::new(
+                format!("{path}.format.name"),
+                format!("unknown output format '{}'", output.format.name),
+            ));
+        }
+    }
+
+    errors
+}
+
+fn unknown_stream_message<'a>(
+    direction: &str,
+    stream: &str,
+    known: impl Iterator<Item = &'a str>,
+) -> String {
+    let known = known.collect::<Vec<_>>();
+    if known.is_empty() {
+        format!("unknown {direction} stream '{stream}': the project has no {direction} streams")
+    } else {
+        format!(
+            "unknown {direction} stream '{stream}': expected one of {}",
+            known.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{validate_pipeline_config, ConfigValidationError, ProjectSchema};
+
+    const SCHEMA: &str = r#"{
+        "inputs": [{
+            "name": "USERS",
+            "fields": [{ "name": "NAME", "type": "VARCHAR", "nullable": true }]
+        }],
+        "outputs": [{
+            "name": "OUTPUT_USERS",
+            "fields": [{ "name": "NAME", "type": "VARCHAR", "nullable": true }]
+        }]
+    }"#;
+
+    #[test]
+    fn valid_config() {
+        let schema = ProjectSchema::parse(SCHEMA).unwrap();
+        let config = r#"
+workers: 4
+inputs:
+    users_in:
+        stream: USERS
+        transport:
+            name: file
+            config:
+                path: users.csv
+        format:
+            name: csv
+outputs:
+    users_out:
+        stream: OUTPUT_USERS
+        transport:
+            name: http
+        format:
+            name: json
+"#;
+        assert_eq!(validate_pipeline_config(config, &schema), vec![]);
+    }
+
+    #[test]
+    fn invalid_config() {
+        let schema = ProjectSchema::parse(SCHEMA).unwrap();
+        let config = r#"
+inputs:
+    users_in:
+        stream: OUTPUT_USERS
+        transport:
+            name: ftp
+        format:
+            name: csv
+outputs:
+    users_out:
+        stream: USERS
+        transport:
+            name: file
+        format:
+            name: xml
+"#;
+        let errors = validate_pipeline_config(config, &schema);
+        assert_eq!(
+            errors,
+            vec![
+                ConfigValidationError {
+                    path: "inputs.users_in.stream".to_string(),
+                    message: "unknown input stream 'OUTPUT_USERS': expected one of USERS"
+                        .to_string()
+                },
+                ConfigValidationError {
+                    path: "inputs.users_in.transport.name".to_string(),
+                    message: "unknown input transport 'ftp'".to_string()
+                },
+                ConfigValidationError {
+                    path: "outputs.users_out.stream".to_string(),
+                    message: "unknown output stream 'USERS': expected one of OUTPUT_USERS"
+                        .to_string()
+                },
+                ConfigValidationError {
+                    path: "outputs.users_out.format.name".to_string(),
+                    message: "unknown output format 'xml'".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            ConfigValidationError::describe_all(&errors[..2]),
+            "inputs.users_in.stream: unknown input stream 'OUTPUT_USERS': expected one of USERS; \
+             inputs.users_in.transport.name: unknown input transport 'ftp'"
+        );
+    }
+
+    #[test]
+    fn unparsable_config() {
+        let schema = ProjectSchema::parse(SCHEMA).unwrap();
+        let errors = validate_pipeline_config("inputs: [1, 2]", &schema);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "");
+        assert_eq!(errors[0].to_string(), errors[0].message);
+    }
+}
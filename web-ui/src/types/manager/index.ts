@@ -20,6 +20,7 @@ export type { CsvParserConfig } from './models/CsvParserConfig'
 export { Direction } from './models/Direction'
 export type { ErrorResponse } from './models/ErrorResponse'
 export type { FileInputConfig } from './models/FileInputConfig'
+export { FileInputMode } from './models/FileInputMode'
 export type { FileOutputConfig } from './models/FileOutputConfig'
 export type { FormatConfig } from './models/FormatConfig'
 export type { InputEndpointConfig } from './models/InputEndpointConfig'
@@ -2,4 +2,19 @@
 /* tslint:disable */
 /* eslint-disable */
 
-export type CsvParserConfig = {}
+export type CsvParserConfig = {
+  /**
+   * Field delimiter.  Must be an ASCII character.  Defaults to `,`.
+   */
+  delimiter?: string
+  /**
+   * Quote character.  Must be an ASCII character.  Defaults to `"`.
+   */
+  quote?: string
+  /**
+   * The first record of the input stream is a header that names the
+   * columns and is skipped by the parser.  Records are always mapped to
+   * columns by position.  Defaults to `false`.
+   */
+  has_headers?: boolean
+}
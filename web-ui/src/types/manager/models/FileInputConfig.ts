@@ -2,6 +2,8 @@
 /* tslint:disable */
 /* eslint-disable */
 
+import type { FileInputMode } from './FileInputMode'
+
 export type FileInputConfig = {
   /**
    * Read buffer size.
@@ -11,16 +13,20 @@ export type FileInputConfig = {
    */
   buffer_size_bytes?: number
   /**
-   * Enable file following.
+   * Deprecated: use `mode` instead.
    *
-   * When `false`, the endpoint outputs an [`eoi`](`InputConsumer::eoi`)
-   * message and stops upon reaching the end of file.  When `true`, the
-   * endpoint will keep watching the file and outputting any new content
-   * appended to it.
+   * `follow: true` is equivalent to `mode: follow`, `follow: false` is
+   * equivalent to `mode: once`.  Specifying both `follow` and `mode` is
+   * an error.
    */
   follow?: boolean
+  mode?: FileInputMode
   /**
-   * File path.
+   * File path or glob pattern, e.g., `data/*.csv`.
+   *
+   * Files that match the pattern are read one after another in
+   * lexicographic order of their paths, so replaying the same set of
+   * files always produces the same input.
    */
   path: string
 }
@@ -0,0 +1,11 @@
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+
+/**
+ * File input mode.
+ */
+export enum FileInputMode {
+  ONCE = 'once',
+  FOLLOW = 'follow'
+}
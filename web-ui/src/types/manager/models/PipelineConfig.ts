@@ -10,6 +10,20 @@ import type { OutputEndpointConfig } from './OutputEndpointConfig'
  * a new pipeline instance.
  */
 export type PipelineConfig = {
+  /**
+   * Output streams whose outputs are consolidated before they are sent to
+   * endpoints.
+   *
+   * By default, the outputs computed by different workers during a step
+   * are sent to the endpoints as separate batches.  For the streams listed
+   * here, these batches are merged into one, so that a record whose
+   * insertions and deletions cancel out within the step, e.g., an
+   * aggregate that was recomputed but didn't change, is not sent at all.
+   * This option only consolidates the outputs of each step.  It does not
+   * keep track of outputs sent during previous steps.  It applies to all
+   * endpoints connected to the stream.
+   */
+  consolidate_outputs?: Array<string>
   /**
    * Enable CPU profiler.
    */